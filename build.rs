@@ -0,0 +1,51 @@
+// スプライトの当たり判定を、Aseprite のスライスから作る。
+//
+// gfx/sprites.aseprite の Hitbox スライスを読み、タグごとに各フレームの箱 (スプライトの左上からの x, y, 幅, 高さ) を
+// $OUT_DIR/hitboxes.rs の HITBOXES に書き出す (resources モジュールが Rect を決めて取り込む)。
+// スライスのキーは、そのフレームから次のキーまで続く。キーより前のフレームはスプライト全体。
+// 絵を描き変えたらスライスも合わせて動かせば、当たり判定も付いてくる。
+
+use std::{env, fmt::Write, fs, path::Path};
+
+use asefile::AsepriteFile;
+
+const SPRITES: &str = "gfx/sprites.aseprite";
+const SLICE: &str = "Hitbox";
+
+fn main() {
+    println!("cargo:rerun-if-changed={SPRITES}");
+    let file = AsepriteFile::read_file(Path::new(SPRITES)).expect("cannot read the sprites");
+    let whole = (0, 0, file.width() as i32, file.height() as i32);
+    let keys = file
+        .slices()
+        .iter()
+        .find(|slice| slice.name == SLICE)
+        .map_or(&[][..], |slice| &slice.keys[..]);
+    let hitbox = |frame: u32| {
+        keys.iter()
+            .filter(|key| key.from_frame <= frame)
+            .max_by_key(|key| key.from_frame)
+            .map_or(whole, |key| {
+                let (x, y) = key.origin;
+                (x, y, key.size.0 as i32, key.size.1 as i32)
+            })
+    };
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        "pub const HITBOXES: [(&str, &[Rect]); {}] = [",
+        file.num_tags()
+    )
+    .unwrap();
+    for id in 0..file.num_tags() {
+        let tag = file.tag(id);
+        let frames: Vec<String> = (tag.from_frame()..=tag.to_frame())
+            .map(|frame| format!("{:?}", hitbox(frame)))
+            .collect();
+        writeln!(out, "    ({:?}, &[{}]),", tag.name(), frames.join(", ")).unwrap();
+    }
+    writeln!(out, "];").unwrap();
+    let path = Path::new(&env::var("OUT_DIR").unwrap()).join("hitboxes.rs");
+    fs::write(path, out).unwrap();
+}
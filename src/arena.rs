@@ -0,0 +1,260 @@
+// 世代番号付きのエンティティ置き場。
+//
+// Vec から swap_remove で消すと他の要素の添字がずれるので、
+// 別のエンティティを指し続けたいもの (スイッチと床、追いかけてくる鍵...) が持てない。
+// ここでは (添字, 世代) の組を EntityId として渡し、消すときにその枠の世代を進める。
+// 消えたエンティティの EntityId は以後 get() で None になり、
+// 同じ枠が再利用されても世代が違うので取り違えない。
+//
+// 枠の数は固定で、毎ティックの処理中にヒープは使わない。
+
+use alloc::vec::Vec;
+
+use crate::snapshot::{encode_fields, Encode, Reader};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct EntityId {
+    index: u16,
+    generation: u16,
+}
+
+encode_fields!(EntityId { index, generation });
+
+impl EntityId {
+    // 枠の番号。描画側で前回の状態を枠ごとに覚えておくときに使う。
+    pub fn index(self) -> usize {
+        self.index as usize
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Slot<T> {
+    generation: u16,
+    value: Option<T>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Arena<T, const N: usize> {
+    slots: [Slot<T>; N],
+    len: usize,
+}
+
+/* 空いた枠も世代ごと書く (読んだ後も同じ EntityId が使える) */
+impl<T: Encode, const N: usize> Encode for Arena<T, N> {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        for slot in &self.slots {
+            slot.generation.encode(bytes);
+            slot.value.encode(bytes);
+        }
+    }
+
+    fn decode(reader: &mut Reader) -> Option<Self> {
+        let mut arena = Self::new();
+        for slot in &mut arena.slots {
+            slot.generation = u16::decode(reader)?;
+            slot.value = Option::decode(reader)?;
+        }
+        arena.len = arena
+            .slots
+            .iter()
+            .filter(|slot| slot.value.is_some())
+            .count();
+        Some(arena)
+    }
+}
+
+impl<T, const N: usize> Arena<T, N> {
+    pub fn new() -> Self {
+        Self {
+            slots: core::array::from_fn(|_| Slot {
+                generation: 0,
+                value: None,
+            }),
+            len: 0,
+        }
+    }
+
+    // 空いている枠に置く。満杯なら置かずに返す。
+    pub fn spawn(&mut self, value: T) -> Result<EntityId, T> {
+        let Some(index) = self.slots.iter().position(|slot| slot.value.is_none()) else {
+            return Err(value);
+        };
+        let slot = &mut self.slots[index];
+        slot.value = Some(value);
+        self.len += 1;
+        Ok(EntityId {
+            index: index as u16,
+            generation: slot.generation,
+        })
+    }
+
+    // 取り除いて返す。既に消えていれば None 。
+    pub fn despawn(&mut self, id: EntityId) -> Option<T> {
+        let slot = self.slots.get_mut(id.index())?;
+        if slot.generation != id.generation {
+            return None;
+        }
+        let value = slot.value.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.len -= 1;
+        Some(value)
+    }
+
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn get(&self, id: EntityId) -> Option<&T> {
+        let slot = self.slots.get(id.index())?;
+        if slot.generation != id.generation {
+            return None;
+        }
+        slot.value.as_ref()
+    }
+
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn get_mut(&mut self, id: EntityId) -> Option<&mut T> {
+        let slot = self.slots.get_mut(id.index())?;
+        if slot.generation != id.generation {
+            return None;
+        }
+        slot.value.as_mut()
+    }
+
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // 生きているものだけを枠の順に返す。
+    pub fn iter(&self) -> impl Iterator<Item = (EntityId, &T)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            let id = EntityId {
+                index: index as u16,
+                generation: slot.generation,
+            };
+            slot.value.as_ref().map(|value| (id, value))
+        })
+    }
+
+    // 生きているものの値だけを枠の順に返す。EntityId を作らず、最後の 1 つより後の空いた枠も見ないので
+    // iter() より軽い (毎ティック全て見る当たり判定向け)。
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.slots
+            .iter()
+            .filter_map(|slot| slot.value.as_ref())
+            .take(self.len)
+    }
+
+    // values() の書き換えられる版。
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.slots
+            .iter_mut()
+            .filter_map(|slot| slot.value.as_mut())
+            .take(self.len)
+    }
+
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (EntityId, &mut T)> {
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, slot)| {
+                let id = EntityId {
+                    index: index as u16,
+                    generation: slot.generation,
+                };
+                slot.value.as_mut().map(|value| (id, value))
+            })
+    }
+
+    // keep が false を返したものを全て取り除く。取り除いたものの EntityId は以後使えない。
+    pub fn retain(&mut self, mut keep: impl FnMut(&T) -> bool) {
+        for slot in &mut self.slots {
+            if slot.value.as_ref().is_some_and(|value| !keep(value)) {
+                slot.value = None;
+                slot.generation = slot.generation.wrapping_add(1);
+                self.len -= 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    #[test_case]
+    fn ids_die_with_their_entity(_gba: &mut agb::Gba) {
+        let mut arena = Arena::<u32, 4>::new();
+        let a = arena.spawn(1).unwrap();
+        let b = arena.spawn(2).unwrap();
+        assert_eq!(arena.get(a), Some(&1));
+        assert_eq!(arena.despawn(a), Some(1));
+        assert_eq!(arena.get(a), None);
+        assert_eq!(arena.get_mut(a), None);
+        /* 2 度目は何もしない */
+        assert_eq!(arena.despawn(a), None);
+        assert_eq!(arena.get(b), Some(&2));
+        assert_eq!(arena.len(), 1);
+    }
+
+    #[test_case]
+    fn reused_slots_get_a_new_generation(_gba: &mut agb::Gba) {
+        let mut arena = Arena::<u32, 1>::new();
+        let old = arena.spawn(1).unwrap();
+        arena.despawn(old);
+        let new = arena.spawn(2).unwrap();
+        assert_eq!(old.index(), new.index());
+        assert_ne!(old, new);
+        /* 古い id では新しい中身に触れない */
+        assert_eq!(arena.get(old), None);
+        assert_eq!(arena.despawn(old), None);
+        *arena.get_mut(new).unwrap() += 10;
+        assert_eq!(arena.get(new), Some(&12));
+    }
+
+    #[test_case]
+    fn iteration_skips_dead_slots(_gba: &mut agb::Gba) {
+        let mut arena = Arena::<u32, 4>::new();
+        let ids: Vec<EntityId> = (0..4).map(|n| arena.spawn(n).unwrap()).collect();
+        arena.despawn(ids[1]);
+        arena.despawn(ids[2]);
+        for (_, value) in arena.iter_mut() {
+            *value += 100;
+        }
+        let live: Vec<(EntityId, u32)> = arena.iter().map(|(id, value)| (id, *value)).collect();
+        assert_eq!(live, [(ids[0], 100), (ids[3], 103)]);
+        let values: Vec<u32> = arena.values().copied().collect();
+        assert_eq!(values, [100, 103]);
+    }
+
+    #[test_case]
+    fn a_full_arena_hands_the_value_back(_gba: &mut agb::Gba) {
+        let mut arena = Arena::<u32, 2>::new();
+        let first = arena.spawn(1).unwrap();
+        arena.spawn(2).unwrap();
+        assert_eq!(arena.spawn(3), Err(3));
+        assert_eq!(arena.len(), 2);
+        arena.despawn(first);
+        assert!(arena.spawn(3).is_ok());
+        assert!(!arena.is_empty());
+    }
+
+    #[test_case]
+    fn retain_despawns_the_rest(_gba: &mut agb::Gba) {
+        let mut arena = Arena::<u32, 4>::new();
+        let ids: Vec<EntityId> = (0..4).map(|n| arena.spawn(n).unwrap()).collect();
+        arena.retain(|value| value % 2 == 0);
+        assert_eq!(arena.len(), 2);
+        assert_eq!(arena.get(ids[1]), None);
+        assert_eq!(arena.get(ids[2]), Some(&2));
+        /* 枠は空くが、世代が進んでいるので古い id とは取り違えない */
+        let reused = arena.spawn(9).unwrap();
+        assert_eq!(reused.index(), ids[1].index());
+        assert_eq!(arena.get(ids[1]), None);
+    }
+}
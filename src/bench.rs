@@ -0,0 +1,488 @@
+// ベンチマーク。
+//
+// 毎フレーム通る処理のサイクル数をタイマー 2, 3 (カスケード) で測り、
+// 上限を超えたらテストを落とす。実機で原因の分からない処理落ちとして見つかる前に、
+// テストの時点で遅くなったことに気付けるようにする。
+// 測った値は mGBA のログに出すので、変化を追いたいときはテストのログを比べる。
+//
+// 上限はフレーム予算 (FRAME_CYCLES) に対して余裕を持たせてある。
+// 処理を足して超えるようになったら、上限を上げる前にまず予算の使い道を見直すこと。
+
+use alloc::boxed::Box;
+
+use agb::{
+    display::{
+        tiled::{TileSetting, VRamManager},
+        Priority,
+    },
+    input::Button,
+    timer::{Divider, Timer, TimerController},
+};
+
+use crate::{
+    arena::Arena,
+    collision::Passage,
+    crumbling::MAX_CRUMBLING_PLATFORMS,
+    debris::MAX_DEBRIS,
+    difficulty::Difficulty,
+    door::MAX_KEYS,
+    font,
+    game::{Game, GROUND_Y, MAX_APPLES, MAX_COINS},
+    gameplay::{lay_ground, GROUND_FILL, GROUND_ROW},
+    gfx::tiles,
+    input::Input,
+    layer::Layer,
+    level::Level,
+    math,
+    mode::Mode,
+    moving_platform::MAX_MOVING_PLATFORMS,
+    oam::ObjectPool,
+    player::PlayerState,
+    presenter::Presenter,
+    resources::SpriteSet,
+    spawn::EntityKind,
+    spring::MAX_SPRINGS,
+    switch::MAX_SWITCHES,
+    text::{self, TextLayer},
+    wind::MAX_WINDS,
+};
+
+/* 1 フレーム (228 ライン x 1232 サイクル) */
+const FRAME_CYCLES: u32 = 280_896;
+
+struct Stopwatch {
+    low: Timer,
+    high: Timer,
+}
+
+impl Stopwatch {
+    fn new(timers: &mut TimerController) -> Self {
+        let timers = timers.timers();
+        let (mut low, mut high) = (timers.timer2, timers.timer3);
+        low.set_divider(Divider::Divider1);
+        high.set_cascade(true);
+        Self { low, high }
+    }
+
+    // f を実行したサイクル数。
+    fn measure(&mut self, f: impl FnOnce()) -> u32 {
+        self.low.set_enabled(false).set_overflow_amount(0);
+        self.high.set_enabled(false).set_overflow_amount(0);
+        self.high.set_enabled(true);
+        self.low.set_enabled(true);
+        f();
+        self.low.set_enabled(false);
+        (u32::from(self.high.value()) << 16) | u32::from(self.low.value())
+    }
+}
+
+fn report(name: &str, cycles: u32, limit: u32) {
+    agb::println!(
+        "bench {}: {} cycles ({}.{}% of a frame, limit {})",
+        name,
+        cycles,
+        cycles * 100 / FRAME_CYCLES,
+        cycles * 1000 / FRAME_CYCLES % 10,
+        limit
+    );
+    assert!(
+        cycles <= limit,
+        "{name} took {cycles} cycles (limit {limit})"
+    );
+}
+
+// 歩きながら跳び続けたときの 1 ティックの最大。
+#[test_case]
+fn game_tick(gba: &mut agb::Gba) {
+    let mut stopwatch = Stopwatch::new(&mut gba.timers);
+    let mut game = Game::new(Difficulty::Normal, 1);
+    let mut previous = Button::empty();
+    let mut worst = 0;
+    for tick in 0..240 {
+        let current = if tick % 40 < 2 {
+            Button::RIGHT | Button::UP
+        } else {
+            Button::RIGHT
+        };
+        let input = Input::new(current, previous);
+        previous = current;
+        worst = worst.max(stopwatch.measure(|| game.update(&input)));
+    }
+    report("game tick", worst, FRAME_CYCLES / 20);
+}
+
+// エンドレスで歩きながら跳び続けたときの、ティックの段階ごとの最大。
+// どの段階も IWRAM に置いてある (memory モジュール参照)。置いたときの記録 (サイクル、debug-tools 無し):
+//   段階           ROM    IWRAM
+//   droid update   3148   2929
+//   climb update   1771   1479
+//   coin collection 515    389
+//   event handling 1480   1080
+// ROM に戻っていないことは memory のテストで確かめるので、上限は他と同じくフレーム予算で決める。
+#[test_case]
+fn game_tick_phases(gba: &mut agb::Gba) {
+    let mut stopwatch = Stopwatch::new(&mut gba.timers);
+    let mut game = Game::new(Difficulty::Normal, 1).with_mode(Mode::Endless);
+    let mut previous = Button::empty();
+    let mut worst = [0; 4];
+    for tick in 0..240 {
+        let current = if tick % 40 < 2 {
+            Button::RIGHT | Button::UP
+        } else {
+            Button::RIGHT
+        };
+        let input = Input::new(current, previous);
+        previous = current;
+        let phases = [
+            stopwatch.measure(|| game.update_droid(&input)),
+            stopwatch.measure(|| game.update_climb()),
+            stopwatch.measure(|| game.collect_coins()),
+            stopwatch.measure(|| game.handle_events()),
+        ];
+        for (worst, cycles) in worst.iter_mut().zip(phases) {
+            *worst = (*worst).max(cycles);
+        }
+    }
+    let [droid, climb, coins, events] = worst;
+    report("droid update", droid, FRAME_CYCLES / 40);
+    report("climb update", climb, FRAME_CYCLES / 100);
+    report("coin collection", coins, FRAME_CYCLES / 200);
+    report("event handling", events, FRAME_CYCLES / 100);
+}
+
+// 足場、ばね、スイッチ、風、コイン、りんご、鍵を入れ物いっぱいに (合わせて 32 個) 置いたときの、
+// 全てのエンティティを 1 ティック進める (Game::update_entities) 最大。ドロイド君は地面にいて何にも触らない。
+#[test_case]
+fn entity_update_32(gba: &mut agb::Gba) {
+    let mut stopwatch = Stopwatch::new(&mut gba.timers);
+    let mut game = Game::new(Difficulty::Normal, 1);
+    game.moving_platforms = Arena::new();
+    game.crumbling_platforms = Arena::new();
+    game.springs = Arena::new();
+    game.switches = Arena::new();
+    game.winds = Arena::new();
+    game.coins = Arena::new();
+    game.apples = Arena::new();
+    game.keys = Arena::new();
+    let kinds = [
+        (
+            EntityKind::MovingPlatform {
+                to: (200, 24),
+                passage: Passage::OneWay,
+            },
+            MAX_MOVING_PLATFORMS,
+        ),
+        (EntityKind::CrumblingPlatform, MAX_CRUMBLING_PLATFORMS),
+        (EntityKind::Spring, MAX_SPRINGS),
+        (EntityKind::Switch, MAX_SWITCHES),
+        (
+            EntityKind::Wind {
+                size: (32, 48),
+                force: (48, 0),
+            },
+            MAX_WINDS,
+        ),
+        (EntityKind::Coin, MAX_COINS),
+        (EntityKind::Apple, MAX_APPLES),
+        (EntityKind::Key, MAX_KEYS),
+    ];
+    let mut placed = 0;
+    for (row, (kind, count)) in kinds.into_iter().enumerate() {
+        for column in 0..count {
+            let at = (8 + 24 * column as i32, 24 + 8 * row as i32);
+            game.spawn(kind).at(at).build().unwrap();
+            placed += 1;
+        }
+    }
+    assert_eq!(placed, 32);
+    let mut worst = 0;
+    for _ in 0..120 {
+        worst = worst.max(stopwatch.measure(|| game.update_entities()));
+    }
+    report("entity update (32)", worst, FRAME_CYCLES / 20);
+}
+
+// 同じ所に重ねた 8 つの足場 (止まった動く足場と崩れる足場を 4 つずつ) の上で跳び続けるときの、
+// ドロイド君の 1 ティック (Game::update_droid) の最大。着地も端を掴むのも足場を 1 つずつ見るので、
+// 当たり判定が一番重くなる場面。崩れる足場は崩れては戻るので、重なっている数は途中で変わる。
+#[test_case]
+fn collision_8_platforms(gba: &mut agb::Gba) {
+    const AT: (i32, i32) = (96, 80);
+    let mut stopwatch = Stopwatch::new(&mut gba.timers);
+    let mut game = Game::new(Difficulty::Normal, 1);
+    game.moving_platforms = Arena::new();
+    game.crumbling_platforms = Arena::new();
+    for _ in 0..MAX_MOVING_PLATFORMS {
+        game.spawn(EntityKind::MovingPlatform {
+            to: AT,
+            passage: Passage::OneWay,
+        })
+        .at(AT)
+        .build()
+        .unwrap();
+    }
+    for _ in 0..MAX_CRUMBLING_PLATFORMS {
+        game.spawn(EntityKind::CrumblingPlatform)
+            .at(AT)
+            .build()
+            .unwrap();
+    }
+    game.droid.x = AT.0;
+    game.droid.y = 24;
+    game.droid.state = PlayerState::Airborne { double: false };
+    let mut previous = Button::empty();
+    let mut worst = 0;
+    let mut landed = false;
+    for tick in 0..240 {
+        let current = if tick % 40 < 2 {
+            Button::UP
+        } else {
+            Button::empty()
+        };
+        let input = Input::new(current, previous);
+        previous = current;
+        game.update_entities();
+        worst = worst.max(stopwatch.measure(|| game.update_droid(&input)));
+        game.handle_events();
+        landed |= game.droid.is_grounded() && game.droid.y < GROUND_Y;
+    }
+    assert!(landed, "the droid never landed on the platforms");
+    report("collision with 8 platforms", worst, FRAME_CYCLES / 40);
+}
+
+// 破片を出せるだけ (MAX_DEBRIS) 出し続けたときの、全てのエンティティを 1 ティック進める最大。
+// 消えた破片はその場で出し直すので、いつも入れ物いっぱいに飛んでいる。
+#[test_case]
+fn debris_at_capacity(gba: &mut agb::Gba) {
+    let mut stopwatch = Stopwatch::new(&mut gba.timers);
+    let mut game = Game::new(Difficulty::Normal, 1);
+    let mut worst = 0;
+    for _ in 0..120 {
+        while game.debris.len() < MAX_DEBRIS {
+            game.scatter_debris((120, 80));
+        }
+        worst = worst.max(stopwatch.measure(|| game.update_entities()));
+    }
+    report("debris at capacity", worst, FRAME_CYCLES / 40);
+}
+
+// 得点が変わって HUD の 1 行を全て書き直すとき。
+#[test_case]
+fn hud_rewrite(gba: &mut agb::Gba) {
+    let (gfx, mut vram) = gba.display.video.tiled0();
+    let mut stopwatch = Stopwatch::new(&mut gba.timers);
+    let mut hud = TextLayer::new(Layer::new(&gfx, Priority::P0), &mut vram);
+    let mut worst = 0;
+    for score in [11111, 22222, 33333, 44444] {
+        worst = worst.max(stopwatch.measure(|| {
+            hud.print_line(&mut vram, 0, format_args!("{0}{0}{0}{0}{0}{0}", score));
+            hud.commit(&mut vram);
+        }));
+    }
+    hud.release(&mut vram);
+    report("hud rewrite", worst, FRAME_CYCLES / 4);
+}
+
+// 背景 1 画面分 (30x20) のタイルを並べ直すとき。
+// 毎フレームではなくレベルを読み込むときのコストなので、上限は数フレーム分にしてある。
+#[test_case]
+fn full_screen_of_tiles(gba: &mut agb::Gba) {
+    let (gfx, mut vram) = gba.display.video.tiled0();
+    let mut stopwatch = Stopwatch::new(&mut gba.timers);
+    let mut layer = Layer::new(&gfx, Priority::P0);
+    let cycles = stopwatch.measure(|| {
+        for y in 0..20 {
+            for x in 0..30 {
+                let setting =
+                    TileSetting::new(font::tile_for(b'A' + (x % 26) as u8), false, false, 0);
+                layer.set_tile(&mut vram, (x, y), &font::TILESET, setting);
+            }
+        }
+        layer.commit(&mut vram);
+    });
+    layer.release(&mut vram);
+    report("full screen of tiles", cycles, FRAME_CYCLES * 3);
+}
+
+// レベルの地面を並べて commit するまで。
+// 1 セルずつ set_tile で置いていたときは、レベルを読み込むたびに前のレベルのレイヤーを返して
+// 作り直していた (old reload)。今はレベルの背景を使い回し、置いたセルを消してから VRAM の
+// マップに直接書き直す (level reload)。2 回目からの読み込みは全てこれなので、1/10 以下を見張る。
+// 最初の 1 回 (level load) は 1/10 に届かない。agb の分 (4 種類のタイルを VRAM に読み込むのと、
+// commit でマップ 2 KiB を丸ごと写すの) が大半で、どちらのやり方でも同じだけかかるからで、
+// 届かないことが分かるよう名前に入れて 1/3 以下を見張る。
+// 坂はタイルの種類ごとにかかる分が増えるだけで比べたいところではないので、平らな地面
+// (チュートリアル) で測る。
+#[test_case]
+fn level_load(gba: &mut agb::Gba) {
+    // 前のやり方。1 セルずつ set_tile で置く。
+    fn lay_one_by_one(layer: &mut Layer, vram: &mut VRamManager, level: &Level) {
+        let settings = |index: usize| tiles::bg.tile_settings[index];
+        for (x, (&tile, terrain)) in level.ground.iter().zip(level.terrain).enumerate() {
+            let surface = match terrain.tile() {
+                Some((slope, hflip)) => {
+                    layer.set_tile(
+                        vram,
+                        (x as u16, GROUND_ROW - 1),
+                        &tiles::bg.tiles,
+                        settings(slope).hflip(hflip),
+                    );
+                    GROUND_FILL
+                }
+                None => tile,
+            };
+            layer.set_tile(
+                vram,
+                (x as u16, GROUND_ROW),
+                &tiles::bg.tiles,
+                settings(surface),
+            );
+        }
+        for x in 0..text::COLUMNS {
+            for y in GROUND_ROW + 1..text::ROWS {
+                layer.set_tile(vram, (x, y), &tiles::bg.tiles, settings(GROUND_FILL));
+            }
+        }
+    }
+
+    let (gfx, mut vram) = gba.display.video.tiled0();
+    let mut stopwatch = Stopwatch::new(&mut gba.timers);
+    let level = Level::tutorial();
+    let previous = Level::load(false);
+    let mut layer = Layer::new(&gfx, Priority::P0);
+    let old = stopwatch.measure(|| {
+        lay_one_by_one(&mut layer, &mut vram, &level);
+        layer.commit(&mut vram);
+    });
+    layer.release(&mut vram);
+    let mut layer = Layer::new(&gfx, Priority::P0);
+    lay_one_by_one(&mut layer, &mut vram, &previous);
+    layer.commit(&mut vram);
+    let mut layer = Some(layer);
+    let old_reload = stopwatch.measure(|| {
+        if let Some(layer) = layer.take() {
+            layer.release(&mut vram);
+        }
+        let mut next = Layer::new(&gfx, Priority::P0);
+        lay_one_by_one(&mut next, &mut vram, &level);
+        next.commit(&mut vram);
+        layer = Some(next);
+    });
+    if let Some(layer) = layer {
+        layer.release(&mut vram);
+    }
+
+    let mut layer = Layer::persistent(&gfx, Priority::P0);
+    let cycles = stopwatch.measure(|| {
+        lay_ground(&mut layer, &mut vram, &level);
+        layer.commit(&mut vram);
+    });
+    agb::println!("bench level load (one by one): {} cycles", old);
+    report("level load (first, misses 1/10)", cycles, old / 3);
+    lay_ground(&mut layer, &mut vram, &previous);
+    layer.commit(&mut vram);
+    let reload = stopwatch.measure(|| {
+        layer.clear_cells(&mut vram);
+        lay_ground(&mut layer, &mut vram, &level);
+        layer.commit(&mut vram);
+    });
+    layer.release(&mut vram);
+    agb::println!("bench level reload (one by one): {} cycles", old_reload);
+    report("level reload", reload, old_reload / 10);
+}
+
+// ドロイド君が動いているフレームの描画と commit 。
+#[test_case]
+fn presenter_frame(gba: &mut agb::Gba) {
+    let game = Game::new(Difficulty::Normal, 1);
+    let (worst, _) = draw_frames(gba, game, |game, _| {
+        game.update(&Input::new(Button::RIGHT, Button::RIGHT));
+    });
+    report("presenter frame", worst, FRAME_CYCLES / 10);
+}
+
+// エンドレスで 1 秒 (60 ティック) に画面 1 枚分 (160 ドット) 上がるときの、1 フレームの描画と commit の最大。
+// 上に出てくる足場を背景に書き、下へ出た行と足場を片付ける。カメラが一番速く動く場面。
+// 足場を set_tile で置いていたときは最大 48000 ほどで、今は 41000 ほど。
+// 足場と行の片付けは Layer の直接セルで済むので、残りは高さの表示 (文字の整形) と OAM の転送。
+#[test_case]
+fn scroll_one_screen_per_second(gba: &mut agb::Gba) {
+    /* カメラはドロイド君が上から 56 ドットより上へ行くと追いかける */
+    let start = 56;
+    let mut camera = 0;
+    let game = endless_game();
+    let (worst, total) = draw_frames(gba, game, |game, tick| {
+        /* カメラはドロイド君を追うので、ドロイド君を持ち上げて引っ張る */
+        game.droid.y = start - 160 * tick / 60;
+        game.droid.vy = math::Fixed::new(0);
+        game.droid.state = PlayerState::Airborne { double: false };
+        game.update(&Input::default());
+        camera = game.climb.as_ref().unwrap().camera_y();
+    });
+    assert!(camera <= -155, "the camera only reached {camera}");
+    agb::println!("bench scroll one screen: {} cycles in total", total);
+    report("scroll one screen per second", worst, FRAME_CYCLES / 6);
+}
+
+// with_mode の受け渡しで Game が 2 つ並ばないよう、呼ぶ側とは別のフレームで作る。
+#[inline(never)]
+fn endless_game() -> Game {
+    Game::new(Difficulty::Normal, 1).with_mode(Mode::Endless)
+}
+
+// 60 ティックのあいだ、ティックごとに tick (何ティック目か 1 から数える) で game を進め、
+// 1 フレームの描画と commit を測る。最大と合計を返す。
+// Presenter は本編 (シーンの中) と同じくヒープに置く。スタックに置くとフレームが 10K を超え、
+// 呼ぶ側の Game と重ねるとスタックが IWRAM のコードまで伸びて止まる。同じ理由で Game::new も
+// ここから呼ばず、呼ぶ側で作って渡す。
+#[inline(never)]
+fn draw_frames(
+    gba: &mut agb::Gba,
+    mut game: Game,
+    mut tick: impl FnMut(&mut Game, i32),
+) -> (u32, u32) {
+    let (gfx, mut vram) = gba.display.video.tiled0();
+    let oam = gba.display.object.get_managed();
+    let object = ObjectPool::new(&oam);
+    let mut stopwatch = Stopwatch::new(&mut gba.timers);
+    let sprites = SpriteSet::load();
+    let hud = TextLayer::new(Layer::new(&gfx, Priority::P0), &mut vram);
+    let bg = Layer::new(&gfx, Priority::P0);
+    let mut presenter = Box::new(Presenter::new(&object, &sprites, &mut vram, bg, hud, &game));
+    presenter.commit(&object, &mut vram);
+    let mut worst = 0;
+    let mut total = 0;
+    for n in 1..=60 {
+        tick(&mut game, n);
+        let cycles = stopwatch.measure(|| {
+            presenter.draw(&object, &mut vram, &game);
+            presenter.commit(&object, &mut vram);
+        });
+        worst = worst.max(cycles);
+        total += cycles;
+    }
+    presenter.release(&mut vram).release(&mut vram);
+    (worst, total)
+}
+
+// sin 1 回あたり。同じ回数だけ回す空のループを先に測って引き、ループと black_box の分は含めない。
+// 表も命令も ROM にあるのでウェイトが乗るが、1/4 周の表を 1 回引いて折り返すだけなので数十サイクルに収まる。
+#[test_case]
+fn sine_lookup(gba: &mut agb::Gba) {
+    const CALLS: i32 = 1024;
+    let mut stopwatch = Stopwatch::new(&mut gba.timers);
+    let mut sum = math::Fixed::new(0);
+    let empty = stopwatch.measure(|| {
+        for angle in 0..CALLS {
+            sum += math::Fixed::from_raw(core::hint::black_box(angle));
+        }
+    });
+    let cycles = stopwatch.measure(|| {
+        for angle in 0..CALLS {
+            sum += math::sin(core::hint::black_box(angle));
+        }
+    });
+    core::hint::black_box(sum);
+    let per_call = cycles.saturating_sub(empty) / CALLS as u32;
+    report("sine lookup", per_call, 32);
+}
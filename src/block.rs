@@ -0,0 +1,158 @@
+// 壊せるブロック。
+//
+// レベルの列ごとに 1 つまで、空中に浮かべて置く (Level::blocks)。壊れるまでは当たり判定のマップの固いタイルで、
+// 横からはぶつかり、上には立て、下から跳べば頭をぶつける。
+// 跳び上がって頭をぶつけたとき (Droid::bonked) は頭の上の、ヒップドロップで着地したとき (Event::GroundPound) は
+// 足元のブロックが壊れる。どちらも足元の幅 (collision::footprint) に掛かっているものは全て壊れる。
+//
+// 壊すときは当たり判定のマップと残っているブロック (Game::blocks) を一緒に書き換え (Game::break_block)、
+// Event::BlockBroken で破片 (debris モジュール) を散らして Sfx::Break を鳴らす。
+// 背景のタイルは presenter が Game::blocks と見比べて消す。
+
+use crate::{
+    collision::footprint,
+    events::{Event, Sfx},
+    game::{Game, DROID_WIDTH},
+    physics::Gravity,
+    tilemap::Cell,
+};
+
+/* ブロック (タイル 1 枚) の大きさ */
+const BLOCK_SIZE: i32 = 8;
+
+impl Game {
+    // 頭をぶつけたドロイド君の頭の上のブロックを壊す。ドロイド君を動かした後に呼ぶ。
+    pub fn break_blocks_above(&mut self) {
+        let droid = &self.droid;
+        if droid.bonked && droid.gravity == Gravity::Down {
+            self.break_blocks_at(droid.x, droid.y - 1);
+        }
+    }
+
+    // ヒップドロップで着地したドロイド君 (x は足元の真ん中、y はドロイド君の y 座標) の足元のブロックを壊す。
+    #[inline(never)]
+    pub fn break_blocks_below(&mut self, x: i32, y: i32) {
+        self.break_blocks_at(x - DROID_WIDTH / 2, y + DROID_WIDTH);
+    }
+
+    // x にいるドロイド君の足元の幅で、y ドット目の行にあるブロックを壊す。
+    fn break_blocks_at(&mut self, x: i32, y: i32) {
+        let Ok(row) = u16::try_from(y.div_euclid(BLOCK_SIZE)) else {
+            return;
+        };
+        let feet = footprint(x, y);
+        for column in feet.x.div_euclid(BLOCK_SIZE)..=(feet.right() - 1).div_euclid(BLOCK_SIZE) {
+            let Ok(column) = usize::try_from(column) else {
+                continue;
+            };
+            if self.blocks.get(column) == Some(&Some(row)) {
+                self.break_block(column, row);
+            }
+        }
+    }
+
+    // column 列の row 行のブロックを、当たり判定ごと取り除く。
+    pub fn break_block(&mut self, column: usize, row: u16) {
+        self.blocks[column] = None;
+        self.collision_map
+            .set(column, usize::from(row), Cell::Empty);
+        self.push_event(Event::BlockBroken { column, row });
+    }
+
+    // 壊れたブロックの破片を散らす (Event::BlockBroken)。
+    #[inline(never)]
+    pub fn shatter_block(&mut self, column: usize, row: u16) {
+        log_debug!("block broken at column {}, row {}", column, row);
+        let centre = |cell: i32| cell * BLOCK_SIZE + BLOCK_SIZE / 2;
+        self.scatter_debris((centre(column as i32), centre(i32::from(row))));
+        self.push_event(Event::PlaySfx(Sfx::Break));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use agb::input::Button;
+
+    use super::*;
+    use crate::{
+        difficulty::Difficulty,
+        game::GROUND_Y,
+        gameplay::GROUND_ROW,
+        input::Input,
+        level::{Level, GROUND_COLUMNS},
+        player::PlayerState,
+        tilemap::CollisionMap,
+    };
+
+    /* 出発点 (x = 120) の真上の 2 列に、跳べば頭が届く高さで置く */
+    const ROW: u16 = 11;
+
+    fn game_with_blocks() -> Game {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        let mut level = Level::load(false);
+        level.blocks = [None; GROUND_COLUMNS];
+        level.blocks[15..17].fill(Some(ROW));
+        game.blocks = level.blocks;
+        game.collision_map = CollisionMap::new(&level);
+        game
+    }
+
+    // 真下から跳ぶと頭をぶつけたブロックだけが割れて破片が飛び、もう一度跳べば通り抜ける。
+    #[test_case]
+    fn jumping_into_a_block_breaks_it(_gba: &mut agb::Gba) {
+        let mut game = game_with_blocks();
+        /* 足元の幅が 14, 15 列に掛かる所 */
+        game.droid.x = 112;
+        game.update(&Input::new(Button::UP, Button::empty()));
+        while !game.droid.bonked {
+            game.update(&Input::new(Button::UP, Button::UP));
+        }
+        assert_eq!(game.droid.y, i32::from(ROW + 1) * BLOCK_SIZE);
+        assert_eq!(game.blocks[15], None);
+        assert_eq!(game.blocks[16], Some(ROW));
+        game.update(&Input::default());
+        assert!(!game.debris.is_empty());
+
+        while game.droid.y != GROUND_Y {
+            game.update(&Input::default());
+        }
+        game.update(&Input::new(Button::UP, Button::empty()));
+        while game.droid.y == GROUND_Y {
+            game.update(&Input::new(Button::UP, Button::UP));
+        }
+        let mut highest = GROUND_Y;
+        while game.droid.y != GROUND_Y {
+            game.update(&Input::new(Button::UP, Button::UP));
+            assert!(!game.droid.bonked);
+            highest = highest.min(game.droid.y);
+        }
+        assert!(highest < i32::from(ROW + 1) * BLOCK_SIZE);
+    }
+
+    // 上に乗ったブロックはヒップドロップで割れ、そのまま地面まで落ちる。
+    #[test_case]
+    fn a_ground_pound_breaks_the_block_underneath(_gba: &mut agb::Gba) {
+        let mut game = game_with_blocks();
+        game.droid.x = 128 - DROID_WIDTH / 2;
+        game.droid.y = i32::from(ROW) * BLOCK_SIZE - DROID_WIDTH;
+        game.update(&Input::default());
+        assert_eq!(game.droid.state, PlayerState::Idle);
+        assert_eq!(game.droid.y, i32::from(ROW) * BLOCK_SIZE - DROID_WIDTH);
+
+        game.update(&Input::new(Button::UP, Button::empty()));
+        while game.droid.state != PlayerState::GroundPound {
+            game.update(&Input::new(Button::DOWN, Button::DOWN.complement()));
+        }
+        while game.blocks[15..17] != [None, None] {
+            game.update(&Input::default());
+        }
+        for _ in 0..60 {
+            game.update(&Input::default());
+        }
+        assert_eq!(game.droid.y, GROUND_Y);
+        assert_eq!(
+            game.collision_map.floor_y(128 - DROID_WIDTH / 2, 0),
+            i32::from(GROUND_ROW) * BLOCK_SIZE - DROID_WIDTH
+        );
+    }
+}
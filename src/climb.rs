@@ -0,0 +1,439 @@
+// エンドレスモード (縦に登り続ける)。
+//
+// 座標は普通のモードと同じで、地面 (GROUND_Y) から上へ y が負の方向に伸びていく。
+// 画面に映す範囲の上端が camera で、Presenter はここからの差で描く。
+//
+// - 足場 (りんご、雲、崩れる岩棚) は画面の上端より LOOKAHEAD 上まで乱数で作っておき、
+//   画面の下へ出たものから消す。置き場所は MAX_PLATFORMS 個で使い回す。
+//   コインも足場の上にときどき置き、同じように画面の下へ出たら消す。
+// - カメラは GRACE_TICKS 後から自動で少しずつ上がり、ドロイド君が画面の上の方へ来たら追いかける。
+//   下がることは無い。ドロイド君が画面の下端より下へ落ちたら終わり。
+// - 登った高さ RAMP_METRES ごとに、足場の間隔が広がり、岩棚が増え、カメラが速くなる。
+// - 得点は最高到達点 (m) とコインの点数の合計。
+
+use agb::display::{HEIGHT, WIDTH};
+
+use crate::{
+    arena::Arena,
+    collision::{self, Aabb, Surface, Sweep},
+    events::Event,
+    game::{Game, APPLE_HITBOX, GROUND_Y, MAX_COINS},
+    math::Fixed,
+    player::PlayerState,
+    rng::Rng,
+    snapshot::{encode_fields, encode_variants},
+    spawn::EntityKind,
+    time::Timer,
+};
+
+/* 同時に置ける足場の数 */
+pub const MAX_PLATFORMS: usize = 12;
+/* 雲と岩棚の厚み (タイル 1 枚) */
+const PLATFORM_THICKNESS: i32 = 8;
+/* 高さの単位。1 m = タイル 1 枚 */
+const METRE: i32 = 8;
+/* カメラが自動で上がり始めるまでのティック数 */
+const GRACE_TICKS: u32 = 2 * 60;
+/* ドロイド君が画面の上端からこれより上に来たらカメラが付いていく */
+const FOLLOW_Y: i32 = 56;
+/* 画面の上端からさらにこれだけ上まで足場を作っておく */
+const LOOKAHEAD: i32 = 48;
+/* 最初の足場の y 座標 */
+const FIRST_Y: i32 = 96;
+/* 難しくなる高さの間隔 (m) と、難しさの上限 */
+const RAMP_METRES: u32 = 32;
+const MAX_LEVEL: u32 = 3;
+/* 足場の縦の間隔 (ドット)。難しさ 1 ごとに GAP_STEP 広がる */
+const GAP_BASE: i32 = 24;
+const GAP_STEP: i32 = 8;
+/* カメラが自動で上がる速さ (ドット/ティック)。難しさ 1 ごとに RISE_STEP 速くなる */
+const RISE_BASE: Fixed = Fixed::from_raw(32);
+const RISE_STEP: Fixed = Fixed::from_raw(16);
+/* 次の足場を前の足場から左右にどこまでずらすか */
+const REACH: i32 = 64;
+/* 足場が岩棚になる確率 (%)。難しさ 1 ごとに LEDGE_STEP 増える */
+const LEDGE_PERCENT: u32 = 10;
+const LEDGE_STEP: u32 = 15;
+/* 岩棚でなければ、りんごになる確率 (%)。残りは雲 */
+const APPLE_PERCENT: u32 = 25;
+/* 足場の上にコインを置く確率 (%) */
+const COIN_PERCENT: u32 = 30;
+/* 岩棚に乗ってから崩れるまでのティック数 */
+const CRUMBLE_TICKS: u16 = 40;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlatformKind {
+    // スプライトのりんご。
+    Apple,
+    Cloud,
+    // 乗ってから CRUMBLE_TICKS で崩れて消える。
+    Ledge,
+}
+
+encode_variants!(PlatformKind {
+    Apple,
+    Cloud,
+    Ledge
+});
+
+impl PlatformKind {
+    pub fn width(self) -> i32 {
+        match self {
+            PlatformKind::Apple => 16,
+            PlatformKind::Cloud => 32,
+            PlatformKind::Ledge => 24,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Platform {
+    pub kind: PlatformKind,
+    /* 左上。タイルに合わせて 8 の倍数 */
+    pub x: i32,
+    pub y: i32,
+    /* 崩れるまでの残り (岩棚に乗ったときに動き出す) */
+    pub crumble: Timer,
+}
+
+encode_fields!(Platform {
+    kind,
+    x,
+    y,
+    crumble
+});
+
+impl Surface for Platform {
+    fn bounds(&self) -> Aabb {
+        match self.kind {
+            /* りんごは絵の当たり判定 (上が空いている) */
+            PlatformKind::Apple => Aabb::new(
+                self.x + APPLE_HITBOX.x,
+                self.y + APPLE_HITBOX.y,
+                self.kind.width(),
+                APPLE_HITBOX.height,
+            ),
+            _ => Aabb::new(self.x, self.y, self.kind.width(), PLATFORM_THICKNESS),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Climb {
+    /* 画面の上端の y 座標。上がる (減る) だけ */
+    camera: Fixed,
+    pub platforms: Arena<Platform, MAX_PLATFORMS>,
+    /* 次に足場を置く y 座標 (これより下は作り終えた) */
+    next_y: i32,
+    /* 直前に置いた足場の x 座標。次の足場はここから届く範囲に置く */
+    last_x: i32,
+    /* 最高到達点 (m) */
+    pub height: u32,
+    /* 画面の下へ落ちたか */
+    pub fallen: bool,
+}
+
+encode_fields!(Climb {
+    camera,
+    platforms,
+    next_y,
+    last_x,
+    height,
+    fallen
+});
+
+impl Climb {
+    pub fn new() -> Self {
+        Self {
+            camera: Fixed::new(0),
+            platforms: Arena::new(),
+            next_y: FIRST_Y,
+            last_x: WIDTH / 2,
+            height: 0,
+            fallen: false,
+        }
+    }
+
+    // 画面の上端の y 座標 (ドット)。
+    pub fn camera_y(&self) -> i32 {
+        self.camera.floor()
+    }
+
+    // 今の難しさ (0..=MAX_LEVEL)。
+    fn level(&self) -> u32 {
+        (self.height / RAMP_METRES).min(MAX_LEVEL)
+    }
+
+    fn rise_speed(&self) -> Fixed {
+        RISE_BASE + RISE_STEP * self.level() as i32
+    }
+
+    fn gap(&self) -> i32 {
+        GAP_BASE + GAP_STEP * self.level() as i32
+    }
+
+    // x にいるドロイド君が y で足場に立っていられるか。
+    #[link_section = ".iwram.climb_supports"]
+    pub fn supports(&self, x: i32, y: i32) -> bool {
+        self.platforms
+            .iter()
+            .any(|(_, platform)| platform.top_y() == y && collision::supports(platform, x))
+    }
+
+    // 足元の箱が sweep のように落ちる途中で足場に着地するなら、そのドロイド君の位置 (x, y)。
+    #[link_section = ".iwram.climb_land_on"]
+    pub fn land_on(&self, sweep: Sweep) -> Option<(i32, i32)> {
+        self.platforms
+            .iter()
+            .find_map(|(_, platform)| collision::land_on(platform, sweep))
+    }
+
+    // dir を向いて y_before から y_after まで落ちる途中で足場の端を掴むなら、その位置 (x, y)。
+    pub fn resolve_grab(
+        &self,
+        x: i32,
+        dir: i32,
+        y_before: i32,
+        y_after: i32,
+    ) -> Option<(i32, i32)> {
+        self.platforms
+            .iter()
+            .find_map(|(_, platform)| collision::resolve_grab(platform, x, dir, y_before, y_after))
+    }
+
+    // (x, y) で dir を向いてぶら下がっている足場の端があるか。
+    pub fn holds(&self, x: i32, y: i32, dir: i32) -> bool {
+        self.platforms
+            .iter()
+            .any(|(_, platform)| collision::holds(platform, x, y, dir))
+    }
+
+    // next_y に足場を 1 つ置き、次の位置へ進める。コインも置くならその左上を返す。
+    fn place_platform(&mut self, rng: &mut Rng) -> Option<(i32, i32)> {
+        let level = self.level();
+        let kind = if rng.chance(LEDGE_PERCENT + LEDGE_STEP * level) {
+            PlatformKind::Ledge
+        } else if rng.chance(APPLE_PERCENT) {
+            PlatformKind::Apple
+        } else {
+            PlatformKind::Cloud
+        };
+        let width = kind.width();
+        let x = (self.last_x + rng.range(-REACH, REACH + 1)).clamp(0, WIDTH - width) / 8 * 8;
+        let y = self.next_y;
+        let platform = Platform {
+            kind,
+            x,
+            y,
+            crumble: Timer::new(),
+        };
+        if self.platforms.spawn(platform).is_err() {
+            log_warn!("no room for a platform at ({}, {})", x, y);
+        }
+        self.last_x = x;
+        self.next_y -= self.gap();
+        /* 真ん中のタイルの 3 枚上 */
+        rng.chance(COIN_PERCENT)
+            .then_some((x + width / 16 * 8, y - 3 * METRE))
+    }
+}
+
+impl Game {
+    // エンドレスの 1 ティック分。ドロイド君を動かした後に呼ぶ。
+    #[link_section = ".iwram.update_climb"]
+    pub fn update_climb(&mut self) {
+        let crumble_ticks = self.modifiers.timer(CRUMBLE_TICKS.into()) as u16;
+        let Some(climb) = &mut self.climb else {
+            return;
+        };
+        let droid = &self.droid;
+        let height = ((GROUND_Y - droid.y) / METRE).max(0) as u32;
+        climb.height = climb.height.max(height);
+
+        /* 岩棚は乗ったら (着地してすぐジャンプ準備に入っても) 崩れ始める */
+        for (_, platform) in climb.platforms.iter_mut() {
+            if platform.crumble.is_running() {
+                platform.crumble.tick();
+            } else if platform.kind == PlatformKind::Ledge
+                && matches!(
+                    droid.state,
+                    PlayerState::Idle | PlayerState::JumpSquat { double: false }
+                )
+                && droid.y == platform.top_y()
+                && collision::supports(platform, droid.x)
+            {
+                platform.crumble.start(crumble_ticks);
+            }
+        }
+        climb
+            .platforms
+            .retain(|platform| !platform.crumble.just_finished());
+
+        /* カメラ */
+        if self.time > GRACE_TICKS {
+            climb.camera -= climb.rise_speed();
+        }
+        let follow = Fixed::new(droid.y - FOLLOW_Y);
+        if follow < climb.camera {
+            climb.camera = follow;
+        }
+
+        /* 画面の下へ出たものを消す */
+        let bottom = climb.camera_y() + HEIGHT;
+        let fell = droid.y >= bottom && !climb.fallen;
+        if fell {
+            log_info!("fell off at {} m", climb.height);
+            climb.fallen = true;
+        }
+        climb.platforms.retain(|platform| platform.y < bottom);
+        self.coins.retain(|coin| coin.y < bottom);
+        if fell {
+            self.push_event(Event::PlayerDied);
+        }
+
+        /* 画面の上に足場を足す */
+        loop {
+            let Some(climb) = &mut self.climb else {
+                return;
+            };
+            if climb.next_y <= climb.camera_y() - LOOKAHEAD {
+                break;
+            }
+            let coin = climb.place_platform(&mut self.rng);
+            if let Some(position) = coin {
+                if self.coins.len() < MAX_COINS {
+                    self.spawn(EntityKind::Coin).at(position).build();
+                }
+            }
+        }
+    }
+
+    // エンドレスの得点。最高到達点 (m) とコインの点数の合計。
+    pub fn climb_score(&self) -> u32 {
+        self.climb
+            .as_ref()
+            .map_or(self.score, |climb| climb.height + self.score)
+    }
+
+    // エンドレスを始める。レベルのコインと足場は片付け、最初の足場を作る。
+    pub fn start_climb(&mut self) {
+        self.coins.retain(|_| false);
+        self.keys.retain(|_| false);
+        self.doors.retain(|_| false);
+        self.waters.retain(|_| false);
+        self.winds.retain(|_| false);
+        self.teleporters.retain(|_| false);
+        self.spikes.retain(|_| false);
+        self.apples.retain(|_| false);
+        self.moving_platforms.retain(|_| false);
+        self.crumbling_platforms.retain(|_| false);
+        self.springs.retain(|_| false);
+        self.switches.retain(|_| false);
+        self.climb = Some(Climb::new());
+        self.update_climb();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use agb::input::Button;
+
+    use super::*;
+    use crate::{difficulty::Difficulty, game::Outcome, input::Input, mode::Mode};
+
+    fn endless() -> Game {
+        Game::new(Difficulty::Normal, 7).with_mode(Mode::Endless)
+    }
+
+    // 足場は常に画面の上まで作ってあり、画面の下へ出たものは残らない。
+    #[test_case]
+    fn platforms_stream_in_above_and_out_below(_gba: &mut agb::Gba) {
+        let mut game = endless();
+        assert!(game.coins.iter().all(|(_, coin)| coin.y < FIRST_Y));
+        for _ in 0..20 * 60 {
+            game.droid.y = GROUND_Y;
+            game.update(&Input::default());
+            let climb = game.climb.as_ref().unwrap();
+            let camera = climb.camera_y();
+            assert!(climb.next_y <= camera - LOOKAHEAD);
+            assert!(climb
+                .platforms
+                .iter()
+                .all(|(_, platform)| platform.y < camera + HEIGHT));
+            assert!(game.coins.iter().all(|(_, coin)| coin.y < camera + HEIGHT));
+            if climb.fallen {
+                break;
+            }
+        }
+        /* 地面にいるだけでもカメラが上がっていき、いずれ落ちたことになる */
+        assert_eq!(game.outcome(), Some(Outcome::Died));
+    }
+
+    // 同じシードなら同じ足場が並ぶ。
+    #[test_case]
+    fn the_tower_depends_only_on_the_seed(_gba: &mut agb::Gba) {
+        let tower = |seed| {
+            let game = Game::new(Difficulty::Normal, seed).with_mode(Mode::Endless);
+            let climb = game.climb.unwrap();
+            let platforms: alloc::vec::Vec<_> = climb
+                .platforms
+                .iter()
+                .map(|(_, platform)| (platform.kind, platform.x, platform.y))
+                .collect();
+            platforms
+        };
+        assert_eq!(tower(3), tower(3));
+        assert_ne!(tower(3), tower(4));
+    }
+
+    // 岩棚は乗ってから CRUMBLE_TICKS で消え、ドロイド君は落ち始める。
+    #[test_case]
+    fn ledges_crumble_under_the_droid(_gba: &mut agb::Gba) {
+        let mut game = endless();
+        let climb = game.climb.as_mut().unwrap();
+        let id = climb
+            .platforms
+            .spawn(Platform {
+                kind: PlatformKind::Ledge,
+                x: 40,
+                y: 64,
+                crumble: Timer::new(),
+            })
+            .unwrap();
+        let top = climb.platforms.get(id).unwrap().top_y();
+        game.droid.x = 40;
+        game.droid.y = top;
+        for _ in 0..CRUMBLE_TICKS {
+            game.update(&Input::default());
+            assert_eq!((game.droid.state, game.droid.y), (PlayerState::Idle, top));
+        }
+        game.update(&Input::default());
+        assert!(game.climb.as_ref().unwrap().platforms.get(id).is_none());
+        game.update(&Input::default());
+        assert_eq!(game.droid.state, PlayerState::Airborne { double: false });
+    }
+
+    // 登るほど足場の間隔が広がり、カメラが速くなる。
+    #[test_case]
+    fn climbing_higher_ramps_up_the_difficulty(_gba: &mut agb::Gba) {
+        let mut climb = Climb::new();
+        let (gap, rise) = (climb.gap(), climb.rise_speed());
+        climb.height = RAMP_METRES;
+        assert!(climb.gap() > gap && climb.rise_speed() > rise);
+        climb.height = 100 * RAMP_METRES;
+        assert_eq!(climb.level(), MAX_LEVEL);
+        /* 間隔が広がっても、上端の足場から 2 段ジャンプせずに届く */
+        assert!(climb.gap() <= 48);
+
+        /* 跳んで高さを稼ぐと、得点はコインの点数に足される */
+        let mut game = endless();
+        game.droid.x = game.apple.x;
+        game.update(&Input::new(Button::UP, Button::empty()));
+        for _ in 0..60 {
+            game.update(&Input::new(Button::UP, Button::empty()));
+        }
+        let climb = game.climb.as_ref().unwrap();
+        assert!(climb.height > 0);
+        assert_eq!(game.climb_score(), climb.height + game.score);
+    }
+}
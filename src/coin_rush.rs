@@ -0,0 +1,197 @@
+// コインラッシュ。制限時間の中でコインを集める得点のモード。
+//
+// 普通のモードとの違い:
+// - 始まる前にタイムアタックと同じカウントダウンがあり、残り RUSH_TICKS から減っていく。
+// - レベルにはコインラッシュ層のコインも置く (Game::load) ので、コインはおよそ 3 倍になる。
+// - コインを取るたびに残り時間が COIN_BONUS_TICKS 増える (RUSH_TICKS まで)。
+// - 残り時間が尽きるか、コインを全て取ると終わる。得点はコインの点数で、全て取れば FINISH_BONUS 倍。
+// - 体力が尽きても終わらず、残り時間が DEATH_PENALTY_TICKS 減ってレベルの出発点からやり直す。
+// - 残りが LOW_TIME_TICKS を切ると HUD の残り時間が赤く点滅し、1 秒ごとに警告音を鳴らす。
+// 最高得点は難易度ごとに普通のモードとは別に記録する (save::Board::CoinRush)。
+
+use crate::{
+    events::{Event, Sfx},
+    game::{Game, Outcome},
+    math::Fixed,
+    player::PlayerState,
+    snapshot::encode_fields,
+};
+
+/* 最初の残り時間で、コインで増やせる上限 */
+const RUSH_TICKS: u32 = 60 * 60;
+/* コイン 1 枚で増える時間 */
+const COIN_BONUS_TICKS: u32 = 60;
+/* 体力が尽きたときに減る時間 */
+const DEATH_PENALTY_TICKS: u32 = 5 * 60;
+/* 残り時間の警告を始める残り */
+pub const LOW_TIME_TICKS: u32 = 10 * 60;
+/* コインを全て取ったときの得点の倍率 */
+pub const FINISH_BONUS: u32 = 2;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CoinRush {
+    /* 残り時間 (ティック) */
+    pub remaining: u32,
+}
+
+encode_fields!(CoinRush { remaining });
+
+impl CoinRush {
+    pub fn new() -> Self {
+        Self {
+            remaining: RUSH_TICKS,
+        }
+    }
+
+    // コインを 1 枚取った。
+    pub fn collect(&mut self) {
+        self.remaining = (self.remaining + COIN_BONUS_TICKS).min(RUSH_TICKS);
+    }
+}
+
+impl Game {
+    // コインラッシュの 1 ティック分。イベントを処理した後に呼ぶ。
+    pub fn update_rush(&mut self) {
+        /* 他のモードでは毎ティック終わり方を調べるまでもない */
+        if self.rush.is_none() || self.outcome().is_some() {
+            return;
+        }
+        let Some(rush) = &mut self.rush else {
+            return;
+        };
+        rush.remaining -= 1;
+        if self.health == 0 {
+            rush.remaining = rush.remaining.saturating_sub(DEATH_PENALTY_TICKS);
+            log_debug!("rush respawn, remaining = {}", rush.remaining);
+        }
+        if rush.remaining < LOW_TIME_TICKS && rush.remaining % 60 == 0 {
+            self.push_event(Event::PlaySfx(Sfx::LowTime));
+        }
+        if self.health == 0 {
+            self.health = self.difficulty.settings().lives;
+            let level = self.level();
+            (self.droid.x, self.droid.y) = level.droid;
+            self.droid.hflip = level.hflip;
+            self.droid.vy = Fixed::new(0);
+            self.droid.enter(PlayerState::Idle);
+        }
+    }
+
+    // コインラッシュの終わり方。コインを全て取ればクリア、時間が尽きれば Died 。
+    pub fn rush_outcome(&self, rush: &CoinRush) -> Option<Outcome> {
+        if self.coins.is_empty() {
+            Some(Outcome::Cleared)
+        } else if rush.remaining == 0 {
+            Some(Outcome::Died)
+        } else {
+            None
+        }
+    }
+
+    // コインラッシュの得点。
+    pub fn rush_score(&self) -> u32 {
+        if self.coins.is_empty() {
+            self.score * FINISH_BONUS
+        } else {
+            self.score
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        difficulty::Difficulty,
+        game::{COIN_SCORE, LEVEL_OBJECTS, RUSH_OBJECTS},
+        input::Input,
+        level::Level,
+        mode::Mode,
+        spawn::EntityKind,
+        time_attack::COUNTDOWN_TICKS,
+    };
+
+    /* レベルとコインラッシュ層のコインの数 (レベルにはりんごも置いてある) */
+    fn all_coins() -> usize {
+        LEVEL_OBJECTS
+            .iter()
+            .chain(&RUSH_OBJECTS)
+            .filter(|(kind, _)| *kind == EntityKind::Coin)
+            .count()
+    }
+
+    fn rush() -> Game {
+        let mut game = Game::new(Difficulty::Normal, 1).with_mode(Mode::CoinRush);
+        for _ in 0..COUNTDOWN_TICKS {
+            game.update(&Input::default());
+        }
+        game
+    }
+
+    fn remaining(game: &Game) -> u32 {
+        game.rush.as_ref().unwrap().remaining
+    }
+
+    // コインは時間を足し (上限まで)、時間が尽きたら終わる。
+    #[test_case]
+    fn coins_buy_time_until_it_runs_out(_gba: &mut agb::Gba) {
+        let mut game = rush();
+        assert_eq!(game.coins.len(), all_coins());
+        assert_eq!(remaining(&game), RUSH_TICKS);
+        game.update(&Input::default());
+        assert_eq!(remaining(&game), RUSH_TICKS - 1);
+
+        let (coin, _) = game.coins.iter().next().unwrap();
+        game.push_event(Event::CoinCollected { coin });
+        game.update(&Input::default());
+        /* 上限で止まる */
+        assert_eq!(remaining(&game), RUSH_TICKS - 1);
+        for _ in 0..LOW_TIME_TICKS * 2 {
+            game.update(&Input::default());
+        }
+        let before = remaining(&game);
+        let (coin, _) = game.coins.iter().next().unwrap();
+        game.push_event(Event::CoinCollected { coin });
+        game.update(&Input::default());
+        assert_eq!(remaining(&game), before + COIN_BONUS_TICKS - 1);
+
+        while game.outcome().is_none() {
+            game.update(&Input::default());
+        }
+        assert_eq!(game.outcome(), Some(Outcome::Died));
+        assert_eq!(remaining(&game), 0);
+        /* 全て取っていないので倍率は掛からない */
+        assert_eq!(game.rush_score(), 2 * COIN_SCORE);
+    }
+
+    // 体力が尽きても終わらず、時間を失って出発点からやり直す。
+    #[test_case]
+    fn running_out_of_health_costs_time(_gba: &mut agb::Gba) {
+        let mut game = rush();
+        game.droid.x += 40;
+        game.health = 1;
+        game.push_event(Event::PlayerDamaged {
+            amount: 1,
+            from_x: 0,
+        });
+        game.update(&Input::default());
+        assert_eq!(game.outcome(), None);
+        assert_eq!(remaining(&game), RUSH_TICKS - 1 - DEATH_PENALTY_TICKS);
+        assert_eq!(game.health, Difficulty::Normal.settings().lives);
+        assert_eq!((game.droid.x, game.droid.y), Level::load(false).droid);
+    }
+
+    // 全て取れば終わり、得点に倍率が掛かる。
+    #[test_case]
+    fn clearing_the_rush_doubles_the_score(_gba: &mut agb::Gba) {
+        let mut game = rush();
+        let coins: alloc::vec::Vec<_> = game.coins.iter().map(|(id, _)| id).collect();
+        for coin in coins {
+            game.push_event(Event::CoinCollected { coin });
+        }
+        game.update(&Input::default());
+        assert_eq!(game.outcome(), Some(Outcome::Cleared));
+        let collected = all_coins() as u32;
+        assert_eq!(game.rush_score(), collected * COIN_SCORE * FINISH_BONUS);
+    }
+}
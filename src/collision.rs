@@ -0,0 +1,894 @@
+// 乗れるもの (りんご、エンドレスモードの足場) との当たり判定。
+//
+// 当たり判定の形は全て軸に沿った箱 (Aabb) で、重なり (overlaps)、接触 (contacts)、
+// 上から乗ったときの位置 (Sweep::land_on)、横にめり込んだときに押し戻す位置 (push_out_x) はこれで求める。
+// 新しく当たるものを入れるときは、その箱を返せば同じ判定が使える。
+//
+// 乗れるもの (Surface) は乗れる面の箱 (bounds) だけを決める。ドロイド君は足元 (footprint、左右 3 ドットずつ
+// 内側) が箱と横に重なっていれば乗れるので、左右 4 ドットまではみ出しても立っていられる。
+// 横方向は Surface::x_range() に入っていれば (両端を含む) 乗れる。
+// 縦方向は落下中に上面 top_y() を上から跨いだ (ちょうど重なった場合も含む)
+// ときだけ着地する。下から跳び上がってりんごを通り抜けている途中は決して乗らない。
+// 下から通り抜けられるか (Passage) は乗れるものごとに決まる。通り抜けられない (Solid) ものには
+// 下から頭をぶつけ、その下面で止まる。りんごとエンドレスの足場は全て通り抜けられ (OneWay)、
+// 動く足場はレベルのデータ (EntityKind::MovingPlatform の passage) で決める。崩れる足場は通り抜けられる。
+// 着地は 1 ティックに動いた線分 (Sweep) で見る。斜めに速く動いて、動き終わりでは横に外れていても、
+// 下端が上面に届いたときに重なっていれば端に乗るので、角をすり抜けない。
+// 立っているときも着地するときも同じ supports() で判定するので、
+// 「乗れるけど立っていられない」位置はできない。
+//
+// 乗れるものは Surfaces でまとめて見る (りんご、レベルに置いた他のりんご、エンドレスの足場、動く足場、
+// 崩れる足場、ばね)。崩れる足場は落ち始めたら (is_solid でなくなったら) 乗れるものから外れる。
+// ばねは着地したティックのうちに打ち上げる (spring モジュール) ので、着地だけを見る。
+// 地面 (GROUND_Y) はどこにでもあるので、ここには入れない。
+//
+// 乗れるものの端は掴める。x_range() のすぐ外 LEDGE_REACH ドットまでにいて端の方を向いているドロイド君が、
+// 落ちる途中で手 (ぶら下がったときの y = top_y() + LEDGE_HANG) を上から跨ぐと掴む。
+// 掴んだら x は端のすぐ外に揃える。
+//
+// 壁 (Walls) は横方向の仕切りで、ドロイド君は左右の壁の間から出られない。
+// 壁の無いレベルでは画面端で回り込む (game::wrap_x)。
+//
+// ぶつかり合うもの (Collider) は、当たり判定の箱 (hitbox) と、自分の層と当たる相手の層の組 (Filter) を持つ。
+// 層 (Layers) はプレイヤー、敵、危険物、拾えるもの、地形の 5 つ。当たるかどうかは層のビットだけで決まり
+// (Filter::hits)、touching() で相手の入れ物から当たっているものを探す。
+// 新しい種類のものは、層を決めて Collider を実装すれば、メインループを書き換えずに当たる相手を選べる。
+
+use core::ops::{BitOr, RangeInclusive};
+
+use crate::{
+    arena::{Arena, EntityId},
+    climb::Climb,
+    crumbling::{CrumblingPlatform, MAX_CRUMBLING_PLATFORMS},
+    game::{Apple, APPLE_HITBOX, DROID_WIDTH, MAX_APPLES},
+    math::Fixed,
+    moving_platform::{MovingPlatform, MAX_MOVING_PLATFORMS},
+    physics::Tuning,
+    snapshot::{encode_fields, encode_variants},
+    spring::{Spring, MAX_SPRINGS},
+};
+
+// 軸に沿った箱。(x, y) が左上で、右端 (right) と下端 (bottom) は含まない。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Aabb {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+encode_fields!(Aabb {
+    x,
+    y,
+    width,
+    height
+});
+
+impl Aabb {
+    pub const fn new(x: i32, y: i32, width: i32, height: i32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub const fn right(self) -> i32 {
+        self.x + self.width
+    }
+
+    pub const fn bottom(self) -> i32 {
+        self.y + self.height
+    }
+
+    // 横の範囲が重なっているか。辺が接しているだけなら重ならない。
+    pub const fn overlaps_x(self, other: Aabb) -> bool {
+        self.x < other.right() && other.x < self.right()
+    }
+
+    // 縦の範囲が重なっているか。
+    pub const fn overlaps_y(self, other: Aabb) -> bool {
+        self.y < other.bottom() && other.y < self.bottom()
+    }
+
+    pub const fn overlaps(self, other: Aabb) -> bool {
+        self.overlaps_x(other) && self.overlaps_y(other)
+    }
+
+    // 点 (x, y) が中にあるか。
+    pub const fn contains(self, (x, y): (i32, i32)) -> bool {
+        self.x <= x && x < self.right() && self.y <= y && y < self.bottom()
+    }
+
+    // 重ならずに辺で接しているか (上下か左右に隣り合っている)。角だけが接しているのは含まない。
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub const fn contacts(self, other: Aabb) -> bool {
+        let stacked = self.bottom() == other.y || other.bottom() == self.y;
+        let beside = self.right() == other.x || other.right() == self.x;
+        (stacked && self.overlaps_x(other)) || (beside && self.overlaps_y(other))
+    }
+
+    // y_before から今の y まで上へ動いた箱が、other の下面を下から跨いだなら、other の下に付けたときの y 座標。
+    pub const fn bump_into(self, other: Aabb, y_before: i32) -> Option<i32> {
+        if self.overlaps_x(other) && other.bottom() <= y_before && self.y < other.bottom() {
+            Some(other.bottom())
+        } else {
+            None
+        }
+    }
+
+    // 上面を跨いだが動き終わりで横に外れている箱が、上面に届いたときに other と重なっていたなら、
+    // other の端に寄せて乗せたときの位置。滅多に起きないので、毎ティックの処理からは外しておく。
+    #[cold]
+    #[inline(never)]
+    const fn graze_onto(self, other: Aabb, from: (i32, i32)) -> Option<(i32, i32)> {
+        let (x_before, y_before) = from;
+        /* 横に重なる x は left < x < right */
+        let (left, right) = (other.x - self.width, other.right());
+        /* 届いたときの x は x_before + dx * reach / drop。割り算はせず、drop を掛けて比べる */
+        let drop = self.y - y_before;
+        if drop == 0 {
+            return None;
+        }
+        let reached = x_before * drop + (self.x - x_before) * (other.y - (y_before + self.height));
+        if reached <= left * drop || right * drop <= reached {
+            return None;
+        }
+        let x = if self.x <= left { left + 1 } else { right - 1 };
+        Some((x, other.y - self.height))
+    }
+
+    // dir (-1, 1) へ横に動いて other にめり込んだ箱を、来た方へ押し戻したときの x 座標。
+    // めり込んでいなければ今の x のまま。
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub const fn push_out_x(self, other: Aabb, dir: i32) -> i32 {
+        if !self.overlaps(other) {
+            self.x
+        } else if dir > 0 {
+            other.x - self.width
+        } else if dir < 0 {
+            other.right()
+        } else {
+            self.x
+        }
+    }
+}
+
+// from (左上) から to まで、1 ティックの間に真っ直ぐ動いた箱。
+// 横に動いた範囲 (left..right) は作るときに一度だけ求め、乗れるものを順に見るときは比べるだけにする。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sweep {
+    from: (i32, i32),
+    to: Aabb,
+    left: i32,
+    right: i32,
+}
+
+impl Sweep {
+    pub const fn new(from: (i32, i32), to: Aabb) -> Self {
+        let (left, right) = if from.0 < to.x {
+            (from.0, to.right())
+        } else {
+            (to.x, from.0 + to.width)
+        };
+        Self {
+            from,
+            to,
+            left,
+            right,
+        }
+    }
+
+    // other の上面を上から跨いだ (ちょうど接した場合も含む) なら、other の上に乗せたときの位置 (x, y)。
+    // 動き終わりで横に重なっていれば、その x で乗る。外れていても、斜めに動いて下端が上面に届いたときに
+    // 重なっていれば、端に寄せて乗る (Aabb::graze_onto)。
+    #[inline]
+    pub const fn land_on(self, other: Aabb) -> Option<(i32, i32)> {
+        let to = self.to;
+        if self.right <= other.x || other.right() <= self.left {
+            /* 動いた範囲のどこでも横に重ならない (ほとんどのものはここで外れる) */
+            None
+        } else if self.from.1 + to.height > other.y || other.y > to.bottom() {
+            None
+        } else if to.overlaps_x(other) {
+            Some((to.x, other.y - to.height))
+        } else {
+            to.graze_onto(other, self.from)
+        }
+    }
+}
+
+// 当たり判定の層の組 (ビットの集まり)。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Layers(u8);
+
+impl Layers {
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub const NONE: Self = Self(0);
+    pub const PLAYER: Self = Self(1 << 0);
+    pub const ENEMY: Self = Self(1 << 1);
+    pub const HAZARD: Self = Self(1 << 2);
+    pub const PICKUP: Self = Self(1 << 3);
+    pub const TERRAIN: Self = Self(1 << 4);
+
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    // 共通の層があるか。
+    pub const fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl BitOr for Layers {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        self.union(other)
+    }
+}
+
+// 自分の層 (layer) と、当たる相手の層 (mask)。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Filter {
+    pub layer: Layers,
+    pub mask: Layers,
+}
+
+impl Filter {
+    pub const fn new(layer: Layers, mask: Layers) -> Self {
+        Self { layer, mask }
+    }
+
+    // self が other に当たるか (other の層が self の mask に入っているか)。
+    pub const fn hits(self, other: Filter) -> bool {
+        self.mask.intersects(other.layer)
+    }
+}
+
+// ぶつかり合うもの。
+pub trait Collider {
+    fn hitbox(&self) -> Aabb;
+
+    fn filter(&self) -> Filter;
+}
+
+// collider に当たっている arena のもの。層が合わないものは箱が重なっていても返さない。
+pub fn touching<'a, T: Collider, const N: usize>(
+    collider: &impl Collider,
+    arena: &'a Arena<T, N>,
+) -> impl Iterator<Item = EntityId> + 'a {
+    let (hitbox, filter) = (collider.hitbox(), collider.filter());
+    arena.iter().filter_map(move |(id, other)| {
+        (filter.hits(other.filter()) && hitbox.overlaps(other.hitbox())).then_some(id)
+    })
+}
+
+/* 乗るときに、ドロイド君の左右の端からはみ出してよいドット数 (足元の箱の内側への幅) */
+const FOOT_INSET: i32 = 3;
+
+// (x, y) にいるドロイド君の足元の箱。乗れるものの箱と横に重なっていれば乗れる。
+pub const fn footprint(x: i32, y: i32) -> Aabb {
+    Aabb::new(x + FOOT_INSET, y, DROID_WIDTH - 2 * FOOT_INSET, DROID_WIDTH)
+}
+
+// 乗れるものを下から通り抜けられるか。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Passage {
+    // 下から跳び上がって通り抜け、落ちてくるときだけ乗る。
+    OneWay,
+    // 下から頭をぶつけて通れない。
+    Solid,
+}
+
+encode_variants!(Passage { OneWay, Solid });
+
+pub trait Surface {
+    // 乗れる面の箱。上端 (y) にドロイド君の足の裏が乗る。
+    fn bounds(&self) -> Aabb;
+
+    fn passage(&self) -> Passage {
+        Passage::OneWay
+    }
+
+    // ドロイド君がこの x の範囲にいれば乗れる (足元が bounds と横に重なる範囲)。
+    fn x_range(&self) -> RangeInclusive<i32> {
+        let bounds = self.bounds();
+        (bounds.x - DROID_WIDTH + FOOT_INSET + 1)..=(bounds.right() - FOOT_INSET - 1)
+    }
+
+    // 乗っているときのドロイド君の y 座標。
+    fn top_y(&self) -> i32 {
+        self.bounds().y - DROID_WIDTH
+    }
+}
+
+impl Surface for Apple {
+    // 絵の当たり判定 (スプライトの上の空いているところを除いた箱)。狭いりんご (修飾) は左右から同じだけ削る。
+    fn bounds(&self) -> Aabb {
+        let trim = Tuning::NORMAL.apple_half_width - self.half_width;
+        Aabb::new(
+            self.x + APPLE_HITBOX.x + trim,
+            self.y + APPLE_HITBOX.y,
+            APPLE_HITBOX.width - 2 * trim,
+            APPLE_HITBOX.height,
+        )
+    }
+}
+
+// ドロイド君がいられる横の範囲。left と right は壁の面の x 座標。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Walls {
+    pub left: i32,
+    pub right: i32,
+}
+
+impl Walls {
+    // 壁にめり込まない x 。
+    pub fn clamp(&self, x: i32) -> i32 {
+        x.clamp(self.left, self.right - DROID_WIDTH)
+    }
+
+    // x にいるドロイド君が dir (-1, 1) の側の壁に接しているか。
+    pub fn touches(&self, x: i32, dir: i32) -> bool {
+        match dir {
+            -1 => x <= self.left,
+            1 => x >= self.right - DROID_WIDTH,
+            _ => false,
+        }
+    }
+}
+
+/* ぶら下がっているドロイド君の y は、立っているときより LEDGE_HANG 下 (手が上面に掛かる) */
+pub const LEDGE_HANG: i32 = 14;
+/* 端からこのドット数まで外にいれば掴める */
+pub const LEDGE_REACH: i32 = 4;
+
+// ドロイド君が乗れるもの全て。
+pub struct Surfaces<'a> {
+    pub apple: &'a Apple,
+    /* レベルに置いた他のりんご */
+    pub apples: &'a Arena<Apple, MAX_APPLES>,
+    /* エンドレスの足場 */
+    pub climb: Option<&'a Climb>,
+    pub moving: &'a Arena<MovingPlatform, MAX_MOVING_PLATFORMS>,
+    pub crumbling: &'a Arena<CrumblingPlatform, MAX_CRUMBLING_PLATFORMS>,
+    pub springs: &'a Arena<Spring, MAX_SPRINGS>,
+}
+
+impl Surfaces<'_> {
+    // x にいるドロイド君が y で何かに立っていられるか。
+    pub fn supports(&self, x: i32, y: i32) -> bool {
+        (self.apple.top_y() == y && supports(self.apple, x))
+            || self
+                .apples
+                .values()
+                .any(|apple| apple.top_y() == y && supports(apple, x))
+            || self.climb.is_some_and(|climb| climb.supports(x, y))
+            || self
+                .moving
+                .values()
+                .any(|platform| platform.top_y() == y && supports(platform, x))
+            || self
+                .solid_crumbling()
+                .any(|platform| platform.top_y() == y && supports(platform, x))
+    }
+
+    // まだ崩れ落ちていない崩れる足場。
+    fn solid_crumbling(&self) -> impl Iterator<Item = &CrumblingPlatform> {
+        self.crumbling
+            .values()
+            .filter(|platform| platform.is_solid())
+    }
+
+    // from から to まで速度 vy で動いたとき、どれかに着地するならその位置 (x, y)。
+    #[link_section = ".iwram.surfaces_resolve_landing"]
+    pub fn resolve_landing(
+        &self,
+        from: (i32, i32),
+        to: (i32, i32),
+        vy: Fixed,
+    ) -> Option<(i32, i32)> {
+        let sweep = landing_sweep(from, to, vy)?;
+        land_on(self.apple, sweep)
+            .or_else(|| self.climb?.land_on(sweep))
+            .or_else(|| self.land_on_entities(sweep))
+    }
+
+    // 他のりんごと動く足場、崩れる足場、ばねへの着地。IWRAM に置く resolve_landing を小さく保つため分けておく。
+    #[inline(never)]
+    fn land_on_entities(&self, sweep: Sweep) -> Option<(i32, i32)> {
+        self.apples
+            .values()
+            .find_map(|apple| land_on(apple, sweep))
+            .or_else(|| {
+                self.moving
+                    .values()
+                    .find_map(|platform| land_on(platform, sweep))
+            })
+            .or_else(|| {
+                self.solid_crumbling()
+                    .find_map(|platform| land_on(platform, sweep))
+            })
+            .or_else(|| {
+                self.springs
+                    .values()
+                    .find_map(|spring| land_on(spring, sweep))
+            })
+    }
+
+    // y_before から y_after まで上ったとき、通り抜けられないものに頭をぶつけるなら止まる y 座標。
+    // りんごとエンドレスの足場は通り抜けられるので、動く足場だけを見る。
+    pub fn resolve_bump(&self, x: i32, y_before: i32, y_after: i32) -> Option<i32> {
+        self.moving
+            .values()
+            .find_map(|platform| resolve_bump(platform, x, y_before, y_after))
+    }
+
+    // dir (-1, 1) を向いて y_before から y_after まで落ちたとき、どれかの端を掴むなら掴んだ位置 (x, y)。
+    pub fn resolve_grab(
+        &self,
+        x: i32,
+        dir: i32,
+        y_before: i32,
+        y_after: i32,
+    ) -> Option<(i32, i32)> {
+        resolve_grab(self.apple, x, dir, y_before, y_after)
+            .or_else(|| {
+                self.apples
+                    .values()
+                    .find_map(|apple| resolve_grab(apple, x, dir, y_before, y_after))
+            })
+            .or_else(|| self.climb?.resolve_grab(x, dir, y_before, y_after))
+            .or_else(|| {
+                self.moving
+                    .values()
+                    .find_map(|platform| resolve_grab(platform, x, dir, y_before, y_after))
+            })
+            .or_else(|| {
+                self.solid_crumbling()
+                    .find_map(|platform| resolve_grab(platform, x, dir, y_before, y_after))
+            })
+    }
+
+    // (x, y) で dir を向いてぶら下がっている端がまだあるか。
+    pub fn holds(&self, x: i32, y: i32, dir: i32) -> bool {
+        holds(self.apple, x, y, dir)
+            || self.apples.values().any(|apple| holds(apple, x, y, dir))
+            || self.climb.is_some_and(|climb| climb.holds(x, y, dir))
+            || self
+                .moving
+                .values()
+                .any(|platform| holds(platform, x, y, dir))
+            || self
+                .solid_crumbling()
+                .any(|platform| holds(platform, x, y, dir))
+    }
+}
+
+// x にいるドロイド君を surface が支えられるか。
+pub fn supports(surface: &impl Surface, x: i32) -> bool {
+    footprint(x, surface.top_y()).overlaps_x(surface.bounds())
+}
+
+// from から to まで速度 vy で動いたとき、surface に着地するならその位置 (x, y)。
+#[cfg_attr(not(test), allow(dead_code))]
+pub fn resolve_landing(
+    surface: &impl Surface,
+    from: (i32, i32),
+    to: (i32, i32),
+    vy: Fixed,
+) -> Option<(i32, i32)> {
+    landing_sweep(from, to, vy).and_then(|sweep| land_on(surface, sweep))
+}
+
+// from から to まで速度 vy で落ちたときの足元の箱の動き。上っていれば着地しないので None。
+pub fn landing_sweep(from: (i32, i32), to: (i32, i32), vy: Fixed) -> Option<Sweep> {
+    if vy < Fixed::new(0) {
+        Some(Sweep::new(
+            (from.0 + FOOT_INSET, from.1),
+            footprint(to.0, to.1),
+        ))
+    } else {
+        None
+    }
+}
+
+// 足元の箱が sweep のように動いたとき、surface に着地するならそのドロイド君の位置 (x, y)。
+#[inline]
+pub fn land_on(surface: &impl Surface, sweep: Sweep) -> Option<(i32, i32)> {
+    sweep
+        .land_on(surface.bounds())
+        .map(|(x, y)| (x - FOOT_INSET, y))
+}
+
+// y_before から y_after まで上ったとき、surface が通り抜けられないもので下から頭をぶつけるなら、止まる y 座標。
+pub fn resolve_bump(surface: &impl Surface, x: i32, y_before: i32, y_after: i32) -> Option<i32> {
+    match surface.passage() {
+        Passage::OneWay => None,
+        Passage::Solid => footprint(x, y_after).bump_into(surface.bounds(), y_before),
+    }
+}
+
+// x にいて dir (-1, 1) を向いているドロイド君の手が届く surface の端。掴んだときの x (端のすぐ外) を返す。
+pub fn ledge_x(surface: &impl Surface, x: i32, dir: i32) -> Option<i32> {
+    let range = surface.x_range();
+    let edge = match dir {
+        1 => range.start() - 1,
+        -1 => range.end() + 1,
+        _ => return None,
+    };
+    /* 端から外へ LEDGE_REACH ドットまで */
+    let outside = (edge - x) * dir;
+    (0..LEDGE_REACH).contains(&outside).then_some(edge)
+}
+
+// dir を向いて y_before から y_after まで落ちたとき、surface の端を掴むならその位置 (x, y)。
+pub fn resolve_grab(
+    surface: &impl Surface,
+    x: i32,
+    dir: i32,
+    y_before: i32,
+    y_after: i32,
+) -> Option<(i32, i32)> {
+    let hang = surface.top_y() + LEDGE_HANG;
+    if y_before < hang && hang <= y_after {
+        ledge_x(surface, x, dir).map(|x| (x, hang))
+    } else {
+        None
+    }
+}
+
+// (x, y) で dir を向いてぶら下がっているのが surface の端か。
+pub fn holds(surface: &impl Surface, x: i32, y: i32, dir: i32) -> bool {
+    surface.top_y() + LEDGE_HANG == y && ledge_x(surface, x, dir) == Some(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+
+    use agb::{fixnum::num, input::Button};
+
+    use super::*;
+    use crate::{difficulty::Difficulty, game::Game, input::Input, player::PlayerState};
+
+    const APPLE: Apple = Apple {
+        x: 160,
+        y: 120,
+        half_width: Tuning::NORMAL.apple_half_width,
+    };
+
+    // 辺が接しているだけなら重ならずに接触で、めり込んだら来た方へ押し戻す。上から跨げば上に乗る。
+    #[test_case]
+    fn boxes_overlap_touch_and_resolve(_gba: &mut agb::Gba) {
+        let block = Aabb::new(32, 40, 16, 8);
+        let beside = Aabb::new(16, 36, 16, 16);
+        assert!(!beside.overlaps(block));
+        assert!(beside.contacts(block) && block.contacts(beside));
+        /* 角だけ接しているのは接触ではない */
+        assert!(!Aabb::new(16, 24, 16, 16).contacts(block));
+        /* 右へ 2 ドット進んでめり込んだら、左へ押し戻す (左へ進んだなら右へ) */
+        let moved = Aabb::new(18, 36, 16, 16);
+        assert!(moved.overlaps(block) && !moved.contacts(block));
+        assert_eq!(moved.push_out_x(block, 1), 16);
+        assert_eq!(Aabb::new(46, 36, 16, 16).push_out_x(block, -1), 48);
+        assert_eq!(beside.push_out_x(block, 1), 16);
+        /* 上から跨いだら上に乗り、下からや横に外れていたら乗らない */
+        let falling = Aabb::new(36, 30, 8, 16);
+        let fall = |from: (i32, i32), to: Aabb| Sweep::new(from, to).land_on(block);
+        assert_eq!(fall((36, 20), falling), Some((36, 24)));
+        assert_eq!(fall((36, 26), falling), None);
+        assert_eq!(fall((48, 20), Aabb::new(48, 30, 8, 16)), None);
+        /* 下から跨いだら下面で止まる */
+        let rising = Aabb::new(36, 44, 8, 16);
+        assert_eq!(rising.bump_into(block, 50), Some(48));
+        assert_eq!(rising.bump_into(block, 44), None);
+        assert_eq!(falling.bump_into(block, 20), None);
+        assert!(block.contains((32, 47)) && !block.contains((48, 40)));
+    }
+
+    struct Thing {
+        at: (i32, i32),
+        filter: Filter,
+    }
+
+    impl Collider for Thing {
+        fn hitbox(&self) -> Aabb {
+            Aabb::new(self.at.0, self.at.1, 8, 8)
+        }
+
+        fn filter(&self) -> Filter {
+            self.filter
+        }
+    }
+
+    // 重なっていても、層が mask に入っていない相手には当たらない。
+    #[test_case]
+    fn masks_choose_what_touches(_gba: &mut agb::Gba) {
+        let pickup = Filter::new(Layers::PICKUP, Layers::PLAYER);
+        let hazard = Filter::new(Layers::HAZARD, Layers::PLAYER);
+        let mut things: Arena<Thing, 4> = Arena::new();
+        let coin = things
+            .spawn(Thing {
+                at: (0, 0),
+                filter: pickup,
+            })
+            .ok()
+            .unwrap();
+        let spike = things
+            .spawn(Thing {
+                at: (4, 4),
+                filter: hazard,
+            })
+            .ok()
+            .unwrap();
+        things
+            .spawn(Thing {
+                at: (32, 0),
+                filter: pickup,
+            })
+            .ok()
+            .unwrap();
+        let touched = |mask: Layers| {
+            let player = Thing {
+                at: (2, 2),
+                filter: Filter::new(Layers::PLAYER, mask),
+            };
+            touching(&player, &things).collect::<alloc::vec::Vec<_>>()
+        };
+        assert_eq!(touched(Layers::PICKUP | Layers::HAZARD), [coin, spike]);
+        assert_eq!(touched(Layers::PICKUP), [coin]);
+        assert_eq!(touched(Layers::NONE), []);
+        /* 当たるかは片方の mask だけで決まる */
+        assert!(pickup.hits(Filter::new(Layers::PLAYER, Layers::NONE)));
+        assert!(!Filter::new(Layers::PLAYER, Layers::NONE).hits(pickup));
+    }
+
+    // 乗れる x の範囲は箱から決まり、左右 4 ドットまではみ出して乗れる。
+    #[test_case]
+    fn surfaces_hold_up_to_four_dots_of_overhang(_gba: &mut agb::Gba) {
+        assert_eq!(APPLE.x_range(), (APPLE.x - 12)..=(APPLE.x + 12));
+        assert_eq!(APPLE.top_y(), APPLE.y - 13);
+        let platform = MovingPlatform::new((40, 80), (40, 80), Passage::OneWay);
+        assert_eq!(platform.x_range(), 28..=68);
+        assert_eq!(platform.top_y(), 80 - DROID_WIDTH);
+    }
+
+    #[test_case]
+    fn both_edges_of_the_apple_are_inclusive(_gba: &mut agb::Gba) {
+        let top = APPLE.top_y();
+        for x in [APPLE.x - 12, APPLE.x, APPLE.x + 12] {
+            assert!(supports(&APPLE, x), "x = {x}");
+            assert_eq!(
+                resolve_landing(&APPLE, (x, top - 2), (x, top + 1), Fixed::new(-1)),
+                Some((x, top))
+            );
+        }
+        for x in [APPLE.x - 13, APPLE.x + 13] {
+            assert!(!supports(&APPLE, x), "x = {x}");
+            assert_eq!(
+                resolve_landing(&APPLE, (x, top - 2), (x, top + 1), Fixed::new(-1)),
+                None
+            );
+        }
+    }
+
+    #[test_case]
+    fn landing_needs_to_cross_the_top_from_above(_gba: &mut agb::Gba) {
+        let (x, top) = (APPLE.x, APPLE.top_y());
+        /* ちょうど上面で止まったフレーム */
+        assert_eq!(
+            resolve_landing(&APPLE, (x, top - 3), (x, top), Fixed::new(-3)),
+            Some((x, top))
+        );
+        /* 上面から動き出したフレーム */
+        assert_eq!(
+            resolve_landing(&APPLE, (x, top), (x, top + 2), Fixed::new(-2)),
+            Some((x, top))
+        );
+        /* まだ上面に届いていない */
+        assert_eq!(
+            resolve_landing(&APPLE, (x, top - 3), (x, top - 1), Fixed::new(-2)),
+            None
+        );
+        /* 上面より下から落ちている (通り抜けた後) */
+        assert_eq!(
+            resolve_landing(&APPLE, (x, top + 1), (x, top + 3), Fixed::new(-2)),
+            None
+        );
+    }
+
+    // 斜めに落ちて動き終わりで外れていても、上面に届いたときに重なっていれば端に乗る。
+    #[test_case]
+    fn a_diagonal_fall_lands_where_it_crosses_the_top(_gba: &mut agb::Gba) {
+        let (x, top) = (APPLE.x, APPLE.top_y());
+        let fall = |from: (i32, i32), to: (i32, i32)| resolve_landing(&APPLE, from, to, num!(-8.));
+        /* 半分落ちたところで右端にちょうど届く (左へも同じ) */
+        assert_eq!(
+            fall((x + 8, top - 4), (x + 16, top + 4)),
+            Some((x + 12, top))
+        );
+        assert_eq!(
+            fall((x - 8, top - 4), (x - 16, top + 4)),
+            Some((x - 12, top))
+        );
+        /* 1 ドット外側を通ると届かない */
+        assert_eq!(fall((x + 9, top - 4), (x + 17, top + 4)), None);
+        /* 動き終わりで重なっていれば、そのまま乗る */
+        assert_eq!(
+            fall((x + 20, top - 4), (x + 12, top + 4)),
+            Some((x + 12, top))
+        );
+        /* ちょうど上面で止まったフレームは動き終わりの x */
+        assert_eq!(fall((x + 15, top - 3), (x + 12, top)), Some((x + 12, top)));
+        /* 縦に動いていなければ、上面にいる x だけで見る */
+        assert_eq!(fall((x + 8, top), (x + 12, top)), Some((x + 12, top)));
+        assert_eq!(fall((x + 8, top), (x + 13, top)), None);
+    }
+
+    #[test_case]
+    fn rising_never_lands(_gba: &mut agb::Gba) {
+        let (x, top) = (APPLE.x, APPLE.top_y());
+        /* 下から上面を跨いで上昇 */
+        assert_eq!(
+            resolve_landing(&APPLE, (x, top + 2), (x, top - 2), Fixed::new(4)),
+            None
+        );
+        /* 頂点で速度が 0 (符号が変わる直前) */
+        assert_eq!(resolve_landing(&APPLE, (x, top), (x, top), num!(0.)), None);
+        /* 符号が変わった直後は、上面にいれば着地する */
+        assert_eq!(
+            resolve_landing(&APPLE, (x, top), (x, top), num!(-0.3)),
+            Some((x, top))
+        );
+    }
+
+    // りんごの真下の地面から跳ぶと、上昇中は通り抜け、落ちてくるときに乗る。
+    #[test_case]
+    fn jumping_up_through_the_apple_lands_on_the_way_down(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        game.droid.x = game.apple.x;
+        game.update(&Input::new(Button::UP, Button::empty()));
+        let mut landed_at = None;
+        for tick in 0..120 {
+            let rising = game.droid.vy > Fixed::new(0);
+            game.update(&Input::default());
+            if game.droid.state == PlayerState::Idle {
+                assert!(!rising, "landed while rising at tick {tick}");
+                landed_at = Some(game.droid.y);
+                break;
+            }
+        }
+        assert_eq!(landed_at, Some(game.apple.top_y()));
+    }
+
+    // レベルに置いた他のりんごにも乗れるので、りんごからりんごへ跳び移れる。
+    #[test_case]
+    fn the_droid_hops_from_apple_to_apple(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        let (_, floating) = game.apples.iter().next().unwrap();
+        let (target_x, target_top) = (floating.x, floating.top_y());
+        game.droid.x = game.apple.x + 12;
+        game.droid.y = game.apple.top_y();
+        game.update(&Input::new(Button::UP | Button::RIGHT, Button::empty()));
+        for _ in 0..120 {
+            let held = if game.droid.x < target_x {
+                Button::UP | Button::RIGHT
+            } else {
+                Button::UP
+            };
+            game.update(&Input::new(held, held));
+            if game.droid.state == PlayerState::Idle {
+                break;
+            }
+        }
+        assert_eq!(game.droid.y, target_top);
+        /* 乗ったりんごの上に立っていられる */
+        for _ in 0..30 {
+            game.update(&Input::default());
+        }
+        assert_eq!(
+            (game.droid.state, game.droid.y),
+            (PlayerState::Idle, target_top)
+        );
+    }
+
+    // 端の外 LEDGE_REACH ドットまでで、端の方を向いていれば掴める。
+    #[test_case]
+    fn ledges_are_within_reach_when_facing_them(_gba: &mut agb::Gba) {
+        let (left, right) = (APPLE.x - 13, APPLE.x + 13);
+        for outside in 0..LEDGE_REACH {
+            assert_eq!(ledge_x(&APPLE, left - outside, 1), Some(left));
+            assert_eq!(ledge_x(&APPLE, right + outside, -1), Some(right));
+        }
+        for x in [left - LEDGE_REACH, left + 1] {
+            assert_eq!(ledge_x(&APPLE, x, 1), None, "x = {x}");
+        }
+        assert_eq!(ledge_x(&APPLE, left, -1), None);
+        assert_eq!(ledge_x(&APPLE, right, 1), None);
+    }
+
+    // 宙に浮かせたりんごの右の端のそばを、hflip の向きで落とす。着くか掴むまで進めた Game を返す。
+    // 3 回作るので、テストのスタックに Game が並ばないよう分けてヒープに置く (ghost のテストと同じ)。
+    #[inline(never)]
+    fn grabbed(hflip: bool) -> Box<Game> {
+        let mut game = Box::new(Game::new(Difficulty::Normal, 1));
+        game.apple.y = 60;
+        game.droid.x = game.apple.x + 13;
+        game.droid.y = 30;
+        game.droid.hflip = hflip;
+        game.droid.state = PlayerState::Airborne { double: false };
+        while matches!(game.droid.state, PlayerState::Airborne { double: false }) {
+            game.update(&Input::default());
+        }
+        game
+    }
+
+    // 宙に浮かせたりんごの横を落ちると端を掴み、UP でよじ登り、DOWN で手を離す。
+    #[test_case]
+    fn falling_past_a_ledge_grabs_it(_gba: &mut agb::Gba) {
+        let floor = |game: &Game| game.collision_map.floor_y(game.droid.x, 0);
+        /* 背を向けていれば掴まずに地面まで落ちる */
+        let game = grabbed(false);
+        assert_eq!(game.droid.y, floor(&game));
+
+        let mut game = grabbed(true);
+        let (x, top) = (game.apple.x + 13, game.apple.top_y());
+        assert_eq!(
+            (game.droid.state, game.droid.x, game.droid.y),
+            (PlayerState::LedgeHang, x, top + LEDGE_HANG)
+        );
+        for _ in 0..30 {
+            game.update(&Input::default());
+        }
+        assert_eq!(
+            (game.droid.state, game.droid.y),
+            (PlayerState::LedgeHang, top + LEDGE_HANG)
+        );
+        game.update(&Input::new(Button::UP, Button::empty()));
+        while game.droid.state == PlayerState::LedgeClimb {
+            game.update(&Input::default());
+        }
+        assert_eq!(
+            (game.droid.state, game.droid.x, game.droid.y),
+            (PlayerState::Idle, x - LEDGE_REACH, top)
+        );
+
+        let mut game = grabbed(true);
+        game.update(&Input::new(Button::DOWN, Button::empty()));
+        assert_eq!(game.droid.state, PlayerState::Airborne { double: false });
+        while matches!(game.droid.state, PlayerState::Airborne { double: false }) {
+            game.update(&Input::default());
+        }
+        assert_eq!(game.droid.y, floor(&game));
+    }
+
+    // 端のピクセルに立っているとき、外へ 1 歩出れば落ち、内へ戻れば立ったまま。
+    #[test_case]
+    fn stepping_off_the_edge_pixel(_gba: &mut agb::Gba) {
+        for (edge, away, toward) in [
+            (APPLE.x + 12, Button::RIGHT, Button::LEFT),
+            (APPLE.x - 12, Button::LEFT, Button::RIGHT),
+        ] {
+            for (button, falls) in [(away, true), (toward, false), (Button::empty(), false)] {
+                let mut game = Game::new(Difficulty::Normal, 1);
+                game.droid.x = edge;
+                game.droid.y = game.apple.top_y();
+                /* 押している向きへ歩く速さで動いているところ (止まっていれば 1 歩目は出ない) */
+                let dir = if button == Button::RIGHT { 1 } else { -1 };
+                if button != Button::empty() {
+                    game.droid.vx = game.tuning.ground.speed * dir;
+                }
+                game.update(&Input::new(button, button));
+                assert_eq!(
+                    game.droid.state != PlayerState::Idle,
+                    falls,
+                    "edge {edge}, {button:?}"
+                );
+            }
+        }
+    }
+}
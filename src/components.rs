@@ -0,0 +1,206 @@
+// エンティティを組み立てる小さな部品 (コンポーネント) と、それを進める処理 (システム)。
+//
+// 種類ごとに位置や速度や寿命を一から書かず、ここの部品をフィールドに並べて、Entity::update から
+// 同じ処理 (movement, gravity, Lifetime::tick, SpriteAnim::advance) を呼ぶ。
+// 破片 (debris) は Position, Velocity, Lifetime 、衝撃波 (shockwave) は Lifetime 、
+// ドロイド君の歩きのコマ送りは SpriteAnim でできている。敵やパーティクルを足すときも同じ部品を並べれば、
+// 動きとコマ送りは書かずに済む。
+// 当たり判定の部品は collision::Collider (箱と、ぶつかる相手の組) をそのまま使う。
+//
+// どれも値型で、Game に入れておけばリプレイでもそのまま再現する。
+
+use crate::{
+    math::Fixed,
+    snapshot::encode_fields,
+    time::{Repeater, Timer},
+};
+
+// 位置 (1/256 ドット単位)。描くときは pixel() で切り捨てる。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Position {
+    pub x: Fixed,
+    pub y: Fixed,
+}
+
+encode_fields!(Position { x, y });
+
+impl Position {
+    pub fn new(x: i32, y: i32) -> Self {
+        Self {
+            x: Fixed::new(x),
+            y: Fixed::new(y),
+        }
+    }
+
+    // ドット単位の位置。0 に向かって切り捨てる。
+    pub fn pixel(&self) -> (i32, i32) {
+        (self.x.trunc(), self.y.trunc())
+    }
+}
+
+// 1 ティックに動く量 (1/256 ドット単位、右と下が正)。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Velocity {
+    pub x: Fixed,
+    pub y: Fixed,
+}
+
+encode_fields!(Velocity { x, y });
+
+impl Velocity {
+    // 1/256 ドット単位の (横, 縦) から。
+    pub fn from_raw((x, y): (i32, i32)) -> Self {
+        Self {
+            x: Fixed::from_raw(x),
+            y: Fixed::from_raw(y),
+        }
+    }
+}
+
+// 出てから消えるまでの寿命。
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Lifetime {
+    timer: Timer,
+}
+
+encode_fields!(Lifetime { timer });
+
+impl Lifetime {
+    // ticks 回 tick() すると尽きる。
+    pub fn new(ticks: u16) -> Self {
+        Self {
+            timer: Timer::started(ticks),
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.timer.tick();
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.timer.is_running()
+    }
+
+    // 尽きるまでの残りティック数。
+    pub fn remaining(&self) -> u16 {
+        self.timer.remaining()
+    }
+}
+
+// 一定のティックごとに次のコマへ送り、frames コマで最初に戻るコマ送り。
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpriteAnim {
+    frames: u8,
+    frame: u8,
+    repeater: Repeater,
+}
+
+encode_fields!(SpriteAnim {
+    frames,
+    frame,
+    repeater
+});
+
+impl SpriteAnim {
+    // period ティックごとに 1 コマ送る。
+    pub const fn new(frames: u8, period: u16) -> Self {
+        Self {
+            frames,
+            frame: 0,
+            repeater: Repeater::new(period),
+        }
+    }
+
+    // 今のコマ (0..frames)。
+    pub fn frame(&self) -> u8 {
+        self.frame
+    }
+
+    // ticks ティック進める。コマが変わったら true 。
+    pub fn advance(&mut self, ticks: u16) -> bool {
+        let steps = self.repeater.advance(ticks);
+        /* 毎ティック通るので割り算はしない (送るのはほとんど 1 コマ) */
+        for _ in 0..steps {
+            self.frame += 1;
+            if self.frame >= self.frames {
+                self.frame = 0;
+            }
+        }
+        steps > 0
+    }
+
+    // 1 コマを見せるティック数を変える。今のコマはそのまま。
+    pub fn set_period(&mut self, period: u16) {
+        self.repeater.set_period(period);
+    }
+
+    // 最初のコマに戻す。次のコマ送りまでの数えはそのまま。
+    pub fn rewind(&mut self) {
+        self.frame = 0;
+    }
+
+    // 最初のコマに戻し、次のコマ送りも period ティック後にする。
+    pub fn restart(&mut self) {
+        self.frame = 0;
+        self.repeater.reset();
+    }
+}
+
+// 速度の分だけ動かす。
+pub fn movement(position: &mut Position, velocity: &Velocity) {
+    position.x += velocity.x;
+    position.y += velocity.y;
+}
+
+// 下向きの速度へ gravity を足す。
+pub fn gravity(velocity: &mut Velocity, gravity: Fixed) {
+    velocity.y += gravity;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 重力を足してから動かすと、放物線を描いて落ちてくる。
+    #[test_case]
+    fn things_fly_and_fall(_gba: &mut agb::Gba) {
+        let mut position = Position::new(10, 20);
+        let mut velocity = Velocity::from_raw((128, -512));
+        let mut highest = 20;
+        for _ in 0..16 {
+            gravity(&mut velocity, Fixed::from_raw(64));
+            movement(&mut position, &velocity);
+            highest = highest.min(position.pixel().1);
+        }
+        assert_eq!(position.pixel().0, 18);
+        assert!(highest < 20);
+        assert!(position.pixel().1 > highest);
+    }
+
+    // コマは period ごとに送られて一周し、寿命は数え終わると尽きる。
+    #[test_case]
+    fn frames_loop_and_lifetimes_run_out(_gba: &mut agb::Gba) {
+        let mut anim = SpriteAnim::new(3, 2);
+        let frames: alloc::vec::Vec<_> = (0..8)
+            .map(|_| {
+                anim.advance(1);
+                anim.frame()
+            })
+            .collect();
+        assert_eq!(frames, [0, 1, 1, 2, 2, 0, 0, 1]);
+        /* 戻してもコマ送りまでの数えは続き、やり直すと数え直す */
+        anim.advance(1);
+        anim.rewind();
+        assert!(anim.advance(1));
+        assert_eq!(anim.frame(), 1);
+        anim.restart();
+        assert!(!anim.advance(1));
+        assert_eq!(anim.frame(), 0);
+
+        let mut life = Lifetime::new(2);
+        life.tick();
+        assert!(life.is_alive());
+        life.tick();
+        assert!(!life.is_alive());
+    }
+}
@@ -0,0 +1,107 @@
+// ベルトコンベア。
+//
+// レベルの地面の列ごとに、上に立っているものを流す速さ (Level::conveyors) を持つ。
+// 地面に立っているドロイド君は、足元の真ん中の列の速さ (drift_at) だけ、左右の速度とは別に流される。
+// 流す分は左右の速度の端数 (Droid::advance) に足すので、1 ティックに 1 ドットに満たない遅いベルトでも
+// 少しずつ流れ、左右の速度そのものは変わらない (流れに逆らって歩けば遅く、沿って歩けば速く進む)。
+// 空中や逆さのとき、りんごや足場の上では流されない。地面に立つのはドロイド君だけなので、流れるのも今はドロイド君だけ。
+//
+// 表面は gameplay::CONVEYOR_TILES のコマで描く。右へ流れる絵を 2 ドットずつずらしたコマで、左へ流れるベルトは
+// 左右反転する。ベルトがそれまでに流した距離からコマを決める (tile) ので、速いベルトほど絵も速く流れる。
+
+use agb::display::WIDTH;
+
+use crate::{game::DROID_WIDTH, gameplay::CONVEYOR_TILES, level::GROUND_COLUMNS, math::Fixed};
+
+/* 絵の模様が 1 コマでずれるドット数 */
+const FRAME_SHIFT: u32 = 2;
+
+// x にいるドロイド君の足元 (真ん中) の列のベルトの速さ。画面の外やベルトの無い列は 0 。
+pub fn drift_at(conveyors: &[Fixed; GROUND_COLUMNS], x: i32) -> Fixed {
+    let foot = x + DROID_WIDTH / 2;
+    if (0..WIDTH).contains(&foot) {
+        conveyors[(foot / 8) as usize]
+    } else {
+        Fixed::new(0)
+    }
+}
+
+// 速さ speed のベルトを tick ティック目に描くタイル (tiles::bg.tile_settings の添字) と左右反転。
+// ベルトが無ければ None 。
+pub fn tile(speed: Fixed, tick: u32) -> Option<(usize, bool)> {
+    if speed == Fixed::new(0) {
+        return None;
+    }
+    /* 流した距離 (ドット)。長く遊んで溢れたら回り込む (絵が 1 度飛ぶだけ) */
+    let travelled = tick.wrapping_mul(speed.abs().to_raw() as u32) >> 8;
+    let frame = (travelled / FRAME_SHIFT) as usize % CONVEYOR_TILES.len();
+    Some((CONVEYOR_TILES[frame], speed < Fixed::new(0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+
+    use agb::input::Button;
+
+    use super::*;
+    use crate::{
+        difficulty::Difficulty,
+        game::{Game, GROUND_Y},
+        input::Input,
+    };
+
+    /* 何も置いていない平らな列 (12..16) に置くベルト。
+    テストで 3 つ並べるので、スタックが溢れないようヒープに置く (ghost のテストと同じ) */
+    #[inline(never)]
+    fn game_with_belt(speed: Fixed) -> Box<Game> {
+        let mut game = Box::new(Game::new(Difficulty::Normal, 1));
+        game.conveyors = [Fixed::new(0); GROUND_COLUMNS];
+        game.conveyors[12..16].fill(speed);
+        game.droid.x = 100;
+        game
+    }
+
+    // 立っていれば速さの分だけ流され、跳んでいる間は流されない。逆らって歩けば遅くなる。
+    #[test_case]
+    fn standing_droids_drift_with_the_belt(_gba: &mut agb::Gba) {
+        let mut game = game_with_belt(Fixed::from_raw(-128));
+        for _ in 0..16 {
+            game.update(&Input::default());
+        }
+        assert_eq!(game.droid.x, 92);
+        assert_eq!(game.droid.vx, Fixed::new(0));
+
+        game.update(&Input::new(Button::UP, Button::empty()));
+        while game.droid.y == GROUND_Y {
+            game.update(&Input::default());
+        }
+        let x = game.droid.x;
+        while game.droid.y != GROUND_Y {
+            game.update(&Input::default());
+            assert_eq!(game.droid.x, x);
+        }
+
+        let mut walking = game_with_belt(Fixed::new(0));
+        let mut against = game_with_belt(Fixed::from_raw(-128));
+        for _ in 0..8 {
+            walking.update(&Input::new(Button::RIGHT, Button::RIGHT));
+            against.update(&Input::new(Button::RIGHT, Button::RIGHT));
+        }
+        assert_eq!(against.droid.vx, walking.droid.vx);
+        assert!(against.droid.x < walking.droid.x);
+    }
+
+    // 絵は流した 2 ドットごとに次のコマへ進み、左へ流れるベルトは反転する。
+    #[test_case]
+    fn belt_tiles_follow_the_distance_travelled(_gba: &mut agb::Gba) {
+        let half = Fixed::from_raw(128);
+        let frames: alloc::vec::Vec<_> = (0..10).map(|tick| tile(half, tick)).collect();
+        let expected =
+            [0, 0, 0, 0, 1, 1, 1, 1, 2, 2].map(|frame| Some((CONVEYOR_TILES[frame], false)));
+        assert_eq!(frames, expected);
+        assert_eq!(tile(Fixed::new(-2), 3), Some((CONVEYOR_TILES[3], true)));
+        assert_eq!(tile(Fixed::new(-2), 4), Some((CONVEYOR_TILES[0], true)));
+        assert_eq!(tile(Fixed::new(0), 5), None);
+    }
+}
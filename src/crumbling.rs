@@ -0,0 +1,233 @@
+// 崩れる足場。
+//
+// レベルの足場の層 (Level::platforms) から置く、動かない足場。ドロイド君が乗ると CRUMBLE_SHAKE_TICKS の間
+// 揺れてから落ちていき、CRUMBLE_RESPAWN_TICKS 経つと元の位置に戻る。
+// 乗れるのは揺れ終わるまで (is_solid) で、落ち始めたら乗っているドロイド君も一緒に落ちる。
+// 下からは通り抜けられる (Passage::OneWay)。
+//
+// 乗ったかどうかはドロイド君を動かす前に見て、足場を進める (Game::update_crumbling_platforms)。
+// 落ちていく絵の位置 (shown_at) は表示のためだけのもので、当たり判定には使わない。
+
+use alloc::vec::Vec;
+
+use crate::{
+    collision::{self, Aabb, Surface},
+    entity::{Entity, Look},
+    game::Game,
+    physics::Gravity,
+    snapshot::{Encode, Reader},
+    time::Task,
+};
+
+/* 同時に置ける崩れる足場の数 */
+pub const MAX_CRUMBLING_PLATFORMS: usize = 4;
+/* 崩れる足場の幅 (スプライト 2 枚分) */
+pub const CRUMBLING_PLATFORM_WIDTH: i32 = 32;
+/* 崩れる足場の厚み */
+const CRUMBLING_PLATFORM_THICKNESS: i32 = 8;
+/* 乗ってから落ち始めるまで */
+const CRUMBLE_SHAKE_TICKS: u16 = 30;
+/* 落ちていく絵を出しておく間 (画面の下まで落ちきる) */
+const CRUMBLE_FALL_TICKS: u16 = 40;
+/* 落ち始めてから元に戻るまで */
+const CRUMBLE_RESPAWN_TICKS: u16 = 180;
+
+// 乗られてからの段階。崩れていない間は Task が止まっている。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Phase {
+    Shaking,
+    Falling,
+    Gone,
+}
+
+/* 乗られてから元に戻るまで */
+const CRUMBLE: [(Phase, u16); 3] = [
+    (Phase::Shaking, CRUMBLE_SHAKE_TICKS),
+    (Phase::Falling, CRUMBLE_FALL_TICKS),
+    (Phase::Gone, CRUMBLE_RESPAWN_TICKS - CRUMBLE_FALL_TICKS),
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CrumblingPlatform {
+    /* 左上 (崩れる前の位置) */
+    pub x: i32,
+    pub y: i32,
+    /* 乗られてからの段階 (崩れていなければ止まっている) */
+    crumble: Task<Phase>,
+    /* 次に進めるとき (Entity::update) にドロイド君が乗っているか */
+    stood_on: bool,
+    /* 前のティックに絵を出していた位置 (shown_at) */
+    previous: Option<(i32, i32)>,
+}
+
+/* 段階の並びは CRUMBLE と決まっているので、進み具合 (と前のティックの絵の位置) だけ書く */
+impl Encode for CrumblingPlatform {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        self.x.encode(bytes);
+        self.y.encode(bytes);
+        self.crumble.encode_state(bytes);
+        self.stood_on.encode(bytes);
+        self.previous.encode(bytes);
+    }
+
+    fn decode(reader: &mut Reader) -> Option<Self> {
+        let mut platform = Self::new(i32::decode(reader)?, i32::decode(reader)?);
+        platform.crumble.decode_state(reader)?;
+        platform.stood_on = bool::decode(reader)?;
+        platform.previous = Option::decode(reader)?;
+        Some(platform)
+    }
+}
+
+impl CrumblingPlatform {
+    pub fn new(x: i32, y: i32) -> Self {
+        Self {
+            x,
+            y,
+            crumble: Task::new(&CRUMBLE),
+            stood_on: false,
+            previous: Some((x, y)),
+        }
+    }
+
+    // まだ乗れるか (揺れ終わるまで)。
+    pub fn is_solid(&self) -> bool {
+        matches!(self.crumble.step(), None | Some(Phase::Shaking))
+    }
+
+    // 1 ティック進める。stood_on は崩れる前の足場にドロイド君が乗っているか。
+    pub fn advance(&mut self, stood_on: bool) {
+        if self.crumble.is_running() {
+            self.crumble.tick();
+        } else if stood_on {
+            self.crumble.start();
+        }
+    }
+
+    // 絵を出す位置 (左上)。揺れている間は左右に 1 ドットずつずれ、落ちていく間は加速しながら下がる。
+    // 落ちきったら None 。
+    pub fn shown_at(&self) -> Option<(i32, i32)> {
+        let remaining = i32::from(self.crumble.remaining());
+        match self.crumble.step() {
+            None => Some((self.x, self.y)),
+            Some(Phase::Shaking) => Some((self.x + remaining / 2 % 2 * 2 - 1, self.y)),
+            Some(Phase::Falling) => {
+                let fallen = i32::from(CRUMBLE_FALL_TICKS) - remaining;
+                Some((self.x, self.y + fallen * fallen / 8))
+            }
+            Some(Phase::Gone) => None,
+        }
+    }
+}
+
+impl Surface for CrumblingPlatform {
+    fn bounds(&self) -> Aabb {
+        Aabb::new(
+            self.x,
+            self.y,
+            CRUMBLING_PLATFORM_WIDTH,
+            CRUMBLING_PLATFORM_THICKNESS,
+        )
+    }
+}
+
+impl Entity for CrumblingPlatform {
+    fn update(&mut self) {
+        self.previous = self.shown_at();
+        let stood_on = core::mem::take(&mut self.stood_on);
+        self.advance(stood_on);
+    }
+
+    fn draw(&self) -> Option<Look> {
+        self.shown_at().map(|(x, y)| Look::at(x, y))
+    }
+
+    fn drawn_before(&self) -> Option<Look> {
+        self.previous.map(|(x, y)| Look::at(x, y))
+    }
+}
+
+impl Game {
+    // 崩れる足場を 1 ティック分進める (Entity::update)。乗られた足場は揺れ始める。ドロイド君を動かす前に
+    // (Game::update_entities から) 呼ぶ。
+    pub fn update_crumbling_platforms(&mut self) {
+        let droid = &self.droid;
+        let standing = droid.is_grounded() && droid.gravity == Gravity::Down;
+        for platform in self.crumbling_platforms.values_mut() {
+            let stood_on = standing
+                && platform.is_solid()
+                && droid.y == platform.top_y()
+                && collision::supports(platform, droid.x);
+            platform.stood_on = stood_on;
+            platform.update();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        arena::Arena,
+        difficulty::Difficulty,
+        game::{DROID_WIDTH, GROUND_Y},
+        input::Input,
+        player::PlayerState,
+        spawn::EntityKind,
+    };
+
+    // 乗ると揺れてから落ち、しばらくすると元に戻る。乗らなければ崩れない。
+    #[test_case]
+    fn platforms_shake_fall_and_come_back(_gba: &mut agb::Gba) {
+        let mut platform = CrumblingPlatform::new(40, 80);
+        for _ in 0..100 {
+            platform.advance(false);
+        }
+        assert_eq!(platform.crumble.step(), None);
+        platform.advance(true);
+        let mut shown = alloc::vec::Vec::new();
+        for _ in 0..CRUMBLE_RESPAWN_TICKS + CRUMBLE_SHAKE_TICKS {
+            assert_eq!(
+                platform.is_solid(),
+                shown.len() < CRUMBLE_SHAKE_TICKS.into()
+            );
+            shown.push(platform.shown_at());
+            platform.advance(false);
+        }
+        assert_eq!(platform.crumble.step(), None);
+        assert_eq!(platform.shown_at(), Some((40, 80)));
+        /* 揺れている間は左右にずれ、落ちる間は下がり続け、その後は消えている */
+        let (shaking, rest) = shown.split_at(CRUMBLE_SHAKE_TICKS.into());
+        let (falling, gone) = rest.split_at(CRUMBLE_FALL_TICKS.into());
+        assert!(shaking
+            .iter()
+            .all(|&at| at == Some((39, 80)) || at == Some((41, 80))));
+        assert!(falling
+            .windows(2)
+            .all(|pair| matches!(pair, [Some((40, a)), Some((40, b))] if a <= b)));
+        assert!(falling.last().unwrap().unwrap().1 > agb::display::HEIGHT);
+        assert!(gone.iter().all(Option::is_none));
+    }
+
+    // 乗ったドロイド君は揺れ終わると一緒に落ち、地面に着く。
+    #[test_case]
+    fn the_droid_falls_with_the_platform(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        game.crumbling_platforms = Arena::new();
+        game.spawn(EntityKind::CrumblingPlatform)
+            .at((128, 80))
+            .build()
+            .unwrap();
+        game.droid.x = 128;
+        game.droid.y = 80 - DROID_WIDTH;
+        for _ in 0..CRUMBLE_SHAKE_TICKS {
+            game.update(&Input::default());
+            assert_eq!(game.droid.y, 80 - DROID_WIDTH);
+        }
+        for _ in 0..60 {
+            game.update(&Input::default());
+        }
+        assert_eq!(game.droid.state, PlayerState::Idle);
+        assert_eq!(game.droid.y, GROUND_Y);
+    }
+}
@@ -0,0 +1,193 @@
+// カットシーン (決まった動きを見せる短い場面)。
+//
+// カットシーンは手順 (Step) を並べた定数のデータで、番号 (CUTSCENES の添字) で呼ぶ。
+// ゾーン (ZoneAction::Cutscene) や場面が Game::cutscene に番号を残すと、GameScene がそれを取り出して流す。
+// 流している間は、プレイヤーの入力の代わりに Cutscene が押したことにした入力で Game を進めるので、
+// ドロイド君は普段と同じ物理と絵 (歩き、しゃがみ...) で動く。入力が変わるだけなので、記録したリプレイでもそのまま再現する。
+// A を押すと飛ばせる。
+//
+// 普通のモードでは、始める前に INTRO (ドロイド君が歩いてきて、りんごを見つける) を流す。
+
+use agb::input::Button;
+
+use crate::{
+    game::{wrap_x, Game, APPLE_SIZE, DROID_WIDTH},
+    input::Input,
+    time::Timer,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Step {
+    // ドロイド君を dx ドット横へずらして置く。
+    Shift(i32),
+    // ドロイド君を dx ドット先まで歩かせる。壁で止まると着かないので、平らな地面で使うこと。
+    Walk(i32),
+    // ticks ティック、何も押さずに待つ。
+    Wait(u16),
+    // ticks ティック buttons を押し続ける。ドロイド君の動き (DOWN でしゃがむ...) がそのまま絵になる。
+    Hold { buttons: Button, ticks: u16 },
+    // ドロイド君をりんごの方へ向ける。
+    FaceApple,
+    // 案内の行 (Game::hint) に出す。None で消す。
+    Say(Option<&'static str>),
+}
+
+/* 導入でドロイド君が歩いてくる距離 */
+const WALK_IN: i32 = 24;
+
+/* 導入。出発点の手前から歩いてきて、りんごを見つけてしゃがんで覗き込む */
+const INTRO_STEPS: [Step; 8] = [
+    Step::Shift(-WALK_IN),
+    Step::Walk(WALK_IN),
+    Step::Wait(20),
+    Step::FaceApple,
+    Step::Say(Some("WHAT A BIG APPLE!")),
+    Step::Hold {
+        buttons: Button::DOWN,
+        ticks: 40,
+    },
+    Step::Wait(40),
+    Step::Say(None),
+];
+
+/* カットシーンの番号 */
+pub const INTRO: u8 = 0;
+
+const CUTSCENES: [&[Step]; 1] = [&INTRO_STEPS];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cutscene {
+    steps: &'static [Step],
+    /* 今の手順 */
+    next: usize,
+    /* 歩いて着く x (Walk の間) */
+    target: Option<i32>,
+    /* 今の手順の残り (Wait と Hold の間) */
+    timer: Option<Timer>,
+    /* 前のティックに押したことにしたボタン */
+    held: Button,
+}
+
+impl Cutscene {
+    // number 番のカットシーン。無い番号なら None 。
+    pub fn new(number: u8) -> Option<Self> {
+        let steps = *CUTSCENES.get(usize::from(number))?;
+        Some(Self {
+            steps,
+            next: 0,
+            target: None,
+            timer: None,
+            held: Button::empty(),
+        })
+    }
+
+    // 1 ティック分進め、このティックに game へ渡す入力を返す。
+    // 終わったか、input で A を押して飛ばしたら None 。
+    // ミラーモードでは左右を反転して動かす。
+    pub fn update(&mut self, game: &mut Game, input: &Input) -> Option<Input> {
+        if input.is_just_pressed(Button::A) {
+            log_info!("cutscene skipped");
+            self.next = self.steps.len();
+            game.hint = None;
+            return None;
+        }
+        let sign = if game.modifiers.mirror { -1 } else { 1 };
+        let buttons = loop {
+            let Some(&step) = self.steps.get(self.next) else {
+                log_info!("cutscene finished");
+                return None;
+            };
+            let droid = &mut game.droid;
+            match step {
+                Step::Shift(dx) => droid.x = wrap_x(droid.x + dx * sign),
+                Step::Walk(dx) => {
+                    let dx = dx * sign;
+                    let target = *self.target.get_or_insert(droid.x + dx);
+                    if (target - droid.x) * dx > 0 {
+                        break if dx < 0 { Button::LEFT } else { Button::RIGHT };
+                    }
+                    self.target = None;
+                }
+                Step::Wait(ticks) | Step::Hold { ticks, .. } => {
+                    let timer = self.timer.get_or_insert(Timer::started(ticks));
+                    if timer.is_running() {
+                        timer.tick();
+                        break match step {
+                            Step::Hold { buttons, .. } => buttons,
+                            _ => Button::empty(),
+                        };
+                    }
+                    self.timer = None;
+                }
+                Step::FaceApple => {
+                    droid.hflip = game.apple.x + APPLE_SIZE / 2 < droid.x + DROID_WIDTH / 2;
+                }
+                Step::Say(line) => game.hint = line,
+            }
+            self.next += 1;
+        };
+        let input = Input::new(buttons, self.held);
+        self.held = buttons;
+        Some(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{difficulty::Difficulty, modifiers::Modifiers, player::PlayerState};
+
+    // 流し終えるまで Game を進め、ドロイド君の x としゃがんだか、出た案内を返す。
+    // プレイヤーはずっと LEFT を押しているが効かない。
+    fn play(game: &mut Game) -> (i32, bool, Option<&'static str>) {
+        let mut cutscene = Cutscene::new(INTRO).unwrap();
+        let held = Input::new(Button::LEFT, Button::LEFT);
+        let (mut crouched, mut said) = (false, None);
+        for _ in 0..600 {
+            let Some(input) = cutscene.update(game, &held) else {
+                return (game.droid.x, crouched, said);
+            };
+            game.update(&input);
+            crouched |= game.droid.state == PlayerState::Crouch;
+            said = said.or(game.hint);
+        }
+        panic!("the intro did not finish");
+    }
+
+    // 導入では手前から出発点まで歩いてきて、りんごを向いてしゃがみ、案内を出して消す。
+    #[test_case]
+    fn the_intro_walks_in_and_looks_at_the_apple(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        let start = game.droid.x;
+        let (x, crouched, said) = play(&mut game);
+        assert!((start..start + 8).contains(&x), "x = {}", x);
+        assert!(!game.droid.hflip);
+        assert!(crouched);
+        assert_eq!(said, Some("WHAT A BIG APPLE!"));
+        assert_eq!(game.hint, None);
+
+        /* ミラーモードでは反対から歩いてきて、左のりんごを向く */
+        let mirror = Modifiers {
+            mirror: true,
+            ..Modifiers::NONE
+        };
+        let mut game = Game::new(Difficulty::Normal, 1).with_modifiers(mirror);
+        let start = game.droid.x;
+        let (x, _, _) = play(&mut game);
+        assert!((start - 8..=start).contains(&x), "x = {}", x);
+        assert!(game.droid.hflip);
+    }
+
+    // A で飛ばすと、出ていた案内も消える。無い番号のカットシーンは作れない。
+    #[test_case]
+    fn a_skips_the_cutscene(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        let mut cutscene = Cutscene::new(INTRO).unwrap();
+        game.hint = Some("HINT");
+        assert!(cutscene.update(&mut game, &Input::default()).is_some());
+        let a = Input::new(Button::A, Button::empty());
+        assert_eq!(cutscene.update(&mut game, &a), None);
+        assert_eq!(game.hint, None);
+        assert_eq!(Cutscene::new(CUTSCENES.len() as u8), None);
+    }
+}
@@ -0,0 +1,133 @@
+// 壊れたブロックの破片。
+//
+// 水しぶき (water モジュール) も、飛び出す速度を変えて同じ粒で出す。
+// 飛び出した向きに動きながら重力で落ち、DEBRIS_TICKS で消える。当たり判定は無く、地面も通り抜ける。
+// 見た目だけのものだが Game の中で (他のエンティティと一緒に Game::update_entities で) 動かすので、
+// リプレイでも同じように飛ぶ。出たティックの次のティックから動く。
+// 同時に出せるのは MAX_DEBRIS 個までで、溢れた分は出さない。
+// 位置は破片の絵 (16x16 のスプライトの真ん中に小さな欠片) の左上。
+// 動きと寿命は components の部品 (Position, Velocity, Lifetime) でできている。
+
+use crate::{
+    components::{self, Lifetime, Position, Velocity},
+    entity::{Entity, Look},
+    game::Game,
+    math::Fixed,
+    snapshot::encode_fields,
+};
+
+/* 同時に出せる破片の数 (ブロック 2 つ分) */
+pub const MAX_DEBRIS: usize = 8;
+/* 破片の絵の大きさ */
+pub const DEBRIS_SIZE: i32 = 16;
+/* 飛び出してから消えるまで */
+const DEBRIS_TICKS: u16 = 40;
+/* 1 ティックに下向きの速度へ足す量 (1/256 ドット) */
+const DEBRIS_GRAVITY: i32 = 48;
+/* ブロック 1 つから飛ぶ破片の、飛び出す速度 (1/256 ドット、右と下が正)。上の 2 つは高く、下の 2 つは低く飛ぶ */
+const SCATTER: [(i32, i32); 4] = [(-256, -768), (256, -768), (-128, -384), (128, -384)];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Debris {
+    position: Position,
+    /* 前のティックの絵の左上。描くときに今の位置との間を補う */
+    previous: (i32, i32),
+    velocity: Velocity,
+    life: Lifetime,
+}
+
+encode_fields!(Debris {
+    position,
+    previous,
+    velocity,
+    life
+});
+
+impl Debris {
+    fn new((x, y): (i32, i32), velocity: (i32, i32)) -> Self {
+        Self {
+            position: Position::new(x, y),
+            previous: (x, y),
+            velocity: Velocity::from_raw(velocity),
+            life: Lifetime::new(DEBRIS_TICKS),
+        }
+    }
+
+    // 絵の左上。
+    pub fn position(&self) -> (i32, i32) {
+        self.position.pixel()
+    }
+
+    // 1 ティック進める。
+    fn advance(&mut self) {
+        self.previous = self.position();
+        components::gravity(&mut self.velocity, Fixed::from_raw(DEBRIS_GRAVITY));
+        components::movement(&mut self.position, &self.velocity);
+        self.life.tick();
+    }
+}
+
+impl Entity for Debris {
+    fn update(&mut self) {
+        self.advance();
+    }
+
+    // 消えたら None で、Game::update_entities が片付ける。
+    fn draw(&self) -> Option<Look> {
+        let (x, y) = self.position();
+        self.life.is_alive().then_some(Look::at(x, y))
+    }
+
+    fn drawn_before(&self) -> Option<Look> {
+        Some(Look::at(self.previous.0, self.previous.1))
+    }
+}
+
+impl Game {
+    // 真ん中が (x, y) の所から破片を散らす。
+    pub fn scatter_debris(&mut self, centre: (i32, i32)) {
+        self.scatter(centre, &SCATTER);
+    }
+
+    // 真ん中が (x, y) の所から、velocities の速度で 1 つずつ粒を飛ばす。
+    pub fn scatter(&mut self, (x, y): (i32, i32), velocities: &[(i32, i32)]) {
+        let at = (x - DEBRIS_SIZE / 2, y - DEBRIS_SIZE / 2);
+        for &velocity in velocities {
+            if self.debris.spawn(Debris::new(at, velocity)).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::Difficulty;
+
+    // 破片は左右に分かれて上へ飛び出し、落ちてきて DEBRIS_TICKS で消える。溢れた分は出さない。
+    #[test_case]
+    fn debris_flies_apart_and_fades(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        game.scatter_debris((100, 80));
+        game.scatter_debris((100, 80));
+        game.scatter_debris((100, 80));
+        assert_eq!(game.debris.len(), MAX_DEBRIS);
+        for _ in 0..8 {
+            game.update_entities();
+        }
+        let positions: alloc::vec::Vec<_> = game.debris.values().map(Debris::position).collect();
+        assert!(positions.iter().any(|&(x, _)| x < 100 - DEBRIS_SIZE / 2));
+        assert!(positions.iter().any(|&(x, _)| x > 100 - DEBRIS_SIZE / 2));
+        assert!(positions.iter().all(|&(_, y)| y < 80 - DEBRIS_SIZE / 2));
+        for _ in 8..DEBRIS_TICKS - 1 {
+            game.update_entities();
+        }
+        assert!(game
+            .debris
+            .values()
+            .all(|debris| debris.position().1 > 80 - DEBRIS_SIZE / 2));
+        game.update_entities();
+        assert!(game.debris.is_empty());
+    }
+}
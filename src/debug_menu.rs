@@ -0,0 +1,260 @@
+// デバッグメニュー (debug-tools フィーチャー専用)。
+//
+// L+R+START で開き、上下で選んで A で実行、B で閉じる。
+// 背景はもう余っていないので、シーンにはせずデバッグ表示のレイヤーに重ねて描く。
+// 開いている間はシーンのティックを止める。
+//
+// シーンから見えるのは Resources::debug (DebugState) だけ。
+// 切り替え (表示、すり抜け、調整値の変更) はそこに置いたままにし、
+// 1 回だけの命令 (コイン、レベル飛ばし、調整値の変更量) は次の 1 ティックだけ置いておく。
+
+use alloc::vec::Vec;
+
+use agb::{display::tiled::VRamManager, input::Button};
+
+use crate::{
+    arena::EntityId,
+    events::Event,
+    game::{move_x, Game, COIN_SCORE},
+    input::Input,
+    math::Fixed,
+    player::PlayerState,
+    text::TextLayer,
+    tuner::KNOBS,
+};
+
+/* すり抜け中の移動速度 (ドット/ティック) */
+const FLY_SPEED: i32 = 2;
+/* 「コインをもらう」で増える枚数 */
+const GIVEN_COINS: u32 = 10;
+
+#[derive(Default)]
+pub struct DebugState {
+    // 処理時間のオーバーレイを出すか。
+    pub overlay: bool,
+    // ドロイド君が重力も当たり判定も無視して十字キーで飛び回るか。
+    pub noclip: bool,
+    // SELECT+十字キーで調整値を変えられるようにするか (tuner)。
+    pub tuning: bool,
+    // 次のティックでゲーム本編に実行させる命令。
+    pub command: Option<Command>,
+    // 次のティックで tuner::KNOBS のそれぞれを何段変えるか。
+    pub tweaks: [i32; KNOBS.len()],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    // 今のレベルのコインを全て取る。
+    SkipLevel,
+    GiveCoins,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Item {
+    Overlay,
+    Noclip,
+    Tuning,
+    SkipLevel,
+    GiveCoins,
+    Panic,
+}
+
+const ITEMS: [Item; 6] = [
+    Item::Overlay,
+    Item::Noclip,
+    Item::Tuning,
+    Item::SkipLevel,
+    Item::GiveCoins,
+    Item::Panic,
+];
+
+/* メニューを描き始める行 */
+const TOP_ROW: u16 = 4;
+
+pub struct DebugMenu {
+    open: bool,
+    cursor: usize,
+}
+
+impl DebugMenu {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            cursor: 0,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn open(&mut self) {
+        log_info!("debug menu opened");
+        self.open = true;
+    }
+
+    // 開いている間、描画フレームごとに呼ぶ。
+    pub fn update(&mut self, input: &Input, state: &mut DebugState) {
+        if input.is_just_pressed(Button::B) {
+            self.open = false;
+            return;
+        }
+        if input.is_just_pressed(Button::UP) {
+            self.cursor = (self.cursor + ITEMS.len() - 1) % ITEMS.len();
+        }
+        if input.is_just_pressed(Button::DOWN) {
+            self.cursor = (self.cursor + 1) % ITEMS.len();
+        }
+        if !input.is_just_pressed(Button::A) {
+            return;
+        }
+        let item = ITEMS[self.cursor];
+        log_info!("debug menu: {:?}", item);
+        match item {
+            Item::Overlay => state.overlay = !state.overlay,
+            Item::Noclip => state.noclip = !state.noclip,
+            Item::Tuning => state.tuning = !state.tuning,
+            Item::SkipLevel | Item::GiveCoins => {
+                state.command = Some(match item {
+                    Item::SkipLevel => Command::SkipLevel,
+                    _ => Command::GiveCoins,
+                });
+                /* 命令はゲームに戻ってから実行される */
+                self.open = false;
+            }
+            Item::Panic => panic!("deliberate panic from the debug menu"),
+        }
+    }
+
+    pub fn draw(&self, layer: &mut TextLayer, vram: &mut VRamManager, state: &DebugState) {
+        let on_off = |on: bool| if on { "ON" } else { "OFF" };
+        layer.print_line(vram, TOP_ROW, format_args!(" DEBUG MENU   (B: CLOSE)"));
+        for (row, item) in ITEMS.iter().enumerate() {
+            let cursor = if row == self.cursor { '>' } else { ' ' };
+            let row = TOP_ROW + 2 + row as u16;
+            match item {
+                Item::Overlay => layer.print_line(
+                    vram,
+                    row,
+                    format_args!(" {} OVERLAY     {}", cursor, on_off(state.overlay)),
+                ),
+                Item::Noclip => layer.print_line(
+                    vram,
+                    row,
+                    format_args!(" {} NOCLIP      {}", cursor, on_off(state.noclip)),
+                ),
+                Item::Tuning => layer.print_line(
+                    vram,
+                    row,
+                    format_args!(" {} TUNING      {}", cursor, on_off(state.tuning)),
+                ),
+                Item::SkipLevel => {
+                    layer.print_line(vram, row, format_args!(" {} SKIP LEVEL", cursor))
+                }
+                Item::GiveCoins => layer.print_line(
+                    vram,
+                    row,
+                    format_args!(" {} GIVE {} COINS", cursor, GIVEN_COINS),
+                ),
+                Item::Panic => layer.print_line(vram, row, format_args!(" {} PANIC", cursor)),
+            }
+        }
+    }
+}
+
+impl Game {
+    // すり抜け中の移動。十字キーの方向へ FLY_SPEED ずつ動く。
+    // 切ったときにその場から落ちるよう、ずっと落下中の扱いにしておく。
+    pub fn fly(&mut self, input: &Input) {
+        let ceiling = self.ceiling();
+        let droid = &mut self.droid;
+        let dir = droid.walk_dir(input);
+        for _ in 0..FLY_SPEED {
+            droid.x = move_x(droid.x, dir);
+        }
+        if input.is_pressed(Button::UP) {
+            droid.y -= FLY_SPEED;
+        }
+        if input.is_pressed(Button::DOWN) {
+            droid.y += FLY_SPEED;
+        }
+        droid.y = droid
+            .y
+            .clamp(ceiling, self.collision_map.floor_y(droid.x, ceiling));
+        droid.vy = Fixed::new(0);
+        droid.enter(PlayerState::Airborne { double: false });
+    }
+
+    pub fn run_command(&mut self, command: Command) {
+        log_info!("debug command {:?}", command);
+        match command {
+            Command::SkipLevel => {
+                /* 普通に取ったときと同じくイベントを通す */
+                let coins: Vec<EntityId> = self.coins.iter().map(|(id, _)| id).collect();
+                for coin in coins {
+                    self.push_event(Event::CoinCollected { coin });
+                }
+            }
+            Command::GiveCoins => self.score += COIN_SCORE * GIVEN_COINS,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{difficulty::Difficulty, game::GROUND_Y};
+
+    fn press(button: Button) -> Input {
+        Input::new(button, Button::empty())
+    }
+
+    #[test_case]
+    fn menu_items_toggle_and_queue_commands(_gba: &mut agb::Gba) {
+        let mut menu = DebugMenu::new();
+        let mut state = DebugState::default();
+        menu.open();
+        menu.update(&press(Button::A), &mut state);
+        assert!(state.overlay);
+        menu.update(&press(Button::DOWN), &mut state);
+        menu.update(&press(Button::A), &mut state);
+        assert!(state.noclip);
+        menu.update(&press(Button::DOWN), &mut state);
+        menu.update(&press(Button::A), &mut state);
+        assert!(state.tuning);
+        /* 上で末尾へ回り込み、PANIC の 1 つ上 */
+        menu.update(&press(Button::UP), &mut state);
+        menu.update(&press(Button::UP), &mut state);
+        menu.update(&press(Button::UP), &mut state);
+        menu.update(&press(Button::UP), &mut state);
+        menu.update(&press(Button::A), &mut state);
+        assert_eq!(state.command, Some(Command::GiveCoins));
+        assert!(!menu.is_open());
+    }
+
+    #[test_case]
+    fn commands_go_through_the_game(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        game.run_command(Command::SkipLevel);
+        game.update(&Input::default());
+        assert!(game.coins.is_empty());
+        let cleared = game.score;
+        game.run_command(Command::GiveCoins);
+        assert_eq!(game.score, cleared + COIN_SCORE * GIVEN_COINS);
+
+        /* すり抜け中は上へも動け、切るとその場から落ちる */
+        game.noclip = true;
+        for _ in 0..10 {
+            game.update(&press(Button::UP));
+        }
+        assert_eq!(game.droid.y, GROUND_Y - 10 * FLY_SPEED);
+        game.noclip = false;
+        for _ in 0..60 {
+            game.update(&Input::default());
+        }
+        assert_eq!(
+            (game.droid.y, game.droid.state),
+            (GROUND_Y, PlayerState::Idle)
+        );
+    }
+}
@@ -0,0 +1,143 @@
+// デバッグ用の毎フレームの処理 (debug-tools フィーチャー専用)。
+//
+// メインループからはフレームの始めと終わりに 1 度ずつ呼ぶだけにして、ボタン操作、デバッグメニュー、
+// 計測のオーバーレイ、取りこぼしの知らせをここにまとめる。ボタンはティックと関係なく毎フレーム読む。
+//
+// L+R+START: デバッグメニュー (debug_menu) を開く。開いている間はティックを進めない
+// L+SELECT:  スロー再生 (1/4) の切り替え
+// R+SELECT:  計測のオーバーレイの表示切り替え
+// SELECT+十字キー: 調整値を変える (tuner) 。メニューで TUNING を ON にしている間だけ
+
+use agb::{
+    display::{
+        tiled::{Tiled0, VRamManager},
+        Priority,
+    },
+    input::{Button, ButtonController},
+    timer::Timer,
+};
+
+use crate::{
+    debug_menu::DebugMenu,
+    font,
+    input::Input,
+    layer::Layer,
+    pacing::{TickAccumulator, SPEED_NORMAL},
+    profiler,
+    scene::Resources,
+    text::{self, TextLayer},
+    time,
+    tuner::Tuner,
+};
+
+/* 取りこぼしたときに右下の隅を点滅させるフレーム数 */
+const OVERRUN_FLASH_FRAMES: u16 = 32;
+
+pub struct DebugTools<'g> {
+    input: ButtonController,
+    /* メニューと計測を重ねて描く一番手前のレイヤー */
+    overlay: TextLayer<'g>,
+    menu: DebugMenu,
+    tuner: Tuner,
+    /* 取りこぼしを知らせる表示の残り (フレームごとに進める) */
+    overrun_flash: time::Timer,
+}
+
+impl<'g> DebugTools<'g> {
+    // オーバーレイを用意し、timer で計測を始める。
+    pub fn new(gfx: &'g Tiled0<'g>, vram: &mut VRamManager, timer: Timer) -> Self {
+        let mut overlay = TextLayer::new(Layer::persistent(gfx, Priority::P0), vram);
+        overlay.set_visible(true);
+        profiler::start(timer);
+        Self {
+            input: ButtonController::new(),
+            overlay,
+            menu: DebugMenu::new(),
+            tuner: Tuner::new(),
+            overrun_flash: time::Timer::new(),
+        }
+    }
+
+    // フレームの始めに呼ぶ。ボタンを読んでメニューとスロー再生を切り替える。
+    // このフレームにティックを進めてよいか (メニューが閉じていて、調整値を変えている最中でないか) を返す。
+    pub fn begin_frame(
+        &mut self,
+        res: &mut Resources<'g>,
+        accumulator: &mut TickAccumulator,
+    ) -> bool {
+        profiler::begin_frame();
+        let input = &mut self.input;
+        input.update();
+        if self.menu.is_open() {
+            self.menu.update(&Input::sample(input), &mut res.debug);
+            if !self.menu.is_open() {
+                self.overlay.clear(&mut res.vram);
+            }
+        } else if input.is_pressed(Button::L)
+            && input.is_pressed(Button::R)
+            && input.is_just_pressed(Button::START)
+        {
+            self.menu.open();
+        }
+        let tuning = !self.menu.is_open()
+            && res.debug.tuning
+            && self.tuner.update(&Input::sample(input), &mut res.debug);
+        if input.is_pressed(Button::L)
+            && !input.is_pressed(Button::R)
+            && input.is_just_pressed(Button::SELECT)
+        {
+            let speed = if accumulator.speed() == SPEED_NORMAL {
+                SPEED_NORMAL / 4
+            } else {
+                SPEED_NORMAL
+            };
+            accumulator.set_speed(speed);
+            log_info!("logic speed {}/{}", speed, SPEED_NORMAL);
+        }
+        !self.menu.is_open() && !tuning
+    }
+
+    // フレームの終わり (シーンを描いた後) に呼ぶ。メニューか計測を描き、計測を締める。
+    // leaks はシーンが返し忘れた資源の数、overruns はこれまでに取りこぼしたフレームの数。
+    pub fn end_frame(&mut self, res: &mut Resources<'g>, leaks: u32, overruns: u32) {
+        if self.input.is_pressed(Button::R) && self.input.is_just_pressed(Button::SELECT) {
+            res.debug.overlay = !res.debug.overlay;
+            if !res.debug.overlay {
+                self.overlay.clear(&mut res.vram);
+            }
+        }
+        profile_count!("scene leaks", leaks);
+        profile_count!("oam rejected", res.oam.rejected());
+        if self.menu.is_open() {
+            self.menu.draw(&mut self.overlay, &mut res.vram, &res.debug);
+        } else if res.debug.overlay {
+            profiler::draw_overlay(&mut self.overlay, &mut res.vram, overruns);
+        }
+        if !self.menu.is_open() && res.debug.tuning {
+            self.tuner.draw(&mut self.overlay, &mut res.vram);
+        }
+        /* 右下の隅を点滅させて取りこぼしを知らせる */
+        if self.overrun_flash.is_running() {
+            self.overrun_flash.tick();
+            if self.overrun_flash.remaining() % 8 < 4 {
+                self.overlay.put_tile(
+                    &mut res.vram,
+                    text::COLUMNS - 1,
+                    text::ROWS - 1,
+                    font::tile_for(b'!'),
+                );
+            } else {
+                self.overlay
+                    .clear_cell(&mut res.vram, text::COLUMNS - 1, text::ROWS - 1);
+            }
+        }
+        self.overlay.commit(&mut res.vram);
+        profiler::end_frame();
+    }
+
+    // フレームを取りこぼしたときに呼ぶ。そのフレームの計測を書き出し、隅を点滅させ始める。
+    pub fn overran(&mut self) {
+        profiler::log_last_frame();
+        self.overrun_flash.start(OVERRUN_FLASH_FRAMES);
+    }
+}
@@ -0,0 +1,72 @@
+// 難易度。
+//
+// ラン開始時にタイトル画面で選び、Game に持たせる。
+// 難易度で変わる値は定数として散らばらせず、全て TABLE に並べて settings() から読む。
+// 敵やチェックポイントのように難易度で変えたいものが増えたら、ここに項目を足す。
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+pub struct Settings {
+    /* HUD に出す名前 */
+    pub name: &'static str,
+    /* 開始時の体力 */
+    pub lives: u8,
+    /* 受けるダメージの倍率 */
+    pub damage_scale: u8,
+}
+
+/* Difficulty の並び順と揃えること */
+const TABLE: [Settings; 3] = [
+    Settings {
+        name: "EASY",
+        lives: 5,
+        damage_scale: 1,
+    },
+    Settings {
+        name: "NORMAL",
+        lives: 3,
+        damage_scale: 1,
+    },
+    Settings {
+        name: "HARD",
+        lives: 1,
+        damage_scale: 2,
+    },
+];
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    pub fn settings(self) -> &'static Settings {
+        &TABLE[self as usize]
+    }
+
+    // タイトル画面の左右キーで選ぶときの前後。端では止まる。
+    pub fn prev(self) -> Self {
+        Self::ALL[(self as usize).saturating_sub(1)]
+    }
+
+    pub fn next(self) -> Self {
+        Self::ALL[(self as usize + 1).min(Self::ALL.len() - 1)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn the_table_follows_the_enum(_gba: &mut agb::Gba) {
+        assert_eq!(Difficulty::Easy.settings().name, "EASY");
+        assert_eq!(Difficulty::Normal.settings().name, "NORMAL");
+        assert_eq!(Difficulty::Hard.settings().name, "HARD");
+        assert_eq!(Difficulty::Easy.prev(), Difficulty::Easy);
+        assert_eq!(Difficulty::Easy.next(), Difficulty::Normal);
+        assert_eq!(Difficulty::Hard.next(), Difficulty::Hard);
+    }
+}
@@ -0,0 +1,195 @@
+// 鍵と、鍵で開く扉。
+//
+// どちらもレベルの物の層 (Level::objects、コインラッシュでは Level::rush も) から置き、コインと同じく
+// 背景のタイル (gameplay::KEY、gameplay::DOOR_TILES) で描くので、タイルの格子に揃えて置く。
+// 鍵は触れると拾い、持っている数 (Game::keys_held) が増えて HUD に出る。
+// 扉はタイル 1 枚の幅で 2 枚の高さの壁で、置いたときに当たり判定のマップの固いタイルになる (Game::place_door)。
+// 鍵を持って扉に触れる (横から押し当てる) と鍵を 1 つ使って開き、当たり判定ごと消える。
+// 持っている鍵はレベルを読み込み直すまで残るので、コインラッシュで体力が尽きて出発点からやり直しても減らない。
+// 拾ったときに Sfx::Key、開けたときに Sfx::Door を鳴らす。
+
+use crate::{
+    arena::EntityId,
+    collision::{self, Aabb, Collider, Filter, Layers},
+    entity::{Entity, Look},
+    events::{Event, Sfx},
+    game::{Game, DROID_WIDTH},
+    snapshot::encode_fields,
+    tilemap::Cell,
+};
+
+/* 同時に置ける鍵と扉の数 */
+pub const MAX_KEYS: usize = 4;
+pub const MAX_DOORS: usize = 2;
+/* 鍵の大きさ (タイル 1 枚) */
+pub const KEY_SIZE: i32 = 8;
+/* 扉の幅と高さ */
+pub const DOOR_WIDTH: i32 = 8;
+pub const DOOR_HEIGHT: i32 = 16;
+/* 鍵はコインと同じく、ドロイド君にだけ拾われる */
+const KEY_FILTER: Filter = Filter::new(Layers::PICKUP, Layers::PLAYER);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Key {
+    pub x: i32,
+    pub y: i32,
+}
+
+encode_fields!(Key { x, y });
+
+impl Collider for Key {
+    fn hitbox(&self) -> Aabb {
+        Aabb::new(self.x, self.y, KEY_SIZE, KEY_SIZE)
+    }
+
+    fn filter(&self) -> Filter {
+        KEY_FILTER
+    }
+}
+
+impl Entity for Key {
+    fn draw(&self) -> Option<Look> {
+        Some(Look::at(self.x, self.y))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Door {
+    /* 左上 */
+    pub x: i32,
+    pub y: i32,
+}
+
+encode_fields!(Door { x, y });
+
+impl Door {
+    // 当たり判定のマップで扉が塞ぐタイル (列, 行)。
+    fn cells(&self) -> impl Iterator<Item = (usize, usize)> {
+        let (column, row) = ((self.x / 8) as usize, (self.y / 8) as usize);
+        (row..row + (DOOR_HEIGHT / 8) as usize).map(move |row| (column, row))
+    }
+
+    // x, y にいるドロイド君が触れているか。扉は固いので重なりはせず、横に押し当てたときに 1 ドット広げて見る。
+    fn touches(&self, x: i32, y: i32) -> bool {
+        let droid = Aabb::new(x - 1, y, DROID_WIDTH + 2, DROID_WIDTH);
+        droid.overlaps(Aabb::new(self.x, self.y, DOOR_WIDTH, DOOR_HEIGHT))
+    }
+}
+
+impl Game {
+    // 扉を置き、塞ぐタイルを当たり判定のマップで固くする。置き場所が満杯なら置かずに None 。
+    pub fn place_door(&mut self, door: Door) -> Option<EntityId> {
+        let id = self.doors.spawn(door).ok()?;
+        for (column, row) in door.cells() {
+            self.collision_map.set(column, row, Cell::Solid);
+        }
+        Some(id)
+    }
+
+    // 触れた鍵を拾い、鍵を持っていれば触れた扉を開ける。ドロイド君を動かした後に呼ぶ。
+    pub fn update_doors(&mut self) {
+        if self.keys.is_empty() && self.doors.is_empty() {
+            return;
+        }
+        /* 重なった鍵は 1 ティックに 1 つずつ拾う */
+        let key = collision::touching(&self.droid, &self.keys).next();
+        if let Some(key) = key.and_then(|key| self.keys.despawn(key)) {
+            self.keys_held += 1;
+            log_debug!("key at ({}, {}), {} held", key.x, key.y, self.keys_held);
+            self.push_event(Event::PlaySfx(Sfx::Key));
+        }
+        if self.keys_held == 0 {
+            return;
+        }
+        let (x, y) = (self.droid.x, self.droid.y);
+        let door = self
+            .doors
+            .iter()
+            .find(|(_, door)| door.touches(x, y))
+            .map(|(id, _)| id);
+        if let Some(door) = door.and_then(|door| self.doors.despawn(door)) {
+            self.keys_held -= 1;
+            for (column, row) in door.cells() {
+                self.collision_map.set(column, row, Cell::Empty);
+            }
+            log_debug!("door at ({}, {}) opened", door.x, door.y);
+            self.push_event(Event::PlaySfx(Sfx::Door));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use agb::input::Button;
+
+    use super::*;
+    use crate::{
+        arena::Arena, difficulty::Difficulty, game::GROUND_Y, input::Input, level::Level,
+        mode::Mode, spawn::EntityKind, time_attack::COUNTDOWN_TICKS,
+    };
+
+    /* 出発点 (x = 120) の右の扉と、その手前の地面に置いた鍵 */
+    const DOOR: (i32, i32) = (160, GROUND_Y);
+    const KEY: (i32, i32) = (136, GROUND_Y + 8);
+
+    fn game_with_door() -> Game {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        game.doors = Arena::new();
+        game.keys = Arena::new();
+        game.spawn(EntityKind::Door).at(DOOR).build().unwrap();
+        game
+    }
+
+    fn walk_right(game: &mut Game, ticks: usize) {
+        for _ in 0..ticks {
+            game.update(&Input::new(Button::RIGHT, Button::RIGHT));
+        }
+    }
+
+    // 鍵が無ければ扉で止まり、鍵を拾ってから押し当てると開いて通り抜けられる。
+    #[test_case]
+    fn a_key_opens_a_door(_gba: &mut agb::Gba) {
+        let mut game = game_with_door();
+        walk_right(&mut game, 60);
+        assert_eq!(game.droid.x, DOOR.0 - DROID_WIDTH);
+        assert_eq!(game.doors.len(), 1);
+
+        let mut game = game_with_door();
+        game.spawn(EntityKind::Key).at(KEY).build().unwrap();
+        while game.keys_held == 0 {
+            walk_right(&mut game, 1);
+        }
+        assert!(game.keys.is_empty());
+        walk_right(&mut game, 60);
+        assert!(game.doors.is_empty());
+        assert_eq!(game.keys_held, 0);
+        assert!(game.droid.x > DOOR.0);
+    }
+
+    // 持っている鍵は、コインラッシュで体力が尽きて出発点からやり直しても残る。
+    #[test_case]
+    fn keys_survive_a_respawn(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1).with_mode(Mode::CoinRush);
+        for _ in 0..COUNTDOWN_TICKS {
+            game.update(&Input::default());
+        }
+        let (x, y) = game
+            .keys
+            .values()
+            .next()
+            .copied()
+            .map(|key| (key.x, key.y))
+            .unwrap();
+        (game.droid.x, game.droid.y) = (x - 4, y - 4);
+        game.update(&Input::default());
+        assert_eq!(game.keys_held, 1);
+        game.health = 1;
+        game.push_event(Event::PlayerDamaged {
+            amount: 1,
+            from_x: 0,
+        });
+        game.update(&Input::default());
+        assert_eq!((game.droid.x, game.droid.y), Level::load(false).droid);
+        assert_eq!(game.keys_held, 1);
+    }
+}
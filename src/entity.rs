@@ -0,0 +1,167 @@
+// エンティティ (レベルに置いて毎ティック回すもの) の共通の入り口。
+//
+// 足場 (動く足場、崩れる足場)、ばね、スイッチ、風、拾えるもの (コイン、りんご、鍵)、破片は Entity を実装する。
+// Game::update_entities が全ての入れ物を同じ順に回し、それぞれを 1 ティック進める (update)。
+// update は自分の中だけを進め、ドロイド君には触らない。乗せて運ぶ、打ち上げる、拾うといったドロイド君との
+// やりとりは、これまで通り種類ごとの Game の関数が受け持つ。足場だけはドロイド君が乗っているかを進める前に見て、
+// 進めた分だけ運ぶので、種類ごとの関数 (Game::update_moving_platforms, Game::update_crumbling_platforms) が
+// 1 つずつ update を呼ぶ。
+//
+// 描くのは presenter なので、draw は OAM にも VRAM にも触らず、描く位置と絵の番号 (Look) を返すだけにする。
+// presenter は入れ物ごとに枠の順の見た目 (looks) を作り、前のフレームと見比べて変わった枠だけ書き直す。
+// 動くもの (足場、破片) は前のティックの見た目 (drawn_before) も返し、presenter はその間を補って描く (looks_between)。
+// 当たり判定は Entity には持たせない。乗れるものは Surface::bounds 、ぶつかり合うものは Collider::hitbox で見る。
+//
+// 新しい種類のものは Entity を実装し、入れ物を update_entities に足せば、毎ティック動いて同じ形で描ける。
+
+use crate::{arena::Arena, game::Game, pacing};
+
+// 描くときの見た目。左上の位置と、種類ごとに決める絵の番号 (ばねが縮んでいる、スイッチが押されている...)。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Look {
+    pub x: i32,
+    pub y: i32,
+    pub frame: u8,
+}
+
+impl Look {
+    pub const fn at(x: i32, y: i32) -> Self {
+        Self { x, y, frame: 0 }
+    }
+
+    pub const fn frame(self, frame: u8) -> Self {
+        Self { frame, ..self }
+    }
+}
+
+pub trait Entity {
+    // 1 ティック進める。ドロイド君に関わらず進むもの (動く足場、縮んだばね、流れる風の粒...) だけ。
+    fn update(&mut self) {}
+
+    // 描くときの見た目。見えていなければ None 。
+    fn draw(&self) -> Option<Look>;
+
+    // 前のティックの見た目。動かないものは今と同じ。
+    fn drawn_before(&self) -> Option<Look> {
+        self.draw()
+    }
+}
+
+// 入れ物の全てを 1 ティック進める。
+fn update_all<T: Entity, const N: usize>(arena: &mut Arena<T, N>) {
+    for entity in arena.values_mut() {
+        entity.update();
+    }
+}
+
+// 入れ物の枠ごとの見た目。空いている枠と見えていないものは None 。
+pub fn looks<T: Entity, const N: usize>(arena: &Arena<T, N>) -> [Option<Look>; N] {
+    let mut looks = [None; N];
+    for (id, entity) in arena.iter() {
+        looks[id.index()] = entity.draw();
+    }
+    looks
+}
+
+// 入れ物の枠ごとの、前のティックの見た目から今の見た目へ alpha だけ寄せた見た目 (pacing::between)。
+// 絵の番号は今のもの。前のティックに見えていなかったものは今の見た目で描く。
+pub fn looks_between<T: Entity, const N: usize>(
+    arena: &Arena<T, N>,
+    alpha: u32,
+) -> [Option<Look>; N] {
+    let mut looks = [None; N];
+    for (id, entity) in arena.iter() {
+        looks[id.index()] = entity.draw().map(|now| match entity.drawn_before() {
+            Some(before) => {
+                let (x, y) = pacing::between((before.x, before.y), (now.x, now.y), alpha);
+                Look { x, y, ..now }
+            }
+            None => now,
+        });
+    }
+    looks
+}
+
+impl Game {
+    // 全てのエンティティを 1 ティック進める。ドロイド君を動かす前に呼ぶ。
+    pub fn update_entities(&mut self) {
+        /* 足場は乗っているドロイド君を見ながら 1 つずつ進める */
+        self.update_moving_platforms();
+        self.update_crumbling_platforms();
+        update_all(&mut self.springs);
+        update_all(&mut self.switches);
+        update_all(&mut self.winds);
+        update_all(&mut self.coins);
+        update_all(&mut self.apples);
+        update_all(&mut self.keys);
+        if !self.debris.is_empty() {
+            update_all(&mut self.debris);
+            self.debris.retain(|debris| debris.draw().is_some());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        collision::Passage, difficulty::Difficulty, game::Coin, moving_platform::MovingPlatform,
+        pacing::SPEED_NORMAL, spring::Spring,
+    };
+
+    // 見た目は枠の順に並び、空いた枠は None 。
+    #[test_case]
+    fn looks_follow_the_slots(_gba: &mut agb::Gba) {
+        let mut springs: Arena<Spring, 3> = Arena::new();
+        let first = springs.spawn(Spring::new(8, 16)).unwrap();
+        springs.spawn(Spring::new(40, 16)).unwrap();
+        springs.despawn(first);
+        assert_eq!(looks(&springs), [None, Some(Look::at(40, 16)), None]);
+    }
+
+    // 動くものは前のティックの位置から寄せて描き、動かないものは今の位置に描く。
+    #[test_case]
+    fn moving_things_are_drawn_from_the_previous_tick(_gba: &mut agb::Gba) {
+        let mut lifts: Arena<MovingPlatform, 1> = Arena::new();
+        lifts
+            .spawn(MovingPlatform::new((0, 40), (64, 40), Passage::OneWay))
+            .unwrap();
+        /* 1 ティックに半ドットなので、4 ティック目に 1 から 2 へ動く */
+        for _ in 0..4 {
+            update_all(&mut lifts);
+        }
+        assert_eq!(looks(&lifts), [Some(Look::at(2, 40))]);
+        assert_eq!(looks_between(&lifts, 0), [Some(Look::at(1, 40))]);
+        let mut springs: Arena<Spring, 1> = Arena::new();
+        springs.spawn(Spring::new(8, 16)).unwrap();
+        assert_eq!(looks_between(&springs, SPEED_NORMAL / 2), looks(&springs));
+    }
+
+    // 1 ティックに 1 度ずつ、全ての種類が進む。
+    #[test_case]
+    fn every_entity_moves_once_a_tick(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        game.moving_platforms = Arena::new();
+        let lift = game
+            .moving_platforms
+            .spawn(MovingPlatform::new((0, 40), (64, 40), Passage::OneWay))
+            .unwrap();
+        game.scatter_debris((100, 80));
+        let debris: alloc::vec::Vec<_> = game.debris.values().map(Entity::draw).collect();
+        for _ in 0..4 {
+            game.update_entities();
+        }
+        assert_eq!(game.moving_platforms.get(lift).unwrap().x, 2);
+        for (before, after) in debris.iter().zip(game.debris.values()) {
+            assert_ne!(*before, after.draw());
+        }
+    }
+
+    // 見た目はそれぞれの左上に、種類ごとの絵の番号で描く。
+    #[test_case]
+    fn a_look_is_the_top_left_and_the_frame(_gba: &mut agb::Gba) {
+        let coin = Coin { x: 24, y: 32 };
+        assert_eq!(coin.draw(), Some(Look::at(24, 32)));
+        assert_eq!(Spring::new(8, 16).draw(), Some(Look::at(8, 16).frame(0)));
+    }
+}
@@ -0,0 +1,274 @@
+// ティックごとのイベントキュー。
+//
+// エンティティ同士が直接 &mut で触り合うと借用が絡まるので、
+// 更新中に起きたこと (コインを取った、ダメージを受けた...) はイベントとしてここに積み、
+// エンティティの更新が終わった後でまとめて処理する。
+// 処理中に積んだイベント (効果音など) は次のティックで処理される。
+// ランが終わったティック (倒れた、クリアした) だけは次が無いので、Game::update がその場で処理しきる。
+//
+// 毎ティック使うのでヒープは使わず、固定長の配列に積む。
+// スイッチやパーティクルは、それらが入るときにイベントを足す。
+
+use core::mem;
+
+use alloc::vec::Vec;
+
+use crate::{
+    arena::EntityId,
+    snapshot::{encode_variants, Encode, Reader},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sfx {
+    Coin,
+    Hurt,
+    // ヒップドロップで地面を叩いた。
+    Slam,
+    // 跳び上がって頭をぶつけた。
+    Bonk,
+    // ばねに打ち上げられた。
+    Spring,
+    // 壊せるブロックが割れた。
+    Break,
+    // スイッチを踏んだ。
+    Switch,
+    // 鍵を拾った。
+    Key,
+    // 鍵で扉を開けた。
+    Door,
+    // 水に入った、水から出た。
+    Splash,
+    // テレポーターで移った。
+    Warp,
+    // コインラッシュの残り時間が少ない (1 秒ごと)。
+    LowTime,
+    // 地面や足場に降りた。
+    Land,
+    // 体力が尽きた、塔から落ちた。
+    Die,
+}
+
+encode_variants!(Sfx {
+    Coin,
+    Hurt,
+    Slam,
+    Bonk,
+    Spring,
+    Break,
+    Switch,
+    Key,
+    Door,
+    Splash,
+    Warp,
+    LowTime,
+    Land,
+    Die
+});
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    // 取られたコイン。処理するときに取り除く。
+    CoinCollected { coin: EntityId },
+    // from_x はダメージを与えたものの x 座標。ノックバックの向きに使う。
+    PlayerDamaged { amount: u8, from_x: i32 },
+    // 空中から地面や足場に降りた (跳ね返ったときは積まない)。x, y はドロイド君の左上。
+    PlayerLanded { x: i32, y: i32 },
+    // 体力が尽きた、エンドレスで塔から落ちた。このティックでランが終わる。
+    PlayerDied,
+    // ヒップドロップで着地した。x はドロイド君の足元の真ん中、y はドロイド君の y 座標。
+    // 衝撃波を出し、足元の壊せるブロックを壊し、(入ったら) 届いた範囲の敵を跳ねさせる。
+    GroundPound { x: i32, y: i32 },
+    PlaySfx(Sfx),
+    // 壊せるブロックが壊れた (block モジュール)。破片を散らす。
+    BlockBroken { column: usize, row: u16 },
+    // ドロイド君の中心がトリガーゾーンに入った、出た (zone モジュール)。
+    ZoneEntered { zone: EntityId },
+    ZoneExited { zone: EntityId },
+}
+
+/* 種類の番号の後に、その種類のフィールドを並べた順に書く */
+impl Encode for Event {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        match *self {
+            Event::CoinCollected { coin } => {
+                bytes.push(0);
+                coin.encode(bytes);
+            }
+            Event::PlayerDamaged { amount, from_x } => {
+                bytes.push(1);
+                amount.encode(bytes);
+                from_x.encode(bytes);
+            }
+            Event::PlayerLanded { x, y } => {
+                bytes.push(2);
+                (x, y).encode(bytes);
+            }
+            Event::PlayerDied => bytes.push(3),
+            Event::GroundPound { x, y } => {
+                bytes.push(4);
+                (x, y).encode(bytes);
+            }
+            Event::PlaySfx(sfx) => {
+                bytes.push(5);
+                sfx.encode(bytes);
+            }
+            Event::BlockBroken { column, row } => {
+                bytes.push(6);
+                column.encode(bytes);
+                row.encode(bytes);
+            }
+            Event::ZoneEntered { zone } => {
+                bytes.push(7);
+                zone.encode(bytes);
+            }
+            Event::ZoneExited { zone } => {
+                bytes.push(8);
+                zone.encode(bytes);
+            }
+        }
+    }
+
+    fn decode(reader: &mut Reader) -> Option<Self> {
+        Some(match reader.take()? {
+            [0] => Event::CoinCollected {
+                coin: EntityId::decode(reader)?,
+            },
+            [1] => Event::PlayerDamaged {
+                amount: u8::decode(reader)?,
+                from_x: i32::decode(reader)?,
+            },
+            [2] => {
+                let (x, y) = Encode::decode(reader)?;
+                Event::PlayerLanded { x, y }
+            }
+            [3] => Event::PlayerDied,
+            [4] => {
+                let (x, y) = Encode::decode(reader)?;
+                Event::GroundPound { x, y }
+            }
+            [5] => Event::PlaySfx(Sfx::decode(reader)?),
+            [6] => Event::BlockBroken {
+                column: usize::decode(reader)?,
+                row: u16::decode(reader)?,
+            },
+            [7] => Event::ZoneEntered {
+                zone: EntityId::decode(reader)?,
+            },
+            [8] => Event::ZoneExited {
+                zone: EntityId::decode(reader)?,
+            },
+            _ => return None,
+        })
+    }
+}
+
+// 溢れたときの処理。書式化が大きいので、積む側 (IWRAM に置いた処理) へインライン展開させない。
+#[cold]
+#[inline(never)]
+fn overflowed(event: Event) {
+    debug_assert!(false, "event queue overflow: {:?}", event);
+    log_warn!("event queue overflow, dropped {:?}", event);
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EventQueue<const N: usize> {
+    events: [Option<Event>; N],
+    len: usize,
+}
+
+impl<const N: usize> Encode for EventQueue<N> {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        self.events.encode(bytes);
+        self.len.encode(bytes);
+    }
+
+    fn decode(reader: &mut Reader) -> Option<Self> {
+        let events = Encode::decode(reader)?;
+        let len = usize::decode(reader)?;
+        (len <= N).then_some(Self { events, len })
+    }
+}
+
+impl<const N: usize> EventQueue<N> {
+    pub const fn new() -> Self {
+        Self {
+            events: [None; N],
+            len: 0,
+        }
+    }
+
+    // 積む。溢れたイベントは捨てる (デバッグビルドでは止める)。
+    pub fn push(&mut self, event: Event) {
+        if let Err(event) = self.try_push(event) {
+            overflowed(event);
+        }
+    }
+
+    // 積む。満杯なら積まずに返す。
+    pub fn try_push(&mut self, event: Event) -> Result<(), Event> {
+        let Some(slot) = self.events.get_mut(self.len) else {
+            return Err(event);
+        };
+        *slot = Some(event);
+        self.len += 1;
+        Ok(())
+    }
+
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // 今積まれているイベントを積んだ順に取り出す。
+    // 取り出している間に積んだイベントは、次の drain() で出てくる。
+    pub fn drain(&mut self) -> impl Iterator<Item = Event> {
+        mem::replace(self, Self::new()).events.into_iter().flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(amount: u8) -> Event {
+        Event::PlayerDamaged { amount, from_x: 0 }
+    }
+
+    #[test_case]
+    fn events_come_out_in_order_and_follow_ups_wait(_gba: &mut agb::Gba) {
+        let mut queue = EventQueue::<4>::new();
+        queue.push(hit(1));
+        queue.push(hit(2));
+        let mut seen = [None; 4];
+        for (i, event) in queue.drain().enumerate() {
+            seen[i] = Some(event);
+            queue.push(Event::PlaySfx(Sfx::Coin));
+        }
+        assert_eq!(seen, [Some(hit(1)), Some(hit(2)), None, None]);
+        /* 処理中に積んだものは次の回に出てくる */
+        assert_eq!(queue.len(), 2);
+        assert!(queue
+            .drain()
+            .all(|event| event == Event::PlaySfx(Sfx::Coin)));
+        assert!(queue.is_empty());
+    }
+
+    #[test_case]
+    fn a_full_queue_rejects_without_losing_earlier_events(_gba: &mut agb::Gba) {
+        let mut queue = EventQueue::<2>::new();
+        assert_eq!(queue.try_push(hit(1)), Ok(()));
+        assert_eq!(queue.try_push(hit(2)), Ok(()));
+        assert_eq!(queue.try_push(hit(3)), Err(hit(3)));
+        assert_eq!(queue.len(), 2);
+        let mut drained = queue.drain();
+        assert_eq!(drained.next(), Some(hit(1)));
+        assert_eq!(drained.next(), Some(hit(2)));
+        assert_eq!(drained.next(), None);
+        /* 空けばまた積める */
+        assert_eq!(queue.try_push(hit(3)), Ok(()));
+    }
+}
@@ -0,0 +1,127 @@
+// 組み込みの 8x8 フォント。
+//
+// font8x8 のビットマップをビルド時に 4bpp のタイル列へ変換して ROM に置く。
+// グリフはパレットの 1 番、バー用のタイルは 2 番の色で描かれ、それ以外は透過 (0 番)。
+// パニック画面のように VRAM へ直接書き込む場合にも、
+// agb の TileSet 経由で背景に並べる場合にも同じデータを使う。
+
+use agb::display::tiled::{TileFormat, TileSet};
+use font8x8::legacy::BASIC_LEGACY;
+
+/* フォントに含める文字 (ASCII の表示可能文字) */
+pub const FIRST_CHAR: u8 = b' ';
+pub const LAST_CHAR: u8 = b'~';
+const GLYPH_COUNT: usize = (LAST_CHAR - FIRST_CHAR + 1) as usize;
+
+// グリフの後ろに、左から 0..=8 ドット埋まった細い横棒のタイルを並べる。
+pub const BAR_TILE_BASE: u16 = GLYPH_COUNT as u16;
+const BAR_TILE_COUNT: usize = 9;
+/* 横棒を描く行 (タイルの上端寄り) */
+const BAR_ROWS: core::ops::Range<usize> = 1..4;
+
+pub const TILE_COUNT: usize = GLYPH_COUNT + BAR_TILE_COUNT;
+pub const TILE_SIZE: usize = 32;
+
+// agb はタイルを ldmia/stmia でコピーするので 4 バイト境界に揃える必要がある。
+#[repr(align(4))]
+pub struct Tiles(pub [u8; TILE_COUNT * TILE_SIZE]);
+
+pub static TILES: Tiles = Tiles(build_tiles());
+
+pub static TILESET: TileSet<'static> = TileSet::new(&TILES.0, TileFormat::FourBpp);
+
+// 文字に対応するタイル番号。フォントに無い文字は '?' になる。
+pub const fn tile_for(ch: u8) -> u16 {
+    let ch = if FIRST_CHAR <= ch && ch <= LAST_CHAR {
+        ch
+    } else {
+        b'?'
+    };
+    (ch - FIRST_CHAR) as u16
+}
+
+// 左から filled ドット (0..=8) 埋まった横棒のタイル番号。
+pub const fn bar_tile(filled: usize) -> u16 {
+    let filled = if filled > 8 { 8 } else { filled };
+    BAR_TILE_BASE + filled as u16
+}
+
+// 1 行 8 ドットのビットマップ (LSB が左端) を 4bpp のタイル 1 行に変換する。
+pub const fn glyph_row_4bpp(bits: u8, colour: u32) -> u32 {
+    let mut row = 0;
+    let mut x = 0;
+    while x < 8 {
+        if bits & (1 << x) != 0 {
+            row |= colour << (x * 4);
+        }
+        x += 1;
+    }
+    row
+}
+
+// tile 番目のタイルの y 行目のビットマップと色。
+const fn tile_row(tile: usize, y: usize) -> (u8, u32) {
+    if tile < GLYPH_COUNT {
+        (BASIC_LEGACY[FIRST_CHAR as usize + tile][y], 1)
+    } else if BAR_ROWS.start <= y && y < BAR_ROWS.end {
+        let filled = tile - GLYPH_COUNT;
+        (((1u16 << filled) - 1) as u8, 2)
+    } else {
+        (0, 0)
+    }
+}
+
+const fn build_tiles() -> [u8; TILE_COUNT * TILE_SIZE] {
+    let mut out = [0; TILE_COUNT * TILE_SIZE];
+    let mut i = 0;
+    while i < out.len() {
+        let (bits, colour) = tile_row(i / TILE_SIZE, i % TILE_SIZE / 4);
+        let row = glyph_row_4bpp(bits, colour);
+        out[i] = (row >> (i % 4 * 8)) as u8;
+        i += 1;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rom_row(tile: u16, y: usize) -> u32 {
+        let start = tile as usize * TILE_SIZE + y * 4;
+        u32::from_le_bytes(TILES.0[start..start + 4].try_into().unwrap())
+    }
+
+    #[test_case]
+    fn glyph_rows_map_left_pixel_to_low_nibble(_gba: &mut agb::Gba) {
+        assert_eq!(glyph_row_4bpp(0, 1), 0);
+        assert_eq!(glyph_row_4bpp(0b0000_0001, 1), 0x0000_0001);
+        assert_eq!(glyph_row_4bpp(0b1000_0000, 1), 0x1000_0000);
+        assert_eq!(glyph_row_4bpp(0xFF, 2), 0x2222_2222);
+    }
+
+    #[test_case]
+    fn every_printable_character_has_a_tile(_gba: &mut agb::Gba) {
+        assert_eq!(tile_for(b' '), 0);
+        assert_eq!(tile_for(b'~') as usize, GLYPH_COUNT - 1);
+        assert_eq!(tile_for(b'\n'), tile_for(b'?'));
+        /* 空白以外の表示可能文字はどれも何かしらのドットを持っている */
+        for ch in (FIRST_CHAR + 1)..=LAST_CHAR {
+            assert!(
+                (0..8).any(|y| rom_row(tile_for(ch), y) != 0),
+                "{}",
+                ch as char
+            );
+        }
+    }
+
+    #[test_case]
+    fn bar_tiles_fill_from_the_left(_gba: &mut agb::Gba) {
+        assert_eq!(rom_row(bar_tile(0), BAR_ROWS.start), 0);
+        assert_eq!(rom_row(bar_tile(3), BAR_ROWS.start), 0x0000_0222);
+        assert_eq!(rom_row(bar_tile(8), BAR_ROWS.start), 0x2222_2222);
+        assert_eq!(bar_tile(100), bar_tile(8));
+        /* 横棒の外側の行は空 */
+        assert_eq!(rom_row(bar_tile(8), 0), 0);
+    }
+}
@@ -0,0 +1,1712 @@
+// ゲームロジック。
+//
+// ここでは OAM や VRAM には一切触らず、入力から次の状態を計算するだけにする。
+// 描画は presenter モジュールが Game の状態を読んで行うので、
+// フレームを取りこぼしたときに描画抜きでティックだけ追加で回すことができる。
+
+use alloc::vec::Vec;
+
+use agb::display::WIDTH;
+
+use crate::{
+    arena::Arena,
+    climb::Climb,
+    coin_rush::CoinRush,
+    collision::{self, Aabb, Collider, Filter, Layers, Passage, Surface, Surfaces, Walls},
+    crumbling::{CrumblingPlatform, MAX_CRUMBLING_PLATFORMS},
+    debris::{Debris, MAX_DEBRIS},
+    difficulty::Difficulty,
+    door::{Door, Key, MAX_DOORS, MAX_KEYS},
+    entity::{Entity, Look},
+    events::{Event, EventQueue, Sfx},
+    gameplay::{GROUND_BOUNCY, GROUND_ICE},
+    input::Input,
+    level::{self, Level, GROUND_COLUMNS},
+    math::Fixed,
+    mode::Mode,
+    modifiers::Modifiers,
+    moving_platform::{MovingPlatform, MAX_MOVING_PLATFORMS},
+    physics::{Gravity, Medium, Tuning},
+    player::{Droid, PlayerState, Surroundings},
+    practice::Practice,
+    resources,
+    rng::Rng,
+    script::Script,
+    shockwave::Shockwave,
+    snapshot::{encode_fields, Encode, Reader},
+    spawn::EntityKind,
+    speedrun::Speedrun,
+    spike::{Spike, MAX_SPIKES},
+    spring::{Spring, MAX_SPRINGS},
+    switch::{BlockSet, Switch, MAX_SWITCHES},
+    teleporter::{Teleporter, MAX_TELEPORTERS},
+    terrain::{Material, Terrain},
+    tilemap::CollisionMap,
+    time::Timer,
+    time_attack::{Par, COUNTDOWN_TICKS, PENALTY_TICKS},
+    water::{Water, MAX_WATERS},
+    wind::{Wind, MAX_WINDS},
+    zone::{Zone, ZoneAction, MAX_ZONES},
+};
+
+/* ドロイド君のスプライト幅 */
+pub const DROID_WIDTH: i32 = 16;
+/* 地面に立っているときの y 座標 */
+pub const GROUND_Y: i32 = 120;
+/* コインは 8x8 */
+pub const COIN_SIZE: i32 = 8;
+/* ジャンプ準備にかかるティック数 */
+pub const JUMP_SQUAT_TICKS: u16 = 4;
+/* コイン 1 枚の点数 */
+pub const COIN_SCORE: u32 = 10;
+/* 1 ティックに起きるイベントの上限 */
+const EVENT_CAPACITY: usize = 16;
+/* 同時に置けるコインの数 */
+pub const MAX_COINS: usize = 8;
+/* レベルの (Level::apple の他に) 置けるりんごの数 */
+pub const MAX_APPLES: usize = 4;
+/*
+ * レベルに置くもの。コインは背景のタイルに合わせて 8 の倍数の位置にしておく。
+ * りんごは地面のりんごから跳び移れる高さに浮かべる。壁 (LEVEL_WALLS) の向こうは水で、壁の手前で案内を出す。
+ * 左のエレベーターの上では、上の横に動く足場の方へ風が吹いている。
+ * 案内の右の地面と右の高い地面にテレポーターの台を 1 対置く
+ */
+pub const LEVEL_OBJECTS: [(EntityKind, (i32, i32)); 9] = [
+    (EntityKind::Coin, (64, 96)),
+    (EntityKind::Coin, (160, 72)),
+    (EntityKind::Coin, (208, 104)),
+    (EntityKind::Apple, (200, 72)),
+    (EntityKind::Water { size: (32, 24) }, (0, 112)),
+    (
+        EntityKind::Wind {
+            size: (56, 40),
+            force: (48, 0),
+        },
+        (0, 16),
+    ),
+    (
+        EntityKind::Zone {
+            size: (40, 32),
+            action: ZoneAction::Hint("HOP OVER THE WALL"),
+        },
+        (40, 104),
+    ),
+    (EntityKind::Teleporter { pair: 0 }, (80, GROUND_Y + 8)),
+    (EntityKind::Teleporter { pair: 0 }, (192, GROUND_Y)),
+];
+/*
+ * コインラッシュでだけ足すコインと、鍵と扉 (レベルのコインラッシュ層)。コインはレベルのコインと合わせて MAX_COINS 以内。
+ * 扉は出発点の左の地面を塞いでいて、右端のばねの手前の鍵で開く。左の低い壁の上には棘があり、跳び越すときは高く跳ぶ
+ */
+pub const RUSH_OBJECTS: [(EntityKind, (i32, i32)); 8] = [
+    (EntityKind::Coin, (24, 104)),
+    (EntityKind::Coin, (96, 80)),
+    (EntityKind::Coin, (120, 104)),
+    (EntityKind::Coin, (184, 64)),
+    (EntityKind::Coin, (224, 88)),
+    (EntityKind::Key, (216, GROUND_Y - 8)),
+    (EntityKind::Door, (96, GROUND_Y)),
+    (EntityKind::Spike, (32, GROUND_Y - 8)),
+];
+/*
+ * レベルの動く足場、崩れる足場とばね、スイッチ。左のエレベーターから、上の横に動くものへ乗り継げる。
+ * 上の足場は下から通り抜けられず、真下で跳ぶと頭をぶつける。崩れる足場は右の浮いたりんごから跳び乗る。
+ * 右端のばねは崩れる足場への近道。出発点の右のスイッチは LEVEL_SWITCH_BLOCKS を切り替える
+ */
+pub const LEVEL_PLATFORMS: [(EntityKind, (i32, i32)); 5] = [
+    (EntityKind::Elevator { to_y: 64, wait: 60 }, (16, 112)),
+    (
+        EntityKind::MovingPlatform {
+            to: (112, 48),
+            passage: Passage::Solid,
+        },
+        (56, 48),
+    ),
+    (EntityKind::CrumblingPlatform, (200, 40)),
+    (EntityKind::Spring, (224, GROUND_Y)),
+    (EntityKind::Switch, (136, GROUND_Y)),
+];
+/*
+ * レベルの地面の表面のタイル。氷と跳ねる地面が左右対称に張ってある (ミラーモードでも同じ地面で済む)。
+ * 材質のあるタイルは、丘 (LEVEL_TERRAIN) とミラーモードで反転した丘のどちらにも掛からない平らな列に置く
+ */
+pub const LEVEL_GROUND: [usize; GROUND_COLUMNS] = {
+    let mut ground = level::ground();
+    let mut column = 1;
+    while column < 3 {
+        ground[column] = GROUND_BOUNCY;
+        ground[GROUND_COLUMNS - 1 - column] = GROUND_BOUNCY;
+        column += 1;
+    }
+    column = 9;
+    while column < 12 {
+        ground[column] = GROUND_ICE;
+        ground[GROUND_COLUMNS - 1 - column] = GROUND_ICE;
+        column += 1;
+    }
+    ground
+};
+/* レベルの地面の形。右寄りに緩い坂で上って急な坂で下りる丘がある */
+pub const LEVEL_TERRAIN: [Terrain; GROUND_COLUMNS] = {
+    let mut terrain = [Terrain::Flat; GROUND_COLUMNS];
+    terrain[22] = Terrain::Up22Low;
+    terrain[23] = Terrain::Up22High;
+    terrain[24] = Terrain::Raised;
+    terrain[25] = Terrain::Raised;
+    terrain[26] = Terrain::Down45;
+    terrain
+};
+/* レベルのベルトコンベア (列ごとの流す速さ)。壁の右で右へ流れていて、壁へ向かうときは押し戻される */
+pub const LEVEL_CONVEYORS: [Fixed; GROUND_COLUMNS] = {
+    let mut conveyors = [Fixed::from_raw(0); GROUND_COLUMNS];
+    let mut column = 5;
+    while column < 9 {
+        conveyors[column] = Fixed::from_raw(128);
+        column += 1;
+    }
+    conveyors
+};
+/* レベルの壊せるブロック (列ごとの行)。右端のばねの上に並んでいて、打ち上げられると頭で割れる */
+pub const LEVEL_BLOCKS: [Option<u16>; GROUND_COLUMNS] = {
+    let mut blocks = [None; GROUND_COLUMNS];
+    let mut column = 28;
+    while column < 30 {
+        blocks[column] = Some(2);
+        column += 1;
+    }
+    blocks
+};
+/* レベルのスイッチのブロック (列ごとの行と組)。高い所に A と B の足場が並んでいて、スイッチを踏むたびに固い方が入れ替わる */
+pub const LEVEL_SWITCH_BLOCKS: [Option<(u16, BlockSet)>; GROUND_COLUMNS] = {
+    let mut blocks = [None; GROUND_COLUMNS];
+    let mut column = 17;
+    while column < 20 {
+        blocks[column] = Some((3, BlockSet::A));
+        blocks[column + 3] = Some((3, BlockSet::B));
+        column += 1;
+    }
+    blocks
+};
+/* レベルのはしご (列ごとの上端の行)。出発点の左から、上の横に動く足場の通り道の下まで上れる */
+pub const LEVEL_LADDERS: [Option<u16>; GROUND_COLUMNS] = {
+    let mut ladders = [None; GROUND_COLUMNS];
+    ladders[14] = Some(8);
+    ladders
+};
+/* レベルの壁 (列ごとの、地面から立つ段数)。左の動く足場の下に、跳び越せる低い壁がある */
+pub const LEVEL_WALLS: [usize; GROUND_COLUMNS] = {
+    let mut walls = [0; GROUND_COLUMNS];
+    walls[4] = 2;
+    walls
+};
+/* タイムアタックの目標タイム */
+pub const LEVEL_PAR: Par = Par {
+    gold: 8 * 60,
+    silver: 12 * 60,
+    bronze: 20 * 60,
+};
+
+// 画面端のラップアラウンド。
+// x の合法範囲は -DROID_WIDTH..WIDTH の半開区間で、
+// 右端を越えると左端の画面外へ、左端を越えると右端の画面外へ 1px ずつ回り込む。
+pub fn wrap_x(x: i32) -> i32 {
+    (x + DROID_WIDTH).rem_euclid(WIDTH + DROID_WIDTH) - DROID_WIDTH
+}
+
+// 横方向に dx だけ動かす。接地中と空中の両方から使う。
+pub fn move_x(x: i32, dx: i32) -> i32 {
+    wrap_x(x + dx)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Apple {
+    /* 左上 */
+    pub x: i32,
+    pub y: i32,
+    /* ドロイド君が乗れる x の範囲 (Surface::x_range) の、りんごの x からの幅 (Game::apple_half_width) */
+    pub half_width: i32,
+}
+
+encode_fields!(Apple { x, y, half_width });
+
+/* りんごのスプライトの大きさと、絵の当たり判定 (上が空いていて、乗れる面はその下) */
+pub const APPLE_SIZE: i32 = 16;
+pub const APPLE_HITBOX: Aabb = resources::hitbox("Apple", 0);
+
+/* コインはドロイド君にだけ拾われる */
+const COIN_FILTER: Filter = Filter::new(Layers::PICKUP, Layers::PLAYER);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Coin {
+    pub x: i32,
+    pub y: i32,
+}
+
+encode_fields!(Coin { x, y });
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    // コインを全て取った。
+    Cleared,
+    // 体力が尽きた。エンドレスでは画面の下へ落ちたときも。
+    Died,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Game {
+    /* 何ティック目か。リプレイやゴーストは描画フレームではなくこれを基準にする */
+    pub tick: u32,
+    pub difficulty: Difficulty,
+    pub droid: Droid,
+    pub apple: Apple,
+    /* レベルに置いた他のりんご (Level::objects) */
+    pub apples: Arena<Apple, MAX_APPLES>,
+    pub coins: Arena<Coin, MAX_COINS>,
+    /* 拾われていない鍵と、開いていない扉 (Level::objects) */
+    pub keys: Arena<Key, MAX_KEYS>,
+    pub doors: Arena<Door, MAX_DOORS>,
+    /* 持っている鍵の数。レベルを読み込み直すまで残る */
+    pub keys_held: u8,
+    /* 水と風 (Level::objects) */
+    pub waters: Arena<Water, MAX_WATERS>,
+    pub winds: Arena<Wind, MAX_WINDS>,
+    /* テレポーターの台 (Level::objects) と、移ってから止まっている残り */
+    pub teleporters: Arena<Teleporter, MAX_TELEPORTERS>,
+    pub warp: Timer,
+    /* トリガーゾーン (Level::objects) */
+    pub zones: Arena<Zone, MAX_ZONES>,
+    /* 出口のゾーンに入った (コインが残っていてもクリア) */
+    pub exited: bool,
+    /* 入っているゾーンの案内 */
+    pub hint: Option<&'static str>,
+    /* 始めてほしいカットシーンの番号 (cutscene モジュール)。GameScene が取り出して流す */
+    pub cutscene: Option<u8>,
+    /* 動く足場 (Level::platforms) */
+    pub moving_platforms: Arena<MovingPlatform, MAX_MOVING_PLATFORMS>,
+    /* 崩れる足場 (Level::platforms) */
+    pub crumbling_platforms: Arena<CrumblingPlatform, MAX_CRUMBLING_PLATFORMS>,
+    /* ばね (Level::platforms) */
+    pub springs: Arena<Spring, MAX_SPRINGS>,
+    /* スイッチ (Level::platforms) */
+    pub switches: Arena<Switch, MAX_SWITCHES>,
+    /* 棘 (Level::objects) */
+    pub spikes: Arena<Spike, MAX_SPIKES>,
+    /* 地面の当たり判定 (Level の地面を並べたタイルから作る) */
+    pub collision_map: CollisionMap,
+    /* 地面の材質 (Level::ground のタイルから決まる) */
+    pub materials: [Material; GROUND_COLUMNS],
+    /* 地面のベルトコンベアの速さ (Level::conveyors) */
+    pub conveyors: [Fixed; GROUND_COLUMNS],
+    /* 残っている壊せるブロックの行 (Level::blocks) */
+    pub blocks: [Option<u16>; GROUND_COLUMNS],
+    /* 壊れたブロックの破片 */
+    pub debris: Arena<Debris, MAX_DEBRIS>,
+    /* スイッチのブロックの行と組 (Level::switch_blocks) */
+    pub switch_blocks: [Option<(u16, BlockSet)>; GROUND_COLUMNS],
+    /* はしごの上端の行 (Level::ladders) */
+    pub ladders: [Option<u16>; GROUND_COLUMNS],
+    /* 今固いスイッチのブロックの組 */
+    pub solid_set: BlockSet,
+    /* ヒップドロップの衝撃波 (出ている間だけ) */
+    pub shockwave: Option<Shockwave>,
+    pub score: u32,
+    pub health: u8,
+    pub mode: Mode,
+    /* HARD モードの修飾 (modifiers モジュール参照) */
+    pub modifiers: Modifiers,
+    /* 跳び方と歩く速さ (physics モジュール参照) */
+    pub tuning: Tuning,
+    /* 画面端が壁か (Level::solid_edges) */
+    pub solid_edges: bool,
+    /* 何人目のプレイヤーか (0 始まり。交代で遊ぶモード以外は 0) */
+    pub player: u8,
+    /* 始まる前のカウントダウン (タイムアタックのみ) */
+    pub countdown: Timer,
+    /* カウントダウンが終わってからのティック数。タイムアタックではペナルティも足される */
+    pub time: u32,
+    /* 足場とカメラ (エンドレスのみ) */
+    pub climb: Option<Climb>,
+    /* 覚えた状態と区間タイム (練習モードのみ) */
+    pub practice: Option<Practice>,
+    /* 今のレベルと区切りタイム (スピードランのみ) */
+    pub speedrun: Option<Speedrun>,
+    /* 残り時間 (コインラッシュのみ) */
+    pub rush: Option<CoinRush>,
+    /* 関門の仕掛け (チュートリアルのみ) */
+    pub script: Option<Script>,
+    /* ランの始めのシード。同じシードと入力なら同じ展開になる */
+    pub seed: u32,
+    /* ラン中の乱数は全てここから引く (rng モジュール参照) */
+    pub rng: Rng,
+    events: EventQueue<EVENT_CAPACITY>,
+    /* デバッグメニューのすり抜け */
+    #[cfg(feature = "debug-tools")]
+    pub noclip: bool,
+}
+
+impl Game {
+    // 同じ難易度とシードで作り、同じ入力を与えれば、必ず同じ展開になる。
+    pub fn new(difficulty: Difficulty, seed: u32) -> Self {
+        let mut game = Self {
+            tick: 0,
+            difficulty,
+            /* 位置と向き、りんご、コインは load でレベルから置く */
+            droid: Droid::new(),
+            apple: Apple {
+                x: 0,
+                y: 0,
+                half_width: Tuning::NORMAL.apple_half_width,
+            },
+            apples: Arena::new(),
+            coins: Arena::new(),
+            keys: Arena::new(),
+            doors: Arena::new(),
+            keys_held: 0,
+            waters: Arena::new(),
+            winds: Arena::new(),
+            teleporters: Arena::new(),
+            warp: Timer::new(),
+            zones: Arena::new(),
+            exited: false,
+            hint: None,
+            cutscene: None,
+            moving_platforms: Arena::new(),
+            crumbling_platforms: Arena::new(),
+            springs: Arena::new(),
+            switches: Arena::new(),
+            spikes: Arena::new(),
+            collision_map: CollisionMap::EMPTY,
+            materials: [Material::Normal; GROUND_COLUMNS],
+            conveyors: [Fixed::new(0); GROUND_COLUMNS],
+            blocks: [None; GROUND_COLUMNS],
+            debris: Arena::new(),
+            switch_blocks: [None; GROUND_COLUMNS],
+            ladders: [None; GROUND_COLUMNS],
+            solid_set: BlockSet::A,
+            shockwave: None,
+            score: 0,
+            health: difficulty.settings().lives,
+            mode: Mode::Normal,
+            modifiers: Modifiers::NONE,
+            tuning: Tuning::NORMAL,
+            solid_edges: false,
+            player: 0,
+            countdown: Timer::new(),
+            time: 0,
+            climb: None,
+            practice: None,
+            speedrun: None,
+            rush: None,
+            script: None,
+            seed,
+            rng: Rng::new(seed),
+            events: EventQueue::new(),
+            #[cfg(feature = "debug-tools")]
+            noclip: false,
+        };
+        game.load(&Level::load(false));
+        game
+    }
+
+    // level の物を置く。ドロイド君は出発点へ戻し、コインは全て置き直す。
+    // コインラッシュではコインラッシュ層の物も置く。
+    pub fn load(&mut self, level: &Level) {
+        (self.droid.x, self.droid.y) = level.droid;
+        self.droid.previous = level.droid;
+        self.droid.hflip = level.hflip;
+        self.droid.gravity = Gravity::Down;
+        self.droid.medium = Medium::Air;
+        self.solid_edges = level.solid_edges;
+        self.collision_map = CollisionMap::new(level);
+        self.materials = level.ground.map(Material::of_tile);
+        self.conveyors = level.conveyors;
+        self.blocks = level.blocks;
+        self.debris = Arena::new();
+        self.switch_blocks = level.switch_blocks;
+        self.ladders = level.ladders;
+        self.solid_set = BlockSet::A;
+        self.shockwave = None;
+        (self.apple.x, self.apple.y) = level.apple;
+        self.apples = Arena::new();
+        self.coins = Arena::new();
+        self.keys = Arena::new();
+        self.doors = Arena::new();
+        self.keys_held = 0;
+        self.waters = Arena::new();
+        self.winds = Arena::new();
+        self.teleporters = Arena::new();
+        self.warp = Timer::new();
+        self.zones = Arena::new();
+        self.exited = false;
+        self.hint = None;
+        self.cutscene = None;
+        let rush = if self.rush.is_some() {
+            &level.rush[..]
+        } else {
+            &[]
+        };
+        self.moving_platforms = Arena::new();
+        self.crumbling_platforms = Arena::new();
+        self.springs = Arena::new();
+        self.switches = Arena::new();
+        self.spikes = Arena::new();
+        for &(kind, position) in level.objects.iter().chain(rush).chain(&level.platforms) {
+            self.spawn(kind).at(position).build();
+        }
+    }
+
+    // mode で遊ぶ。始める前 (最初の update の前) に呼ぶこと。
+    pub fn with_mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        match mode {
+            Mode::Normal | Mode::HotSeat => {}
+            Mode::TimeAttack | Mode::GhostRace | Mode::LinkRace => {
+                self.countdown.start(COUNTDOWN_TICKS)
+            }
+            Mode::Endless => self.start_climb(),
+            Mode::Practice => self.practice = Some(Practice::new()),
+            Mode::Speedrun => {
+                self.countdown.start(COUNTDOWN_TICKS);
+                self.speedrun = Some(Speedrun::new());
+            }
+            Mode::CoinRush => {
+                self.countdown.start(COUNTDOWN_TICKS);
+                self.rush = Some(CoinRush::new());
+                /* コインラッシュ層のコインも置く */
+                self.load(&self.level());
+            }
+            Mode::Tutorial => {
+                let level = Level::tutorial();
+                self.script = Some(Script::new(level.script));
+                self.load(&level);
+            }
+        }
+        self
+    }
+
+    // りんごに乗れる範囲の幅。調整値の幅から修飾の分だけ狭める。
+    pub fn apple_half_width(&self) -> i32 {
+        (self.tuning.apple_half_width - self.modifiers.apple_trim()).max(1)
+    }
+
+    // modifiers を掛けて遊ぶ。始める前 (最初の update の前) に呼ぶこと。
+    pub fn with_modifiers(mut self, modifiers: Modifiers) -> Self {
+        self.modifiers = modifiers;
+        self.apple.half_width = self.apple_half_width();
+        for (_, apple) in self.apples.iter_mut() {
+            apple.half_width = self.apple.half_width;
+        }
+        /* エンドレスの塔は乱数で作るので、反転するレベルが無い */
+        if modifiers.mirror && self.climb.is_none() {
+            self.load(&self.level());
+        }
+        self
+    }
+
+    // タイムアタックの目標タイム。
+    pub fn par(&self) -> Par {
+        self.modifiers.par(LEVEL_PAR)
+    }
+
+    // 終わっていれば、どう終わったか。
+    pub fn outcome(&self) -> Option<Outcome> {
+        if let Some(climb) = &self.climb {
+            /* エンドレスにはクリアが無い */
+            return (climb.fallen || self.health == 0).then_some(Outcome::Died);
+        }
+        if self.practice.is_some() {
+            /* 練習は終わらない */
+            return None;
+        }
+        if let Some(rush) = &self.rush {
+            return self.rush_outcome(rush);
+        }
+        if let Some(script) = &self.script {
+            /* チュートリアルは仕掛けを全て終えたらクリア */
+            return script.is_done().then_some(Outcome::Cleared);
+        }
+        if self.level_cleared() {
+            Some(Outcome::Cleared)
+        } else if self.health == 0 {
+            Some(Outcome::Died)
+        } else {
+            None
+        }
+    }
+
+    // コインを全て取ったか、出口に入った。
+    pub fn level_cleared(&self) -> bool {
+        self.coins.is_empty() || self.exited
+    }
+
+    // 1 ティック (1/60 秒) 進める。
+    // エンティティを全て動かしてから、その間に積まれたイベントを処理する。
+    pub fn update(&mut self, input: &Input) {
+        self.tick = self.tick.wrapping_add(1);
+        self.droid.previous = (self.droid.x, self.droid.y);
+        if self.countdown.is_running() {
+            self.countdown.tick();
+            return;
+        }
+        if self.outcome().is_none() {
+            self.time += 1;
+        }
+        self.update_practice(input);
+        self.droid.invulnerable.tick();
+        let before = self.droid.state;
+        let airborne = !self.switches.is_empty() && !self.droid.is_grounded();
+        self.update_entities();
+        self.update_teleporters(input);
+        /* テレポーターで移った直後は止まっている */
+        if !self.warp.is_running() {
+            self.update_ladder(input);
+            self.update_water(input);
+            self.update_wind();
+            {
+                profile_scope!("droid");
+                #[cfg(feature = "debug-tools")]
+                if self.noclip {
+                    self.fly(input);
+                } else {
+                    self.update_droid(input);
+                }
+                #[cfg(not(feature = "debug-tools"))]
+                self.update_droid(input);
+            }
+        }
+        self.update_springs();
+        self.update_switches(airborne);
+        self.update_doors();
+        self.update_spikes();
+        self.break_blocks_above();
+        self.update_tutorial(before);
+        {
+            profile_scope!("climb");
+            self.update_climb();
+        }
+        self.update_zones();
+        {
+            profile_scope!("coins");
+            self.collect_coins();
+        }
+        if self
+            .shockwave
+            .as_mut()
+            .is_some_and(|shockwave| !shockwave.tick())
+        {
+            self.shockwave = None;
+        }
+        {
+            profile_scope!("events");
+            self.handle_events();
+            /* 終わったティックには次が無いので、処理中に積んだもの (効果音...) もここで処理しきる */
+            while !self.events.is_empty() && self.outcome().is_some() {
+                self.handle_events();
+            }
+        }
+        self.update_speedrun();
+        self.update_rush();
+    }
+
+    pub fn push_event(&mut self, event: Event) {
+        self.events.push(event);
+    }
+
+    // collect_coins, handle_events, update_droid は毎ティック通るので IWRAM に置く (memory モジュール参照)。
+    #[link_section = ".iwram.collect_coins"]
+    pub fn collect_coins(&mut self) {
+        for coin in collision::touching(&self.droid, &self.coins) {
+            self.events.push(Event::CoinCollected { coin });
+        }
+    }
+
+    // 出入りしたトリガーゾーンのイベントを積む (zone モジュール参照)。ドロイド君を動かした後に呼ぶ。
+    pub fn update_zones(&mut self) {
+        let centre = (
+            self.droid.x + DROID_WIDTH / 2,
+            self.droid.y + DROID_WIDTH / 2,
+        );
+        for (zone, entered) in self
+            .zones
+            .iter_mut()
+            .filter_map(|(id, zone)| Some((id, zone.update(centre)?)))
+        {
+            self.events.push(if entered {
+                Event::ZoneEntered { zone }
+            } else {
+                Event::ZoneExited { zone }
+            });
+        }
+    }
+
+    #[link_section = ".iwram.handle_events"]
+    pub fn handle_events(&mut self) {
+        for event in self.events.drain() {
+            match event {
+                Event::CoinCollected { coin } => {
+                    /* 得点。同じコインが 2 度数えられないよう、取り除けたときだけ */
+                    if let Some(coin) = self.coins.despawn(coin) {
+                        self.score += COIN_SCORE;
+                        if let Some(rush) = &mut self.rush {
+                            rush.collect();
+                        }
+                        log_debug!("coin at ({}, {}), score = {}", coin.x, coin.y, self.score);
+                        self.events.push(Event::PlaySfx(Sfx::Coin));
+                    }
+                }
+                Event::PlayerDamaged { amount, from_x } => {
+                    /* 無敵時間中は何も起きない */
+                    if !self.droid.invulnerable.try_use() {
+                        continue;
+                    }
+                    /* ダメージを与えたものの方を向き、反対へ飛ばされる */
+                    self.droid.hflip = from_x < self.droid.x;
+                    self.droid.knock_back(&self.tuning);
+                    match self.mode {
+                        Mode::Normal
+                        | Mode::Endless
+                        | Mode::HotSeat
+                        | Mode::LinkRace
+                        | Mode::CoinRush => {
+                            let amount = if self.modifiers.one_hit {
+                                self.health
+                            } else {
+                                amount.saturating_mul(self.difficulty.settings().damage_scale)
+                            };
+                            let before = self.health;
+                            self.health = self.health.saturating_sub(amount);
+                            log_debug!("damaged by {}, health = {}", amount, self.health);
+                            if before > 0 && self.health == 0 {
+                                self.events.push(Event::PlayerDied);
+                            }
+                        }
+                        Mode::Practice | Mode::Tutorial => {
+                            log_debug!("damage ignored in {}", self.mode.name());
+                        }
+                        Mode::TimeAttack | Mode::GhostRace | Mode::Speedrun => {
+                            /* 体力の代わりにタイムが増える */
+                            self.time += PENALTY_TICKS;
+                            log_debug!("time penalty, time = {}", self.time);
+                        }
+                    }
+                    self.events.push(Event::PlaySfx(Sfx::Hurt));
+                }
+                Event::GroundPound { x, y } => {
+                    /* 敵が入ったら、ここで shockwave.reaches に入るものに効かせる */
+                    log_debug!("ground pound at ({}, {})", x, y);
+                    self.break_blocks_below(x, y);
+                    self.shockwave = Some(Shockwave::new(x, y));
+                    self.events.push(Event::PlaySfx(Sfx::Slam));
+                }
+                Event::PlayerLanded { .. } => self.events.push(Event::PlaySfx(Sfx::Land)),
+                Event::PlayerDied => {
+                    log_debug!("died at ({}, {})", self.droid.x, self.droid.y);
+                    self.events.push(Event::PlaySfx(Sfx::Die));
+                }
+                Event::BlockBroken { column, row } => self.shatter_block(column, row),
+                Event::ZoneEntered { zone } => self.enter_zone(zone),
+                Event::ZoneExited { zone } => self.exit_zone(zone),
+                Event::PlaySfx(sfx) => {
+                    /* 音はミキサーが入るまでログだけ。着地は降りるたびに鳴るので出さない */
+                    if sfx != Sfx::Land {
+                        log_debug!("sfx {:?}", sfx);
+                    }
+                }
+            }
+        }
+    }
+
+    // ドロイド君が上へ出られない y 座標。エンドレスでは上に限りが無い。
+    pub fn ceiling(&self) -> i32 {
+        if self.climb.is_some() {
+            i32::MIN
+        } else {
+            0
+        }
+    }
+
+    // ドロイド君を仕切る壁。壁の無いレベルでは None で、画面端で回り込む。
+    // チュートリアルでは閉じている関門が右の壁になる。
+    pub fn walls(&self) -> Option<Walls> {
+        if !self.solid_edges {
+            return None;
+        }
+        let right = self
+            .script
+            .as_ref()
+            .and_then(Script::current)
+            .and_then(|trigger| trigger.gate)
+            .unwrap_or(WIDTH);
+        Some(Walls { left: 0, right })
+    }
+
+    // ドロイド君を 1 ティック進め (Droid::update)、頭をぶつけた音と着地、ヒップドロップのイベントを積む。
+    #[link_section = ".iwram.update_droid"]
+    pub fn update_droid(&mut self, input: &Input) {
+        let around = Surroundings {
+            map: &self.collision_map,
+            materials: &self.materials,
+            conveyors: &self.conveyors,
+            surfaces: Surfaces {
+                apple: &self.apple,
+                apples: &self.apples,
+                climb: self.climb.as_ref(),
+                moving: &self.moving_platforms,
+                crumbling: &self.crumbling_platforms,
+                springs: &self.springs,
+            },
+            ceiling: self.ceiling(),
+            walls: self.walls(),
+            tuning: &self.tuning,
+            double_jump: !self.modifiers.no_double_jump,
+            /* 上に限りの無いエンドレスでは天井に立てない */
+            can_flip: self.climb.is_none(),
+        };
+        let droid = &mut self.droid;
+        let pounding = droid.state == PlayerState::GroundPound;
+        droid.update(input, &around);
+        if pounding && droid.state != PlayerState::GroundPound {
+            /* ヒップドロップで着地した */
+            self.events.push(Event::GroundPound {
+                x: droid.x + DROID_WIDTH / 2,
+                y: droid.y,
+            });
+        }
+        if droid.bonked {
+            self.events.push(Event::PlaySfx(Sfx::Bonk));
+        }
+        if droid.landed {
+            self.events.push(Event::PlayerLanded {
+                x: droid.x,
+                y: droid.y,
+            });
+        }
+    }
+}
+
+impl Collider for Coin {
+    fn hitbox(&self) -> Aabb {
+        Aabb::new(self.x, self.y, COIN_SIZE, COIN_SIZE)
+    }
+
+    fn filter(&self) -> Filter {
+        COIN_FILTER
+    }
+}
+
+impl Entity for Coin {
+    fn draw(&self) -> Option<Look> {
+        Some(Look::at(self.x, self.y))
+    }
+}
+
+impl Entity for Apple {
+    fn draw(&self) -> Option<Look> {
+        Some(Look::at(self.x, self.y))
+    }
+}
+
+// 難易度・モード・修飾・シードと何人目かを先に書き、読むときはそれで Game を作り直してから残りを読む
+// (snapshot モジュール)。材質やはしごのようにレベルから決まって変わらないものと、調整値 (tuning) や
+// デバッグメニューのすり抜けは書かず、作り直した方のものを使う。
+// 案内は出しているゾーンの番号で書く (カットシーンの台詞は流している間しか出ないので書かない)。
+impl Encode for Game {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        (self.difficulty as u8).encode(bytes);
+        self.mode.index().encode(bytes);
+        self.modifiers.bits().encode(bytes);
+        self.seed.encode(bytes);
+        self.player.encode(bytes);
+        /* 先のレベルにいれば、読むときにそのレベルを置き直す */
+        self.speedrun.encode(bytes);
+        self.tick.encode(bytes);
+        self.droid.encode(bytes);
+        self.apple.encode(bytes);
+        self.apples.encode(bytes);
+        self.coins.encode(bytes);
+        self.keys.encode(bytes);
+        self.doors.encode(bytes);
+        self.keys_held.encode(bytes);
+        self.waters.encode(bytes);
+        self.winds.encode(bytes);
+        self.teleporters.encode(bytes);
+        self.warp.encode(bytes);
+        self.zones.len().encode(bytes);
+        for zone in self.zones.values() {
+            zone.encode_state(bytes);
+        }
+        self.exited.encode(bytes);
+        let hint = self.hint.and_then(|hint| {
+            self.zones
+                .values()
+                .position(|zone| zone.action == ZoneAction::Hint(hint))
+        });
+        hint.encode(bytes);
+        self.cutscene.encode(bytes);
+        self.moving_platforms.encode(bytes);
+        self.crumbling_platforms.encode(bytes);
+        self.springs.encode(bytes);
+        self.switches.encode(bytes);
+        self.spikes.encode(bytes);
+        self.collision_map.encode(bytes);
+        self.blocks.encode(bytes);
+        self.debris.encode(bytes);
+        self.solid_set.encode(bytes);
+        self.shockwave.encode(bytes);
+        self.score.encode(bytes);
+        self.health.encode(bytes);
+        self.countdown.encode(bytes);
+        self.time.encode(bytes);
+        self.climb.encode(bytes);
+        self.practice.encode(bytes);
+        self.rush.encode(bytes);
+        /* チュートリアルかどうかはモードで決まる */
+        if let Some(script) = &self.script {
+            script.encode_state(bytes);
+        }
+        self.rng.encode(bytes);
+        self.events.encode(bytes);
+    }
+
+    fn decode(reader: &mut Reader) -> Option<Self> {
+        let difficulty = *Difficulty::ALL.get(usize::from(u8::decode(reader)?))?;
+        let mode = Mode::from_index(u8::decode(reader)?)?;
+        let modifiers = Modifiers::from_bits(u8::decode(reader)?);
+        let mut game = Game::new(difficulty, u32::decode(reader)?)
+            .with_mode(mode)
+            .with_modifiers(modifiers);
+        game.player = u8::decode(reader)?;
+        game.speedrun = Encode::decode(reader)?;
+        if game.speedrun.is_some() {
+            game.load(&game.level());
+        }
+        game.tick = Encode::decode(reader)?;
+        game.droid = Encode::decode(reader)?;
+        game.apple = Encode::decode(reader)?;
+        game.apples = Encode::decode(reader)?;
+        game.coins = Encode::decode(reader)?;
+        game.keys = Encode::decode(reader)?;
+        game.doors = Encode::decode(reader)?;
+        game.keys_held = Encode::decode(reader)?;
+        game.waters = Encode::decode(reader)?;
+        game.winds = Encode::decode(reader)?;
+        game.teleporters = Encode::decode(reader)?;
+        game.warp = Encode::decode(reader)?;
+        if usize::decode(reader)? != game.zones.len() {
+            return None;
+        }
+        for zone in game.zones.values_mut() {
+            zone.decode_state(reader)?;
+        }
+        game.exited = Encode::decode(reader)?;
+        game.hint = match Option::<usize>::decode(reader)? {
+            Some(index) => match game.zones.values().nth(index)?.action {
+                ZoneAction::Hint(hint) => Some(hint),
+                _ => return None,
+            },
+            None => None,
+        };
+        game.cutscene = Encode::decode(reader)?;
+        game.moving_platforms = Encode::decode(reader)?;
+        game.crumbling_platforms = Encode::decode(reader)?;
+        game.springs = Encode::decode(reader)?;
+        game.switches = Encode::decode(reader)?;
+        game.spikes = Encode::decode(reader)?;
+        game.collision_map = Encode::decode(reader)?;
+        game.blocks = Encode::decode(reader)?;
+        game.debris = Encode::decode(reader)?;
+        game.solid_set = Encode::decode(reader)?;
+        game.shockwave = Encode::decode(reader)?;
+        game.score = Encode::decode(reader)?;
+        game.health = Encode::decode(reader)?;
+        game.countdown = Encode::decode(reader)?;
+        game.time = Encode::decode(reader)?;
+        game.climb = Encode::decode(reader)?;
+        game.practice = Encode::decode(reader)?;
+        game.rush = Encode::decode(reader)?;
+        if let Some(script) = &mut game.script {
+            script.decode_state(reader)?;
+        }
+        game.rng = Encode::decode(reader)?;
+        game.events = Encode::decode(reader)?;
+        Some(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use agb::input::Button;
+
+    use super::*;
+    use crate::{
+        collision::Surface, player::INVULNERABLE_TICKS, shockwave::SHOCKWAVE_TICKS, tilemap::Cell,
+    };
+
+    fn in_band(x: i32) -> bool {
+        (-DROID_WIDTH..WIDTH).contains(&x)
+    }
+
+    // 接地中・空中のどちらの分岐も move_x を通るので、ここでは x と向きだけを総当たりする。
+    #[test_case]
+    fn move_x_stays_in_band(_gba: &mut agb::Gba) {
+        for x in (-DROID_WIDTH - 4)..=(WIDTH + 4) {
+            for dir in [-1, 0, 1] {
+                let next = move_x(wrap_x(x), dir);
+                assert!(in_band(next), "x = {x}, dir = {dir} -> {next}");
+            }
+        }
+    }
+
+    #[test_case]
+    fn wrap_x_is_identity_inside_band(_gba: &mut agb::Gba) {
+        for x in -DROID_WIDTH..WIDTH {
+            assert_eq!(wrap_x(x), x);
+        }
+        assert_eq!(wrap_x(WIDTH), -DROID_WIDTH);
+        assert_eq!(wrap_x(-DROID_WIDTH - 1), WIDTH - 1);
+    }
+
+    // 一方向に歩き続けたとき、1 周で全ての位置をちょうど 1 回ずつ通り、
+    // 二重の回り込みや足踏みが起きないこと。
+    #[test_case]
+    fn continuous_movement_is_monotone_modulo_wrap(_gba: &mut agb::Gba) {
+        let period = (WIDTH + DROID_WIDTH) as usize;
+        for dir in [-1, 1] {
+            for start in [-DROID_WIDTH, 0, WIDTH / 2, WIDTH - 1] {
+                let mut visited = [false; 512];
+                let mut x = start;
+                for _ in 0..period {
+                    let next = move_x(x, dir);
+                    let wrapped = if dir > 0 {
+                        x == WIDTH - 1 && next == -DROID_WIDTH
+                    } else {
+                        x == -DROID_WIDTH && next == WIDTH - 1
+                    };
+                    assert!(next == x + dir || wrapped, "{x} -> {next} (dir = {dir})");
+                    let slot = (next + DROID_WIDTH) as usize;
+                    assert!(!visited[slot], "visited {next} twice");
+                    visited[slot] = true;
+                    x = next;
+                }
+                assert_eq!(x, start);
+            }
+        }
+    }
+
+    // 描画なしでロジックだけ回せること: 一度跳んだら必ず地面に戻ってくる。
+    #[test_case]
+    fn a_jump_lands_without_rendering(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        game.update(&Input::new(Button::UP, Button::empty()));
+        assert_eq!(game.droid.state, PlayerState::JumpSquat { double: false });
+        let mut peak = GROUND_Y;
+        for _ in 0..120 {
+            game.update(&Input::default());
+            peak = peak.min(game.droid.y);
+        }
+        assert_eq!(game.droid.state, PlayerState::Idle);
+        assert_eq!(game.droid.y, GROUND_Y);
+        assert!(peak < GROUND_Y - 16, "peak = {peak}");
+    }
+
+    // 頭の上の固いタイルには下から頭をぶつけ、そこで上るのを止めて落ちてくる。
+    #[test_case]
+    fn jumping_into_a_ceiling_bonks(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        let row = 10;
+        for column in 14..18 {
+            game.collision_map.set(column, row, Cell::Solid);
+        }
+        let bottom = (row as i32 + 1) * 8;
+        let held = Input::new(Button::UP, Button::UP);
+        game.update(&Input::new(Button::UP, Button::empty()));
+        let (mut peak, mut bonks) = (GROUND_Y, 0);
+        for _ in 0..120 {
+            game.update(&held);
+            peak = peak.min(game.droid.y);
+            if game.droid.bonked {
+                bonks += 1;
+                assert_eq!(game.droid.y, bottom);
+                /* 上る速さを失い、同じティックの重力でもう落ち始めている */
+                assert!(game.droid.vy <= Fixed::new(0));
+            }
+        }
+        assert_eq!(peak, bottom);
+        assert_eq!(bonks, 1);
+        assert_eq!(
+            (game.droid.state, game.droid.y),
+            (PlayerState::Idle, GROUND_Y)
+        );
+    }
+
+    #[test_case]
+    fn coins_score_through_events(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        let (_, (x, y)) = LEVEL_OBJECTS[0];
+        let before = game.coins.len();
+        game.droid.x = x;
+        game.droid.y = y;
+        game.update(&Input::default());
+        assert_eq!(game.coins.len(), before - 1);
+        assert!(game
+            .coins
+            .iter()
+            .all(|(_, coin)| (coin.x, coin.y) != (x, y)));
+        assert_eq!(game.score, COIN_SCORE);
+        /* 効果音は次のティックで鳴らす */
+        assert_eq!(game.events.drain().next(), Some(Event::PlaySfx(Sfx::Coin)));
+        /* 同じコインは 2 度取れない */
+        game.update(&Input::default());
+        assert_eq!(game.score, COIN_SCORE);
+    }
+
+    #[test_case]
+    fn damage_is_applied_after_the_entity_pass(_gba: &mut agb::Gba) {
+        const MAX_HEALTH: u8 = 3;
+        let mut game = Game::new(Difficulty::Normal, 1);
+        assert_eq!(game.health, MAX_HEALTH);
+        let x = game.droid.x;
+        game.push_event(Event::PlayerDamaged {
+            amount: 1,
+            from_x: x - 10,
+        });
+        assert_eq!(game.health, MAX_HEALTH);
+        game.update(&Input::default());
+        assert_eq!(game.health, MAX_HEALTH - 1);
+        assert!(game.droid.hflip);
+        /* 無敵時間が切れるまで待つ。ノックバックで動いた先から見て右で受ける */
+        for _ in 0..INVULNERABLE_TICKS {
+            game.update(&Input::default());
+        }
+        game.push_event(Event::PlayerDamaged {
+            amount: 5,
+            from_x: game.droid.x + 10,
+        });
+        game.update(&Input::default());
+        assert_eq!(game.health, 0);
+        assert!(!game.droid.hflip);
+    }
+
+    // 降りたことと倒れたことはイベントで知らせる。倒れたティックには効果音まで処理しきる。
+    #[test_case]
+    fn landing_and_dying_are_announced(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        game.update(&Input::default());
+        assert!(!game.droid.landed);
+        game.update(&Input::new(Button::UP, Button::empty()));
+        let mut ticks = 0;
+        while !game.droid.landed {
+            game.update(&Input::new(Button::UP, Button::UP));
+            ticks += 1;
+            assert!(ticks < 120, "never landed");
+        }
+        /* 降りたティックに処理され、効果音は次のティックで鳴らす */
+        assert_eq!(game.events.drain().next(), Some(Event::PlaySfx(Sfx::Land)));
+
+        game.push_event(Event::PlayerDamaged {
+            amount: u8::MAX,
+            from_x: 0,
+        });
+        game.update(&Input::default());
+        assert_eq!(game.outcome(), Some(Outcome::Died));
+        assert!(game.events.is_empty());
+    }
+
+    // ダメージを受けると反対へ跳ね上げられ、knockback_ticks の間は左右キーも UP も効かない。
+    #[test_case]
+    fn damage_knocks_the_droid_back_and_locks_the_keys(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        let x = game.droid.x;
+        game.push_event(Event::PlayerDamaged {
+            amount: 1,
+            from_x: x - 10,
+        });
+        game.update(&Input::default());
+        assert_eq!(game.droid.state, PlayerState::Knockback);
+        let tuning = game.tuning;
+        let (mut peak, mut flashed) = (GROUND_Y, false);
+        for tick in 1..=tuning.knockback_ticks {
+            flashed |= !game.droid.is_visible();
+            game.update(&Input::new(Button::LEFT | Button::UP, Button::UP));
+            assert_eq!(game.droid.vx, tuning.knockback_speed);
+            peak = peak.min(game.droid.y);
+            if tick < tuning.knockback_ticks {
+                assert_eq!(game.droid.state, PlayerState::Knockback, "tick {tick}");
+            }
+        }
+        assert!(game.droid.x > x);
+        assert_eq!(peak, GROUND_Y - 12);
+        /* 無敵時間で点滅している */
+        assert!(flashed);
+        /* キーが効くようになったら、押している向きへ戻っていく */
+        assert_eq!(game.droid.state, PlayerState::Airborne { double: true });
+        game.update(&Input::new(Button::LEFT, Button::empty()));
+        assert!(game.droid.vx < tuning.knockback_speed);
+        while game.droid.state != PlayerState::Idle {
+            game.update(&Input::new(Button::LEFT, Button::empty()));
+        }
+        assert_eq!(game.droid.y, GROUND_Y);
+    }
+
+    // ジャンプの頂上で DOWN を押すと、止まってから真下へ落ち、着地した所に衝撃波が出る。
+    // 上っている途中で押しても何も起きない。
+    #[test_case]
+    fn down_at_the_apex_pounds_the_ground(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        let tuning = game.tuning;
+        game.update(&Input::new(Button::UP, Button::empty()));
+        while !matches!(game.droid.state, PlayerState::Airborne { double: false }) {
+            game.update(&Input::new(Button::UP, Button::UP));
+        }
+        game.update(&Input::new(Button::UP | Button::DOWN, Button::UP));
+        assert_eq!(game.droid.state, PlayerState::Airborne { double: false });
+        while tuning.ground_pound_apex < game.droid.vy {
+            game.update(&Input::new(Button::UP, Button::UP));
+        }
+        game.update(&Input::new(Button::RIGHT | Button::DOWN, Button::RIGHT));
+        assert_eq!(
+            (game.droid.state, game.droid.pose()),
+            (PlayerState::GroundPound, 22)
+        );
+        let (x, y) = (game.droid.x, game.droid.y);
+        for _ in 1..tuning.ground_pound_halt_ticks {
+            game.update(&Input::new(Button::RIGHT, Button::RIGHT));
+            assert_eq!((game.droid.x, game.droid.y), (x, y));
+        }
+        let mut falls = 0;
+        while game.droid.state == PlayerState::GroundPound {
+            assert!(game.shockwave.is_none());
+            game.update(&Input::new(Button::RIGHT, Button::RIGHT));
+            falls += 1;
+            assert_eq!(game.droid.x, x);
+        }
+        /* 地面を越えたティックに着地する */
+        assert_eq!(falls, (GROUND_Y - y) / 8 + 1);
+        assert_eq!(
+            (game.droid.state, game.droid.y),
+            (PlayerState::Idle, GROUND_Y)
+        );
+        let shockwave = game.shockwave.as_ref().expect("no shockwave");
+        assert_eq!((shockwave.x, shockwave.y), (x + DROID_WIDTH / 2, GROUND_Y));
+        for _ in 0..SHOCKWAVE_TICKS {
+            game.update(&Input::default());
+        }
+        assert!(game.shockwave.is_none());
+    }
+
+    // 歩いて足場から落ちたときは、頂上と同じ速さでも DOWN はヒップドロップにならずに急降下になる。
+    #[test_case]
+    fn down_after_walking_off_a_ledge_fast_falls(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        game.droid.x = game.apple.x + game.apple.half_width;
+        game.droid.y = game.apple.top_y();
+        game.droid.vx = game.tuning.ground.speed;
+        game.update(&Input::new(Button::RIGHT, Button::RIGHT));
+        assert_eq!(game.droid.state, PlayerState::Airborne { double: false });
+        assert!(game.droid.vy.abs() <= game.tuning.ground_pound_apex);
+        game.update(&Input::new(Button::DOWN, Button::RIGHT));
+        assert_eq!(game.droid.state, PlayerState::Airborne { double: false });
+        game.update(&Input::new(Button::DOWN, Button::DOWN));
+        assert!(game.droid.fast_falling);
+        while game.droid.state != PlayerState::Idle {
+            assert_ne!(game.droid.state, PlayerState::GroundPound);
+            game.update(&Input::new(Button::DOWN, Button::DOWN.complement()));
+        }
+        assert!(game.shockwave.is_none());
+    }
+
+    #[test_case]
+    fn a_hard_run_starts_weaker_and_takes_more_damage(_gba: &mut agb::Gba) {
+        let mut easy = Game::new(Difficulty::Easy, 1);
+        let mut hard = Game::new(Difficulty::Hard, 1);
+        assert_eq!(easy.health, 5);
+        assert_eq!(hard.health, 1);
+        for game in [&mut easy, &mut hard] {
+            game.push_event(Event::PlayerDamaged {
+                amount: 1,
+                from_x: 0,
+            });
+            game.update(&Input::default());
+        }
+        assert_eq!(easy.health, 4);
+        assert_eq!(hard.health, 0);
+    }
+
+    // HARD モードの修飾は、それぞれを読む仕組みだけが効かせる。
+    #[test_case]
+    fn hard_modifiers_reach_their_systems(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Easy, 1).with_modifiers(Modifiers::HARD);
+        assert_eq!(game.apple.x_range(), 151..=169);
+        assert_eq!(game.par().gold, LEVEL_PAR.gold * 3 / 4);
+        /* 跳んでから UP を押しても二段ジャンプにならない */
+        game.update(&Input::new(Button::UP, Button::empty()));
+        for _ in 0..JUMP_SQUAT_TICKS + 2 {
+            game.update(&Input::default());
+        }
+        assert_eq!(game.droid.state, PlayerState::Airborne { double: false });
+        game.update(&Input::new(Button::UP, Button::empty()));
+        assert_eq!(game.droid.state, PlayerState::Airborne { double: false });
+        /* 体力が多くても 1 回で尽きる */
+        game.push_event(Event::PlayerDamaged {
+            amount: 1,
+            from_x: 0,
+        });
+        game.update(&Input::default());
+        assert_eq!(game.health, 0);
+    }
+
+    // 無敵時間はダメージを受けたティックから INVULNERABLE_TICKS 後にちょうど切れる。
+    #[test_case]
+    fn hits_during_invulnerability_are_ignored(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Easy, 1);
+        let hit = Event::PlayerDamaged {
+            amount: 1,
+            from_x: 0,
+        };
+        game.push_event(hit);
+        game.update(&Input::default());
+        assert_eq!(game.health, 4);
+        for _ in 1..INVULNERABLE_TICKS {
+            game.push_event(hit);
+            game.update(&Input::default());
+        }
+        assert_eq!(game.health, 4);
+        game.push_event(hit);
+        game.update(&Input::default());
+        assert_eq!(game.health, 3);
+    }
+
+    // りんごから歩いて落ちてから coyote_ticks ティックの間は、UP で地面からと同じように跳べる。
+    // その後は今まで通り二段ジャンプ (切っていれば何も起きない) になる。
+    #[test_case]
+    fn jumps_are_accepted_just_after_walking_off(_gba: &mut agb::Gba) {
+        for modifiers in [Modifiers::NONE, Modifiers::HARD] {
+            let coyote = Tuning::NORMAL.coyote_ticks;
+            for delay in 0..coyote + 2 {
+                let mut game = Game::new(Difficulty::Normal, 1).with_modifiers(modifiers);
+                game.droid.x = game.apple.x + game.apple.half_width;
+                game.droid.y = game.apple.top_y();
+                game.droid.vx = game.tuning.ground.speed;
+                game.update(&Input::new(Button::RIGHT, Button::RIGHT));
+                assert_eq!(game.droid.state, PlayerState::Airborne { double: false });
+                for _ in 0..delay {
+                    game.update(&Input::default());
+                }
+                game.update(&Input::new(Button::UP, Button::empty()));
+                let expected = if delay < coyote {
+                    PlayerState::JumpSquat { double: false }
+                } else if modifiers.no_double_jump {
+                    PlayerState::Airborne { double: false }
+                } else {
+                    PlayerState::JumpSquat { double: true }
+                };
+                assert_eq!(game.droid.state, expected, "{modifiers:?}, delay {delay}");
+                if !matches!(expected, PlayerState::JumpSquat { double: false }) {
+                    continue;
+                }
+                /* 地面から跳んだときと同じ速さで跳び、二段ジャンプも残っている */
+                while matches!(game.droid.state, PlayerState::JumpSquat { double: false }) {
+                    game.update(&Input::default());
+                }
+                assert_eq!(game.droid.vy, Tuning::NORMAL.jump_speed);
+                game.update(&Input::new(Button::UP, Button::empty()));
+                assert_eq!(
+                    game.droid.state,
+                    if modifiers.no_double_jump {
+                        PlayerState::Airborne { double: false }
+                    } else {
+                        PlayerState::JumpSquat { double: true }
+                    }
+                );
+            }
+        }
+        /* 0 にすると受け付けない */
+        let mut game = Game::new(Difficulty::Normal, 1);
+        game.tuning = Tuning {
+            coyote_ticks: 0,
+            ..Tuning::NORMAL
+        };
+        game.droid.x = game.apple.x + game.apple.half_width;
+        game.droid.y = game.apple.top_y();
+        game.droid.vx = game.tuning.ground.speed;
+        game.update(&Input::new(Button::RIGHT, Button::RIGHT));
+        game.update(&Input::new(Button::UP, Button::RIGHT));
+        assert_eq!(game.droid.state, PlayerState::JumpSquat { double: true });
+    }
+
+    // 二段ジャンプにならない UP (二段ジャンプの後か、二段ジャンプを切っているとき) を
+    // 着地の jump_buffer_ticks ティック前までに押せば、着地したティックに跳び始める。
+    #[test_case]
+    fn a_jump_pressed_just_before_landing_fires_on_landing(_gba: &mut agb::Gba) {
+        let buffer = Tuning::NORMAL.jump_buffer_ticks;
+        for modifiers in [Modifiers::NONE, Modifiers::HARD] {
+            let mut airborne = Game::new(Difficulty::Normal, 1).with_modifiers(modifiers);
+            airborne.update(&Input::new(Button::UP, Button::empty()));
+            while !matches!(
+                airborne.droid.state,
+                PlayerState::Airborne { double: false }
+            ) {
+                airborne.update(&Input::default());
+            }
+            if !modifiers.no_double_jump {
+                airborne.update(&Input::new(Button::UP, Button::empty()));
+                while !matches!(airborne.droid.state, PlayerState::Airborne { double: true }) {
+                    airborne.update(&Input::default());
+                }
+            }
+            /* 何もしなければ何ティック後に着地するか */
+            let mut probe = airborne.clone();
+            let mut landing = 0;
+            while probe.droid.state != PlayerState::Idle {
+                probe.update(&Input::default());
+                landing += 1;
+            }
+            for early in 0..buffer + 2 {
+                let mut game = airborne.clone();
+                for _ in 1..landing - early {
+                    game.update(&Input::default());
+                }
+                game.update(&Input::new(Button::UP, Button::empty()));
+                for _ in 0..early {
+                    game.update(&Input::default());
+                }
+                let expected = if early < buffer {
+                    PlayerState::JumpSquat { double: false }
+                } else {
+                    PlayerState::Idle
+                };
+                assert_eq!(game.droid.state, expected, "{modifiers:?}, {early} early");
+                assert_eq!(game.droid.y, GROUND_Y);
+            }
+        }
+    }
+
+    // レベルの壁のタイルでは歩いて止まり、跳べば越えられる。画面端では回り込んだまま。
+    #[test_case]
+    fn level_walls_block_walking_but_not_jumping(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        let column = LEVEL_WALLS.iter().position(|&height| height > 0).unwrap();
+        let wall_right = (column as i32 + 1) * 8;
+        for _ in 0..200 {
+            game.update(&Input::new(Button::LEFT, Button::LEFT));
+        }
+        assert_eq!((game.droid.x, game.droid.y), (wall_right, GROUND_Y));
+        game.update(&Input::new(Button::LEFT | Button::UP, Button::LEFT));
+        while game.droid.state != PlayerState::Idle {
+            game.update(&Input::new(
+                Button::LEFT | Button::UP,
+                Button::LEFT | Button::UP,
+            ));
+        }
+        assert!(game.droid.x < wall_right - 8, "x = {}", game.droid.x);
+        /* 壁の向こうは画面端で回り込む */
+        for _ in 0..100 {
+            game.update(&Input::new(Button::LEFT, Button::LEFT));
+        }
+        assert!(game.droid.x > wall_right, "x = {}", game.droid.x);
+    }
+
+    // 壁のあるレベルでは壁を越えられず、空中で壁に向かって UP を押すと反対へ蹴り出して跳ぶ。
+    #[test_case]
+    fn wall_jumps_kick_away_from_the_wall(_gba: &mut agb::Gba) {
+        let tuning = Tuning::NORMAL;
+        for (toward, wall_x, away) in [
+            (Button::LEFT, 0, 1),
+            (Button::RIGHT, WIDTH - DROID_WIDTH, -1),
+        ] {
+            let mut game = Game::new(Difficulty::Normal, 1);
+            game.solid_edges = true;
+            /* 壁ジャンプは画面端の壁でだけ。レベルの壁のタイルと左端の水は除いておく */
+            let mut level = Level::load(false);
+            level.walls = [0; GROUND_COLUMNS];
+            game.collision_map = CollisionMap::new(&level);
+            game.waters = Arena::new();
+            for _ in 0..200 {
+                game.update(&Input::new(toward, toward));
+            }
+            assert_eq!(game.droid.x, wall_x);
+            let jump = Button::UP | toward;
+            game.update(&Input::new(jump, toward));
+            while !matches!(game.droid.state, PlayerState::Airborne { double: false }) {
+                game.update(&Input::new(jump, jump));
+            }
+            assert_eq!(game.droid.x, wall_x);
+            /* 一度離してから壁に向かったまま押す */
+            game.update(&Input::new(toward, jump));
+            game.update(&Input::new(jump, toward));
+            assert_eq!(game.droid.state, PlayerState::Airborne { double: false });
+            assert_eq!(game.droid.vy, tuning.jump_speed);
+            assert_eq!(game.droid.hflip, toward == Button::RIGHT);
+            for tick in 1..=tuning.wall_kick_ticks as i32 {
+                game.update(&Input::new(jump, jump));
+                assert_eq!(game.droid.x, wall_x + away * tick * tuning.wall_kick_speed);
+            }
+            /* 蹴り出しが終われば、押している方へ速度が落ちていく */
+            game.update(&Input::new(jump, jump));
+            assert_eq!(
+                game.droid.vx,
+                Fixed::new(away * tuning.wall_kick_speed) - tuning.air.decel * away
+            );
+        }
+    }
+
+    // 落ちている間に壁へ押していると、max_wall_slide_speed でずり落ち、そこから壁ジャンプできる。
+    // 押すのをやめれば普通に落ちる。
+    #[test_case]
+    fn pushing_into_a_wall_slides_down_it(_gba: &mut agb::Gba) {
+        let tuning = Tuning::NORMAL;
+        let mut game = Game::new(Difficulty::Normal, 1);
+        game.solid_edges = true;
+        /* 左端の水の中では泳いでしまうので、水を抜いておく */
+        game.waters = Arena::new();
+        game.droid.x = 0;
+        let jump = Button::UP | Button::LEFT;
+        game.update(&Input::new(jump, Button::empty()));
+        while game.droid.vy >= Fixed::new(0) {
+            game.update(&Input::new(jump, jump));
+        }
+        for _ in 0..30 {
+            game.update(&Input::new(Button::LEFT, Button::LEFT));
+            assert!(game.droid.wall_sliding);
+            assert_eq!(game.droid.pose(), 26);
+            assert!(game.droid.vy >= -tuning.max_wall_slide_speed);
+        }
+        assert_eq!(
+            (game.droid.x, game.droid.state),
+            (0, PlayerState::Airborne { double: false })
+        );
+        game.update(&Input::new(jump, Button::LEFT));
+        assert_eq!(game.droid.vy, tuning.jump_speed);
+        assert!(!game.droid.wall_sliding);
+        while game.droid.vy >= Fixed::new(0) {
+            game.update(&Input::default());
+        }
+        game.update(&Input::default());
+        assert!(!game.droid.wall_sliding);
+    }
+
+    #[test_case]
+    fn dashes_last_their_ticks_and_then_cool_down(_gba: &mut agb::Gba) {
+        let tuning = Tuning::NORMAL;
+        let mut game = Game::new(Difficulty::Normal, 1);
+        game.droid.hflip = false;
+        game.update(&Input::new(Button::B, Button::empty()));
+        assert_eq!(game.droid.state, PlayerState::Dash);
+        assert_eq!(game.droid.pose(), 12);
+        let x = game.droid.x;
+        for tick in 1..=tuning.dash_ticks as i32 {
+            assert_eq!(game.droid.state, PlayerState::Dash);
+            game.update(&Input::default());
+            assert_eq!(game.droid.x, move_x(x, tick * 3));
+        }
+        /* 終われば立った状態に戻り、歩く速さから止まっていく */
+        assert_eq!(game.droid.state, PlayerState::Idle);
+        assert_eq!(game.droid.vx, tuning.ground.speed);
+        /* 始めてから dash_cooldown_ticks 経つまでは出せない */
+        for _ in (tuning.dash_ticks + 1)..tuning.dash_cooldown_ticks {
+            game.update(&Input::new(Button::B, Button::empty()));
+            assert_eq!(game.droid.state, PlayerState::Idle);
+        }
+        game.update(&Input::new(Button::B, Button::empty()));
+        assert_eq!(game.droid.state, PlayerState::Dash);
+    }
+
+    // 地上のダッシュからはしゃがまずに跳び、押している間はダッシュの速さがゆっくりしか落ちない。
+    #[test_case]
+    fn dash_jumps_keep_their_momentum(_gba: &mut agb::Gba) {
+        let tuning = Tuning::NORMAL;
+        let mut game = Game::new(Difficulty::Normal, 1);
+        game.update(&Input::new(Button::B | Button::RIGHT, Button::empty()));
+        game.update(&Input::new(Button::RIGHT, Button::B | Button::RIGHT));
+        game.update(&Input::new(Button::UP | Button::RIGHT, Button::RIGHT));
+        assert_eq!(game.droid.state, PlayerState::Airborne { double: false });
+        assert_eq!(game.droid.vy, tuning.jump_speed);
+        assert_eq!(game.droid.vx, tuning.dash_speed);
+        game.update(&Input::new(
+            Button::UP | Button::RIGHT,
+            Button::UP | Button::RIGHT,
+        ));
+        assert_eq!(game.droid.vx, tuning.dash_speed - tuning.air.friction);
+    }
+
+    // 空中のダッシュは落ちながら進み、着地したところで終わる。
+    #[test_case]
+    fn landing_cancels_an_air_dash(_gba: &mut agb::Gba) {
+        let tuning = Tuning::NORMAL;
+        let mut game = Game::new(Difficulty::Normal, 1);
+        game.droid.state = PlayerState::Airborne { double: false };
+        game.droid.y = GROUND_Y - 4;
+        game.update(&Input::new(Button::B, Button::empty()));
+        assert_eq!(
+            (game.droid.state, game.droid.dash_from),
+            (PlayerState::Dash, PlayerState::Airborne { double: false })
+        );
+        let mut ticks = 0;
+        while game.droid.state == PlayerState::Dash {
+            game.update(&Input::default());
+            ticks += 1;
+        }
+        assert!(ticks < tuning.dash_ticks, "landed after {ticks} ticks");
+        assert_eq!(
+            (game.droid.state, game.droid.y),
+            (PlayerState::Idle, GROUND_Y)
+        );
+        assert!(!game.droid.dash.is_running());
+        assert_eq!(game.droid.vx.abs(), tuning.ground.speed);
+    }
+
+    // しゃがんでいる間は頭の高さのコインに届かず、立ち上がれば取れる。
+    #[test_case]
+    fn crouching_lowers_the_hitbox(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        game.coins = Arena::new();
+        let x = game.droid.x;
+        game.spawn(EntityKind::Coin)
+            .at((x, GROUND_Y))
+            .build()
+            .unwrap();
+        game.droid.state = PlayerState::Crouch;
+        game.update(&Input::new(Button::DOWN, Button::DOWN));
+        assert_eq!(game.droid.state, PlayerState::Crouch);
+        assert_eq!(game.droid.pose(), 14);
+        assert_eq!(game.coins.len(), 1);
+        game.update(&Input::new(Button::empty(), Button::DOWN));
+        assert_eq!(game.droid.state, PlayerState::Idle);
+        assert!(game.coins.is_empty());
+    }
+
+    // 止まっていればしゃがむだけ、走っていればスライディングで滑ってからしゃがむ。
+    #[test_case]
+    fn down_crouches_or_slides(_gba: &mut agb::Gba) {
+        let tuning = Tuning::NORMAL;
+        let mut game = Game::new(Difficulty::Normal, 1);
+        game.update(&Input::new(Button::DOWN, Button::empty()));
+        assert_eq!(game.droid.state, PlayerState::Crouch);
+        /* しゃがんだままでは歩けない */
+        let x = game.droid.x;
+        game.update(&Input::new(Button::DOWN | Button::RIGHT, Button::DOWN));
+        assert_eq!((game.droid.state, game.droid.x), (PlayerState::Crouch, x));
+        game.update(&Input::new(Button::empty(), Button::DOWN));
+        assert_eq!(game.droid.state, PlayerState::Idle);
+
+        game.droid.vx = tuning.ground.speed;
+        game.update(&Input::new(Button::RIGHT | Button::DOWN, Button::RIGHT));
+        assert_eq!(game.droid.state, PlayerState::Slide);
+        assert_eq!(game.droid.vx, tuning.slide_speed);
+        let x = game.droid.x;
+        let mut ticks = 0;
+        while game.droid.state == PlayerState::Slide {
+            game.update(&Input::new(Button::DOWN, Button::DOWN));
+            ticks += 1;
+        }
+        /* slide_speed から slide_friction ずつ落ちて、ちょうど 0 になったティックで止まる */
+        let friction = tuning.slide_friction.to_raw();
+        assert_eq!(ticks, tuning.slide_speed.to_raw() / friction);
+        assert_eq!(game.droid.state, PlayerState::Crouch);
+        assert_ne!(game.droid.x, x);
+    }
+
+    // スライディングから跳ぶと、滑っていた速さのまま飛んでいく。
+    #[test_case]
+    fn slide_jumps_keep_their_momentum(_gba: &mut agb::Gba) {
+        let tuning = Tuning::NORMAL;
+        let mut game = Game::new(Difficulty::Normal, 1);
+        game.droid.vx = -tuning.ground.speed;
+        game.update(&Input::new(Button::DOWN, Button::empty()));
+        assert_eq!(game.droid.state, PlayerState::Slide);
+        game.update(&Input::new(Button::UP, Button::DOWN));
+        assert_eq!(game.droid.state, PlayerState::Airborne { double: false });
+        assert_eq!(game.droid.vy, tuning.jump_speed);
+        assert_eq!(game.droid.vx, -tuning.slide_speed);
+    }
+
+    // 丘を歩いて越えると、上りも下りも足元の地面の高さに沿い、浮いたり落ちたりしない。
+    #[test_case]
+    fn walking_over_the_hill_follows_the_slopes(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        /* 上から見た地面の高さを比べるので、丘の上に浮いているブロックは除いておく */
+        let mut level = Level::load(false);
+        level.blocks = [None; GROUND_COLUMNS];
+        game.collision_map = CollisionMap::new(&level);
+        let floor = |game: &Game| game.collision_map.floor_y(game.droid.x, 0);
+        game.droid.x = 160;
+        let mut peak = GROUND_Y;
+        for (button, dir, until) in [(Button::RIGHT, 1, 224), (Button::LEFT, -1, 160)] {
+            while (until - game.droid.x) * dir > 0 {
+                game.update(&Input::new(button, Button::empty()));
+                assert_eq!(game.droid.state, PlayerState::Idle, "x = {}", game.droid.x);
+                assert_eq!(game.droid.y, floor(&game));
+                peak = peak.min(game.droid.y);
+            }
+        }
+        assert_eq!(peak, GROUND_Y - 8);
+        /* 坂の途中で跳んでも、落ちてきたところの坂に着地する */
+        game.droid.x = 180;
+        game.droid.y = floor(&game);
+        game.update(&Input::new(Button::UP, Button::empty()));
+        for _ in 0..120 {
+            game.update(&Input::new(Button::RIGHT, Button::empty()));
+            if game.droid.state == PlayerState::Idle {
+                break;
+            }
+        }
+        assert_eq!(game.droid.state, PlayerState::Idle);
+        assert!(game.droid.y < GROUND_Y);
+        assert_eq!(game.droid.y, floor(&game));
+    }
+
+    // UP を押したティックから数えて JUMP_SQUAT_TICKS 回目の次で跳ぶ。
+    #[test_case]
+    fn the_jump_squat_lasts_exactly_its_ticks(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        game.update(&Input::new(Button::UP, Button::empty()));
+        for _ in 1..JUMP_SQUAT_TICKS {
+            game.update(&Input::default());
+            assert_eq!(game.droid.state, PlayerState::JumpSquat { double: false });
+        }
+        game.update(&Input::default());
+        assert_eq!(game.droid.state, PlayerState::Airborne { double: false });
+        assert_eq!(game.droid.y, GROUND_Y);
+    }
+
+    // カウントダウンの間は動けず、タイムも進まない。ダメージは体力ではなくタイムに付く。
+    #[test_case]
+    fn time_attack_counts_down_and_penalises_time(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1).with_mode(Mode::TimeAttack);
+        let x = game.droid.x;
+        for _ in 0..COUNTDOWN_TICKS {
+            game.update(&Input::new(Button::RIGHT, Button::RIGHT));
+        }
+        assert_eq!(
+            (game.droid.x, game.droid.vx, game.time),
+            (x, Fixed::new(0), 0)
+        );
+        /* 終わったティックから動き出す */
+        game.update(&Input::new(Button::RIGHT, Button::RIGHT));
+        assert_eq!((game.droid.vx, game.time), (game.tuning.ground.accel, 1));
+
+        game.push_event(Event::PlayerDamaged {
+            amount: 1,
+            from_x: 0,
+        });
+        game.update(&Input::default());
+        assert_eq!(game.time, 2 + PENALTY_TICKS);
+        assert_eq!(game.health, Difficulty::Normal.settings().lives);
+        assert_eq!(game.outcome(), None);
+    }
+
+    // コインを全て取ったらタイムが止まる。
+    #[test_case]
+    fn clearing_the_level_stops_the_clock(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        let coins: alloc::vec::Vec<_> = game.coins.iter().map(|(id, _)| id).collect();
+        for coin in coins {
+            game.push_event(Event::CoinCollected { coin });
+        }
+        game.update(&Input::default());
+        assert_eq!(game.outcome(), Some(Outcome::Cleared));
+        let time = game.time;
+        game.update(&Input::default());
+        assert_eq!(game.time, time);
+    }
+}
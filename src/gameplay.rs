@@ -0,0 +1,627 @@
+// ゲーム本編のシーン。START でポーズ。
+// 交代で遊ぶモードでは、番が移るたびに GetReadyScene を積む。
+// 通信対戦では毎ティック Race で相手とやりとりし、勝負がついたら結果画面へ。
+// タイムアタックでは入力を記録しておき、最高タイムを更新したらゴーストとして保存する。
+// スピードランではポーズするたびにタイムのペナルティを足す。
+// チュートリアルを終えるか飛ばしたら、そのままレベル 1 (普通のモード) のシーンに替える。
+// 普通のモードでは、始める前に導入のカットシーン (cutscene::INTRO) を流す。
+// カットシーンを流している間は、入力の代わりにカットシーンが押したことにした入力で進める。
+
+use alloc::boxed::Box;
+
+use agb::{
+    display::{tiled::VRamManager, Priority},
+    input::Button,
+};
+
+use crate::{
+    cutscene::{self, Cutscene},
+    difficulty::Difficulty,
+    game::Outcome,
+    get_ready::GetReadyScene,
+    gfx::{rgb5, tiles},
+    ghost::Ghost,
+    input::Input,
+    layer::{Layer, Tile},
+    level::{Level, GROUND_COLUMNS},
+    mode::Mode,
+    modifiers::Modifiers,
+    pause::PauseScene,
+    presenter::Presenter,
+    race::Race,
+    replay::Replay,
+    results::{PlayerResult, Results, ResultsScene},
+    save::BestTime,
+    scene::{Resources, Scene, Transition},
+    speedrun::Speedrun,
+    switch::BlockSet,
+    terrain::Terrain,
+    text::{self, TextLayer},
+    turns::{TurnEnd, Turns},
+};
+
+pub struct GameScene<'g> {
+    turns: Turns,
+    /* 通信対戦のときだけ */
+    race: Option<Race>,
+    /* タイムアタックとゴーストとの競争のときだけ */
+    recording: Option<Replay>,
+    ghost: Option<Ghost>,
+    /* チュートリアルのときだけ、続けて遊ぶレベル 1 の修飾と、SELECT で飛ばせるか */
+    level_one: Option<Modifiers>,
+    skippable: bool,
+    /* 流しているカットシーン */
+    cutscene: Option<Cutscene>,
+    presenter: Option<Presenter<'g>>,
+}
+
+impl GameScene<'_> {
+    pub fn new(difficulty: Difficulty, mode: Mode, modifiers: Modifiers, seed: u32) -> Self {
+        let recording = matches!(mode, Mode::TimeAttack | Mode::GhostRace)
+            .then(|| Replay::new(difficulty, mode, modifiers, seed));
+        let mut turns = Turns::new(difficulty, mode, modifiers, seed);
+        if mode == Mode::Normal {
+            turns.game_mut().cutscene = Some(cutscene::INTRO);
+        }
+        Self {
+            turns,
+            race: None,
+            recording,
+            ghost: None,
+            level_one: None,
+            skippable: false,
+            cutscene: None,
+            presenter: None,
+        }
+    }
+
+    // チュートリアルを遊んでからレベル 1 を modifiers で遊ぶ。
+    // チュートリアル自体は修飾を掛けない。skippable なら SELECT で飛ばせる。
+    pub fn tutorial(
+        difficulty: Difficulty,
+        modifiers: Modifiers,
+        seed: u32,
+        skippable: bool,
+    ) -> Self {
+        Self {
+            level_one: Some(modifiers),
+            skippable,
+            ..Self::new(difficulty, Mode::Tutorial, Modifiers::NONE, seed)
+        }
+    }
+
+    // チュートリアルの後のレベル 1 。
+    fn level_one<'g>(&self, modifiers: Modifiers) -> Transition<'g> {
+        let game = self.turns.game();
+        Transition::Replace(Box::new(GameScene::new(
+            game.difficulty,
+            Mode::Normal,
+            modifiers,
+            game.seed,
+        )))
+    }
+
+    // replay のゴーストと競う。難易度、修飾、シードは replay に合わせる。
+    pub fn ghost(replay: Replay) -> Self {
+        Self {
+            ghost: Some(Ghost::new(replay.clone())),
+            ..Self::new(
+                replay.difficulty,
+                Mode::GhostRace,
+                replay.modifiers,
+                replay.seed,
+            )
+        }
+    }
+
+    // 握手を済ませた race で通信対戦をする。
+    pub fn race(difficulty: Difficulty, modifiers: Modifiers, seed: u32, race: Race) -> Self {
+        Self {
+            race: Some(race),
+            ..Self::new(difficulty, Mode::LinkRace, modifiers, seed)
+        }
+    }
+
+    // 終わったランを記録して結果をまとめる。outcome は最後に終わったプレイヤーのもの。
+    fn finish(&mut self, res: &mut Resources, outcome: Outcome) -> Results {
+        let game = self.turns.game();
+        let records = &mut res.save.records;
+        let hard = game.modifiers.is_hard();
+        let mut players = None;
+        let (medal, new_best) = match (game.mode, outcome) {
+            (Mode::Normal, _) => (None, records.record(game.difficulty, game.score, hard)),
+            (Mode::TimeAttack | Mode::GhostRace, Outcome::Cleared) => {
+                let medal = game.par().medal(game.time);
+                let time = BestTime {
+                    ticks: game.time,
+                    medal,
+                };
+                let mirror = game.modifiers.mirror;
+                (
+                    medal,
+                    records.record_time(game.difficulty, mirror, time, hard),
+                )
+            }
+            (Mode::TimeAttack | Mode::GhostRace | Mode::Speedrun, Outcome::Died) => (None, false),
+            /* ミラーモードはレベルの順が違うので記録しない */
+            (Mode::Speedrun, Outcome::Cleared) => {
+                let splits = game.speedrun.as_ref().and_then(Speedrun::splits);
+                match splits {
+                    Some(splits) if !game.modifiers.mirror => {
+                        (None, records.record_speedrun(game.difficulty, splits, hard))
+                    }
+                    _ => (None, false),
+                }
+            }
+            /* 通信対戦と練習は記録しない */
+            (Mode::LinkRace | Mode::Practice | Mode::Tutorial, _) => (None, false),
+            (Mode::CoinRush, _) => (
+                None,
+                records.record_rush(game.difficulty, game.rush_score(), hard),
+            ),
+            (Mode::Endless, _) => (
+                None,
+                records.record_climb(game.difficulty, game.climb_score(), game.seed, hard),
+            ),
+            /* 2 人とも別々に最高得点と比べる */
+            (Mode::HotSeat, _) => {
+                let mut results = [PlayerResult {
+                    score: 0,
+                    outcome: Outcome::Died,
+                    new_best: false,
+                }; 2];
+                for ((result, game), outcome) in results
+                    .iter_mut()
+                    .zip(self.turns.games())
+                    .zip(self.turns.outcomes())
+                {
+                    *result = PlayerResult {
+                        score: game.score,
+                        outcome: outcome.unwrap_or(Outcome::Died),
+                        new_best: records.record(game.difficulty, game.score, hard),
+                    };
+                }
+                players = Some(results);
+                (None, results.iter().any(|result| result.new_best))
+            }
+        };
+        log_info!(
+            "{:?} run {:?}: score {}, time {}, medal {:?}",
+            game.mode,
+            outcome,
+            game.score,
+            game.time,
+            medal
+        );
+        /* 誰かがレベルをクリアしたら練習モードが選べるようになる */
+        let cleared =
+            outcome == Outcome::Cleared || self.turns.outcomes().contains(&Some(Outcome::Cleared));
+        let first_clear = cleared && records.record_clear();
+        if new_best || first_clear {
+            save(res);
+        }
+        /* 記録していたのはタイムアタックだけなので、最高タイムを更新したということ。
+        ゴーストは難易度ごとに 1 つなので、ミラーモードの記録では作らない */
+        let recording = self
+            .recording
+            .take()
+            .filter(|replay| !replay.modifiers.mirror);
+        if let (true, Some(replay)) = (new_best, recording) {
+            match res.save.save_ghost(replay) {
+                Ok(()) => log_info!("ghost saved"),
+                Err(error) => log_warn!("failed to save the ghost: {:?}", error),
+            }
+        }
+        Results {
+            mode: game.mode,
+            difficulty: game.difficulty,
+            outcome,
+            score: match game.mode {
+                Mode::CoinRush => game.rush_score(),
+                _ => game.climb_score(),
+            },
+            height: game.climb.as_ref().map_or(0, |climb| climb.height),
+            time: game.time,
+            medal,
+            new_best,
+            players,
+            race: None,
+            hard,
+            par: game.par(),
+            seed: game.seed,
+            mirror: game.modifiers.mirror,
+            splits: game.speedrun.as_ref().and_then(Speedrun::splits),
+            comparison: game.speedrun.as_ref().and_then(Speedrun::comparison),
+        }
+    }
+}
+
+fn save(res: &mut Resources) {
+    match res.save.save() {
+        Ok(()) => log_info!("records saved"),
+        Err(error) => log_warn!("failed to save the records: {:?}", error),
+    }
+}
+
+// 地面に使うタイル (tiles::bg.tile_settings の添字)。エンドレスの岩棚にも使う。
+pub const GROUND_LEFT: usize = 5 * 32;
+pub const GROUND_TOP: usize = 1 + 5 * 32;
+pub const GROUND_RIGHT: usize = 2 + 5 * 32;
+pub const GROUND_FILL: usize = 3 + 5 * 32;
+/* 坂 (terrain モジュール)。下り坂は左右反転して使う */
+pub const SLOPE_45: usize = 4 + 5 * 32;
+pub const SLOPE_22_LOW: usize = 5 + 5 * 32;
+pub const SLOPE_22_HIGH: usize = 6 + 5 * 32;
+/* 氷の表面 (terrain::Material::Ice)。平らな列に置く */
+pub const GROUND_ICE: usize = 7 + 5 * 32;
+/* 跳ねるゴムの表面 (terrain::Material::Bouncy)。平らな列に置く */
+pub const GROUND_BOUNCY: usize = 8 + 5 * 32;
+/*
+ * ベルトコンベアの表面 (conveyor モジュール)。右へ流れる絵のコマが 2 ドットずつずれて並んでいて、
+ * 左へ流れるものは左右反転して使う。置くときは最初のコマ (GROUND_CONVEYOR)
+ */
+pub const CONVEYOR_TILES: [usize; 4] = [9 + 5 * 32, 10 + 5 * 32, 11 + 5 * 32, 12 + 5 * 32];
+pub const GROUND_CONVEYOR: usize = CONVEYOR_TILES[0];
+/* 壊せるブロック (block モジュール)。地面のタイルではないが、壊れるまでは固い */
+pub const BLOCK: usize = 13 + 5 * 32;
+/* スイッチで固さの入れ替わるブロック (switch モジュール)。A と B の組ごとに、固いときの塗りと通り抜けられるときの枠 */
+pub const SWITCH_BLOCK_TILES: [[usize; 2]; 2] =
+    [[14 + 5 * 32, 15 + 5 * 32], [16 + 5 * 32, 17 + 5 * 32]];
+/* スイッチの絵の下半分 (左、右)。上がっているときと踏まれているとき。上半分は空なので描かない */
+pub const SWITCH_TILES: [[usize; 2]; 2] = [[18 + 5 * 32, 19 + 5 * 32], [20 + 5 * 32, 21 + 5 * 32]];
+/* 鍵と、扉 (上、下) (door モジュール) */
+pub const KEY: usize = 24 + 5 * 32;
+pub const DOOR_TILES: [usize; 2] = [22 + 5 * 32, 23 + 5 * 32];
+/* はしご (ladder モジュール)。縦に並べてつなぐ */
+pub const LADDER: usize = 25 + 5 * 32;
+/* 水 (water モジュール)。水面と、その下に敷き詰めるもの */
+pub const WATER_TILES: [usize; 2] = [26 + 5 * 32, 27 + 5 * 32];
+/* 風の粒 (wind モジュール)。右へ流れる絵と上へ流れる絵 */
+pub const STREAK_TILES: [usize; 2] = [28 + 5 * 32, 29 + 5 * 32];
+/* テレポーターの台 (左、右) (teleporter モジュール) */
+pub const TELEPORTER_TILES: [usize; 2] = [30 + 5 * 32, 31 + 5 * 32];
+/* 棘 (spike モジュール) */
+pub const SPIKE: usize = 6 * 32;
+/* 空の色 (背景のパレット 0 の色 0)。テレポーターで移ったときは presenter が一時的に白く光らせる */
+pub const SKY: u16 = rgb5(15, 15, 31);
+/* 地面の表面の行 */
+pub const GROUND_ROW: u16 = 17;
+/* 地面のタイル全て。当たり判定 (tilemap::Cell::of_tile) では坂以外は固い */
+pub const GROUND_TILES: [usize; 10] = [
+    GROUND_LEFT,
+    GROUND_TOP,
+    GROUND_RIGHT,
+    GROUND_FILL,
+    SLOPE_45,
+    SLOPE_22_LOW,
+    SLOPE_22_HIGH,
+    GROUND_ICE,
+    GROUND_BOUNCY,
+    GROUND_CONVEYOR,
+];
+
+// level の地面を並べた背景を作る。
+fn build_level<'g>(res: &mut Resources<'g>, level: &Level) -> Layer<'g> {
+    // https://www.coranac.com/tonc/text/regbg.htm#ssec-ctrl-bgs
+    let vram: &mut VRamManager = &mut res.vram;
+    /* 2 回目からは前のレベルの背景を使い回し、置いたセルだけを消して並べ直す */
+    let mut bg0 = match res.level_bg.take() {
+        Some(mut bg0) => {
+            bg0.clear_cells(vram);
+            bg0
+        }
+        None => Layer::persistent(res.gfx, Priority::P0),
+    };
+    vram.set_background_palettes(tiles::PALETTES);
+    vram.set_background_palette_colour(
+        0, // パレットバンク番号
+        0, // パレット内の色番号
+        SKY,
+    );
+
+    bg0.set_scroll_y(0);
+    lay_ground(&mut bg0, vram, level);
+    bg0.set_visible(true);
+    bg0
+}
+
+// level の 1 列に並べる地面のタイル。背景 (lay_ground) と当たり判定 (tilemap::CollisionMap) の両方がこれを使う。
+#[derive(Clone, Copy, Debug)]
+pub struct GroundColumn {
+    /* 表面の 1 段上の行 (GROUND_ROW - 1) のタイルと左右反転。平らな列には置かない */
+    pub slope: Option<(usize, bool)>,
+    /*
+     * 表面の行 (GROUND_ROW) のタイルと左右反転。これより下の行は全て GROUND_FILL 。
+     * ベルトコンベアの列は GROUND_CONVEYOR で、左へ流れるものは左右反転する
+     */
+    pub surface: (usize, bool),
+    /* 表面の行から上に積む GROUND_FILL の段数 (Level::walls) */
+    pub wall: usize,
+    /* 浮かべるブロックの行とタイル。スイッチのブロックは、始めに固い A の組を塗り、B の組を枠で置く */
+    pub block: Option<(u16, usize)>,
+}
+
+// level の column 列の地面。
+// 平らでない列 (Level::terrain) は、表面の 1 段上に坂などを置き、表面の行を中身で埋める。
+// ベルトコンベアの列 (Level::conveyors) は、表面をベルトにする。
+pub fn ground_column(level: &Level, column: usize) -> GroundColumn {
+    GroundColumn {
+        slope: level.terrain[column].tile(),
+        surface: surface(level, column),
+        wall: level.walls[column],
+        block: block(level, column),
+    }
+}
+
+// level の column 列の表面の行のタイルと左右反転 (GroundColumn::surface)。
+#[inline(always)]
+fn surface(level: &Level, column: usize) -> (usize, bool) {
+    /* Fixed の比較は ROM の関数を呼ぶので、生の値で比べる */
+    let speed = level.conveyors[column].to_raw();
+    if level.terrain[column] != Terrain::Flat {
+        (GROUND_FILL, false)
+    } else if speed != 0 {
+        (GROUND_CONVEYOR, speed < 0)
+    } else {
+        (level.ground[column], false)
+    }
+}
+
+// level の column 列に浮かべるブロック (GroundColumn::block)。
+#[inline(always)]
+fn block(level: &Level, column: usize) -> Option<(u16, usize)> {
+    match (level.blocks[column], level.switch_blocks[column]) {
+        (Some(row), _) => Some((row, BLOCK)),
+        (None, Some((row, set))) => Some((row, set.tile(matches!(set, BlockSet::A)))),
+        (None, None) => None,
+    }
+}
+
+// level の地面を bg に並べる。並べるものは ground_column と同じ。
+// レベルを読み込むたびに ROM から実行するので、列ごとに GroundColumn を作らず、行ごとに同じタイルが
+// 続くところを短いループで探して、まとめて VRAM のマップに書く。
+pub fn lay_ground(bg: &mut Layer, vram: &mut VRamManager, level: &Level) {
+    let tileset = &tiles::bg.tiles;
+    let fill = Tile::of(&tiles::bg, GROUND_FILL);
+    /* 画面の下端まで。エンドレスでスクロールしたときに上から回り込んで見えないよう、それより下には置かない */
+    bg.fill(
+        vram,
+        0..text::COLUMNS,
+        GROUND_ROW + 1..text::ROWS,
+        tileset,
+        fill,
+    );
+    /* 表面の 1 段上の行。平らなところは空白のまま */
+    let mut start = 0;
+    while start < GROUND_COLUMNS {
+        let terrain = level.terrain[start];
+        let end = run_end(start, |column| level.terrain[column] == terrain);
+        if let Some((index, hflip)) = terrain.tile() {
+            lay_run(bg, vram, start..end, GROUND_ROW - 1, index, hflip);
+        }
+        start = end;
+    }
+    /* 表面の行 */
+    /*
+     * 列ごとに surface を比べると重いので、元になる地形とベルトの速さと表面のタイルで比べる。
+     * 平らでない列どうしは、どれも中身で埋める
+     */
+    let mut start = 0;
+    while start < GROUND_COLUMNS {
+        let flat = level.terrain[start] == Terrain::Flat;
+        let speed = level.conveyors[start].to_raw();
+        let ground = level.ground[start];
+        let end = run_end(start, |column| {
+            if level.terrain[column] == Terrain::Flat {
+                flat && level.conveyors[column].to_raw() == speed && level.ground[column] == ground
+            } else {
+                !flat
+            }
+        });
+        let (index, hflip) = surface(level, start);
+        lay_run(bg, vram, start..end, GROUND_ROW, index, hflip);
+        start = end;
+    }
+    /* 壁と浮かべたブロックは、坂の行に重なっても上に置く */
+    let mut column = 0;
+    while column < GROUND_COLUMNS {
+        let wall = level.walls[column];
+        if wall == 0 && level.blocks[column].is_none() && level.switch_blocks[column].is_none() {
+            column += 1;
+            continue;
+        }
+        if wall > 0 {
+            bg.fill(
+                vram,
+                column as u16..column as u16 + 1,
+                GROUND_ROW - wall as u16..GROUND_ROW,
+                tileset,
+                fill,
+            );
+        }
+        if let Some((row, tile)) = block(level, column) {
+            let tile = Tile::of(&tiles::bg, tile);
+            bg.write_row(vram, (column as u16, row), tileset, &[Some(tile)]);
+        }
+        column += 1;
+    }
+}
+
+// start 列から、same な列が続くところの終わり (最初に same でない列か GROUND_COLUMNS)。
+#[inline(always)]
+fn run_end(start: usize, same: impl Fn(usize) -> bool) -> usize {
+    let mut end = start + 1;
+    while end < GROUND_COLUMNS && same(end) {
+        end += 1;
+    }
+    end
+}
+
+// row 行の columns に、bg のタイル index を並べる。
+fn lay_run(
+    bg: &mut Layer,
+    vram: &mut VRamManager,
+    columns: core::ops::Range<usize>,
+    row: u16,
+    index: usize,
+    hflip: bool,
+) {
+    bg.fill(
+        vram,
+        columns.start as u16..columns.end as u16,
+        row..row + 1,
+        &tiles::bg.tiles,
+        Tile::of(&tiles::bg, index).hflip(hflip),
+    );
+}
+
+impl<'g> Scene<'g> for GameScene<'g> {
+    fn enter(&mut self, res: &mut Resources<'g>) {
+        let game = self.turns.game_mut();
+        let mirror = game.modifiers.mirror;
+        if let Some(speedrun) = &mut game.speedrun {
+            if !mirror {
+                speedrun.set_comparison(res.save.records.best_speedrun(game.difficulty));
+            }
+        }
+        let bg = build_level(res, &game.level());
+        let hud = TextLayer::new(Layer::new(res.gfx, Priority::P0), &mut res.vram);
+        let mut presenter = Presenter::new(
+            res.oam,
+            res.sprites,
+            &mut res.vram,
+            bg,
+            hud,
+            self.turns.game(),
+        );
+        if self.skippable {
+            presenter.draw_skip_hint(&mut res.vram);
+        }
+        presenter.commit(res.oam, &mut res.vram);
+        self.presenter = Some(presenter);
+        log_info!("background ready");
+    }
+
+    fn update(&mut self, res: &mut Resources<'g>, input: &Input) -> Transition<'g> {
+        /* 通信対戦は相手を待たせられないのでポーズできない */
+        if input.is_just_pressed(Button::START) && self.race.is_none() {
+            self.turns.game_mut().pause_speedrun();
+            return Transition::Push(Box::new(PauseScene::new()));
+        }
+        if let Some(modifiers) = self.level_one {
+            if self.skippable && input.is_just_pressed(Button::SELECT) {
+                log_info!("tutorial skipped");
+                return self.level_one(modifiers);
+            }
+        }
+        #[cfg(feature = "debug-tools")]
+        {
+            let game = self.turns.game_mut();
+            game.noclip = res.debug.noclip;
+            if let Some(command) = res.debug.command {
+                game.run_command(command);
+            }
+            for (&knob, &steps) in crate::tuner::KNOBS.iter().zip(&res.debug.tweaks) {
+                if steps != 0 {
+                    game.tweak(knob, steps);
+                }
+            }
+        }
+        if let Some(race) = &mut self.race {
+            /* 自分の分が終わったら、止めて相手を待つ */
+            if !race.finished() {
+                self.turns.game_mut().update(input);
+            }
+            let Some(result) = race.update(self.turns.game()) else {
+                return Transition::Stay;
+            };
+            let outcome = self.turns.game().outcome().unwrap_or(Outcome::Died);
+            let mut results = self.finish(res, outcome);
+            results.race = Some(result);
+            return Transition::Replace(Box::new(ResultsScene::new(results)));
+        }
+        let game = self.turns.game_mut();
+        if let Some(number) = game.cutscene.take() {
+            self.cutscene = Cutscene::new(number);
+        }
+        let scripted = self
+            .cutscene
+            .as_mut()
+            .and_then(|cutscene| cutscene.update(game, input));
+        if scripted.is_none() {
+            self.cutscene = None;
+        }
+        let input = scripted.as_ref().unwrap_or(input);
+        /* リプレイではカットシーンを流さないので、押したことにした入力もそのまま記録する */
+        let input = match &mut self.recording {
+            Some(recording) => recording.record(input),
+            None => *input,
+        };
+        self.turns.game_mut().update(&input);
+        if let Some(ghost) = &mut self.ghost {
+            ghost.update(self.turns.game());
+        }
+        match self.turns.end_turn() {
+            TurnEnd::Continue => Transition::Stay,
+            TurnEnd::Pass { player } => Transition::Push(Box::new(GetReadyScene::new(player))),
+            TurnEnd::Finished => {
+                if let Some(modifiers) = self.level_one {
+                    if res.save.records.record_tutorial() {
+                        save(res);
+                    }
+                    return self.level_one(modifiers);
+                }
+                let outcome = self.turns.game().outcome().unwrap_or(Outcome::Died);
+                let results = self.finish(res, outcome);
+                Transition::Replace(Box::new(ResultsScene::new(results)))
+            }
+        }
+    }
+
+    fn draw(&mut self, res: &mut Resources<'g>) {
+        if let Some(presenter) = &mut self.presenter {
+            presenter.set_alpha(res.alpha);
+            presenter.draw(res.oam, &mut res.vram, self.turns.game());
+            if let Some(race) = &self.race {
+                presenter.draw_ghost(res.oam, race.ghost(res.alpha));
+            }
+            if let Some(ghost) = &self.ghost {
+                presenter.draw_ghost(res.oam, ghost.position(res.alpha));
+                presenter.draw_split(&mut res.vram, ghost.delta(self.turns.game()));
+            }
+            let game = self.turns.game();
+            if let Some(speedrun) = &game.speedrun {
+                presenter.draw_speedrun_split(&mut res.vram, speedrun.shown(game.time));
+            }
+            presenter.commit(res.oam, &mut res.vram);
+        }
+    }
+
+    fn exit(&mut self, res: &mut Resources<'g>) {
+        /* 途中でやめたときも、得点は最高得点として残す */
+        let records = &mut res.save.records;
+        let mut new_best = false;
+        for game in self.turns.games() {
+            let hard = game.modifiers.is_hard();
+            new_best |= match game.mode {
+                Mode::Normal | Mode::HotSeat => records.record(game.difficulty, game.score, hard),
+                Mode::TimeAttack
+                | Mode::GhostRace
+                | Mode::Speedrun
+                | Mode::CoinRush
+                | Mode::LinkRace
+                | Mode::Practice
+                | Mode::Tutorial => false,
+                Mode::Endless => {
+                    records.record_climb(game.difficulty, game.climb_score(), game.seed, hard)
+                }
+            };
+        }
+        if new_best {
+            save(res);
+        }
+        if let Some(presenter) = self.presenter.take() {
+            res.level_bg = Some(presenter.release(&mut res.vram));
+        }
+    }
+}
@@ -0,0 +1,52 @@
+// 交代で遊ぶモードで、次のプレイヤーに番が回ったときに出す画面。
+// ゲーム本編の上に積まれ、START か A で本編に戻る。
+
+use agb::{display::Priority, input::Button};
+
+use crate::{
+    input::Input,
+    layer::Layer,
+    scene::{Resources, Scene, Transition},
+    text::TextLayer,
+};
+
+pub struct GetReadyScene<'g> {
+    /* 0 始まり */
+    player: usize,
+    text: Option<TextLayer<'g>>,
+}
+
+impl GetReadyScene<'_> {
+    pub fn new(player: usize) -> Self {
+        Self { player, text: None }
+    }
+}
+
+impl<'g> Scene<'g> for GetReadyScene<'g> {
+    fn enter(&mut self, res: &mut Resources<'g>) {
+        let mut text = TextLayer::new(Layer::new(res.gfx, Priority::P0), &mut res.vram);
+        text.print_line(
+            &mut res.vram,
+            8,
+            format_args!("      PLAYER {} GET READY", self.player + 1),
+        );
+        text.print_line(&mut res.vram, 10, format_args!("         PRESS START"));
+        text.set_visible(true);
+        text.commit(&mut res.vram);
+        self.text = Some(text);
+    }
+
+    fn update(&mut self, _res: &mut Resources<'g>, input: &Input) -> Transition<'g> {
+        if input.is_just_pressed(Button::START) || input.is_just_pressed(Button::A) {
+            Transition::Pop
+        } else {
+            Transition::Stay
+        }
+    }
+
+    fn exit(&mut self, res: &mut Resources<'g>) {
+        if let Some(text) = self.text.take() {
+            text.release(&mut res.vram);
+        }
+    }
+}
@@ -0,0 +1,19 @@
+// 画像と色。
+//
+// 背景のタイル (gfx/bg.png) はビルド時に agb が取り込み、tiles::bg として使えるようにする。
+// スプライトは resources モジュールが読み込む。
+
+pub mod tiles {
+    /* agb が作るモジュールは外から見えないので、中の絵とパレットだけを出す */
+    agb::include_background_gfx!(background,
+        "ff00ff", // 透過色p
+        bg => "gfx/bg.png");
+
+    pub use background::{bg, PALETTES};
+}
+
+// 0..=31 の赤、緑、青から GBA の 15 ビットカラーを作る。
+pub const fn rgb5(r: u8, g: u8, b: u8) -> u16 {
+    let (r, g, b) = (r as u16, g as u16, b as u16);
+    (r) | ((g) << 5) | ((b) << 10)
+}
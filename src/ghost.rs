@@ -0,0 +1,194 @@
+// タイムアタックの最高記録のゴースト。
+//
+// 記録したときの入力を、もう 1 つの Game (ゴースト側) に 1 ティックずつ渡して
+// 遊んでいる Game と並べて進めるだけ。2 つの Game は互いに何も知らないので、ぶつかることも無い。
+// ゴースト側は Replay::start で作るので、難易度、修飾、シードは必ず記録したときと同じになる
+// (遊ぶ側もそれに合わせる。GameScene::ghost 参照)。
+//
+// レベルの幅を 4 等分した位置にチェックポイントを置き、ドロイド君の中心がそこを横切ったときのタイムを
+// 両方について覚える。両方が通ったチェックポイントでは、HUD に差を出す。
+// レベルは画面 1 枚で左右が回り込むので、チェックポイントは通った順に関わらず最初の 1 回だけ数える。
+
+use agb::display::WIDTH;
+
+use crate::{
+    game::{Game, DROID_WIDTH},
+    replay::{Playback, Replay},
+};
+
+const CHECKPOINTS: usize = 4;
+/* 差を HUD に出しておくティック数 */
+const DELTA_TICKS: u32 = 2 * 60;
+
+// チェックポイントごとに、最初に横切ったときのタイム。
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Splits([Option<u32>; CHECKPOINTS]);
+
+impl Splits {
+    // before から game へ動いた間に横切ったチェックポイントを記録する。新しく通ったものを返す。
+    fn track(&mut self, before: i32, game: &Game) -> Option<usize> {
+        let after = centre(game);
+        /* 画面端で回り込んだティックは横切ったことにしない */
+        if (after - before).abs() >= WIDTH / 2 {
+            return None;
+        }
+        let mut passed = None;
+        for (index, split) in self.0.iter_mut().enumerate() {
+            let line = (index as i32 + 1) * WIDTH / CHECKPOINTS as i32;
+            if split.is_none() && (before < line) != (after < line) {
+                *split = Some(game.time);
+                passed = Some(index);
+            }
+        }
+        passed
+    }
+}
+
+fn centre(game: &Game) -> i32 {
+    game.droid.x + DROID_WIDTH / 2
+}
+
+pub struct Ghost {
+    game: Game,
+    playback: Playback,
+    ghost_splits: Splits,
+    live_splits: Splits,
+    /* 直前のティックの、遊んでいる方のドロイド君の中心 */
+    live_before: i32,
+    /* 最後に出した差 (遊んでいる方 - ゴースト) と、それを出した Game::time */
+    delta: Option<(i32, u32)>,
+}
+
+impl Ghost {
+    pub fn new(replay: Replay) -> Self {
+        let game = replay.start();
+        let before = centre(&game);
+        Self {
+            game,
+            playback: Playback::new(replay),
+            ghost_splits: Splits::default(),
+            live_splits: Splits::default(),
+            live_before: before,
+            delta: None,
+        }
+    }
+
+    // live を 1 ティック進めた後に呼ぶ。ゴーストも 1 ティック進める。
+    pub fn update(&mut self, live: &Game) {
+        /* 記録が尽きたら (ゴーストがゴールした後) はそのまま止まっている */
+        if let Some(input) = self.playback.next() {
+            let before = centre(&self.game);
+            self.game.update(&input);
+            if let Some(index) = self.ghost_splits.track(before, &self.game) {
+                self.compare(index, live.time);
+            }
+        }
+        if let Some(index) = self.live_splits.track(self.live_before, live) {
+            self.compare(index, live.time);
+        }
+        self.live_before = centre(live);
+    }
+
+    // 両方が index を通っていれば差を出す。
+    fn compare(&mut self, index: usize, now: u32) {
+        if let (Some(live), Some(ghost)) = (self.live_splits.0[index], self.ghost_splits.0[index]) {
+            let delta = live as i32 - ghost as i32;
+            log_debug!("checkpoint {}: {} ticks against the ghost", index, delta);
+            self.delta = Some((delta, now));
+        }
+    }
+
+    // HUD に出す差 (ティック、負ならゴーストより速い)。出しておく時間を過ぎたら None 。
+    pub fn delta(&self, live: &Game) -> Option<i32> {
+        let (delta, since) = self.delta?;
+        (live.time < since + DELTA_TICKS).then_some(delta)
+    }
+
+    // ゴーストのドロイド君を描く位置 (Droid::drawn_at)。ゴールした後は消す。
+    pub fn position(&self, alpha: u32) -> Option<(i32, i32)> {
+        let droid = &self.game.droid;
+        self.game.outcome().is_none().then(|| droid.drawn_at(alpha))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use agb::input::Button;
+    use alloc::boxed::Box;
+
+    use super::*;
+    use crate::{difficulty::Difficulty, input::Input, mode::Mode, modifiers::Modifiers};
+
+    // 右へ歩き続けて、ときどき跳ぶ。
+    fn run(tick: u32) -> Button {
+        if tick % 40 < 2 {
+            Button::RIGHT | Button::UP
+        } else {
+            Button::RIGHT
+        }
+    }
+
+    // run の入力で 600 ティック遊び、入力を replay に記録する。遊び終えた Game を返す。
+    // Game はそれぞれ 3K 余りあり、幾つもテストのスタックに置くと溢れるので、作るところを分けてヒープに置く。
+    #[inline(never)]
+    fn record(replay: &mut Replay) -> Box<Game> {
+        let mut recorded = replay.start();
+        let mut previous = Button::empty();
+        for tick in 0..600 {
+            let input = replay.record(&Input::new(run(tick), previous));
+            previous = run(tick);
+            recorded.update(&input);
+        }
+        Box::new(recorded)
+    }
+
+    // replay のゴーストと、同じ記録から始める遊ぶ方の Game 。
+    #[inline(never)]
+    fn start(replay: &Replay) -> (Box<Ghost>, Box<Game>) {
+        (
+            Box::new(Ghost::new(replay.clone())),
+            Box::new(replay.start()),
+        )
+    }
+
+    // 記録したランをゴーストで再生すると、記録したときと全く同じ展開になる。
+    // 同じ入力で並べて走ると、チェックポイントごとの差は 0 。
+    #[test_case]
+    fn the_ghost_retraces_the_recorded_run(_gba: &mut agb::Gba) {
+        let mut replay = Replay::new(Difficulty::Hard, Mode::TimeAttack, Modifiers::HARD, 9);
+        let recorded = record(&mut replay);
+
+        let (mut ghost, mut live) = start(&replay);
+        let mut previous = Button::empty();
+        let mut deltas = 0;
+        for tick in 0..600 {
+            live.update(&Input::new(run(tick), previous));
+            previous = run(tick);
+            ghost.update(&live);
+            if ghost.delta(&live).is_some() {
+                assert_eq!(ghost.delta(&live), Some(0));
+                deltas += 1;
+            }
+        }
+        assert_eq!(ghost.game, *recorded);
+        assert!(deltas > 0, "never crossed a checkpoint");
+        assert_eq!(ghost.ghost_splits, ghost.live_splits);
+    }
+
+    // 遊んでいる方が止まっていれば、ゴーストだけが先へ進み、差は出ない。
+    #[test_case]
+    fn standing_still_falls_behind(_gba: &mut agb::Gba) {
+        let mut replay = Replay::new(Difficulty::Normal, Mode::TimeAttack, Modifiers::NONE, 3);
+        for tick in 0..400 {
+            replay.record(&Input::new(run(tick), Button::empty()));
+        }
+        let (mut ghost, mut live) = start(&replay);
+        for _ in 0..400 {
+            live.update(&Input::default());
+            ghost.update(&live);
+        }
+        assert_ne!(ghost.position(0), Some(live.droid.previous));
+        assert!(ghost.ghost_splits.0.iter().any(Option::is_some));
+        assert_eq!(ghost.delta(&live), None);
+    }
+}
@@ -0,0 +1,82 @@
+// 1 ティック分のキー入力。
+//
+// agb の ButtonController は update() を呼んだ回数でしか「押した瞬間」を判定できないので、
+// ゲームロジックにはティックごとに update() してから取ったこのスナップショットを渡す。
+// 値型なので、記録した入力の再生やテストからも同じように組み立てられる。
+
+use agb::input::{Button, ButtonController};
+
+/* A+B+SELECT+START 同時押しでタイトルに戻る */
+pub const SOFT_RESET: Button = Button::A
+    .union(Button::B)
+    .union(Button::SELECT)
+    .union(Button::START);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Input {
+    current: Button,
+    previous: Button,
+}
+
+impl Default for Input {
+    fn default() -> Self {
+        Self::new(Button::empty(), Button::empty())
+    }
+}
+
+impl Input {
+    pub fn new(current: Button, previous: Button) -> Self {
+        Self { current, previous }
+    }
+
+    // update() 済みの ButtonController から今フレームの入力を取り出す。
+    pub fn sample(controller: &ButtonController) -> Self {
+        let mut current = Button::empty();
+        let mut previous = Button::empty();
+        for button in Button::all().iter() {
+            if controller.is_pressed(button) {
+                current |= button;
+            }
+            /* 離した瞬間か、押し続けているなら前フレームも押されていた */
+            if controller.is_just_released(button)
+                || (controller.is_pressed(button) && !controller.is_just_pressed(button))
+            {
+                previous |= button;
+            }
+        }
+        Self { current, previous }
+    }
+
+    // 押されているボタン全て。入力の記録に使う。
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn buttons(&self) -> Button {
+        self.current
+    }
+
+    pub fn is_pressed(&self, button: Button) -> bool {
+        self.current.contains(button)
+    }
+
+    pub fn is_just_pressed(&self, button: Button) -> bool {
+        self.current.contains(button) && !self.previous.contains(button)
+    }
+
+    pub fn is_just_released(&self, button: Button) -> bool {
+        !self.current.contains(button) && self.previous.contains(button)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn edges_come_from_the_previous_state(_gba: &mut agb::Gba) {
+        let input = Input::new(Button::UP | Button::LEFT, Button::LEFT | Button::A);
+        assert!(input.is_just_pressed(Button::UP));
+        assert!(!input.is_just_pressed(Button::LEFT));
+        assert!(input.is_pressed(Button::LEFT));
+        assert!(input.is_just_released(Button::A));
+        assert!(!input.is_pressed(Button::A));
+    }
+}
@@ -0,0 +1,179 @@
+// はしご。
+//
+// レベルの列ごとに 1 つまで、背景の行 (Level::ladders) から地面の上面まで掛ける。当たり判定のマップには入らず、
+// 背景のタイル (gameplay::LADDER) は presenter がレベルを始めるときに描く。
+// 足元の真ん中がはしごの列にあるドロイド君が、左右を押さずに UP (宙にいれば DOWN でも) を押すと掴まる (状態 12)。
+// 掴まっている間は重力が効かず、UP と DOWN で CLIMB_SPEED ずつ上り下りする。上ははしごの上端に手が掛かる所まで、
+// 下は地面に着いたら立つ。左右のどちらかを押しながら UP を押すと、地面と同じくしゃがんでからその向きへ跳ぶ。
+// 上り下りの絵はぶら下がりとよじ登りの絵を、CLIMB_STEP ドット進むごとに入れ替える (climb_pose)。
+// 重力が逆さの間は掴まらない。
+
+use agb::input::Button;
+
+use crate::{
+    game::{Game, DROID_WIDTH},
+    input::Input,
+    math::Fixed,
+    physics::Gravity,
+    player::PlayerState,
+};
+
+/* はしご (タイル 1 枚) の幅 */
+const LADDER_WIDTH: i32 = 8;
+/* 1 ティックに上り下りするドット数 */
+const CLIMB_SPEED: i32 = 1;
+/* 上り下りの絵を入れ替えるドット数 */
+const CLIMB_STEP: i32 = 4;
+// はしごの y で上り下りしている絵 (Droid::pose の値)。ぶら下がりとよじ登りの絵を交互に。
+pub fn climb_pose(y: i32) -> u16 {
+    if y.div_euclid(CLIMB_STEP) % 2 == 0 {
+        18
+    } else {
+        20
+    }
+}
+
+impl Game {
+    // はしごに掴まり、掴まっていれば上り下りする。ドロイド君を動かす前に呼ぶ。
+    pub fn update_ladder(&mut self, input: &Input) {
+        let droid = &mut self.droid;
+        /* 掴まるのは押したティックだけ */
+        if droid.state != PlayerState::Climbing
+            && !input.is_just_pressed(Button::UP)
+            && !input.is_just_pressed(Button::DOWN)
+        {
+            return;
+        }
+        let column = (droid.x + DROID_WIDTH / 2).div_euclid(LADDER_WIDTH);
+        let Some(top) = usize::try_from(column)
+            .ok()
+            .and_then(|column| self.ladders.get(column).copied().flatten())
+        else {
+            return;
+        };
+        let top = i32::from(top) * LADDER_WIDTH;
+        let sideways = input.is_pressed(Button::LEFT) || input.is_pressed(Button::RIGHT);
+        if droid.state != PlayerState::Climbing {
+            let reach = match droid.state {
+                PlayerState::Idle => input.is_just_pressed(Button::UP),
+                PlayerState::Airborne { .. } => {
+                    input.is_just_pressed(Button::UP) || input.is_just_pressed(Button::DOWN)
+                }
+                _ => false,
+            };
+            if !reach || sideways || droid.gravity != Gravity::Down || droid.y + DROID_WIDTH <= top
+            {
+                return;
+            }
+            log_debug!("grabbed a ladder at ({}, {})", droid.x, droid.y);
+            /* はしごの真ん中に寄せる */
+            droid.x = column * LADDER_WIDTH + LADDER_WIDTH / 2 - DROID_WIDTH / 2;
+            droid.vx = Fixed::new(0);
+            droid.x_sub = Fixed::new(0);
+            droid.vy = Fixed::new(0);
+            droid.enter(PlayerState::Climbing);
+        } else if sideways && input.is_just_pressed(Button::UP) {
+            /* 押している向きへ跳び降りる (二段ジャンプは残る) */
+            log_debug!("jumped off a ladder at ({}, {})", droid.x, droid.y);
+            droid.walk_dir(input);
+            droid.enter(PlayerState::JumpSquat { double: false });
+            return;
+        }
+        let dir =
+            i32::from(input.is_pressed(Button::DOWN)) - i32::from(input.is_pressed(Button::UP));
+        /* 上は手が上端に掛かる所まで */
+        droid.y = (droid.y + dir * CLIMB_SPEED).max(top - DROID_WIDTH / 2);
+        let floor = self.collision_map.floor_y(droid.x, droid.y);
+        if droid.y >= floor {
+            droid.y = floor;
+            droid.enter(PlayerState::Idle);
+            droid.ch = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        difficulty::Difficulty,
+        game::GROUND_Y,
+        level::{Level, GROUND_COLUMNS},
+    };
+
+    /* 出発点 (x = 120) の左の、地面から 8 段目までのはしご */
+    const COLUMN: usize = 12;
+    const TOP: u16 = 9;
+
+    fn game_with_ladder() -> Game {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        let mut level = Level::load(false);
+        level.ladders = [None; GROUND_COLUMNS];
+        level.ladders[COLUMN] = Some(TOP);
+        game.ladders = level.ladders;
+        /* 足元の真ん中がはしごの列に掛かる所 */
+        game.droid.x = COLUMN as i32 * LADDER_WIDTH - 2;
+        game
+    }
+
+    fn press(game: &mut Game, buttons: Button, ticks: usize) {
+        game.update(&Input::new(buttons, Button::empty()));
+        for _ in 0..ticks {
+            game.update(&Input::new(buttons, buttons));
+        }
+    }
+
+    // UP で掴まって上端まで上り、DOWN で地面まで下りると立つ。上り下りの間は絵が入れ替わる。
+    #[test_case]
+    fn the_droid_climbs_a_ladder_up_and_down(_gba: &mut agb::Gba) {
+        let mut game = game_with_ladder();
+        press(&mut game, Button::UP, 0);
+        assert_eq!(game.droid.state, PlayerState::Climbing);
+        assert_eq!(game.droid.x, COLUMN as i32 * LADDER_WIDTH - 4);
+        let mut poses = [false; 2];
+        for _ in 0..100 {
+            game.update(&Input::new(Button::UP, Button::UP));
+            poses[usize::from(game.droid.pose() == 20)] = true;
+        }
+        assert_eq!(poses, [true, true]);
+        assert_eq!(game.droid.state, PlayerState::Climbing);
+        assert_eq!(game.droid.y, i32::from(TOP) * 8 - DROID_WIDTH / 2);
+        /* 放していれば落ちない */
+        for _ in 0..30 {
+            game.update(&Input::default());
+        }
+        assert_eq!(game.droid.y, i32::from(TOP) * 8 - DROID_WIDTH / 2);
+        /* DOWN を押したまま下りきると、そのまましゃがむ */
+        press(&mut game, Button::DOWN, 100);
+        assert_eq!(game.droid.state, PlayerState::Crouch);
+        game.update(&Input::default());
+        assert_eq!(
+            (game.droid.state, game.droid.y),
+            (PlayerState::Idle, GROUND_Y)
+        );
+    }
+
+    // 左右を押しながら UP で跳び降り (左の壁の手前に下りる)、宙で左右を押していなければまた掴まる。左右を押しながらの UP は普通に跳ぶ。
+    #[test_case]
+    fn the_droid_jumps_off_a_ladder_sideways(_gba: &mut agb::Gba) {
+        let mut game = game_with_ladder();
+        press(&mut game, Button::UP, 40);
+        press(&mut game, Button::LEFT | Button::UP, 0);
+        assert_eq!(game.droid.state, PlayerState::JumpSquat { double: false });
+        press(&mut game, Button::LEFT, 60);
+        assert_eq!(
+            (game.droid.state, game.droid.y),
+            (PlayerState::Idle, GROUND_Y)
+        );
+        assert!(game.droid.x < COLUMN as i32 * LADDER_WIDTH - DROID_WIDTH);
+
+        let mut game = game_with_ladder();
+        press(&mut game, Button::RIGHT | Button::UP, 0);
+        assert_eq!(game.droid.state, PlayerState::JumpSquat { double: false });
+        while game.droid.y > GROUND_Y - DROID_WIDTH {
+            game.update(&Input::default());
+        }
+        press(&mut game, Button::DOWN, 0);
+        assert_eq!(game.droid.state, PlayerState::Climbing);
+    }
+}
@@ -0,0 +1,988 @@
+// 背景 1 枚分のマップ。
+//
+// agb の MapLoan は drop してもタイルの参照を返さない (VRAM のタイルが漏れる)。
+// そこで置いたタイルを数えておき、シーンを抜けるときに clear() し忘れていないか
+// 確かめられるようにする。全レイヤーの合計は tiles_in_use() で取れる。
+// 使い終わったら release() で片付けてから手放す。
+// デバッグ表示やレベルの背景のようにシーンをまたいで使い続けるレイヤーは persistent() で作り、数えない。
+//
+// 空白にするときは TileSetting::BLANK を set_tile() に渡さず clear_tile() を使うこと
+// (TileSetting の中身は外から見えないので、空白かどうかをここで判定できない)。
+// 同じ理由で、直接セルには TileSetting ではなく、比べられる番号を一緒に持つ Tile を渡す。
+//
+// タイルの置き方は 2 通りある。
+// - set_tile(): agb のマップに置く。agb は 1 セルごとに VRAM のタイルを探して参照を数え、
+//   commit のたびにマップ (2 KiB) を丸ごと VRAM に写す。HUD のように時々書き換えるだけならこれでよい。
+// - write_row() / write_column() / fill(): VRAM のマップに直接書く (直接セル)。
+//   タイルの参照は、画面に映らない右端の 2 列 (HIDDEN_COLUMNS) に agb のセルとして 1 種類 1 つ置いておき、
+//   commit で agb が写した後、そのセルの値を読んで直接セルに書く。agb のマップは変わらないので
+//   丸ごとの転送も起きない。足場やコインのように毎フレーム流れてくるものと、レベルの地面はこちら。
+// agb が別の理由でマップを写したときは、直接セルは空白に戻っているので全て書き直す。
+// 直接セルを書くマップの場所は、背景の番号 (表示を切り替えたときに変わる DISPCNT のビット) と、
+// agb が commit で設定した BG の制御レジスタから求める。どちらもハードウェアの決まりなので agb の中身には頼らない。
+// 背景の番号が分からなければ (agb が表示の切り替えを commit まで遅らせるようになったなど)、直接セルは使わず
+// write_row() なども全て set_tile() で置く。遅くなるだけで画面は同じ。
+// 参照のセルは clear() まで持ったまま (使う種類は数えるほどしかない)。足りなくなったら set_tile() で置く。
+
+use alloc::{vec, vec::Vec};
+use core::ops::Range;
+
+use agb::{
+    display::{
+        tile_data::TileData,
+        tiled::{
+            MapLoan, RegularBackgroundSize, RegularMap, TileFormat, TileSet, TileSetting, Tiled0,
+            TiledMap, VRamManager,
+        },
+        Priority,
+    },
+    sync::Static,
+};
+
+use crate::font;
+
+const SIZE: u16 = 32;
+/* 画面に映る列 (240 ドット) と、映らないのでタイルの参照を置く列 */
+const VISIBLE_COLUMNS: u16 = 30;
+const HIDDEN_COLUMNS: Range<u16> = VISIBLE_COLUMNS..SIZE;
+const VISIBLE: u32 = (1 << VISIBLE_COLUMNS) - 1;
+const MAX_REFERENCES: usize = ((HIDDEN_COLUMNS.end - HIDDEN_COLUMNS.start) * SIZE) as usize;
+/* 表示の制御レジスタ (8 ビット目から BG0..3 を映すか) と、BG0 の制御レジスタと、VRAM のマップの置き場所 (スクリーンブロック) */
+const DISPLAY_CONTROL: *const u16 = 0x0400_0000 as *const u16;
+const BG_CONTROL: usize = 0x0400_0008;
+const SCREENBLOCK_BASE: usize = 0x0600_0000;
+const SCREENBLOCK_SIZE: usize = 0x800;
+
+static TILES_IN_USE: Static<u32> = Static::new(0);
+
+// 全レイヤーに置かれているタイルの数。
+pub fn tiles_in_use() -> u32 {
+    TILES_IN_USE.read()
+}
+
+// 直接セルに置くタイル。agb の TileSetting は中身を読めず比べられないので、何のタイルかを表す番号を一緒に持つ。
+#[derive(Clone, Copy, Debug)]
+pub struct Tile {
+    setting: TileSetting,
+    /* タイルセットの中の番号、反転、パレット。TileData から作ったものは番号に FROM_DATA を足す */
+    key: u32,
+}
+
+const FROM_DATA: u32 = 1 << 16;
+const HFLIP: u32 = 1 << 10;
+const VFLIP: u32 = 1 << 11;
+
+impl Tile {
+    // タイルセットの id 番目のタイルを palette の色で (TileSetting::new と同じ)。
+    pub const fn new(id: u16, palette: u8) -> Self {
+        Self {
+            setting: TileSetting::new(id, false, false, palette),
+            key: id as u32 | (palette as u32) << 12,
+        }
+    }
+
+    // data の index 番目のタイル。パレットはタイルごとに決まっているので、番号だけで比べる。
+    pub fn of(data: &TileData, index: usize) -> Self {
+        debug_assert!(index < 1 << 10, "tile {index} is out of range");
+        Self {
+            setting: data.tile_settings[index],
+            key: index as u32 | FROM_DATA,
+        }
+    }
+
+    pub const fn hflip(self, flip: bool) -> Self {
+        Self {
+            setting: self.setting.hflip(flip),
+            key: self.key ^ if flip { HFLIP } else { 0 },
+        }
+    }
+
+    pub const fn vflip(self, flip: bool) -> Self {
+        Self {
+            setting: self.setting.vflip(flip),
+            key: self.key ^ if flip { VFLIP } else { 0 },
+        }
+    }
+}
+
+impl PartialEq for Tile {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl Eq for Tile {}
+
+/* 直接セルが使うタイルの参照 (画面の外の agb のセル) */
+struct Reference {
+    tileset: &'static TileSet<'static>,
+    key: u32,
+    cell: (u16, u16),
+    /* VRAM のマップでの値。agb が写すまでは分からない */
+    entry: Option<u16>,
+}
+
+pub struct Layer<'g> {
+    map: MapLoan<'g, RegularMap>,
+    /* 背景の番号 (0..4) と、直接セルを使うか (背景の番号が分かったか) */
+    background: usize,
+    direct_cells: bool,
+    /* タイルが置かれているセル (1 行 32 セルを 1 ワードで持つ)。直接セルと参照のセルも含む */
+    occupied: [u32; SIZE as usize],
+    /* 直接セルと、その参照 (references の添字 + 1)。直接セルでないセルの参照は使わないので消さずに残す */
+    direct: [u32; SIZE as usize],
+    cells: Vec<u8>,
+    /* 前の commit から書き換えた (空白にしたものも含む) 直接セル */
+    pending: [u32; SIZE as usize],
+    /* 前の clear_cells から見えるところにタイルを置いた行 (1 行を 1 ビット) */
+    rows: u32,
+    /* 行の直接セルが全て同じ参照なら、その参照 (cells と同じ値)。混ざっていれば 0 */
+    runs: [u8; SIZE as usize],
+    /*
+     * clear_cells で空白にしたが、まだ commit していないので VRAM には前の参照が映っている直接セルと、
+     * その行の参照 (runs)。commit までに同じタイルを置き直したセルは書き直さない
+     */
+    kept: [u32; SIZE as usize],
+    kept_runs: [u8; SIZE as usize],
+    keeping: bool,
+    references: Vec<Reference>,
+    dirty: bool,
+    /* agb のマップを書き換えたか。スクロールしただけなら agb は VRAM のマップを写し直さない */
+    map_dirty: bool,
+    /* 縦のスクロール位置 */
+    scroll_y: i16,
+    /* tiles_in_use() に数えるか */
+    counted: bool,
+}
+
+impl<'g> Layer<'g> {
+    pub fn new(gfx: &'g Tiled0<'_>, priority: Priority) -> Self {
+        Self::with_counting(gfx, priority, true)
+    }
+
+    // シーンに属さず、起動中ずっと使うレイヤー。
+    pub fn persistent(gfx: &'g Tiled0<'_>, priority: Priority) -> Self {
+        Self::with_counting(gfx, priority, false)
+    }
+
+    fn with_counting(gfx: &'g Tiled0<'_>, priority: Priority, counted: bool) -> Self {
+        let mut map = gfx.background(
+            priority,
+            RegularBackgroundSize::Background32x32,
+            TileFormat::FourBpp,
+        );
+        let background = background_of(&mut map);
+        Self {
+            map,
+            background: background.unwrap_or(0),
+            direct_cells: background.is_some(),
+            occupied: [0; SIZE as usize],
+            direct: [0; SIZE as usize],
+            cells: vec![0; usize::from(SIZE * SIZE)],
+            pending: [0; SIZE as usize],
+            rows: 0,
+            runs: [0; SIZE as usize],
+            kept: [0; SIZE as usize],
+            kept_runs: [0; SIZE as usize],
+            keeping: false,
+            /* 使うタイルは数種類なので、置くたびに伸ばさないよう先に取っておく */
+            references: Vec::with_capacity(8),
+            dirty: false,
+            /* agb は最初の commit でマップを写す */
+            map_dirty: true,
+            scroll_y: 0,
+            counted,
+        }
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.map.set_visible(visible);
+    }
+
+    // 画面の上端に映すドット行。マップの高さ (256 ドット) で回り込む。
+    // agb はスクロール位置もマップと一緒に commit で書き込むので、変わったら転送し直す。
+    pub fn set_scroll_y(&mut self, y: i32) {
+        let y = y.rem_euclid(i32::from(SIZE) * 8) as i16;
+        if y != self.scroll_y {
+            self.scroll_y = y;
+            self.map.set_scroll_pos((0, y));
+            self.dirty = true;
+        }
+    }
+
+    pub fn set_tile(
+        &mut self,
+        vram: &mut VRamManager,
+        pos: (u16, u16),
+        tileset: &TileSet<'_>,
+        setting: TileSetting,
+    ) {
+        debug_assert!(
+            pos.0 < VISIBLE_COLUMNS,
+            "({}, {}) is off screen",
+            pos.0,
+            pos.1
+        );
+        self.undirect(pos);
+        self.map.set_tile(vram, pos, tileset, setting);
+        self.map_dirty = true;
+        self.rows |= 1 << (pos.1 % SIZE);
+        self.mark(pos, true);
+    }
+
+    pub fn clear_tile(&mut self, vram: &mut VRamManager, pos: (u16, u16), tileset: &TileSet<'_>) {
+        if self.undirect(pos) {
+            self.pending[(pos.1 % SIZE) as usize] |= 1 << (pos.0 % SIZE);
+        } else if self.is_occupied(pos) {
+            self.map.set_tile(vram, pos, tileset, TileSetting::BLANK);
+            self.map_dirty = true;
+        }
+        self.mark(pos, false);
+    }
+
+    // (x, y) から右へ entries を並べる (None は空白)。まとめて VRAM のマップに直接書く。
+    pub fn write_row(
+        &mut self,
+        vram: &mut VRamManager,
+        (x, y): (u16, u16),
+        tileset: &'static TileSet<'static>,
+        entries: &[Option<Tile>],
+    ) {
+        /* 同じタイルが続くところは 1 回で書く */
+        let mut start = 0;
+        while start < entries.len() {
+            let first = entries[start];
+            let mut end = start + 1;
+            while end < entries.len() && entries[end] == first {
+                end += 1;
+            }
+            let columns = x + start as u16..x + end as u16;
+            match first {
+                Some(tile) => self.fill(vram, columns, y..y + 1, tileset, tile),
+                None => {
+                    for x in columns {
+                        self.clear_tile(vram, (x, y), tileset);
+                    }
+                }
+            }
+            start = end;
+        }
+    }
+
+    // (x, y) から下へ entries を並べる (None は空白)。マップの下端から上端へ回り込む。
+    pub fn write_column(
+        &mut self,
+        vram: &mut VRamManager,
+        (x, y): (u16, u16),
+        tileset: &'static TileSet<'static>,
+        entries: &[Option<Tile>],
+    ) {
+        for (i, &entry) in entries.iter().enumerate() {
+            let y = (y + i as u16) % SIZE;
+            match entry {
+                Some(tile) => self.fill(vram, x..x + 1, y..y + 1, tileset, tile),
+                None => self.clear_tile(vram, (x, y), tileset),
+            }
+        }
+    }
+
+    // columns x rows の矩形に同じタイルを敷き詰める。参照は矩形ごとに 1 度だけ探す。
+    pub fn fill(
+        &mut self,
+        vram: &mut VRamManager,
+        columns: Range<u16>,
+        rows: Range<u16>,
+        tileset: &'static TileSet<'static>,
+        tile: Tile,
+    ) {
+        if columns.is_empty() {
+            return;
+        }
+        debug_assert!(
+            columns.end <= VISIBLE_COLUMNS,
+            "{:?} is off screen",
+            columns
+        );
+        let Some(index) = self.reference(vram, tileset, tile) else {
+            for y in rows {
+                for x in columns.clone() {
+                    self.set_tile(vram, (x, y), tileset, tile.setting);
+                }
+            }
+            return;
+        };
+        /* Range の for は ROM の関数を呼ぶので、添字で回す */
+        let mut y = rows.start;
+        while y < rows.end {
+            self.write_run(vram, y, columns.clone(), tileset, index);
+            y += 1;
+        }
+    }
+
+    // 後で直接セルに使う tileset の tile の参照を、先に画面の外に置いておく。
+    // アニメーションのコマのように途中で初めて使うタイルがあるときに描き始める前に呼び、
+    // 遊んでいる最中に参照を置いて agb のマップを丸ごと写すことにならないようにする。
+    pub fn reserve(
+        &mut self,
+        vram: &mut VRamManager,
+        tileset: &'static TileSet<'static>,
+        tile: Tile,
+    ) {
+        self.reference(vram, tileset, tile);
+    }
+
+    // y 行目の空でない columns に、references[index] のタイルを直接書く。
+    // セルごとの処理は ROM から実行すると重いので、行のビットで扱う。
+    fn write_run(
+        &mut self,
+        vram: &mut VRamManager,
+        y: u16,
+        columns: Range<u16>,
+        tileset: &'static TileSet<'static>,
+        index: u8,
+    ) {
+        let row = (y % SIZE) as usize;
+        let mask = (u32::MAX >> (SIZE - (columns.end - columns.start))) << columns.start;
+        /* agb のセルを空けてから直接セルにする */
+        let mut placed = self.occupied[row] & !self.direct[row] & mask;
+        while placed != 0 {
+            let x = placed.trailing_zeros() as u16;
+            placed &= placed - 1;
+            self.map.set_tile(vram, (x, y), tileset, TileSetting::BLANK);
+            self.map_dirty = true;
+        }
+        /* count_ones も ROM の関数なので、全て空いていたときは数えない */
+        let added = mask & !self.occupied[row];
+        if self.counted && added != 0 {
+            let added = if added == mask {
+                columns.len() as u32
+            } else {
+                added.count_ones()
+            };
+            TILES_IN_USE.write(TILES_IN_USE.read() + added);
+        }
+        let run = index + 1;
+        /* clear_cells の前と同じタイルを置き直したセルは、VRAM に映っているままなので書かない */
+        let same = if self.kept_runs[row] == run {
+            self.kept[row] & mask
+        } else {
+            0
+        };
+        self.kept[row] &= !mask;
+        self.runs[row] = if self.direct[row] & !mask == 0 || self.runs[row] == run {
+            run
+        } else {
+            0
+        };
+        self.occupied[row] |= mask;
+        self.direct[row] |= mask;
+        self.pending[row] = (self.pending[row] | mask) & !same;
+        self.rows |= 1 << row;
+        let start = offset((columns.start, y));
+        self.cells[start..start + columns.len()].fill(run);
+        self.dirty = true;
+    }
+
+    // tileset の tile を直接セルに使うための参照。無ければ画面の外に置く。
+    // 置く場所が無いか、直接セルを使わないレイヤーなら None 。
+    fn reference(
+        &mut self,
+        vram: &mut VRamManager,
+        tileset: &'static TileSet<'static>,
+        tile: Tile,
+    ) -> Option<u8> {
+        if !self.direct_cells {
+            return None;
+        }
+        /* イテレータは ROM の関数を呼ぶので、添字で探す */
+        let mut index = 0;
+        while index < self.references.len() {
+            let reference = &self.references[index];
+            if reference.key == tile.key && core::ptr::eq(reference.tileset, tileset) {
+                return Some(index as u8);
+            }
+            index += 1;
+        }
+        let index = index as u16;
+        if usize::from(index) >= MAX_REFERENCES {
+            return None;
+        }
+        let cell = (HIDDEN_COLUMNS.start + index / SIZE, index % SIZE);
+        self.map.set_tile(vram, cell, tileset, tile.setting);
+        self.map_dirty = true;
+        self.mark(cell, true);
+        self.references.push(Reference {
+            tileset,
+            key: tile.key,
+            cell,
+            entry: None,
+        });
+        Some(index as u8)
+    }
+
+    // pos が直接セルなら、直接セルでなくす。直接セルだったら true 。
+    fn undirect(&mut self, (x, y): (u16, u16)) -> bool {
+        let (row, bit) = ((y % SIZE) as usize, 1 << (x % SIZE));
+        if self.direct[row] & bit == 0 {
+            return false;
+        }
+        self.direct[row] &= !bit;
+        self.pending[row] &= !bit;
+        true
+    }
+
+    // 前の commit から書き換えたか。
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    // (x, y) のセルにタイルが置かれているか。
+    pub fn is_occupied(&self, (x, y): (u16, u16)) -> bool {
+        self.occupied[(y % SIZE) as usize] & (1 << (x % SIZE)) != 0
+    }
+
+    // 背景のマップの VRAM 上の位置。agb が commit で設定した BG の制御レジスタから読む。
+    fn screenblock(&self) -> *mut u16 {
+        // Safety: BG の制御レジスタは読み出せる
+        let control = unsafe { ((BG_CONTROL + 2 * self.background) as *const u16).read_volatile() };
+        (SCREENBLOCK_BASE + SCREENBLOCK_SIZE * ((control >> 8) & 0x1f) as usize) as *mut u16
+    }
+
+    // 直接セルを使わず、全て set_tile() で置くようにする。背景の番号が分からなかったときと同じ。
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn without_direct_cells(mut self) -> Self {
+        self.direct_cells = false;
+        self
+    }
+
+    // agb が写した後に、rows の各行の直接セルを VRAM のマップに書く。直接セルでなくなったセルは空白にする。
+    #[link_section = ".iwram.layer_write_direct"]
+    fn write_direct(&mut self, rows: &[u32; SIZE as usize]) {
+        /* 直接セルを使わないレイヤーには、書くものが無い */
+        if !self.direct_cells {
+            return;
+        }
+        let screenblock = self.screenblock();
+        /* cells の値から VRAM に書く値を引く表 (0 は空白) */
+        let mut entries = [0; MAX_REFERENCES + 1];
+        for (reference, entry) in self.references.iter_mut().zip(&mut entries[1..]) {
+            *entry = *reference.entry.get_or_insert_with(|| {
+                // Safety: 32x32 のマップの中で、agb が写した後
+                unsafe { screenblock.add(offset(reference.cell)).read_volatile() }
+            });
+        }
+        /*
+         * ARMv4T には CLZ が無く trailing_zeros は ROM の関数を呼ぶので、ビットは 1 つずつずらして見る。
+         * イテレータも ROM の関数を呼ぶので、添字で回す
+         */
+        let cells = &self.cells[..];
+        let mut y = 0;
+        while y < usize::from(SIZE) {
+            let mut row = rows[y];
+            if row == 0 {
+                y += 1;
+                continue;
+            }
+            /* 直接セルでなくなったセルは空白にする */
+            let mut direct = self.direct[y];
+            let mut cell = y * usize::from(SIZE);
+            /* 書かないセルが左に続く間は 8 セルずつ飛ばす */
+            while row & 0xff == 0 {
+                row >>= 8;
+                direct >>= 8;
+                cell += 8;
+            }
+            /* 書くセルが全て空白になる行 (前のレベルの地面を消したところなど) は、cells を見ずに書く */
+            if direct & row == 0 {
+                while row != 0 {
+                    if row & 1 != 0 {
+                        // Safety: 32x32 のマップの中
+                        unsafe { screenblock.add(cell).write_volatile(0) };
+                    }
+                    row >>= 1;
+                    cell += 1;
+                }
+            }
+            while row != 0 {
+                if row & 1 != 0 {
+                    let entry = if direct & 1 != 0 {
+                        entries[usize::from(cells[cell])]
+                    } else {
+                        0
+                    };
+                    // Safety: 32x32 のマップの中
+                    unsafe { screenblock.add(cell).write_volatile(entry) };
+                }
+                row >>= 1;
+                direct >>= 1;
+                cell += 1;
+            }
+            y += 1;
+        }
+    }
+
+    fn mark(&mut self, (x, y): (u16, u16), occupied: bool) {
+        let row = &mut self.occupied[(y % SIZE) as usize];
+        let bit = 1 << (x % SIZE);
+        if (*row & bit != 0) != occupied {
+            *row ^= bit;
+            if self.counted {
+                let in_use = TILES_IN_USE.read();
+                TILES_IN_USE.write(if occupied { in_use + 1 } else { in_use - 1 });
+            }
+        }
+        self.dirty = true;
+    }
+
+    // y 行目に置いたタイルを全て返す。直接セルは空白にするだけで agb のマップに触らない。
+    pub fn clear_row(&mut self, vram: &mut VRamManager, y: u16) {
+        let index = (y % SIZE) as usize;
+        let direct = self.direct[index];
+        if direct != 0 {
+            self.occupied[index] &= !direct;
+            self.direct[index] = 0;
+            self.pending[index] |= direct;
+            if self.counted {
+                TILES_IN_USE.write(TILES_IN_USE.read() - direct.count_ones());
+            }
+            self.dirty = true;
+        }
+        /* 参照のセルは clear() まで残す */
+        let mut row = self.occupied[index] & VISIBLE;
+        while row != 0 {
+            let x = row.trailing_zeros() as u16;
+            row &= row - 1;
+            /* 空白にするだけなのでタイルセットは形式が合っていれば何でもよい */
+            self.clear_tile(vram, (x, y), &font::TILESET);
+        }
+    }
+
+    // 置いたタイルを全て返す。全ての行で clear_row を呼ぶのと同じだが、直接セルはまとめて空白にする。
+    // 参照のセルは残すので、次に同じタイルを置くときに agb のマップを写し直さずに済む。
+    pub fn clear_cells(&mut self, vram: &mut VRamManager) {
+        /* レベルを読み込み直すたびに ROM から実行するので、タイルを置いた行だけを添字で回す */
+        let mut removed = 0;
+        let mut rows = core::mem::take(&mut self.rows);
+        let mut y = 0;
+        while rows != 0 {
+            if rows & 1 != 0 {
+                let row = usize::from(y);
+                let direct = self.direct[row];
+                if direct != 0 {
+                    let committed = direct & !self.pending[row];
+                    if committed != 0 {
+                        /* 前の clear_cells の分がまだ残っていて参照が違えば、どちらとも比べない */
+                        if self.kept[row] != 0 && self.kept_runs[row] != self.runs[row] {
+                            self.kept_runs[row] = 0;
+                        } else {
+                            self.kept_runs[row] = self.runs[row];
+                        }
+                        self.kept[row] |= committed;
+                        self.keeping = true;
+                    }
+                    self.occupied[row] &= !direct;
+                    self.pending[row] |= direct;
+                    self.direct[row] = 0;
+                    if self.counted {
+                        removed += direct.count_ones();
+                    }
+                    self.dirty = true;
+                }
+                /* 参照のセルは画面の外にあるので、見えるところに残っているのは agb のマップに置いたものだけ */
+                let mut placed = self.occupied[row] & VISIBLE;
+                while placed != 0 {
+                    let x = placed.trailing_zeros() as u16;
+                    placed &= placed - 1;
+                    self.clear_tile(vram, (x, y), &font::TILESET);
+                }
+            }
+            rows >>= 1;
+            y += 1;
+        }
+        if removed != 0 {
+            TILES_IN_USE.write(TILES_IN_USE.read() - removed);
+        }
+    }
+
+    // 置いたタイルを全て返す。シーンを抜けるときは必ず呼ぶ。
+    // agb の RegularMap::clear() はマップを書き換えたことにしないので、commit しても
+    // 解放済みのタイルを指したままの画面が残る。置いたセルを 1 つずつ空白にして避ける。
+    pub fn clear(&mut self, vram: &mut VRamManager) {
+        for y in 0..SIZE {
+            self.clear_row(vram, y);
+        }
+        for reference in core::mem::take(&mut self.references) {
+            self.map
+                .set_tile(vram, reference.cell, reference.tileset, TileSetting::BLANK);
+            self.mark(reference.cell, false);
+        }
+        self.map_dirty = true;
+    }
+
+    // 片付けて手放す。タイルを返し、非表示にして、VRAM の解放まで済ませる。
+    pub fn release(mut self, vram: &mut VRamManager) {
+        self.clear(vram);
+        self.set_visible(false);
+        /* agb は commit の中でしかタイルを解放しない */
+        self.commit(vram);
+    }
+
+    // 書き換えたタイルがあるときだけ VRAM に転送する。転送したら true。
+    pub fn commit(&mut self, vram: &mut VRamManager) -> bool {
+        let dirty = self.dirty;
+        if dirty {
+            self.map.commit(vram);
+            /* agb が写したマップでは直接セルが空白なので、全て書き直す */
+            if self.map_dirty {
+                let direct = self.direct;
+                self.write_direct(&direct);
+                self.map_dirty = false;
+            } else {
+                let pending = self.pending;
+                self.write_direct(&pending);
+            }
+            self.pending = [0; SIZE as usize];
+            /* 映っていた参照は書き直したので、もう比べられない */
+            if self.keeping {
+                self.kept = [0; SIZE as usize];
+                self.keeping = false;
+            }
+            self.dirty = false;
+        }
+        dirty
+    }
+}
+
+// map の背景の番号 (0..4)。映すかどうかを一瞬だけ切り替えて、DISPCNT のどのビットが変わるかで知る。
+// 変わったビットがちょうど 1 つでなければ (agb が表示の切り替えを commit まで遅らせるようになったなど) None 。
+fn background_of(map: &mut MapLoan<'_, RegularMap>) -> Option<usize> {
+    let visible = map.is_visible();
+    // Safety: DISPCNT は読み出せる
+    let before = unsafe { DISPLAY_CONTROL.read_volatile() };
+    map.set_visible(!visible);
+    // Safety: 同上
+    let after = unsafe { DISPLAY_CONTROL.read_volatile() };
+    map.set_visible(visible);
+    let changed = (before ^ after) >> 8;
+    if changed.count_ones() != 1 || changed >= 1 << 4 {
+        log_warn!(
+            "DISPCNT {:#x} -> {:#x}; placing every tile with set_tile",
+            before,
+            after
+        );
+        return None;
+    }
+    Some(changed.trailing_zeros() as usize)
+}
+
+// 32x32 のマップでのセルの位置。
+fn offset((x, y): (u16, u16)) -> usize {
+    usize::from(y % SIZE) * usize::from(SIZE) + usize::from(x % SIZE)
+}
+
+impl Drop for Layer<'_> {
+    fn drop(&mut self) {
+        debug_assert!(
+            self.occupied.iter().all(|row| *row == 0),
+            "layer dropped without clear(); its tiles leak in VRAM"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn tiles_are_counted_until_cleared(gba: &mut agb::Gba) {
+        let (gfx, mut vram) = gba.display.video.tiled0();
+        let before = tiles_in_use();
+        let mut layer = Layer::new(&gfx, Priority::P0);
+        let setting = TileSetting::new(font::tile_for(b'A'), false, false, 0);
+        layer.set_tile(&mut vram, (0, 0), &font::TILESET, setting);
+        layer.set_tile(&mut vram, (1, 0), &font::TILESET, setting);
+        /* 同じセルに置き直しても増えない */
+        layer.set_tile(&mut vram, (1, 0), &font::TILESET, setting);
+        assert_eq!(tiles_in_use(), before + 2);
+        layer.clear_tile(&mut vram, (0, 0), &font::TILESET);
+        assert_eq!(tiles_in_use(), before + 1);
+        layer.release(&mut vram);
+        assert_eq!(tiles_in_use(), before);
+    }
+
+    // 行と列をまとめて書くと、1 つずつ置いたのと同じ画面になり、agb はマップを写し直さない。
+    #[test_case]
+    fn rows_and_columns_are_written_in_one_pass(gba: &mut agb::Gba) {
+        let (gfx, mut vram) = gba.display.video.tiled0();
+        let before = tiles_in_use();
+        let tile = |ch: u8| Some(Tile::new(font::tile_for(ch), 0));
+        let setting = |ch: u8| tile(ch).unwrap().setting;
+        let mut layer = Layer::new(&gfx, Priority::P0);
+        layer.set_tile(&mut vram, (0, 0), &font::TILESET, setting(b'A'));
+        let row = [tile(b'A'), tile(b'A'), None, tile(b'B')];
+        /* 下端から上端へ回り込む */
+        let column = [tile(b'C'), None, tile(b'C')];
+        layer.write_row(&mut vram, (3, 5), &font::TILESET, &row);
+        layer.write_column(&mut vram, (8, 31), &font::TILESET, &column);
+        /* 初めて使うタイルは参照を置くので agb が写す */
+        layer.commit(&mut vram);
+        assert_eq!(tiles_in_use(), before + 1 + 5 + 3);
+
+        /* 2 度目からは写さない。写せば消える目印を、agb の知らないセルに書いておく */
+        let screenblock = layer.screenblock();
+        // Safety: 32x32 のマップの中
+        unsafe { screenblock.add(offset((29, 0))).write_volatile(0x1234) };
+        layer.clear_row(&mut vram, 5);
+        layer.write_row(&mut vram, (3, 5), &font::TILESET, &row);
+        layer.write_column(&mut vram, (8, 31), &font::TILESET, &column);
+        layer.commit(&mut vram);
+        // Safety: 同上
+        assert_eq!(
+            unsafe { screenblock.add(offset((29, 0))).read_volatile() },
+            0x1234
+        );
+        assert_eq!(tiles_in_use(), before + 1 + 5 + 3);
+
+        let mut expected = Layer::new(&gfx, Priority::P1);
+        expected.set_tile(&mut vram, (0, 0), &font::TILESET, setting(b'A'));
+        for (pos, entry) in [((3, 5), b'A'), ((4, 5), b'A'), ((6, 5), b'B')] {
+            expected.set_tile(&mut vram, pos, &font::TILESET, setting(entry));
+        }
+        for pos in [(8, 31), (8, 1)] {
+            expected.set_tile(&mut vram, pos, &font::TILESET, setting(b'C'));
+        }
+        expected.commit(&mut vram);
+        let read = |layer: &Layer, pos| {
+            // Safety: 同上
+            unsafe { layer.screenblock().add(offset(pos)).read_volatile() }
+        };
+        for y in 0..SIZE {
+            for x in 0..VISIBLE_COLUMNS - 1 {
+                assert_eq!(read(&layer, (x, y)), read(&expected, (x, y)), "({x}, {y})");
+            }
+        }
+        /* 空白を書くと片付き、agb のセルも直接セルで上書きできる */
+        layer.write_row(&mut vram, (3, 5), &font::TILESET, &[None; 4]);
+        layer.write_row(&mut vram, (0, 0), &font::TILESET, &[tile(b'B')]);
+        layer.commit(&mut vram);
+        assert_eq!(read(&layer, (3, 5)), 0);
+        assert_eq!(read(&layer, (0, 0)), read(&expected, (6, 5)));
+        assert_eq!(tiles_in_use(), before + 1 + 2 + 3 + 6);
+        layer.release(&mut vram);
+        expected.release(&mut vram);
+        assert_eq!(tiles_in_use(), before);
+    }
+
+    // 直接書いたセルは、1 つずつ置いたセルと VRAM のマップで同じ値になる。
+    #[test_case]
+    fn a_fill_matches_tiles_placed_one_by_one(gba: &mut agb::Gba) {
+        let (gfx, mut vram) = gba.display.video.tiled0();
+        let before = tiles_in_use();
+        let tile = Tile::new(font::tile_for(b'#'), 3);
+        let mut tiles = Layer::new(&gfx, Priority::P0);
+        for y in 10..13 {
+            for x in 2..30 {
+                tiles.set_tile(&mut vram, (x, y), &font::TILESET, tile.setting);
+            }
+        }
+        tiles.commit(&mut vram);
+        let mut filled = Layer::new(&gfx, Priority::P1);
+        filled.fill(&mut vram, 2..30, 10..13, &font::TILESET, tile);
+        filled.commit(&mut vram);
+        /* 参照のセルが 1 つ増える */
+        assert_eq!(tiles_in_use(), before + 2 * 28 * 3 + 1);
+        /* 画面に映る範囲だけ比べる */
+        let entries = |layer: &Layer| -> alloc::vec::Vec<u16> {
+            let screenblock = layer.screenblock();
+            (0..SIZE * SIZE)
+                .filter(|offset| offset % SIZE < VISIBLE_COLUMNS)
+                // Safety: 32x32 のマップの中
+                .map(|offset| unsafe { screenblock.add(usize::from(offset)).read_volatile() })
+                .collect()
+        };
+        assert_eq!(entries(&filled), entries(&tiles));
+        /* スクロールしただけの commit でも消えない */
+        filled.set_scroll_y(8);
+        filled.commit(&mut vram);
+        tiles.set_scroll_y(8);
+        tiles.commit(&mut vram);
+        assert_eq!(entries(&filled), entries(&tiles));
+
+        /* 行を片付けても、残りの行はそのまま */
+        filled.clear_row(&mut vram, 10);
+        tiles.clear_row(&mut vram, 10);
+        filled.clear_tile(&mut vram, (5, 11), &font::TILESET);
+        tiles.clear_tile(&mut vram, (5, 11), &font::TILESET);
+        filled.commit(&mut vram);
+        tiles.commit(&mut vram);
+        assert_eq!(entries(&filled), entries(&tiles));
+        /* agb のセルに置き直すと、agb がマップを写しても他の直接セルは消えない */
+        let other = TileSetting::new(font::tile_for(b'@'), false, false, 3);
+        filled.set_tile(&mut vram, (6, 11), &font::TILESET, other);
+        tiles.set_tile(&mut vram, (6, 11), &font::TILESET, other);
+        filled.commit(&mut vram);
+        tiles.commit(&mut vram);
+        assert_eq!(entries(&filled), entries(&tiles));
+        assert_eq!(tiles_in_use(), before + 2 * (28 * 2 - 1) + 1);
+        filled.release(&mut vram);
+        tiles.release(&mut vram);
+        assert_eq!(tiles_in_use(), before);
+    }
+
+    // clear_cells のあとに置き直すと、前に置いたものは残らず、初めから置いたのと同じ画面になる。
+    #[test_case]
+    fn cells_laid_again_after_clear_cells_match_a_fresh_layer(gba: &mut agb::Gba) {
+        let (gfx, mut vram) = gba.display.video.tiled0();
+        let before = tiles_in_use();
+        let a = Tile::new(font::tile_for(b'A'), 0);
+        let b = Tile::new(font::tile_for(b'B'), 0);
+        let mut layer = Layer::persistent(&gfx, Priority::P0);
+        layer.fill(&mut vram, 0..10, 3..6, &font::TILESET, a);
+        layer.set_tile(&mut vram, (12, 4), &font::TILESET, b.setting);
+        layer.commit(&mut vram);
+        layer.clear_cells(&mut vram);
+        /* 同じタイルを置き直すセル、違うタイルにするセル、空けたままのセルが混ざる */
+        layer.fill(&mut vram, 0..4, 3..5, &font::TILESET, a);
+        layer.fill(&mut vram, 4..8, 3..4, &font::TILESET, b);
+        layer.commit(&mut vram);
+        /* 数えないレイヤーなので増えない */
+        assert_eq!(tiles_in_use(), before);
+
+        let mut fresh = Layer::new(&gfx, Priority::P1);
+        fresh.fill(&mut vram, 0..4, 3..5, &font::TILESET, a);
+        fresh.fill(&mut vram, 4..8, 3..4, &font::TILESET, b);
+        fresh.commit(&mut vram);
+        let entries = |layer: &Layer| -> alloc::vec::Vec<u16> {
+            let screenblock = layer.screenblock();
+            (0..SIZE * SIZE)
+                .filter(|offset| offset % SIZE < VISIBLE_COLUMNS)
+                // Safety: 32x32 のマップの中
+                .map(|offset| unsafe { screenblock.add(usize::from(offset)).read_volatile() })
+                .collect()
+        };
+        assert_eq!(entries(&layer), entries(&fresh));
+        layer.release(&mut vram);
+        fresh.release(&mut vram);
+        assert_eq!(tiles_in_use(), before);
+    }
+
+    // 直接セルを使わないレイヤーでも、fill と write_row は直接セルと同じ画面になる。
+    #[test_case]
+    fn without_direct_cells_the_same_tiles_are_placed(gba: &mut agb::Gba) {
+        let (gfx, mut vram) = gba.display.video.tiled0();
+        let before = tiles_in_use();
+        let tile = Tile::new(font::tile_for(b'#'), 3);
+        let row = [Some(tile), None, Some(tile.hflip(true))];
+        let mut direct = Layer::new(&gfx, Priority::P0);
+        let mut plain = Layer::new(&gfx, Priority::P1).without_direct_cells();
+        for layer in [&mut direct, &mut plain] {
+            layer.fill(&mut vram, 2..30, 10..13, &font::TILESET, tile);
+            layer.write_row(&mut vram, (4, 5), &font::TILESET, &row);
+            layer.commit(&mut vram);
+        }
+        assert!(plain.references.is_empty());
+        let entries = |layer: &Layer| -> alloc::vec::Vec<u16> {
+            let screenblock = layer.screenblock();
+            (0..SIZE * SIZE)
+                .filter(|offset| offset % SIZE < VISIBLE_COLUMNS)
+                // Safety: 32x32 のマップの中
+                .map(|offset| unsafe { screenblock.add(usize::from(offset)).read_volatile() })
+                .collect()
+        };
+        assert_eq!(entries(&plain), entries(&direct));
+        direct.release(&mut vram);
+        plain.release(&mut vram);
+        assert_eq!(tiles_in_use(), before);
+    }
+
+    // 映しているレイヤーがあるときに次のレイヤーを作っても、前のレイヤーは映ったまま (一瞬消すだけ) で、
+    // 次のレイヤーは前のレイヤーの背景と取り違えずに自分の背景へ直接書く。
+    #[test_case]
+    fn making_a_layer_keeps_a_visible_one_and_finds_its_own_background(gba: &mut agb::Gba) {
+        let (gfx, mut vram) = gba.display.video.tiled0();
+        let before = tiles_in_use();
+        let tile = Tile::new(font::tile_for(b'#'), 0);
+        // Safety: DISPCNT は読み出せる
+        let shown = |background: usize| unsafe {
+            DISPLAY_CONTROL.read_volatile() & (1 << (8 + background)) != 0
+        };
+        let mut first = Layer::new(&gfx, Priority::P0);
+        first.set_visible(true);
+        first.fill(&mut vram, 0..4, 0..1, &font::TILESET, tile);
+        first.commit(&mut vram);
+        assert!(shown(first.background));
+
+        let mut second = Layer::new(&gfx, Priority::P1);
+        assert!(shown(first.background));
+        assert!(second.direct_cells);
+        assert_ne!(second.background, first.background);
+        assert_eq!(shown(second.background), second.map.is_visible());
+        second.fill(&mut vram, 0..4, 2..3, &font::TILESET, tile);
+        second.commit(&mut vram);
+        let read = |layer: &Layer, pos| {
+            // Safety: 32x32 のマップの中
+            unsafe { layer.screenblock().add(offset(pos)).read_volatile() }
+        };
+        /* 参照のセルは agb が置いたもの。取り違えていれば別の背景から空白を読んで書く */
+        let reference = read(&second, second.references[0].cell);
+        assert_ne!(reference, 0);
+        for x in 0..4 {
+            assert_eq!(read(&second, (x, 2)), reference, "({x}, 2)");
+            assert_eq!(read(&first, (x, 2)), 0, "({x}, 2)");
+        }
+        second.release(&mut vram);
+        first.release(&mut vram);
+        assert_eq!(tiles_in_use(), before);
+    }
+
+    // 4 枚の背景のどれに割り当てられても、レイヤーは自分の背景の番号を見つけ、その背景のマップにだけ直接書く。
+    #[test_case]
+    fn every_background_slot_is_found(gba: &mut agb::Gba) {
+        let (gfx, mut vram) = gba.display.video.tiled0();
+        let before = tiles_in_use();
+        let tile = Tile::new(font::tile_for(b'#'), 0);
+        // Safety: DISPCNT は読み出せる
+        let shown = || unsafe { (DISPLAY_CONTROL.read_volatile() >> 8) & 0xf };
+        let mut layers: Vec<Layer> = Vec::new();
+        for (slot, priority) in [Priority::P0, Priority::P1, Priority::P2, Priority::P3]
+            .into_iter()
+            .enumerate()
+        {
+            let mut layer = Layer::new(&gfx, priority);
+            assert!(layer.direct_cells, "slot {slot}");
+            /* 探し直しても同じ番号 */
+            assert_eq!(background_of(&mut layer.map), Some(layer.background));
+            /* 映すと自分のビットだけが立つ */
+            let others = shown();
+            layer.set_visible(true);
+            assert_eq!(shown(), others | 1 << layer.background, "slot {slot}");
+            let row = slot as u16;
+            layer.fill(&mut vram, 0..4, row..row + 1, &font::TILESET, tile);
+            layer.commit(&mut vram);
+            layers.push(layer);
+        }
+        let mut found: Vec<usize> = layers.iter().map(|layer| layer.background).collect();
+        found.sort_unstable();
+        assert_eq!(found, [0, 1, 2, 3]);
+        let read = |layer: &Layer, pos| {
+            // Safety: 32x32 のマップの中
+            unsafe { layer.screenblock().add(offset(pos)).read_volatile() }
+        };
+        for (slot, layer) in layers.iter().enumerate() {
+            let reference = read(layer, layer.references[0].cell);
+            assert_ne!(reference, 0);
+            for row in 0..4 {
+                let expected = if row == slot { reference } else { 0 };
+                assert_eq!(
+                    read(layer, (0, row as u16)),
+                    expected,
+                    "slot {slot}, row {row}"
+                );
+            }
+        }
+        for layer in layers {
+            layer.release(&mut vram);
+        }
+        assert_eq!(tiles_in_use(), before);
+    }
+}
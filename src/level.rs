@@ -0,0 +1,361 @@
+// レベルの読み込み。
+//
+// レベルは画面 1 枚分で、ドロイド君の出発点、りんご、コインと他のりんご、水と風、トリガーゾーン、テレポーター (LEVEL_OBJECTS)、動く足場やエレベーター、崩れる足場、ばね、スイッチ (LEVEL_PLATFORMS) と
+// 地面の表面のタイルの並び (LEVEL_GROUND。氷のような材質もここから決まる)、地面の形 (LEVEL_TERRAIN)、
+// 地面から立つ壁 (LEVEL_WALLS)、地面のベルトコンベア (LEVEL_CONVEYORS)、浮かべた壊せるブロック (LEVEL_BLOCKS)、
+// スイッチのブロック (LEVEL_SWITCH_BLOCKS) とはしご (LEVEL_LADDERS) だけでできている。コインラッシュで足すコインと鍵、扉、棘は別の層 (RUSH_OBJECTS) に持つ。
+// チュートリアル (レベル 0) はそれに加えて仕掛けの並び (script モジュール) を持ち、画面端が壁になっている。
+// ミラーモードでは読み込むときに左右を反転し (x → 画面の幅 - x - 物の幅) 、
+// 向きのあるデータ (ドロイド君の向き、地面の左端と右端のタイル、坂の向き、ベルトの流れる向き、横の風の向き) も入れ替えておく。
+// なので Game も背景も反転したことを知らずに普通のレベルとして扱い、毎フレームの手間も無い。
+
+use alloc::vec::Vec;
+
+use agb::display::WIDTH;
+
+use crate::{
+    crumbling::CRUMBLING_PLATFORM_WIDTH,
+    door::{DOOR_WIDTH, KEY_SIZE},
+    game::{
+        Game, APPLE_SIZE, COIN_SIZE, DROID_WIDTH, GROUND_Y, LEVEL_BLOCKS, LEVEL_CONVEYORS,
+        LEVEL_GROUND, LEVEL_LADDERS, LEVEL_OBJECTS, LEVEL_PLATFORMS, LEVEL_SWITCH_BLOCKS,
+        LEVEL_TERRAIN, LEVEL_WALLS, RUSH_OBJECTS,
+    },
+    gameplay::{GROUND_LEFT, GROUND_RIGHT, GROUND_TOP},
+    math::Fixed,
+    moving_platform::MOVING_PLATFORM_WIDTH,
+    script::Trigger,
+    spawn::EntityKind,
+    spike::SPIKE_SIZE,
+    spring::SPRING_SIZE,
+    switch::{BlockSet, SWITCH_SIZE},
+    teleporter::TELEPORTER_WIDTH,
+    terrain::Terrain,
+    tutorial::TUTORIAL_SCRIPT,
+};
+
+/* 地面の列数 (画面の幅) */
+pub const GROUND_COLUMNS: usize = 30;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Level {
+    /* ドロイド君の出発点 (左上) と向き */
+    pub droid: (i32, i32),
+    pub hflip: bool,
+    /* りんごの左上 */
+    pub apple: (i32, i32),
+    pub objects: Vec<(EntityKind, (i32, i32))>,
+    /* コインラッシュのときだけ置く物 */
+    pub rush: Vec<(EntityKind, (i32, i32))>,
+    /* 動く足場 */
+    pub platforms: Vec<(EntityKind, (i32, i32))>,
+    /* 地面の表面のタイル (tiles::bg.tile_settings の添字) を左の列から */
+    pub ground: [usize; GROUND_COLUMNS],
+    /* 地面の形を左の列から */
+    pub terrain: [Terrain; GROUND_COLUMNS],
+    /* 地面から立つ固い壁のタイルの段数を左の列から (0 なら無し)。平らな列にだけ立てる */
+    pub walls: [usize; GROUND_COLUMNS],
+    /*
+     * ベルトコンベアが上に立っているものを流す速さ (1 ティックのドット数、右向きが正) を左の列から。
+     * 0 ならベルトの無い地面。平らで壁も材質も無い列にだけ置く
+     */
+    pub conveyors: [Fixed; GROUND_COLUMNS],
+    /* 壊せるブロックを置く背景の行 (y / 8) を左の列から (None なら無し)。1 列に 1 つまで */
+    pub blocks: [Option<u16>; GROUND_COLUMNS],
+    /* スイッチで固さの入れ替わるブロックの行と組を左の列から。1 列に 1 つまでで、壊せるブロックとは別の列に置く */
+    pub switch_blocks: [Option<(u16, BlockSet)>; GROUND_COLUMNS],
+    /* はしごの上端の行を左の列から (None なら無し)。地面の上面まで掛ける。平らで壁もブロックも無い列にだけ置く */
+    pub ladders: [Option<u16>; GROUND_COLUMNS],
+    /* 仕掛け (チュートリアルのみ)。チュートリアルは反転しないので、反転しても変えない */
+    pub script: &'static [Trigger],
+    /* 画面端が壁か。false なら回り込む */
+    pub solid_edges: bool,
+}
+
+pub const fn ground() -> [usize; GROUND_COLUMNS] {
+    let mut ground = [GROUND_TOP; GROUND_COLUMNS];
+    ground[0] = GROUND_LEFT;
+    ground[GROUND_COLUMNS - 1] = GROUND_RIGHT;
+    ground
+}
+
+impl Level {
+    // mirror ならミラーモードの、左右を反転したレベル。
+    pub fn load(mirror: bool) -> Self {
+        let level = Self {
+            droid: (120, GROUND_Y),
+            hflip: false,
+            apple: (160, 120),
+            objects: LEVEL_OBJECTS.to_vec(),
+            rush: RUSH_OBJECTS.to_vec(),
+            platforms: LEVEL_PLATFORMS.to_vec(),
+            ground: LEVEL_GROUND,
+            terrain: LEVEL_TERRAIN,
+            walls: LEVEL_WALLS,
+            conveyors: LEVEL_CONVEYORS,
+            blocks: LEVEL_BLOCKS,
+            switch_blocks: LEVEL_SWITCH_BLOCKS,
+            ladders: LEVEL_LADDERS,
+            script: &[],
+            solid_edges: false,
+        };
+        if mirror {
+            level.mirrored()
+        } else {
+            level
+        }
+    }
+
+    // チュートリアル (レベル 0)。左端から始め、関門を開けながら右端のりんごを目指す。
+    pub fn tutorial() -> Self {
+        Self {
+            droid: (8, GROUND_Y),
+            hflip: false,
+            apple: (208, 120),
+            objects: Vec::new(),
+            rush: Vec::new(),
+            platforms: Vec::new(),
+            ground: ground(),
+            terrain: [Terrain::Flat; GROUND_COLUMNS],
+            walls: [0; GROUND_COLUMNS],
+            conveyors: [Fixed::new(0); GROUND_COLUMNS],
+            blocks: [None; GROUND_COLUMNS],
+            switch_blocks: [None; GROUND_COLUMNS],
+            ladders: [None; GROUND_COLUMNS],
+            script: &TUTORIAL_SCRIPT,
+            solid_edges: true,
+        }
+    }
+
+    fn mirrored(&self) -> Self {
+        let (droid_x, droid_y) = self.droid;
+        let (apple_x, apple_y) = self.apple;
+        let mut ground = self.ground;
+        ground.reverse();
+        let mut terrain = self.terrain;
+        terrain.reverse();
+        let mut walls = self.walls;
+        walls.reverse();
+        let mut conveyors = self.conveyors;
+        conveyors.reverse();
+        let mut blocks = self.blocks;
+        blocks.reverse();
+        let mut switch_blocks = self.switch_blocks;
+        switch_blocks.reverse();
+        let mut ladders = self.ladders;
+        ladders.reverse();
+        Self {
+            droid: (mirror_x(droid_x, DROID_WIDTH), droid_y),
+            hflip: !self.hflip,
+            apple: (mirror_x(apple_x, APPLE_SIZE), apple_y),
+            objects: self.objects.iter().copied().map(mirror_object).collect(),
+            rush: self.rush.iter().copied().map(mirror_object).collect(),
+            platforms: self.platforms.iter().copied().map(mirror_object).collect(),
+            ground: ground.map(mirror_tile),
+            terrain: terrain.map(Terrain::mirrored),
+            walls,
+            conveyors: conveyors.map(|speed| -speed),
+            blocks,
+            switch_blocks,
+            ladders,
+            script: self.script,
+            solid_edges: self.solid_edges,
+        }
+    }
+}
+
+impl Game {
+    // 今遊んでいるレベル (ミラーモードなら反転したもの)。
+    pub fn level(&self) -> Level {
+        if self.script.is_some() {
+            Level::tutorial()
+        } else if let Some(speedrun) = &self.speedrun {
+            speedrun.level(self.modifiers.mirror)
+        } else {
+            Level::load(self.modifiers.mirror)
+        }
+    }
+}
+
+fn mirror_x(x: i32, width: i32) -> i32 {
+    WIDTH - x - width
+}
+
+fn mirror_object((kind, (x, y)): (EntityKind, (i32, i32))) -> (EntityKind, (i32, i32)) {
+    let width = width(kind);
+    let kind = match kind {
+        /* 往復する先も反転する */
+        EntityKind::MovingPlatform {
+            to: (to_x, to_y),
+            passage,
+        } => EntityKind::MovingPlatform {
+            to: (mirror_x(to_x, width), to_y),
+            passage,
+        },
+        /* 横の風は反対へ吹く */
+        EntityKind::Wind {
+            size,
+            force: (force_x, force_y),
+        } => EntityKind::Wind {
+            size,
+            force: (-force_x, force_y),
+        },
+        kind => kind,
+    };
+    (kind, (mirror_x(x, width), y))
+}
+
+fn width(kind: EntityKind) -> i32 {
+    match kind {
+        EntityKind::Coin => COIN_SIZE,
+        EntityKind::Apple => APPLE_SIZE,
+        EntityKind::MovingPlatform { .. } | EntityKind::Elevator { .. } => MOVING_PLATFORM_WIDTH,
+        EntityKind::CrumblingPlatform => CRUMBLING_PLATFORM_WIDTH,
+        EntityKind::Spring => SPRING_SIZE,
+        EntityKind::Switch => SWITCH_SIZE,
+        EntityKind::Key => KEY_SIZE,
+        EntityKind::Door => DOOR_WIDTH,
+        EntityKind::Spike => SPIKE_SIZE,
+        EntityKind::Teleporter { .. } => TELEPORTER_WIDTH,
+        EntityKind::Water { size: (width, _) }
+        | EntityKind::Wind {
+            size: (width, _), ..
+        }
+        | EntityKind::Zone {
+            size: (width, _), ..
+        } => width,
+    }
+}
+
+// 左右反転したときに代わりに置くタイル。端のタイルだけ左右で別の絵になっている。
+fn mirror_tile(index: usize) -> usize {
+    match index {
+        GROUND_LEFT => GROUND_RIGHT,
+        GROUND_RIGHT => GROUND_LEFT,
+        index => index,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{difficulty::Difficulty, game::Game, modifiers::Modifiers, tilemap::CollisionMap};
+
+    // 反転したレベルの物は全て、普通のレベルの物を画面の中心で折り返した位置にある。
+    #[test_case]
+    fn a_mirrored_level_is_an_exact_reflection(_gba: &mut agb::Gba) {
+        let normal = Level::load(false);
+        let mirrored = Level::load(true);
+        let centre = |x: i32, width: i32| 2 * x + width;
+        assert_eq!(
+            centre(normal.droid.0, DROID_WIDTH) + centre(mirrored.droid.0, DROID_WIDTH),
+            2 * WIDTH
+        );
+        assert_eq!(normal.droid.1, mirrored.droid.1);
+        assert_ne!(normal.hflip, mirrored.hflip);
+        assert_eq!(
+            centre(normal.apple.0, APPLE_SIZE) + centre(mirrored.apple.0, APPLE_SIZE),
+            2 * WIDTH
+        );
+        let objects = normal
+            .objects
+            .iter()
+            .chain(&normal.rush)
+            .chain(&normal.platforms);
+        let mirrored_objects = mirrored
+            .objects
+            .iter()
+            .chain(&mirrored.rush)
+            .chain(&mirrored.platforms);
+        for (&(kind, (x, y)), &(mirrored_kind, (mirrored_x, mirrored_y))) in
+            objects.zip(mirrored_objects)
+        {
+            match (kind, mirrored_kind) {
+                (
+                    EntityKind::MovingPlatform { to, .. },
+                    EntityKind::MovingPlatform {
+                        to: mirrored_to, ..
+                    },
+                ) => {
+                    assert_eq!(to.0 + mirrored_to.0 + width(kind), WIDTH);
+                    assert_eq!(to.1, mirrored_to.1);
+                }
+                (
+                    EntityKind::Wind { size, force },
+                    EntityKind::Wind {
+                        size: mirrored_size,
+                        force: mirrored_force,
+                    },
+                ) => {
+                    assert_eq!(size, mirrored_size);
+                    assert_eq!((-force.0, force.1), mirrored_force);
+                }
+                _ => assert_eq!(kind, mirrored_kind),
+            }
+            assert_eq!(x + mirrored_x + width(kind), WIDTH);
+            assert_eq!(y, mirrored_y);
+            /* コインは背景のタイルに合わせて 8 の倍数のまま */
+            assert_eq!(mirrored_x % 8, 0);
+        }
+        assert_eq!(mirrored.ground[0], GROUND_LEFT);
+        assert_eq!(mirrored.ground[GROUND_COLUMNS - 1], GROUND_RIGHT);
+        /* ベルトは折り返した列で逆向きに流れる */
+        for (column, speed) in normal.conveyors.iter().enumerate() {
+            assert_eq!(mirrored.conveyors[GROUND_COLUMNS - 1 - column], -*speed);
+        }
+        assert!(normal.conveyors.iter().any(|&speed| speed != Fixed::new(0)));
+        /* ブロックは折り返した列の同じ行に */
+        for (column, row) in normal.blocks.iter().enumerate() {
+            assert_eq!(mirrored.blocks[GROUND_COLUMNS - 1 - column], *row);
+        }
+        assert!(normal.blocks.iter().any(Option::is_some));
+        for (column, block) in normal.switch_blocks.iter().enumerate() {
+            assert_eq!(mirrored.switch_blocks[GROUND_COLUMNS - 1 - column], *block);
+        }
+        for (column, ladder) in normal.ladders.iter().enumerate() {
+            assert_eq!(mirrored.ladders[GROUND_COLUMNS - 1 - column], *ladder);
+        }
+        /* 地面の高さも折り返した位置で同じ */
+        let (normal_map, mirrored_map) = (CollisionMap::new(&normal), CollisionMap::new(&mirrored));
+        for x in -DROID_WIDTH..WIDTH {
+            assert_eq!(
+                normal_map.floor_y(x, 0),
+                mirrored_map.floor_y(mirror_x(x, DROID_WIDTH), 0),
+                "x = {x}"
+            );
+        }
+        assert_eq!(mirrored.mirrored(), normal);
+
+        /* Game も反転したレベルをそのまま置く */
+        let game = Game::new(Difficulty::Normal, 1).with_modifiers(Modifiers {
+            mirror: true,
+            ..Modifiers::NONE
+        });
+        assert_eq!((game.droid.x, game.droid.y), mirrored.droid);
+        assert_eq!((game.apple.x, game.apple.y), mirrored.apple);
+        let coins: alloc::vec::Vec<_> = game
+            .coins
+            .iter()
+            .map(|(_, coin)| (coin.x, coin.y))
+            .collect();
+        let expected: alloc::vec::Vec<_> = mirrored
+            .objects
+            .iter()
+            .filter(|(kind, _)| *kind == EntityKind::Coin)
+            .map(|&(_, at)| at)
+            .collect();
+        assert_eq!(coins, expected);
+        let apples: alloc::vec::Vec<_> = game
+            .apples
+            .iter()
+            .map(|(_, apple)| (apple.x, apple.y))
+            .collect();
+        let expected: alloc::vec::Vec<_> = mirrored
+            .objects
+            .iter()
+            .filter(|(kind, _)| *kind == EntityKind::Apple)
+            .map(|&(_, at)| at)
+            .collect();
+        assert_eq!(apples, expected);
+    }
+}
@@ -0,0 +1,166 @@
+// 通信ケーブルでのやりとり。
+//
+// 相手と 16 ビットの値を 1 ティックに 1 つずつ交換するだけの Link として抽象化し、
+// 実機では SerialLink (マルチプレイモードの SIO) 、テストでは Loopback を使う。
+// 値の意味付け (握手や位置の送り方) は race モジュールが Link の上で行う。
+//
+// マルチプレイモードでは親機 (SI 端子が Low の方) が転送を始め、子機は送る値を置いておくだけ。
+// 転送の周期は両者のティックと揃っていないので、同じ値を 2 度受け取ったり、
+// 取りこぼしたりすることがある。上の層はそれを前提にしておくこと。
+
+#[cfg(test)]
+use alloc::{collections::VecDeque, rc::Rc};
+#[cfg(test)]
+use core::cell::{Cell, RefCell};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkError {
+    // ケーブルが抜けているか、相手の電源が入っていない。
+    Disconnected,
+}
+
+pub trait Link {
+    // 親機か。握手でどちらの値に合わせるかを決めるのに使う。
+    fn is_master(&self) -> bool;
+    // word を送り、前回の転送で相手から届いた値を受け取る。
+    // 今回は何も届かなかった (転送中、取りこぼし) なら None 。
+    fn exchange(&mut self, word: u16) -> Result<Option<u16>, LinkError>;
+}
+
+/* https://problemkaputt.de/gbatek.htm#siomultiplayermode */
+const SIOMULTI: *const u16 = 0x0400_0120 as *const u16;
+const SIOCNT: *mut u16 = 0x0400_0128 as *mut u16;
+const SIOMLT_SEND: *mut u16 = 0x0400_012a as *mut u16;
+const RCNT: *mut u16 = 0x0400_0134 as *mut u16;
+/* マルチプレイモード、115200bps */
+const SIOCNT_MULTIPLAYER: u16 = 0x2003;
+const SIOCNT_CHILD: u16 = 1 << 2;
+const SIOCNT_READY: u16 = 1 << 3;
+const SIOCNT_ERROR: u16 = 1 << 6;
+const SIOCNT_BUSY: u16 = 1 << 7;
+/* つながっていない相手の SIOMULTI に入る値 */
+const NO_PARTNER: u16 = 0xffff;
+
+// 通信ポートのマルチプレイモード。2 台だけを相手にする。
+pub struct SerialLink {
+    _private: (),
+}
+
+impl SerialLink {
+    // ポートをマルチプレイモードにする。通信ポートを使うのはこれ 1 つだけにすること。
+    pub fn open() -> Self {
+        // Safety: 通信ポートのレジスタは agb も他のモジュールも触らない
+        unsafe {
+            RCNT.write_volatile(0);
+            SIOCNT.write_volatile(SIOCNT_MULTIPLAYER);
+        }
+        Self { _private: () }
+    }
+
+    fn control(&self) -> u16 {
+        // Safety: 読むだけ
+        unsafe { SIOCNT.read_volatile() }
+    }
+}
+
+impl Link for SerialLink {
+    fn is_master(&self) -> bool {
+        self.control() & SIOCNT_CHILD == 0
+    }
+
+    fn exchange(&mut self, word: u16) -> Result<Option<u16>, LinkError> {
+        let control = self.control();
+        if control & SIOCNT_READY == 0 {
+            return Err(LinkError::Disconnected);
+        }
+        /* 前回の転送がまだ終わっていなければ、今回は送りも受け取りもしない */
+        if control & SIOCNT_BUSY != 0 {
+            return Ok(None);
+        }
+        /* 2 台なので、相手は自分でない方 */
+        let me = (control >> 4) & 0b11;
+        let received = if control & SIOCNT_ERROR != 0 {
+            None
+        } else {
+            // Safety: 転送が終わった後の受信レジスタを読むだけ
+            Some(unsafe { SIOMULTI.add(1 - me.min(1) as usize).read_volatile() })
+        };
+        // Safety: 転送中でないときに送る値を置き、親機なら転送を始める
+        unsafe {
+            SIOMLT_SEND.write_volatile(word);
+            if control & SIOCNT_CHILD == 0 {
+                SIOCNT.write_volatile(SIOCNT_MULTIPLAYER | SIOCNT_BUSY);
+            }
+        }
+        match received {
+            Some(NO_PARTNER) => Err(LinkError::Disconnected),
+            received => Ok(received),
+        }
+    }
+}
+
+// テスト用に、同じ本体の中で 2 つの Link をつなぐ。
+// 送った値は相手が次に exchange したときに届く。
+#[cfg(test)]
+#[derive(Clone)]
+pub struct Loopback {
+    master: bool,
+    inbox: Rc<RefCell<VecDeque<u16>>>,
+    outbox: Rc<RefCell<VecDeque<u16>>>,
+    /* 両端で共有する状態 */
+    plugged: Rc<Cell<bool>>,
+    dropping: Rc<Cell<bool>>,
+}
+
+#[cfg(test)]
+impl Loopback {
+    // (親機, 子機) 。
+    pub fn pair() -> (Self, Self) {
+        let a = Rc::new(RefCell::new(VecDeque::new()));
+        let b = Rc::new(RefCell::new(VecDeque::new()));
+        let plugged = Rc::new(Cell::new(true));
+        let dropping = Rc::new(Cell::new(false));
+        let master = Self {
+            master: true,
+            inbox: a.clone(),
+            outbox: b.clone(),
+            plugged: plugged.clone(),
+            dropping: dropping.clone(),
+        };
+        let child = Self {
+            master: false,
+            inbox: b,
+            outbox: a,
+            plugged,
+            dropping,
+        };
+        (master, child)
+    }
+
+    // ケーブルを抜く。
+    pub fn unplug(&self) {
+        self.plugged.set(false);
+    }
+
+    // true の間は両方向とも送った値が全て消える。
+    pub fn set_dropping(&self, dropping: bool) {
+        self.dropping.set(dropping);
+    }
+}
+
+#[cfg(test)]
+impl Link for Loopback {
+    fn is_master(&self) -> bool {
+        self.master
+    }
+
+    fn exchange(&mut self, word: u16) -> Result<Option<u16>, LinkError> {
+        if !self.plugged.get() {
+            return Err(LinkError::Disconnected);
+        }
+        if !self.dropping.get() {
+            self.outbox.borrow_mut().push_back(word);
+        }
+        Ok(self.inbox.borrow_mut().pop_front())
+    }
+}
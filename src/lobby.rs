@@ -0,0 +1,94 @@
+// 通信対戦の相手を待つシーン。
+// 握手が済んだらゲーム本編へ、B でタイトルへ戻る。
+
+use alloc::boxed::Box;
+
+use agb::{display::Priority, input::Button};
+
+use crate::{
+    difficulty::Difficulty,
+    gameplay::GameScene,
+    gfx::rgb5,
+    input::Input,
+    layer::Layer,
+    link::SerialLink,
+    modifiers::Modifiers,
+    race::{Handshake, Race},
+    scene::{Resources, Scene, Transition},
+    text::TextLayer,
+    title::TitleScene,
+};
+
+pub struct LobbyScene<'g> {
+    difficulty: Difficulty,
+    modifiers: Modifiers,
+    seed: u32,
+    /* 通信ポートは enter で開く */
+    race: Option<Race>,
+    shown: Option<Handshake>,
+    text: Option<TextLayer<'g>>,
+}
+
+impl LobbyScene<'_> {
+    // seed は親機になったときに 2 人で使う。
+    pub fn new(difficulty: Difficulty, modifiers: Modifiers, seed: u32) -> Self {
+        Self {
+            difficulty,
+            modifiers,
+            seed,
+            race: None,
+            shown: None,
+            text: None,
+        }
+    }
+}
+
+impl<'g> Scene<'g> for LobbyScene<'g> {
+    fn enter(&mut self, res: &mut Resources<'g>) {
+        res.vram.set_background_palette_colour(0, 0, rgb5(2, 2, 8));
+        let mut text = TextLayer::new(Layer::new(res.gfx, Priority::P0), &mut res.vram);
+        text.print_line(&mut res.vram, 6, format_args!("          LINK RACE"));
+        text.print_line(&mut res.vram, 14, format_args!("          B: CANCEL"));
+        text.set_visible(true);
+        text.commit(&mut res.vram);
+        self.text = Some(text);
+        self.race = Some(Race::new(Box::new(SerialLink::open()), self.seed));
+    }
+
+    fn update(&mut self, _res: &mut Resources<'g>, input: &Input) -> Transition<'g> {
+        if input.is_just_pressed(Button::B) {
+            return Transition::Replace(Box::new(TitleScene::new()));
+        }
+        let Some(race) = &mut self.race else {
+            return Transition::Stay;
+        };
+        let handshake = race.handshake();
+        if let Handshake::Ready { seed } = handshake {
+            log_info!("link race start, seed {}", seed);
+            let race = self.race.take().unwrap();
+            let scene = GameScene::race(self.difficulty, self.modifiers, seed, race);
+            return Transition::Replace(Box::new(scene));
+        }
+        self.shown = Some(handshake);
+        Transition::Stay
+    }
+
+    fn draw(&mut self, res: &mut Resources<'g>) {
+        let Some(text) = &mut self.text else {
+            return;
+        };
+        let message = match self.shown {
+            Some(Handshake::NoCable) => "    CONNECT THE LINK CABLE",
+            Some(Handshake::Mismatch) => "     VERSION MISMATCH",
+            _ => "    WAITING FOR PARTNER",
+        };
+        text.print_line(&mut res.vram, 10, format_args!("{}", message));
+        text.commit(&mut res.vram);
+    }
+
+    fn exit(&mut self, res: &mut Resources<'g>) {
+        if let Some(text) = self.text.take() {
+            text.release(&mut res.vram);
+        }
+    }
+}
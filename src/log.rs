@@ -0,0 +1,65 @@
+// mGBA のデバッグコンソールへのログ出力。
+//
+// `debug-tools` フィーチャーが無効なビルドでは各マクロは何も生成しない
+// (引数の型チェックのためだけに `if false` の中で format_args! を展開する)。
+// 有効なビルドでも、mGBA 上で動いていなければ何も書き込まない。
+// 書式化は agb::mgba が core::fmt でデバッグ出力レジスタへ直接書き込むので、
+// アロケーションは発生しない。1 メッセージは 255 バイトで切り詰められる。
+
+#[cfg(feature = "debug-tools")]
+use agb::mgba::{DebugLevel, Mgba};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+    Debug,
+    Info,
+    Warn,
+}
+
+// IWRAM に置いた処理からも呼ぶので、インライン展開させずに ROM に置いておく。
+#[cfg(feature = "debug-tools")]
+#[inline(never)]
+pub fn write(level: Level, args: core::fmt::Arguments) {
+    if let Some(mut mgba) = Mgba::new() {
+        let level = match level {
+            Level::Debug => DebugLevel::Debug,
+            Level::Info => DebugLevel::Info,
+            Level::Warn => DebugLevel::Warning,
+        };
+        let _ = mgba.print(args, level);
+    }
+}
+
+#[cfg(feature = "debug-tools")]
+macro_rules! log_at {
+    ($level:expr, $($arg:tt)*) => {
+        $crate::log::write($level, format_args!($($arg)*))
+    };
+}
+
+#[cfg(not(feature = "debug-tools"))]
+macro_rules! log_at {
+    ($level:expr, $($arg:tt)*) => {
+        if false {
+            let _ = ($level, format_args!($($arg)*));
+        }
+    };
+}
+
+macro_rules! log_debug {
+    ($($arg:tt)*) => {
+        log_at!($crate::log::Level::Debug, $($arg)*)
+    };
+}
+
+macro_rules! log_info {
+    ($($arg:tt)*) => {
+        log_at!($crate::log::Level::Info, $($arg)*)
+    };
+}
+
+macro_rules! log_warn {
+    ($($arg:tt)*) => {
+        log_at!($crate::log::Level::Warn, $($arg)*)
+    };
+}
@@ -18,272 +18,189 @@
 
 extern crate alloc;
 
-use agb::{
-    display::{
-        object::{Graphics, Sprite, Tag, TagMap},
-        tiled::{RegularBackgroundSize, TileFormat, TiledMap},
-        Priority, WIDTH,
-    },
-    input::Button,
-};
+#[macro_use]
+mod log;
+#[cfg(feature = "debug-tools")]
+#[macro_use]
+mod profiler;
 
-agb::include_background_gfx!(tiles,
-    "ff00ff", // 透過色p
-    bg => "gfx/bg.png");
-
-const GRAPHICS: &Graphics = agb::include_aseprite!("gfx/sprites.aseprite");
-const TAG_MAP: &TagMap = GRAPHICS.tags();
-
-const IDLE: &Tag = TAG_MAP.get("Idle");
-const WALKING: &Tag = TAG_MAP.get("Walking");
-const JUMPING: &Tag = TAG_MAP.get("Jumping");
-const APPLE: &Tag = TAG_MAP.get("Apple");
-const WINDOW: &Tag = TAG_MAP.get("Window");
-
-fn rgb5(r: u8, g: u8, b: u8) -> u16 {
-    let (r, g, b) = (r as u16, g as u16, b as u16);
-    (r) | ((g) << 5) | ((b) << 10)
+// debug-tools フィーチャーが無効なときは計測ごと消す
+#[cfg(not(feature = "debug-tools"))]
+macro_rules! profile_scope {
+    ($name:expr) => {};
 }
-
-// 過去実装で OBJ_CHAR (ATTR2_ID) で表現していた部分の互換処理
-fn sprite_for_char(ch: u16) -> &'static Sprite {
-    match ch {
-        2 => WALKING.sprite(0),
-        4 => WALKING.sprite(2),
-        6 => JUMPING.sprite(0),
-        8 => JUMPING.sprite(1),
-        10 => JUMPING.sprite(2),
-        _ => IDLE.sprite(0),
-    }
+#[cfg(not(feature = "debug-tools"))]
+macro_rules! profile_count {
+    ($name:expr, $n:expr) => {
+        let _ = $n;
+    };
 }
 
+mod arena;
+#[cfg(test)]
+mod bench;
+mod block;
+mod climb;
+mod coin_rush;
+mod collision;
+mod components;
+mod conveyor;
+mod crumbling;
+mod cutscene;
+mod debris;
+#[cfg(feature = "debug-tools")]
+mod debug_menu;
+#[cfg(feature = "debug-tools")]
+mod debug_tools;
+mod difficulty;
+mod door;
+mod entity;
+mod events;
+mod font;
+mod game;
+mod gameplay;
+mod get_ready;
+mod gfx;
+mod ghost;
+mod input;
+mod ladder;
+mod layer;
+mod level;
+mod link;
+mod lobby;
+mod math;
+mod memory;
+mod mode;
+mod modifiers;
+mod moving_platform;
+mod oam;
+mod pacing;
+mod panic_screen;
+mod pause;
+mod physics;
+mod player;
+mod practice;
+mod presenter;
+mod race;
+mod replay;
+mod resources;
+mod results;
+mod rng;
+mod save;
+mod scene;
+mod script;
+mod seed_entry;
+mod shockwave;
+mod snapshot;
+mod spawn;
+mod speedrun;
+mod spike;
+mod split_delta;
+mod spring;
+mod switch;
+mod teleporter;
+mod terrain;
+mod text;
+mod tilemap;
+mod time;
+mod time_attack;
+mod title;
+#[cfg(feature = "debug-tools")]
+mod tuner;
+mod turns;
+mod tutorial;
+#[cfg(feature = "debug-tools")]
+mod validate;
+mod water;
+mod wind;
+mod zone;
+
+use alloc::boxed::Box;
+
 // メイン関数は1つの引数を取り、値を返さない。
 // agb::entry 修飾子によって全てがお膳立てされる。
 // `agb` によってスタックとインタラプトハンドラのセットアップが正常に完了した時点で呼ばれる。
 // 関数内で利用するための `Gba` 構造体の生成も行われる。
+//
+// ここでは資源を用意してシーンにつなぐだけにする。中身は各モジュールが受け持つ。
+// 描画の素材は gfx と resources 、レベルの形は level 、ドロイド君は player 、
+// キー入力は input 、1 ティックの進め方は game 、画面の切り替えは scene 。
 #[agb::entry]
 fn main(mut gba: agb::Gba) -> ! {
     let vblank = agb::interrupt::VBlank::get();
+    /* 見張り番が画面を上書きしないよう、見張り番より先に検査する */
+    #[cfg(feature = "debug-tools")]
+    validate::check_or_halt();
+    let _watchdog = panic_screen::install_watchdog();
     // グラフィックスモード 0
-    let (gfx, mut vram) = gba.display.video.tiled0();
-    let mut input = agb::input::ButtonController::new();
-    // https://www.coranac.com/tonc/text/regbg.htm#ssec-ctrl-bgs
-    let mut bg0 = gfx.background(
-        Priority::P0,                           // BG0
-        RegularBackgroundSize::Background32x32, // BG_REG_32x32
-        TileFormat::FourBpp,                    // BG_4BPP 16 色
-    );
-    vram.set_background_palettes(tiles::PALETTES);
-    vram.set_background_palette_colour(
-        0, // パレットバンク番号
-        0, // パレット内の色番号
-        rgb5(15, 15, 31),
-    );
-
-    /* ドロイド君 */
-    let (mut dx, mut dy) = (120, 120);
+    let (gfx, vram) = gba.display.video.tiled0();
     let object = gba.display.object.get_managed();
-    let mut droid_object = object.object_sprite(IDLE.sprite(0));
-    droid_object.set_position((dx, dy)).set_z(0).show();
-    /* りんご */
-    let (ax, ay) = (160, 120);
-    let ax_range = (ax - 12)..=(ax + 12);
-    let a_top_y = ay - 13;
-    let mut apple_object = object.object_sprite(APPLE.sprite(0));
-    apple_object.set_position((ax, ay)).set_z(1).show();
-    /* 窓 */
-    let mut window_object = object.object_sprite(WINDOW.sprite(0));
-    window_object.set_position((40, 40)).set_z(1).show();
-
-    object.commit();
-
-    /* BG0 をセット */
-    let tileset = &tiles::bg.tiles;
-    bg0.set_tile(
-        &mut vram,
-        (0u16, 17u16),
-        tileset,
-        tiles::bg.tile_settings[5 * 32],
-    );
-    bg0.set_tile(
-        &mut vram,
-        (29u16, 17u16),
-        tileset,
-        tiles::bg.tile_settings[2 + 5 * 32],
-    );
-    for i in 1..29 {
-        bg0.set_tile(
-            &mut vram,
-            (i, 17u16),
-            tileset,
-            tiles::bg.tile_settings[1 + 5 * 32],
-        );
-    }
-    for xx in 0u16..30 {
-        for yy in 18u16..32 {
-            bg0.set_tile(
-                &mut vram,
-                (xx, yy),
-                tileset,
-                tiles::bg.tile_settings[3 + 5 * 32],
-            );
-        }
-    }
-    bg0.commit(&mut vram);
-    bg0.set_visible(true);
+    let objects = oam::ObjectPool::new(&object);
+    let sprites = resources::SpriteSet::load();
+    let mut res = scene::Resources {
+        gfx: &gfx,
+        vram,
+        oam: &objects,
+        sprites: &sprites,
+        save: save::open(&mut gba.save),
+        alpha: 0,
+        level_bg: None,
+        #[cfg(feature = "debug-tools")]
+        debug: Default::default(),
+    };
+    let mut input = agb::input::ButtonController::new();
+    #[cfg(feature = "debug-tools")]
+    let mut debug = debug_tools::DebugTools::new(&gfx, &mut res.vram, gba.timers.timers().timer2);
 
-    /*
-     * ドロイド君の状態。
-     * 0 => 待機
-     * 1 => ジャンプ準備中
-     * 2 => ジャンプ中
-     */
-    let mut state = 0u8;
-    /* ドロイド君の y 方向の速度 */
-    let mut vy = 0f32;
+    let mut scenes = scene::SceneStack::new(&mut res, Box::new(title::TitleScene::new()));
 
-    /* フレーム数用の変数 */
-    let mut f = 0u16;
-    /* 表示するキャラクタ */
-    let mut ch = 0u16;
-    /* 歩き状態 (0, 1, 2) */
-    let mut wstate = 0u8;
+    let mut pacer = pacing::FramePacer::new(panic_screen::vblank_count());
+    let mut accumulator = pacing::TickAccumulator::new(panic_screen::vblank_count());
 
     /* メインループ */
     loop {
         /* VBLANK 割り込み待ち */
         vblank.wait_for_vblank();
-        /* キー状態取得 */
-        input.update();
+        pacer.begin_frame(panic_screen::vblank_count());
+        let ticks = accumulator.consume(panic_screen::vblank_count());
+        panic_screen::feed();
 
-        match state {
-            /* 待機中 */
-            0 if input.is_just_pressed(Button::UP) => {
-                // ジャンプ開始
-                state = 1;
-                f = 0;
-                ch = 0;
-            }
-            0 => {
-                if input.is_pressed(Button::LEFT) {
-                    dx -= 1;
-                    droid_object.set_hflip(true);
-                }
-                if input.is_pressed(Button::RIGHT) {
-                    dx += 1;
-                    droid_object.set_hflip(false);
-                }
-                if dx < -16 {
-                    dx = WIDTH;
-                }
-                if input.is_just_pressed(Button::LEFT) || input.is_just_pressed(Button::RIGHT) {
-                    wstate = 0;
-                    f = 0;
-                }
-                if input.is_just_released(Button::LEFT) || input.is_just_released(Button::RIGHT) {
-                    ch = 0;
-                }
-                if WIDTH < dx {
-                    dx = -16;
-                }
-                if input.is_pressed(Button::LEFT) || input.is_pressed(Button::RIGHT) {
-                    /* 歩きモーション */
-                    f += 1;
-                    if 5 < f {
-                        match wstate {
-                            0 => {
-                                wstate = 1;
-                                ch = 2;
-                            }
-                            1 => {
-                                wstate = 2;
-                                ch = 0;
-                            }
-                            2 => {
-                                wstate = 3;
-                                ch = 4
-                            }
-                            _ => {
-                                wstate = 0;
-                                ch = 0;
-                            }
-                        }
-                        f = 0
-                    }
-                }
-                if dy == a_top_y && !ax_range.contains(&dx) {
-                    /* りんごから落ちる */
-                    vy = -0.;
-                    state = 2;
-                    wstate = 0;
+        /* メニューを開いている間に溜まったティックは捨てる */
+        #[cfg(feature = "debug-tools")]
+        let ticks = if debug.begin_frame(&mut res, &mut accumulator) {
+            ticks
+        } else {
+            0
+        };
+        {
+            profile_scope!("update");
+            for _ in 0..ticks {
+                /* キー状態はティックごとに取る。同じフレームで 2 回目なら押しっぱなし扱いになる */
+                input.update();
+                let input = input::Input::sample(&input);
+                if input.is_just_pressed(input::SOFT_RESET) {
+                    log_info!("soft reset");
+                    scenes.reset(&mut res, Box::new(title::TitleScene::new()));
                 } else {
-                    droid_object
-                        .set_position((dx, dy))
-                        .set_sprite(object.sprite(sprite_for_char(ch)));
-                }
-            }
-            1 | 3 => {
-                /* ジャンプ準備 */
-                droid_object.set_sprite(object.sprite(sprite_for_char(6)));
-                f += 1;
-                if 3 < f {
-                    vy = 4.;
-                    if 1 == state {
-                        state = 2
-                    } else {
-                        state = 4
-                    };
+                    scenes.update(&mut res, &input);
                 }
             }
-            2 if input.is_just_pressed(Button::UP) => {
-                /* 二段ジャンプ */
-                state = 3;
-                f = 0;
-            }
-            2 | 4 => {
-                /* ジャンプ中 */
-                if input.is_pressed(Button::LEFT) {
-                    dx -= 1;
-                    droid_object.set_hflip(true);
-                }
-                if input.is_pressed(Button::RIGHT) {
-                    dx += 1;
-                    droid_object.set_hflip(false);
-                }
-                if dx < -16 {
-                    dx = WIDTH;
-                }
-                if WIDTH < dx {
-                    dx = -16;
-                }
-                if 0.5 < vy && input.is_pressed(Button::UP) {
-                    vy += 0.2;
-                }
-                dy -= vy as i32;
-                if vy < 0. {
-                    droid_object.set_sprite(object.sprite(sprite_for_char(10)));
-                } else {
-                    droid_object.set_sprite(object.sprite(sprite_for_char(8)));
-                }
-                if dy < 0 {
-                    dy = 0;
-                    vy = -0.;
-                }
-                if (vy < 0.) && ax_range.contains(&dx) && ( a_top_y < dy ) {
-                    /* りんごに乗る */
-                    dy = a_top_y;
-                    state = 0;
-                }
-                if 120 < dy {
-                    /* 着地 */
-                    dy = 120;
-                    state = 0;
-                }
-                droid_object.set_position((dx, dy));
-                vy -= 0.3;
-            }
-            _ => {}
         }
-        object.commit();
+
+        {
+            profile_scope!("render");
+            res.alpha = accumulator.alpha();
+            scenes.draw(&mut res);
+        }
+
+        #[cfg(feature = "debug-tools")]
+        debug.end_frame(&mut res, scenes.leaks(), pacer.overruns());
+
+        let missed = pacer.end_frame(panic_screen::vblank_count());
+        if missed > 0 {
+            log_warn!("frame overran by {} vblank(s)", missed);
+            #[cfg(feature = "debug-tools")]
+            debug.overran();
+        }
     }
 }
@@ -0,0 +1,173 @@
+// 整数と固定小数点の計算。
+//
+// 浮動小数点の三角関数はソフトウェア実装で遅く、ROM も食うので使わない。
+// 角度は 1 周を ANGLE_STEPS 等分した整数で表し、sin/cos は 1/4 周分の表を
+// 対称性で折り返して引く。戻り値は 24.8 の固定小数点 (Fixed) 。
+//
+// SINE_TABLE は round(sin(i / 1024 * 2π) * 256) を i = 0..256 について並べたもの。
+
+// Fixed はドロイド君の縦の速度 (Droid::vy) に使っている。
+// 三角関数と isqrt、abs、sign、lerp は揺れや雪の舞い等の使い道が揃うまではテストからしか呼ばれない。
+
+use agb::fixnum::Num;
+
+pub type Fixed = Num<i32, 8>;
+
+/* 1 周 */
+pub const ANGLE_STEPS: i32 = 1024;
+const QUARTER: i32 = ANGLE_STEPS / 4;
+/* Fixed の 1.0 */
+const ONE: i32 = 1 << 8;
+
+static SINE_TABLE: [u16; QUARTER as usize] = [
+    0, 2, 3, 5, 6, 8, 9, 11, 13, 14, 16, 17, 19, 20, 22, 24, 25, 27, 28, 30, 31, 33, 34, 36, 38,
+    39, 41, 42, 44, 45, 47, 48, 50, 51, 53, 55, 56, 58, 59, 61, 62, 64, 65, 67, 68, 70, 71, 73, 74,
+    76, 77, 79, 80, 82, 83, 85, 86, 88, 89, 91, 92, 94, 95, 97, 98, 99, 101, 102, 104, 105, 107,
+    108, 109, 111, 112, 114, 115, 117, 118, 119, 121, 122, 123, 125, 126, 128, 129, 130, 132, 133,
+    134, 136, 137, 138, 140, 141, 142, 144, 145, 146, 147, 149, 150, 151, 152, 154, 155, 156, 157,
+    159, 160, 161, 162, 164, 165, 166, 167, 168, 170, 171, 172, 173, 174, 175, 177, 178, 179, 180,
+    181, 182, 183, 184, 185, 186, 188, 189, 190, 191, 192, 193, 194, 195, 196, 197, 198, 199, 200,
+    201, 202, 203, 204, 205, 206, 207, 207, 208, 209, 210, 211, 212, 213, 214, 215, 215, 216, 217,
+    218, 219, 220, 220, 221, 222, 223, 224, 224, 225, 226, 227, 227, 228, 229, 229, 230, 231, 231,
+    232, 233, 233, 234, 235, 235, 236, 237, 237, 238, 238, 239, 239, 240, 241, 241, 242, 242, 243,
+    243, 244, 244, 245, 245, 245, 246, 246, 247, 247, 248, 248, 248, 249, 249, 249, 250, 250, 250,
+    251, 251, 251, 252, 252, 252, 252, 253, 253, 253, 253, 254, 254, 254, 254, 254, 255, 255, 255,
+    255, 255, 255, 255, 256, 256, 256, 256, 256, 256, 256, 256, 256, 256,
+];
+
+// 0..=QUARTER の sin (0 以上)。表は QUARTER の手前までなので、ちょうど 1/4 周は 1.0 。
+fn quarter_sine(step: i32) -> i32 {
+    if step >= QUARTER {
+        ONE
+    } else {
+        SINE_TABLE[step as usize] as i32
+    }
+}
+
+// angle は 1 周 ANGLE_STEPS 。範囲外や負の値も 1 周で折り返す。
+#[cfg_attr(not(test), allow(dead_code))]
+pub fn sin(angle: i32) -> Fixed {
+    let angle = angle & (ANGLE_STEPS - 1);
+    let step = angle & (QUARTER - 1);
+    let raw = match angle / QUARTER {
+        0 => quarter_sine(step),
+        1 => quarter_sine(QUARTER - step),
+        2 => -quarter_sine(step),
+        _ => -quarter_sine(QUARTER - step),
+    };
+    Fixed::from_raw(raw)
+}
+
+#[cfg_attr(not(test), allow(dead_code))]
+pub fn cos(angle: i32) -> Fixed {
+    sin(angle.wrapping_add(QUARTER))
+}
+
+// 切り捨ての平方根。距離の比較はなるべく 2 乗のまま行い、これはたまに使うだけにすること。
+#[cfg_attr(not(test), allow(dead_code))]
+pub fn isqrt(n: u32) -> u32 {
+    let mut rest = n;
+    let mut root = 0;
+    /* 4 の冪で n 以下の最大のもの */
+    let mut bit = 1u32 << 30;
+    while bit > n {
+        bit >>= 2;
+    }
+    while bit != 0 {
+        if rest >= root + bit {
+            rest -= root + bit;
+            root = (root >> 1) + bit;
+        } else {
+            root >>= 1;
+        }
+        bit >>= 2;
+    }
+    root
+}
+
+#[cfg_attr(not(test), allow(dead_code))]
+pub fn abs(x: Fixed) -> Fixed {
+    Fixed::from_raw(x.to_raw().abs())
+}
+
+// -1, 0, 1 のいずれか。
+#[cfg_attr(not(test), allow(dead_code))]
+pub fn sign(x: Fixed) -> Fixed {
+    Fixed::new(x.to_raw().signum())
+}
+
+// t = 0 で a 、t = 1 で b 。t が 0..=1 の外なら外挿する。
+#[cfg_attr(not(test), allow(dead_code))]
+pub fn lerp(a: Fixed, b: Fixed, t: Fixed) -> Fixed {
+    a + (b - a) * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 期待値は round(sin(angle / 1024 * 2π) * 256) と cos の同様の値。
+    #[test_case]
+    fn sine_and_cosine_match_the_reference(_gba: &mut agb::Gba) {
+        const EXPECTED: [(i32, i32, i32); 12] = [
+            (0, 0, 256),
+            (37, 58, 249),
+            (100, 147, 209),
+            (128, 181, 181),
+            (256, 256, 0),
+            (300, 247, -68),
+            (512, 0, -256),
+            (640, -181, -181),
+            (768, -256, 0),
+            (900, -177, 185),
+            (1023, -2, 256),
+            (-100, -147, 209),
+        ];
+        for (angle, sine, cosine) in EXPECTED {
+            /* 表の丸めで 1/256 までずれてよい */
+            assert!((sin(angle).to_raw() - sine).abs() <= 1, "sin {angle}");
+            assert!((cos(angle).to_raw() - cosine).abs() <= 1, "cos {angle}");
+        }
+        /* 1 周ずれても同じ */
+        assert_eq!(sin(37 + ANGLE_STEPS), sin(37));
+        assert_eq!(sin(37 - 3 * ANGLE_STEPS), sin(37));
+    }
+
+    #[test_case]
+    fn isqrt_rounds_down(_gba: &mut agb::Gba) {
+        for (n, root) in [
+            (0, 0),
+            (1, 1),
+            (3, 1),
+            (4, 2),
+            (99, 9),
+            (100, 10),
+            (65535, 255),
+        ] {
+            assert_eq!(isqrt(n), root, "isqrt {n}");
+        }
+        assert_eq!(isqrt(u32::MAX), 65535);
+        for n in 0..2000 {
+            let root = isqrt(n);
+            assert!(root * root <= n && n < (root + 1) * (root + 1), "isqrt {n}");
+        }
+    }
+
+    #[test_case]
+    fn fixed_point_helpers(_gba: &mut agb::Gba) {
+        let half = Fixed::new(1) / 2;
+        assert_eq!(abs(Fixed::new(-3)), Fixed::new(3));
+        assert_eq!(sign(-half), Fixed::new(-1));
+        assert_eq!(sign(Fixed::new(0)), Fixed::new(0));
+        assert_eq!(sign(half), Fixed::new(1));
+        assert_eq!(lerp(Fixed::new(10), Fixed::new(20), half), Fixed::new(15));
+        assert_eq!(
+            lerp(Fixed::new(10), Fixed::new(20), Fixed::new(0)),
+            Fixed::new(10)
+        );
+        assert_eq!(
+            lerp(Fixed::new(10), Fixed::new(-20), Fixed::new(1)),
+            Fixed::new(-20)
+        );
+    }
+}
@@ -0,0 +1,130 @@
+// メモリの置き場所。
+//
+// ROM は 16bit バスでウェイトが乗るので、毎ティック通る処理 (ドロイド君の移動、足場や地面のタイルとの当たり判定、
+// コインの取得、イベントの処理) と、背景の直接セルを VRAM に書き直す Layer::write_direct は
+// #[link_section = ".iwram.*"] で IWRAM に置いている。
+// 起動時に agb が ROM から写し、そこから実行する。Thumb のまま置いているのは、
+// ARM にすると Thumb の小さな関数 (Arena の走査等) をインライン展開できずに ROM を呼びに行き、
+// かえって遅くなったから。ROM に置いていたときとの比較は bench::game_tick_phases の記録を参照。
+//
+// 書き換える大きなデータは EWRAM にある。Game (コインの Arena、イベントのキュー、足場) は
+// ヒープに置かれ、agb のヒープは EWRAM なので、特に何もしなくてよい。static も既定で EWRAM に入る。
+//
+// IWRAM (32 KiB) の使い道:
+//   0x0300_0000 から  .iwram セクション (agb 自身の分が約 600 バイトと、上の処理)
+//   その後ろ          空き (agb::InternalAllocator の領域。今は使っていない)
+//   0x0300_7F00 から下へ  スタック (その上は割り込みと BIOS のスタック、BIOS の予約領域)
+// .iwram セクションは IWRAM_BUDGET までに収める (validate と下のテストで確かめる)。
+// 今はリリースビルドでもインライン展開の分を含めて 4 KiB 弱で、残りの 27 KiB ほどはスタックと空き。
+// 一番大きくなるのは debug-tools を有効にしたテストビルドで、ドロイド君の移動に書いたログの書式化の分が増える。
+// ここに処理を足すのは bench で ROM のときより速くなると確かめてからにすること。
+
+// 検査とテストでしか使わない。
+#![cfg_attr(not(any(test, feature = "debug-tools")), allow(dead_code))]
+
+/* .iwram セクションに使ってよい大きさ */
+pub const IWRAM_BUDGET: usize = 10 * 1024;
+
+// .iwram セクションの大きさ (バイト)。
+pub fn iwram_used() -> usize {
+    extern "C" {
+        static __iwram_data_start: u8;
+        static __iwram_end: u8;
+    }
+    // Safety: リンカが定義するシンボルで、アドレスを取るだけ
+    unsafe {
+        core::ptr::addr_of!(__iwram_end) as usize - core::ptr::addr_of!(__iwram_data_start) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::ops::Range;
+
+    use super::*;
+    use crate::{
+        climb::Climb,
+        collision::{Surfaces, Sweep},
+        difficulty::Difficulty,
+        game::Game,
+        input::Input,
+        math::Fixed,
+        mode::Mode,
+        physics::Gravity,
+        player::{Droid, Surroundings},
+        tilemap::CollisionMap,
+    };
+
+    const IWRAM: Range<usize> = 0x0300_0000..0x0300_8000;
+    const EWRAM: Range<usize> = 0x0200_0000..0x0204_0000;
+
+    // 毎ティック通る処理は IWRAM から実行する。
+    #[test_case]
+    fn the_hot_path_runs_from_iwram(_gba: &mut agb::Gba) {
+        let hot = [
+            (
+                "update_droid",
+                Game::update_droid as fn(&mut Game, &Input) as usize,
+            ),
+            (
+                "Droid::update",
+                Droid::update as fn(&mut Droid, &Input, &Surroundings<'static>) as usize,
+            ),
+            ("update_climb", Game::update_climb as fn(&mut Game) as usize),
+            (
+                "collect_coins",
+                Game::collect_coins as fn(&mut Game) as usize,
+            ),
+            (
+                "handle_events",
+                Game::handle_events as fn(&mut Game) as usize,
+            ),
+            (
+                "supports",
+                Climb::supports as fn(&Climb, i32, i32) -> bool as usize,
+            ),
+            (
+                "land_on",
+                Climb::land_on as fn(&Climb, Sweep) -> Option<(i32, i32)> as usize,
+            ),
+            (
+                "resolve_landing",
+                Surfaces::resolve_landing
+                    as fn(&Surfaces<'static>, (i32, i32), (i32, i32), Fixed) -> Option<(i32, i32)>
+                    as usize,
+            ),
+            (
+                "floor_y",
+                CollisionMap::floor_y as fn(&CollisionMap, i32, i32) -> i32 as usize,
+            ),
+            (
+                "wall_x",
+                CollisionMap::wall_x as fn(&CollisionMap, i32, i32, i32, Gravity) -> i32 as usize,
+            ),
+        ];
+        for (name, address) in hot {
+            assert!(IWRAM.contains(&address), "{name} is at {address:#010X}");
+        }
+    }
+
+    // エンティティの入れ物は EWRAM にある。
+    #[test_case]
+    fn entities_live_in_ewram(_gba: &mut agb::Gba) {
+        let game =
+            alloc::boxed::Box::new(Game::new(Difficulty::Normal, 1).with_mode(Mode::Endless));
+        let climb = game.climb.as_ref().unwrap();
+        for address in [
+            &game.coins as *const _ as usize,
+            &climb.platforms as *const _ as usize,
+        ] {
+            assert!(EWRAM.contains(&address), "found at {address:#010X}");
+        }
+    }
+
+    #[test_case]
+    fn iwram_use_fits_the_budget(_gba: &mut agb::Gba) {
+        let used = iwram_used();
+        agb::println!("iwram: {} / {} bytes", used, IWRAM_BUDGET);
+        assert!(used <= IWRAM_BUDGET, "{used} > {IWRAM_BUDGET}");
+    }
+}
@@ -0,0 +1,78 @@
+// 遊び方 (モード)。
+//
+// タイトル画面の上下キーで選び、Game に持たせる。
+// モードごとの違いは Game、Presenter、結果画面がそれぞれ Mode を見て切り替える。
+// 中身は各モードのモジュールにある
+// (time_attack, climb, turns, race, practice, ghost, speedrun, coin_rush, tutorial)。
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Normal,
+    TimeAttack,
+    // 縦に登り続けるエンドレス。
+    Endless,
+    // 2 人で 1 台を渡し合って交代で遊ぶ (turns モジュール)。
+    HotSeat,
+    // 通信ケーブルでつないだ 2 台で競争する (race モジュール)。
+    LinkRace,
+    // 状態を覚えて戻りながら練習する (practice モジュール)。レベルをクリアすると選べる。
+    Practice,
+    // タイムアタックの最高記録のゴーストと競う (ghost モジュール)。決まりはタイムアタックと同じ。
+    GhostRace,
+    // 全てのレベルを通して遊び、タイムを競う (speedrun モジュール)。
+    Speedrun,
+    // 制限時間の中でコインを集める (coin_rush モジュール)。
+    CoinRush,
+    // レベル 1 の前に遊ぶチュートリアル (tutorial モジュール)。普通のモードが自動で始めるので、一覧には並べない。
+    Tutorial,
+}
+
+impl Mode {
+    /* タイトル画面に並べる順 */
+    const ALL: [Mode; 9] = [
+        Mode::Normal,
+        Mode::TimeAttack,
+        Mode::GhostRace,
+        Mode::Speedrun,
+        Mode::CoinRush,
+        Mode::Endless,
+        Mode::HotSeat,
+        Mode::LinkRace,
+        Mode::Practice,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Normal => "NORMAL",
+            Mode::TimeAttack => "TIME ATTACK",
+            Mode::Endless => "ENDLESS",
+            Mode::HotSeat => "2P HOT SEAT",
+            Mode::LinkRace => "LINK RACE",
+            Mode::Practice => "PRACTICE",
+            Mode::GhostRace => "GHOST RACE",
+            Mode::Speedrun => "SPEEDRUN",
+            Mode::CoinRush => "COIN RUSH",
+            Mode::Tutorial => "TUTORIAL",
+        }
+    }
+
+    // リプレイに保存するときの番号と、その逆。
+    pub fn index(self) -> u8 {
+        self as u8
+    }
+
+    pub fn from_index(index: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|&mode| mode as u8 == index)
+    }
+
+    // タイトル画面の上下キーで選ぶときの前後。端では反対側へ回る。
+    pub fn prev(self) -> Self {
+        let index = Self::ALL.iter().position(|&mode| mode == self).unwrap_or(0);
+        Self::ALL[(index + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&mode| mode == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
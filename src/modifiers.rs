@@ -0,0 +1,147 @@
+// ランに掛ける修飾 (HARD モード)。
+//
+// 難易度の表とは別に、ラン開始時にタイトル画面の SELECT で入れる。
+// 修飾は 1 つずつ独立したフラグで、それぞれを読むのはそのフラグに関わる仕組みだけ:
+// - one_hit: ダメージの処理 (Game::handle_events) 。1 回で体力が尽きる。
+// - no_double_jump: ドロイド君のジャンプ (Droid::update) 。二段ジャンプができない。
+// - fast_enemies: 敵の速さの倍率 (enemy_speed) 。
+// - strict_timers: 時間の決まり (タイムアタックの目標タイム、エンドレスの岩棚が崩れるまで) 。
+// - narrow_apple: りんごの当たり幅 (Apple::half_width) 。
+// - mirror: レベルの読み込み (level モジュール) 。左右を反転する。難しくはならないので HARD には含めない。
+// 今のタイトル画面には全部まとめて入れる HARD とミラーしか無いが、好きな組み合わせでも遊べる。
+
+use crate::{math::Fixed, time_attack::Par};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub one_hit: bool,
+    pub no_double_jump: bool,
+    pub fast_enemies: bool,
+    pub strict_timers: bool,
+    pub narrow_apple: bool,
+    pub mirror: bool,
+}
+
+impl Modifiers {
+    pub const NONE: Self = Self {
+        one_hit: false,
+        no_double_jump: false,
+        fast_enemies: false,
+        strict_timers: false,
+        narrow_apple: false,
+        mirror: false,
+    };
+
+    pub const HARD: Self = Self {
+        one_hit: true,
+        no_double_jump: true,
+        fast_enemies: true,
+        strict_timers: true,
+        narrow_apple: true,
+        mirror: false,
+    };
+
+    // ミラー以外が 1 つでも入っていれば、記録と結果画面に HARD の印を付ける。
+    pub fn is_hard(self) -> bool {
+        Self {
+            mirror: false,
+            ..self
+        } != Self::NONE
+    }
+
+    // 敵の速さの倍率。敵はまだいないので、読むのはテストだけ。
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn enemy_speed(self) -> Fixed {
+        if self.fast_enemies {
+            /* 1.25 */
+            Fixed::from_raw(320)
+        } else {
+            Fixed::new(1)
+        }
+    }
+
+    // 時間の決まり ticks を、この修飾に合わせて縮める。
+    pub fn timer(self, ticks: u32) -> u32 {
+        if self.strict_timers {
+            ticks * 3 / 4
+        } else {
+            ticks
+        }
+    }
+
+    // タイムアタックの目標タイム。
+    pub fn par(self, par: Par) -> Par {
+        Par {
+            gold: self.timer(par.gold),
+            silver: self.timer(par.silver),
+            bronze: self.timer(par.bronze),
+        }
+    }
+
+    // リプレイに保存するときの 1 バイト。ビット 0 から宣言の順。
+    pub fn bits(self) -> u8 {
+        [
+            self.one_hit,
+            self.no_double_jump,
+            self.fast_enemies,
+            self.strict_timers,
+            self.narrow_apple,
+            self.mirror,
+        ]
+        .iter()
+        .enumerate()
+        .fold(0, |bits, (bit, &on)| bits | u8::from(on) << bit)
+    }
+
+    pub fn from_bits(bits: u8) -> Self {
+        let on = |bit: u8| bits & 1 << bit != 0;
+        Self {
+            one_hit: on(0),
+            no_double_jump: on(1),
+            fast_enemies: on(2),
+            strict_timers: on(3),
+            narrow_apple: on(4),
+            mirror: on(5),
+        }
+    }
+
+    // りんごの当たり幅を左右それぞれ何 px 狭めるか。
+    pub fn apple_trim(self) -> i32 {
+        if self.narrow_apple {
+            3
+        } else {
+            0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn hard_tightens_every_knob(_gba: &mut agb::Gba) {
+        let none = Modifiers::NONE;
+        let hard = Modifiers::HARD;
+        assert!(!none.is_hard() && hard.is_hard());
+        assert_eq!(none.enemy_speed(), Fixed::new(1));
+        assert_eq!(hard.enemy_speed() * 4, Fixed::new(5));
+        assert_eq!((none.timer(40), hard.timer(40)), (40, 30));
+        /* 左右で 6px 狭い */
+        assert_eq!(hard.apple_trim() * 2, 6);
+        /* 1 つだけでも HARD の印が付く */
+        let one = Modifiers {
+            one_hit: true,
+            ..Modifiers::NONE
+        };
+        assert!(one.is_hard());
+        let mirror = Modifiers {
+            mirror: true,
+            ..Modifiers::NONE
+        };
+        assert!(!mirror.is_hard());
+        assert_eq!(Modifiers::from_bits(mirror.bits()), mirror);
+        assert_eq!(Modifiers::from_bits(one.bits()), one);
+        assert_eq!(Modifiers::from_bits(hard.bits()), hard);
+    }
+}
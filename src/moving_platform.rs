@@ -0,0 +1,348 @@
+// 動く足場。
+//
+// レベルの足場の層 (Level::platforms) から置き、from と to の間を MOVING_PLATFORM_SPEED で往復する。
+// 横に動くもの (from と to の y が同じ) と縦に動くもの (x が同じ) があり、斜めには動かない。
+// 位置は整数のまま、進み具合 (progress) だけ固定小数点で持つ。
+//
+// 下から通り抜けられるか (collision::Passage) は足場ごとにレベルのデータで決める。
+// エレベーター (EntityKind::Elevator) は縦に動く足場で、両端に着くたびに決まったティック数 (wait) だけ止まる。
+//
+// 乗っているドロイド君は (端にぶら下がっていても)、足場が動いた分だけ一緒に動く (Game::update_moving_platforms)。
+// ドロイド君を動かす前に足場を動かすので、着地も歩いて落ちるのも動いた後の足場で判定する。
+// 上っていく足場が下から足の裏を追い越したら、ドロイド君を上面まで押し上げる (そのままだと足場の中にいて、
+// 上から跨いでいないので着地できずに落ちてしまう)。
+
+use crate::{
+    collision::{self, Aabb, Passage, Surface},
+    entity::{Entity, Look},
+    game::{move_x, Game},
+    math::Fixed,
+    physics::Gravity,
+    player::PlayerState,
+    snapshot::encode_fields,
+    time::Timer,
+};
+
+/* 同時に置ける動く足場の数 */
+pub const MAX_MOVING_PLATFORMS: usize = 4;
+/* 動く足場の幅 (スプライト 2 枚分) */
+pub const MOVING_PLATFORM_WIDTH: i32 = 32;
+/* 動く足場の厚み */
+const MOVING_PLATFORM_THICKNESS: i32 = 8;
+/* 1 ティックに進むドット数 */
+const MOVING_PLATFORM_SPEED: Fixed = Fixed::from_raw(128);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MovingPlatform {
+    /* 左上 */
+    pub x: i32,
+    pub y: i32,
+    /* 前のティックの左上。描くときに今の位置との間を補う */
+    previous: (i32, i32),
+    /* 往復する両端 (左上)。from から動き出す */
+    from: (i32, i32),
+    to: (i32, i32),
+    /* from から進んだドット数と、to へ向かっているか */
+    progress: Fixed,
+    forward: bool,
+    passage: Passage,
+    /* 端に着いてから折り返すまで止まっているティック数と、止まっている残り */
+    wait: u16,
+    pause: Timer,
+}
+
+encode_fields!(MovingPlatform {
+    x,
+    y,
+    previous,
+    from,
+    to,
+    progress,
+    forward,
+    passage,
+    wait,
+    pause
+});
+
+impl MovingPlatform {
+    pub fn new(from: (i32, i32), to: (i32, i32), passage: Passage) -> Self {
+        debug_assert!(from.0 == to.0 || from.1 == to.1, "diagonal path");
+        Self {
+            x: from.0,
+            y: from.1,
+            previous: from,
+            from,
+            to,
+            progress: Fixed::new(0),
+            forward: true,
+            passage,
+            wait: 0,
+            pause: Timer::new(),
+        }
+    }
+
+    // 端に着くたびに ticks だけ止まる (エレベーター)。
+    pub fn with_wait(mut self, ticks: u16) -> Self {
+        self.wait = ticks;
+        self
+    }
+
+    fn length(&self) -> i32 {
+        (self.to.0 - self.from.0).abs() + (self.to.1 - self.from.1).abs()
+    }
+
+    // 1 ティック進め、動いたドット数 (dx, dy) を返す。端に着いたら (wait だけ止まってから) 折り返す。
+    pub fn advance(&mut self) -> (i32, i32) {
+        self.previous = (self.x, self.y);
+        if self.pause.is_running() {
+            self.pause.tick();
+            return (0, 0);
+        }
+        let length = Fixed::new(self.length());
+        if self.forward {
+            self.progress += MOVING_PLATFORM_SPEED;
+            if self.progress >= length {
+                self.progress = length;
+                self.forward = false;
+                self.pause.start(self.wait);
+            }
+        } else {
+            self.progress -= MOVING_PLATFORM_SPEED;
+            if self.progress <= Fixed::new(0) {
+                self.progress = Fixed::new(0);
+                self.forward = true;
+                self.pause.start(self.wait);
+            }
+        }
+        let step = self.progress.floor();
+        let x = self.from.0 + (self.to.0 - self.from.0).signum() * step;
+        let y = self.from.1 + (self.to.1 - self.from.1).signum() * step;
+        let delta = (x - self.x, y - self.y);
+        (self.x, self.y) = (x, y);
+        delta
+    }
+}
+
+impl Entity for MovingPlatform {
+    fn update(&mut self) {
+        self.advance();
+    }
+
+    fn draw(&self) -> Option<Look> {
+        Some(Look::at(self.x, self.y))
+    }
+
+    fn drawn_before(&self) -> Option<Look> {
+        Some(Look::at(self.previous.0, self.previous.1))
+    }
+}
+
+impl Surface for MovingPlatform {
+    fn bounds(&self) -> Aabb {
+        Aabb::new(
+            self.x,
+            self.y,
+            MOVING_PLATFORM_WIDTH,
+            MOVING_PLATFORM_THICKNESS,
+        )
+    }
+
+    fn passage(&self) -> Passage {
+        self.passage
+    }
+}
+
+impl Game {
+    // 動く足場を 1 ティック分動かし (Entity::update)、乗っていたドロイド君も同じだけ動かす。
+    // 上っていく足場に足の裏を追い越されたドロイド君は上面まで押し上げる。ドロイド君を動かす前に
+    // (Game::update_entities から) 呼ぶ。
+    pub fn update_moving_platforms(&mut self) {
+        let walls = self.walls();
+        let droid = &mut self.droid;
+        for platform in self.moving_platforms.values_mut() {
+            /* 乗っているか、端にぶら下がっている */
+            let riding = (droid.is_grounded()
+                && droid.gravity == Gravity::Down
+                && droid.y == platform.top_y()
+                && collision::supports(platform, droid.x))
+                || (matches!(
+                    droid.state,
+                    PlayerState::LedgeHang | PlayerState::LedgeClimb
+                ) && collision::holds(platform, droid.x, droid.y, droid.facing()));
+            let (x, y) = (platform.x, platform.y);
+            platform.update();
+            let (dx, dy) = (platform.x - x, platform.y - y);
+            if riding {
+                droid.x = match walls {
+                    Some(walls) => walls.clamp(droid.x + dx),
+                    None => move_x(droid.x, dx),
+                };
+                droid.y += dy;
+            } else if dy < 0
+                && droid.gravity == Gravity::Down
+                && droid.vy <= Fixed::new(0)
+                && (platform.top_y() + 1..=platform.top_y() - dy).contains(&droid.y)
+                && collision::supports(platform, droid.x)
+            {
+                /* 跳び上がっている途中でなければ、足場の上面に載せる (次の落下で着地する) */
+                droid.y = platform.top_y();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        difficulty::Difficulty,
+        game::{DROID_WIDTH, GROUND_Y},
+        input::Input,
+        spawn::EntityKind,
+    };
+
+    // 両端の間を行き来し、1 往復で元の位置に戻る。
+    #[test_case]
+    fn platforms_go_back_and_forth_along_their_path(_gba: &mut agb::Gba) {
+        for to in [(64, 40), (16, 80)] {
+            let mut platform = MovingPlatform::new((16, 40), to, Passage::OneWay);
+            let length = platform.length();
+            let ticks = length * 2 * 256 / MOVING_PLATFORM_SPEED.to_raw();
+            let (mut x, mut y) = (platform.x, platform.y);
+            for tick in 1..=ticks {
+                let (dx, dy) = platform.advance();
+                (x, y) = (x + dx, y + dy);
+                assert_eq!((x, y), (platform.x, platform.y));
+                assert!(platform.x == 16 || platform.y == 40);
+                if tick == ticks / 2 {
+                    assert_eq!((platform.x, platform.y), to);
+                }
+            }
+            assert_eq!((platform.x, platform.y), (16, 40));
+        }
+    }
+
+    // エレベーターは両端に着くたびに wait ティック止まってから折り返す。
+    #[test_case]
+    fn elevators_wait_at_both_ends(_gba: &mut agb::Gba) {
+        let mut elevator = MovingPlatform::new((16, 80), (16, 64), Passage::OneWay).with_wait(10);
+        let ys: alloc::vec::Vec<_> = (0..200)
+            .map(|_| {
+                elevator.advance();
+                elevator.y
+            })
+            .collect();
+        /* 16 ドット上るのに 32 ティック */
+        assert_eq!(ys[31], 64);
+        assert!(ys[31..=41].iter().all(|&y| y == 64));
+        assert_eq!(ys[42], 65);
+        assert!(ys[73..=84].iter().all(|&y| y == 80));
+        assert_eq!(ys[85], 79);
+    }
+
+    // 上っていくエレベーターに足の裏を追い越されたら押し上げられて乗り、下りるときも離れない。
+    #[test_case]
+    fn elevators_push_the_droid_up_and_carry_it_down(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        game.moving_platforms = crate::arena::Arena::new();
+        game.spawn(EntityKind::Elevator { to_y: 48, wait: 20 })
+            .at((136, 96))
+            .build()
+            .unwrap();
+        /* 次のティックに 1 ドット上る */
+        game.moving_platforms.iter_mut().next().unwrap().1.advance();
+        /* 跳び上がりきって、足の裏が上面と同じ高さにいる */
+        game.droid.x = 136;
+        game.droid.y = 96 - DROID_WIDTH;
+        game.droid.state = PlayerState::Airborne { double: false };
+        game.droid.vy = Fixed::new(0);
+        let top = |game: &Game| game.moving_platforms.iter().next().unwrap().1.top_y();
+        /* 落ち始めるまでの数ティックも足場の中へは沈まない */
+        for _ in 0..4 {
+            game.update(&Input::default());
+            assert_eq!(game.droid.y, top(&game));
+        }
+        for _ in 0..300 {
+            game.update(&Input::default());
+            assert_eq!(
+                (game.droid.state, game.droid.y),
+                (PlayerState::Idle, top(&game))
+            );
+        }
+    }
+
+    // 乗っている間は足場と一緒に動き、降りれば置いていかれる。
+    #[test_case]
+    fn standing_on_a_platform_carries_the_droid(_gba: &mut agb::Gba) {
+        for (from, to) in [((40, 80), (104, 80)), ((40, 112), (40, 64))] {
+            let mut game = Game::new(Difficulty::Normal, 1);
+            game.moving_platforms = crate::arena::Arena::new();
+            game.spawn(EntityKind::MovingPlatform {
+                to,
+                passage: Passage::OneWay,
+            })
+            .at(from)
+            .build()
+            .unwrap();
+            game.droid.x = from.0;
+            game.droid.y = from.1 - DROID_WIDTH;
+            for _ in 0..60 {
+                game.update(&Input::default());
+                let (_, platform) = game.moving_platforms.iter().next().unwrap();
+                assert_eq!(game.droid.state, PlayerState::Idle);
+                assert_eq!((game.droid.x, game.droid.y), (platform.x, platform.top_y()));
+            }
+            /* 跳べば足場は離れていく */
+            game.update(&Input::new(
+                agb::input::Button::UP,
+                agb::input::Button::empty(),
+            ));
+            while !matches!(game.droid.state, PlayerState::Airborne { double: false }) {
+                game.update(&Input::default());
+            }
+            let x = game.droid.x;
+            for _ in 0..4 {
+                game.update(&Input::default());
+            }
+            assert_eq!(game.droid.x, x);
+            assert!(game.droid.y < GROUND_Y);
+        }
+    }
+
+    // 通り抜けられる足場は下から跳び上がって上に乗り、通り抜けられない足場には頭をぶつけて落ちる。
+    #[test_case]
+    fn solid_platforms_stop_a_jump_from_below(_gba: &mut agb::Gba) {
+        for passage in [Passage::OneWay, Passage::Solid] {
+            let mut game = Game::new(Difficulty::Normal, 1);
+            game.moving_platforms = crate::arena::Arena::new();
+            game.spawn(EntityKind::MovingPlatform {
+                to: (40, 80),
+                passage,
+            })
+            .at((40, 80))
+            .build()
+            .unwrap();
+            game.droid.x = 40;
+            game.droid.y = GROUND_Y;
+            game.update(&Input::new(
+                agb::input::Button::UP,
+                agb::input::Button::empty(),
+            ));
+            let (mut highest, mut bonked) = (GROUND_Y, false);
+            for _ in 0..120 {
+                game.update(&Input::new(agb::input::Button::UP, agb::input::Button::UP));
+                highest = highest.min(game.droid.y);
+                bonked |= game.droid.bonked;
+            }
+            assert_eq!(game.droid.state, PlayerState::Idle);
+            assert_eq!(bonked, passage == Passage::Solid);
+            match passage {
+                Passage::OneWay => assert_eq!(game.droid.y, 80 - DROID_WIDTH),
+                Passage::Solid => {
+                    assert_eq!(highest, 80 + MOVING_PLATFORM_THICKNESS);
+                    assert_eq!(game.droid.y, GROUND_Y);
+                }
+            }
+        }
+    }
+}
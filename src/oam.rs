@@ -0,0 +1,184 @@
+// オブジェクトの割り当て。
+//
+// agb の OamManaged はスプライト VRAM が足りないと panic する (実機では画面が真っ暗になる)。
+// また 128 個を超えたオブジェクトは、何の知らせもなく奥のものから表示されなくなる。
+// そこでオブジェクトは必ずこの ObjectPool から作る:
+//
+// - スプライト VRAM は自前の SpriteLoader から try_ で取り、取れなければ None を返す。
+// - 種類 (Category) ごとに使ってよい OAM の数に上限を設け、足りなくなってきたら
+//   エフェクトのような無くても遊べるものから断る。プレイヤーや足場の分は最後まで残す。
+// - 断った回数を数えておき、デバッグ表示に出す。
+//
+// OamManaged::object_sprite() と sprite() を直接呼ぶと上の全てを素通りするので使わないこと。
+
+use core::{
+    cell::{Cell, RefCell},
+    ops::{Deref, DerefMut},
+};
+
+use agb::display::object::{OamManaged, Object, Sprite, SpriteLoader, SpriteVram};
+
+/* OAM の数 */
+const OAM_SLOTS: u32 = 128;
+
+// オブジェクトの種類。足りなくなったときは下のものから断る。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Category {
+    Player,
+    // 乗れるもの (りんご等)。
+    Platform,
+    // 見た目だけの置物。
+    Scenery,
+    // パーティクルや得点のポップアップ。
+    Effect,
+}
+
+impl Category {
+    // この種類を新しく作ってよいのは、使用中の数がこれ未満のとき。
+    fn limit(self) -> u32 {
+        match self {
+            Category::Player | Category::Platform => OAM_SLOTS,
+            Category::Scenery => OAM_SLOTS - 8,
+            Category::Effect => OAM_SLOTS - 32,
+        }
+    }
+}
+
+pub struct ObjectPool<'g> {
+    oam: &'g OamManaged<'g>,
+    loader: RefCell<SpriteLoader>,
+    /* 作って、まだ drop されていないオブジェクトの数 */
+    live: Cell<u32>,
+    /* 断った回数 (起動してからの合計) */
+    rejected: Cell<u32>,
+}
+
+impl<'g> ObjectPool<'g> {
+    pub fn new(oam: &'g OamManaged<'g>) -> Self {
+        Self {
+            oam,
+            loader: RefCell::new(SpriteLoader::default()),
+            live: Cell::new(0),
+            rejected: Cell::new(0),
+        }
+    }
+
+    // 表示していない状態のオブジェクトを作る。作れなければ None 。
+    pub fn try_object(&'g self, sprite: &'static Sprite, category: Category) -> Option<Pooled<'g>> {
+        if self.live.get() >= category.limit() {
+            self.reject(category);
+            return None;
+        }
+        let sprite = self.try_sprite(sprite)?;
+        self.live.set(self.live.get() + 1);
+        Some(Pooled {
+            object: self.oam.object(sprite),
+            pool: self,
+        })
+    }
+
+    // 既にあるオブジェクトの絵を差し替えるときに使う。VRAM が足りなければ None 。
+    pub fn try_sprite(&self, sprite: &'static Sprite) -> Option<SpriteVram> {
+        match self.loader.borrow_mut().try_get_vram_sprite(sprite) {
+            Ok(sprite) => Some(sprite),
+            Err(_) => {
+                self.rejected.set(self.rejected.get() + 1);
+                log_warn!("out of sprite VRAM");
+                None
+            }
+        }
+    }
+
+    fn reject(&self, category: Category) {
+        self.rejected.set(self.rejected.get() + 1);
+        log_warn!("no OAM for {:?} ({} in use)", category, self.live.get());
+    }
+
+    #[cfg_attr(not(any(test, feature = "debug-tools")), allow(dead_code))]
+    pub fn rejected(&self) -> u32 {
+        self.rejected.get()
+    }
+
+    // OAM に書き込む。描画フレームごとに呼ぶ。
+    pub fn commit(&self) {
+        self.oam.commit();
+    }
+
+    // 使われなくなったスプライトを、読み込んだスプライトの表から片付ける。シーンを切り替えるときに呼ぶ。
+    // agb の garbage_collect は表を全てなめるので、毎フレーム呼ぶと 1 フレームの 2% ほどかかる。
+    // 片付けなくても VRAM は返っていて、表はスプライトの種類より大きくならないので、シーンごとで足りる。
+    pub fn collect_garbage(&self) {
+        self.loader.borrow_mut().garbage_collect();
+    }
+}
+
+// ObjectPool から作ったオブジェクト。drop すると数が返る。
+pub struct Pooled<'g> {
+    object: Object<'g>,
+    pool: &'g ObjectPool<'g>,
+}
+
+impl<'g> Deref for Pooled<'g> {
+    type Target = Object<'g>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.object
+    }
+}
+
+impl DerefMut for Pooled<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.object
+    }
+}
+
+impl Drop for Pooled<'_> {
+    fn drop(&mut self) {
+        self.pool.live.set(self.pool.live.get() - 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::{resources::GRAPHICS, scene::Usage};
+
+    // 1 フレームでパーティクルを 200 個出そうとしても止まらず、
+    // プレイヤーと足場の分は残っていること。
+    #[test_case]
+    fn a_flood_of_effects_leaves_room_for_the_player(gba: &mut agb::Gba) {
+        let oam = gba.display.object.get_managed();
+        let pool = ObjectPool::new(&oam);
+        let sprites = GRAPHICS.sprites();
+        let mut player = pool.try_object(&sprites[0], Category::Player).unwrap();
+        player.show();
+
+        let mut effects = Vec::new();
+        for i in 0..200 {
+            if let Some(mut effect) = pool.try_object(&sprites[i % sprites.len()], Category::Effect)
+            {
+                effect.set_position((i as i32, 0)).show();
+                effects.push(effect);
+            }
+        }
+        assert_eq!(effects.len() as u32, Category::Effect.limit() - 1);
+        assert_eq!(pool.rejected(), 200 - effects.len() as u32);
+
+        /* 足場と置物はまだ作れる */
+        let mut platform = pool.try_object(&sprites[1], Category::Platform).unwrap();
+        platform.show();
+        let mut scenery = pool.try_object(&sprites[1], Category::Scenery).unwrap();
+        scenery.show();
+        pool.commit();
+        assert_eq!(Usage::now().oam_slots, effects.len() as u32 + 3);
+
+        /* 片付ければまた作れる */
+        effects.clear();
+        assert!(pool.try_object(&sprites[0], Category::Effect).is_some());
+        drop((player, platform, scenery));
+        pool.commit();
+        assert_eq!(pool.live.get(), 0);
+    }
+}
@@ -0,0 +1,242 @@
+// ロジックの進み方 (ティック) と描画フレームの歩調合わせ。
+//
+// ゲームロジックは VBlank の回数を時計にして、常に 60 ティック/秒で進める。
+// 描画が VBlank に間に合わなかったフレームがあっても、次のフレームで
+// その分のティックをまとめて回すので、ゲームの速さは変わらない。
+// ただし処理落ちが続いたときに雪だるま式に重くならないよう、1 フレームあたり
+// MAX_TICKS_PER_FRAME ティックまでに抑え、溜められる遅れも MAX_BACKLOG までとする
+// (それを超えた分は諦める)。
+//
+// 取りこぼしの検出について: 1 フレームの処理が VBlank を跨ぐと、agb の
+// wait_for_vblank は次の VBlank を待たずにすぐ戻ってくる。そのため VBlank 待ちから
+// 戻った時点の VBlank 数を比べるだけでは気付けない。FramePacer は「処理を始めた時点」
+// と「処理を終えて VBlank 待ちに入る時点」の VBlank 数を比べ、処理中に VBlank が
+// 来ていたらオーバーランとみなす。
+//
+// 描画はロジックと切り離してあり、ティックを回さないフレームも描く。動くもの (ドロイド君、足場、破片、ゴースト) は
+// 前のティックの位置も持っておき、前のティックから今のティックへ、次のティックまでの進み (TickAccumulator::alpha)
+// だけ寄せたところに描く (between)。描くのはいつも 1 ティック遅れで、普段の速さでは alpha は常に 0 なので
+// 前のティックの位置そのまま。スロー再生で 1 ティックが何フレームにも渡っても滑らかに動き、
+// 先を見込まないので、床に降りたティックや壁で止まったティックにめり込んで描くことも無い。
+
+/* 1 フレームに回すロジックのティック数の上限 */
+pub const MAX_TICKS_PER_FRAME: u32 = 2;
+/* 持ち越せる遅れ (ティック数) の上限 */
+const MAX_BACKLOG: u32 = 4;
+
+/* 速度の単位。SPEED_NORMAL で 1 VBlank = 1 ティック */
+pub const SPEED_NORMAL: u32 = 256;
+/* 1 ティックにこれより大きく動いたもの (画面端で回り込んだ、テレポーターで移った) は間を補わない */
+const MAX_BLEND_STEP: i32 = 16;
+
+// 前のティックの位置 previous から今のティックの位置 current へ、alpha (1/SPEED_NORMAL ティック単位) だけ
+// 寄せたところ。大きく飛んだティックは今の位置。
+pub fn between(previous: (i32, i32), current: (i32, i32), alpha: u32) -> (i32, i32) {
+    let (dx, dy) = (current.0 - previous.0, current.1 - previous.1);
+    if dx.abs() > MAX_BLEND_STEP || dy.abs() > MAX_BLEND_STEP {
+        return current;
+    }
+    /* 左右と上下で同じに丸まるよう、絶対値で割ってから符号を戻す */
+    let part = |d: i32| d.signum() * (d.abs() * alpha as i32 / SPEED_NORMAL as i32);
+    (previous.0 + part(dx), previous.1 + part(dy))
+}
+
+pub struct TickAccumulator {
+    last_vblank: u32,
+    /* 1/SPEED_NORMAL ティック単位で溜まっている分 */
+    pending: u32,
+    speed: u32,
+}
+
+impl TickAccumulator {
+    pub fn new(vblanks: u32) -> Self {
+        Self {
+            last_vblank: vblanks,
+            pending: 0,
+            speed: SPEED_NORMAL,
+        }
+    }
+
+    // 進める速さを変える。SPEED_NORMAL / 2 ならスロー再生、SPEED_NORMAL * 2 なら早送り。
+    #[cfg_attr(not(feature = "debug-tools"), allow(dead_code))]
+    pub fn set_speed(&mut self, speed: u32) {
+        self.speed = speed;
+    }
+
+    #[cfg_attr(not(feature = "debug-tools"), allow(dead_code))]
+    pub fn speed(&self) -> u32 {
+        self.speed
+    }
+
+    // VBlank 待ちから戻った直後に呼び、今フレームで回すティック数を返す。
+    pub fn consume(&mut self, vblanks: u32) -> u32 {
+        let elapsed = vblanks.wrapping_sub(self.last_vblank);
+        self.last_vblank = vblanks;
+        self.pending = self
+            .pending
+            .saturating_add(elapsed.saturating_mul(self.speed));
+        let ticks = (self.pending / SPEED_NORMAL).min(MAX_TICKS_PER_FRAME);
+        self.pending -= ticks * SPEED_NORMAL;
+        self.pending = self.pending.min(MAX_BACKLOG * SPEED_NORMAL);
+        ticks
+    }
+
+    // 最後に回したティックから、次のティックまでに進んだ分 (1/SPEED_NORMAL ティック単位、0..SPEED_NORMAL)。
+    // consume の後、描く前に見る。
+    pub fn alpha(&self) -> u32 {
+        self.pending.min(SPEED_NORMAL - 1)
+    }
+}
+
+pub struct FramePacer {
+    frame_start: u32,
+    overruns: u32,
+}
+
+impl FramePacer {
+    pub fn new(vblanks: u32) -> Self {
+        Self {
+            frame_start: vblanks,
+            overruns: 0,
+        }
+    }
+
+    // VBlank 待ちから戻った直後に呼ぶ。
+    pub fn begin_frame(&mut self, vblanks: u32) {
+        self.frame_start = vblanks;
+    }
+
+    // VBlank 待ちに入る直前に呼ぶ。このフレームの処理中に過ぎた VBlank の数を返す
+    // (0 なら間に合っている)。
+    pub fn end_frame(&mut self, vblanks: u32) -> u32 {
+        let missed = vblanks.wrapping_sub(self.frame_start);
+        if missed > 0 {
+            self.overruns += 1;
+        }
+        missed
+    }
+
+    // 起動してからオーバーランしたフレームの数。
+    #[cfg_attr(not(feature = "debug-tools"), allow(dead_code))]
+    pub fn overruns(&self) -> u32 {
+        self.overruns
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use agb::display::WIDTH;
+
+    use super::*;
+
+    #[test_case]
+    fn on_time_frames_run_one_tick(_gba: &mut agb::Gba) {
+        let mut pacer = FramePacer::new(0);
+        let mut ticks = TickAccumulator::new(0);
+        for vblank in 1..10 {
+            pacer.begin_frame(vblank);
+            assert_eq!(ticks.consume(vblank), 1);
+            assert_eq!(pacer.end_frame(vblank), 0);
+        }
+        assert_eq!(pacer.overruns(), 0);
+    }
+
+    #[test_case]
+    fn an_overrun_is_detected(_gba: &mut agb::Gba) {
+        let mut pacer = FramePacer::new(0);
+        pacer.begin_frame(1);
+        /* 処理中に VBlank を 1 回跨いだ */
+        assert_eq!(pacer.end_frame(2), 1);
+        /* wait_for_vblank はすぐ戻ってくるので VBlank 数は 2 のまま */
+        pacer.begin_frame(2);
+        assert_eq!(pacer.end_frame(2), 0);
+        assert_eq!(pacer.overruns(), 1);
+    }
+
+    #[test_case]
+    fn an_overrun_is_made_up_on_the_next_frame(_gba: &mut agb::Gba) {
+        let mut ticks = TickAccumulator::new(0);
+        assert_eq!(ticks.consume(1), 1);
+        /* 描画が 2 フレーム分かかった */
+        assert_eq!(ticks.consume(3), 2);
+        assert_eq!(ticks.consume(4), 1);
+    }
+
+    #[test_case]
+    fn catch_up_is_spread_over_frames_and_capped(_gba: &mut agb::Gba) {
+        let mut ticks = TickAccumulator::new(0);
+        /* 3 ティック遅れたら 2 + 2 で取り戻す */
+        assert_eq!(ticks.consume(3), MAX_TICKS_PER_FRAME);
+        assert_eq!(ticks.consume(4), MAX_TICKS_PER_FRAME);
+        assert_eq!(ticks.consume(5), 1);
+        /* 大きく止まった分は MAX_BACKLOG を超えて持ち越さない */
+        let mut total = 0;
+        total += ticks.consume(105);
+        for vblank in 106..120 {
+            total += ticks.consume(vblank);
+        }
+        assert_eq!(total, MAX_TICKS_PER_FRAME + MAX_BACKLOG + 14);
+    }
+
+    // 描画が何度かフレームを落としても、1 秒間に回るティックはちょうど 60 になること。
+    #[test_case]
+    fn a_second_is_sixty_ticks_despite_missed_frames(_gba: &mut agb::Gba) {
+        let dropped = [3, 4, 17, 30, 31, 45, 52];
+        let mut ticks = TickAccumulator::new(0);
+        let mut total = 0;
+        for vblank in 1..=60 {
+            if dropped.contains(&vblank) {
+                continue;
+            }
+            total += ticks.consume(vblank);
+        }
+        assert_eq!(total, 60);
+    }
+
+    #[test_case]
+    fn half_speed_runs_every_other_frame(_gba: &mut agb::Gba) {
+        let mut ticks = TickAccumulator::new(0);
+        ticks.set_speed(SPEED_NORMAL / 2);
+        let total: u32 = (1..=60).map(|vblank| ticks.consume(vblank)).sum();
+        assert_eq!(total, 30);
+    }
+
+    // ティックを回さないフレームでは、次のティックまでの進みが溜まっていく。
+    #[test_case]
+    fn alpha_grows_between_slow_ticks(_gba: &mut agb::Gba) {
+        let mut ticks = TickAccumulator::new(0);
+        assert_eq!((ticks.consume(1), ticks.alpha()), (1, 0));
+        ticks.set_speed(SPEED_NORMAL / 4);
+        let frames: [(u32, u32); 4] =
+            core::array::from_fn(|vblank| (ticks.consume(vblank as u32 + 2), ticks.alpha()));
+        assert_eq!(
+            frames,
+            [
+                (0, SPEED_NORMAL / 4),
+                (0, SPEED_NORMAL / 2),
+                (0, SPEED_NORMAL * 3 / 4),
+                (1, 0)
+            ]
+        );
+    }
+
+    #[test_case]
+    fn vblank_counter_may_wrap(_gba: &mut agb::Gba) {
+        let mut ticks = TickAccumulator::new(u32::MAX);
+        assert_eq!(ticks.consume(1), 2);
+        let mut pacer = FramePacer::new(u32::MAX - 1);
+        pacer.begin_frame(u32::MAX);
+        assert_eq!(pacer.end_frame(0), 1);
+    }
+
+    // 前のティックの位置から今の位置へ寄せて描き、回り込んだティックは今の位置に描く。
+    #[test_case]
+    fn drawing_blends_from_the_previous_tick(_gba: &mut agb::Gba) {
+        let (previous, current) = ((40, 80), (44, 78));
+        assert_eq!(between(previous, current, 0), (40, 80));
+        assert_eq!(between(previous, current, SPEED_NORMAL / 2), (42, 79));
+        assert_eq!(between(previous, current, SPEED_NORMAL / 4), (41, 80));
+        assert_eq!(between(previous, current, SPEED_NORMAL - 1), (43, 79));
+        assert_eq!(between((WIDTH - 2, 80), (2, 80), SPEED_NORMAL / 2), (2, 80));
+    }
+}
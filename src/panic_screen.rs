@@ -0,0 +1,186 @@
+// パニック画面。
+//
+// agb は独自の #[panic_handler] を持っていて差し替えられない。
+// testing フィーチャーの有無でどちらかの実装が必ず入るので、こちらで定義すると
+// lang item の重複 (E0152) でコンパイルできない。フィーチャーで切り替える余地も無い。
+// その実装は mGBA のデバッグログにメッセージを書いて無限ループするだけなので、
+// 実機では真っ黒 (もしくは最後のフレームのまま) で止まってしまう。
+//
+// そこで VBlank 割り込みで見張り番を動かし、メインループが一定時間 feed() しなくなったら
+// パニック (またはハング) とみなして画面を乗っ取る。
+// パニックはゲームのどの時点でも起き得るので、表示モード・BG・パレット・8x8 フォントの
+// タイルまで全てここで一から設定し、ゲーム側のタイル状態には一切依存しない。
+//
+// 制限: パニックのメッセージと場所 (ファイルと行) はこの画面に出せない。
+// agb のハンドラは PanicInfo を mGBA のデバッグログに書くだけで、横取りするフックも無く、
+// 見張り番が気付いたときにはどこにも残っていない。実機ではログも無いので、止まったことと止まったフレームしか分からない。
+// 画面には mGBA で動いているか (Mgba::new で分かる) を見て、メッセージの読み方を出す。
+
+use core::fmt::{self, Write};
+
+use agb::{
+    interrupt::{add_interrupt_handler, Interrupt, InterruptHandler},
+    mgba::Mgba,
+    sync::Static,
+};
+
+use crate::font;
+
+/* メインループが止まったとみなすまでの VBlank 数 (約 2 秒) */
+const WATCHDOG_FRAMES: u32 = 120;
+
+static FRAMES_SINCE_FEED: Static<u32> = Static::new(0);
+static TOTAL_FRAMES: Static<u32> = Static::new(0);
+
+const DISPCNT: *mut u16 = 0x0400_0000 as *mut u16;
+const BG0CNT: *mut u16 = 0x0400_0008 as *mut u16;
+const BG0HOFS: *mut u16 = 0x0400_0010 as *mut u16;
+const BG0VOFS: *mut u16 = 0x0400_0012 as *mut u16;
+const MOSAIC: *mut u16 = 0x0400_004C as *mut u16;
+const BLDCNT: *mut u16 = 0x0400_0050 as *mut u16;
+const BG_PALETTE: *mut u16 = 0x0500_0000 as *mut u16;
+/* キャラクタベースブロック 0 */
+const CHAR_BASE: *mut u32 = 0x0600_0000 as *mut u32;
+/* スクリーンベースブロック 31 */
+const SCREEN_BASE: *mut u16 = 0x0600_F800 as *mut u16;
+const SCREEN_BLOCK: u16 = 31;
+
+const COLUMNS: usize = 30;
+const ROWS: usize = 20;
+
+// 見張り番を VBlank 割り込みに登録する。戻り値を保持している間だけ有効。
+pub fn install_watchdog() -> InterruptHandler {
+    // Safety: ハンドラ内ではアロケーションしない
+    unsafe {
+        add_interrupt_handler(Interrupt::VBlank, |_| {
+            TOTAL_FRAMES.write(TOTAL_FRAMES.read().wrapping_add(1));
+            let frames = FRAMES_SINCE_FEED.read().saturating_add(1);
+            FRAMES_SINCE_FEED.write(frames);
+            if frames == WATCHDOG_FRAMES {
+                log_warn!("main loop stalled for {} frames", frames);
+                show_watchdog_screen();
+            }
+        })
+    }
+}
+
+// メインループから毎フレーム呼び、生きていることを見張り番に知らせる。
+pub fn feed() {
+    FRAMES_SINCE_FEED.write(0);
+}
+
+// 見張り番を入れてから数えた VBlank の回数。フレームの取りこぼし検出にも使う。
+pub fn vblank_count() -> u32 {
+    TOTAL_FRAMES.read()
+}
+
+fn show_watchdog_screen() {
+    let where_to_look = if Mgba::new().is_some() {
+        "The message and location are\n\
+         in the mGBA debug log."
+    } else {
+        "The message and location are\n\
+         only logged in mGBA. Replay\n\
+         this there to read them."
+    };
+    let mut screen = Screen::setup();
+    let _ = write!(
+        screen,
+        "*** PANIC ***\n\n\
+         The main loop stopped\n\
+         responding.\n\n\
+         {}\n\n\
+         frame: {}",
+        where_to_look,
+        TOTAL_FRAMES.read().wrapping_sub(WATCHDOG_FRAMES)
+    );
+}
+
+// 起動時の検査で見つかった問題を並べて止まる。見張り番を入れる前に呼ぶこと。
+#[cfg(feature = "debug-tools")]
+pub fn show_problems(problems: &[alloc::string::String]) -> ! {
+    let mut screen = Screen::setup();
+    let _ = write!(screen, "*** BAD DATA ***\n\n");
+    for problem in problems {
+        let _ = writeln!(screen, "{}", problem);
+    }
+    loop {
+        agb::syscall::halt();
+    }
+}
+
+// 直接レジスタと VRAM を叩く最小限のテキスト画面。
+struct Screen {
+    column: usize,
+    row: usize,
+}
+
+impl Screen {
+    fn setup() -> Self {
+        // Safety: メインループは止まっているので、画面関係のハードウェアはここが占有できる
+        unsafe {
+            /* 描画中の乱れを避けるため、設定が終わるまで強制ブランク */
+            DISPCNT.write_volatile(1 << 7);
+            BG0CNT.write_volatile(SCREEN_BLOCK << 8);
+            BG0HOFS.write_volatile(0);
+            BG0VOFS.write_volatile(0);
+            MOSAIC.write_volatile(0);
+            BLDCNT.write_volatile(0);
+
+            BG_PALETTE.write_volatile(rgb5(8, 0, 0));
+            BG_PALETTE.add(1).write_volatile(rgb5(31, 31, 31));
+
+            /* VRAM は 8bit 書き込みできないので 32bit ずつ転送する */
+            let tiles = font::TILES.0.as_ptr() as *const u32;
+            for i in 0..font::TILES.0.len() / 4 {
+                CHAR_BASE.add(i).write_volatile(tiles.add(i).read());
+            }
+
+            for i in 0..32 * 32 {
+                SCREEN_BASE.add(i).write_volatile(font::tile_for(b' '));
+            }
+
+            /* モード 0 、 BG0 のみ表示 */
+            DISPCNT.write_volatile(1 << 8);
+        }
+
+        Self { column: 1, row: 1 }
+    }
+
+    fn newline(&mut self) {
+        self.column = 1;
+        self.row += 1;
+    }
+
+    fn put(&mut self, ch: u8) {
+        if self.column >= COLUMNS - 1 {
+            self.newline();
+        }
+        if self.row >= ROWS - 1 {
+            return;
+        }
+        // Safety: 行と列は画面内に収めてある
+        unsafe {
+            SCREEN_BASE
+                .add(self.row * 32 + self.column)
+                .write_volatile(font::tile_for(ch));
+        }
+        self.column += 1;
+    }
+}
+
+impl Write for Screen {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for ch in s.bytes() {
+            match ch {
+                b'\n' => self.newline(),
+                _ => self.put(ch),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn rgb5(r: u16, g: u16, b: u16) -> u16 {
+    r | (g << 5) | (b << 10)
+}
@@ -0,0 +1,44 @@
+// ポーズ画面のシーン。ゲーム本編の上に積まれ、START で本編に戻る。
+
+use agb::{display::Priority, input::Button};
+
+use crate::{
+    input::Input,
+    layer::Layer,
+    scene::{Resources, Scene, Transition},
+    text::TextLayer,
+};
+
+pub struct PauseScene<'g> {
+    text: Option<TextLayer<'g>>,
+}
+
+impl PauseScene<'_> {
+    pub fn new() -> Self {
+        Self { text: None }
+    }
+}
+
+impl<'g> Scene<'g> for PauseScene<'g> {
+    fn enter(&mut self, res: &mut Resources<'g>) {
+        let mut text = TextLayer::new(Layer::new(res.gfx, Priority::P0), &mut res.vram);
+        text.print_line(&mut res.vram, 9, format_args!("            PAUSE"));
+        text.set_visible(true);
+        text.commit(&mut res.vram);
+        self.text = Some(text);
+    }
+
+    fn update(&mut self, _res: &mut Resources<'g>, input: &Input) -> Transition<'g> {
+        if input.is_just_pressed(Button::START) {
+            Transition::Pop
+        } else {
+            Transition::Stay
+        }
+    }
+
+    fn exit(&mut self, res: &mut Resources<'g>) {
+        if let Some(text) = self.text.take() {
+            text.release(&mut res.vram);
+        }
+    }
+}
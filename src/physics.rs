@@ -0,0 +1,791 @@
+// ドロイド君の動きの調整値。
+//
+// 跳び方、歩く速さと加減速、コヨーテタイムと先行入力の猶予、壁ジャンプの蹴り出しはここにまとめてあり、
+// Game が持つ Tuning を Game::update_droid が Droid::update に渡して読ませる。
+// 低重力や重いドロイド君のようなモードを作るときは、Game::tuning を差し替える (with_jump で重力と高さから作れる)。
+// リプレイには保存しないので、差し替えるモードを作るときはリプレイにも残すこと。
+//
+// 跳び方は重力と、一番低いジャンプと一番高いジャンプの高さで決める。
+// 跳んだ瞬間は必ず一番高く跳べる速さ (jump_speed) で上り始め、上っている途中で UP を離すと
+// 速度を release_speed まで落とす。なので跳んですぐ離せば min_jump_height 、
+// 押し続ければ max_jump_height まで上り、その間は離すのが遅いほど高い。
+// 2 つの速さは高さから speed_for で求める。位置は毎ティック速度の整数部だけ動くので、
+// 公式 (v² = 2gh) ではなく、実際に動かしてちょうどその高さに届く一番遅い速さを探している。
+//
+// 落ちる速さは max_fall_speed で頭打ちになる。NORMAL では一番高いジャンプから地面に落ちても届かない速さで、
+// 効くのはエンドレスの長い落下だけ。落ちている間に DOWN を押していると (急降下)、
+// 重力が fast_fall_gravity に、上限が max_fast_fall_speed に変わる。A を押していると (滑空)、
+// 上限が max_glide_speed まで下がり、ゆっくり落ちながら横に進める。
+// 壁に接してその方へ押していると (壁ずり)、上限が max_wall_slide_speed に下がる。そのまま壁ジャンプできる。
+//
+// 左右は止まっているところから accel ずつ speed まで速くなり、離すと friction ずつ遅くなって止まる。
+// 地上で B を押し続けていると走り、run の速さまで速くなる。走ったまま跳ぶと、空中でも run の速さまで出せるので
+// 歩いて跳ぶより遠くへ届く。
+// 氷の地面 (terrain::Material::Ice) の上では、加減速と摩擦が ice のものになる (速さは歩く、走るのまま)。
+// この 4 つ (Control) は接地中 (ground) と空中 (air) で別に持ち、跳んだときの横の動きだけを
+// ふわりとさせたり重くしたりできる。NORMAL は両方同じ値。
+// speed より速く動いているとき (ダッシュの後) は、同じ向きに押していれば friction ずつしか遅くならない。
+//
+// 走っているところで DOWN を押すとスライディングになり、slide_speed から slide_friction ずつ遅くなって止まる。
+//
+// B のダッシュは dash_ticks の間、向いている方へ dash_speed で進む。次のダッシュは
+// 始めてから dash_cooldown_ticks 経つまで出せない。
+//
+// ダメージを受けると、ダメージを与えたものと反対へ knockback_speed で飛ばされ、KNOCKBACK_HEIGHT まで
+// 跳ね上がる (ノックバック)。knockback_ticks の間か着地するまでは、キーが効かない。
+//
+// 跳んだ後 (歩いて落ちたときは除く)、ジャンプの頂上 (上下の速さが ground_pound_apex 以下) で DOWN を押すとヒップドロップになる。
+// ground_pound_halt_ticks の間その場で止まり、それから ground_pound_speed で真下へ落ちる。
+//
+// 跳ねる地面 (terrain::Material::Bouncy) に min_bounce_speed 以上の速さで落ちると、着地せずに
+// 落ちてきた速さの bounce 倍で跳ね返る。UP を押していれば held_bounce 倍になる。どちらも 1 未満なので
+// 跳ね続けても段々低くなり、いずれ着地する。跳ね返って上る間は UP を離しても速度を落とさない。
+// ばね (spring モジュール) に乗ると spring_speed で打ち上げられ、SPRING_HEIGHT まで上る。こちらも UP を離しても
+// 速度を落とさない。
+//
+// 歩きモーションは walk_frame_ticks ごとに 1 コマ進む (走っているときは倍の速さ)。
+// りんごに乗れる範囲は、りんごの x から左右に apple_half_width まで (修飾で狭まる、Game::apple_half_width)。
+// りんごの絵の当たり判定は NORMAL の幅に合わせてあり、違う幅なら左右から同じだけ削るか広げる。
+//
+// 水の中 (Medium::Water、water モジュール) では Game が持つ Tuning の代わりに WATER で動く (for_medium)。
+// 重力と落ちる速さの上限が小さく、低くしか跳べず、左右もゆっくりになる。
+//
+// 速度は 1 ティックに進むドット数で、24.8 の固定小数点 (値は raw で 1/256 ドット単位)。
+//
+// 上下の速度は「跳ぶ向き」が正で、重力の向き (Gravity) が逆さなら画面の下へ跳んで天井に落ちる。
+// 画面の y と比べるところは、直接比べずに Gravity を通す。
+
+use crate::{math::Fixed, snapshot::encode_variants};
+
+// ドロイド君のいる所。
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Medium {
+    #[default]
+    Air,
+    Water,
+}
+
+encode_variants!(Medium { Air, Water });
+
+// 重力の向き。Up のときは天井 (画面の上端) に立ち、地面の方へ跳ぶ。
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Gravity {
+    #[default]
+    Down,
+    Up,
+}
+
+encode_variants!(Gravity { Down, Up });
+
+impl Gravity {
+    pub const fn flipped(self) -> Self {
+        match self {
+            Gravity::Down => Gravity::Up,
+            Gravity::Up => Gravity::Down,
+        }
+    }
+
+    // 跳ぶ向きへ dy ドット進んだときの、画面の y の変化。
+    pub const fn rise(self, dy: i32) -> i32 {
+        match self {
+            Gravity::Down => -dy,
+            Gravity::Up => dy,
+        }
+    }
+
+    // y が足元の y (floor) を越えて、重力の向きへめり込んでいるか。
+    pub const fn sinks_past(self, y: i32, floor: i32) -> bool {
+        match self {
+            Gravity::Down => y > floor,
+            Gravity::Up => y < floor,
+        }
+    }
+
+    // y が頭の方の限り (ceiling) を越えているか。
+    pub const fn rises_past(self, y: i32, ceiling: i32) -> bool {
+        self.flipped().sinks_past(y, ceiling)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tuning {
+    /* 毎ティック引く速度 */
+    pub gravity: Fixed,
+    /* 跳んですぐ UP を離したときに上る高さ (ドット) */
+    pub min_jump_height: i32,
+    /* UP を押し続けたときに上る高さ (ドット) */
+    pub max_jump_height: i32,
+    /* 跳んだ瞬間の速度。重力と max_jump_height から決まる (with_jump) */
+    pub jump_speed: Fixed,
+    /* UP を離したときに落とす速度。重力と min_jump_height から決まる (with_jump) */
+    pub release_speed: Fixed,
+    /* 落ちる速さの上限 (終端速度)。足場を跨ぐ判定はするが、1 ティックに動ける量も抑えておく */
+    pub max_fall_speed: Fixed,
+    /* 急降下中の重力と、落ちる速さの上限 */
+    pub fast_fall_gravity: Fixed,
+    pub max_fast_fall_speed: Fixed,
+    /* 滑空中の落ちる速さの上限。y は速度の整数部だけ動くので、1 ドット未満にすると落ちなくなる */
+    pub max_glide_speed: Fixed,
+    /* 壁ずり中の落ちる速さの上限 */
+    pub max_wall_slide_speed: Fixed,
+    /* 接地中と空中の左右の動き */
+    pub ground: Control,
+    pub air: Control,
+    /* 走っているときの左右の動き (地上と、走ったまま跳んだ空中の速さ) */
+    pub run: Control,
+    /* 氷の上の加減速と摩擦 (speed は使わない) */
+    pub ice: Control,
+    /* 足場から歩いて落ちた後、まだ地面からのジャンプを受け付けるティック数 (コヨーテタイム)。0 なら受け付けない */
+    pub coyote_ticks: u16,
+    /* 着地のこのティック数前までに押した UP は、着地したときに跳ぶ (先行入力)。0 なら受け付けない */
+    pub jump_buffer_ticks: u16,
+    /* 壁ジャンプで壁から離れる向きに 1 ティックに進むドット数と、そのティック数 (その間は左右キーが効かない) */
+    pub wall_kick_speed: i32,
+    pub wall_kick_ticks: u16,
+    /* ダッシュの速さ (ドット/ティック) と続くティック数 */
+    pub dash_speed: Fixed,
+    pub dash_ticks: u16,
+    /* ダッシュを始めてから、次のダッシュが出せるまでのティック数 */
+    pub dash_cooldown_ticks: u16,
+    /* スライディングを始めたときの速さと、毎ティック落とす量 */
+    pub slide_speed: Fixed,
+    pub slide_friction: Fixed,
+    /* ノックバックの横の速さと、跳ね上がる速さ (KNOCKBACK_HEIGHT から決まる) */
+    pub knockback_speed: Fixed,
+    pub knockback_lift: Fixed,
+    /* ノックバックでキーが効かないティック数 */
+    pub knockback_ticks: u16,
+    /* ヒップドロップを受け付ける、頂上付近の上下の速さ */
+    pub ground_pound_apex: Fixed,
+    /* ヒップドロップで止まっているティック数と、その後に落ちる速さ */
+    pub ground_pound_halt_ticks: u16,
+    pub ground_pound_speed: Fixed,
+    /* 跳ねる地面で跳ね返るときの、落ちてきた速さに掛ける割合 (UP を押していないときと押しているとき) */
+    pub bounce: Fixed,
+    pub held_bounce: Fixed,
+    /* これより遅く落ちてきたら、跳ねる地面でも跳ね返らずに着地する */
+    pub min_bounce_speed: Fixed,
+    /* ばねで打ち上げられる速さ (SPRING_HEIGHT から決まる) */
+    pub spring_speed: Fixed,
+    /* 歩きモーションを 1 コマ進めるティック数 */
+    pub walk_frame_ticks: u16,
+    /* りんごに乗れる範囲の、りんごの x からの幅 */
+    pub apple_half_width: i32,
+}
+
+// 左右の動き。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Control {
+    /* 左右キーを押し続けたときの速さ (ドット/ティック) */
+    pub speed: Fixed,
+    /* 押している向きへ速度を上げる量 (毎ティック) */
+    pub accel: Fixed,
+    /* 動いているのと逆へ押したときに速度を落とす量 (毎ティック) */
+    pub decel: Fixed,
+    /* 左右キーを離しているときに速度を落とす量 (毎ティック)。小さいほど長く滑る */
+    pub friction: Fixed,
+}
+
+const WALK: Control = Control {
+    speed: Fixed::from_raw(256),
+    accel: Fixed::from_raw(64),
+    decel: Fixed::from_raw(128),
+    friction: Fixed::from_raw(32),
+};
+
+const RUN: Control = Control {
+    speed: Fixed::from_raw(448),
+    ..WALK
+};
+
+const ICE: Control = Control {
+    accel: Fixed::from_raw(16),
+    decel: Fixed::from_raw(16),
+    friction: Fixed::from_raw(4),
+    ..WALK
+};
+
+/* 水の中の左右の動き。走っても速くならない */
+const SWIM: Control = Control {
+    speed: Fixed::from_raw(160),
+    accel: Fixed::from_raw(32),
+    decel: Fixed::from_raw(64),
+    friction: Fixed::from_raw(16),
+};
+
+/* NORMAL の重力 (raw) と高さ。高さは固定小数点にする前の、跳んですぐ離したときと押し続けたときの高さ */
+const GRAVITY: i32 = 76;
+const MIN_JUMP_HEIGHT: i32 = 23;
+const MAX_JUMP_HEIGHT: i32 = 72;
+/* 水の中の重力 (raw) と高さ */
+const WATER_GRAVITY: i32 = GRAVITY / 4;
+const WATER_MIN_JUMP_HEIGHT: i32 = 8;
+const WATER_MAX_JUMP_HEIGHT: i32 = 24;
+/* ノックバックで跳ね上げられる高さ */
+const KNOCKBACK_HEIGHT: i32 = 12;
+/* ばねで打ち上げられる高さ (一番高いジャンプの 1.5 倍) */
+pub const SPRING_HEIGHT: i32 = MAX_JUMP_HEIGHT * 3 / 2;
+
+impl Tuning {
+    pub const NORMAL: Self = Self {
+        gravity: Fixed::from_raw(GRAVITY),
+        min_jump_height: MIN_JUMP_HEIGHT,
+        max_jump_height: MAX_JUMP_HEIGHT,
+        jump_speed: Fixed::from_raw(speed_for(MAX_JUMP_HEIGHT, GRAVITY)),
+        release_speed: Fixed::from_raw(speed_for(MIN_JUMP_HEIGHT, GRAVITY)),
+        max_fall_speed: Fixed::from_raw(7 << 8),
+        fast_fall_gravity: Fixed::from_raw(GRAVITY * 2),
+        max_fast_fall_speed: Fixed::from_raw(9 << 8),
+        max_glide_speed: Fixed::from_raw(1 << 8),
+        max_wall_slide_speed: Fixed::from_raw(2 << 8),
+        ground: WALK,
+        air: WALK,
+        run: RUN,
+        ice: ICE,
+        coyote_ticks: 6,
+        jump_buffer_ticks: 5,
+        wall_kick_speed: 2,
+        wall_kick_ticks: 8,
+        dash_speed: Fixed::from_raw(3 << 8),
+        dash_ticks: 12,
+        dash_cooldown_ticks: 40,
+        slide_speed: Fixed::from_raw(640),
+        slide_friction: Fixed::from_raw(16),
+        knockback_speed: Fixed::from_raw(384),
+        knockback_lift: Fixed::from_raw(speed_for(KNOCKBACK_HEIGHT, GRAVITY)),
+        knockback_ticks: 16,
+        ground_pound_apex: Fixed::from_raw(256),
+        ground_pound_halt_ticks: 8,
+        ground_pound_speed: Fixed::from_raw(8 << 8),
+        bounce: Fixed::from_raw(192),
+        held_bounce: Fixed::from_raw(240),
+        min_bounce_speed: Fixed::from_raw(2 << 8),
+        spring_speed: Fixed::from_raw(speed_for(SPRING_HEIGHT, GRAVITY)),
+        walk_frame_ticks: 6,
+        apple_half_width: 12,
+    };
+
+    // 水の中。重力が 1/4 で、落ちる速さは 1 ドットで頭打ちになる。跳ぶ速さは水を掻いて上る速さにも使う。
+    // 跳ねる地面に落ちても跳ね返る速さにならない。
+    pub const WATER: Self = Self {
+        gravity: Fixed::from_raw(WATER_GRAVITY),
+        min_jump_height: WATER_MIN_JUMP_HEIGHT,
+        max_jump_height: WATER_MAX_JUMP_HEIGHT,
+        jump_speed: Fixed::from_raw(speed_for(WATER_MAX_JUMP_HEIGHT, WATER_GRAVITY)),
+        release_speed: Fixed::from_raw(speed_for(WATER_MIN_JUMP_HEIGHT, WATER_GRAVITY)),
+        max_fall_speed: Fixed::from_raw(1 << 8),
+        fast_fall_gravity: Fixed::from_raw(GRAVITY / 2),
+        max_fast_fall_speed: Fixed::from_raw(2 << 8),
+        max_wall_slide_speed: Fixed::from_raw(1 << 8),
+        ground: SWIM,
+        air: SWIM,
+        run: SWIM,
+        dash_speed: Fixed::from_raw(2 << 8),
+        slide_speed: Fixed::from_raw(384),
+        ground_pound_speed: Fixed::from_raw(3 << 8),
+        ..Self::NORMAL
+    };
+
+    // medium にいるときに使う調整値。水の中なら持っている値に関わらず WATER 。
+    pub fn for_medium(&self, medium: Medium) -> &Self {
+        match medium {
+            Medium::Air => self,
+            Medium::Water => &Self::WATER,
+        }
+    }
+
+    // 重力と高さを変え、跳ぶ速さを求め直したもの。モードが無いので、使うのはテストと調整 (tuner) だけ。
+    #[cfg_attr(not(any(test, feature = "debug-tools")), allow(dead_code))]
+    pub fn with_jump(self, gravity: Fixed, min_height: i32, max_height: i32) -> Self {
+        let gravity_raw = gravity.to_raw();
+        Self {
+            gravity,
+            min_jump_height: min_height,
+            max_jump_height: max_height,
+            jump_speed: Fixed::from_raw(speed_for(max_height, gravity_raw)),
+            release_speed: Fixed::from_raw(speed_for(min_height, gravity_raw)),
+            ..self
+        }
+    }
+}
+
+// 速さ speed で跳び、重力 gravity だけで減速したときに上る高さ。どちらも raw 。
+const fn apex(mut speed: i32, gravity: i32) -> i32 {
+    let mut height = 0;
+    while speed > 0 {
+        height += speed >> 8;
+        speed -= gravity;
+    }
+    height
+}
+
+// ちょうど height まで上る一番遅い速さ (raw)。
+const fn speed_for(height: i32, gravity: i32) -> i32 {
+    let (mut low, mut high) = (0, 64 << 8);
+    while low < high {
+        let middle = (low + high) / 2;
+        if apex(middle, gravity) >= height {
+            high = middle;
+        } else {
+            low = middle + 1;
+        }
+    }
+    low
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Self::NORMAL
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use agb::input::Button;
+
+    use super::*;
+    use crate::{
+        collision::{Aabb, Collider},
+        difficulty::Difficulty,
+        game::{Game, DROID_WIDTH, GROUND_Y},
+        input::Input,
+        level::{Level, GROUND_COLUMNS},
+        mode::Mode,
+        player::PlayerState,
+        terrain::Material,
+    };
+
+    // 重力が半分で、高く跳ぶ。
+    fn low_gravity() -> Tuning {
+        let normal = Tuning::NORMAL;
+        normal.with_jump(normal.gravity / 2, 32, 100)
+    }
+
+    // 重いドロイド君。重力が 1.25 倍で、低くしか跳べない。
+    fn heavy() -> Tuning {
+        let normal = Tuning::NORMAL;
+        normal.with_jump(normal.gravity * 5 / 4, 16, 48)
+    }
+
+    // ジャンプ準備の間と、跳んでから hold ティックの間 UP を押して跳んだときの、
+    // 頂点の高さと空中にいたティック数。
+    // 呼ぶ所ごとにインライン展開されると Game がその数だけスタックに並び、IWRAM の処理の所まで伸びるので展開しない。
+    #[inline(never)]
+    fn jump(tuning: Tuning, hold: u32) -> (i32, u32) {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        game.tuning = tuning;
+        let mut previous = Button::empty();
+        let (mut apex, mut ticks) = (GROUND_Y, 0);
+        loop {
+            let buttons = if !matches!(game.droid.state, PlayerState::Airborne { double: false })
+                || ticks < hold
+            {
+                Button::UP
+            } else {
+                Button::empty()
+            };
+            game.update(&Input::new(buttons, previous));
+            previous = buttons;
+            match game.droid.state {
+                PlayerState::JumpSquat { double: false } => {}
+                PlayerState::Airborne { double: false } => ticks += 1,
+                _ => return (GROUND_Y - apex, ticks),
+            }
+            apex = apex.min(game.droid.y);
+        }
+    }
+
+    // 跳んですぐ離せば一番低く、押し続ければ一番高く跳び、その間は長く押すほど高い。
+    #[test_case]
+    fn jump_height_follows_how_long_up_is_held(_gba: &mut agb::Gba) {
+        for tuning in [Tuning::NORMAL, low_gravity(), heavy()] {
+            let heights: alloc::vec::Vec<_> = (0..40).map(|hold| jump(tuning, hold).0).collect();
+            assert_eq!(heights[0], tuning.min_jump_height, "{tuning:?}");
+            assert_eq!(
+                jump(tuning, u32::MAX).0,
+                tuning.max_jump_height,
+                "{tuning:?}"
+            );
+            assert!(
+                heights.windows(2).all(|pair| pair[0] <= pair[1]),
+                "{heights:?}"
+            );
+            assert!(heights
+                .iter()
+                .all(|height| (tuning.min_jump_height..=tuning.max_jump_height).contains(height)));
+        }
+    }
+
+    // 定数の跳ぶ速さは、その重力と高さから求め直したものと同じ。
+    #[test_case]
+    fn preset_speeds_match_their_heights(_gba: &mut agb::Gba) {
+        for tuning in [Tuning::NORMAL, Tuning::WATER] {
+            assert_eq!(
+                tuning.with_jump(
+                    tuning.gravity,
+                    tuning.min_jump_height,
+                    tuning.max_jump_height
+                ),
+                tuning
+            );
+        }
+        /* 一番低いジャンプは、固定小数点にする前の跳ぶ速さ 4 */
+        assert_eq!(Tuning::NORMAL.release_speed, Fixed::new(4));
+    }
+
+    // 調整値を差し替えると跳び方が変わり、同じ調整値なら同じ跳び方になる。
+    #[test_case]
+    fn tunings_change_the_jump(_gba: &mut agb::Gba) {
+        let normal = jump(Tuning::NORMAL, u32::MAX);
+        assert_eq!(jump(Tuning::default(), u32::MAX), normal);
+        let low = jump(low_gravity(), u32::MAX);
+        let heavy = jump(heavy(), u32::MAX);
+        assert!(
+            low.0 > normal.0 && low.1 > normal.1,
+            "{low:?} vs {normal:?}"
+        );
+        assert!(
+            heavy.0 < normal.0 && heavy.1 < normal.1,
+            "{heavy:?} vs {normal:?}"
+        );
+    }
+
+    // 押し始めは accel ずつ速くなって speed で頭打ちになり、離すと friction ずつ滑って止まる。
+    #[test_case]
+    fn walking_ramps_up_and_skids_to_a_stop(_gba: &mut agb::Gba) {
+        let walk = Tuning::NORMAL.ground;
+        let mut game = Game::new(Difficulty::Normal, 1);
+        let mut previous = Button::empty();
+        let ramp = (walk.speed / walk.accel).trunc();
+        for tick in 1..=ramp {
+            game.update(&Input::new(Button::LEFT, previous));
+            previous = Button::LEFT;
+            assert_eq!(game.droid.vx, -walk.accel * tick);
+        }
+        let x = game.droid.x;
+        for _ in 0..10 {
+            game.update(&Input::new(Button::LEFT, Button::LEFT));
+        }
+        assert_eq!(game.droid.vx, -walk.speed);
+        assert_eq!(game.droid.x, x - 10);
+        /* 離すと少し滑ってから止まる */
+        let x = game.droid.x;
+        let skid = (walk.speed / walk.friction).trunc();
+        game.update(&Input::new(Button::empty(), Button::LEFT));
+        for _ in 1..skid {
+            game.update(&Input::default());
+        }
+        assert_eq!(game.droid.vx, Fixed::new(0));
+        let stopped = game.droid.x;
+        assert!(stopped < x - 1, "skidded from {x} to {stopped}");
+        game.update(&Input::default());
+        assert_eq!(game.droid.x, stopped);
+        /* 逆へ押すと decel ずつ落ちる */
+        game.droid.vx = walk.speed;
+        game.update(&Input::new(Button::LEFT, Button::empty()));
+        assert_eq!(game.droid.vx, walk.speed - walk.decel);
+    }
+
+    // 接地中と空中はそれぞれの Control で動き、遊んでいる途中でも差し替えられる。
+    #[test_case]
+    fn ground_and_air_control_are_separate(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        game.tuning = Tuning {
+            ground: Control {
+                speed: Fixed::new(3),
+                accel: Fixed::new(3),
+                ..WALK
+            },
+            air: Control {
+                speed: Fixed::new(2),
+                accel: Fixed::new(2),
+                friction: Fixed::new(0),
+                ..WALK
+            },
+            ..Tuning::NORMAL
+        };
+        let x = game.droid.x;
+        game.update(&Input::new(Button::LEFT, Button::empty()));
+        game.update(&Input::new(Button::LEFT, Button::LEFT));
+        assert_eq!(game.droid.x, x - 6);
+        /* 跳んでから空中で */
+        game.update(&Input::new(Button::UP, Button::empty()));
+        while !matches!(game.droid.state, PlayerState::Airborne { double: false }) {
+            game.update(&Input::default());
+        }
+        game.droid.stop();
+        let x = game.droid.x;
+        game.update(&Input::new(Button::RIGHT, Button::empty()));
+        assert_eq!(game.droid.x, x + 2);
+        /* 空中で離しても摩擦が無ければ流れ続ける */
+        for _ in 0..3 {
+            game.update(&Input::default());
+        }
+        assert_eq!(game.droid.x, x + 8);
+    }
+
+    // B を押したまま歩くと走る速さまで出て、歩きモーションが倍の速さになる。
+    // 走ったまま跳ぶと、空中でもその速さのまま進むので遠くへ届く。
+    #[test_case]
+    fn running_is_faster_and_jumps_further(_gba: &mut agb::Gba) {
+        let tuning = Tuning::NORMAL;
+        let run = |held: Button| {
+            let mut game = Game::new(Difficulty::Normal, 1);
+            game.load(&Level::tutorial());
+            let mut steps = 0;
+            for _ in 0..60 {
+                let ch = game.droid.ch;
+                game.update(&Input::new(held, held));
+                steps += u32::from(game.droid.ch != ch);
+            }
+            let speed = game.droid.vx;
+            let x = game.droid.x;
+            game.update(&Input::new(held | Button::UP, held));
+            while game.droid.state != PlayerState::Idle {
+                game.update(&Input::new(held | Button::UP, held | Button::UP));
+            }
+            (speed, steps, game.droid.x - x)
+        };
+        let (walk_speed, walk_steps, walk_distance) = run(Button::RIGHT);
+        let (run_speed, run_steps, run_distance) = run(Button::RIGHT | Button::B);
+        assert_eq!(walk_speed, tuning.ground.speed);
+        assert_eq!(run_speed, tuning.run.speed);
+        assert!(
+            run_steps >= 2 * walk_steps - 1,
+            "{run_steps} vs {walk_steps}"
+        );
+        assert!(
+            run_distance > walk_distance * 3 / 2,
+            "{run_distance} vs {walk_distance}"
+        );
+    }
+
+    // 氷の上では離してもなかなか止まらず、普通の地面よりずっと遠くまで滑っていく。
+    // 氷かどうかは地面のタイルで決まり、普通のレベルの両端寄りにも張ってある。
+    #[test_case]
+    fn ice_keeps_the_droid_sliding(_gba: &mut agb::Gba) {
+        let slide = |material: Material| {
+            let mut game = Game::new(Difficulty::Normal, 1);
+            game.load(&Level::tutorial());
+            game.materials = [material; GROUND_COLUMNS];
+            for _ in 0..60 {
+                game.update(&Input::new(Button::RIGHT, Button::RIGHT));
+            }
+            assert_eq!(game.droid.vx, Tuning::NORMAL.ground.speed);
+            let x = game.droid.x;
+            let mut ticks = 0;
+            game.update(&Input::new(Button::empty(), Button::RIGHT));
+            while game.droid.vx != Fixed::new(0) {
+                game.update(&Input::default());
+                ticks += 1;
+            }
+            (ticks, game.droid.x - x)
+        };
+        let (ground_ticks, ground_distance) = slide(Material::Normal);
+        let (ice_ticks, ice_distance) = slide(Material::Ice);
+        assert!(
+            ice_ticks > ground_ticks * 4,
+            "{ice_ticks} vs {ground_ticks}"
+        );
+        assert!(
+            ice_distance > ground_distance * 4,
+            "{ice_distance} vs {ground_distance}"
+        );
+        assert!(Level::load(false)
+            .ground
+            .map(Material::of_tile)
+            .contains(&Material::Ice));
+    }
+
+    // 跳ねる地面に落ちると着地せずに跳ね返り、UP を押していればもっと高く跳ねる。
+    // 跳ね返りは段々低くなって、いずれ着地する。普通の地面ではそのまま着地する。
+    #[test_case]
+    fn bouncy_ground_throws_the_droid_back_up(_gba: &mut agb::Gba) {
+        let tuning = Tuning::NORMAL;
+        let bounce = |material: Material, held: Button| {
+            let mut game = Game::new(Difficulty::Normal, 1);
+            game.load(&Level::tutorial());
+            game.materials = [material; GROUND_COLUMNS];
+            game.update(&Input::new(Button::UP, Button::empty()));
+            while game.droid.vy >= Fixed::new(0) {
+                game.update(&Input::new(Button::UP, Button::UP));
+            }
+            /* 跳ね返るかは落ちてきたときに UP を押しているかで決まる */
+            while game.droid.state != PlayerState::Idle && !game.droid.bouncing {
+                game.update(&Input::new(held, held));
+            }
+            /* 跳ね返った高さ */
+            let mut top = GROUND_Y;
+            while game.droid.bouncing {
+                game.update(&Input::new(held, held));
+                top = top.min(game.droid.y);
+            }
+            for _ in 0..600 {
+                game.update(&Input::new(held, held));
+            }
+            assert_eq!(
+                (game.droid.state, game.droid.y),
+                (PlayerState::Idle, GROUND_Y)
+            );
+            GROUND_Y - top
+        };
+        assert_eq!(bounce(Material::Normal, Button::empty()), 0);
+        let low = bounce(Material::Bouncy, Button::empty());
+        let high = bounce(Material::Bouncy, Button::UP);
+        /* 離していても一番低いジャンプに抑えられない */
+        assert!(tuning.min_jump_height < low, "{low}");
+        assert!(low < high && high < tuning.max_jump_height, "{low} {high}");
+    }
+
+    // SELECT で重力が逆さになると天井へ落ちて立ち、そこから地面の方へ同じ高さだけ跳ぶ。
+    // もう一度押せば地面へ戻る。エンドレスでは天井が無いので反転しない。
+    #[test_case]
+    fn flipped_gravity_walks_on_the_ceiling(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        game.update(&Input::new(Button::SELECT, Button::empty()));
+        assert_eq!(
+            (game.droid.state, game.droid.gravity),
+            (PlayerState::Airborne { double: false }, Gravity::Up)
+        );
+        while game.droid.state != PlayerState::Idle {
+            game.update(&Input::default());
+        }
+        assert_eq!(game.droid.y, 0);
+        /* 歩いても天井から離れない */
+        for _ in 0..10 {
+            game.update(&Input::new(Button::LEFT, Button::LEFT));
+            assert_eq!((game.droid.state, game.droid.y), (PlayerState::Idle, 0));
+        }
+        /* しゃがむと天井側の半分だけになる */
+        game.update(&Input::new(Button::DOWN, Button::empty()));
+        assert_eq!(
+            game.droid.hitbox(),
+            Aabb::new(game.droid.x, 0, DROID_WIDTH, DROID_WIDTH / 2)
+        );
+        game.update(&Input::default());
+        /* 下へ跳んで戻ってくる */
+        game.update(&Input::new(Button::UP, Button::empty()));
+        let mut lowest = 0;
+        while game.droid.state != PlayerState::Idle {
+            game.update(&Input::new(Button::UP, Button::UP));
+            lowest = lowest.max(game.droid.y);
+        }
+        assert_eq!(lowest, Tuning::NORMAL.max_jump_height);
+        assert_eq!(game.droid.y, 0);
+        game.update(&Input::new(Button::SELECT, Button::empty()));
+        while game.droid.state != PlayerState::Idle {
+            game.update(&Input::default());
+        }
+        assert_eq!(
+            (game.droid.y, game.droid.gravity),
+            (GROUND_Y, Gravity::Down)
+        );
+
+        let mut endless = Game::new(Difficulty::Normal, 1).with_mode(Mode::Endless);
+        endless.update(&Input::new(Button::SELECT, Button::empty()));
+        assert_eq!(
+            (endless.droid.state, endless.droid.gravity),
+            (PlayerState::Idle, Gravity::Down)
+        );
+    }
+
+    // 長く落ちても max_fall_speed より速くならない。
+    #[test_case]
+    fn falls_are_clamped_to_the_terminal_velocity(_gba: &mut agb::Gba) {
+        for max_fall_speed in [Tuning::NORMAL.max_fall_speed, Fixed::new(2)] {
+            let mut game = Game::new(Difficulty::Normal, 1);
+            game.tuning = Tuning {
+                max_fall_speed,
+                ..Tuning::NORMAL
+            };
+            /* 画面の一番上から落とす */
+            game.droid.y = 0;
+            game.droid.state = PlayerState::Airborne { double: false };
+            let mut reached = false;
+            while matches!(game.droid.state, PlayerState::Airborne { double: false }) {
+                let y = game.droid.y;
+                game.update(&Input::default());
+                assert!(game.droid.vy >= -max_fall_speed, "{:?}", game.droid.vy);
+                assert!(game.droid.y - y <= max_fall_speed.trunc());
+                reached |= game.droid.vy == -max_fall_speed;
+            }
+            assert!(reached, "never reached {max_fall_speed:?}");
+            assert_eq!(game.droid.y, GROUND_Y);
+        }
+    }
+
+    // 落ちている間に DOWN を押すと、強い重力で速い終端速度まで落ちる。上っている間は効かない。
+    #[test_case]
+    fn fast_falls_drop_faster_to_a_higher_terminal_velocity(_gba: &mut agb::Gba) {
+        let tuning = Tuning::NORMAL;
+        let fall = |buttons: Button| {
+            let mut game = Game::new(Difficulty::Normal, 1);
+            /* 画面の一番上から、上りのジャンプの途中として落とす */
+            game.droid.y = 0;
+            game.droid.state = PlayerState::Airborne { double: false };
+            game.droid.vy = tuning.gravity * 3;
+            let mut ticks = 0;
+            let mut fastest = Fixed::new(0);
+            while matches!(game.droid.state, PlayerState::Airborne { double: false }) {
+                let rising = game.droid.vy > Fixed::new(0);
+                let vy = game.droid.vy;
+                game.update(&Input::new(buttons, buttons));
+                if rising {
+                    assert_eq!(game.droid.vy, vy - tuning.gravity);
+                } else if buttons == Button::DOWN
+                    && vy < Fixed::new(0)
+                    && matches!(game.droid.state, PlayerState::Airborne { double: false })
+                {
+                    assert!(game.droid.fast_falling);
+                    assert_eq!(game.droid.pose(), 16);
+                }
+                fastest = fastest.min(game.droid.vy);
+                ticks += 1;
+            }
+            assert!(!game.droid.fast_falling);
+            (ticks, fastest)
+        };
+        let (normal_ticks, normal_fastest) = fall(Button::empty());
+        let (fast_ticks, fast_fastest) = fall(Button::DOWN);
+        assert_eq!(normal_fastest, -tuning.max_fall_speed);
+        assert_eq!(fast_fastest, -tuning.max_fast_fall_speed);
+        assert!(fast_ticks < normal_ticks, "{fast_ticks} vs {normal_ticks}");
+    }
+
+    // 落ちている間に A を押していると、max_glide_speed より速くは落ちず、同じ高さから遠くまで進める。
+    // DOWN も押していれば急降下の方が勝つ。
+    #[test_case]
+    fn gliding_caps_the_fall_speed(_gba: &mut agb::Gba) {
+        let tuning = Tuning::NORMAL;
+        let fall = |buttons: Button| {
+            let mut game = Game::new(Difficulty::Normal, 1);
+            game.droid.x = 0;
+            game.droid.y = 0;
+            game.droid.state = PlayerState::Airborne { double: false };
+            let mut fastest = Fixed::new(0);
+            while matches!(game.droid.state, PlayerState::Airborne { double: false }) {
+                let falling = game.droid.vy < Fixed::new(0);
+                game.update(&Input::new(buttons, buttons));
+                if falling
+                    && matches!(game.droid.state, PlayerState::Airborne { double: false })
+                    && buttons == Button::RIGHT | Button::A
+                {
+                    assert!(game.droid.gliding);
+                    assert_eq!(game.droid.pose(), 24);
+                }
+                if matches!(game.droid.state, PlayerState::Airborne { double: false }) {
+                    fastest = fastest.min(game.droid.vy);
+                }
+            }
+            assert!(!game.droid.gliding);
+            (game.droid.x, fastest)
+        };
+        let (normal_x, normal_fastest) = fall(Button::RIGHT);
+        let (glide_x, glide_fastest) = fall(Button::RIGHT | Button::A);
+        let (_, fast_fastest) = fall(Button::RIGHT | Button::A | Button::DOWN);
+        assert_eq!(glide_fastest, -tuning.max_glide_speed);
+        assert!(glide_x > 2 * normal_x, "{glide_x} vs {normal_x}");
+        assert_eq!(normal_fastest, -tuning.max_fall_speed);
+        assert_eq!(fast_fastest, -tuning.max_fast_fall_speed);
+    }
+}
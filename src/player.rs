@@ -0,0 +1,1139 @@
+// ドロイド君。
+//
+// 位置と速度、向き、状態 (Droid::state) とタイマーを持つ。1 ティック分の動きは Droid::update で進める。
+// 地形や足場との当たりが要るので、Game::update_droid が周り (Surroundings) をまとめて渡す。
+// OAM には触らず、Droid::draw は見た目 (DroidLook) を返すだけで、スプライトは presenter がそれを読んで出す。
+
+use core::mem;
+
+use alloc::vec::Vec;
+
+use agb::input::Button;
+
+use crate::{
+    collision::{Aabb, Collider, Filter, Layers, Surfaces, Walls, LEDGE_HANG, LEDGE_REACH},
+    components::SpriteAnim,
+    conveyor,
+    game::{move_x, DROID_WIDTH, JUMP_SQUAT_TICKS},
+    input::Input,
+    ladder::climb_pose,
+    level::GROUND_COLUMNS,
+    math::Fixed,
+    pacing,
+    physics::{Control, Gravity, Medium, Tuning},
+    resources,
+    snapshot::{encode_fields, Encode, Reader},
+    terrain::{self, Material},
+    tilemap::CollisionMap,
+    time::{Cooldown, Timer},
+    water::swim_pose,
+};
+
+/* 歩きモーションのコマごとに表示するキャラクタ (止まった姿、歩き 1、止まった姿、歩き 2) */
+pub const WALK_CHARS: [u16; 4] = [0, 2, 0, 4];
+/* ダメージを受けてから次のダメージを受けるまでのティック数 */
+pub const INVULNERABLE_TICKS: u16 = 60;
+/* 足場の端からよじ登るのにかかるティック数 */
+const LEDGE_CLIMB_TICKS: u16 = 10;
+
+/* ドロイド君は敵と危険物、拾えるもの、地形に当たる */
+const DROID_FILTER: Filter = Filter::new(
+    Layers::PLAYER,
+    Layers::ENEMY
+        .union(Layers::HAZARD)
+        .union(Layers::PICKUP)
+        .union(Layers::TERRAIN),
+);
+
+// ドロイド君の状態。状態ごとの動きは Droid::update の match から呼ぶ update_* に 1 つずつあり、
+// 状態を足せばその match (と Droid::pose) が足りない腕をコンパイルエラーで知らせる。
+// 状態は Droid::enter でだけ変え、抜けるときと入るときの後始末はそこ (と Droid::exit) にまとめる。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlayerState {
+    /* 立っているか歩いている (歩きのコマは Droid::walk) */
+    Idle,
+    /* ジャンプ準備中。double なら二段ジャンプの */
+    JumpSquat { double: bool },
+    /* ジャンプ中か落ちている。double なら二段ジャンプの後で、もう二段ジャンプはできない */
+    Airborne { double: bool },
+    Dash,
+    Crouch,
+    Slide,
+    /* ダメージで飛ばされていてキーが効かない */
+    Knockback,
+    /* 足場の端にぶら下がっている */
+    LedgeHang,
+    /* 足場の端からよじ登っている */
+    LedgeClimb,
+    GroundPound,
+    /* はしごに掴まっている (ladder モジュール) */
+    Climbing,
+}
+
+/* 状態の番号の後に、二段ジャンプかどうかを持つものはそれも書く */
+impl Encode for PlayerState {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        let (index, double) = match *self {
+            PlayerState::Idle => (0, None),
+            PlayerState::JumpSquat { double } => (1, Some(double)),
+            PlayerState::Airborne { double } => (2, Some(double)),
+            PlayerState::Dash => (3, None),
+            PlayerState::Crouch => (4, None),
+            PlayerState::Slide => (5, None),
+            PlayerState::Knockback => (6, None),
+            PlayerState::LedgeHang => (7, None),
+            PlayerState::LedgeClimb => (8, None),
+            PlayerState::GroundPound => (9, None),
+            PlayerState::Climbing => (10, None),
+        };
+        bytes.push(index);
+        if let Some(double) = double {
+            double.encode(bytes);
+        }
+    }
+
+    fn decode(reader: &mut Reader) -> Option<Self> {
+        Some(match reader.take()? {
+            [0] => PlayerState::Idle,
+            [1] => PlayerState::JumpSquat {
+                double: bool::decode(reader)?,
+            },
+            [2] => PlayerState::Airborne {
+                double: bool::decode(reader)?,
+            },
+            [3] => PlayerState::Dash,
+            [4] => PlayerState::Crouch,
+            [5] => PlayerState::Slide,
+            [6] => PlayerState::Knockback,
+            [7] => PlayerState::LedgeHang,
+            [8] => PlayerState::LedgeClimb,
+            [9] => PlayerState::GroundPound,
+            [10] => PlayerState::Climbing,
+            _ => return None,
+        })
+    }
+}
+
+// ドロイド君の周り。Droid::update が 1 ティック進めるのに使う地形と足場、壁と天井、調整値。
+// Game::update_droid が毎ティック Game から作って渡す。
+pub struct Surroundings<'a> {
+    pub map: &'a CollisionMap,
+    /* 地面の列ごとの材質とベルトコンベアの速さ */
+    pub materials: &'a [Material; GROUND_COLUMNS],
+    pub conveyors: &'a [Fixed; GROUND_COLUMNS],
+    pub surfaces: Surfaces<'a>,
+    /* 上へ出られない y 座標と、仕切る壁 (Game::ceiling, Game::walls) */
+    pub ceiling: i32,
+    pub walls: Option<Walls>,
+    /* 空気の中の調整値。水の中では Tuning::for_medium で選び直す */
+    pub tuning: &'a Tuning,
+    /* 二段ジャンプができるか (Modifiers::no_double_jump) と、重力を反転できるか */
+    pub double_jump: bool,
+    pub can_flip: bool,
+}
+
+// 1 ティック分の動きに使う周りと入力。状態ごとの Droid::update_* が受け取る。
+struct Motion<'a> {
+    around: &'a Surroundings<'a>,
+    input: &'a Input,
+    /* 水の中なら水の中の動き */
+    tuning: &'a Tuning,
+    /* このティックに動き始めた x (着地はここからの線分で見る) */
+    x_start: i32,
+    /* 押している向き (-1, 0, 1)。向きは変えない */
+    pushing: i32,
+}
+
+// ドロイド君の見た目。presenter は前回と同じところは書き込まない。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DroidLook {
+    pub position: (i32, i32),
+    pub hflip: bool,
+    /* 重力が逆さなら上下も反転する */
+    pub vflip: bool,
+    pub ch: u16,
+    pub visible: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Droid {
+    pub x: i32,
+    pub y: i32,
+    /* 前のティックの位置。描くときに今の位置との間を補う (Droid::drawn_at) */
+    pub previous: (i32, i32),
+    /* y 方向の速度 (上が正)。位置は整数のまま、毎ティック速度の整数部だけ動かす */
+    pub vy: Fixed,
+    /* x 方向の速度 (右が正) と、まだ x に足していない端数 */
+    pub vx: Fixed,
+    pub x_sub: Fixed,
+    pub state: PlayerState,
+    /* ジャンプ準備の残り */
+    pub squat: Timer,
+    /* 歩いて落ちてから、まだ地面からのジャンプを受け付ける残り (Tuning::coyote_ticks) */
+    pub coyote: Timer,
+    /* 空中で押した UP を着地まで覚えておく残り (Tuning::jump_buffer_ticks) */
+    pub jump_buffer: Timer,
+    /* 壁ジャンプで蹴り出している残り (Tuning::wall_kick_ticks) */
+    pub kick: Timer,
+    /* ダッシュの残りと、次のダッシュが出せるまでの残り (Tuning::dash_ticks, Tuning::dash_cooldown_ticks) */
+    pub dash: Timer,
+    pub dash_cooldown: Timer,
+    /* ダッシュを始めた状態 (Idle か Airborne)。ダッシュが終わるとここに戻り、空中なら落ちながら進む */
+    pub dash_from: PlayerState,
+    /* 落ちている間に DOWN を押しているか (急降下) */
+    pub fast_falling: bool,
+    /* 落ちている間に A を押しているか (滑空) */
+    pub gliding: bool,
+    /* 落ちている間に、接している壁の方へ押しているか (壁ずり) */
+    pub wall_sliding: bool,
+    /* 跳んでから着地するまでか。ヒップドロップは跳んだ後にしか出せない (歩いて落ちたときは急降下) */
+    pub jumped: bool,
+    /* 跳ねる地面で跳ね返って上っているところか。UP を離しても速度を落とさない */
+    pub bouncing: bool,
+    /* 地上で B を押しているか (走る)。空中では跳んだときのまま */
+    pub running: bool,
+    /* 重力の向き。逆さなら天井を歩く */
+    pub gravity: Gravity,
+    /* 水の中にいるか (water モジュール)。水の中では Tuning::WATER で動く */
+    pub medium: Medium,
+    /* ノックバックでキーが効かない残り (Tuning::knockback_ticks) */
+    pub knockback: Timer,
+    /* よじ登りの残り */
+    pub climb_up: Timer,
+    /* ヒップドロップで止まっている残り (Tuning::ground_pound_halt_ticks)。終わったら真下へ落ちる */
+    pub pound: Timer,
+    /* このティックに頭を (固いタイルや通り抜けられない足場に) ぶつけたか */
+    pub bonked: bool,
+    /* このティックに空中から地面や足場に降りたか */
+    pub landed: bool,
+    /* 歩きモーションのコマ送り (WALK_CHARS の添字) */
+    pub walk: SpriteAnim,
+    /* ダメージを受けた後の無敵時間 */
+    pub invulnerable: Cooldown,
+    /* 表示するキャラクタ */
+    pub ch: u16,
+    /* 左を向いているか */
+    pub hflip: bool,
+}
+
+encode_fields!(Droid {
+    x,
+    y,
+    previous,
+    vy,
+    vx,
+    x_sub,
+    state,
+    squat,
+    coyote,
+    jump_buffer,
+    kick,
+    dash,
+    dash_cooldown,
+    dash_from,
+    fast_falling,
+    gliding,
+    wall_sliding,
+    jumped,
+    bouncing,
+    running,
+    gravity,
+    medium,
+    knockback,
+    climb_up,
+    pound,
+    bonked,
+    landed,
+    walk,
+    invulnerable,
+    ch,
+    hflip,
+});
+
+impl Droid {
+    // 出発点に置く前の、止まって右を向いたドロイド君。位置は Game::load でレベルから置く。
+    pub fn new() -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            previous: (0, 0),
+            vy: Fixed::new(0),
+            vx: Fixed::new(0),
+            x_sub: Fixed::new(0),
+            state: PlayerState::Idle,
+            squat: Timer::new(),
+            coyote: Timer::new(),
+            jump_buffer: Timer::new(),
+            kick: Timer::new(),
+            dash: Timer::new(),
+            dash_cooldown: Timer::new(),
+            dash_from: PlayerState::Idle,
+            fast_falling: false,
+            gliding: false,
+            wall_sliding: false,
+            jumped: false,
+            bouncing: false,
+            running: false,
+            gravity: Gravity::Down,
+            medium: Medium::Air,
+            knockback: Timer::new(),
+            climb_up: Timer::new(),
+            pound: Timer::new(),
+            bonked: false,
+            landed: false,
+            walk: SpriteAnim::new(WALK_CHARS.len() as u8, Tuning::NORMAL.walk_frame_ticks),
+            invulnerable: Cooldown::new(INVULNERABLE_TICKS),
+            ch: 0,
+            hflip: false,
+        }
+    }
+
+    // 描くときの見た目。位置は drawn_at で、カメラは引いていない。
+    pub fn draw(&self, alpha: u32) -> DroidLook {
+        DroidLook {
+            position: self.drawn_at(alpha),
+            hflip: self.hflip,
+            vflip: self.gravity == Gravity::Up,
+            ch: self.pose(),
+            visible: self.is_visible(),
+        }
+    }
+
+    // 描く位置。前のティックの位置から今の位置へ、alpha (1/SPEED_NORMAL ティック単位) だけ寄せたところ
+    // (pacing::between)。
+    pub fn drawn_at(&self, alpha: u32) -> (i32, i32) {
+        pacing::between(self.previous, (self.x, self.y), alpha)
+    }
+
+    // 左右の速度を dir (-1, 0, 1) の向きの control.speed へ近づける。
+    // 押している向きへは accel 、動いているのと逆へ押せば decel 、離していれば friction ずつ。
+    #[inline]
+    pub fn accelerate(&mut self, dir: i32, control: &Control) {
+        let zero = Fixed::new(0);
+        let target = control.speed * dir;
+        let rate = if dir == 0 {
+            control.friction
+        } else if self.vx != zero && (self.vx < zero) != (dir < 0) {
+            control.decel
+        } else if control.speed < self.vx.abs() {
+            /* ダッシュの勢いは、押している間はゆっくり落とす */
+            control.friction
+        } else {
+            control.accel
+        };
+        self.vx = if self.vx < target {
+            (self.vx + rate).min(target)
+        } else {
+            (self.vx - rate).max(target)
+        };
+    }
+
+    // 左右の速度と、足元のベルトに流される分 drift で 1 ティックに進むドット数。端数は次のティックへ持ち越す。
+    #[inline]
+    pub fn advance(&mut self, drift: Fixed) -> i32 {
+        self.x_sub += self.vx + drift;
+        let dx = self.x_sub.trunc();
+        self.x_sub -= Fixed::new(dx);
+        dx
+    }
+
+    // 左右の動きを止める。
+    pub fn stop(&mut self) {
+        self.vx = Fixed::new(0);
+        self.x_sub = Fixed::new(0);
+    }
+
+    // 左右キーから移動方向を求め、向きも合わせて更新する。
+    pub fn walk_dir(&mut self, input: &Input) -> i32 {
+        let mut dir = 0;
+        if input.is_pressed(Button::LEFT) {
+            dir -= 1;
+            self.hflip = true;
+        }
+        if input.is_pressed(Button::RIGHT) {
+            dir += 1;
+            self.hflip = false;
+        }
+        dir
+    }
+
+    // 地上の左右の動き。走っていれば速く、氷の上では速さはそのままで加減速だけ鈍くなる。
+    pub fn ground_control(&self, tuning: &Tuning, material: Material) -> Control {
+        let control = if self.running {
+            tuning.run
+        } else {
+            tuning.ground
+        };
+        match material {
+            Material::Normal | Material::Bouncy => control,
+            Material::Ice => Control {
+                speed: control.speed,
+                ..tuning.ice
+            },
+        }
+    }
+
+    // 空中の左右の動き。走ったまま跳んでいれば、走る速さまで出せる。
+    pub fn air_control(&self, tuning: &Tuning) -> Control {
+        if self.running {
+            Control {
+                speed: tuning.run.speed,
+                ..tuning.air
+            }
+        } else {
+            tuning.air
+        }
+    }
+
+    // 今の状態で表示するキャラクタ (SpriteSet::droid の引数)。
+    pub fn pose(&self) -> u16 {
+        match self.state {
+            PlayerState::JumpSquat { .. } => 6,
+            PlayerState::Dash => 12,
+            PlayerState::Crouch | PlayerState::Slide => 14,
+            PlayerState::Airborne { .. } if self.fast_falling && self.vy < Fixed::new(0) => 16,
+            PlayerState::Airborne { .. } if self.wall_sliding && self.vy < Fixed::new(0) => 26,
+            PlayerState::Airborne { .. } if self.medium == Medium::Water => swim_pose(self.vy),
+            PlayerState::Airborne { .. } if self.gliding && self.vy < Fixed::new(0) => 24,
+            PlayerState::Airborne { .. } | PlayerState::Knockback if self.vy < Fixed::new(0) => 10,
+            PlayerState::Airborne { .. } | PlayerState::Knockback => 8,
+            PlayerState::LedgeHang => 18,
+            PlayerState::LedgeClimb => 20,
+            PlayerState::GroundPound => 22,
+            PlayerState::Climbing => climb_pose(self.y),
+            PlayerState::Idle => self.ch,
+        }
+    }
+
+    // 地面や足場に立っているか (ジャンプ準備中、しゃがみ、地上のダッシュも)。
+    pub fn is_grounded(&self) -> bool {
+        match self.state {
+            PlayerState::Idle
+            | PlayerState::JumpSquat { double: false }
+            | PlayerState::Crouch
+            | PlayerState::Slide => true,
+            PlayerState::Dash => self.dash_from == PlayerState::Idle,
+            _ => false,
+        }
+    }
+
+    // 1 ティック進める。地形や足場は around で受け取り、動いた結果の音やイベントは Game::update_droid が積む。
+    // 毎ティック通るので IWRAM に置く (memory モジュール参照)。状態ごとの update_* はここに展開させて一緒に置く。
+    #[link_section = ".iwram.droid_update"]
+    pub fn update(&mut self, input: &Input, around: &Surroundings) {
+        self.bonked = false;
+        self.landed = false;
+        let motion = Motion {
+            around,
+            input,
+            tuning: around.tuning.for_medium(self.medium),
+            x_start: self.x,
+            pushing: i32::from(input.is_pressed(Button::RIGHT))
+                - i32::from(input.is_pressed(Button::LEFT)),
+        };
+        self.dash_cooldown.tick();
+        match self.state {
+            PlayerState::Idle => self.update_idle(&motion),
+            PlayerState::Crouch => self.update_crouch(&motion),
+            PlayerState::Slide => self.update_slide(&motion),
+            PlayerState::JumpSquat { double } => self.update_jump_squat(&motion, double),
+            PlayerState::Airborne { double } => self.update_airborne(&motion, double),
+            PlayerState::Dash => self.update_dash(&motion),
+            PlayerState::Knockback => self.update_knockback(&motion),
+            PlayerState::LedgeHang => self.update_ledge_hang(&motion),
+            PlayerState::LedgeClimb => self.update_ledge_climb(&motion),
+            PlayerState::GroundPound => self.update_ground_pound(&motion),
+            /* はしごに掴まっている間は update_ladder が動かす */
+            PlayerState::Climbing => {}
+        }
+    }
+
+    // 待機中。
+    #[inline(always)]
+    fn update_idle(&mut self, motion: &Motion) {
+        let (input, tuning) = (motion.input, motion.tuning);
+        if self.try_dash(motion) || self.try_flip(motion) {
+            return;
+        }
+        if input.is_just_pressed(Button::UP) {
+            // ジャンプ開始
+            log_debug!("jump squat at ({}, {})", self.x, self.y);
+            self.enter(PlayerState::JumpSquat { double: false });
+        } else if input.is_pressed(Button::DOWN) {
+            if input.is_just_pressed(Button::DOWN) && tuning.ground.speed <= self.vx.abs() {
+                /* 走っているところでしゃがむとスライディング */
+                log_debug!("slide at ({}, {})", self.x, self.y);
+                let dir = if self.vx < Fixed::new(0) { -1 } else { 1 };
+                self.vx = tuning.slide_speed * dir;
+                self.enter(PlayerState::Slide);
+            } else {
+                self.enter(PlayerState::Crouch);
+            }
+            self.walk.rewind();
+            self.ch = 0;
+        } else {
+            let dir = self.walk_dir(input);
+            self.running = input.is_pressed(Button::B);
+            let control = self.ground_control(tuning, self.material(motion));
+            self.accelerate(dir, &control);
+            self.step(motion);
+            if input.is_just_pressed(Button::LEFT) || input.is_just_pressed(Button::RIGHT) {
+                self.walk.restart();
+            }
+            if input.is_just_released(Button::LEFT) || input.is_just_released(Button::RIGHT) {
+                self.ch = 0;
+            }
+            if input.is_pressed(Button::LEFT) || input.is_pressed(Button::RIGHT) {
+                /* 歩きモーション。走っているときは倍の速さでコマを送る */
+                self.walk.set_period(tuning.walk_frame_ticks);
+                if self.walk.advance(if self.running { 2 } else { 1 }) {
+                    self.ch = WALK_CHARS[usize::from(self.walk.frame())];
+                }
+            }
+            if self.walked_off(motion) {
+                self.walk_off(tuning.coyote_ticks);
+            }
+        }
+    }
+
+    // しゃがんでいる間は歩けず、動いていた分だけ止まっていく。
+    #[inline(always)]
+    fn update_crouch(&mut self, motion: &Motion) {
+        if self.try_flip(motion) {
+            return;
+        }
+        let tuning = motion.tuning;
+        let control = self.ground_control(tuning, self.material(motion));
+        self.accelerate(0, &control);
+        self.step(motion);
+        if self.walked_off(motion) {
+            self.walk_off(tuning.coyote_ticks);
+        } else if !motion.input.is_pressed(Button::DOWN) {
+            self.enter(PlayerState::Idle);
+        }
+    }
+
+    // スライディング中。左右キーは効かず、止まったらしゃがむか立つ。
+    #[inline(always)]
+    fn update_slide(&mut self, motion: &Motion) {
+        let (input, tuning) = (motion.input, motion.tuning);
+        if input.is_just_pressed(Button::UP) {
+            /* スライディングからはしゃがまずに跳び、滑っていた速さのまま飛んでいく */
+            log_debug!("slide jump at ({}, {})", self.x, self.y);
+            self.take_off(tuning.jump_speed, false);
+            return;
+        }
+        let slide = Control {
+            friction: tuning.slide_friction,
+            ..tuning.ground
+        };
+        self.accelerate(0, &slide);
+        self.step(motion);
+        if self.walked_off(motion) {
+            self.walk_off(tuning.coyote_ticks);
+        } else if self.vx == Fixed::new(0) {
+            self.enter(if input.is_pressed(Button::DOWN) {
+                PlayerState::Crouch
+            } else {
+                PlayerState::Idle
+            });
+        }
+    }
+
+    // ジャンプ準備。
+    #[inline(always)]
+    fn update_jump_squat(&mut self, motion: &Motion, double: bool) {
+        self.squat.tick();
+        if self.squat.just_finished() {
+            self.take_off(motion.tuning.jump_speed, double);
+        }
+    }
+
+    // ジャンプ中か落ちている。
+    #[inline(always)]
+    fn update_airborne(&mut self, motion: &Motion, double: bool) {
+        let (input, tuning, pushing) = (motion.input, motion.tuning, motion.pushing);
+        let walls = motion.around.walls;
+        if self.try_dash(motion) {
+            return;
+        }
+        if input.is_just_pressed(Button::UP)
+            && walls.is_some_and(|walls| walls.touches(self.x, pushing))
+        {
+            /* 壁ジャンプ。壁と反対へ蹴り出し、二段ジャンプはまた使える */
+            log_debug!("wall jump at ({}, {})", self.x, self.y);
+            self.take_off(tuning.jump_speed, false);
+            self.kick.start(tuning.wall_kick_ticks);
+            self.vx = Fixed::new(-pushing * tuning.wall_kick_speed);
+            self.x_sub = Fixed::new(0);
+            self.hflip = pushing > 0;
+            self.wall_sliding = false;
+        } else if !double && self.coyote.is_running() && input.is_just_pressed(Button::UP) {
+            /* 歩いて落ちた直後は、まだ地面から跳んだことにする (二段ジャンプは残る) */
+            log_debug!("coyote jump at ({}, {})", self.x, self.y);
+            self.enter(PlayerState::JumpSquat { double: false });
+        } else if !double && motion.around.double_jump && input.is_just_pressed(Button::UP) {
+            /* 二段ジャンプ */
+            log_debug!("double jump at ({}, {})", self.x, self.y);
+            self.enter(PlayerState::JumpSquat { double: true });
+        } else if self.jumped
+            && input.is_just_pressed(Button::DOWN)
+            && self.vy.abs() <= tuning.ground_pound_apex
+        {
+            self.ground_pound(tuning.ground_pound_halt_ticks);
+        } else {
+            self.coyote.tick();
+            self.jump_buffer.tick();
+            /* 二段ジャンプにならなかった UP は着地まで覚えておく */
+            if input.is_just_pressed(Button::UP) {
+                self.jump_buffer.start(tuning.jump_buffer_ticks);
+            }
+            if self.kick.is_running() {
+                /* 蹴り出している間は左右キーが効かず、蹴った速さのまま進む */
+                self.kick.tick();
+            } else {
+                let dir = self.walk_dir(input);
+                let air = self.air_control(tuning);
+                self.accelerate(dir, &air);
+            }
+            self.step(motion);
+            /*
+             * 落ちている間だけ、DOWN で急降下し、接している壁の方へ押していれば壁をずり落ち、
+             * A で滑空する (重なったらこの順で先のもの)
+             */
+            let falling = self.vy < Fixed::new(0);
+            self.fast_falling = falling && input.is_pressed(Button::DOWN);
+            self.wall_sliding = falling
+                && !self.fast_falling
+                && walls.is_some_and(|walls| walls.touches(self.x, pushing));
+            self.gliding =
+                falling && !self.fast_falling && !self.wall_sliding && input.is_pressed(Button::A);
+            let y_before = self.y;
+            self.fall(motion);
+            if matches!(self.state, PlayerState::Airborne { .. })
+                && self.gravity == Gravity::Down
+                && !self.bouncing
+            {
+                /* 着地しなかったら、向いている方の端を掴めるか (跳ね返って上っている間は掴まない) */
+                if let Some((x, y)) =
+                    motion
+                        .around
+                        .surfaces
+                        .resolve_grab(self.x, self.facing(), y_before, self.y)
+                {
+                    self.grab(x, y);
+                }
+            }
+        }
+    }
+
+    // ダッシュ中。左右キーは効かない。
+    #[inline(always)]
+    fn update_dash(&mut self, motion: &Motion) {
+        let (input, tuning) = (motion.input, motion.tuning);
+        if self.dash_from == PlayerState::Idle && input.is_just_pressed(Button::UP) {
+            /* 地上のダッシュからはしゃがまずに跳び、ダッシュの速さのまま飛んでいく */
+            log_debug!("dash jump at ({}, {})", self.x, self.y);
+            self.take_off(tuning.jump_speed, false);
+            return;
+        }
+        self.dash.tick();
+        self.step(motion);
+        if self.dash_from == PlayerState::Idle && self.walked_off(motion) {
+            /* ダッシュのまま足場から出たら、そこからは落ちながら進む */
+            self.vy = Fixed::new(0);
+            self.dash_from = PlayerState::Airborne { double: false };
+        } else if self.dash_from != PlayerState::Idle {
+            self.fall(motion);
+        }
+        if self.state != PlayerState::Dash {
+            /* 着地したらダッシュはそこで終わる */
+            log_debug!("dash cancelled at ({}, {})", self.x, self.y);
+        } else if self.dash.just_finished() {
+            self.enter(self.dash_from);
+        }
+        if matches!(
+            self.state,
+            PlayerState::Idle | PlayerState::JumpSquat { double: false }
+        ) {
+            /* 地上では勢いを残さず、歩く (B を押したままなら走る) 速さから滑り出す */
+            self.running = input.is_pressed(Button::B);
+            let speed = self.ground_control(tuning, Material::Normal).speed;
+            self.vx = self.vx.clamp(-speed, speed);
+        }
+        self.walk.rewind();
+        self.ch = 0;
+    }
+
+    // ノックバック中。左右キーは効かず、飛ばされた速さのまま進む。
+    #[inline(always)]
+    fn update_knockback(&mut self, motion: &Motion) {
+        self.knockback.tick();
+        self.step(motion);
+        self.fall(motion);
+        if self.state == PlayerState::Knockback && self.knockback.just_finished() {
+            /* 空中でキーが効くようになったら、そこからは普通に落ちる (二段ジャンプは使えない) */
+            self.enter(PlayerState::Airborne { double: true });
+        }
+    }
+
+    // ぶら下がり中。UP でよじ登り、DOWN で手を離して落ちる (同じ端はもう掴まない)。
+    #[inline(always)]
+    fn update_ledge_hang(&mut self, motion: &Motion) {
+        let input = motion.input;
+        if input.is_just_pressed(Button::UP) {
+            log_debug!("climbing up at ({}, {})", self.x, self.y);
+            self.enter(PlayerState::LedgeClimb);
+            self.climb_up.start(LEDGE_CLIMB_TICKS);
+        } else if !self.holds_ledge(motion) {
+            /* 掴んでいた端が無くなったら落ちる */
+            self.enter(PlayerState::Airborne { double: false });
+        } else if input.is_just_pressed(Button::DOWN) {
+            log_debug!("let go at ({}, {})", self.x, self.y);
+            self.enter(PlayerState::Airborne { double: false });
+        }
+    }
+
+    // よじ登り中。掴んでいた端が無くなったら落ちる。
+    #[inline(always)]
+    fn update_ledge_climb(&mut self, motion: &Motion) {
+        if !self.holds_ledge(motion) {
+            self.enter(PlayerState::Airborne { double: false });
+            return;
+        }
+        self.climb_up.tick();
+        if self.climb_up.just_finished() {
+            /* 端から少し内側に立つ */
+            self.x += self.facing() * LEDGE_REACH;
+            self.y -= LEDGE_HANG;
+            self.enter(PlayerState::Idle);
+        }
+    }
+
+    // ヒップドロップ中。止まっている間は動かず、それからは左右キーも効かずに真下へ落ちる。
+    #[inline(always)]
+    fn update_ground_pound(&mut self, motion: &Motion) {
+        self.pound.tick();
+        if !self.pound.is_running() {
+            self.vy = -motion.tuning.ground_pound_speed;
+            self.fall(motion);
+        }
+    }
+
+    // 地上か空中で B を押したらダッシュする。押している向き (押していなければ向いている向き) へ一定時間進む。
+    #[inline(always)]
+    fn try_dash(&mut self, motion: &Motion) -> bool {
+        let (input, tuning) = (motion.input, motion.tuning);
+        if !input.is_just_pressed(Button::B) || self.dash_cooldown.is_running() {
+            return false;
+        }
+        let dir = self.walk_dir(input);
+        let dir = if dir != 0 { dir } else { self.facing() };
+        log_debug!("dash at ({}, {})", self.x, self.y);
+        self.dash_from = self.state;
+        self.enter(PlayerState::Dash);
+        self.dash.start(tuning.dash_ticks);
+        self.dash_cooldown.start(tuning.dash_cooldown_ticks);
+        self.vx = tuning.dash_speed * dir;
+        true
+    }
+
+    // 立っているかしゃがんでいるときに SELECT を押したら、重力を反転し、反対側 (天井か地面) へ落ちていく。
+    #[inline(always)]
+    fn try_flip(&mut self, motion: &Motion) -> bool {
+        if !motion.around.can_flip || !motion.input.is_just_pressed(Button::SELECT) {
+            return false;
+        }
+        self.flip_gravity();
+        true
+    }
+
+    // 掴んでいる端がまだあるか。
+    fn holds_ledge(&self, motion: &Motion) -> bool {
+        motion.around.surfaces.holds(self.x, self.y, self.facing())
+    }
+
+    // 今の x で立つ y 座標と、頭がぶつかる y 座標。地面の高さは坂では列の中でも変わる。
+    // 重力が逆さなら天井に立ち、地面に頭をぶつける。
+    #[inline]
+    fn floor(&self, motion: &Motion) -> i32 {
+        match self.gravity {
+            Gravity::Down => motion.around.map.floor_y(self.x, self.y),
+            Gravity::Up => motion.around.ceiling,
+        }
+    }
+
+    #[inline]
+    fn head(&self, motion: &Motion) -> i32 {
+        match self.gravity {
+            Gravity::Down => motion.around.ceiling,
+            Gravity::Up => motion.around.map.floor_y(self.x, self.y),
+        }
+    }
+
+    // 足元の材質。逆さのときや、りんごや足場の上は普通の地面。
+    fn material(&self, motion: &Motion) -> Material {
+        if self.gravity == Gravity::Down && self.y == self.floor(motion) {
+            terrain::material_at(motion.around.materials, self.x)
+        } else {
+            Material::Normal
+        }
+    }
+
+    // 立っている足場から外れたか (逆さのときは天井にしか立てない)。
+    fn walked_off(&self, motion: &Motion) -> bool {
+        self.y != self.floor(motion)
+            && (self.gravity == Gravity::Up || !motion.around.surfaces.supports(self.x, self.y))
+    }
+
+    // 左右の速度で動かす。地面に立っていれば足元のベルトコンベアにも流される (逆さのときは流されない)。
+    // 壁や固いタイルにぶつかったら止まる。地面を歩いていれば坂に沿って上り下りする。
+    fn step(&mut self, motion: &Motion) {
+        let on_floor = self.is_grounded() && self.y == self.floor(motion);
+        let x_before = self.x;
+        let drift = if on_floor && self.gravity == Gravity::Down {
+            conveyor::drift_at(motion.around.conveyors, self.x)
+        } else {
+            Fixed::new(0)
+        };
+        let dx = self.advance(drift);
+        match motion.around.walls {
+            Some(walls) => {
+                let x = walls.clamp(self.x + dx);
+                if x != self.x + dx {
+                    self.stop();
+                }
+                self.x = x;
+            }
+            None => self.x = move_x(self.x, dx),
+        }
+        let x = motion
+            .around
+            .map
+            .wall_x(x_before, self.x, self.y, self.gravity);
+        if x != self.x {
+            self.x = x;
+            self.stop();
+        }
+        if on_floor {
+            self.y = self.floor(motion);
+        }
+    }
+
+    // 空中の縦の動き。着地したら Idle (先行入力があればジャンプ準備) にする。
+    fn fall(&mut self, motion: &Motion) {
+        let (input, tuning, surfaces) = (motion.input, motion.tuning, &motion.around.surfaces);
+        if self.state != PlayerState::Knockback
+            && !self.bouncing
+            && tuning.release_speed < self.vy
+            && !input.is_pressed(Button::UP)
+        {
+            /* 上っている途中で離したら、そこからは一番低いジャンプと同じだけ上る */
+            self.vy = tuning.release_speed;
+        }
+        if self.vy <= Fixed::new(0) {
+            self.bouncing = false;
+        }
+        let y_before = self.y;
+        /* 0 に向かって切り捨てる (f32 の as i32 と同じ) */
+        self.y += self.gravity.rise(self.vy.trunc());
+        if self.gravity.rises_past(self.y, self.head(motion)) {
+            self.y = self.head(motion);
+            self.vy = Fixed::new(0);
+        }
+        if self.gravity == Gravity::Down {
+            /* 近い方 (y の大きい方) にぶつかる */
+            let bump = if Fixed::new(0) < self.vy {
+                surfaces
+                    .resolve_bump(self.x, y_before, self.y)
+                    .max(motion.around.map.ceiling_y(self.x, y_before, self.y))
+            } else {
+                None
+            };
+            if let Some(y) = bump {
+                /* 固いタイルや通り抜けられない足場に下から頭をぶつけた */
+                self.y = y;
+                self.vy = Fixed::new(0);
+                self.bonked = true;
+                log_debug!("bonked at ({}, {})", self.x, self.y);
+            }
+            /*
+             * 横に動いた分も含めた線分で見て、斜めに角をかすめても通り抜けない。
+             * 画面端で回り込んだティックは今の x だけで見る
+             */
+            let x_before = if (self.x - motion.x_start).abs() < DROID_WIDTH {
+                motion.x_start
+            } else {
+                self.x
+            };
+            if let Some((x, y)) =
+                surfaces.resolve_landing((x_before, y_before), (self.x, self.y), self.vy)
+            {
+                /* りんごや足場に乗る (斜めに端をかすめたときは端に寄る) */
+                self.x = x;
+                self.y = y;
+                self.enter(PlayerState::Idle);
+                self.landed = true;
+                log_debug!("landed on a platform at ({}, {})", self.x, self.y);
+            }
+        }
+        let floor_y = self.floor(motion);
+        if self.gravity.sinks_past(self.y, floor_y) {
+            self.y = floor_y;
+            if matches!(self.state, PlayerState::Airborne { .. })
+                && self.material(motion) == Material::Bouncy
+                && tuning.min_bounce_speed <= -self.vy
+            {
+                /*
+                 * 跳ねる地面では、落ちてきた速さの一部で跳ね返る。UP を押していれば高く跳ね、
+                 * 二段ジャンプもまた使える
+                 */
+                let bounce = if input.is_pressed(Button::UP) {
+                    tuning.held_bounce
+                } else {
+                    tuning.bounce
+                };
+                self.vy = -self.vy * bounce;
+                self.enter(PlayerState::Airborne { double: false });
+                self.bouncing = true;
+                log_debug!("bounced at ({}, {})", self.x, self.y);
+            } else {
+                /* 着地 */
+                self.enter(PlayerState::Idle);
+                self.landed = true;
+                log_debug!("landed at ({}, {})", self.x, self.y);
+            }
+        }
+        if self.state == PlayerState::Idle && self.jump_buffer.is_running() {
+            /* 着地の少し前に押していれば、そのまま跳ぶ */
+            log_debug!("buffered jump at ({}, {})", self.x, self.y);
+            self.jump_buffer.start(0);
+            self.enter(PlayerState::JumpSquat { double: false });
+        }
+        let (gravity, max_fall_speed) = if self.fast_falling {
+            (tuning.fast_fall_gravity, tuning.max_fast_fall_speed)
+        } else if self.wall_sliding {
+            (tuning.gravity, tuning.max_wall_slide_speed)
+        } else if self.gliding {
+            (tuning.gravity, tuning.max_glide_speed)
+        } else {
+            (tuning.gravity, tuning.max_fall_speed)
+        };
+        self.vy = (self.vy - gravity).max(-max_fall_speed);
+    }
+
+    // state に移る。今の状態を抜ける後始末 (exit) をしてから入る。同じ種類の状態のまま
+    // (二段ジャンプの後や壁ジャンプ、跳ね返り) なら抜けない。ジャンプ準備に入ると準備の数えを始める。
+    // 着地するか、空中の動きを遮るもの (端を掴む、ヒップドロップ、ノックバック、はしご) に入ると、跳んだ印を消す。
+    // 遮るものに入るときは、歩いて落ちた分の猶予と先行入力、歩きのコマも捨てる。
+    // update (IWRAM) を膨らませないよう、インライン展開させずに ROM に置いておく。
+    #[inline(never)]
+    pub fn enter(&mut self, state: PlayerState) {
+        if mem::discriminant(&self.state) != mem::discriminant(&state) {
+            self.exit(state);
+        }
+        self.state = state;
+        match state {
+            PlayerState::Idle => self.jumped = false,
+            PlayerState::JumpSquat { .. } => {
+                self.squat.start(JUMP_SQUAT_TICKS);
+                self.ch = 0;
+            }
+            PlayerState::LedgeHang
+            | PlayerState::GroundPound
+            | PlayerState::Knockback
+            | PlayerState::Climbing => {
+                self.jumped = false;
+                self.coyote.start(0);
+                self.jump_buffer.start(0);
+                self.bouncing = false;
+                self.walk.rewind();
+                self.ch = 0;
+            }
+            _ => {}
+        }
+    }
+
+    // 今の状態から next へ移る前に、今の状態の間だけ使っていたものを片付ける。
+    fn exit(&mut self, next: PlayerState) {
+        match self.state {
+            /* 蹴り出しと急降下、滑空、壁ずりは空中を抜けると終わる (二段ジャンプの準備はまだ空中) */
+            PlayerState::Airborne { .. } if next != (PlayerState::JumpSquat { double: true }) => {
+                self.kick.start(0);
+                self.fast_falling = false;
+                self.gliding = false;
+                self.wall_sliding = false;
+            }
+            PlayerState::JumpSquat { .. } => self.squat.start(0),
+            PlayerState::Dash => self.dash.start(0),
+            PlayerState::Knockback => self.knockback.start(0),
+            PlayerState::GroundPound => self.pound.start(0),
+            _ => {}
+        }
+    }
+
+    // speed で跳び上がる。double なら二段ジャンプで、もう二段ジャンプはできない。
+    // 跳んだら、歩いて落ちた分の猶予はもう使えない。enter と同じく ROM に置いておく。
+    #[inline(never)]
+    pub fn take_off(&mut self, speed: Fixed, double: bool) {
+        self.vy = speed;
+        self.enter(PlayerState::Airborne { double });
+        self.coyote.start(0);
+        self.bouncing = false;
+        self.jumped = true;
+    }
+
+    // 重力を反転する。立っていたところから、跳ぶ速さ 0 で反対側へ落ち始める。
+    pub fn flip_gravity(&mut self) {
+        log_debug!("gravity flipped at ({}, {})", self.x, self.y);
+        self.gravity = self.gravity.flipped();
+        self.vy = Fixed::new(0);
+        self.enter(PlayerState::Airborne { double: false });
+        self.coyote.start(0);
+        self.walk.rewind();
+        self.ch = 0;
+    }
+
+    // 足場から歩いて落ちる。しばらくは地面からのジャンプも受け付ける (コヨーテタイム)。
+    pub fn walk_off(&mut self, coyote_ticks: u16) {
+        log_debug!("walked off at ({}, {})", self.x, self.y);
+        self.vy = Fixed::new(0);
+        self.enter(PlayerState::Airborne { double: false });
+        self.walk.rewind();
+        self.coyote.start(coyote_ticks);
+    }
+
+    // 向いている向き (-1, 1)。
+    pub fn facing(&self) -> i32 {
+        if self.hflip {
+            -1
+        } else {
+            1
+        }
+    }
+
+    // 足場の端を掴んで (x, y) にぶら下がる。
+    pub fn grab(&mut self, x: i32, y: i32) {
+        log_debug!("grabbed a ledge at ({}, {})", x, y);
+        (self.x, self.y) = (x, y);
+        self.vx = Fixed::new(0);
+        self.x_sub = Fixed::new(0);
+        self.vy = Fixed::new(0);
+        self.enter(PlayerState::LedgeHang);
+    }
+
+    // ジャンプの頂上でヒップドロップを始める。halt_ticks の間はその場で止まる。
+    pub fn ground_pound(&mut self, halt_ticks: u16) {
+        log_debug!("ground pound from ({}, {})", self.x, self.y);
+        self.vx = Fixed::new(0);
+        self.x_sub = Fixed::new(0);
+        self.vy = Fixed::new(0);
+        self.enter(PlayerState::GroundPound);
+        self.pound.start(halt_ticks);
+    }
+
+    // ダメージを受けて、向いているのと反対へ飛ばされる。していた動き (ダッシュ、先行入力等) は全て止める。
+    pub fn knock_back(&mut self, tuning: &Tuning) {
+        log_debug!("knocked back at ({}, {})", self.x, self.y);
+        let dir = -self.facing();
+        self.vx = tuning.knockback_speed * dir;
+        self.x_sub = Fixed::new(0);
+        self.vy = tuning.knockback_lift;
+        self.enter(PlayerState::Knockback);
+        self.knockback.start(tuning.knockback_ticks);
+        self.running = false;
+    }
+
+    // 無敵時間中は 4 ティックごとに点滅させる。
+    pub fn is_visible(&self) -> bool {
+        self.invulnerable.remaining() / 4 % 2 == 0
+    }
+}
+
+impl Collider for Droid {
+    // 当たり判定の箱。今のポーズの絵の箱 (しゃがんでいる間とスライディング中は足元の半分) を、
+    // 向きと重力に合わせて反転して置く。
+    fn hitbox(&self) -> Aabb {
+        let art = resources::droid_hitbox(self.pose());
+        let x = if self.hflip {
+            DROID_WIDTH - art.right()
+        } else {
+            art.x
+        };
+        let y = match self.gravity {
+            Gravity::Down => art.y,
+            Gravity::Up => DROID_WIDTH - art.bottom(),
+        };
+        Aabb::new(self.x + x, self.y + y, art.width, art.height)
+    }
+
+    fn filter(&self) -> Filter {
+        DROID_FILTER
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{difficulty::Difficulty, game::Game, pacing::SPEED_NORMAL};
+
+    // 当たり判定の箱は、左を向けば左右に、重力が逆さなら上下に反転する。
+    #[test_case]
+    fn the_hitbox_follows_facing_and_gravity(_gba: &mut agb::Gba) {
+        let mut droid = Droid::new();
+        (droid.x, droid.y) = (40, 80);
+        let right = droid.hitbox();
+        droid.hflip = true;
+        let left = droid.hitbox();
+        assert_eq!(left.y, right.y);
+        assert_eq!(left.x - 40, 40 + DROID_WIDTH - right.right());
+        droid.gravity = Gravity::Up;
+        let flipped = droid.hitbox();
+        assert_eq!(flipped.x, left.x);
+        assert_eq!(flipped.y - 80, 80 + DROID_WIDTH - left.bottom());
+    }
+
+    // ノックバックは向いているのと反対へ飛ばし、していた動きを止める。
+    #[test_case]
+    fn a_knock_back_pushes_away_from_the_facing(_gba: &mut agb::Gba) {
+        let tuning = Tuning::NORMAL;
+        let mut droid = Droid::new();
+        droid.state = PlayerState::Dash;
+        droid.dash.start(tuning.dash_ticks);
+        droid.running = true;
+        droid.knock_back(&tuning);
+        assert_eq!(droid.state, PlayerState::Knockback);
+        assert_eq!(droid.vx, -tuning.knockback_speed);
+        assert_eq!(droid.vy, tuning.knockback_lift);
+        assert!(!droid.dash.is_running() && !droid.running);
+        droid.hflip = true;
+        droid.knock_back(&tuning);
+        assert_eq!(droid.vx, tuning.knockback_speed);
+    }
+
+    // 床に降りたティックも、どの alpha でも床の下には描かない。
+    #[test_case]
+    fn a_landing_is_never_drawn_below_the_floor(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        let floor = game.droid.y;
+        game.droid.y -= 40;
+        game.droid.enter(PlayerState::Airborne { double: false });
+        let mut landed = false;
+        for _ in 0..60 {
+            game.update(&Input::default());
+            landed |= game.droid.landed;
+            for alpha in (0..SPEED_NORMAL).step_by(8) {
+                let (_, y) = game.droid.drawn_at(alpha);
+                assert!(
+                    y <= floor,
+                    "drawn at {} below {} (alpha = {})",
+                    y,
+                    floor,
+                    alpha
+                );
+            }
+        }
+        assert!(landed);
+        assert_eq!(game.droid.y, floor);
+    }
+}
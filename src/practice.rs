@@ -0,0 +1,203 @@
+// 練習モード。
+//
+// レベルを一度クリアするとタイトル画面で選べるようになる。普通のモードとの違い:
+// - L でその場の状態を覚え、R でいつでもそこへ戻る。
+//   覚えるのは Game のロジック側の状態全て (snapshot モジュール) で、ドロイド君のタイマー (ジャンプ準備や無敵時間の途中)
+//   も足場や乱数も含めて丸ごと写すので、どの瞬間に覚えても同じ続きになる。練習モード自身の状態 (覚えたものと区間タイム) は戻さない。
+//   絵は presenter が Game と見比べて描き直すので、戻したことを知らなくて良い。
+// - ダメージを受けても体力は減らず、コインを全て取っても終わらない (ポーズから抜ける) 。
+// - HUD には戻ってからの区間タイムと「PRACTICE」の透かしを出す。
+// - 得点もタイムも記録には残さない。
+
+use agb::input::Button;
+
+use crate::{
+    game::Game,
+    input::Input,
+    snapshot::{encode_fields, Snapshot},
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Practice {
+    /* L で覚えた状態。練習モード自身 (Game::practice) は抜いて写す */
+    saved: Option<Snapshot>,
+    /* 最後に戻った (か始めた) ときの Game::time */
+    segment_start: u32,
+}
+
+encode_fields!(Practice {
+    saved,
+    segment_start
+});
+
+impl Practice {
+    pub fn new() -> Self {
+        Self {
+            saved: None,
+            segment_start: 0,
+        }
+    }
+
+    // 戻ってからのティック数。
+    pub fn segment(&self, time: u32) -> u32 {
+        time - self.segment_start
+    }
+}
+
+impl Game {
+    // 練習モードの 1 ティック分。ドロイド君を動かす前に呼ぶ。
+    pub fn update_practice(&mut self, input: &Input) {
+        if !input.is_just_pressed(Button::L) && !input.is_just_pressed(Button::R) {
+            return;
+        }
+        /* 覚えたものの中に前に覚えたものが入れ子にならないよう、練習モードを抜いてから写す */
+        let Some(mut practice) = self.practice.take() else {
+            return;
+        };
+        if input.is_just_pressed(Button::L) {
+            log_info!("practice save at ({}, {})", self.droid.x, self.droid.y);
+            practice.saved = Some(Snapshot::take(self));
+        }
+        if input.is_just_pressed(Button::R) {
+            if let Some(saved) = &practice.saved {
+                saved.restore(self);
+                log_info!("practice restore to ({}, {})", self.droid.x, self.droid.y);
+                practice.segment_start = self.time;
+            }
+        }
+        self.practice = Some(practice);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+
+    use super::*;
+    use crate::{
+        arena::Arena,
+        collision::{Passage, Surface, LEDGE_HANG},
+        difficulty::Difficulty,
+        events::Event,
+        mode::Mode,
+        player::PlayerState,
+        spawn::EntityKind,
+    };
+
+    fn press(button: Button) -> Input {
+        Input::new(button, Button::empty())
+    }
+
+    // ジャンプ準備と無敵時間の途中で覚えても、戻ったら足場や乱数も含めて同じ続きになる。
+    #[test_case]
+    fn a_restore_continues_exactly_where_the_save_was(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1).with_mode(Mode::Practice);
+        game.push_event(Event::PlayerDamaged {
+            amount: 1,
+            from_x: 0,
+        });
+        /* ノックバックから着地してから跳ぶ */
+        game.update(&Input::default());
+        while game.droid.state != PlayerState::Idle {
+            game.update(&Input::default());
+        }
+        game.update(&press(Button::UP));
+        assert_eq!(game.droid.state, PlayerState::JumpSquat { double: false });
+        assert!(!game.droid.invulnerable.is_ready());
+        /* 練習ではダメージを受けても体力は減らない */
+        assert_eq!(game.health, Difficulty::Normal.settings().lives);
+        game.update(&press(Button::L));
+        let mut expected = game.clone();
+        for _ in 0..30 {
+            expected.update(&Input::default());
+        }
+
+        for _ in 0..50 {
+            game.update(&press(Button::RIGHT));
+        }
+        game.update(&press(Button::R));
+        assert_eq!(game.practice.as_ref().unwrap().segment(game.time), 0);
+        for _ in 0..30 {
+            game.update(&Input::default());
+        }
+        assert_eq!(game.droid, expected.droid);
+        assert_eq!(game.coins, expected.coins);
+        assert_eq!(game.moving_platforms, expected.moving_platforms);
+        assert_eq!(game.rng.state(), expected.rng.state());
+        assert_eq!(game.time, expected.time);
+    }
+
+    // 右へ動く足場に乗せるか (hanging なら左の端にぶら下げた) 練習モードの Game 。
+    // 2 回作るので、テストのスタックに Game が並ばないよう分けてヒープに置く。
+    #[inline(never)]
+    fn on_a_platform(hanging: bool) -> Box<Game> {
+        let mut game = Box::new(Game::new(Difficulty::Normal, 1).with_mode(Mode::Practice));
+        game.moving_platforms = Arena::new();
+        game.spawn(EntityKind::MovingPlatform {
+            to: (104, 80),
+            passage: Passage::OneWay,
+        })
+        .at((40, 80))
+        .build()
+        .unwrap();
+        let (_, platform) = game.moving_platforms.iter().next().unwrap();
+        let (x, y) = if hanging {
+            (
+                *platform.x_range().start() - 1,
+                platform.top_y() + LEDGE_HANG,
+            )
+        } else {
+            (platform.x, platform.top_y())
+        };
+        (game.droid.x, game.droid.y) = (x, y);
+        game.droid.hflip = false;
+        if hanging {
+            game.droid.enter(PlayerState::LedgeHang);
+        }
+        game
+    }
+
+    // 動く足場に乗っていても端にぶら下がっていても、戻ると足場も覚えた所に戻り、そのまま運ばれ続ける。
+    #[test_case]
+    fn a_restore_on_a_moving_platform_keeps_riding_it(_gba: &mut agb::Gba) {
+        for (hanging, state) in [(false, PlayerState::Idle), (true, PlayerState::LedgeHang)] {
+            let mut game = on_a_platform(hanging);
+            for _ in 0..20 {
+                game.update(&Input::default());
+            }
+            assert_eq!(game.droid.state, state);
+            game.update(&press(Button::L));
+            let mut expected = game.clone();
+            for _ in 0..30 {
+                expected.update(&Input::default());
+            }
+
+            /* 跳ぶか手を離して、足場に置いていかれてから戻る */
+            game.update(&press(if hanging { Button::DOWN } else { Button::UP }));
+            for _ in 0..60 {
+                game.update(&Input::default());
+            }
+            game.update(&press(Button::R));
+            for _ in 0..30 {
+                game.update(&Input::default());
+                assert_eq!(game.droid.state, state, "hanging = {hanging}");
+            }
+            assert_eq!(game.droid, expected.droid);
+            assert_eq!(game.moving_platforms, expected.moving_platforms);
+        }
+    }
+
+    // 覚えたときに残っていたコインは戻ると復活し、コインを全て取っても終わらない。
+    #[test_case]
+    fn coins_come_back_and_practice_never_ends(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1).with_mode(Mode::Practice);
+        game.update(&press(Button::L));
+        game.coins.retain(|_| false);
+        game.score = 30;
+        game.update(&Input::default());
+        assert_eq!(game.outcome(), None);
+        game.update(&press(Button::R));
+        assert_eq!(game.coins.len(), 3);
+        assert_eq!(game.score, 0);
+    }
+}
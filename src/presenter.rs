@@ -0,0 +1,1555 @@
+// Game の状態をスプライトと背景に反映する。
+//
+// ゲームロジックの結果を読むだけで、状態は一切書き換えない。
+// 前回描いた値を覚えておき、変わったものだけ OAM に書き込む。
+// 何も変わっていないフレームは OAM も背景も commit しない。
+// 得点・体力 (タイムアタックではタイムとカウントダウン) は HUD 用の文字レイヤーに描く。
+// チュートリアルでは待っている仕掛けの案内を HUD に、閉じている関門を背景に描く。
+// コインとエンドレスの足場は背景に描き、カメラに合わせて背景ごとスクロールする。
+// 地面のベルトコンベアは、表面のタイルをコマが変わった列だけ書き直して動かす。
+// 壊せるブロックは背景に描いておき、壊れたら消す。破片はスプライトで描く。
+// スイッチのブロックは、固い組が入れ替わったら全て描き直す。スイッチも背景に描き、踏まれたら描き直す。
+// はしごと水、テレポーターの台は始めに背景に描いておく。テレポーターで移ったら空の色を白く光らせて戻していく。風の粒も背景に描き、風の向きへタイルを移していく。鍵と扉も背景に描き、拾われたり開いたりしたら消す。持っている鍵の数は HUD に出す。
+// 背景のマップは 32 行で回り込むので、画面の下へ出た行を片付けて上の行に使い直す。
+
+use agb::display::{object::Sprite, tiled::VRamManager, HEIGHT};
+
+use crate::{
+    climb::{Climb, PlatformKind, MAX_PLATFORMS},
+    coin_rush::LOW_TIME_TICKS,
+    conveyor,
+    crumbling::MAX_CRUMBLING_PLATFORMS,
+    debris::MAX_DEBRIS,
+    door::{Door, MAX_DOORS, MAX_KEYS},
+    entity::{self, Look},
+    font,
+    game::{Game, MAX_APPLES, MAX_COINS},
+    gameplay::{
+        BLOCK, CONVEYOR_TILES, DOOR_TILES, GROUND_LEFT, GROUND_RIGHT, GROUND_ROW, GROUND_TOP, KEY,
+        LADDER, SKY, SPIKE, STREAK_TILES, SWITCH_BLOCK_TILES, SWITCH_TILES, TELEPORTER_TILES,
+        WATER_TILES,
+    },
+    gfx::{rgb5, tiles},
+    layer::{Layer, Tile},
+    level::GROUND_COLUMNS,
+    math::Fixed,
+    mode::Mode,
+    moving_platform::MAX_MOVING_PLATFORMS,
+    oam::{Category, ObjectPool, Pooled},
+    player::DroidLook,
+    resources::{Preloaded, SpriteSet},
+    spawn::ObjectSpawn,
+    speedrun::Split,
+    split_delta,
+    spring::MAX_SPRINGS,
+    switch::{BlockSet, MAX_SWITCHES, SWITCH_SIZE},
+    teleporter::WARP_TICKS,
+    text::{self, TextLayer},
+    time_attack::TimeDisplay,
+    wind::{MAX_WINDS, STREAKS},
+};
+
+/* HUD に表示中の内容。前回と同じなら書き込まない */
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Status {
+    /* player は交代で遊ぶときだけ。難易度の代わりに P1, P2 を出す */
+    Score {
+        score: u32,
+        health: u8,
+        player: Option<u8>,
+    },
+    Time {
+        time: u32,
+        banner: &'static str,
+    },
+    Climb {
+        height: u32,
+        score: u32,
+    },
+    Practice {
+        segment: u32,
+    },
+    /* hint は待っている仕掛けの案内 (全て終えたら空) */
+    Tutorial {
+        hint: &'static str,
+    },
+    /* warning は残り時間を赤で書いているか (点滅) */
+    Rush {
+        remaining: u32,
+        score: u32,
+        health: u8,
+        banner: &'static str,
+        warning: bool,
+    },
+}
+
+/* 足場の種類と左上。前回と同じなら書き込まない */
+type PlatformLook = (PlatformKind, i32, i32);
+
+/* 雲のタイルの色 (背景のパレットバンクと色番号) */
+const CLOUD_PALETTE: u8 = 14;
+const CLOUD_COLOUR: usize = 2;
+
+/* 持っている鍵の数を出す行 (得点の下) */
+const KEY_ROW: u16 = 1;
+/* 練習モードの透かしと操作の案内を出す行 */
+const WATERMARK_ROW: u16 = 2;
+/* チュートリアルの案内と、飛ばし方を出す行 */
+const HINT_ROW: u16 = 2;
+const SKIP_ROW: u16 = 3;
+/* トリガーゾーンの案内を出す行 (他の案内や区切りと重ならない行) */
+const ZONE_HINT_ROW: u16 = 4;
+/* ゴーストとの差とスピードランの区切りを出す行と、区切りの差を出す桁 */
+const SPLIT_ROW: u16 = 2;
+const DELTA_COLUMN: u16 = 23;
+/* 関門を描く行 (HUD の案内の下から地面の上まで) */
+const GATE_ROWS: core::ops::Range<u16> = SKIP_ROW + 1..17;
+const GATE_HEIGHT: usize = (GATE_ROWS.end - GATE_ROWS.start) as usize;
+/* 一番幅の広い足場 (雲) の列数 */
+const PLATFORM_COLUMNS: usize = 4;
+/* カウントダウンを出す行 */
+const BANNER_ROW: u16 = 8;
+/* コインラッシュの残り時間の桁と、残りが少ないときの点滅の間隔 */
+const RUSH_TIME_COLUMN: u16 = 6;
+const RUSH_FLASH_TICKS: u32 = 15;
+/* カウントダウンの後に「GO!」を出しておくティック数 */
+const GO_TICKS: u32 = 30;
+
+// ゴースト (通信対戦の相手、タイムアタックの最高記録) は半透明にする。
+// agb のオブジェクトには半透明の指定が無いので、OAM を commit した後で属性 0 を直接書き換える。
+// ゴーストは一番手前 (GHOST_Z) にしておき、commit で必ず OAM の 0 番に入るようにする。
+const GHOST_Z: i32 = -1;
+const OAM: *mut u16 = 0x0700_0000 as *mut u16;
+const BLDCNT: *mut u16 = 0x0400_0050 as *mut u16;
+const BLDALPHA: *mut u16 = 0x0400_0052 as *mut u16;
+/* 属性 0 の 10-11 ビット (0b01 で半透明) */
+const OBJ_MODE_MASK: u16 = 0b11 << 10;
+const OBJ_MODE_SEMI_TRANSPARENT: u16 = 0b01 << 10;
+/* 重ねる先は全ての BG と背景色、重みは半分ずつ */
+const BLEND_SECOND_TARGETS: u16 = 0b10_1111 << 8;
+const BLEND_HALF: u16 = 8 | 8 << 8;
+
+pub struct Presenter<'a> {
+    sprites: &'a SpriteSet,
+    bg: Layer<'a>,
+    hud: TextLayer<'a>,
+    status_shown: Option<Status>,
+    /* 枠ごとに表示中のコイン */
+    coins_shown: [Option<Look>; MAX_COINS],
+    /* 枠ごとに表示中の足場と、りんごの足場のオブジェクト (エンドレスのみ) */
+    platforms_shown: [Option<PlatformLook>; MAX_PLATFORMS],
+    platform_objects: [Option<Pooled<'a>>; MAX_PLATFORMS],
+    /* 枠ごとに表示中の動く足場の見た目と、そのオブジェクト (左右の半分) */
+    moving_shown: [Option<Look>; MAX_MOVING_PLATFORMS],
+    moving_objects: [[Option<Pooled<'a>>; 2]; MAX_MOVING_PLATFORMS],
+    /* 崩れる足場も同じ (落ちきって消えている間は None) */
+    crumbling_shown: [Option<Look>; MAX_CRUMBLING_PLATFORMS],
+    crumbling_objects: [[Option<Pooled<'a>>; 2]; MAX_CRUMBLING_PLATFORMS],
+    /* 表示中の衝撃波 (始めた所と広がったドット数) と、そのオブジェクト (左右) */
+    shockwave_shown: Option<(i32, i32, i32)>,
+    shockwave_objects: [Option<Pooled<'a>>; 2],
+    /* 表示中のカメラの位置 */
+    camera_shown: i32,
+    /* 背景のこの行 (y / 8) から下は片付けてある */
+    cleared_row: i32,
+    /* 地面の列ごとに表示中のベルトコンベアのタイルと左右反転 (ベルトの無い列は None) */
+    conveyors_shown: [Option<(usize, bool)>; GROUND_COLUMNS],
+    /* 地面の列ごとに表示中の壊せるブロックの行 */
+    blocks_shown: [Option<u16>; GROUND_COLUMNS],
+    /* 破片 (Game::debris と同じ添字) の見た目。破片は毎ティック動くので、カメラを引いた画面上の位置で覚える */
+    debris_shown: [Option<Look>; MAX_DEBRIS],
+    debris_objects: [Option<Pooled<'a>>; MAX_DEBRIS],
+    /* 風 (Game::winds と同じ添字) ごとの、粒を描いたときに流れていたタイル数 (Wind::step) と、粒を描いたタイル */
+    streak_steps: [Option<(i32, i32)>; MAX_WINDS],
+    streak_cells: [Option<(u16, u16)>; MAX_WINDS * STREAKS],
+    /* 背景に固く描いてあるスイッチのブロックの組 */
+    solid_set_shown: BlockSet,
+    /* 背景に描いてあるスイッチ (Game::switches と同じ添字) の見た目 (絵の番号は踏まれているか) */
+    switches_shown: [Option<Look>; MAX_SWITCHES],
+    /* 背景に描いてある鍵と扉 (Game::keys, Game::doors と同じ添字) と、HUD に出している持っている鍵の数 */
+    keys_shown: [Option<Look>; MAX_KEYS],
+    doors_shown: [Option<Door>; MAX_DOORS],
+    keys_held_shown: Option<u8>,
+    /* 空を光らせている残りのティック数 (Game::warp) */
+    flash_shown: u16,
+    /* OAM が足りずに作れなかったものは None (表示されないだけ) */
+    droid: Option<Pooled<'a>>,
+    droid_shown: Option<DroidLook>,
+    /* 最後のティックから次のティックまでに進んだ分 (set_alpha) */
+    alpha: u32,
+    /* スプライト VRAM に読み込んでおいたレベルの絵。入りきらなければ None で、その都度読み込む */
+    preloaded: Option<Preloaded>,
+    apple: Option<Pooled<'a>>,
+    /* 表示中のりんごの位置 (スピードランでは次のレベルで動く) */
+    apple_shown: (i32, i32),
+    /* 他のりんご (Game::apples と同じ添字) */
+    apples_shown: [Option<Look>; MAX_APPLES],
+    apple_objects: [Option<Pooled<'a>>; MAX_APPLES],
+    /* ばね (Game::springs と同じ添字) の見た目 (絵の番号は縮んでいるか) */
+    springs_shown: [Option<Look>; MAX_SPRINGS],
+    spring_objects: [Option<Pooled<'a>>; MAX_SPRINGS],
+    _window: Option<Pooled<'a>>,
+    /* ゴースト (最初に位置が分かったときに作る) */
+    ghost: Option<Pooled<'a>>,
+    ghost_shown: Option<(i32, i32)>,
+    /* 表示中のゴーストとの差 */
+    split_shown: Option<i32>,
+    /* 表示中のスピードランの区切りと、差を書いた色 */
+    speedrun_split_shown: Option<(Split, Option<u8>)>,
+    /* 表示中の閉じた関門の x 座標 (チュートリアルのみ) */
+    gate_shown: Option<i32>,
+    /* 表示中のトリガーゾーンの案内 */
+    zone_hint_shown: Option<&'static str>,
+    /* 前回の commit 以降に OAM へ書き込んだ属性の数 */
+    object_writes: u32,
+    /* 前回の commit 以降にスプライト VRAM から絵を引いた回数 (読み込みが起きうるので一番重い) */
+    sprite_uploads: u32,
+}
+
+impl<'a> Presenter<'a> {
+    // 背景はタイルを並べ終えた状態で受け取り、最初の commit で転送する。
+    pub fn new(
+        objects: &'a ObjectPool<'a>,
+        sprites: &'a SpriteSet,
+        vram: &mut VRamManager,
+        bg: Layer<'a>,
+        mut hud: TextLayer<'a>,
+        game: &Game,
+    ) -> Self {
+        /* 遊んでいる間に読み込みが起きないよう、先に全ての絵を読み込む */
+        let preloaded = sprites.preload(objects);
+        if preloaded.is_none() {
+            log_warn!("sprites do not fit in sprite VRAM");
+        }
+        /* ドロイド君 */
+        let droid = ObjectSpawn::new(objects, sprites.idle, Category::Player)
+            .z(0)
+            .build();
+        /* りんご */
+        let apple = ObjectSpawn::new(objects, sprites.apple, Category::Platform)
+            .at((game.apple.x, game.apple.y))
+            .z(1)
+            .build();
+        /* 窓 */
+        let window = ObjectSpawn::new(objects, sprites.window, Category::Scenery)
+            .at((40, 40))
+            .z(1)
+            .build();
+
+        hud.set_visible(true);
+        vram.set_background_palette_colour(CLOUD_PALETTE as usize, CLOUD_COLOUR, rgb5(31, 31, 31));
+        let mut presenter = Self {
+            sprites,
+            bg,
+            hud,
+            status_shown: None,
+            coins_shown: [None; MAX_COINS],
+            platforms_shown: [None; MAX_PLATFORMS],
+            platform_objects: core::array::from_fn(|_| None),
+            moving_shown: [None; MAX_MOVING_PLATFORMS],
+            moving_objects: core::array::from_fn(|_| [None, None]),
+            crumbling_shown: [None; MAX_CRUMBLING_PLATFORMS],
+            crumbling_objects: core::array::from_fn(|_| [None, None]),
+            shockwave_shown: None,
+            shockwave_objects: [None, None],
+            camera_shown: 0,
+            cleared_row: text::ROWS as i32,
+            /* 地面を並べたとき (gameplay::lay_ground) は最初のコマで描いてある */
+            conveyors_shown: game.conveyors.map(|speed| conveyor::tile(speed, 0)),
+            /* ブロックも地面と一緒に並べてある */
+            blocks_shown: game.blocks,
+            debris_shown: [None; MAX_DEBRIS],
+            debris_objects: core::array::from_fn(|_| None),
+            streak_steps: [None; MAX_WINDS],
+            streak_cells: [None; MAX_WINDS * STREAKS],
+            solid_set_shown: game.solid_set,
+            switches_shown: [None; MAX_SWITCHES],
+            keys_shown: [None; MAX_KEYS],
+            doors_shown: [None; MAX_DOORS],
+            keys_held_shown: None,
+            flash_shown: 0,
+            droid,
+            droid_shown: None,
+            alpha: 0,
+            preloaded,
+            apple,
+            apple_shown: (game.apple.x, game.apple.y),
+            apples_shown: [None; MAX_APPLES],
+            springs_shown: [None; MAX_SPRINGS],
+            spring_objects: core::array::from_fn(|_| None),
+            apple_objects: core::array::from_fn(|_| None),
+            _window: window,
+            ghost: None,
+            ghost_shown: None,
+            split_shown: None,
+            speedrun_split_shown: None,
+            gate_shown: None,
+            zone_hint_shown: None,
+            /* 作ったばかりのオブジェクトはまだ OAM に載っていない */
+            object_writes: 3,
+            sprite_uploads: 0,
+        };
+        /* ベルトのコマを送るたびに背景のマップを丸ごと写すことにならないよう、使うコマを先に用意しておく */
+        for hflip in [false, true] {
+            let direction =
+                |speed: &Fixed| *speed != Fixed::new(0) && (*speed < Fixed::new(0)) == hflip;
+            if game.conveyors.iter().any(direction) {
+                for tile in CONVEYOR_TILES {
+                    let setting = Tile::of(&tiles::bg, tile).hflip(hflip);
+                    presenter.bg.reserve(vram, &tiles::bg.tiles, setting);
+                }
+            }
+        }
+        /* スイッチのブロックも同じく、塗りと枠の両方を用意しておく。スイッチの絵も上がったのと踏まれたのを */
+        let switch_tiles = [
+            (
+                game.switch_blocks.iter().any(Option::is_some),
+                SWITCH_BLOCK_TILES,
+            ),
+            (!game.switches.is_empty(), SWITCH_TILES),
+        ];
+        for (_, tiles) in switch_tiles.iter().filter(|(used, _)| *used) {
+            for tile in tiles.iter().flatten() {
+                presenter
+                    .bg
+                    .reserve(vram, &tiles::bg.tiles, Tile::of(&tiles::bg, *tile));
+            }
+        }
+        /* はしごは当たり判定のマップに入らないので地面とは別に、動かないのでここで一度だけ描く */
+        for (column, ladder) in game.ladders.iter().enumerate() {
+            if let Some(top) = *ladder {
+                let column = column as u16;
+                presenter.bg.fill(
+                    vram,
+                    column..column + 1,
+                    top..GROUND_ROW,
+                    &tiles::bg.tiles,
+                    Tile::of(&tiles::bg, LADDER),
+                );
+            }
+        }
+        /* 風の粒は流れるたびに描くので、向きごとの絵を用意しておく */
+        for (_, wind) in game.winds.iter() {
+            presenter
+                .bg
+                .reserve(vram, &tiles::bg.tiles, streak_setting(wind.force));
+        }
+        /* 水も動かないので一度だけ描く。一番上の行は水面 */
+        for (_, water) in game.waters.iter() {
+            let rect = water.rect;
+            let columns = (rect.x / 8) as u16..(rect.right() / 8) as u16;
+            let (top, bottom) = ((rect.y / 8) as u16, (rect.bottom() / 8) as u16);
+            for (rows, tile) in [
+                (top..top + 1, WATER_TILES[0]),
+                (top + 1..bottom, WATER_TILES[1]),
+            ] {
+                presenter.bg.fill(
+                    vram,
+                    columns.clone(),
+                    rows,
+                    &tiles::bg.tiles,
+                    Tile::of(&tiles::bg, tile),
+                );
+            }
+        }
+        /* テレポーターの台も動かないので一度だけ描く */
+        for (_, pad) in game.teleporters.iter() {
+            let (column, row) = ((pad.x / 8) as u16, map_row(pad.y));
+            for (offset, tile) in TELEPORTER_TILES.into_iter().enumerate() {
+                let column = column + offset as u16;
+                presenter.bg.fill(
+                    vram,
+                    column..column + 1,
+                    row..row + 1,
+                    &tiles::bg.tiles,
+                    Tile::of(&tiles::bg, tile),
+                );
+            }
+        }
+        /* 棘も動かないので一度だけ描く */
+        for (_, spike) in game.spikes.iter() {
+            let (column, row) = ((spike.x / 8) as u16, map_row(spike.y));
+            presenter.bg.fill(
+                vram,
+                column..column + 1,
+                row..row + 1,
+                &tiles::bg.tiles,
+                Tile::of(&tiles::bg, SPIKE),
+            );
+        }
+        presenter.draw(objects, vram, game);
+        presenter
+    }
+
+    // 次の draw で、動くもの (ドロイド君、足場、破片) を前のティックの位置から alpha (1/SPEED_NORMAL ティック単位)
+    // だけ最後のティックの位置へ寄せて描く (pacing::between)。
+    pub fn set_alpha(&mut self, alpha: u32) {
+        self.alpha = alpha;
+    }
+
+    pub fn draw(&mut self, objects: &'a ObjectPool<'a>, vram: &mut VRamManager, game: &Game) {
+        let camera = game.climb.as_ref().map_or(0, Climb::camera_y);
+        let scrolled = camera != self.camera_shown;
+        if scrolled {
+            self.scroll(vram, game, camera);
+        } else {
+            /* スクロールしたフレームは背景の書き換えが多いので、ベルトやブロックは止まっているフレームで描く */
+            self.draw_conveyors(vram, game);
+            self.draw_blocks(vram, game);
+            self.draw_switch_blocks(vram, game);
+            self.draw_switches(vram, game);
+            self.draw_doors(vram, game);
+        }
+        self.draw_hud(vram, game);
+        self.draw_coins(vram, game);
+        self.draw_platforms(objects, vram, game);
+        self.draw_moving_platforms(objects, game, camera);
+        self.draw_crumbling_platforms(objects, game, camera);
+        self.draw_apples(objects, game, camera);
+        self.draw_springs(objects, game, camera);
+        self.draw_shockwave(objects, game, camera);
+        self.draw_debris(objects, game, camera);
+        self.draw_gate(vram, game);
+        self.draw_zone_hint(vram, game);
+        self.draw_flash(vram, game);
+        if !scrolled {
+            self.draw_streaks(vram, game);
+        }
+        let apple = (game.apple.x, game.apple.y);
+        if apple != self.apple_shown {
+            if let Some(object) = &mut self.apple {
+                object.set_position((apple.0, apple.1 - camera));
+                self.object_writes += 1;
+            }
+            self.apple_shown = apple;
+        }
+
+        let Some(object) = &mut self.droid else {
+            return;
+        };
+        let mut look = game.droid.draw(self.alpha);
+        look.position.1 -= camera;
+        let shown = self.droid_shown.replace(look);
+        if shown.map(|shown| shown.position) != Some(look.position) {
+            object.set_position(look.position);
+            self.object_writes += 1;
+        }
+        if shown.map(|shown| shown.hflip) != Some(look.hflip) {
+            object.set_hflip(look.hflip);
+            self.object_writes += 1;
+        }
+        if shown.map(|shown| shown.vflip) != Some(look.vflip) {
+            object.set_vflip(look.vflip);
+            self.object_writes += 1;
+        }
+        if shown.map(|shown| shown.visible) != Some(look.visible) {
+            if look.visible {
+                object.show();
+            } else {
+                object.hide();
+            }
+            self.object_writes += 1;
+        }
+        /* 絵が変わったときだけ差し替える。読み込んであれば表を引くだけ */
+        if shown.map(|shown| shown.ch) != Some(look.ch) {
+            let sprite = match &self.preloaded {
+                Some(preloaded) => Some(preloaded.droid(look.ch)),
+                None => {
+                    self.sprite_uploads += 1;
+                    objects.try_sprite(self.sprites.droid(look.ch))
+                }
+            };
+            /* VRAM が足りなければ前の絵のまま */
+            if let Some(sprite) = sprite {
+                object.set_sprite(sprite);
+                self.object_writes += 1;
+            }
+        }
+    }
+
+    // ゴーストを position に出す。None なら隠す。
+    pub fn draw_ghost(&mut self, objects: &'a ObjectPool<'a>, position: Option<(i32, i32)>) {
+        if self.ghost.is_none() && position.is_some() {
+            /* 出すのは下で位置を決めてから */
+            self.ghost = ObjectSpawn::new(objects, self.sprites.idle, Category::Player)
+                .z(GHOST_Z)
+                .visible(false)
+                .build();
+            // Safety: 色の合成は他に誰も使っていない
+            unsafe {
+                BLDCNT.write_volatile(BLEND_SECOND_TARGETS);
+                BLDALPHA.write_volatile(BLEND_HALF);
+            }
+        }
+        let Some(object) = &mut self.ghost else {
+            return;
+        };
+        if self.ghost_shown == position {
+            return;
+        }
+        match position {
+            Some(position) => object.set_position(position).show(),
+            None => object.hide(),
+        };
+        self.ghost_shown = position;
+        self.object_writes += 1;
+    }
+
+    // ゴーストとの差 (ティック、負なら速い) を出す。None なら消す。
+    pub fn draw_split(&mut self, vram: &mut VRamManager, delta: Option<i32>) {
+        if self.split_shown == delta {
+            return;
+        }
+        match delta {
+            Some(delta) => {
+                let sign = if delta < 0 { '-' } else { '+' };
+                self.hud.print_line(
+                    vram,
+                    SPLIT_ROW,
+                    format_args!("       GHOST {}{}", sign, TimeDisplay(delta.unsigned_abs())),
+                );
+            }
+            None => self.hud.print_line(vram, SPLIT_ROW, format_args!("")),
+        }
+        self.split_shown = delta;
+    }
+
+    // スピードランの区切り (レベルのタイム、通しのタイム、最高記録との差) を出す。None なら消す。
+    // split は出してからのティック数と一緒に渡す。差は出したばかりの間は点滅する。
+    pub fn draw_speedrun_split(&mut self, vram: &mut VRamManager, split: Option<(Split, u32)>) {
+        let shown = split.map(|(split, age)| {
+            let palette = split.delta.map(|delta| split_delta::palette(delta, age));
+            (split, palette)
+        });
+        if self.speedrun_split_shown == shown {
+            return;
+        }
+        match split {
+            Some((split, age)) => {
+                self.hud.print_line(
+                    vram,
+                    SPLIT_ROW,
+                    format_args!(
+                        " LV{} {}  {}",
+                        split.segment + 1,
+                        TimeDisplay(split.level_time),
+                        TimeDisplay(split.total)
+                    ),
+                );
+                if let Some(delta) = split.delta {
+                    split_delta::draw(&mut self.hud, vram, DELTA_COLUMN, SPLIT_ROW, delta, age);
+                }
+            }
+            None => self.hud.print_line(vram, SPLIT_ROW, format_args!("")),
+        }
+        self.speedrun_split_shown = shown;
+    }
+
+    // チュートリアルを飛ばせることを出す。
+    pub fn draw_skip_hint(&mut self, vram: &mut VRamManager) {
+        self.hud
+            .print_line(vram, SKIP_ROW, format_args!("     SELECT: SKIP TUTORIAL"));
+    }
+
+    // カメラを camera へ動かす。背景はスクロールし、オブジェクトは位置を描き直す。
+    fn scroll(&mut self, vram: &mut VRamManager, game: &Game, camera: i32) {
+        self.bg.set_scroll_y(camera);
+        /* 画面の下へ出た行を片付ける。同じ行は 32 行上で使い直す */
+        while (self.cleared_row - 1) * 8 >= camera + HEIGHT {
+            self.cleared_row -= 1;
+            self.bg.clear_row(vram, map_row(self.cleared_row * 8));
+        }
+        if let Some(apple) = &mut self.apple {
+            apple.set_position((game.apple.x, game.apple.y - camera));
+            self.object_writes += 1;
+        }
+        for (object, shown) in self.platform_objects.iter_mut().zip(&self.platforms_shown) {
+            if let (Some(object), Some((_, x, y))) = (object, shown) {
+                object.set_position((*x, *y - camera));
+                self.object_writes += 1;
+            }
+        }
+        for (object, shown) in self.apple_objects.iter_mut().zip(&self.apples_shown) {
+            if let (Some(object), Some(look)) = (object, shown) {
+                object.set_position((look.x, look.y - camera));
+                self.object_writes += 1;
+            }
+        }
+        for (object, shown) in self.spring_objects.iter_mut().zip(&self.springs_shown) {
+            if let (Some(object), Some(look)) = (object, shown) {
+                object.set_position((look.x, look.y - camera));
+                self.object_writes += 1;
+            }
+        }
+        let lifts = self
+            .moving_objects
+            .iter_mut()
+            .zip(&self.moving_shown)
+            .chain(self.crumbling_objects.iter_mut().zip(&self.crumbling_shown));
+        for (halves, shown) in lifts {
+            if let Some(look) = shown {
+                for (half, object) in halves.iter_mut().enumerate() {
+                    if let Some(object) = object {
+                        object.set_position((look.x + 16 * half as i32, look.y - camera));
+                        self.object_writes += 1;
+                    }
+                }
+            }
+        }
+        self.camera_shown = camera;
+    }
+
+    fn draw_hud(&mut self, vram: &mut VRamManager, game: &Game) {
+        let status = match game.mode {
+            Mode::Normal => Status::Score {
+                score: game.score,
+                health: game.health,
+                player: None,
+            },
+            Mode::HotSeat => Status::Score {
+                score: game.score,
+                health: game.health,
+                player: Some(game.player + 1),
+            },
+            /* 通信対戦とゴーストとの競争もタイムで競うのでタイムアタックと同じ */
+            Mode::TimeAttack | Mode::GhostRace | Mode::Speedrun | Mode::LinkRace => Status::Time {
+                time: game.time,
+                banner: banner(game),
+            },
+            Mode::Endless => Status::Climb {
+                height: game.climb.as_ref().map_or(0, |climb| climb.height),
+                score: game.climb_score(),
+            },
+            Mode::CoinRush => {
+                let remaining = game.rush.as_ref().map_or(0, |rush| rush.remaining);
+                Status::Rush {
+                    remaining,
+                    score: game.score,
+                    health: game.health,
+                    banner: banner(game),
+                    warning: remaining < LOW_TIME_TICKS && remaining / RUSH_FLASH_TICKS % 2 == 0,
+                }
+            }
+            Mode::Tutorial => Status::Tutorial {
+                hint: game
+                    .script
+                    .as_ref()
+                    .and_then(|script| script.current())
+                    .map_or("", |trigger| trigger.hint),
+            },
+            Mode::Practice => Status::Practice {
+                segment: game
+                    .practice
+                    .as_ref()
+                    .map_or(0, |practice| practice.segment(game.time)),
+            },
+        };
+        if self.status_shown != Some(status) {
+            let name = game.difficulty.settings().name;
+            match status {
+                Status::Score {
+                    score,
+                    health,
+                    player: None,
+                } => self.hud.print_line(
+                    vram,
+                    0,
+                    format_args!(" SCORE {:5}  LIFE {}  {}", score, health, name),
+                ),
+                Status::Score {
+                    score,
+                    health,
+                    player: Some(player),
+                } => self.hud.print_line(
+                    vram,
+                    0,
+                    format_args!(" SCORE {:5}  LIFE {}  P{}", score, health, player),
+                ),
+                Status::Time { time, banner } => {
+                    self.hud.print_line(
+                        vram,
+                        0,
+                        format_args!(" TIME {}         {}", TimeDisplay(time), name),
+                    );
+                    /* 中央に揃える */
+                    let pad = 15 - banner.len() / 2;
+                    self.hud.print_line(
+                        vram,
+                        BANNER_ROW,
+                        format_args!("{:pad$}{}", "", banner, pad = pad),
+                    );
+                }
+                Status::Rush {
+                    remaining,
+                    score,
+                    health,
+                    banner,
+                    warning,
+                } => {
+                    self.hud.print_line(
+                        vram,
+                        0,
+                        format_args!(
+                            " TIME {} SCORE {:3} LIFE {}",
+                            TimeDisplay(remaining),
+                            score,
+                            health
+                        ),
+                    );
+                    if warning {
+                        self.hud.print_at(
+                            vram,
+                            RUSH_TIME_COLUMN,
+                            0,
+                            text::RED_PALETTE,
+                            format_args!("{}", TimeDisplay(remaining)),
+                        );
+                    }
+                    let pad = 15 - banner.len() / 2;
+                    self.hud.print_line(
+                        vram,
+                        BANNER_ROW,
+                        format_args!("{:pad$}{}", "", banner, pad = pad),
+                    );
+                }
+                Status::Practice { segment } => {
+                    self.hud.print_line(
+                        vram,
+                        0,
+                        format_args!(" SEGMENT {}      {}", TimeDisplay(segment), name),
+                    );
+                    if self.status_shown.is_none() {
+                        self.hud.print_line(
+                            vram,
+                            WATERMARK_ROW,
+                            format_args!("           PRACTICE"),
+                        );
+                        self.hud.print_line(
+                            vram,
+                            WATERMARK_ROW + 1,
+                            format_args!("        L:SAVE  R:LOAD"),
+                        );
+                    }
+                }
+                Status::Tutorial { hint } => {
+                    self.hud
+                        .print_line(vram, 0, format_args!(" TUTORIAL              {}", name));
+                    let pad = 15 - hint.len() / 2;
+                    self.hud.print_line(
+                        vram,
+                        HINT_ROW,
+                        format_args!("{:pad$}{}", "", hint, pad = pad),
+                    );
+                }
+                Status::Climb { height, score } => self.hud.print_line(
+                    vram,
+                    0,
+                    format_args!(" HEIGHT {:4}M  SCORE {:5}", height, score),
+                ),
+            }
+            self.status_shown = Some(status);
+        }
+    }
+
+    fn draw_coins(&mut self, vram: &mut VRamManager, game: &Game) {
+        let coins = entity::looks(&game.coins);
+        for (shown, coin) in self.coins_shown.iter_mut().zip(&coins) {
+            if shown == coin {
+                continue;
+            }
+            if let Some(old) = shown {
+                self.bg.write_row(
+                    vram,
+                    ((old.x / 8) as u16, map_row(old.y)),
+                    &font::TILESET,
+                    &[None],
+                );
+            }
+            if let Some(new) = coin {
+                let setting = Tile::new(font::tile_for(b'o'), text::TEXT_PALETTE);
+                self.bg.write_row(
+                    vram,
+                    ((new.x / 8) as u16, map_row(new.y)),
+                    &font::TILESET,
+                    &[Some(setting)],
+                );
+            }
+            *shown = *coin;
+        }
+    }
+
+    // テレポーターで移ったら空を白くし、止まっている間に元の色へ戻していく。パレットの色を 1 つ書き換えるだけ。
+    #[inline(never)]
+    fn draw_flash(&mut self, vram: &mut VRamManager, game: &Game) {
+        let remaining = game.warp.remaining();
+        if remaining == self.flash_shown {
+            return;
+        }
+        self.flash_shown = remaining;
+        let whiten = |channel: u16| (channel + (31 - channel) * remaining / WARP_TICKS) as u8;
+        let (r, g, b) = (SKY & 31, (SKY >> 5) & 31, (SKY >> 10) & 31);
+        vram.set_background_palette_colour(0, 0, rgb5(whiten(r), whiten(g), whiten(b)));
+    }
+
+    // 入っているトリガーゾーンの案内を真ん中に出す。出たら消す。
+    fn draw_zone_hint(&mut self, vram: &mut VRamManager, game: &Game) {
+        if game.hint == self.zone_hint_shown {
+            return;
+        }
+        let hint = game.hint.unwrap_or("");
+        let pad = 15 - hint.len() / 2;
+        self.hud.print_line(
+            vram,
+            ZONE_HINT_ROW,
+            format_args!("{:pad$}{}", "", hint, pad = pad),
+        );
+        self.zone_hint_shown = game.hint;
+    }
+
+    // 閉じている関門を、その x 座標の列に縦棒で描く。
+    fn draw_gate(&mut self, vram: &mut VRamManager, game: &Game) {
+        let gate = game
+            .script
+            .as_ref()
+            .and_then(|script| script.current())
+            .and_then(|trigger| trigger.gate);
+        if gate == self.gate_shown {
+            return;
+        }
+        if let Some(x) = self.gate_shown {
+            let top = ((x / 8) as u16, GATE_ROWS.start);
+            self.bg
+                .write_column(vram, top, &font::TILESET, &[None; GATE_HEIGHT]);
+        }
+        if let Some(x) = gate {
+            let setting = Tile::new(font::tile_for(b'|'), CLOUD_PALETTE);
+            let top = ((x / 8) as u16, GATE_ROWS.start);
+            self.bg
+                .write_column(vram, top, &font::TILESET, &[Some(setting); GATE_HEIGHT]);
+        }
+        self.gate_shown = gate;
+    }
+
+    // 雲と岩棚はタイル、りんごはオブジェクトで描く。
+    fn draw_platforms(&mut self, objects: &'a ObjectPool<'a>, vram: &mut VRamManager, game: &Game) {
+        let Some(climb) = &game.climb else {
+            return;
+        };
+        let mut platforms = [None; MAX_PLATFORMS];
+        for (id, platform) in climb.platforms.iter() {
+            platforms[id.index()] = Some((platform.kind, platform.x, platform.y));
+        }
+        for (index, platform) in platforms.into_iter().enumerate() {
+            if self.platforms_shown[index] == platform {
+                continue;
+            }
+            if let Some((kind, x, y)) = self.platforms_shown[index] {
+                if kind == PlatformKind::Apple {
+                    /* drop すれば次の commit で OAM から外れる */
+                    self.platform_objects[index] = None;
+                    self.object_writes += 1;
+                } else {
+                    let columns = (kind.width() / 8) as usize;
+                    let position = ((x / 8) as u16, map_row(y));
+                    self.bg.write_row(
+                        vram,
+                        position,
+                        &font::TILESET,
+                        &[None; PLATFORM_COLUMNS][..columns],
+                    );
+                }
+            }
+            if let Some((kind, x, y)) = platform {
+                if kind == PlatformKind::Apple {
+                    self.platform_objects[index] =
+                        ObjectSpawn::new(objects, self.sprites.apple, Category::Platform)
+                            .at((x, y - self.camera_shown))
+                            .z(1)
+                            .build();
+                    self.object_writes += 1;
+                } else {
+                    self.put_platform(vram, kind, (x, y));
+                }
+            }
+            self.platforms_shown[index] = platform;
+        }
+    }
+
+    // 動く足場はスプライト 2 枚を並べて描く。現れたときに作り、消えたら drop する。
+    fn draw_moving_platforms(&mut self, objects: &'a ObjectPool<'a>, game: &Game, camera: i32) {
+        if game.moving_platforms.is_empty() && self.moving_shown == [None; MAX_MOVING_PLATFORMS] {
+            return;
+        }
+        self.object_writes += draw_lifts(
+            objects,
+            self.sprites.lift,
+            entity::looks_between(&game.moving_platforms, self.alpha),
+            &mut self.moving_shown,
+            &mut self.moving_objects,
+            camera,
+        );
+    }
+
+    // 崩れる足場も動く足場と同じ絵で、揺れたり落ちたりする位置 (shown_at) に描く。
+    fn draw_crumbling_platforms(&mut self, objects: &'a ObjectPool<'a>, game: &Game, camera: i32) {
+        if game.crumbling_platforms.is_empty()
+            && self.crumbling_shown == [None; MAX_CRUMBLING_PLATFORMS]
+        {
+            return;
+        }
+        self.object_writes += draw_lifts(
+            objects,
+            self.sprites.lift,
+            entity::looks_between(&game.crumbling_platforms, self.alpha),
+            &mut self.crumbling_shown,
+            &mut self.crumbling_objects,
+            camera,
+        );
+    }
+
+    // 他のりんごは現れたときに作り、消えたら drop する。
+    fn draw_apples(&mut self, objects: &'a ObjectPool<'a>, game: &Game, camera: i32) {
+        if game.apples.is_empty() && self.apples_shown == [None; MAX_APPLES] {
+            return;
+        }
+        self.object_writes += draw_sprites(
+            objects,
+            &[self.sprites.apple],
+            Category::Platform,
+            entity::looks(&game.apples),
+            &mut self.apples_shown,
+            &mut self.apple_objects,
+            camera,
+        );
+    }
+
+    // ばねは現れたときに作り、消えたら drop する。縮んだり伸びたりしたら、その絵で作り直す。
+    fn draw_springs(&mut self, objects: &'a ObjectPool<'a>, game: &Game, camera: i32) {
+        /* ばねの無い場面 (エンドレスなど) では毎フレーム見比べない */
+        if game.springs.is_empty() && self.springs_shown == [None; MAX_SPRINGS] {
+            return;
+        }
+        self.object_writes += draw_sprites(
+            objects,
+            &self.sprites.spring,
+            Category::Platform,
+            entity::looks(&game.springs),
+            &mut self.springs_shown,
+            &mut self.spring_objects,
+            camera,
+        );
+    }
+
+    // 衝撃波は左右へ広がるスプライト 2 枚 (左は反転) で描く。出たときに作り、消えたら drop する。
+    fn draw_shockwave(&mut self, objects: &'a ObjectPool<'a>, game: &Game, camera: i32) {
+        let shockwave = game
+            .shockwave
+            .as_ref()
+            .map(|shockwave| (shockwave.x, shockwave.y, shockwave.reach()));
+        if shockwave == self.shockwave_shown {
+            return;
+        }
+        let halves = &mut self.shockwave_objects;
+        match shockwave {
+            None => *halves = [None, None],
+            Some((x, y, reach)) => {
+                for (half, object) in halves.iter_mut().enumerate() {
+                    if object.is_none() {
+                        *object =
+                            ObjectSpawn::new(objects, self.sprites.shockwave, Category::Effect)
+                                .hflip(half == 0)
+                                .z(1)
+                                .build();
+                    }
+                    if let Some(object) = object {
+                        let left = if half == 0 { x - reach - 16 } else { x + reach };
+                        object.set_position((left, y - camera));
+                    }
+                }
+            }
+        }
+        self.object_writes += 2;
+        self.shockwave_shown = shockwave;
+    }
+
+    // 壊れたブロックを背景から消す。エンドレスで画面の下へ出て片付けた行はそのまま。
+    // 見比べるのは Game::blocks なので、描かなかったフレームがあっても次に描くときに追いつく。
+    fn draw_blocks(&mut self, vram: &mut VRamManager, game: &Game) {
+        if game.blocks == self.blocks_shown {
+            return;
+        }
+        for (column, (&block, shown)) in game.blocks.iter().zip(&mut self.blocks_shown).enumerate()
+        {
+            if block == *shown {
+                continue;
+            }
+            for (row, setting) in [(*shown, None), (block, Some(Tile::of(&tiles::bg, BLOCK)))] {
+                if let Some(row) = row.filter(|&row| i32::from(row) < self.cleared_row) {
+                    self.bg.write_row(
+                        vram,
+                        (column as u16, map_row(i32::from(row) * 8)),
+                        &tiles::bg.tiles,
+                        &[setting],
+                    );
+                }
+            }
+            *shown = block;
+        }
+    }
+
+    // 固い組が入れ替わったら、スイッチのブロックを全て描き直す。
+    fn draw_switch_blocks(&mut self, vram: &mut VRamManager, game: &Game) {
+        if game.solid_set == self.solid_set_shown {
+            return;
+        }
+        for (column, block) in game.switch_blocks.iter().enumerate() {
+            if let Some((row, set)) = *block {
+                if i32::from(row) < self.cleared_row {
+                    let setting = Tile::of(&tiles::bg, set.tile(set == game.solid_set));
+                    self.bg.write_row(
+                        vram,
+                        (column as u16, map_row(i32::from(row) * 8)),
+                        &tiles::bg.tiles,
+                        &[Some(setting)],
+                    );
+                }
+            }
+        }
+        self.solid_set_shown = game.solid_set;
+    }
+
+    // スイッチの絵の下半分を背景に描く。踏まれたり離されたり、消えたりしたら描き直す。
+    // 上半分は空なので、絵の下端の行だけを書く。
+    fn draw_switches(&mut self, vram: &mut VRamManager, game: &Game) {
+        if game.switches.is_empty() && self.switches_shown == [None; MAX_SWITCHES] {
+            return;
+        }
+        let switches = entity::looks(&game.switches);
+        for (switch, shown) in switches.into_iter().zip(&mut self.switches_shown) {
+            if switch == *shown {
+                continue;
+            }
+            for (switch, tiles) in [
+                (*shown, None),
+                (
+                    switch,
+                    switch.map(|look| SWITCH_TILES[usize::from(look.frame)]),
+                ),
+            ] {
+                let Some(Look { x, y, .. }) = switch else {
+                    continue;
+                };
+                let bottom = y + SWITCH_SIZE - 8;
+                if bottom.div_euclid(8) < self.cleared_row {
+                    let setting =
+                        |half: usize| tiles.map(|tiles| Tile::of(&tiles::bg, tiles[half]));
+                    self.bg.write_row(
+                        vram,
+                        ((x / 8) as u16, map_row(bottom)),
+                        &tiles::bg.tiles,
+                        &[setting(0), setting(1)],
+                    );
+                }
+            }
+            *shown = switch;
+        }
+    }
+
+    // 鍵と扉を背景に描き、拾われたり開いたりしたら消す。鍵か扉のあるレベルでは持っている鍵の数も HUD に出す。
+    fn draw_doors(&mut self, vram: &mut VRamManager, game: &Game) {
+        if game.keys.is_empty()
+            && game.doors.is_empty()
+            && self.keys_shown == [None; MAX_KEYS]
+            && self.doors_shown == [None; MAX_DOORS]
+        {
+            return;
+        }
+        let keys = entity::looks(&game.keys);
+        for (key, shown) in keys.into_iter().zip(&mut self.keys_shown) {
+            if key == *shown {
+                continue;
+            }
+            for (key, setting) in [(*shown, None), (key, Some(Tile::of(&tiles::bg, KEY)))] {
+                if let Some(key) = key.filter(|key| key.y.div_euclid(8) < self.cleared_row) {
+                    self.bg.write_row(
+                        vram,
+                        ((key.x / 8) as u16, map_row(key.y)),
+                        &tiles::bg.tiles,
+                        &[setting],
+                    );
+                }
+            }
+            *shown = key;
+        }
+        let mut doors = [None; MAX_DOORS];
+        for (id, door) in game.doors.iter() {
+            doors[id.index()] = Some(*door);
+        }
+        for (door, shown) in doors.into_iter().zip(&mut self.doors_shown) {
+            if door == *shown {
+                continue;
+            }
+            for (door, tiles) in [(*shown, None), (door, Some(DOOR_TILES))] {
+                let Some(door) = door else {
+                    continue;
+                };
+                for (half, y) in [door.y, door.y + 8].into_iter().enumerate() {
+                    if y.div_euclid(8) < self.cleared_row {
+                        self.bg.write_row(
+                            vram,
+                            ((door.x / 8) as u16, map_row(y)),
+                            &tiles::bg.tiles,
+                            &[tiles.map(|tiles| Tile::of(&tiles::bg, tiles[half]))],
+                        );
+                    }
+                }
+            }
+            *shown = door;
+        }
+        if self.keys_held_shown != Some(game.keys_held) {
+            self.hud
+                .print_line(vram, KEY_ROW, format_args!(" KEY {}", game.keys_held));
+            self.keys_held_shown = Some(game.keys_held);
+        }
+    }
+
+    // 破片は現れたときに作り、消えたら drop する。
+    fn draw_debris(&mut self, objects: &'a ObjectPool<'a>, game: &Game, camera: i32) {
+        if game.debris.is_empty() && self.debris_shown == [None; MAX_DEBRIS] {
+            return;
+        }
+        let debris = entity::looks_between(&game.debris, self.alpha).map(|look| {
+            look.map(|look| Look {
+                y: look.y - camera,
+                ..look
+            })
+        });
+        self.object_writes += draw_sprites(
+            objects,
+            &[self.sprites.debris],
+            Category::Effect,
+            debris,
+            &mut self.debris_shown,
+            &mut self.debris_objects,
+            0,
+        );
+    }
+
+    // 風の粒を、流れて移ったタイルへ描き直す。粒は全て同じティックに移るので、風ごとに流れたタイル数を見る。
+    // 流れた量は Game が進めているので、描かなかったフレームがあっても次に描くときに追いつく。
+    #[inline(never)]
+    fn draw_streaks(&mut self, vram: &mut VRamManager, game: &Game) {
+        if game.winds.is_empty() && self.streak_steps == [None; MAX_WINDS] {
+            return;
+        }
+        /* 1 フレーム遅れても目立たないので、スクロールもせず背景も HUD も書き換えていない軽いフレームで描く */
+        if self.bg.is_dirty() || self.hud.is_dirty() {
+            return;
+        }
+        let mut steps = [None; MAX_WINDS];
+        for (id, wind) in game.winds.iter() {
+            let index = id.index();
+            steps[index] = Some(wind.step());
+            if steps[index] == self.streak_steps[index] {
+                continue;
+            }
+            let setting = streak_setting(wind.force);
+            for streak in 0..STREAKS {
+                let (x, y) = wind.streak(streak);
+                let cell = ((x / 8) as u16, map_row(y));
+                self.put_streak(vram, index * STREAKS + streak, Some((cell, setting)));
+            }
+        }
+        for (index, step) in steps.into_iter().enumerate() {
+            if step.is_none() && self.streak_steps[index].is_some() {
+                for streak in 0..STREAKS {
+                    self.put_streak(vram, index * STREAKS + streak, None);
+                }
+            }
+            self.streak_steps[index] = step;
+        }
+    }
+
+    // index 番目の粒を前に描いたタイルから消し、cell に描く。他のものが描いてあるタイルには描かない。
+    fn put_streak(
+        &mut self,
+        vram: &mut VRamManager,
+        index: usize,
+        streak: Option<((u16, u16), Tile)>,
+    ) {
+        if let Some(cell) = self.streak_cells[index].take() {
+            self.bg.clear_tile(vram, cell, &tiles::bg.tiles);
+        }
+        if let Some((cell, setting)) = streak {
+            if !self.bg.is_occupied(cell) {
+                self.bg
+                    .write_row(vram, cell, &tiles::bg.tiles, &[Some(setting)]);
+                self.streak_cells[index] = Some(cell);
+            }
+        }
+    }
+
+    // ベルトコンベアのコマを送る。コマの変わった列が続くところをまとめて書き直す。
+    // コマはティックから決まるので、送らなかったフレームがあっても次に送るときに追いつく。
+    // エンドレスで画面の下へ出て片付けた地面は描き直さない。
+    fn draw_conveyors(&mut self, vram: &mut VRamManager, game: &Game) {
+        if self.cleared_row <= i32::from(GROUND_ROW) {
+            return;
+        }
+        /* コマが変わった列のビット */
+        let mut changed = 0u32;
+        let mut entries = [None; GROUND_COLUMNS];
+        for (column, &speed) in game.conveyors.iter().enumerate() {
+            if speed == Fixed::new(0) {
+                continue;
+            }
+            let frame = conveyor::tile(speed, game.tick);
+            if frame != self.conveyors_shown[column] {
+                self.conveyors_shown[column] = frame;
+                entries[column] =
+                    frame.map(|(index, hflip)| Tile::of(&tiles::bg, index).hflip(hflip));
+                changed |= 1 << column;
+            }
+        }
+        /* 続いている列はまとめて書く */
+        while changed != 0 {
+            let start = changed.trailing_zeros() as usize;
+            let len = (!changed >> start).trailing_zeros() as usize;
+            changed &= !(((1 << len) - 1) << start);
+            self.bg.write_row(
+                vram,
+                (start as u16, GROUND_ROW),
+                &tiles::bg.tiles,
+                &entries[start..start + len],
+            );
+        }
+    }
+
+    // 足場 1 つ分の行をまとめて書く。
+    fn put_platform(&mut self, vram: &mut VRamManager, kind: PlatformKind, (x, y): (i32, i32)) {
+        let columns = (kind.width() / 8) as usize;
+        let position = ((x / 8) as u16, map_row(y));
+        let mut entries = [None; PLATFORM_COLUMNS];
+        if kind == PlatformKind::Ledge {
+            /* 地面の表面と同じタイル */
+            for (column, entry) in entries[..columns].iter_mut().enumerate() {
+                let index = match column {
+                    0 => GROUND_LEFT,
+                    _ if column == columns - 1 => GROUND_RIGHT,
+                    _ => GROUND_TOP,
+                };
+                *entry = Some(Tile::of(&tiles::bg, index));
+            }
+            self.bg
+                .write_row(vram, position, &tiles::bg.tiles, &entries[..columns]);
+        } else {
+            /* 雲は上寄りの白い横棒 */
+            let setting = Tile::new(font::bar_tile(8), CLOUD_PALETTE);
+            entries = [Some(setting); PLATFORM_COLUMNS];
+            self.bg
+                .write_row(vram, position, &font::TILESET, &entries[..columns]);
+        }
+    }
+
+    // 変わったものだけハードウェアに転送する。
+    pub fn commit(&mut self, objects: &ObjectPool, vram: &mut VRamManager) {
+        profile_count!("obj writes", self.object_writes);
+        profile_count!("sprite loads", self.sprite_uploads);
+        self.sprite_uploads = 0;
+        profile_count!(
+            "sprite vram",
+            self.preloaded
+                .as_ref()
+                .map_or(0, |preloaded| preloaded.bytes() as u32)
+        );
+        if self.object_writes > 0 {
+            objects.commit();
+            self.object_writes = 0;
+            if self.ghost_shown.is_some() {
+                // Safety: commit の直後に、ゴーストの入った 0 番の属性 0 だけを書き換える
+                unsafe {
+                    let attr0 = OAM.read_volatile() & !OBJ_MODE_MASK;
+                    OAM.write_volatile(attr0 | OBJ_MODE_SEMI_TRANSPARENT);
+                }
+            }
+            profile_count!("oam commits", 1);
+        }
+        let bg = self.bg.commit(vram);
+        let hud = self.hud.commit(vram);
+        if bg || hud {
+            profile_count!("bg commits", 1);
+        }
+    }
+
+    // HUD を片付けて、レベルの背景を隠して返す。背景は次のレベルで使い回す (gameplay::build_level)。
+    // オブジェクトは drop したときに OAM から外れる。
+    pub fn release(mut self, vram: &mut VRamManager) -> Layer<'a> {
+        if self.ghost.is_some() {
+            // Safety: draw_ghost で設定した色の合成を戻す
+            unsafe { BLDCNT.write_volatile(0) };
+        }
+        self.hud.release(vram);
+        self.bg.set_visible(false);
+        self.bg
+    }
+}
+
+// y 座標のドットが入る背景のマップの行。
+fn map_row(y: i32) -> u16 {
+    y.div_euclid(8).rem_euclid(32) as u16
+}
+
+// 強さ force の風の粒の絵。横の方が強ければ横に、そうでなければ縦に流れる絵を、流れる向きに反転する。
+fn streak_setting((x, y): (i32, i32)) -> Tile {
+    if x.abs() >= y.abs() {
+        Tile::of(&tiles::bg, STREAK_TILES[0]).hflip(x < 0)
+    } else {
+        Tile::of(&tiles::bg, STREAK_TILES[1]).vflip(y > 0)
+    }
+}
+
+// エンティティ (見た目、消えていれば None) を枠ごとにスプライト 1 枚で描く。絵は sprites の Look::frame 番目。
+// 現れたときと絵が変わったときに作り、消えたら drop してオブジェクトを OAM に返す。
+// 途中で置いたり消したりしたもの (破片、拾ったもの...) も、見た目が無くなった次のフレームで返る。
+// OAM に書いた数を返す。
+fn draw_sprites<'a, const N: usize>(
+    objects: &'a ObjectPool<'a>,
+    sprites: &[&'static Sprite],
+    category: Category,
+    looks: [Option<Look>; N],
+    shown: &mut [Option<Look>; N],
+    sprite_objects: &mut [Option<Pooled<'a>>; N],
+    camera: i32,
+) -> u32 {
+    let mut writes = 0;
+    for ((look, shown), object) in looks.into_iter().zip(shown).zip(sprite_objects) {
+        if *shown == look {
+            continue;
+        }
+        match look {
+            None => *object = None,
+            Some(look) => {
+                /* 前に作れなかった (OAM が足りなかった) ものも作り直してみる */
+                if object.is_none() || shown.map(|shown| shown.frame) != Some(look.frame) {
+                    let sprite = sprites[usize::from(look.frame)];
+                    *object = ObjectSpawn::new(objects, sprite, category).z(1).build();
+                }
+                if let Some(object) = object {
+                    object.set_position((look.x, look.y - camera));
+                }
+            }
+        }
+        writes += 1;
+        *shown = look;
+    }
+    writes
+}
+
+// 足場 (見た目、消えていれば None) を枠ごとにスプライト 2 枚を並べて描く。
+// 現れたときに作り、消えたら drop する。OAM に書いた数を返す。
+fn draw_lifts<'a, const N: usize>(
+    objects: &'a ObjectPool<'a>,
+    sprite: &'static Sprite,
+    lifts: [Option<Look>; N],
+    shown: &mut [Option<Look>; N],
+    lift_objects: &mut [[Option<Pooled<'a>>; 2]; N],
+    camera: i32,
+) -> u32 {
+    let mut writes = 0;
+    for ((lift, shown), halves) in lifts.into_iter().zip(shown).zip(lift_objects) {
+        if *shown == lift {
+            continue;
+        }
+        match (*shown, lift) {
+            (_, None) => *halves = [None, None],
+            (None, Some(_)) => {
+                for object in halves.iter_mut() {
+                    *object = ObjectSpawn::new(objects, sprite, Category::Platform)
+                        .z(1)
+                        .build();
+                }
+            }
+            (Some(_), Some(_)) => {}
+        }
+        if let Some(look) = lift {
+            for (half, object) in halves.iter_mut().enumerate() {
+                if let Some(object) = object {
+                    object.set_position((look.x + 16 * half as i32, look.y - camera));
+                }
+            }
+        }
+        writes += 2;
+        *shown = lift;
+    }
+    writes
+}
+
+// カウントダウン中は残りの秒数、終わった直後は「GO!」。
+fn banner(game: &Game) -> &'static str {
+    if game.countdown.is_running() {
+        match game.countdown.remaining() {
+            0..=60 => "1",
+            61..=120 => "2",
+            _ => "3",
+        }
+    } else if game.time < GO_TICKS {
+        "GO!"
+    } else {
+        ""
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+
+    use agb::{
+        display::{tiled::Tiled0, Priority},
+        input::Button,
+    };
+
+    use super::*;
+    use crate::{difficulty::Difficulty, input::Input, spawn::EntityKind};
+
+    // Game も Presenter も大きいので、テストのフレームに並べずヒープに作る (replay のテストと同じ)。
+    #[inline(never)]
+    fn started() -> Box<Game> {
+        Box::new(Game::new(Difficulty::Normal, 1))
+    }
+
+    #[inline(never)]
+    fn present<'a>(
+        gfx: &'a Tiled0<'_>,
+        objects: &'a ObjectPool<'a>,
+        sprites: &'a SpriteSet,
+        vram: &mut VRamManager,
+        game: &Game,
+    ) -> Box<Presenter<'a>> {
+        let hud = TextLayer::new(Layer::new(gfx, Priority::P0), vram);
+        let bg = Layer::new(gfx, Priority::P0);
+        Box::new(Presenter::new(objects, sprites, vram, bg, hud, game))
+    }
+
+    #[inline(never)]
+    fn release(presenter: Box<Presenter>, vram: &mut VRamManager) {
+        presenter.release(vram).release(vram);
+    }
+
+    #[test_case]
+    fn an_idle_screen_writes_nothing(gba: &mut agb::Gba) {
+        let (gfx, mut vram) = gba.display.video.tiled0();
+        let oam = gba.display.object.get_managed();
+        let object = ObjectPool::new(&oam);
+        let sprites = SpriteSet::load();
+        let mut game = started();
+        /* 動く足場は毎ティック動き、ベルトはコマを送り、風は粒を流すので片付けておく */
+        game.moving_platforms.retain(|_| false);
+        game.winds.retain(|_| false);
+        game.conveyors = [Fixed::new(0); GROUND_COLUMNS];
+        let mut presenter = present(&gfx, &object, &sprites, &mut vram, &game);
+        presenter.commit(&object, &mut vram);
+
+        for _ in 0..10 {
+            game.update(&Input::default());
+            presenter.draw(&object, &mut vram, &game);
+            assert_eq!(presenter.object_writes, 0);
+            assert!(!presenter.bg.commit(&mut vram));
+            assert!(!presenter.hud.commit(&mut vram));
+            presenter.commit(&object, &mut vram);
+        }
+
+        /* 向きだけ変われば書き込みは 1 回 */
+        game.droid.hflip = true;
+        presenter.draw(&object, &mut vram, &game);
+        assert_eq!(presenter.object_writes, 1);
+        release(presenter, &mut vram);
+    }
+
+    // 遊んでいる途中に置いたものは次のフレームに出て、消えたら次のフレームでオブジェクトを OAM に返す。
+    #[test_case]
+    fn despawned_entities_give_their_objects_back(gba: &mut agb::Gba) {
+        let (gfx, mut vram) = gba.display.video.tiled0();
+        let oam = gba.display.object.get_managed();
+        let object = ObjectPool::new(&oam);
+        let sprites = SpriteSet::load();
+        let mut game = started();
+        let mut presenter = present(&gfx, &object, &sprites, &mut vram, &game);
+        let apple = game.spawn(EntityKind::Apple).at((40, 40)).build().unwrap();
+        game.scatter_debris((100, 80));
+        presenter.draw(&object, &mut vram, &game);
+        assert!(presenter.apple_objects[apple.index()].is_some());
+        assert!(presenter.debris_objects.iter().any(Option::is_some));
+
+        game.apples.despawn(apple);
+        for _ in 0..40 {
+            game.update(&Input::default());
+        }
+        assert!(game.debris.is_empty());
+        presenter.draw(&object, &mut vram, &game);
+        assert!(presenter.apple_objects[apple.index()].is_none());
+        assert!(presenter.debris_objects.iter().all(Option::is_none));
+        release(presenter, &mut vram);
+    }
+
+    // 絵を読み込んであれば、遊んでいる間はスプライト VRAM を引かない。
+    // 読み込めなかったときも、引くのは立っている間は 0 回、歩いている間はコマが変わったときだけ。
+    #[test_case]
+    fn the_droid_sprite_changes_only_with_its_pose(gba: &mut agb::Gba) {
+        let (gfx, mut vram) = gba.display.video.tiled0();
+        let oam = gba.display.object.get_managed();
+        let object = ObjectPool::new(&oam);
+        let sprites = SpriteSet::load();
+        let mut game = started();
+        let mut presenter = present(&gfx, &object, &sprites, &mut vram, &game);
+        /* buttons を 60 ティック押し続け、ポーズが変わった回数と絵を引いた回数を返す */
+        fn walk<'a>(
+            presenter: &mut Presenter<'a>,
+            objects: &'a ObjectPool<'a>,
+            vram: &mut VRamManager,
+            game: &mut Game,
+            buttons: Button,
+        ) -> (u32, u32) {
+            let mut changes = 0;
+            let mut uploads = 0;
+            for _ in 0..60 {
+                let pose = game.droid.pose();
+                game.update(&Input::new(buttons, buttons));
+                if game.droid.pose() != pose {
+                    changes += 1;
+                }
+                presenter.draw(objects, vram, game);
+                assert!(presenter.sprite_uploads <= 1);
+                uploads += presenter.sprite_uploads;
+                presenter.commit(objects, vram);
+            }
+            (changes, uploads)
+        }
+        assert!(presenter.preloaded.is_some());
+        let (changes, uploads) = walk(&mut presenter, &object, &mut vram, &mut game, Button::RIGHT);
+        assert!(changes > 1);
+        assert_eq!(uploads, 0);
+
+        presenter.preloaded = None;
+        let (changes, uploads) = walk(&mut presenter, &object, &mut vram, &mut game, Button::LEFT);
+        assert!(changes > 1 && changes < 60, "{changes} pose changes");
+        assert_eq!(uploads, changes);
+        /* 止まって待機の絵に戻った後は引かない */
+        let stop = walk(
+            &mut presenter,
+            &object,
+            &mut vram,
+            &mut game,
+            Button::empty(),
+        );
+        assert!(stop.0 <= 1 && stop.1 == stop.0);
+        let idle = walk(
+            &mut presenter,
+            &object,
+            &mut vram,
+            &mut game,
+            Button::empty(),
+        );
+        assert_eq!(idle, (0, 0));
+        release(presenter, &mut vram);
+    }
+}
@@ -0,0 +1,256 @@
+// フレーム時間のプロファイラ (debug-tools フィーチャー専用)。
+//
+// タイマー 2 を 64 分周 (1 カウント = 64 サイクル ≒ 3.8µs) で回しっぱなしにし、
+// 区間の始めと終わりで値を読んで差を取るだけなので、計測自体のコストはごくわずか。
+// 1 フレームは 280896 サイクル = 4389 カウント。カウンタは 16bit なので、
+// 1 区間が約 15 フレームを超えると正しく測れない。
+// 始める (start) までは Mutex も取らずに何もしない。テストやベンチマークでは始めないので、計測の分は掛からない。
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use agb::{
+    display::tiled::VRamManager,
+    sync::Mutex,
+    timer::{Divider, Timer},
+};
+
+use crate::{font, text};
+
+pub const TICKS_PER_FRAME: u32 = 280_896 / 64;
+/* 最大値を取る期間 (フレーム数) */
+const HISTORY: usize = 60;
+const MAX_BUCKETS: usize = 8;
+const MAX_COUNTERS: usize = 7;
+
+// 区間ごとの計測値を積み上げる。profile_scope! を抜けるときに加算される。
+macro_rules! profile_scope {
+    ($name:expr) => {
+        let _profile_scope = $crate::profiler::Scope::new($name);
+    };
+}
+
+// フレームごとの回数を数える。profile_scope! と同じくフレームの終わりで締める。
+macro_rules! profile_count {
+    ($name:expr, $n:expr) => {
+        $crate::profiler::count($name, $n)
+    };
+}
+
+#[derive(Clone, Copy)]
+struct Counter {
+    name: &'static str,
+    count: u32,
+}
+
+#[derive(Clone, Copy)]
+struct Bucket {
+    name: &'static str,
+    ticks: u32,
+}
+
+struct Profiler {
+    timer: Timer,
+    frame_start: u16,
+    current: [Option<Bucket>; MAX_BUCKETS],
+    last: [Option<Bucket>; MAX_BUCKETS],
+    counters: [Option<Counter>; MAX_COUNTERS],
+    last_counters: [Option<Counter>; MAX_COUNTERS],
+    history: [u16; HISTORY],
+    history_pos: usize,
+}
+
+static PROFILER: Mutex<Option<Profiler>> = Mutex::new(None);
+/* start 済みか。Mutex を取るより軽いので、始める前はこれだけ見て帰る */
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+impl Profiler {
+    fn now(&self) -> u16 {
+        self.timer.value()
+    }
+
+    fn add(&mut self, name: &'static str, ticks: u32) {
+        let slot = self
+            .current
+            .iter_mut()
+            .find(|bucket| bucket.map_or(true, |bucket| bucket.name == name));
+        match slot {
+            Some(Some(bucket)) => bucket.ticks += ticks,
+            Some(slot) => *slot = Some(Bucket { name, ticks }),
+            None => log_warn!("profiler: no bucket left for {}", name),
+        }
+    }
+
+    fn count(&mut self, name: &'static str, count: u32) {
+        let slot = self
+            .counters
+            .iter_mut()
+            .find(|counter| counter.map_or(true, |counter| counter.name == name));
+        match slot {
+            Some(Some(counter)) => counter.count += count,
+            Some(slot) => *slot = Some(Counter { name, count }),
+            None => log_warn!("profiler: no counter left for {}", name),
+        }
+    }
+}
+
+// タイマーを受け取って計測を始める。
+pub fn start(mut timer: Timer) {
+    timer.set_divider(Divider::Divider64).set_enabled(true);
+    *PROFILER.lock() = Some(Profiler {
+        timer,
+        frame_start: 0,
+        current: [None; MAX_BUCKETS],
+        last: [None; MAX_BUCKETS],
+        counters: [None; MAX_COUNTERS],
+        last_counters: [None; MAX_COUNTERS],
+        history: [0; HISTORY],
+        history_pos: 0,
+    });
+    STARTED.store(true, Ordering::Relaxed);
+}
+
+fn with_profiler(f: impl FnOnce(&mut Profiler)) {
+    if !STARTED.load(Ordering::Relaxed) {
+        return;
+    }
+    if let Some(profiler) = PROFILER.lock().as_mut() {
+        f(profiler);
+    }
+}
+
+fn now() -> u16 {
+    if !STARTED.load(Ordering::Relaxed) {
+        return 0;
+    }
+    PROFILER.lock().as_ref().map_or(0, Profiler::now)
+}
+
+// VBlank 待ちから戻った直後に呼ぶ。
+pub fn begin_frame() {
+    with_profiler(|profiler| profiler.frame_start = profiler.now());
+}
+
+// 次の VBlank 待ちに入る直前に呼ぶ。
+pub fn end_frame() {
+    with_profiler(|profiler| {
+        let total = profiler.now().wrapping_sub(profiler.frame_start);
+        profiler.history[profiler.history_pos] = total;
+        profiler.history_pos = (profiler.history_pos + 1) % HISTORY;
+        profiler.last = profiler.current;
+        profiler.current = [None; MAX_BUCKETS];
+        profiler.last_counters = profiler.counters;
+        profiler.counters = [None; MAX_COUNTERS];
+    });
+}
+
+// 直前のフレームの計測値をログに書く。オーバーランしたフレームの原因調査用。
+pub fn log_last_frame() {
+    if let Some(profiler) = PROFILER.lock().as_ref() {
+        let last = (profiler.history_pos + HISTORY - 1) % HISTORY;
+        log_warn!(
+            "frame took {}us",
+            ticks_to_micros(profiler.history[last] as u32)
+        );
+        for bucket in profiler.last.iter().flatten() {
+            log_warn!("  {}: {}us", bucket.name, ticks_to_micros(bucket.ticks));
+        }
+    }
+}
+
+pub fn count(name: &'static str, count: u32) {
+    with_profiler(|profiler| profiler.count(name, count));
+}
+
+pub struct Scope {
+    name: &'static str,
+    start: u16,
+}
+
+impl Scope {
+    pub fn new(name: &'static str) -> Self {
+        Self { name, start: now() }
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        with_profiler(|profiler| {
+            let ticks = profiler.now().wrapping_sub(self.start);
+            profiler.add(self.name, ticks as u32);
+        });
+    }
+}
+
+pub fn ticks_to_micros(ticks: u32) -> u32 {
+    /* 1 カウント = 64 / 16777216 秒 = 15625 / 4096 µs */
+    ticks * 15625 / 4096
+}
+
+// 1 行目にフレーム予算に対する直近 60 フレームの最大値の横棒、
+// 2 行目以降に数値を描く。
+pub fn draw_overlay(layer: &mut text::TextLayer, vram: &mut VRamManager, overruns: u32) {
+    let Some((last_total, max_total, buckets, counters)) =
+        PROFILER.lock().as_ref().map(|profiler| {
+            let last = (profiler.history_pos + HISTORY - 1) % HISTORY;
+            let max = profiler.history.iter().copied().max().unwrap_or(0);
+            (
+                profiler.history[last] as u32,
+                max as u32,
+                profiler.last,
+                profiler.last_counters,
+            )
+        })
+    else {
+        return;
+    };
+
+    let width = text::COLUMNS as u32 * 8;
+    let filled = (max_total * width / TICKS_PER_FRAME).min(width);
+    for column in 0..text::COLUMNS {
+        let in_tile = filled.saturating_sub(column as u32 * 8) as usize;
+        layer.put_tile(vram, column, 0, font::bar_tile(in_tile));
+    }
+
+    layer.print_line(
+        vram,
+        1,
+        format_args!(
+            "frame {:5}us max {:5}us",
+            ticks_to_micros(last_total),
+            ticks_to_micros(max_total)
+        ),
+    );
+    layer.print_line(vram, 2, format_args!("overruns {}", overruns));
+    let mut row = 3;
+    for bucket in buckets.iter().flatten() {
+        layer.print_line(
+            vram,
+            row,
+            format_args!("{:>12} {:5}us", bucket.name, ticks_to_micros(bucket.ticks)),
+        );
+        row += 1;
+    }
+    for counter in counters.iter().flatten() {
+        layer.print_line(
+            vram,
+            row,
+            format_args!("{:>12} {:3}", counter.name, counter.count),
+        );
+        row += 1;
+    }
+    for row in row..3 + (MAX_BUCKETS + MAX_COUNTERS) as u16 {
+        layer.print_line(vram, row, format_args!(""));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn a_full_frame_is_about_16_7_ms(_gba: &mut agb::Gba) {
+        assert_eq!(ticks_to_micros(TICKS_PER_FRAME), 16742);
+        assert_eq!(ticks_to_micros(0), 0);
+        assert_eq!(ticks_to_micros(4096), 15625);
+    }
+}
@@ -0,0 +1,440 @@
+// 通信ケーブルでの 2 人対戦 (レース)。
+//
+// 2 台が同じレベルを同じシードで遊び、先にコインを全て取った方の勝ち。
+// 相手のドロイド君は半透明のゴーストとして表示する。
+//
+// やりとりは Link の 16 ビットの値 1 つずつで、上位 4 ビットが種類、下位 12 ビットが中身。
+// 値 1 つで完結させておくと、取りこぼしや重複があっても他の値の意味が変わらない。
+// 1. 握手: 版、レベル、シード (12 ビットずつ 3 つ) を繰り返し送り合う。
+//    版とレベルが合わなければ遊べない。シードは親機に合わせ、子機は親機のシードを受け取った時点で、
+//    親機は自分のシードが返ってきた時点で準備完了。カウントダウンの間も送り続け、相手の完了を待つ。
+// 2. レース中: 自分の位置を SEND_INTERVAL ごとに x, y の順で送る。
+//    ゴーストは最後に受け取った位置と、その前の位置から求めた速度で補って動かす。
+// 3. 終わったら (クリアか体力切れ) タイムを下位、上位の 12 ビットずつ送り続ける。
+//    相手のタイムが先に届いたら、まだ走っていても勝負はついているので自分の分も確定させて送る。
+//    両方揃ったら、相手にも届くよう LINGER_TICKS だけ送り続けてから結果を返す。
+// SILENT_TICKS の間何も届かなければケーブルが抜けたものとして打ち切る。
+
+use alloc::boxed::Box;
+use core::cmp::Ordering;
+
+use crate::{
+    game::{self, Game, Outcome, GROUND_Y},
+    link::{Link, LinkError},
+    math::Fixed,
+    pacing,
+    time::Timer,
+};
+
+/* 通信の取り決めの版。変えたら上げる */
+const PROTOCOL_VERSION: u16 = 1;
+/* レベルの番号 (今はレベルが 1 つしかない) */
+const LEVEL: u16 = 0;
+/* 位置を送る間隔 (ティック) 。残りのティックは生きていることだけ知らせる */
+const SEND_INTERVAL: u32 = 6;
+/* これだけ何も届かなければ打ち切る */
+const SILENT_TICKS: u32 = 90;
+/* ゴーストを速度で補って動かすのはここまで。それ以上は止めて待つ */
+const MAX_EXTRAPOLATE: u32 = 30;
+/* 勝負がついた後、自分のタイムを送り続けるティック数 */
+const LINGER_TICKS: u16 = 60;
+/* ゴールしなかったときのタイム */
+pub const DNF: u32 = 0xff_ffff;
+
+/* 値の種類 (上位 4 ビット) 。0xf はつながっていないときの 0xffff と重なるので使わない */
+const TAG_IDLE: u16 = 0;
+const TAG_VERSION: u16 = 1;
+const TAG_LEVEL: u16 = 2;
+const TAG_SEED: u16 = 3; /* 3, 4, 5 の 3 つ */
+const TAG_X: u16 = 6;
+const TAG_Y: u16 = 7;
+const TAG_TIME_LO: u16 = 8;
+const TAG_TIME_HI: u16 = 9;
+
+fn word(tag: u16, payload: u32) -> u16 {
+    tag << 12 | (payload & 0xfff) as u16
+}
+
+// 12 ビットの符号付き整数に戻す。
+fn signed(payload: u16) -> i32 {
+    ((payload as i32) << 20) >> 20
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Handshake {
+    Waiting,
+    // ケーブルが抜けているか、相手の電源が入っていない。
+    NoCable,
+    // 版かレベルが相手と違う。
+    Mismatch,
+    // 2 人とも seed で遊ぶ。
+    Ready { seed: u32 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Won,
+    Lost,
+    Draw,
+    // 途中でケーブルが抜けた。
+    LinkLost,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RaceResult {
+    pub verdict: Verdict,
+    /* 自分と相手のタイム。ゴールしなかったら DNF 、相手のものが届かなかったら None */
+    pub time: u32,
+    pub rival_time: Option<u32>,
+}
+
+/* 握手で受け取った途中のもの */
+#[derive(Default)]
+struct Hello {
+    version: Option<u16>,
+    level: Option<u16>,
+    seed: [Option<u16>; 3],
+}
+
+/* 相手の位置を受け取ったときのもの */
+#[derive(Clone, Copy)]
+struct Sample {
+    x: i32,
+    y: i32,
+    tick: u32,
+}
+
+pub struct Race {
+    link: Box<dyn Link>,
+    seed: u32,
+    ready: bool,
+    mismatch: bool,
+    hello: Hello,
+    /* 送った値の数。握手で送るものを順に回す */
+    sent: u32,
+    /* update した回数 */
+    ticks: u32,
+    silent: u32,
+    /* 相手の位置 (x だけ届いて y を待っているもの、最後の位置、1 ティックあたりの速度) */
+    rival_x: Option<i32>,
+    rival: Option<Sample>,
+    velocity: (Fixed, Fixed),
+    /* タイム (下位を受け取って上位を待っているもの、確定したもの) */
+    rival_time_lo: Option<u16>,
+    rival_time: Option<u32>,
+    time: Option<u32>,
+    /* 両方のタイムが揃ってから結果を返すまでの残り。揃ってから数え始める */
+    linger: Timer,
+}
+
+impl Race {
+    // seed は子機になったときには親機のものに置き換わる。
+    pub fn new(link: Box<dyn Link>, seed: u32) -> Self {
+        Self {
+            link,
+            seed,
+            ready: false,
+            mismatch: false,
+            hello: Hello::default(),
+            sent: 0,
+            ticks: 0,
+            silent: 0,
+            rival_x: None,
+            rival: None,
+            velocity: (Fixed::new(0), Fixed::new(0)),
+            rival_time_lo: None,
+            rival_time: None,
+            time: None,
+            linger: Timer::started(LINGER_TICKS),
+        }
+    }
+
+    // 対戦相手を待つ画面で毎ティック呼ぶ。
+    pub fn handshake(&mut self) -> Handshake {
+        let word = self.hello_word();
+        match self.exchange(word) {
+            Err(LinkError::Disconnected) => Handshake::NoCable,
+            Ok(_) if self.mismatch => Handshake::Mismatch,
+            Ok(_) if self.ready => Handshake::Ready { seed: self.seed },
+            Ok(_) => Handshake::Waiting,
+        }
+    }
+
+    // レース中、game を 1 ティック進めた後に呼ぶ。結果が出たら Some 。
+    pub fn update(&mut self, game: &Game) -> Option<RaceResult> {
+        self.ticks += 1;
+        if self.time.is_none() {
+            self.time = match game.outcome() {
+                Some(Outcome::Cleared) => Some(game.time.min(DNF - 1)),
+                Some(Outcome::Died) => Some(DNF),
+                /* 相手がゴールしたら負け、相手が脱落したら勝ちで確定 */
+                None => self.rival_time.map(|rival| match rival {
+                    DNF => game.time.min(DNF - 1),
+                    _ => DNF,
+                }),
+            };
+            if let Some(time) = self.time {
+                log_info!("race finished in {} ticks", time);
+            }
+        }
+
+        let word = if game.countdown.is_running() {
+            /* 相手がまだ握手を終えていないかもしれない */
+            self.hello_word()
+        } else if let Some(time) = self.time {
+            match self.ticks % 2 {
+                0 => word(TAG_TIME_LO, time),
+                _ => word(TAG_TIME_HI, time >> 12),
+            }
+        } else {
+            let droid = &game.droid;
+            match self.ticks % SEND_INTERVAL {
+                0 => word(TAG_X, droid.x as u32),
+                1 => word(TAG_Y, droid.y as u32),
+                _ => word(TAG_IDLE, 0),
+            }
+        };
+        /* 抜けたかどうかは、何も届かない時間の長さで決める */
+        let _ = self.exchange(word);
+        if self.silent >= SILENT_TICKS {
+            log_warn!("link lost");
+            return Some(RaceResult {
+                verdict: Verdict::LinkLost,
+                time: self.time.unwrap_or(DNF),
+                rival_time: self.rival_time,
+            });
+        }
+
+        let (time, rival_time) = (self.time?, self.rival_time?);
+        self.linger.tick();
+        if self.linger.is_running() {
+            return None;
+        }
+        let verdict = match time.cmp(&rival_time) {
+            Ordering::Less => Verdict::Won,
+            Ordering::Greater => Verdict::Lost,
+            Ordering::Equal => Verdict::Draw,
+        };
+        Some(RaceResult {
+            verdict,
+            time,
+            rival_time: Some(rival_time),
+        })
+    }
+
+    // 自分の分は終わったか。終わったらゲームは止めて結果を待つ。
+    pub fn finished(&self) -> bool {
+        self.time.is_some()
+    }
+
+    // 相手のドロイド君を描く位置。前のティックの見積もりから今の見積もりへ alpha だけ寄せる (pacing::between)。
+    // まだ何も届いていなければ None 。
+    pub fn ghost(&self, alpha: u32) -> Option<(i32, i32)> {
+        Some(pacing::between(self.estimate(1)?, self.estimate(0)?, alpha))
+    }
+
+    // behind ティック前の、相手のドロイド君の位置の見積もり。
+    fn estimate(&self, behind: u32) -> Option<(i32, i32)> {
+        let rival = self.rival?;
+        let elapsed = self.ticks.wrapping_sub(rival.tick).saturating_sub(behind);
+        let elapsed = Fixed::new(elapsed.min(MAX_EXTRAPOLATE) as i32);
+        let x = rival.x + (self.velocity.0 * elapsed).floor();
+        let y = rival.y + (self.velocity.1 * elapsed).floor();
+        Some((game::wrap_x(x), y.min(GROUND_Y)))
+    }
+
+    fn hello_word(&self) -> u16 {
+        let seed = self.seed;
+        match self.sent % 5 {
+            0 => word(TAG_VERSION, PROTOCOL_VERSION as u32),
+            1 => word(TAG_LEVEL, LEVEL as u32),
+            part => word(TAG_SEED + part as u16 - 2, seed >> (12 * (part - 2))),
+        }
+    }
+
+    // word を送り、届いたものを読む。
+    fn exchange(&mut self, word: u16) -> Result<(), LinkError> {
+        self.sent = self.sent.wrapping_add(1);
+        let received = match self.link.exchange(word) {
+            Ok(received) => received,
+            Err(error) => {
+                self.silent += 1;
+                return Err(error);
+            }
+        };
+        let Some(received) = received else {
+            self.silent += 1;
+            return Ok(());
+        };
+        self.silent = 0;
+        let (tag, payload) = (received >> 12, received & 0xfff);
+        match tag {
+            TAG_VERSION => self.hello.version = Some(payload),
+            TAG_LEVEL => self.hello.level = Some(payload),
+            TAG_SEED..=5 => {
+                self.hello.seed[(tag - TAG_SEED) as usize] = Some(payload);
+                self.receive_hello();
+            }
+            TAG_X => self.rival_x = Some(signed(payload)),
+            TAG_Y => {
+                if let Some(x) = self.rival_x.take() {
+                    self.receive_position(x, signed(payload));
+                }
+            }
+            TAG_TIME_LO => self.rival_time_lo = Some(payload),
+            TAG_TIME_HI => {
+                if let (Some(lo), None) = (self.rival_time_lo, self.rival_time) {
+                    let time = (payload as u32) << 12 | lo as u32;
+                    log_info!("rival finished in {} ticks", time);
+                    self.rival_time = Some(time);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // 握手の値が揃っていれば確かめる。
+    fn receive_hello(&mut self) {
+        let Hello {
+            version: Some(version),
+            level: Some(level),
+            seed: [Some(seed0), Some(seed1), Some(seed2)],
+        } = self.hello
+        else {
+            return;
+        };
+        self.hello = Hello::default();
+        if self.ready {
+            return;
+        }
+        if (version, level) != (PROTOCOL_VERSION, LEVEL) {
+            log_warn!("link partner is on version {} level {}", version, level);
+            self.mismatch = true;
+            return;
+        }
+        let seed = seed0 as u32 | (seed1 as u32) << 12 | (seed2 as u32) << 24;
+        if !self.link.is_master() {
+            self.seed = seed;
+        }
+        if seed == self.seed {
+            log_info!("link ready, seed {}", seed);
+            self.ready = true;
+        }
+    }
+
+    fn receive_position(&mut self, x: i32, y: i32) {
+        let sample = Sample {
+            x,
+            y,
+            tick: self.ticks,
+        };
+        if let Some(last) = self.rival {
+            let elapsed = sample.tick.wrapping_sub(last.tick).max(1) as i32;
+            /* 画面端で回り込んだときは近い方へ動いたことにする */
+            let span = agb::display::WIDTH + game::DROID_WIDTH;
+            let mut dx = x - last.x;
+            if dx > span / 2 {
+                dx -= span;
+            } else if dx < -span / 2 {
+                dx += span;
+            }
+            self.velocity = (Fixed::new(dx) / elapsed, Fixed::new(y - last.y) / elapsed);
+        }
+        self.rival = Some(sample);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{difficulty::Difficulty, link::Loopback};
+
+    // 握手を済ませた親機と子機、それとケーブル。
+    fn connected() -> (Race, Race, Loopback) {
+        let (master, child) = Loopback::pair();
+        let cable = master.clone();
+        let mut master = Race::new(Box::new(master), 111);
+        let mut child = Race::new(Box::new(child), 222);
+        for _ in 0..20 {
+            master.handshake();
+            child.handshake();
+        }
+        assert_eq!(master.handshake(), Handshake::Ready { seed: 111 });
+        assert_eq!(child.handshake(), Handshake::Ready { seed: 111 });
+        (master, child, cable)
+    }
+
+    /* カウントダウンの無いモードで作る */
+    fn racer() -> Game {
+        Game::new(Difficulty::Normal, 111)
+    }
+
+    #[test_case]
+    fn the_ghost_is_extrapolated_through_dropped_words(_gba: &mut agb::Gba) {
+        let (mut master, mut child, cable) = connected();
+        let mut game = racer();
+        let idle = racer();
+        /* 親機のドロイド君は右へ 1 ティック 1px で歩く */
+        let mut run = |ticks: core::ops::Range<i32>, game: &mut Game, child: &mut Race| {
+            for tick in ticks {
+                game.droid.x = 40 + tick;
+                master.update(game);
+                child.update(&idle);
+            }
+        };
+        run(0..60, &mut game, &mut child);
+        let (x, y) = child.estimate(0).unwrap();
+        assert!((x - game.droid.x).abs() <= 1, "{} vs {}", x, game.droid.x);
+        assert_eq!(y, game.droid.y);
+        /* 何も届かなくても、速度で補って付いてくる */
+        cable.set_dropping(true);
+        run(60..80, &mut game, &mut child);
+        let (x, _) = child.estimate(0).unwrap();
+        assert!((x - game.droid.x).abs() <= 1, "{} vs {}", x, game.droid.x);
+    }
+
+    // 先にゴールした方が、両方の画面で勝ちになる。
+    #[test_case]
+    fn the_first_to_finish_wins_on_both_screens(_gba: &mut agb::Gba) {
+        let (mut master, mut child, _cable) = connected();
+        let mut winner = racer();
+        winner.coins.retain(|_| false);
+        winner.time = 500;
+        let loser = racer();
+        let (mut won, mut lost) = (None, None);
+        for _ in 0..LINGER_TICKS + 20 {
+            won = won.or(master.update(&winner));
+            lost = lost.or(child.update(&loser));
+        }
+        assert!(child.finished());
+        assert_eq!(
+            won,
+            Some(RaceResult {
+                verdict: Verdict::Won,
+                time: 500,
+                rival_time: Some(DNF),
+            })
+        );
+        assert_eq!(
+            lost,
+            Some(RaceResult {
+                verdict: Verdict::Lost,
+                time: DNF,
+                rival_time: Some(500),
+            })
+        );
+    }
+
+    #[test_case]
+    fn an_unplugged_cable_ends_the_race(_gba: &mut agb::Gba) {
+        let (mut master, _child, cable) = connected();
+        let game = racer();
+        cable.unplug();
+        for _ in 1..SILENT_TICKS {
+            assert_eq!(master.update(&game), None);
+        }
+        let result = master.update(&game).unwrap();
+        assert_eq!(result.verdict, Verdict::LinkLost);
+    }
+}
@@ -0,0 +1,316 @@
+// 入力の記録と再生。
+//
+// ゲームロジックは開始時の難易度・モード・シードと、ティックごとの入力だけで決まる。
+// なので入力列さえ残しておけば、同じ展開を何度でも再現できる
+// (ゴースト、デモ、デイリーシードはこれを前提にする)。
+// Game::update の中で浮動小数点の扱いを変えたり、シードを通さない乱数を使ったりすると
+// この前提が崩れるので、determinism テストで必ず確かめること。
+//
+// ボタンは押しっぱなしが多いので、(ボタン, 続いたティック数) のランレングスで持つ。
+// HARD モードの修飾も展開を変えるので、難易度やシードと一緒に先頭に入れておく。
+//
+// 保存するときのバイト列 (リトルエンディアン):
+//   0     難易度
+//   1     モード (Mode::index)
+//   2     修飾 (Modifiers::bits)
+//   3..7  シード
+//   7..9  ランの数
+//   9..   (ボタン u16, ティック数 u16) x ランの数
+
+use alloc::vec::Vec;
+
+use agb::input::Button;
+
+use crate::{difficulty::Difficulty, game::Game, input::Input, mode::Mode, modifiers::Modifiers};
+
+const HEADER_LEN: usize = 9;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Replay {
+    pub difficulty: Difficulty,
+    pub mode: Mode,
+    pub modifiers: Modifiers,
+    pub seed: u32,
+    /* (押されていたボタン, 続いたティック数) */
+    runs: Vec<(u16, u16)>,
+}
+
+impl Replay {
+    pub fn new(difficulty: Difficulty, mode: Mode, modifiers: Modifiers, seed: u32) -> Self {
+        Self {
+            difficulty,
+            mode,
+            modifiers,
+            seed,
+            runs: Vec::new(),
+        }
+    }
+
+    // 記録の始まりと同じ状態の Game 。
+    pub fn start(&self) -> Game {
+        Game::new(self.difficulty, self.seed)
+            .with_mode(self.mode)
+            .with_modifiers(self.modifiers)
+    }
+
+    // 1 ティック分の入力を記録し、再生したときに渡すのと同じ入力を返す。
+    // ポーズを挟むと「前のティックに押されていたボタン」が記録とずれることがあるので、
+    // 記録しながら遊ぶときは、返した方の入力で Game を進めること。
+    pub fn record(&mut self, input: &Input) -> Input {
+        let previous = self.runs.last().map_or(0, |&(buttons, _)| buttons);
+        let buttons = input.buttons().bits() as u16;
+        match self.runs.last_mut() {
+            Some((last, length)) if *last == buttons && *length < u16::MAX => *length += 1,
+            _ => self.runs.push((buttons, 1)),
+        }
+        Input::new(button(buttons), button(previous))
+    }
+
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn ticks(&self) -> u32 {
+        self.runs.iter().map(|&(_, length)| u32::from(length)).sum()
+    }
+
+    // 保存するときの大きさ。
+    pub fn encoded_len(&self) -> usize {
+        HEADER_LEN + 4 * self.runs.len()
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        bytes.push(self.difficulty as u8);
+        bytes.push(self.mode.index());
+        bytes.push(self.modifiers.bits());
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&(self.runs.len() as u16).to_le_bytes());
+        for &(buttons, length) in &self.runs {
+            bytes.extend_from_slice(&buttons.to_le_bytes());
+            bytes.extend_from_slice(&length.to_le_bytes());
+        }
+        bytes
+    }
+
+    // encode したバイト列から読む。長さや値がおかしければ None 。
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let header = bytes.get(..HEADER_LEN)?;
+        let count = usize::from(u16::from_le_bytes([header[7], header[8]]));
+        let body = bytes.get(HEADER_LEN..HEADER_LEN + 4 * count)?;
+        Some(Self {
+            difficulty: *Difficulty::ALL.get(usize::from(header[0]))?,
+            mode: Mode::from_index(header[1])?,
+            modifiers: Modifiers::from_bits(header[2]),
+            seed: u32::from_le_bytes(header[3..7].try_into().ok()?),
+            runs: body
+                .chunks_exact(4)
+                .map(|run| {
+                    (
+                        u16::from_le_bytes([run[0], run[1]]),
+                        u16::from_le_bytes([run[2], run[3]]),
+                    )
+                })
+                .collect(),
+        })
+    }
+
+    // 記録した入力をティックの順に返す。
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn inputs(&self) -> impl Iterator<Item = Input> + '_ {
+        let mut previous = Button::empty();
+        self.runs
+            .iter()
+            .flat_map(|&(buttons, length)| (0..length).map(move |_| buttons))
+            .map(move |buttons| {
+                let current = button(buttons);
+                let input = Input::new(current, previous);
+                previous = current;
+                input
+            })
+    }
+}
+
+fn button(bits: u16) -> Button {
+    Button::from_bits_truncate(u32::from(bits))
+}
+
+// 記録した入力を 1 ティックずつ取り出す。inputs() と違って Replay を持ったまま進められる。
+pub struct Playback {
+    replay: Replay,
+    /* 次に読むランと、その中で使い終わったティック数 */
+    run: usize,
+    used: u16,
+    previous: u16,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            run: 0,
+            used: 0,
+            previous: 0,
+        }
+    }
+}
+
+impl Iterator for Playback {
+    type Item = Input;
+
+    fn next(&mut self) -> Option<Input> {
+        let &(buttons, length) = self.replay.runs.get(self.run)?;
+        self.used += 1;
+        if self.used == length {
+            self.run += 1;
+            self.used = 0;
+        }
+        let input = Input::new(button(buttons), button(self.previous));
+        self.previous = buttons;
+        Some(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+
+    use super::*;
+    use crate::{
+        collision::{self, Surface},
+        game::{COIN_SIZE, DROID_WIDTH, GROUND_Y},
+        level::Level,
+        spawn::EntityKind,
+        spike::SPIKE_SIZE,
+    };
+
+    /* 65 秒分 */
+    const TICKS: u32 = 65 * 60;
+    /* 出発点の足元から 24 ドット上って戻るエレベーター */
+    const ELEVATOR: (i32, i32) = (120, GROUND_Y + DROID_WIDTH);
+    const ELEVATOR_TO_Y: i32 = ELEVATOR.1 - 24;
+    /* 乗ったまま上って下りきるまで (上りと下りに 48 ティックずつ、上で 60 ティック止まる) */
+    const RIDE_TICKS: u32 = 160;
+    /* 降りて左の地面のコインと棘まで歩く分 */
+    const PRELUDE_TICKS: u32 = RIDE_TICKS + 60;
+
+    // レベルに、出発点の足元のエレベーターと、出発点の左の地面のコインと棘を足したもの。
+    fn level() -> Level {
+        let mut level = Level::load(false);
+        level.platforms.push((
+            EntityKind::Elevator {
+                to_y: ELEVATOR_TO_Y,
+                wait: 60,
+            },
+            ELEVATOR,
+        ));
+        level
+            .objects
+            .push((EntityKind::Coin, (104, GROUND_Y + DROID_WIDTH - COIN_SIZE)));
+        level
+            .objects
+            .push((EntityKind::Spike, (96, GROUND_Y + DROID_WIDTH - SPIKE_SIZE)));
+        level
+    }
+
+    // エレベーターに乗って上り下りし、降りて左のコインを取って棘に触れてから、
+    // 歩く、跳ぶ、二段ジャンプ、りんごに乗る、コインを取る、を繰り返す台本。
+    fn scripted(tick: u32) -> Button {
+        if tick < RIDE_TICKS {
+            return Button::empty();
+        }
+        if tick < PRELUDE_TICKS {
+            return Button::LEFT;
+        }
+        let phase = (tick - PRELUDE_TICKS) % 600;
+        match phase {
+            /* 右へ歩いてりんごに跳び乗り、そのまま反対側へ降りる */
+            0..=19 => Button::RIGHT,
+            20..=59 => Button::RIGHT | Button::UP,
+            /* しばらく待機 */
+            60..=119 => Button::empty(),
+            /* 左へ歩いて戻る */
+            120..=199 => Button::LEFT,
+            /* 二段ジャンプ */
+            200..=201 => Button::UP,
+            210..=211 => Button::UP,
+            /* 左のコインの方へ戻りながら跳ぶ */
+            240..=299 => Button::LEFT,
+            300..=330 => Button::LEFT | Button::UP,
+            /* 画面端を越えて一周する */
+            331..=599 if tick % 7 == 0 => Button::RIGHT | Button::UP,
+            331..=599 => Button::RIGHT,
+            _ => Button::empty(),
+        }
+    }
+
+    // replay の記録を始めたときの Game に、level() を読み込んだもの。with_mode などで受け渡すたびに
+    // Game がフレームに並ぶので、テストや play のフレームに置かないよう分けてヒープに作る (ghost のテストと同じ)。
+    #[inline(never)]
+    fn started(replay: &Replay) -> Box<Game> {
+        let mut game = Box::new(replay.start());
+        game.load(&level());
+        game
+    }
+
+    // 動く足場のどれかに立っている。
+    fn riding(game: &Game) -> bool {
+        let droid = &game.droid;
+        droid.is_grounded()
+            && game.moving_platforms.values().any(|platform| {
+                droid.y == platform.top_y() && collision::supports(platform, droid.x)
+            })
+    }
+
+    // 台本どおりに遊んで、入力を記録しながら最後の状態を返す。
+    // 遊んでいるときと同じく Game はヒープに置く (テストのスタックに何個も並べると IWRAM のスタックが溢れる)。
+    fn play(seed: u32) -> (Box<Game>, Replay) {
+        let mut replay = Replay::new(Difficulty::Normal, Mode::Normal, Modifiers::NONE, seed);
+        let mut game = started(&replay);
+        let lives = game.health;
+        let mut previous = Button::empty();
+        let mut carried = false;
+        for tick in 0..TICKS {
+            let current = scripted(tick);
+            let input = Input::new(current, previous);
+            previous = current;
+            replay.record(&input);
+            let (y, was_riding) = (game.droid.y, riding(&game));
+            game.update(&input);
+            /* 乗ったまま、足場と一緒に上下した */
+            carried |= was_riding && riding(&game) && game.droid.y != y;
+        }
+        /* 台本が見たいものを一通り通っていること */
+        assert!(carried, "never carried by a moving platform");
+        assert!(game.health < lives, "never damaged");
+        assert!(game.score > 0, "collected no coins");
+        (game, replay)
+    }
+
+    #[test_case]
+    fn a_run_replays_bit_for_bit(_gba: &mut agb::Gba) {
+        let (first, replay) = play(12345);
+        let (second, _) = play(12345);
+        assert_eq!(first, second);
+
+        assert_eq!(replay.ticks(), TICKS);
+        let mut replayed = started(&replay);
+        for input in replay.inputs() {
+            replayed.update(&input);
+        }
+        assert_eq!(replayed, first);
+        assert_eq!(replayed.rng.state(), first.rng.state());
+        assert_eq!(replayed.tick, TICKS);
+    }
+
+    // 保存して読み直しても、1 ティックずつ取り出しても同じ入力列になる。
+    #[test_case]
+    fn a_replay_survives_encoding(_gba: &mut agb::Gba) {
+        let (_, mut replay) = play(777);
+        replay.modifiers = Modifiers::HARD;
+        replay.mode = Mode::TimeAttack;
+        let bytes = replay.encode();
+        assert_eq!(bytes.len(), replay.encoded_len());
+        let decoded = Replay::decode(&bytes).unwrap();
+        assert_eq!(decoded, replay);
+        assert!(Replay::decode(&bytes[..bytes.len() - 1]).is_none());
+        assert!(Playback::new(decoded).eq(replay.inputs()));
+    }
+}
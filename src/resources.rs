@@ -0,0 +1,337 @@
+// スプライトの素材。
+//
+// Aseprite のタグは起動時に一度だけ引いて SpriteSet にまとめ、
+// エンティティや Presenter には参照で渡す。
+// 毎フレームタグ名で文字列検索をしないよう、使うフレームもここで取り出しておく。
+// 新しいタグを使うときは TAGS にも足すこと (起動時とテストで揃っているか確かめる)。
+//
+// 当たり判定の箱は、Aseprite の Hitbox スライスから build.rs が作った HITBOXES を引く (hitbox)。
+// 定数の中で引くのでタグ名の検索はビルド時に済み、無いタグはビルドで止まる。
+// ドロイド君はポーズごとの箱を droid_hitbox() で引く。
+//
+// スプライト VRAM への読み込みは、レベルを始めるときに preload() でまとめて済ませる。
+// 遊んでいる間に初めて使う絵を読み込むと、そのフレームだけ重くなるため。
+// 読み込んだ絵の参照 (SpriteVram) は Preloaded が持ち、持っている間は agb が片付けない。
+// なので遊んでいる間の絵の差し替えは表を引くだけで、読み込みは起きない。
+// 新しい絵を足したら frames() にも足すこと。
+
+use alloc::vec::Vec;
+
+use agb::display::object::{Graphics, Sprite, SpriteVram, Tag};
+
+use crate::{collision::Aabb, oam::ObjectPool};
+
+pub const GRAPHICS: &Graphics = agb::include_aseprite!("gfx/sprites.aseprite");
+
+/* 絵の当たり判定 (スプライトの左上からの x, y, 幅, 高さ) */
+type Rect = (i32, i32, i32, i32);
+include!(concat!(env!("OUT_DIR"), "/hitboxes.rs"));
+
+/* ゲームが使うタグと、そのうち何コマ目まで使うか */
+pub const TAGS: [(&str, usize); 17] = [
+    ("Idle", 1),
+    ("Walking", 3),
+    ("Jumping", 3),
+    ("Dash", 1),
+    ("Crouch", 1),
+    ("FastFall", 1),
+    ("Hang", 1),
+    ("ClimbUp", 1),
+    ("Pound", 1),
+    ("Glide", 1),
+    ("WallSlide", 1),
+    ("Apple", 1),
+    ("Window", 1),
+    ("Lift", 1),
+    ("Shockwave", 1),
+    ("Spring", 2),
+    ("Debris", 1),
+];
+
+/* スプライト VRAM の大きさ (4bpp のタイル 1024 枚) */
+pub const SPRITE_VRAM: usize = 32 * 1024;
+/* レベルの絵を読み込んだ後に空けておく分 (得点のポップアップの数字やパーティクル用) */
+pub const SPRITE_HEADROOM: usize = 8 * 1024;
+/* ドロイド君のポーズの数 (Droid::pose の 0, 2, .., 26) */
+const POSES: usize = 14;
+/* ポーズごとの絵 (タグとフレーム)。SpriteSet::droid と同じ並び */
+const POSE_FRAMES: [(&str, usize); POSES] = [
+    ("Idle", 0),
+    ("Walking", 0),
+    ("Walking", 2),
+    ("Jumping", 0),
+    ("Jumping", 1),
+    ("Jumping", 2),
+    ("Dash", 0),
+    ("Crouch", 0),
+    ("FastFall", 0),
+    ("Hang", 0),
+    ("ClimbUp", 0),
+    ("Pound", 0),
+    ("Glide", 0),
+    ("WallSlide", 0),
+];
+/* ポーズごとの当たり判定の箱 */
+const DROID_HITBOXES: [Aabb; POSES] = {
+    let mut hitboxes = [Aabb::new(0, 0, 0, 0); POSES];
+    let mut pose = 0;
+    while pose < POSES {
+        let (name, idx) = POSE_FRAMES[pose];
+        hitboxes[pose] = hitbox(name, idx);
+        pose += 1;
+    }
+    hitboxes
+};
+
+pub struct SpriteSet {
+    pub idle: &'static Sprite,
+    /* 歩きの 2 コマ */
+    pub walk: [&'static Sprite; 2],
+    /* ジャンプ準備、上昇、下降 */
+    pub jump: [&'static Sprite; 3],
+    pub dash: &'static Sprite,
+    /* しゃがみとスライディング */
+    pub crouch: &'static Sprite,
+    /* 急降下 */
+    pub fast_fall: &'static Sprite,
+    /* 足場の端にぶら下がる、よじ登る */
+    pub hang: &'static Sprite,
+    pub climb_up: &'static Sprite,
+    /* ヒップドロップ */
+    pub pound: &'static Sprite,
+    /* 滑空 */
+    pub glide: &'static Sprite,
+    /* 壁ずり (右の壁に手を着いている) */
+    pub wall_slide: &'static Sprite,
+    pub apple: &'static Sprite,
+    pub window: &'static Sprite,
+    /* 動く足場の半分 */
+    pub lift: &'static Sprite,
+    /* ヒップドロップの衝撃波 (右へ広がる方。左は反転して描く) */
+    pub shockwave: &'static Sprite,
+    /* ばね (伸びている、縮んでいる) */
+    pub spring: [&'static Sprite; 2],
+    /* 壊れたブロックの破片 */
+    pub debris: &'static Sprite,
+}
+
+impl SpriteSet {
+    // タグを全て引く。足りないタグがあればデバッグビルドではここで止める。
+    pub fn load() -> Self {
+        let missing: Vec<&str> = TAGS
+            .iter()
+            .map(|&(name, _)| name)
+            .filter(|name| GRAPHICS.tags().try_get(name).is_none())
+            .collect();
+        debug_assert!(missing.is_empty(), "missing sprite tags: {:?}", missing);
+        if !missing.is_empty() {
+            log_warn!("missing sprite tags: {:?}", missing);
+        }
+
+        let idle = tag("Idle");
+        let walking = tag("Walking");
+        let jumping = tag("Jumping");
+        Self {
+            idle: frame(idle, 0),
+            walk: [frame(walking, 0), frame(walking, 2)],
+            jump: [frame(jumping, 0), frame(jumping, 1), frame(jumping, 2)],
+            dash: frame(tag("Dash"), 0),
+            crouch: frame(tag("Crouch"), 0),
+            fast_fall: frame(tag("FastFall"), 0),
+            hang: frame(tag("Hang"), 0),
+            climb_up: frame(tag("ClimbUp"), 0),
+            pound: frame(tag("Pound"), 0),
+            glide: frame(tag("Glide"), 0),
+            wall_slide: frame(tag("WallSlide"), 0),
+            apple: frame(tag("Apple"), 0),
+            window: frame(tag("Window"), 0),
+            lift: frame(tag("Lift"), 0),
+            shockwave: frame(tag("Shockwave"), 0),
+            spring: [frame(tag("Spring"), 0), frame(tag("Spring"), 1)],
+            debris: frame(tag("Debris"), 0),
+        }
+    }
+
+    // 過去実装で OBJ_CHAR (ATTR2_ID) で表現していた部分の互換処理
+    pub fn droid(&self, ch: u16) -> &'static Sprite {
+        match pose(ch) {
+            1 => self.walk[0],
+            2 => self.walk[1],
+            3 => self.jump[0],
+            4 => self.jump[1],
+            5 => self.jump[2],
+            6 => self.dash,
+            7 => self.crouch,
+            8 => self.fast_fall,
+            9 => self.hang,
+            10 => self.climb_up,
+            11 => self.pound,
+            12 => self.glide,
+            13 => self.wall_slide,
+            _ => self.idle,
+        }
+    }
+
+    // 遊んでいる間に使う絵を全て。ドロイド君の全てのポーズ (ゴーストも同じ絵)、りんご、窓、動く足場、衝撃波。
+    // ばねは置いてあるレベルでしか使わないので入れない (読み込んだ絵が多いほど毎フレームの片付けが重くなる)。
+    // 伸びた絵はレベルを始めてばねを作るときに、縮んだ絵は初めて縮んだときに読み込む。
+    // ブロックの破片も同じく、初めてブロックを壊したときに読み込む。
+    pub fn frames(&self) -> [&'static Sprite; POSES + 4] {
+        let mut frames = [self.idle; POSES + 4];
+        for (index, frame) in frames[..POSES].iter_mut().enumerate() {
+            *frame = self.droid(2 * index as u16);
+        }
+        frames[POSES] = self.apple;
+        frames[POSES + 1] = self.window;
+        frames[POSES + 2] = self.lift;
+        frames[POSES + 3] = self.shockwave;
+        frames
+    }
+
+    // frames() を全てスプライト VRAM に読み込む。入りきらなければ None (その都度読み込むことになる)。
+    pub fn preload(&self, objects: &ObjectPool) -> Option<Preloaded> {
+        let mut handles = Vec::new();
+        for frame in self.frames() {
+            handles.push(objects.try_sprite(frame)?);
+        }
+        /* 同じ絵は 1 度しか読み込まれない */
+        let mut distinct: Vec<&'static Sprite> = self.frames().to_vec();
+        distinct.sort_by_key(|sprite| *sprite as *const Sprite);
+        distinct.dedup_by_key(|sprite| *sprite as *const Sprite);
+        let bytes = distinct.iter().map(|sprite| bytes(sprite)).sum();
+        log_info!("preloaded {} sprites ({} bytes)", distinct.len(), bytes);
+        if bytes + SPRITE_HEADROOM > SPRITE_VRAM {
+            log_warn!("only {} bytes of sprite VRAM left", SPRITE_VRAM - bytes);
+        }
+        Some(Preloaded { handles, bytes })
+    }
+}
+
+// スプライト VRAM に読み込んだ、レベルの絵 (SpriteSet::frames の順)。
+pub struct Preloaded {
+    handles: Vec<SpriteVram>,
+    /* 使っているスプライト VRAM の大きさ */
+    bytes: usize,
+}
+
+impl Preloaded {
+    // ポーズ ch のドロイド君の絵。
+    pub fn droid(&self, ch: u16) -> SpriteVram {
+        self.handles[pose(ch)].clone()
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+}
+
+// Droid::pose の値 (0, 2, .., 26) を 0 から数えた番号に。知らない値は待機の絵。
+fn pose(ch: u16) -> usize {
+    let pose = usize::from(ch / 2);
+    if ch % 2 == 0 && pose < POSES {
+        pose
+    } else {
+        0
+    }
+}
+
+// タグ name の idx コマ目の当たり判定の箱 (スプライトの左上から)。無ければ止める。
+pub const fn hitbox(name: &str, idx: usize) -> Aabb {
+    let mut tag = 0;
+    while tag < HITBOXES.len() {
+        let (tag_name, frames) = HITBOXES[tag];
+        if same_name(tag_name, name) {
+            let (x, y, width, height) = frames[idx];
+            return Aabb::new(x, y, width, height);
+        }
+        tag += 1;
+    }
+    panic!("no hitbox for the tag");
+}
+
+// ポーズ ch (Droid::pose) のドロイド君の当たり判定の箱 (スプライトの左上から)。
+pub fn droid_hitbox(ch: u16) -> Aabb {
+    DROID_HITBOXES[pose(ch)]
+}
+
+const fn same_name(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+// 絵 1 枚が使うスプライト VRAM の大きさ (4bpp で 1 タイル 32 バイト)。
+fn bytes(sprite: &Sprite) -> usize {
+    let (width, height) = sprite.size().to_tiles_width_height();
+    width * height * 32
+}
+
+fn tag(name: &str) -> Option<&'static Tag> {
+    GRAPHICS.tags().try_get(name)
+}
+
+// タグが無い、またはフレームが足りないときは先頭のスプライトで代用する。
+fn frame(tag: Option<&'static Tag>, idx: usize) -> &'static Sprite {
+    tag.and_then(|tag| tag.sprites().get(idx))
+        .unwrap_or(&GRAPHICS.sprites()[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // レベルの絵は全てスプライト VRAM に入り、ポップアップやパーティクルの分が残る。
+    #[test_case]
+    fn a_level_fits_in_sprite_vram(gba: &mut agb::Gba) {
+        let oam = gba.display.object.get_managed();
+        let objects = ObjectPool::new(&oam);
+        let sprites = SpriteSet::load();
+        let preloaded = sprites.preload(&objects).expect("the level does not fit");
+        agb::println!("sprite vram: {} / {} bytes", preloaded.bytes(), SPRITE_VRAM);
+        /* ドロイド君だけでも 1 枚より多い */
+        assert!(preloaded.bytes() > bytes(sprites.idle));
+        assert!(preloaded.bytes() + SPRITE_HEADROOM <= SPRITE_VRAM);
+    }
+
+    // ポーズの箱は SpriteSet::droid と同じ絵のもので、しゃがむと下の半分になる。
+    #[test_case]
+    fn every_pose_has_the_hitbox_of_its_frame(_gba: &mut agb::Gba) {
+        let sprites = SpriteSet::load();
+        for (pose, (name, idx)) in POSE_FRAMES.into_iter().enumerate() {
+            let ch = 2 * pose as u16;
+            assert!(
+                core::ptr::eq(sprites.droid(ch), frame(tag(name), idx)),
+                "{name}"
+            );
+            assert_eq!(droid_hitbox(ch), hitbox(name, idx));
+        }
+        assert_eq!(droid_hitbox(0), Aabb::new(0, 0, 16, 16));
+        assert_eq!(droid_hitbox(14), Aabb::new(0, 8, 16, 8));
+        /* りんごは上の 3 ドットが空いている */
+        assert_eq!(hitbox("Apple", 0), Aabb::new(0, 3, 16, 13));
+        /* 表の並びは使うタグと同じ */
+        for (name, frames) in TAGS {
+            assert!(HITBOXES
+                .iter()
+                .any(|&(tag, boxes)| tag == name && boxes.len() >= frames));
+        }
+    }
+
+    #[test_case]
+    fn the_sheet_has_every_tag_the_game_uses(_gba: &mut agb::Gba) {
+        for (name, frames) in TAGS {
+            let tag = tag(name).unwrap_or_else(|| panic!("no tag {name}"));
+            /* 使うフレームが揃っていること */
+            assert!(tag.sprites().len() >= frames, "{name} is too short");
+        }
+    }
+}
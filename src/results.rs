@@ -0,0 +1,257 @@
+// 結果画面のシーン。ゲーム本編が終わったときに入れ替わりで表示し、START でタイトルへ。
+// 表示する内容はモードで変わる
+// (普通のモードは得点、タイムアタックはタイムとメダル、エンドレスは高さと得点、
+// 交代で遊ぶモードは 2 人分の得点、通信対戦は勝ち負けと 2 人のタイム)。
+// エンドレスはシードも出すので、同じ塔で遊びたい人に教えられる。
+// スピードランは通しのタイムとレベルごとの区切りを、最高記録との差と一緒に出す。
+// コインラッシュは取ったコインの点数と、全て取ったときの倍率を掛けた得点を出す。
+
+use alloc::boxed::Box;
+
+use agb::{display::Priority, input::Button};
+
+use crate::{
+    coin_rush::FINISH_BONUS,
+    difficulty::Difficulty,
+    game::{Outcome, COIN_SCORE},
+    gfx::rgb5,
+    input::Input,
+    layer::Layer,
+    mode::Mode,
+    race::{RaceResult, Verdict, DNF},
+    scene::{Resources, Scene, Transition},
+    seed_entry::SeedDisplay,
+    speedrun::SEGMENTS,
+    split_delta,
+    text::TextLayer,
+    time_attack::{Medal, Par, TimeDisplay},
+    title::TitleScene,
+};
+
+// 終わったランの結果。記録の更新は GameScene が済ませてから渡す。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Results {
+    pub mode: Mode,
+    pub difficulty: Difficulty,
+    pub outcome: Outcome,
+    pub score: u32,
+    /* エンドレスの最高到達点 (m) */
+    pub height: u32,
+    pub time: u32,
+    pub medal: Option<Medal>,
+    pub new_best: bool,
+    /* 交代で遊ぶモードのプレイヤーごとの結果 */
+    pub players: Option<[PlayerResult; 2]>,
+    /* 通信対戦の結果 */
+    pub race: Option<RaceResult>,
+    /* HARD モードで遊んだか */
+    pub hard: bool,
+    /* タイムアタックの目標タイム (HARD モードでは厳しくなる) */
+    pub par: Par,
+    /* ランのシード (エンドレスでは教え合えるよう表示する) */
+    pub seed: u32,
+    /* ミラーモードで遊んだか (最高タイムは別に記録される) */
+    pub mirror: bool,
+    /* スピードランの区切り (通しのタイム) と、比べた最高記録の区切り */
+    pub splits: Option<[u32; SEGMENTS]>,
+    pub comparison: Option<[u32; SEGMENTS]>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PlayerResult {
+    pub score: u32,
+    pub outcome: Outcome,
+    pub new_best: bool,
+}
+
+pub struct ResultsScene<'g> {
+    results: Results,
+    text: Option<TextLayer<'g>>,
+}
+
+impl ResultsScene<'_> {
+    pub fn new(results: Results) -> Self {
+        Self {
+            results,
+            text: None,
+        }
+    }
+}
+
+impl<'g> Scene<'g> for ResultsScene<'g> {
+    fn enter(&mut self, res: &mut Resources<'g>) {
+        res.vram.set_background_palette_colour(0, 0, rgb5(2, 2, 8));
+        let mut text = TextLayer::new(Layer::new(res.gfx, Priority::P0), &mut res.vram);
+        let vram = &mut res.vram;
+        let results = &self.results;
+        let heading = match (results.mode, results.outcome) {
+            /* 練習は終わらないので、ここへは来ない */
+            (Mode::Practice, _) => "        PRACTICE",
+            (Mode::Tutorial, _) => "        TUTORIAL",
+            (Mode::LinkRace, _) => match results.race.map(|race| race.verdict) {
+                Some(Verdict::Won) => "        YOU WIN!",
+                Some(Verdict::Lost) => "        YOU LOSE",
+                Some(Verdict::Draw) => "          DRAW",
+                Some(Verdict::LinkLost) | None => "       LINK LOST",
+            },
+            (Mode::HotSeat, _) => match results.players {
+                Some([first, second]) if first.score > second.score => "     PLAYER 1 WINS!",
+                Some([first, second]) if first.score < second.score => "     PLAYER 2 WINS!",
+                _ => "          DRAW",
+            },
+            /* コインラッシュは時間切れでしか Died にならない */
+            (Mode::CoinRush, Outcome::Died) => "        TIME UP!",
+            (_, Outcome::Died) => "       GAME OVER",
+            /* エンドレスは落ちるまで終わらないので CLEAR! にはならない */
+            (Mode::Normal | Mode::Endless | Mode::CoinRush, Outcome::Cleared) => "         CLEAR!",
+            (Mode::TimeAttack | Mode::GhostRace | Mode::Speedrun, Outcome::Cleared) => {
+                "        FINISH!"
+            }
+        };
+        text.print_line(vram, 4, format_args!("{}", heading));
+        match (results.hard, results.mirror) {
+            (true, true) => text.print_line(vram, 5, format_args!("     - HARD - MIRROR -")),
+            (true, false) => text.print_line(vram, 5, format_args!("        - HARD MODE -")),
+            (false, true) => text.print_line(vram, 5, format_args!("       - MIRROR MODE -")),
+            (false, false) => {}
+        }
+        match results.mode {
+            Mode::Practice | Mode::Tutorial => {}
+            Mode::LinkRace => {
+                let race = results.race;
+                let times = [
+                    ("YOU", race.map(|race| race.time)),
+                    ("RIVAL", race.and_then(|race| race.rival_time)),
+                ];
+                for (row, (name, time)) in times.into_iter().enumerate() {
+                    let row = 7 + 2 * row as u16;
+                    match time {
+                        Some(time) if time != DNF => text.print_line(
+                            vram,
+                            row,
+                            format_args!("     {:6} {}", name, TimeDisplay(time)),
+                        ),
+                        _ => text.print_line(vram, row, format_args!("     {:6} -:--.--", name)),
+                    }
+                }
+            }
+            Mode::HotSeat => {
+                for (index, player) in results.players.iter().flatten().enumerate() {
+                    let outcome = match player.outcome {
+                        Outcome::Cleared => "CLEAR",
+                        Outcome::Died => "OUT",
+                    };
+                    let record = if player.new_best { "NEW!" } else { "" };
+                    text.print_line(
+                        vram,
+                        7 + 2 * index as u16,
+                        format_args!(
+                            "   P{}  SCORE {:5}  {:5} {}",
+                            index + 1,
+                            player.score,
+                            outcome,
+                            record
+                        ),
+                    );
+                }
+            }
+            Mode::Normal => {
+                text.print_line(vram, 8, format_args!("     SCORE    {:5}", results.score));
+            }
+            Mode::CoinRush => {
+                let bonus = match results.outcome {
+                    Outcome::Cleared => FINISH_BONUS,
+                    Outcome::Died => 1,
+                };
+                let coins = results.score / COIN_SCORE / bonus;
+                text.print_line(vram, 7, format_args!("     COINS    {:5}", coins));
+                if bonus > 1 {
+                    text.print_line(vram, 9, format_args!("     ALL COINS   X{}", bonus));
+                }
+                text.print_line(vram, 11, format_args!("     SCORE    {:5}", results.score));
+            }
+            Mode::Endless => {
+                text.print_line(vram, 7, format_args!("     HEIGHT   {:4}M", results.height));
+                text.print_line(vram, 9, format_args!("     SCORE    {:5}", results.score));
+                text.print_line(
+                    vram,
+                    11,
+                    format_args!("     SEED     {}", SeedDisplay(results.seed)),
+                );
+            }
+            Mode::Speedrun => {
+                text.print_line(
+                    vram,
+                    7,
+                    format_args!("     TIME     {}", TimeDisplay(results.time)),
+                );
+                /* レベルごとに、そのレベルのタイム、通しのタイム、最高記録との差 */
+                let splits = results.splits.unwrap_or_default();
+                for (segment, &total) in splits.iter().enumerate() {
+                    let row = 9 + 2 * segment as u16;
+                    let start = segment.checked_sub(1).map_or(0, |before| splits[before]);
+                    text.print_line(
+                        vram,
+                        row,
+                        format_args!(
+                            " LV{} {}  {}",
+                            segment + 1,
+                            TimeDisplay(total - start),
+                            TimeDisplay(total)
+                        ),
+                    );
+                    if let Some(comparison) = results.comparison {
+                        let delta = total as i32 - comparison[segment] as i32;
+                        /* 点滅はさせない */
+                        split_delta::draw(&mut text, vram, 23, row, delta, u32::MAX);
+                    }
+                }
+            }
+            Mode::TimeAttack | Mode::GhostRace => {
+                text.print_line(
+                    vram,
+                    7,
+                    format_args!("     TIME     {}", TimeDisplay(results.time)),
+                );
+                let medal = results.medal.map_or("NO", Medal::name);
+                text.print_line(vram, 9, format_args!("     {} MEDAL", medal));
+                /* 目標タイム */
+                for (row, (medal, par)) in [
+                    (Medal::Gold, results.par.gold),
+                    (Medal::Silver, results.par.silver),
+                    (Medal::Bronze, results.par.bronze),
+                ]
+                .into_iter()
+                .enumerate()
+                {
+                    text.print_line(
+                        vram,
+                        11 + row as u16,
+                        format_args!("       {:6} {}", medal.name(), TimeDisplay(par)),
+                    );
+                }
+            }
+        }
+        if results.new_best {
+            text.print_line(vram, 15, format_args!("       NEW RECORD!"));
+        }
+        text.print_line(vram, 17, format_args!("      PRESS START"));
+        text.set_visible(true);
+        text.commit(vram);
+        self.text = Some(text);
+    }
+
+    fn update(&mut self, _res: &mut Resources<'g>, input: &Input) -> Transition<'g> {
+        if input.is_just_pressed(Button::START) {
+            Transition::Replace(Box::new(TitleScene::new()))
+        } else {
+            Transition::Stay
+        }
+    }
+
+    fn exit(&mut self, res: &mut Resources<'g>) {
+        if let Some(text) = self.text.take() {
+            text.release(&mut res.vram);
+        }
+    }
+}
@@ -0,0 +1,125 @@
+// ゲームプレイ用の乱数生成器。
+//
+// GBA には途中のフレームで使えるようなエントロピー源が無いので、
+// ラン開始時に一度だけシードを決め、以降の乱数は全てこのインスタンスから引く。
+// モジュールごとに static な生成器を持たせると、リプレイやデイリーチャレンジで
+// 同じシードから同じ展開を再現できなくなるので禁止。
+//
+// 中身は xorshift32 。周期は 2^32 - 1 で、状態 0 には決して到達しない。
+
+use crate::snapshot::encode_fields;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rng {
+    state: u32,
+}
+
+encode_fields!(Rng { state });
+
+impl Rng {
+    // xorshift32 は状態 0 から抜け出せないので、シード 0 はこの値に置き換える。
+    const ZERO_SEED_REPLACEMENT: u32 = 0x9E37_79B9;
+
+    pub fn new(seed: u32) -> Self {
+        let state = if seed == 0 {
+            Self::ZERO_SEED_REPLACEMENT
+        } else {
+            seed
+        };
+        Self { state }
+    }
+
+    // 現在の内部状態。決定性の検証 (同じ入力で同じ状態になるか) に使う。
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn state(&self) -> u32 {
+        self.state
+    }
+
+    pub fn u32(&mut self) -> u32 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.state = x;
+        x
+    }
+
+    // lo 以上 hi 未満の一様な整数。lo == hi なら lo を返す。
+    pub fn range(&mut self, lo: i32, hi: i32) -> i32 {
+        assert!(lo <= hi, "empty range {lo}..{hi}");
+        let span = hi.wrapping_sub(lo) as u32;
+        if span == 0 {
+            return lo;
+        }
+        // 剰余の偏りを避けるため、span の倍数に収まらない値は引き直す。
+        let limit = u32::MAX - u32::MAX % span;
+        loop {
+            let x = self.u32();
+            if x < limit {
+                return lo.wrapping_add((x % span) as i32);
+            }
+        }
+    }
+
+    // percent % の確率で true 。100 以上なら常に true 。
+    pub fn chance(&mut self, percent: u32) -> bool {
+        (self.range(0, 100) as u32) < percent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn first_outputs_are_pinned(_gba: &mut agb::Gba) {
+        let mut rng = Rng::new(1);
+        let outputs = [rng.u32(), rng.u32(), rng.u32(), rng.u32(), rng.u32()];
+        assert_eq!(
+            outputs,
+            [270369, 67634689, 2647435461, 307599695, 2398689233]
+        );
+    }
+
+    #[test_case]
+    fn same_seed_gives_same_sequence(_gba: &mut agb::Gba) {
+        let mut a = Rng::new(0xC0FFEE);
+        let mut b = Rng::new(0xC0FFEE);
+        for _ in 0..100 {
+            assert_eq!(a.u32(), b.u32());
+        }
+        assert_eq!(a, b);
+    }
+
+    #[test_case]
+    fn zero_seed_does_not_get_stuck(_gba: &mut agb::Gba) {
+        let mut rng = Rng::new(0);
+        assert_ne!(rng.u32(), 0);
+        assert_ne!(rng.state(), 0);
+    }
+
+    #[test_case]
+    fn range_stays_within_bounds_and_covers_them(_gba: &mut agb::Gba) {
+        let mut rng = Rng::new(42);
+        let mut seen = [0u32; 7];
+        for _ in 0..700 {
+            let x = rng.range(-3, 4);
+            assert!((-3..4).contains(&x), "{x}");
+            seen[(x + 3) as usize] += 1;
+        }
+        // 700 回引けば各値は期待値 100 回前後になるはず。
+        for count in seen {
+            assert!((50..150).contains(&count), "{seen:?}");
+        }
+        assert_eq!(rng.range(5, 5), 5);
+    }
+
+    #[test_case]
+    fn chance_extremes(_gba: &mut agb::Gba) {
+        let mut rng = Rng::new(7);
+        for _ in 0..100 {
+            assert!(!rng.chance(0));
+            assert!(rng.chance(100));
+        }
+    }
+}
@@ -0,0 +1,1069 @@
+// セーブデータ。
+//
+// 読み書きの先は SaveBackend として差し替えられるようにしておき、
+// 実機では SramBackend (agb のセーブ API をそのまま呼ぶだけ)、
+// テストや SRAM が無いときは MemoryBackend を使う。
+// 形式の組み立て、チェックサム、二重化、古い版の読み替えは全て SaveFile が
+// SaveBackend の上で行うので、テストでは書き込みを途中で失敗させて確かめられる。
+//
+// 書き込み中に電源を切られても前回の内容が残るよう、同じ内容を 2 つのスロットに交互に書く。
+// 読むときはチェックサムの合うスロットのうち通し番号の新しい方を使う。
+//
+// スロットの中身 (リトルエンディアン):
+//   0..4   MAGIC
+//   4..6   版 (VERSION)
+//   6..8   本体の長さ
+//   8..12  通し番号
+//   12..   本体
+//   末尾 4 バイト  ここまでの FNV-1a
+//
+// 本体 (版 9):
+//   難易度ごとの最高得点 u32 x 3
+//   難易度ごとのタイムアタックの最高タイム u32 (無ければ NO_TIME) とメダル u8 x 3
+//   難易度ごとのエンドレスの最高得点 u32 x 3
+//   上の 3 種類の記録ごとに、HARD モードで作られたかどうか u8 x 3 (ビット 0..3 が難易度)
+//   フラグ u8 (ビット 0 がレベルをクリアしたことがあるか、ビット 1 がチュートリアルを終えたことがあるか)
+//   難易度ごとのエンドレスの最高得点を出したランのシード u32 x 3
+//   難易度ごとのミラーモードのタイムアタックの最高タイムとメダル (上と同じ形) x 3
+//   ミラーモードの最高タイムが HARD モードで作られたかどうか u8
+//   難易度ごとのスピードランの最高記録の区切りタイム u32 x SEGMENTS (無ければ NO_TIME) x 3
+//   スピードランの記録が HARD モードで作られたかどうか u8
+//   難易度ごとのコインラッシュの最高得点 u32 x 3
+//   コインラッシュの記録が HARD モードで作られたかどうか u8
+// 版 1 は最高得点だけ、版 2 はエンドレスの最高得点が無く、版 3 は HARD の印が無く、
+// 版 4 はフラグが無く、版 5 はシードが無く、版 6 はミラーモードの記録が無く、
+// 版 7 はスピードランの記録が無く、版 8 はコインラッシュの記録が無い。
+// 読むと無い記録は無し (0) 、印とフラグは無しになる。
+//
+// 版 5 まではスロットが 64 バイトだったので、2 つ目のスロットは LEGACY_SLOT_OFFSET にある。
+// そこから読んだときは 0 番のスロットとして扱い、次は今の 1 番のスロットに書く
+// (読んだばかりの古いスロットを上書きしないように)。
+//
+// スロットの後ろには、難易度ごとにタイムアタックの最高記録のリプレイ (ゴースト) を 1 つずつ置く。
+// 大きいので二重化はせず、書き込み中に電源を切られたらそのゴーストは無くなる (記録は残る)。
+//   0..4   GHOST_MAGIC
+//   4..6   リプレイの長さ
+//   6..    リプレイ (Replay::encode)
+//   その後 4 バイト  ここまでの FNV-1a
+
+use alloc::{boxed::Box, vec::Vec};
+
+use agb::save::{SaveData, SaveManager};
+
+use crate::{difficulty::Difficulty, replay::Replay, speedrun::SEGMENTS, time_attack::Medal};
+
+const MAGIC: [u8; 4] = *b"JDRD";
+const VERSION: u16 = 9;
+/* 最高タイムが無いことを表す値 */
+const NO_TIME: u32 = u32::MAX;
+const HEADER_LEN: usize = 12;
+const CHECKSUM_LEN: usize = 4;
+/* 1 スロットの大きさ。本体が増えてもしばらく収まるよう余裕を持たせてある */
+const SLOT_LEN: usize = 128;
+const SLOTS: usize = 2;
+/* 版 5 までの 2 つ目のスロットの場所と大きさ */
+const LEGACY_SLOT_OFFSET: usize = 64;
+const LEGACY_SLOT_LEN: usize = 64;
+const LEGACY_VERSION: u16 = 5;
+const GHOST_MAGIC: [u8; 4] = *b"JDGH";
+const GHOST_HEADER_LEN: usize = 6;
+/* ゴースト 1 つ分の場所。1 分ほどのランなら収まる */
+const GHOST_LEN: usize = 2048;
+const GHOSTS_OFFSET: usize = SLOT_LEN * SLOTS;
+/* セーブ全体の大きさ */
+const SAVE_LEN: usize = GHOSTS_OFFSET + GHOST_LEN * Difficulty::ALL.len();
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SaveError {
+    // 範囲外の読み書き。
+    OutOfBounds,
+    // 媒体が応答しない、書き込みに失敗した等。
+    Media,
+}
+
+pub trait SaveBackend {
+    fn len(&self) -> usize;
+    fn read(&mut self, offset: usize, buf: &mut [u8]) -> Result<(), SaveError>;
+    fn write(&mut self, offset: usize, buf: &[u8]) -> Result<(), SaveError>;
+}
+
+impl<B: SaveBackend + ?Sized> SaveBackend for Box<B> {
+    fn len(&self) -> usize {
+        (**self).len()
+    }
+
+    fn read(&mut self, offset: usize, buf: &mut [u8]) -> Result<(), SaveError> {
+        (**self).read(offset, buf)
+    }
+
+    fn write(&mut self, offset: usize, buf: &[u8]) -> Result<(), SaveError> {
+        (**self).write(offset, buf)
+    }
+}
+
+// カートリッジの SRAM 。
+pub struct SramBackend {
+    data: SaveData,
+}
+
+impl SramBackend {
+    // SRAM を使うことを宣言して開く。
+    fn open(manager: &mut SaveManager) -> Option<Self> {
+        manager.init_sram();
+        match manager.access() {
+            Ok(data) => Some(Self { data }),
+            Err(error) => {
+                log_warn!("no save media: {:?}", error);
+                None
+            }
+        }
+    }
+}
+
+impl SaveBackend for SramBackend {
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    fn read(&mut self, offset: usize, buf: &mut [u8]) -> Result<(), SaveError> {
+        self.data.read(offset, buf).map_err(media_error)
+    }
+
+    fn write(&mut self, offset: usize, buf: &[u8]) -> Result<(), SaveError> {
+        self.data
+            .prepare_write(offset..offset + buf.len())
+            .and_then(|mut block| block.write(offset, buf))
+            .map_err(media_error)
+    }
+}
+
+// 起動時に一度だけ呼ぶ。SRAM が無ければメモリ上に保存する (電源を切ると消える)。
+pub fn open(manager: &mut SaveManager) -> SaveFile<Box<dyn SaveBackend>> {
+    let backend: Box<dyn SaveBackend> = match SramBackend::open(manager) {
+        Some(sram) => Box::new(sram),
+        None => Box::new(MemoryBackend::new(SAVE_LEN)),
+    };
+    SaveFile::load(backend)
+}
+
+fn media_error(error: agb::save::Error) -> SaveError {
+    log_warn!("save media error: {:?}", error);
+    match error {
+        agb::save::Error::OutOfBounds => SaveError::OutOfBounds,
+        _ => SaveError::Media,
+    }
+}
+
+// メモリ上だけのセーブ先。電源を切ると消える。
+pub struct MemoryBackend {
+    bytes: Vec<u8>,
+}
+
+impl MemoryBackend {
+    pub fn new(len: usize) -> Self {
+        /* 消去済みのフラッシュや未初期化の SRAM と同じく 0xFF で埋めておく */
+        Self {
+            bytes: alloc::vec![0xff; len],
+        }
+    }
+}
+
+impl SaveBackend for MemoryBackend {
+    fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    fn read(&mut self, offset: usize, buf: &mut [u8]) -> Result<(), SaveError> {
+        let src = self
+            .bytes
+            .get(offset..offset + buf.len())
+            .ok_or(SaveError::OutOfBounds)?;
+        buf.copy_from_slice(src);
+        Ok(())
+    }
+
+    fn write(&mut self, offset: usize, buf: &[u8]) -> Result<(), SaveError> {
+        let dst = self
+            .bytes
+            .get_mut(offset..offset + buf.len())
+            .ok_or(SaveError::OutOfBounds)?;
+        dst.copy_from_slice(buf);
+        Ok(())
+    }
+}
+
+// タイムアタックの最高タイムと、そのときに取ったメダル。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BestTime {
+    pub ticks: u32,
+    pub medal: Option<Medal>,
+}
+
+// 記録の種類。HARD の印を種類ごとに持つ。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Board {
+    Score,
+    Time,
+    Climb,
+    // ミラーモードのタイムアタック。
+    MirrorTime,
+    Speedrun,
+    CoinRush,
+}
+
+// 保存しておく記録。
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Records {
+    /* 難易度ごとの最高得点 */
+    best: [u32; Difficulty::ALL.len()],
+    /* 難易度ごとのタイムアタックの最高タイム。ミラーモードは別に持つ */
+    times: [Option<BestTime>; Difficulty::ALL.len()],
+    mirror_times: [Option<BestTime>; Difficulty::ALL.len()],
+    /* 難易度ごとのエンドレスの最高得点と、そのランのシード */
+    climbs: [u32; Difficulty::ALL.len()],
+    climb_seeds: [u32; Difficulty::ALL.len()],
+    /* 難易度ごとのスピードランの最高記録の区切りタイム (最後が通しのタイム) */
+    speedruns: [Option<[u32; SEGMENTS]>; Difficulty::ALL.len()],
+    /* 難易度ごとのコインラッシュの最高得点 */
+    rushes: [u32; Difficulty::ALL.len()],
+    /* Board ごとの、HARD モードで作られた記録の難易度のビット */
+    hard: [u8; 6],
+    /* レベルをクリアしたことがあるか (練習モードが選べる) */
+    cleared: bool,
+    /* チュートリアルを終えたことがあるか (飛ばせる) */
+    tutorial: bool,
+}
+
+impl Records {
+    pub fn best(&self, difficulty: Difficulty) -> u32 {
+        self.best[difficulty as usize]
+    }
+
+    // 最高得点を更新したら true 。hard は HARD モードで遊んだか。
+    pub fn record(&mut self, difficulty: Difficulty, score: u32, hard: bool) -> bool {
+        let best = &mut self.best[difficulty as usize];
+        if score > *best {
+            *best = score;
+            self.mark(Board::Score, difficulty, hard);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn has_cleared(&self) -> bool {
+        self.cleared
+    }
+
+    // レベルをクリアした。初めてなら true 。
+    pub fn record_clear(&mut self) -> bool {
+        !core::mem::replace(&mut self.cleared, true)
+    }
+
+    pub fn has_finished_tutorial(&self) -> bool {
+        self.tutorial
+    }
+
+    // チュートリアルを終えた。初めてなら true 。
+    pub fn record_tutorial(&mut self) -> bool {
+        !core::mem::replace(&mut self.tutorial, true)
+    }
+
+    // board の difficulty の記録が HARD モードで作られたか。
+    pub fn is_hard(&self, board: Board, difficulty: Difficulty) -> bool {
+        self.hard[board as usize] & 1 << difficulty as usize != 0
+    }
+
+    fn mark(&mut self, board: Board, difficulty: Difficulty, hard: bool) {
+        let bits = &mut self.hard[board as usize];
+        if hard {
+            *bits |= 1 << difficulty as usize;
+        } else {
+            *bits &= !(1 << difficulty as usize);
+        }
+    }
+
+    // mirror ならミラーモードの最高タイム。
+    pub fn best_time(&self, difficulty: Difficulty, mirror: bool) -> Option<BestTime> {
+        let times = if mirror {
+            &self.mirror_times
+        } else {
+            &self.times
+        };
+        times[difficulty as usize]
+    }
+
+    // 最高タイムを更新したら true 。
+    pub fn record_time(
+        &mut self,
+        difficulty: Difficulty,
+        mirror: bool,
+        time: BestTime,
+        hard: bool,
+    ) -> bool {
+        let (times, board) = if mirror {
+            (&mut self.mirror_times, Board::MirrorTime)
+        } else {
+            (&mut self.times, Board::Time)
+        };
+        let best = &mut times[difficulty as usize];
+        if best.map_or(true, |best| time.ticks < best.ticks) {
+            *best = Some(time);
+            self.mark(board, difficulty, hard);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn best_climb(&self, difficulty: Difficulty) -> u32 {
+        self.climbs[difficulty as usize]
+    }
+
+    // エンドレスの最高得点を出したランのシード。同じシードで遊べば同じ塔になる。
+    pub fn best_climb_seed(&self, difficulty: Difficulty) -> u32 {
+        self.climb_seeds[difficulty as usize]
+    }
+
+    // エンドレスの最高得点を更新したら true 。seed はそのランのシード。
+    pub fn record_climb(
+        &mut self,
+        difficulty: Difficulty,
+        score: u32,
+        seed: u32,
+        hard: bool,
+    ) -> bool {
+        let best = &mut self.climbs[difficulty as usize];
+        if score > *best {
+            *best = score;
+            self.climb_seeds[difficulty as usize] = seed;
+            self.mark(Board::Climb, difficulty, hard);
+            true
+        } else {
+            false
+        }
+    }
+
+    // スピードランの最高記録の区切りタイム。
+    pub fn best_speedrun(&self, difficulty: Difficulty) -> Option<[u32; SEGMENTS]> {
+        self.speedruns[difficulty as usize]
+    }
+
+    // 通しのタイムが縮んだら区切りタイムごと記録して true 。
+    pub fn record_speedrun(
+        &mut self,
+        difficulty: Difficulty,
+        splits: [u32; SEGMENTS],
+        hard: bool,
+    ) -> bool {
+        let best = &mut self.speedruns[difficulty as usize];
+        if best.map_or(true, |best| splits[SEGMENTS - 1] < best[SEGMENTS - 1]) {
+            *best = Some(splits);
+            self.mark(Board::Speedrun, difficulty, hard);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn best_rush(&self, difficulty: Difficulty) -> u32 {
+        self.rushes[difficulty as usize]
+    }
+
+    // コインラッシュの最高得点を更新したら true 。
+    pub fn record_rush(&mut self, difficulty: Difficulty, score: u32, hard: bool) -> bool {
+        let best = &mut self.rushes[difficulty as usize];
+        if score > *best {
+            *best = score;
+            self.mark(Board::CoinRush, difficulty, hard);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut body = Vec::new();
+        for score in self.best {
+            body.extend_from_slice(&score.to_le_bytes());
+        }
+        encode_times(&self.times, &mut body);
+        for score in self.climbs {
+            body.extend_from_slice(&score.to_le_bytes());
+        }
+        body.extend_from_slice(&self.hard[..3]);
+        body.push(u8::from(self.cleared) | u8::from(self.tutorial) << 1);
+        for seed in self.climb_seeds {
+            body.extend_from_slice(&seed.to_le_bytes());
+        }
+        encode_times(&self.mirror_times, &mut body);
+        body.push(self.hard[Board::MirrorTime as usize]);
+        for splits in self.speedruns {
+            for split in splits.unwrap_or([NO_TIME; SEGMENTS]) {
+                body.extend_from_slice(&split.to_le_bytes());
+            }
+        }
+        body.push(self.hard[Board::Speedrun as usize]);
+        for score in self.rushes {
+            body.extend_from_slice(&score.to_le_bytes());
+        }
+        body.push(self.hard[Board::CoinRush as usize]);
+        body
+    }
+
+    // version 版の本体を読む。読めない版なら None 。
+    // 本体の形を変えるときは VERSION を上げ、古い版の読み方をここに残す。
+    fn decode(version: u16, body: &[u8]) -> Option<Self> {
+        let mut records = Self::default();
+        let split = 4 * records.best.len();
+        let (scores, rest) = (body.get(..split)?, body.get(split..)?);
+        for (best, bytes) in records.best.iter_mut().zip(scores.chunks_exact(4)) {
+            *best = u32::from_le_bytes(bytes.try_into().ok()?);
+        }
+        match version {
+            /* 版 1 には最高タイムが無い */
+            1 => {}
+            2..=9 => {
+                let rest = decode_times(&mut records.times, rest)?;
+                /* 版 2 にはエンドレスの最高得点が無い */
+                if version >= 3 {
+                    let split = 4 * records.climbs.len();
+                    let (climbs, rest) = (rest.get(..split)?, rest.get(split..)?);
+                    for (best, bytes) in records.climbs.iter_mut().zip(climbs.chunks_exact(4)) {
+                        *best = u32::from_le_bytes(bytes.try_into().ok()?);
+                    }
+                    /* 版 3 には HARD の印が無く、版 4 にはフラグが無く、版 5 にはシードが無い */
+                    if version >= 4 {
+                        let split = 3;
+                        let (hard, rest) = (rest.get(..split)?, rest.get(split..)?);
+                        records.hard[..split].copy_from_slice(hard);
+                        if version >= 5 {
+                            /* ビット 1 は版を上げずに足したので、それより前の版では 0 */
+                            let flags = *rest.first()?;
+                            records.cleared = flags & 1 != 0;
+                            records.tutorial = flags & 2 != 0;
+                        }
+                        if version >= 6 {
+                            let split = 1 + 4 * records.climb_seeds.len();
+                            let (seeds, rest) = (rest.get(1..split)?, rest.get(split..)?);
+                            for (seed, bytes) in
+                                records.climb_seeds.iter_mut().zip(seeds.chunks_exact(4))
+                            {
+                                *seed = u32::from_le_bytes(bytes.try_into().ok()?);
+                            }
+                            if version >= 7 {
+                                let rest = decode_times(&mut records.mirror_times, rest)?;
+                                records.hard[Board::MirrorTime as usize] = *rest.first()?;
+                                if version >= 8 {
+                                    let split = 1 + 4 * SEGMENTS * records.speedruns.len();
+                                    let (speedruns, rest) =
+                                        (rest.get(1..split)?, rest.get(split..)?);
+                                    for (best, bytes) in records
+                                        .speedruns
+                                        .iter_mut()
+                                        .zip(speedruns.chunks_exact(4 * SEGMENTS))
+                                    {
+                                        let mut splits = [0; SEGMENTS];
+                                        for (split, bytes) in
+                                            splits.iter_mut().zip(bytes.chunks_exact(4))
+                                        {
+                                            *split = u32::from_le_bytes(bytes.try_into().ok()?);
+                                        }
+                                        *best = (splits[0] != NO_TIME).then_some(splits);
+                                    }
+                                    records.hard[Board::Speedrun as usize] = *rest.first()?;
+                                    if version >= 9 {
+                                        let split = 1 + 4 * records.rushes.len();
+                                        let (rushes, rest) =
+                                            (rest.get(1..split)?, rest.get(split..)?);
+                                        for (best, bytes) in
+                                            records.rushes.iter_mut().zip(rushes.chunks_exact(4))
+                                        {
+                                            *best = u32::from_le_bytes(bytes.try_into().ok()?);
+                                        }
+                                        records.hard[Board::CoinRush as usize] = *rest.first()?;
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            _ => return None,
+        }
+        Some(records)
+    }
+}
+
+// 最高タイムとメダルを 5 バイトずつ。
+fn encode_times(times: &[Option<BestTime>], body: &mut Vec<u8>) {
+    for time in times {
+        let ticks = time.map_or(NO_TIME, |time| time.ticks);
+        let medal = match time.and_then(|time| time.medal) {
+            None => 0,
+            Some(Medal::Gold) => 1,
+            Some(Medal::Silver) => 2,
+            Some(Medal::Bronze) => 3,
+        };
+        body.extend_from_slice(&ticks.to_le_bytes());
+        body.push(medal);
+    }
+}
+
+// encode_times の逆。読んだ残りを返す。
+fn decode_times<'b>(times: &mut [Option<BestTime>], body: &'b [u8]) -> Option<&'b [u8]> {
+    let split = 5 * times.len();
+    let (bytes, rest) = (body.get(..split)?, body.get(split..)?);
+    for (time, bytes) in times.iter_mut().zip(bytes.chunks_exact(5)) {
+        let ticks = u32::from_le_bytes(bytes[..4].try_into().ok()?);
+        let medal = match bytes[4] {
+            1 => Some(Medal::Gold),
+            2 => Some(Medal::Silver),
+            3 => Some(Medal::Bronze),
+            _ => None,
+        };
+        *time = (ticks != NO_TIME).then_some(BestTime { ticks, medal });
+    }
+    Some(rest)
+}
+
+fn fnv1a(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5, |hash, &byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+pub struct SaveFile<B: SaveBackend> {
+    backend: B,
+    /* 最後に読み書きしたスロットと通し番号 */
+    latest: Option<(usize, u32)>,
+    pub records: Records,
+    /* 難易度ごとのゴースト */
+    ghosts: [Option<Replay>; Difficulty::ALL.len()],
+}
+
+impl<B: SaveBackend> SaveFile<B> {
+    // 読めるスロットのうち新しい方を読む。どちらも読めなければ空の記録から始める。
+    pub fn load(mut backend: B) -> Self {
+        let mut latest: Option<(usize, u32, Records)> = None;
+        /* (スロット, 場所, 大きさ, 読める一番新しい版) */
+        let places = [
+            (0, 0, SLOT_LEN, VERSION),
+            (1, SLOT_LEN, SLOT_LEN, VERSION),
+            (0, LEGACY_SLOT_OFFSET, LEGACY_SLOT_LEN, LEGACY_VERSION),
+        ];
+        for (slot, offset, len, newest) in places {
+            let Some((sequence, records)) = read_slot(&mut backend, offset, len, newest) else {
+                continue;
+            };
+            if latest.as_ref().map_or(true, |&(_, newest, _)| {
+                sequence.wrapping_sub(newest) as i32 > 0
+            }) {
+                latest = Some((slot, sequence, records));
+            }
+        }
+        let ghosts = core::array::from_fn(|index| read_ghost(&mut backend, index));
+        match latest {
+            Some((slot, sequence, records)) => Self {
+                backend,
+                latest: Some((slot, sequence)),
+                records,
+                ghosts,
+            },
+            None => Self {
+                backend,
+                latest: None,
+                records: Records::default(),
+                ghosts,
+            },
+        }
+    }
+
+    pub fn ghost(&self, difficulty: Difficulty) -> Option<&Replay> {
+        self.ghosts[difficulty as usize].as_ref()
+    }
+
+    // replay を replay.difficulty のゴーストとして書く。場所に収まらなければ OutOfBounds 。
+    pub fn save_ghost(&mut self, replay: Replay) -> Result<(), SaveError> {
+        let body = replay.encode();
+        if GHOST_HEADER_LEN + body.len() + CHECKSUM_LEN > GHOST_LEN {
+            return Err(SaveError::OutOfBounds);
+        }
+        let mut bytes = Vec::with_capacity(GHOST_HEADER_LEN + body.len() + CHECKSUM_LEN);
+        bytes.extend_from_slice(&GHOST_MAGIC);
+        bytes.extend_from_slice(&(body.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(&body);
+        let checksum = fnv1a(&bytes);
+        bytes.extend_from_slice(&checksum.to_le_bytes());
+        let index = replay.difficulty as usize;
+        /* 書き始めたら前のゴーストは当てにならない */
+        self.ghosts[index] = None;
+        self.backend
+            .write(GHOSTS_OFFSET + index * GHOST_LEN, &bytes)?;
+        self.ghosts[index] = Some(replay);
+        Ok(())
+    }
+
+    // 古い方のスロットに書く。途中で失敗しても、もう一方のスロットは前回の内容のまま。
+    pub fn save(&mut self) -> Result<(), SaveError> {
+        let (slot, sequence) = match self.latest {
+            Some((slot, sequence)) => ((slot + 1) % SLOTS, sequence.wrapping_add(1)),
+            None => (0, 0),
+        };
+        let bytes = encode_slot(VERSION, sequence, &self.records.encode());
+        self.backend.write(slot * SLOT_LEN, &bytes)?;
+        self.latest = Some((slot, sequence));
+        Ok(())
+    }
+
+    #[cfg(test)]
+    fn into_backend(self) -> B {
+        self.backend
+    }
+}
+
+fn encode_slot(version: u16, sequence: u32, body: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(SLOT_LEN);
+    bytes.extend_from_slice(&MAGIC);
+    bytes.extend_from_slice(&version.to_le_bytes());
+    bytes.extend_from_slice(&(body.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(&sequence.to_le_bytes());
+    bytes.extend_from_slice(body);
+    let checksum = fnv1a(&bytes);
+    bytes.extend_from_slice(&checksum.to_le_bytes());
+    debug_assert!(bytes.len() <= SLOT_LEN);
+    bytes
+}
+
+// index 番目の難易度のゴーストを読む。無いか壊れていれば None 。
+fn read_ghost<B: SaveBackend>(backend: &mut B, index: usize) -> Option<Replay> {
+    let offset = GHOSTS_OFFSET + index * GHOST_LEN;
+    if offset + GHOST_LEN > backend.len() {
+        return None;
+    }
+    let mut bytes = alloc::vec![0; GHOST_LEN];
+    backend.read(offset, &mut bytes).ok()?;
+    if bytes[0..4] != GHOST_MAGIC {
+        return None;
+    }
+    let end = GHOST_HEADER_LEN + u16::from_le_bytes([bytes[4], bytes[5]]) as usize;
+    let checksum = u32::from_le_bytes(bytes.get(end..end + CHECKSUM_LEN)?.try_into().ok()?);
+    if fnv1a(&bytes[..end]) != checksum {
+        log_warn!("ghost {} is corrupt", index);
+        return None;
+    }
+    Replay::decode(&bytes[GHOST_HEADER_LEN..end])
+}
+
+// offset から len バイトのスロットを読む。壊れていたり、newest より新しいか読めない版なら None 。
+fn read_slot<B: SaveBackend>(
+    backend: &mut B,
+    offset: usize,
+    len: usize,
+    newest: u16,
+) -> Option<(u32, Records)> {
+    let mut bytes = [0; SLOT_LEN];
+    let bytes = &mut bytes[..len];
+    if offset + len > backend.len() {
+        return None;
+    }
+    backend.read(offset, bytes).ok()?;
+    if bytes[0..4] != MAGIC {
+        return None;
+    }
+    let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+    let body_len = u16::from_le_bytes([bytes[6], bytes[7]]) as usize;
+    let sequence = u32::from_le_bytes(bytes[8..12].try_into().ok()?);
+    let end = HEADER_LEN + body_len;
+    if version > newest || end + CHECKSUM_LEN > len {
+        return None;
+    }
+    let checksum = u32::from_le_bytes(bytes[end..end + CHECKSUM_LEN].try_into().ok()?);
+    if fnv1a(&bytes[..end]) != checksum {
+        log_warn!("save slot at {} is corrupt", offset);
+        return None;
+    }
+    Some((sequence, Records::decode(version, &bytes[HEADER_LEN..end])?))
+}
+
+#[cfg(test)]
+mod tests {
+    use agb::input::Button;
+
+    use super::*;
+    use crate::{input::Input, mode::Mode, modifiers::Modifiers};
+
+    // 指定したバイト数を書いたところで止まるセーブ先 (書き込み中の電源断)。
+    struct TornBackend {
+        inner: MemoryBackend,
+        budget: Option<usize>,
+    }
+
+    impl SaveBackend for TornBackend {
+        fn len(&self) -> usize {
+            self.inner.len()
+        }
+
+        fn read(&mut self, offset: usize, buf: &mut [u8]) -> Result<(), SaveError> {
+            self.inner.read(offset, buf)
+        }
+
+        fn write(&mut self, offset: usize, buf: &[u8]) -> Result<(), SaveError> {
+            match self.budget {
+                Some(budget) if budget < buf.len() => {
+                    self.inner.write(offset, &buf[..budget])?;
+                    Err(SaveError::Media)
+                }
+                _ => self.inner.write(offset, buf),
+            }
+        }
+    }
+
+    // 読み書きが全て失敗するセーブ先。
+    struct DeadBackend;
+
+    impl SaveBackend for DeadBackend {
+        fn len(&self) -> usize {
+            SLOT_LEN * SLOTS
+        }
+
+        fn read(&mut self, _offset: usize, _buf: &mut [u8]) -> Result<(), SaveError> {
+            Err(SaveError::Media)
+        }
+
+        fn write(&mut self, _offset: usize, _buf: &[u8]) -> Result<(), SaveError> {
+            Err(SaveError::Media)
+        }
+    }
+
+    /* 版 7 で増えたミラーモードの記録、版 8 で増えたスピードランの記録、
+    版 9 で増えたコインラッシュの記録の大きさ */
+    const MIRROR_LEN: usize = 5 * Difficulty::ALL.len() + 1;
+    const SPEEDRUN_LEN: usize = 4 * SEGMENTS * Difficulty::ALL.len() + 1;
+    const RUSH_LEN: usize = 4 * Difficulty::ALL.len() + 1;
+
+    fn memory() -> MemoryBackend {
+        MemoryBackend::new(SAVE_LEN)
+    }
+
+    #[test_case]
+    fn records_survive_a_round_trip(_gba: &mut agb::Gba) {
+        let mut file = SaveFile::load(memory());
+        assert_eq!(file.records, Records::default());
+        assert!(file.records.record(Difficulty::Hard, 120, false));
+        assert!(!file.records.record(Difficulty::Hard, 110, false));
+        file.save().unwrap();
+        /* 2 回目は反対側のスロットに書く */
+        file.records.record(Difficulty::Easy, 30, false);
+        file.save().unwrap();
+        assert_eq!(file.latest, Some((1, 1)));
+
+        let reloaded = SaveFile::load(file.into_backend());
+        assert_eq!(reloaded.records.best(Difficulty::Hard), 120);
+        assert_eq!(reloaded.records.best(Difficulty::Easy), 30);
+        assert_eq!(reloaded.records.best(Difficulty::Normal), 0);
+    }
+
+    // どこで書き込みが止まっても、読み直すと前回か今回のどちらかの内容になる。
+    #[test_case]
+    fn a_torn_write_falls_back_to_the_previous_save(_gba: &mut agb::Gba) {
+        for cut in 0..=SLOT_LEN {
+            let mut file = SaveFile::load(TornBackend {
+                inner: memory(),
+                budget: None,
+            });
+            file.records.record(Difficulty::Normal, 100, false);
+            file.save().unwrap();
+            file.save().unwrap();
+
+            file.records.record(Difficulty::Normal, 200, false);
+            let mut backend = file.into_backend();
+            backend.budget = Some(cut);
+            let mut file = SaveFile::load(backend);
+            file.records.record(Difficulty::Normal, 200, false);
+            let completed = file.save().is_ok();
+
+            let reloaded = SaveFile::load(file.into_backend());
+            let expected = if completed { 200 } else { 100 };
+            assert_eq!(
+                reloaded.records.best(Difficulty::Normal),
+                expected,
+                "cut after {cut} bytes"
+            );
+        }
+    }
+
+    #[test_case]
+    fn unreadable_media_starts_empty(_gba: &mut agb::Gba) {
+        let mut file = SaveFile::load(DeadBackend);
+        assert_eq!(file.records, Records::default());
+        assert_eq!(file.save(), Err(SaveError::Media));
+    }
+
+    // 知らない (新しい) 版のスロットは読まず、もう一方を使う。
+    #[test_case]
+    fn unknown_versions_are_skipped(_gba: &mut agb::Gba) {
+        let mut file = SaveFile::load(memory());
+        file.records.record(Difficulty::Easy, 50, false);
+        file.save().unwrap();
+        let mut backend = file.into_backend();
+        let newer = encode_slot(VERSION + 1, 1, &[0; 8]);
+        backend.write(SLOT_LEN, &newer).unwrap();
+
+        let reloaded = SaveFile::load(backend);
+        assert_eq!(reloaded.latest, Some((0, 0)));
+        assert_eq!(reloaded.records.best(Difficulty::Easy), 50);
+    }
+
+    // 版 5 までの 64 バイトのスロットも読め、次は今の 1 番のスロットに書く。
+    // エンドレスの最高得点のシードは版 6 から残る。
+    #[test_case]
+    fn legacy_slots_are_migrated(_gba: &mut agb::Gba) {
+        let mut records = Records::default();
+        records.record(Difficulty::Normal, 10, false);
+        let mut old = records.encode();
+        old.truncate(
+            old.len() - 4 * records.climb_seeds.len() - MIRROR_LEN - SPEEDRUN_LEN - RUSH_LEN,
+        );
+        let mut backend = memory();
+        backend.write(0, &encode_slot(5, 3, &old)).unwrap();
+        records.record(Difficulty::Normal, 20, false);
+        let mut old = records.encode();
+        old.truncate(
+            old.len() - 4 * records.climb_seeds.len() - MIRROR_LEN - SPEEDRUN_LEN - RUSH_LEN,
+        );
+        backend
+            .write(LEGACY_SLOT_OFFSET, &encode_slot(5, 4, &old))
+            .unwrap();
+
+        let mut file = SaveFile::load(backend);
+        assert_eq!(file.latest, Some((0, 4)));
+        assert_eq!(file.records.best(Difficulty::Normal), 20);
+        assert!(file
+            .records
+            .record_climb(Difficulty::Hard, 90, 0x0a1b_2c3d, false));
+        file.save().unwrap();
+        assert_eq!(file.latest, Some((1, 5)));
+        let reloaded = SaveFile::load(file.into_backend());
+        assert_eq!(reloaded.records.best(Difficulty::Normal), 20);
+        assert_eq!(
+            reloaded.records.best_climb_seed(Difficulty::Hard),
+            0x0a1b_2c3d
+        );
+    }
+
+    // 版 1 (最高得点だけ) のセーブを読むと、最高タイムは無しになる。
+    #[test_case]
+    fn version_1_saves_are_migrated(_gba: &mut agb::Gba) {
+        let mut backend = memory();
+        let body: Vec<u8> = [10u32, 20, 30]
+            .iter()
+            .flat_map(|n| n.to_le_bytes())
+            .collect();
+        backend.write(0, &encode_slot(1, 5, &body)).unwrap();
+
+        let mut file = SaveFile::load(backend);
+        assert_eq!(file.records.best(Difficulty::Hard), 30);
+        assert_eq!(file.records.best_time(Difficulty::Hard, false), None);
+
+        /* 次に書くときは今の版になる */
+        let time = BestTime {
+            ticks: 600,
+            medal: Some(Medal::Silver),
+        };
+        assert!(file
+            .records
+            .record_time(Difficulty::Hard, false, time, false));
+        assert!(!file.records.record_time(
+            Difficulty::Hard,
+            false,
+            BestTime { ticks: 700, ..time },
+            false
+        ));
+        file.save().unwrap();
+        let reloaded = SaveFile::load(file.into_backend());
+        assert_eq!(reloaded.latest, Some((1, 6)));
+        assert_eq!(reloaded.records.best(Difficulty::Normal), 20);
+        assert_eq!(
+            reloaded.records.best_time(Difficulty::Hard, false),
+            Some(time)
+        );
+        assert_eq!(reloaded.records.best_time(Difficulty::Easy, false), None);
+    }
+
+    // 版 2 のセーブを読むと、最高タイムはそのままでエンドレスの最高得点は 0 になる。
+    #[test_case]
+    fn version_2_saves_are_migrated(_gba: &mut agb::Gba) {
+        let mut records = Records::default();
+        records.record(Difficulty::Easy, 40, false);
+        let time = BestTime {
+            ticks: 450,
+            medal: Some(Medal::Gold),
+        };
+        records.record_time(Difficulty::Normal, false, time, false);
+        let mut body = records.encode();
+        body.truncate(
+            body.len()
+                - 4 * records.climbs.len()
+                - 3
+                - 1
+                - 4 * records.climb_seeds.len()
+                - MIRROR_LEN
+                - SPEEDRUN_LEN
+                - RUSH_LEN,
+        );
+        let mut backend = memory();
+        backend.write(0, &encode_slot(2, 0, &body)).unwrap();
+
+        let mut file = SaveFile::load(backend);
+        assert_eq!(file.records, records);
+        assert!(file.records.record_climb(Difficulty::Easy, 75, 1, false));
+        assert!(!file.records.record_climb(Difficulty::Easy, 70, 2, false));
+        file.save().unwrap();
+        let reloaded = SaveFile::load(file.into_backend());
+        assert_eq!(reloaded.records.best_climb(Difficulty::Easy), 75);
+        assert_eq!(
+            reloaded.records.best_time(Difficulty::Normal, false),
+            Some(time)
+        );
+    }
+
+    // HARD の印は記録と一緒に保存され、印の無いランで記録を更新すると消える。
+    #[test_case]
+    fn hard_badges_follow_the_record(_gba: &mut agb::Gba) {
+        let mut file = SaveFile::load(memory());
+        file.records.record(Difficulty::Normal, 50, true);
+        file.records.record_climb(Difficulty::Normal, 30, 1, false);
+        file.save().unwrap();
+        let mut reloaded = SaveFile::load(file.into_backend());
+        assert!(reloaded.records.is_hard(Board::Score, Difficulty::Normal));
+        assert!(!reloaded.records.is_hard(Board::Score, Difficulty::Easy));
+        assert!(!reloaded.records.is_hard(Board::Climb, Difficulty::Normal));
+        /* 更新できなければ印はそのまま */
+        assert!(!reloaded.records.record(Difficulty::Normal, 40, false));
+        assert!(reloaded.records.is_hard(Board::Score, Difficulty::Normal));
+        assert!(reloaded.records.record(Difficulty::Normal, 60, false));
+        assert!(!reloaded.records.is_hard(Board::Score, Difficulty::Normal));
+    }
+
+    #[test_case]
+    fn the_first_clear_is_remembered(_gba: &mut agb::Gba) {
+        let mut file = SaveFile::load(memory());
+        assert!(!file.records.has_cleared());
+        assert!(file.records.record_clear());
+        assert!(!file.records.record_clear());
+        file.save().unwrap();
+        let reloaded = SaveFile::load(file.into_backend());
+        assert!(reloaded.records.has_cleared());
+        /* チュートリアルは同じフラグの別のビット */
+        assert!(!reloaded.records.has_finished_tutorial());
+        let mut file = reloaded;
+        assert!(file.records.record_tutorial());
+        assert!(!file.records.record_tutorial());
+        file.save().unwrap();
+        let reloaded = SaveFile::load(file.into_backend());
+        assert!(reloaded.records.has_cleared());
+        assert!(reloaded.records.has_finished_tutorial());
+    }
+
+    // ミラーモードの最高タイムと HARD の印は、普通の最高タイムとは別に残る。
+    #[test_case]
+    fn mirror_times_are_kept_apart(_gba: &mut agb::Gba) {
+        let mut file = SaveFile::load(memory());
+        let time = |ticks| BestTime { ticks, medal: None };
+        assert!(file
+            .records
+            .record_time(Difficulty::Easy, false, time(900), false));
+        assert!(file
+            .records
+            .record_time(Difficulty::Easy, true, time(1200), true));
+        assert!(!file
+            .records
+            .record_time(Difficulty::Easy, true, time(1300), false));
+        file.save().unwrap();
+        let reloaded = SaveFile::load(file.into_backend());
+        assert_eq!(
+            reloaded.records.best_time(Difficulty::Easy, false),
+            Some(time(900))
+        );
+        assert_eq!(
+            reloaded.records.best_time(Difficulty::Easy, true),
+            Some(time(1200))
+        );
+        assert!(!reloaded.records.is_hard(Board::Time, Difficulty::Easy));
+        assert!(reloaded
+            .records
+            .is_hard(Board::MirrorTime, Difficulty::Easy));
+    }
+
+    // スピードランは通しのタイムが縮んだときだけ、区切りタイムごと置き換わる。
+    #[test_case]
+    fn speedrun_splits_follow_the_total(_gba: &mut agb::Gba) {
+        let mut file = SaveFile::load(memory());
+        assert_eq!(file.records.best_speedrun(Difficulty::Normal), None);
+        assert!(file
+            .records
+            .record_speedrun(Difficulty::Normal, [500, 1100], true));
+        /* 区切りが速くても、通しで遅ければ記録しない */
+        assert!(!file
+            .records
+            .record_speedrun(Difficulty::Normal, [400, 1200], false));
+        file.save().unwrap();
+        let mut reloaded = SaveFile::load(file.into_backend());
+        assert_eq!(
+            reloaded.records.best_speedrun(Difficulty::Normal),
+            Some([500, 1100])
+        );
+        assert_eq!(reloaded.records.best_speedrun(Difficulty::Hard), None);
+        assert!(reloaded
+            .records
+            .is_hard(Board::Speedrun, Difficulty::Normal));
+        assert!(reloaded
+            .records
+            .record_speedrun(Difficulty::Normal, [600, 1000], false));
+        assert!(!reloaded
+            .records
+            .is_hard(Board::Speedrun, Difficulty::Normal));
+    }
+
+    // コインラッシュの最高得点は普通のモードの最高得点とは別に残る。
+    #[test_case]
+    fn coin_rush_bests_are_kept_apart(_gba: &mut agb::Gba) {
+        let mut file = SaveFile::load(memory());
+        file.records.record(Difficulty::Hard, 30, false);
+        assert!(file.records.record_rush(Difficulty::Hard, 160, true));
+        assert!(!file.records.record_rush(Difficulty::Hard, 150, false));
+        file.save().unwrap();
+        let reloaded = SaveFile::load(file.into_backend());
+        assert_eq!(reloaded.records.best(Difficulty::Hard), 30);
+        assert_eq!(reloaded.records.best_rush(Difficulty::Hard), 160);
+        assert_eq!(reloaded.records.best_rush(Difficulty::Easy), 0);
+        assert!(reloaded.records.is_hard(Board::CoinRush, Difficulty::Hard));
+    }
+
+    // ゴーストは難易度ごとに残り、書き込みが途中で止まったものは無かったことになる。
+    #[test_case]
+    fn ghosts_are_kept_per_difficulty(_gba: &mut agb::Gba) {
+        let mut replay = Replay::new(Difficulty::Hard, Mode::TimeAttack, Modifiers::HARD, 42);
+        for buttons in [Button::RIGHT, Button::UP, Button::empty()] {
+            replay.record(&Input::new(buttons, Button::empty()));
+        }
+        let mut file = SaveFile::load(TornBackend {
+            inner: memory(),
+            budget: None,
+        });
+        file.save_ghost(replay.clone()).unwrap();
+        let mut backend = file.into_backend();
+        backend.budget = Some(12);
+        let mut file = SaveFile::load(backend);
+        assert_eq!(file.ghost(Difficulty::Hard), Some(&replay));
+        assert_eq!(file.ghost(Difficulty::Normal), None);
+
+        let mut torn = replay.clone();
+        torn.seed = 43;
+        assert_eq!(file.save_ghost(torn), Err(SaveError::Media));
+        assert_eq!(file.ghost(Difficulty::Hard), None);
+        let reloaded = SaveFile::load(file.into_backend());
+        assert_eq!(reloaded.ghost(Difficulty::Hard), None);
+    }
+}
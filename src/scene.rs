@@ -0,0 +1,287 @@
+// シーン (タイトル、ゲーム本編、ポーズ...) の切り替え。
+//
+// 各シーンは enter() で必要な背景やオブジェクトを Resources から借り、
+// exit() で全て返す。シーンをまたいで残るものは Resources 自体 (レベルの背景を含む) だけにして、
+// どんな順番で切り替えても OAM とタイルの使用量が元に戻るようにする。
+// 戻っているかどうかは Usage で数えて確かめる。
+
+use alloc::{boxed::Box, vec::Vec};
+
+use agb::display::tiled::{Tiled0, VRamManager};
+
+use crate::{
+    input::Input,
+    layer,
+    oam::ObjectPool,
+    resources::SpriteSet,
+    save::{SaveBackend, SaveFile},
+};
+
+// シーンが借りるハードウェア資源と素材。
+pub struct Resources<'g> {
+    pub gfx: &'g Tiled0<'g>,
+    pub vram: VRamManager,
+    pub oam: &'g ObjectPool<'g>,
+    pub sprites: &'g SpriteSet,
+    pub save: SaveFile<Box<dyn SaveBackend>>,
+    /* 最後のティックから次のティックまでに進んだ分 (pacing::TickAccumulator::alpha)。描くときに前のティックとの間を補う */
+    pub alpha: u32,
+    /* レベルの背景。ゲーム本編を抜けても返さず、次のレベルで使い回す (gameplay::build_level) */
+    pub level_bg: Option<layer::Layer<'g>>,
+    #[cfg(feature = "debug-tools")]
+    pub debug: crate::debug_menu::DebugState,
+}
+
+pub trait Scene<'g> {
+    // スタックに積まれたときに呼ばれる。背景やオブジェクトはここで用意する。
+    fn enter(&mut self, res: &mut Resources<'g>);
+    // 1 ティック進める。
+    fn update(&mut self, res: &mut Resources<'g>, input: &Input) -> Transition<'g>;
+    // 描画フレームごとに呼ばれる。ティックとは回数が一致しないことがある。
+    fn draw(&mut self, _res: &mut Resources<'g>) {}
+    // スタックから外されるときに呼ばれる。enter() で借りたものを全て返す。
+    fn exit(&mut self, res: &mut Resources<'g>);
+}
+
+pub enum Transition<'g> {
+    Stay,
+    // 今のシーンの上に積む (下のシーンは資源を持ったまま止まる)。
+    Push(Box<dyn Scene<'g> + 'g>),
+    // 今のシーンを外して下のシーンに戻る。
+    Pop,
+    // 今のシーンを外して別のシーンにする。
+    Replace(Box<dyn Scene<'g> + 'g>),
+}
+
+// OAM とタイルの使用量。シーンを外した後は、そのシーンを積む前と同じになっているはず。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Usage {
+    pub oam_slots: u32,
+    pub bg_tiles: u32,
+}
+
+const OAM: *const u16 = 0x0700_0000 as *const u16;
+
+impl Usage {
+    // OAM は commit した後の実際の中身を数える。
+    pub fn now() -> Self {
+        let mut oam_slots = 0;
+        for slot in 0..128 {
+            // Safety: OAM の属性 0 を読むだけ
+            let attr0 = unsafe { OAM.add(slot * 4).read_volatile() };
+            /* 属性 0 の 8-9 ビットが 0b10 なら非表示 */
+            if (attr0 >> 8) & 0b11 != 0b10 {
+                oam_slots += 1;
+            }
+        }
+        Self {
+            oam_slots,
+            bg_tiles: layer::tiles_in_use(),
+        }
+    }
+}
+
+struct Entry<'g> {
+    scene: Box<dyn Scene<'g> + 'g>,
+    /* このシーンを積む直前の使用量 */
+    usage_before: Usage,
+}
+
+pub struct SceneStack<'g> {
+    entries: Vec<Entry<'g>>,
+    /* 外したシーンが資源を返し忘れた回数 */
+    leaks: u32,
+}
+
+impl<'g> SceneStack<'g> {
+    pub fn new(res: &mut Resources<'g>, first: Box<dyn Scene<'g> + 'g>) -> Self {
+        let mut stack = Self {
+            entries: Vec::new(),
+            leaks: 0,
+        };
+        stack.push(res, first);
+        stack
+    }
+
+    pub fn update(&mut self, res: &mut Resources<'g>, input: &Input) {
+        let Some(top) = self.entries.last_mut() else {
+            return;
+        };
+        let transition = top.scene.update(res, input);
+        /* デバッグメニューの命令と調整値の変更量は、置いた直後の 1 ティックだけ有効 */
+        #[cfg(feature = "debug-tools")]
+        {
+            res.debug.command = None;
+            res.debug.tweaks = Default::default();
+        }
+        match transition {
+            Transition::Stay => {}
+            Transition::Push(scene) => self.push(res, scene),
+            Transition::Pop => {
+                self.pop(res);
+            }
+            Transition::Replace(scene) => {
+                self.pop(res);
+                self.push(res, scene);
+            }
+        }
+    }
+
+    pub fn draw(&mut self, res: &mut Resources<'g>) {
+        if let Some(top) = self.entries.last_mut() {
+            top.scene.draw(res);
+        }
+    }
+
+    // 全てのシーンを外して first からやり直す (ソフトリセット)。
+    pub fn reset(&mut self, res: &mut Resources<'g>, first: Box<dyn Scene<'g> + 'g>) {
+        while self.pop(res) {}
+        self.push(res, first);
+    }
+
+    // 起動してから資源を返し忘れたシーンの数。
+    #[cfg_attr(not(feature = "debug-tools"), allow(dead_code))]
+    pub fn leaks(&self) -> u32 {
+        self.leaks
+    }
+
+    fn push(&mut self, res: &mut Resources<'g>, mut scene: Box<dyn Scene<'g> + 'g>) {
+        let usage_before = Usage::now();
+        scene.enter(res);
+        res.oam.commit();
+        res.oam.collect_garbage();
+        self.entries.push(Entry {
+            scene,
+            usage_before,
+        });
+    }
+
+    fn pop(&mut self, res: &mut Resources<'g>) -> bool {
+        let Some(mut entry) = self.entries.pop() else {
+            return false;
+        };
+        entry.scene.exit(res);
+        drop(entry.scene);
+        /* 消えたオブジェクトを OAM から消す */
+        res.oam.commit();
+        res.oam.collect_garbage();
+        let usage = Usage::now();
+        if usage != entry.usage_before {
+            self.leaks += 1;
+            log_warn!(
+                "scene leaked resources: {:?} -> {:?}",
+                entry.usage_before,
+                usage
+            );
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        layer::Layer,
+        oam::{Category, Pooled},
+        save::MemoryBackend,
+        spawn::ObjectSpawn,
+    };
+
+    // 背景 1 枚とオブジェクトを n 個借りるだけのシーン。
+    struct Dummy<'g> {
+        objects: usize,
+        layer: Option<Layer<'g>>,
+        held: Vec<Pooled<'g>>,
+        next: Option<Transition<'g>>,
+    }
+
+    impl<'g> Dummy<'g> {
+        fn new(objects: usize, next: Transition<'g>) -> Box<Self> {
+            Box::new(Self {
+                objects,
+                layer: None,
+                held: Vec::new(),
+                next: Some(next),
+            })
+        }
+    }
+
+    impl<'g> Scene<'g> for Dummy<'g> {
+        fn enter(&mut self, res: &mut Resources<'g>) {
+            let mut layer = Layer::new(res.gfx, agb::display::Priority::P1);
+            for x in 0..self.objects as u16 {
+                layer.set_tile(
+                    &mut res.vram,
+                    (x, 0),
+                    &crate::font::TILESET,
+                    agb::display::tiled::TileSetting::new(x, false, false, 0),
+                );
+            }
+            layer.commit(&mut res.vram);
+            self.layer = Some(layer);
+            for _ in 0..self.objects {
+                let object = ObjectSpawn::new(res.oam, res.sprites.apple, Category::Scenery)
+                    .build()
+                    .unwrap();
+                self.held.push(object);
+            }
+        }
+
+        fn update(&mut self, _res: &mut Resources<'g>, _input: &Input) -> Transition<'g> {
+            self.next.take().unwrap_or(Transition::Stay)
+        }
+
+        fn exit(&mut self, res: &mut Resources<'g>) {
+            if let Some(layer) = self.layer.take() {
+                layer.release(&mut res.vram);
+            }
+            self.held.clear();
+        }
+    }
+
+    #[test_case]
+    fn transitions_return_resources(gba: &mut agb::Gba) {
+        let (gfx, vram) = gba.display.video.tiled0();
+        let oam = gba.display.object.get_managed();
+        let objects = ObjectPool::new(&oam);
+        let sprites = SpriteSet::load();
+        let mut res = Resources {
+            gfx: &gfx,
+            vram,
+            oam: &objects,
+            sprites: &sprites,
+            save: SaveFile::load(Box::new(MemoryBackend::new(128))),
+            alpha: 0,
+            level_bg: None,
+            #[cfg(feature = "debug-tools")]
+            debug: Default::default(),
+        };
+        oam.commit();
+        let start = Usage::now();
+
+        /* 積む -> 入れ替える -> 外す */
+        let first = Dummy::new(
+            1,
+            Transition::Push(Dummy::new(
+                3,
+                Transition::Replace(Dummy::new(5, Transition::Pop)),
+            )),
+        );
+        let mut stack = SceneStack::new(&mut res, first);
+        let input = Input::default();
+        assert_eq!(Usage::now().oam_slots, start.oam_slots + 1);
+        stack.update(&mut res, &input);
+        assert_eq!(stack.entries.len(), 2);
+        assert_eq!(Usage::now().oam_slots, start.oam_slots + 4);
+        stack.update(&mut res, &input);
+        assert_eq!(Usage::now().oam_slots, start.oam_slots + 6);
+        stack.update(&mut res, &input);
+        assert_eq!(stack.entries.len(), 1);
+
+        stack.reset(&mut res, Dummy::new(2, Transition::Stay));
+        stack.reset(&mut res, Dummy::new(0, Transition::Stay));
+        assert_eq!(stack.leaks(), 0);
+        assert_eq!(Usage::now(), start);
+    }
+}
@@ -0,0 +1,166 @@
+// レベルに置く仕掛け (トリガー) の並び。
+//
+// トリガーは「この矩形 (collision::Aabb) の中で、この条件を満たしたら」という組を並べたデータで、
+// Script が先頭から 1 つずつ待ち、満たされたら次へ進む。
+// 今はチュートリアルの関門だけに使っている (tutorial モジュール) が、
+// 条件の判定と進み方はレベルのデータとは切り離してあるので、演出の類も同じ並びに足せる。
+
+use alloc::vec::Vec;
+
+use crate::{
+    collision::{Aabb, Surface},
+    game::{Game, DROID_WIDTH},
+    player::PlayerState,
+    snapshot::{Encode, Reader},
+};
+
+// トリガーの条件。どれもドロイド君の中心が矩形の中にあるときだけ数える。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Condition {
+    // 入っただけ。
+    Enter,
+    // 地面 (か足場) から跳んだ。
+    Jump,
+    // 空中でもう一度跳んだ。
+    DoubleJump,
+    // りんごの上に降りた。
+    LandOnApple,
+}
+
+impl Condition {
+    // before はこのティックの前のドロイド君の状態 (Droid::state)。
+    fn is_met(self, before: PlayerState, game: &Game) -> bool {
+        let droid = &game.droid;
+        match self {
+            Condition::Enter => true,
+            Condition::Jump => {
+                before == PlayerState::Idle
+                    && matches!(droid.state, PlayerState::JumpSquat { double: false })
+            }
+            Condition::DoubleJump => {
+                matches!(before, PlayerState::Airborne { double: false })
+                    && matches!(droid.state, PlayerState::JumpSquat { double: true })
+            }
+            Condition::LandOnApple => {
+                /* 着地の前に UP を押していれば、着地したティックにジャンプ準備になっている */
+                matches!(before, PlayerState::Airborne { .. })
+                    && matches!(
+                        droid.state,
+                        PlayerState::Idle | PlayerState::JumpSquat { double: false }
+                    )
+                    && droid.y == game.apple.top_y()
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Trigger {
+    pub rect: Aabb,
+    pub condition: Condition,
+    /* 待っている間に出す案内 */
+    pub hint: &'static str,
+    /* 満たされるまで閉じておく関門の x 座標 (ドロイド君はこれより右へ行けない) */
+    pub gate: Option<i32>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Script {
+    triggers: &'static [Trigger],
+    /* 待っているトリガー */
+    next: usize,
+}
+
+impl Script {
+    pub fn new(triggers: &'static [Trigger]) -> Self {
+        Self { triggers, next: 0 }
+    }
+
+    // 待っているトリガー。全て満たされたら None 。
+    pub fn current(&self) -> Option<&'static Trigger> {
+        self.triggers.get(self.next)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.next == self.triggers.len()
+    }
+
+    // 1 ティック進めた game を見て、待っているトリガーが満たされたら次へ進む。満たされたものを返す。
+    pub fn update(&mut self, before: PlayerState, game: &Game) -> Option<&'static Trigger> {
+        let trigger = self.current()?;
+        let centre = (
+            game.droid.x + DROID_WIDTH / 2,
+            game.droid.y + DROID_WIDTH / 2,
+        );
+        if !trigger.rect.contains(centre) || !trigger.condition.is_met(before, game) {
+            return None;
+        }
+        self.next += 1;
+        Some(trigger)
+    }
+
+    // 待っているトリガーを書く (snapshot モジュール)。トリガーの並びはレベルのデータなので書かない。
+    pub fn encode_state(&self, bytes: &mut Vec<u8>) {
+        self.next.encode(bytes);
+    }
+
+    // encode_state で書いたものを、同じトリガーの並びで作ったこの仕掛けに戻す。
+    pub fn decode_state(&mut self, reader: &mut Reader) -> Option<()> {
+        let next = usize::decode(reader)?;
+        if next > self.triggers.len() {
+            return None;
+        }
+        self.next = next;
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use agb::input::Button;
+
+    use super::*;
+    use crate::{difficulty::Difficulty, input::Input};
+
+    const TRIGGERS: [Trigger; 2] = [
+        Trigger {
+            rect: Aabb::new(0, 0, 240, 160),
+            condition: Condition::Jump,
+            hint: "JUMP",
+            gate: None,
+        },
+        Trigger {
+            rect: Aabb::new(0, 0, 8, 8),
+            condition: Condition::Enter,
+            hint: "CORNER",
+            gate: None,
+        },
+    ];
+
+    // トリガーは並べた順に、条件を満たしたときだけ進む。
+    #[test_case]
+    fn triggers_fire_in_order(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        let mut script = Script::new(&TRIGGERS);
+        let tick = |game: &mut Game, script: &mut Script, buttons, previous| {
+            let before = game.droid.state;
+            game.update(&Input::new(buttons, previous));
+            script.update(before, game)
+        };
+        assert_eq!(
+            tick(&mut game, &mut script, Button::RIGHT, Button::empty()),
+            None
+        );
+        let fired = tick(&mut game, &mut script, Button::UP, Button::RIGHT);
+        assert_eq!(fired.map(|trigger| trigger.hint), Some("JUMP"));
+        /* 2 つ目は角の矩形の外では満たされない */
+        assert_eq!(script.current().map(|trigger| trigger.hint), Some("CORNER"));
+        for _ in 0..30 {
+            assert_eq!(
+                tick(&mut game, &mut script, Button::empty(), Button::empty()),
+                None
+            );
+        }
+        assert!(!script.is_done());
+    }
+}
@@ -0,0 +1,273 @@
+// シードを選ぶシーン。タイトル画面でエンドレスを選ぶとここへ来る。
+//
+// シードは 16 進 8 桁 (u32 そのもの) で、文字の表から A で 1 文字ずつ入れ、B で消す。
+// 同じシードなら塔もラン中の乱数も全て同じになる (rng モジュール参照) ので、
+// 結果画面やタイトル画面に出ているシードを教え合えば同じ塔で競える。
+// 「RANDOM」を選ぶとタイトルで待っていた時間からシードを決める。
+
+use alloc::{boxed::Box, string::String};
+use core::fmt::{self, Write};
+
+use agb::{display::Priority, input::Button};
+
+use crate::{
+    difficulty::Difficulty,
+    gameplay::GameScene,
+    gfx::rgb5,
+    input::Input,
+    layer::Layer,
+    mode::Mode,
+    modifiers::Modifiers,
+    rng::Rng,
+    scene::{Resources, Scene, Transition},
+    text::TextLayer,
+    title::TitleScene,
+};
+
+/* シードの桁数 */
+const CODE_LEN: usize = 8;
+const DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+/* 文字の表は 8 文字ずつ 2 行。その下の行に RANDOM と OK */
+const COLUMNS: usize = 8;
+const BUTTON_ROW: usize = DIGITS.len() / COLUMNS;
+
+// シードを 16 進 8 桁で表示する。
+pub struct SeedDisplay(pub u32);
+
+impl fmt::Display for SeedDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:08X}", self.0)
+    }
+}
+
+// 入れ終わった 8 桁をシードにする。表に無い文字や桁の過不足があれば None 。
+fn parse(code: &[u8]) -> Option<u32> {
+    if code.len() != CODE_LEN {
+        return None;
+    }
+    code.iter().try_fold(0, |seed: u32, &c| {
+        let digit = DIGITS.iter().position(|&d| d == c)?;
+        Some(seed << 4 | digit as u32)
+    })
+}
+
+pub struct SeedEntryScene<'g> {
+    difficulty: Difficulty,
+    modifiers: Modifiers,
+    /* RANDOM を選んだときの元 */
+    ticks: u32,
+    code: String,
+    /* (行, 列)。BUTTON_ROW の行は 0 が RANDOM 、1 が OK */
+    cursor: (usize, usize),
+    text: Option<TextLayer<'g>>,
+    dirty: bool,
+}
+
+impl SeedEntryScene<'_> {
+    // ticks はタイトル画面で待っていたティック数。
+    pub fn new(difficulty: Difficulty, modifiers: Modifiers, ticks: u32) -> Self {
+        Self {
+            difficulty,
+            modifiers,
+            ticks,
+            code: String::new(),
+            /* すぐ遊べるよう RANDOM から */
+            cursor: (BUTTON_ROW, 0),
+            text: None,
+            dirty: true,
+        }
+    }
+
+    fn move_cursor(&mut self, input: &Input) {
+        let (row, column) = self.cursor;
+        let width = if row == BUTTON_ROW { 2 } else { COLUMNS };
+        self.cursor = if input.is_just_pressed(Button::LEFT) {
+            (row, (column + width - 1) % width)
+        } else if input.is_just_pressed(Button::RIGHT) {
+            (row, (column + 1) % width)
+        } else if input.is_just_pressed(Button::UP) && row == BUTTON_ROW {
+            (row - 1, column * COLUMNS / 2)
+        } else if input.is_just_pressed(Button::UP) && row > 0 {
+            (row - 1, column)
+        } else if input.is_just_pressed(Button::DOWN) && row + 1 == BUTTON_ROW {
+            (BUTTON_ROW, column / (COLUMNS / 2))
+        } else if input.is_just_pressed(Button::DOWN) && row < BUTTON_ROW {
+            (row + 1, column)
+        } else {
+            return;
+        };
+        self.dirty = true;
+    }
+
+    fn start<'g>(&self, seed: u32) -> Transition<'g> {
+        log_info!(
+            "seeded climb on {:?}, seed {}",
+            self.difficulty,
+            SeedDisplay(seed)
+        );
+        let scene = GameScene::new(self.difficulty, Mode::Endless, self.modifiers, seed);
+        Transition::Replace(Box::new(scene))
+    }
+
+    // 文字の表の row 行目。カーソルのある所を [ ] で囲む。
+    fn grid_line(&self, row: usize) -> String {
+        let mut line = String::from("   ");
+        let cell = |line: &mut String, column: usize, label: &str| {
+            let (open, close) = if self.cursor == (row, column) {
+                ('[', ']')
+            } else {
+                (' ', ' ')
+            };
+            let _ = write!(line, "{}{}{}", open, label, close);
+        };
+        if row == BUTTON_ROW {
+            line.push_str("   ");
+            cell(&mut line, 0, "RANDOM");
+            line.push_str("     ");
+            cell(&mut line, 1, "OK");
+        } else {
+            for column in 0..COLUMNS {
+                let digit = DIGITS[row * COLUMNS + column];
+                cell(
+                    &mut line,
+                    column,
+                    core::str::from_utf8(&[digit]).unwrap_or("?"),
+                );
+            }
+        }
+        line
+    }
+}
+
+impl<'g> Scene<'g> for SeedEntryScene<'g> {
+    fn enter(&mut self, res: &mut Resources<'g>) {
+        res.vram.set_background_palette_colour(0, 0, rgb5(2, 2, 8));
+        let mut text = TextLayer::new(Layer::new(res.gfx, Priority::P0), &mut res.vram);
+        text.print_line(&mut res.vram, 4, format_args!("        SEEDED CLIMB"));
+        text.print_line(&mut res.vram, 17, format_args!("   A: ENTER  B: DELETE"));
+        text.set_visible(true);
+        self.text = Some(text);
+    }
+
+    fn update(&mut self, _res: &mut Resources<'g>, input: &Input) -> Transition<'g> {
+        self.move_cursor(input);
+        if input.is_just_pressed(Button::B) {
+            if self.code.pop().is_none() {
+                return Transition::Replace(Box::new(TitleScene::new()));
+            }
+            self.dirty = true;
+        }
+        let ok = parse(self.code.as_bytes());
+        if input.is_just_pressed(Button::START) {
+            if let Some(seed) = ok {
+                return self.start(seed);
+            }
+        }
+        if !input.is_just_pressed(Button::A) {
+            return Transition::Stay;
+        }
+        match self.cursor {
+            (BUTTON_ROW, 0) => {
+                /* 待っていたティック数は小さいので、混ぜてから使う */
+                let seed = Rng::new(self.ticks).u32();
+                return self.start(seed);
+            }
+            (BUTTON_ROW, _) => {
+                if let Some(seed) = ok {
+                    return self.start(seed);
+                }
+            }
+            (row, column) => {
+                if self.code.len() < CODE_LEN {
+                    self.code.push(DIGITS[row * COLUMNS + column] as char);
+                    self.dirty = true;
+                }
+            }
+        }
+        Transition::Stay
+    }
+
+    fn draw(&mut self, res: &mut Resources<'g>) {
+        if !self.dirty {
+            return;
+        }
+        let lines: [String; BUTTON_ROW + 1] = core::array::from_fn(|row| self.grid_line(row));
+        let Some(text) = &mut self.text else {
+            return;
+        };
+        text.print_line(
+            &mut res.vram,
+            7,
+            format_args!("        SEED {:_<8}", self.code),
+        );
+        for (row, line) in lines.iter().enumerate() {
+            text.print_line(&mut res.vram, 10 + 2 * row as u16, format_args!("{}", line));
+        }
+        text.commit(&mut res.vram);
+        self.dirty = false;
+    }
+
+    fn exit(&mut self, res: &mut Resources<'g>) {
+        if let Some(text) = self.text.take() {
+            text.release(&mut res.vram);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+
+    use super::*;
+    use crate::{game::Game, replay::Replay};
+
+    #[test_case]
+    fn codes_round_trip(_gba: &mut agb::Gba) {
+        for seed in [0, 1, 0x0a1b_2c3d, u32::MAX] {
+            let code = alloc::format!("{}", SeedDisplay(seed));
+            assert_eq!(parse(code.as_bytes()), Some(seed));
+        }
+        assert_eq!(parse(b"0A1B2C3"), None);
+        assert_eq!(parse(b"0A1B2C3G"), None);
+    }
+
+    // replay の記録を始めたときの Game 。受け渡すたびに Game がフレームに並ぶので、
+    // テストのフレームに置かないよう分けてヒープに作る (ghost のテストと同じ)。
+    #[inline(never)]
+    fn started(replay: &Replay) -> Box<Game> {
+        Box::new(replay.start())
+    }
+
+    // seed のエンドレスを決まった入力で 1200 ティック遊び、最後の Game と記録を返す。
+    fn play(seed: u32) -> (Box<Game>, Replay) {
+        let mut replay = Replay::new(Difficulty::Normal, Mode::Endless, Modifiers::NONE, seed);
+        let mut game = started(&replay);
+        for tick in 0..1200u32 {
+            let buttons = match tick % 90 {
+                0..=1 => Button::UP,
+                10..=11 => Button::UP,
+                20..=50 => Button::RIGHT,
+                _ => Button::empty(),
+            };
+            let input = replay.record(&Input::new(buttons, Button::empty()));
+            game.update(&input);
+        }
+        (game, replay)
+    }
+
+    // 同じシードと同じ入力なら、エンドレスの塔も得点も最後の乱数の状態も同じになる。
+    #[test_case]
+    fn a_seed_and_inputs_decide_the_whole_climb(_gba: &mut agb::Gba) {
+        let seed = parse(b"0A1B2C3D").unwrap();
+        let (first, replay) = play(seed);
+        let (second, _) = play(seed);
+        assert_eq!(first, second);
+        assert_eq!(first.seed, seed);
+        let mut replayed = started(&replay);
+        for input in replay.inputs() {
+            replayed.update(&input);
+        }
+        assert_eq!(replayed, first);
+        assert_eq!(replayed.rng.state(), first.rng.state());
+    }
+}
@@ -0,0 +1,77 @@
+// ヒップドロップで着地したときの衝撃波。
+//
+// 着地した所 (ドロイド君の足元の真ん中) から左右へ SHOCKWAVE_SPEED ずつ広がり、SHOCKWAVE_TICKS で消える。
+// 同時に出るのは 1 つだけで、次のヒップドロップで出し直す。
+// 敵に効かせるときは、Game::handle_events の Event::GroundPound で
+// 広がっている範囲 (reaches) に入っているものを探す。
+
+use crate::{components::Lifetime, game::DROID_WIDTH, snapshot::encode_fields};
+
+/* 衝撃波が消えるまでのティック数と、1 ティックに広がるドット数 */
+pub const SHOCKWAVE_TICKS: u16 = 12;
+const SHOCKWAVE_SPEED: i32 = 3;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Shockwave {
+    /* 広がり始めた所 (足元の真ん中) と、ドロイド君の y 座標 */
+    pub x: i32,
+    pub y: i32,
+    life: Lifetime,
+}
+
+encode_fields!(Shockwave { x, y, life });
+
+impl Shockwave {
+    pub fn new(x: i32, y: i32) -> Self {
+        Self {
+            x,
+            y,
+            life: Lifetime::new(SHOCKWAVE_TICKS),
+        }
+    }
+
+    // 1 ティック進める。消えたら false。
+    pub fn tick(&mut self) -> bool {
+        self.life.tick();
+        self.life.is_alive()
+    }
+
+    // 始めた所から左右へ広がったドット数。
+    pub fn reach(&self) -> i32 {
+        i32::from(SHOCKWAVE_TICKS - self.life.remaining()) * SHOCKWAVE_SPEED
+    }
+
+    // 左上が (x, y) でドロイド君と同じ大きさのものに、衝撃波が届いているか。
+    // (敵が入るまではテストからしか使わない)
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn reaches(&self, x: i32, y: i32) -> bool {
+        let centre = x + DROID_WIDTH / 2;
+        (centre - self.x).abs() <= self.reach() + DROID_WIDTH / 2
+            && (y - self.y).abs() < DROID_WIDTH
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 衝撃波は同じ高さのものにだけ、広がった分だけ届き、SHOCKWAVE_TICKS で消える。
+    #[test_case]
+    fn a_shockwave_spreads_along_the_ground(_gba: &mut agb::Gba) {
+        let mut shockwave = Shockwave::new(100, 120);
+        assert!(shockwave.reaches(100 - DROID_WIDTH / 2, 120));
+        assert!(!shockwave.reaches(130, 120));
+        for _ in 1..SHOCKWAVE_TICKS {
+            assert!(shockwave.tick());
+        }
+        assert_eq!(
+            shockwave.reach(),
+            i32::from(SHOCKWAVE_TICKS - 1) * SHOCKWAVE_SPEED
+        );
+        assert!(shockwave.reaches(130, 120));
+        assert!(shockwave.reaches(70 - DROID_WIDTH, 120));
+        /* 上の段には届かない */
+        assert!(!shockwave.reaches(100, 120 - DROID_WIDTH));
+        assert!(!shockwave.tick());
+    }
+}
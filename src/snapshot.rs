@@ -0,0 +1,340 @@
+// ゲームの状態のバイト列 (スナップショット)。
+//
+// Encode を実装した値を to_bytes でバイト列にし、from_bytes で元の値に戻す。Game も Encode で、
+// ドロイド君もエンティティも乱数もタイマーもイベントの列も書くので、戻したものは全く同じ続きになる。
+// Game を丸ごと書いたもの (Snapshot) を練習モードで覚える状態に使い (practice モジュール)、巻き戻しやリプレイの検証
+// (途中から流し直して同じ展開になるか) の土台にもする。
+//
+// 書くのはフィールドを決まった順に並べたものだけ (リトルエンディアン、番地や詰め物は含まない)。
+// 先頭の 1 バイトは形の版 (FORMAT) で、形を変えたら上げる。SRAM には残さないので古い版は読まない。
+// 型ごとの書き方は、その型のフィールドが見えるモジュールに encode_fields! か encode_variants! で書く。
+//
+// Game のうちレベルから決まって遊んでいる間に変わらないもの (地面の材質、はしご...) と、
+// ROM のデータを指す参照 (案内の文、Task の段階の並び) は書かない。戻すときは先頭の難易度・モード・修飾・シードで
+// Game を作り直し、そちらのものを使う (Game の Encode は game モジュール)。
+
+use alloc::vec::Vec;
+
+use crate::{game::Game, math::Fixed};
+
+/* バイト列の形の版 */
+const FORMAT: u8 = 1;
+
+pub trait Encode: Sized {
+    // bytes の後ろに書き足す。
+    fn encode(&self, bytes: &mut Vec<u8>);
+
+    // encode したバイト列から読む。足りないか値がおかしければ None 。
+    fn decode(reader: &mut Reader) -> Option<Self>;
+}
+
+// decode 中のバイト列の、まだ読んでいない残り。
+pub struct Reader<'b> {
+    bytes: &'b [u8],
+}
+
+impl Reader<'_> {
+    // 先頭の N バイトを読み進める。
+    pub fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let (head, rest) = self.bytes.split_first_chunk::<N>()?;
+        self.bytes = rest;
+        Some(*head)
+    }
+}
+
+pub fn to_bytes(value: &impl Encode) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.push(FORMAT);
+    value.encode(&mut bytes);
+    bytes
+}
+
+// to_bytes したバイト列から読む。版が違うか、足りないか余るか、値がおかしければ None 。
+pub fn from_bytes<T: Encode>(bytes: &[u8]) -> Option<T> {
+    let mut reader = Reader { bytes };
+    if reader.take()? != [FORMAT] {
+        return None;
+    }
+    let value = T::decode(&mut reader)?;
+    reader.bytes.is_empty().then_some(value)
+}
+
+// フィールドを並べた順に書いて読む Encode の実装。型の全てのフィールドを並べる。
+macro_rules! encode_fields {
+    ($type:ty { $($field:ident),* $(,)? }) => {
+        impl $crate::snapshot::Encode for $type {
+            fn encode(&self, bytes: &mut alloc::vec::Vec<u8>) {
+                $($crate::snapshot::Encode::encode(&self.$field, bytes);)*
+            }
+
+            fn decode(reader: &mut $crate::snapshot::Reader) -> Option<Self> {
+                Some(Self {
+                    $($field: $crate::snapshot::Encode::decode(reader)?,)*
+                })
+            }
+        }
+    };
+}
+pub(crate) use encode_fields;
+
+// 値を持たない列挙型を、並べた順の番号 1 バイトで書いて読む Encode の実装。全ての値を並べる。
+macro_rules! encode_variants {
+    ($type:ty { $($variant:ident),* $(,)? }) => {
+        impl $crate::snapshot::Encode for $type {
+            fn encode(&self, bytes: &mut alloc::vec::Vec<u8>) {
+                let index = [$(Self::$variant),*].iter().position(|variant| variant == self);
+                bytes.push(index.unwrap_or_default() as u8);
+            }
+
+            fn decode(reader: &mut $crate::snapshot::Reader) -> Option<Self> {
+                let [index] = reader.take()?;
+                [$(Self::$variant),*].get(usize::from(index)).copied()
+            }
+        }
+    };
+}
+pub(crate) use encode_variants;
+
+macro_rules! encode_integers {
+    ($($type:ty),*) => {
+        $(
+            impl Encode for $type {
+                fn encode(&self, bytes: &mut Vec<u8>) {
+                    bytes.extend_from_slice(&self.to_le_bytes());
+                }
+
+                fn decode(reader: &mut Reader) -> Option<Self> {
+                    reader.take().map(<$type>::from_le_bytes)
+                }
+            }
+        )*
+    };
+}
+
+encode_integers!(u8, u16, u32, i32);
+
+/* 添字や数は 32 ビットで足りる */
+impl Encode for usize {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        (*self as u32).encode(bytes);
+    }
+
+    fn decode(reader: &mut Reader) -> Option<Self> {
+        u32::decode(reader).map(|value| value as usize)
+    }
+}
+
+impl Encode for bool {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        bytes.push(u8::from(*self));
+    }
+
+    fn decode(reader: &mut Reader) -> Option<Self> {
+        match reader.take()? {
+            [0] => Some(false),
+            [1] => Some(true),
+            _ => None,
+        }
+    }
+}
+
+impl Encode for Fixed {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        self.to_raw().encode(bytes);
+    }
+
+    fn decode(reader: &mut Reader) -> Option<Self> {
+        i32::decode(reader).map(Fixed::from_raw)
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        self.is_some().encode(bytes);
+        if let Some(value) = self {
+            value.encode(bytes);
+        }
+    }
+
+    fn decode(reader: &mut Reader) -> Option<Self> {
+        if bool::decode(reader)? {
+            T::decode(reader).map(Some)
+        } else {
+            Some(None)
+        }
+    }
+}
+
+impl<A: Encode, B: Encode> Encode for (A, B) {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        self.0.encode(bytes);
+        self.1.encode(bytes);
+    }
+
+    fn decode(reader: &mut Reader) -> Option<Self> {
+        Some((A::decode(reader)?, B::decode(reader)?))
+    }
+}
+
+impl<T: Encode, const N: usize> Encode for [T; N] {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        for item in self {
+            item.encode(bytes);
+        }
+    }
+
+    fn decode(reader: &mut Reader) -> Option<Self> {
+        let items: Vec<T> = (0..N).map(|_| T::decode(reader)).collect::<Option<_>>()?;
+        items.try_into().ok()
+    }
+}
+
+/* 長さ (usize) を先に書く */
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        self.len().encode(bytes);
+        for item in self {
+            item.encode(bytes);
+        }
+    }
+
+    fn decode(reader: &mut Reader) -> Option<Self> {
+        let len = usize::decode(reader)?;
+        /* 壊れた長さで長く回らないよう、残りより多くは読まない */
+        if len > reader.bytes.len() {
+            return None;
+        }
+        (0..len).map(|_| T::decode(reader)).collect()
+    }
+}
+
+// Game を丸ごと書いたバイト列。
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    bytes: Vec<u8>,
+}
+
+encode_fields!(Snapshot { bytes });
+
+impl Snapshot {
+    // game を書く。
+    pub fn take(game: &Game) -> Self {
+        Self {
+            bytes: to_bytes(game),
+        }
+    }
+
+    // game を書いたときの状態に戻す。調整値とデバッグメニューのすり抜けは書いていないので、今のものを残す。
+    // Game は大きいので、呼び出し側のフレームに読んだものを並べないよう分けておく。
+    #[inline(never)]
+    pub fn restore(&self, game: &mut Game) {
+        let Some(mut restored) = from_bytes::<Game>(&self.bytes) else {
+            /* 同じ ROM で書いたものなので読めるはず */
+            log_warn!("snapshot could not be read");
+            return;
+        };
+        restored.tuning = game.tuning;
+        #[cfg(feature = "debug-tools")]
+        {
+            restored.noclip = game.noclip;
+        }
+        *game = restored;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+
+    use agb::input::Button;
+
+    use super::*;
+    use crate::{difficulty::Difficulty, game::Game, input::Input, mode::Mode};
+
+    // Game はテストのフレームに並べずヒープに作る (replay のテストと同じ)。
+    #[inline(never)]
+    fn started(mode: Mode) -> Box<Game> {
+        Box::new(Game::new(Difficulty::Normal, 7).with_mode(mode))
+    }
+
+    #[inline(never)]
+    fn read(bytes: &[u8]) -> Option<Box<Game>> {
+        from_bytes(bytes).map(Box::new)
+    }
+
+    // 右へ歩きながら、ときどき跳ぶ。50 ティック目に L も押す (練習モードで覚える)。
+    fn play(game: &mut Game, ticks: u32) {
+        let mut previous = Button::empty();
+        for tick in 0..ticks {
+            let mut buttons = if tick % 40 < 3 {
+                Button::RIGHT | Button::UP
+            } else {
+                Button::RIGHT
+            };
+            if tick == 50 {
+                buttons |= Button::L;
+            }
+            game.update(&Input::new(buttons, previous));
+            previous = buttons;
+        }
+    }
+
+    // 途中で書き出して読み戻すと元と同じ Game になり、同じ入力で流せば乱数もタイマーも含めて同じ展開になる。
+    #[test_case]
+    fn a_game_read_back_plays_on_the_same(_gba: &mut agb::Gba) {
+        for mode in [
+            Mode::CoinRush,
+            Mode::Endless,
+            Mode::Tutorial,
+            Mode::Practice,
+        ] {
+            let mut game = started(mode);
+            play(&mut game, 100);
+            let mut restored = read(&to_bytes(&*game)).unwrap();
+            assert_eq!(*restored, *game, "{mode:?}");
+            play(&mut game, 200);
+            play(&mut restored, 200);
+            assert_eq!(*restored, *game, "{mode:?}");
+        }
+    }
+
+    // Snapshot で戻すと書いたときの Game になるが、調整値はその後に変えたものが残る。
+    #[test_case]
+    fn a_snapshot_restores_the_game_but_not_the_tuning(_gba: &mut agb::Gba) {
+        let mut game = started(Mode::CoinRush);
+        play(&mut game, 100);
+        let snapshot = Snapshot::take(&game);
+        let mut expected = game.clone();
+        play(&mut game, 100);
+        game.tuning.max_jump_height += 8;
+        snapshot.restore(&mut game);
+        expected.tuning = game.tuning;
+        assert_eq!(game, expected);
+    }
+
+    // スピードランで先のレベルにいれば、読むときにそのレベルを置き直す。
+    #[test_case]
+    fn a_later_speedrun_level_is_laid_again(_gba: &mut agb::Gba) {
+        let mut game = started(Mode::Speedrun);
+        play(&mut game, 200);
+        game.coins.retain(|_| false);
+        play(&mut game, 1);
+        assert_ne!(game.collision_map, started(Mode::Speedrun).collision_map);
+        let restored = read(&to_bytes(&*game)).unwrap();
+        assert_eq!(*restored, *game);
+    }
+
+    // 版が違うか、途中で切れているか、後ろに余計なものがあれば読まない。
+    #[test_case]
+    fn broken_bytes_are_not_read(_gba: &mut agb::Gba) {
+        let bytes = to_bytes(&*started(Mode::Normal));
+        assert!(read(&bytes).is_some());
+        assert!(read(&bytes[..bytes.len() - 1]).is_none());
+        let mut longer = bytes.clone();
+        longer.push(0);
+        assert!(read(&longer).is_none());
+        let mut newer = bytes;
+        newer[0] += 1;
+        assert!(read(&newer).is_none());
+    }
+}
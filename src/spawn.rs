@@ -0,0 +1,278 @@
+// エンティティとオブジェクトの組み立て。
+//
+// ゲームロジック側のエンティティは Game::spawn() から、
+// 表示側のオブジェクトは ObjectSpawn から作る。
+// 呼び出し側ごとに作り方 (置き場所、重なり順、表示するかどうか...) が
+// 少しずつ違ってしまわないよう、どちらも必ずここを通す。
+//
+// エンティティは OAM を持たないので、表示側でオブジェクトが作れなくても
+// ゲームはそのまま進む (表示されないだけ)。
+
+use agb::display::{object::Sprite, Priority};
+
+use crate::{
+    arena::EntityId,
+    collision::{Aabb, Passage},
+    crumbling::CrumblingPlatform,
+    door::{Door, Key},
+    game::{Apple, Coin, Game},
+    moving_platform::MovingPlatform,
+    oam::{Category, ObjectPool, Pooled},
+    spike::Spike,
+    spring::Spring,
+    switch::Switch,
+    teleporter::Teleporter,
+    water::Water,
+    wind::Wind,
+    zone::{Zone, ZoneAction},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EntityKind {
+    Coin,
+    // 乗れるりんご。狭いりんごの修飾も掛かる。
+    Apple,
+    // 置いた位置と to の間を往復する。passage は下から通り抜けられるか。
+    MovingPlatform {
+        to: (i32, i32),
+        passage: Passage,
+    },
+    // 置いた位置と同じ x の to_y の間を上下し、両端で wait ティックずつ止まる。下から通り抜けられる。
+    Elevator {
+        to_y: i32,
+        wait: u16,
+    },
+    // 乗ると崩れ落ち、しばらくすると元に戻る。
+    CrumblingPlatform,
+    // 上に乗ったドロイド君を打ち上げる。
+    Spring,
+    // 跳んで踏むと、A と B のブロックの固さが入れ替わる。
+    Switch,
+    // 触れると拾い、持っている数が増える。
+    Key,
+    // 鍵を持って触れるまで通れない。
+    Door,
+    // 触れるとダメージを受ける。
+    Spike,
+    // 同じ pair の台と対になり、立って UP と A を押すと相手の台へ移る。
+    Teleporter {
+        pair: u8,
+    },
+    // 大きさ size (幅, 高さ) の水。中ではゆっくり動き、泳げる。
+    Water {
+        size: (i32, i32),
+    },
+    // 大きさ size (幅, 高さ) の風。宙にいるドロイド君の速度に、毎ティック force (右と下が正) を足す。
+    Wind {
+        size: (i32, i32),
+        force: (i32, i32),
+    },
+    // 大きさ size (幅, 高さ) の当たらない矩形。出入りで action が起きる。
+    Zone {
+        size: (i32, i32),
+        action: ZoneAction,
+    },
+}
+
+pub struct EntitySpawn<'g> {
+    game: &'g mut Game,
+    kind: EntityKind,
+    position: (i32, i32),
+}
+
+impl Game {
+    // kind のエンティティを作り始める。build() するまで何も置かれない。
+    pub fn spawn(&mut self, kind: EntityKind) -> EntitySpawn<'_> {
+        EntitySpawn {
+            game: self,
+            kind,
+            position: (0, 0),
+        }
+    }
+}
+
+impl EntitySpawn<'_> {
+    // 左上の座標。
+    pub fn at(mut self, position: (i32, i32)) -> Self {
+        self.position = position;
+        self
+    }
+
+    // 置き場所を確保して置く。種類ごとの置き場所が満杯なら置かずに None 。
+    pub fn build(self) -> Option<EntityId> {
+        let (x, y) = self.position;
+        let placed = match self.kind {
+            EntityKind::Coin => self.game.coins.spawn(Coin { x, y }).ok(),
+            EntityKind::Apple => {
+                let half_width = self.game.apple_half_width();
+                self.game.apples.spawn(Apple { x, y, half_width }).ok()
+            }
+            EntityKind::MovingPlatform { to, passage } => self
+                .game
+                .moving_platforms
+                .spawn(MovingPlatform::new((x, y), to, passage))
+                .ok(),
+            EntityKind::Elevator { to_y, wait } => self
+                .game
+                .moving_platforms
+                .spawn(MovingPlatform::new((x, y), (x, to_y), Passage::OneWay).with_wait(wait))
+                .ok(),
+            EntityKind::CrumblingPlatform => self
+                .game
+                .crumbling_platforms
+                .spawn(CrumblingPlatform::new(x, y))
+                .ok(),
+            EntityKind::Spring => self.game.springs.spawn(Spring::new(x, y)).ok(),
+            EntityKind::Switch => self.game.switches.spawn(Switch::new(x, y)).ok(),
+            EntityKind::Key => self.game.keys.spawn(Key { x, y }).ok(),
+            EntityKind::Door => self.game.place_door(Door { x, y }),
+            EntityKind::Spike => self.game.spikes.spawn(Spike { x, y }).ok(),
+            EntityKind::Teleporter { pair } => {
+                self.game.place_teleporter(Teleporter::new(x, y, pair))
+            }
+            EntityKind::Water {
+                size: (width, height),
+            } => self
+                .game
+                .waters
+                .spawn(Water {
+                    rect: Aabb::new(x, y, width, height),
+                })
+                .ok(),
+            EntityKind::Wind {
+                size: (width, height),
+                force,
+            } => self
+                .game
+                .winds
+                .spawn(Wind::new(Aabb::new(x, y, width, height), force))
+                .ok(),
+            EntityKind::Zone {
+                size: (width, height),
+                action,
+            } => self
+                .game
+                .zones
+                .spawn(Zone::new(Aabb::new(x, y, width, height), action))
+                .ok(),
+        };
+        if placed.is_none() {
+            log_warn!("no room for {:?} at {:?}", self.kind, self.position);
+        }
+        placed
+    }
+}
+
+pub struct ObjectSpawn<'g> {
+    objects: &'g ObjectPool<'g>,
+    sprite: &'static Sprite,
+    category: Category,
+    position: (i32, i32),
+    z: i32,
+    hflip: bool,
+    priority: Priority,
+    visible: bool,
+}
+
+impl<'g> ObjectSpawn<'g> {
+    pub fn new(objects: &'g ObjectPool<'g>, sprite: &'static Sprite, category: Category) -> Self {
+        Self {
+            objects,
+            sprite,
+            category,
+            position: (0, 0),
+            z: 0,
+            hflip: false,
+            priority: Priority::P0,
+            visible: true,
+        }
+    }
+
+    pub fn at(mut self, position: (i32, i32)) -> Self {
+        self.position = position;
+        self
+    }
+
+    // 重なり順。小さいほど手前。
+    pub fn z(mut self, z: i32) -> Self {
+        self.z = z;
+        self
+    }
+
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn hflip(mut self, hflip: bool) -> Self {
+        self.hflip = hflip;
+        self
+    }
+
+    // 背景との重なり順。背景と同じなら手前に出る。
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    // false なら隠したまま作る (出すのは呼び出し側で show)。
+    pub fn visible(mut self, visible: bool) -> Self {
+        self.visible = visible;
+        self
+    }
+
+    // オブジェクトを作る。OAM に載るのは次の commit 。
+    // OAM かスプライト VRAM が足りなければ None (ObjectPool 参照)。
+    // エンティティに付けるものは、呼び出し側がそのエンティティの置き場所 (Arena の枠) ごとに持っておく。
+    pub fn build(self) -> Option<Pooled<'g>> {
+        let mut object = self.objects.try_object(self.sprite, self.category)?;
+        object
+            .set_position(self.position)
+            .set_z(self.z)
+            .set_hflip(self.hflip)
+            .set_priority(self.priority);
+        if self.visible {
+            object.show();
+        }
+        Some(object)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{difficulty::Difficulty, game::MAX_COINS, resources::SpriteSet};
+
+    #[test_case]
+    fn entities_are_placed_until_their_storage_is_full(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        let before = game.coins.len();
+        let id = game.spawn(EntityKind::Coin).at((8, 16)).build().unwrap();
+        assert_eq!(game.coins.get(id), Some(&Coin { x: 8, y: 16 }));
+        for _ in before + 1..MAX_COINS {
+            assert!(game.spawn(EntityKind::Coin).build().is_some());
+        }
+        assert_eq!(game.spawn(EntityKind::Coin).build(), None);
+        assert_eq!(game.coins.len(), MAX_COINS);
+    }
+
+    #[test_case]
+    fn objects_come_out_as_specified(gba: &mut agb::Gba) {
+        let oam = gba.display.object.get_managed();
+        let objects = ObjectPool::new(&oam);
+        let sprites = SpriteSet::load();
+        let object = ObjectSpawn::new(&objects, sprites.apple, Category::Platform)
+            .at((40, 50))
+            .z(2)
+            .hflip(true)
+            .priority(Priority::P2)
+            .build()
+            .unwrap();
+        assert_eq!((object.position().x, object.position().y), (40, 50));
+        assert!(object.hflip());
+        assert_eq!(object.priority(), Priority::P2);
+        assert!(object.is_visible());
+        let hidden = ObjectSpawn::new(&objects, sprites.apple, Category::Scenery)
+            .visible(false)
+            .build()
+            .unwrap();
+        assert!(!hidden.is_visible());
+    }
+}
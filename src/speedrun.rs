@@ -0,0 +1,264 @@
+// スピードラン。ゲームを最初から最後まで通して、タイムを競う。
+//
+// ゲーム全体は LEVELS を順に遊ぶこと (今は普通のレベルと、それを左右反転したレベルの 2 面)。
+// 決まりはタイムアタックと同じ (カウントダウンとダメージのペナルティ) で、それに加えて:
+// - レベルのコインを全て取ると、タイムを止めずにすぐ次のレベルを読み込む (Game::update_speedrun)。
+// - レベルを終えるたびに区切りタイム (そのレベルのタイム、通しのタイム、最高記録の区切りとの差) を
+//   HUD にしばらく出し、ログにも残す。
+// - ポーズ中はタイムが止まるが、ポーズするたびに PAUSE_PENALTY_TICKS を足す
+//   (ポーズを挟んで入力をやり直すのを割に合わなくするため)。
+// - 最後のレベルを終えたタイムはスピードランの記録 (save::Board::Speedrun) と比べ、
+//   通しのタイムが縮んだら区切りタイムも全て次の比較用に保存する。
+// ミラーモードではレベルの順が入れ替わるので、区切りが比べられず記録も残さない。
+
+use crate::{game::Game, level::Level, math::Fixed, player::PlayerState, snapshot::encode_fields};
+
+/* 遊ぶ順のレベル。true は左右を反転したレベル */
+const LEVELS: [bool; 2] = [false, true];
+pub const SEGMENTS: usize = LEVELS.len();
+/* ポーズ 1 回で足されるタイム */
+const PAUSE_PENALTY_TICKS: u32 = 2 * 60;
+/* 区切りタイムを HUD に出しておくティック数 */
+const SPLIT_TICKS: u32 = 3 * 60;
+
+// segment 番目のレベル。mirror ならミラーモード。
+pub fn level(segment: usize, mirror: bool) -> Level {
+    Level::load(LEVELS[segment] != mirror)
+}
+
+// レベルを 1 つ終えたときの区切り。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Split {
+    /* 何番目のレベルか (0 始まり) */
+    pub segment: usize,
+    /* そのレベルだけのタイムと、最初からの通しのタイム */
+    pub level_time: u32,
+    pub total: u32,
+    /* 最高記録の同じ区切りとの差 (ティック、負なら速い)。記録が無ければ None */
+    pub delta: Option<i32>,
+}
+
+encode_fields!(Split {
+    segment,
+    level_time,
+    total,
+    delta
+});
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Speedrun {
+    /* 今遊んでいるレベル */
+    segment: usize,
+    /* レベルごとの、終えたときの通しのタイム */
+    splits: [Option<u32>; SEGMENTS],
+    /* 比べる最高記録の区切り */
+    comparison: Option<[u32; SEGMENTS]>,
+    /* 最後の区切りと、それを出した Game::time */
+    last: Option<(Split, u32)>,
+}
+
+encode_fields!(Speedrun {
+    segment,
+    splits,
+    comparison,
+    last
+});
+
+impl Speedrun {
+    pub fn new() -> Self {
+        Self {
+            segment: 0,
+            splits: [None; SEGMENTS],
+            comparison: None,
+            last: None,
+        }
+    }
+
+    // 区切りを比べる最高記録。始める前に決める。
+    pub fn set_comparison(&mut self, comparison: Option<[u32; SEGMENTS]>) {
+        self.comparison = comparison;
+    }
+
+    pub fn comparison(&self) -> Option<[u32; SEGMENTS]> {
+        self.comparison
+    }
+
+    // 今遊んでいるレベル。最後のレベルを終えた後は最後のレベルのまま。
+    pub fn level(&self, mirror: bool) -> Level {
+        level(self.segment.min(SEGMENTS - 1), mirror)
+    }
+
+    // 最後のレベルまで終えていれば、全ての区切り (通しのタイム)。
+    pub fn splits(&self) -> Option<[u32; SEGMENTS]> {
+        let mut splits = [0; SEGMENTS];
+        for (split, time) in splits.iter_mut().zip(self.splits) {
+            *split = time?;
+        }
+        Some(splits)
+    }
+
+    // 最後のレベルを終えたか。
+    pub fn is_finished(&self) -> bool {
+        self.segment == SEGMENTS
+    }
+
+    // 今のレベルを time で終えた。
+    fn split(&mut self, time: u32) -> Split {
+        let start = match self.segment {
+            0 => 0,
+            segment => self.splits[segment - 1].unwrap_or(0),
+        };
+        let split = Split {
+            segment: self.segment,
+            level_time: time - start,
+            total: time,
+            delta: self
+                .comparison
+                .map(|comparison| time as i32 - comparison[self.segment] as i32),
+        };
+        self.splits[self.segment] = Some(time);
+        self.segment += 1;
+        self.last = Some((split, time));
+        split
+    }
+
+    // HUD に出す区切りと、出してからのティック数。出しておく時間を過ぎたら None 。
+    pub fn shown(&self, time: u32) -> Option<(Split, u32)> {
+        let (split, since) = self.last?;
+        let age = time - since;
+        (age < SPLIT_TICKS).then_some((split, age))
+    }
+}
+
+impl Game {
+    // スピードランの 1 ティック分。コインを取った後に呼ぶ。
+    pub fn update_speedrun(&mut self) {
+        let cleared = self.level_cleared();
+        let Some(speedrun) = &mut self.speedrun else {
+            return;
+        };
+        if !cleared || speedrun.is_finished() {
+            return;
+        }
+        let split = speedrun.split(self.time);
+        log_info!(
+            "split {}: level {}, total {}, delta {:?}",
+            split.segment + 1,
+            split.level_time,
+            split.total,
+            split.delta
+        );
+        if speedrun.is_finished() {
+            return;
+        }
+        self.load(&self.level());
+        /* 空中で最後のコインを取っても、次のレベルは立った状態から始める */
+        let droid = &mut self.droid;
+        droid.vy = Fixed::new(0);
+        droid.enter(PlayerState::Idle);
+        droid.walk.rewind();
+        droid.ch = 0;
+    }
+
+    // ポーズした。タイムが進んでいる間ならペナルティを足す。
+    pub fn pause_speedrun(&mut self) {
+        if self.speedrun.is_none() || self.countdown.is_running() || self.outcome().is_some() {
+            return;
+        }
+        self.time += PAUSE_PENALTY_TICKS;
+        log_debug!("pause penalty, time = {}", self.time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        difficulty::Difficulty, events::Event, game::Outcome, input::Input, mode::Mode,
+        time_attack::COUNTDOWN_TICKS,
+    };
+
+    fn speedrun() -> Game {
+        let mut game = Game::new(Difficulty::Normal, 1).with_mode(Mode::Speedrun);
+        for _ in 0..COUNTDOWN_TICKS {
+            game.update(&Input::default());
+        }
+        game
+    }
+
+    // 今のレベルのコインを全て取る。
+    fn clear_level(game: &mut Game) {
+        let coins: alloc::vec::Vec<_> = game.coins.iter().map(|(id, _)| id).collect();
+        for coin in coins {
+            game.push_event(Event::CoinCollected { coin });
+        }
+        game.update(&Input::default());
+    }
+
+    // レベルを終えるとタイムを止めずに次のレベルへ進み、最後のレベルを終えて初めてクリアになる。
+    #[test_case]
+    fn the_clock_runs_through_every_level(_gba: &mut agb::Gba) {
+        let mut game = speedrun();
+        game.speedrun
+            .as_mut()
+            .unwrap()
+            .set_comparison(Some([100, 300]));
+        for _ in 0..89 {
+            game.update(&Input::default());
+        }
+        clear_level(&mut game);
+        assert_eq!(game.outcome(), None);
+        let mirrored = level(1, false);
+        assert_eq!((game.droid.x, game.droid.y), mirrored.droid);
+        assert_eq!(
+            game.coins.len()
+                + game.apples.len()
+                + game.waters.len()
+                + game.winds.len()
+                + game.teleporters.len()
+                + game.zones.len(),
+            mirrored.objects.len()
+        );
+        let speedrun = game.speedrun.as_ref().unwrap();
+        let (split, age) = speedrun.shown(game.time).unwrap();
+        assert_eq!(age, 0);
+        assert_eq!(
+            split,
+            Split {
+                segment: 0,
+                level_time: 90,
+                total: 90,
+                delta: Some(-10),
+            }
+        );
+
+        /* ポーズするたびにタイムが足される */
+        game.pause_speedrun();
+        assert_eq!(game.time, 90 + PAUSE_PENALTY_TICKS);
+        for _ in 0..99 {
+            game.update(&Input::default());
+        }
+        clear_level(&mut game);
+        assert_eq!(game.outcome(), Some(Outcome::Cleared));
+        let total = 90 + PAUSE_PENALTY_TICKS + 100;
+        assert_eq!(game.time, total);
+        let speedrun = game.speedrun.as_ref().unwrap();
+        assert_eq!(speedrun.splits(), Some([90, total]));
+        let (split, _) = speedrun.shown(game.time).unwrap();
+        assert_eq!(split.level_time, PAUSE_PENALTY_TICKS + 100);
+        assert_eq!(split.delta, Some(total as i32 - 300));
+        /* 終わった後のポーズは何も足さない */
+        game.pause_speedrun();
+        assert_eq!(game.time, total);
+    }
+
+    // 全てのレベルは同じ地面なので、レベルを変えても背景を作り直さなくてよい。
+    #[test_case]
+    fn every_level_shares_the_ground(_gba: &mut agb::Gba) {
+        for mirror in [false, true] {
+            for segment in 0..SEGMENTS {
+                assert_eq!(level(segment, mirror).ground, level(0, false).ground);
+            }
+        }
+    }
+}
@@ -0,0 +1,128 @@
+// 棘。
+//
+// レベルの物の層 (コインラッシュでは Level::rush にだけ置いている) から置く、タイル 1 枚の大きさの動かない罠。
+// 当たり判定の層は Layers::HAZARD で、ドロイド君が触れると Event::PlayerDamaged を積む (Game::update_spikes)。
+// 体力やタイムの減り方、ノックバック、無敵時間はほかのダメージと同じく handle_events に任せるので、
+// 無敵時間の間は触れ続けても何も起きない。
+// 乗れも塞ぎもしないので、当たり判定のマップには入れない。
+// 背景のタイル (gameplay::SPIKE) は presenter がレベルを始めるときに描く。タイルの格子に揃え、下端を地面か壁の上面に揃えて置く。
+
+use crate::{
+    collision::{self, Aabb, Collider, Filter, Layers},
+    events::Event,
+    game::Game,
+    snapshot::encode_fields,
+};
+
+/* 同時に置ける棘の数 */
+pub const MAX_SPIKES: usize = 4;
+/* 棘の大きさ (タイル 1 枚) */
+pub const SPIKE_SIZE: i32 = 8;
+/* 絵の上の空いている行。当たり判定は尖ったところから下だけ */
+const SPIKE_TOP: i32 = 2;
+/* 触れたときのダメージ */
+const SPIKE_DAMAGE: u8 = 1;
+/* 棘はドロイド君にだけ当たる */
+const SPIKE_FILTER: Filter = Filter::new(Layers::HAZARD, Layers::PLAYER);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Spike {
+    /* 左上 */
+    pub x: i32,
+    pub y: i32,
+}
+
+encode_fields!(Spike { x, y });
+
+impl Collider for Spike {
+    fn hitbox(&self) -> Aabb {
+        Aabb::new(
+            self.x,
+            self.y + SPIKE_TOP,
+            SPIKE_SIZE,
+            SPIKE_SIZE - SPIKE_TOP,
+        )
+    }
+
+    fn filter(&self) -> Filter {
+        SPIKE_FILTER
+    }
+}
+
+impl Game {
+    // 触れている棘があればダメージを積む。ドロイド君を動かした後に呼ぶ。
+    pub fn update_spikes(&mut self) {
+        if self.spikes.is_empty() {
+            return;
+        }
+        /* 重なった棘は 1 つ分だけ */
+        let spike = collision::touching(&self.droid, &self.spikes).next();
+        if let Some(spike) = spike.and_then(|spike| self.spikes.get(spike)) {
+            let from_x = spike.x + SPIKE_SIZE / 2;
+            self.push_event(Event::PlayerDamaged {
+                amount: SPIKE_DAMAGE,
+                from_x,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use agb::input::Button;
+
+    use super::*;
+    use crate::{
+        difficulty::Difficulty,
+        game::{DROID_WIDTH, GROUND_Y, RUSH_OBJECTS},
+        input::Input,
+        math::Fixed,
+        mode::Mode,
+        player::{PlayerState, INVULNERABLE_TICKS},
+        spawn::EntityKind,
+    };
+
+    /* 出発点 (x = 120) の左の地面に置いた棘 */
+    const SPIKE: (i32, i32) = (96, GROUND_Y + DROID_WIDTH - SPIKE_SIZE);
+
+    fn walk_left(game: &mut Game) {
+        game.update(&Input::new(Button::LEFT, Button::LEFT));
+    }
+
+    // 触れると 1 だけ減って弾き返され、無敵時間が切れるまでは触れ続けても減らない。
+    #[test_case]
+    fn a_spike_hurts_once_per_invulnerability(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        let lives = game.health;
+        game.spawn(EntityKind::Spike).at(SPIKE).build().unwrap();
+        let mut ticks = 0;
+        while game.health == lives {
+            walk_left(&mut game);
+            ticks += 1;
+            assert!(ticks < 60, "never touched the spike");
+        }
+        assert_eq!(game.health, lives - 1);
+        assert_eq!(game.droid.state, PlayerState::Knockback);
+        /* 棘は左にあるので右へ飛ばされる */
+        assert!(game.droid.vx > Fixed::new(0));
+        for _ in 1..INVULNERABLE_TICKS {
+            walk_left(&mut game);
+            assert_eq!(game.health, lives - 1);
+        }
+        for _ in 0..60 {
+            walk_left(&mut game);
+        }
+        assert_eq!(game.health, lives - 2);
+    }
+
+    // コインラッシュでだけ、左の壁の上に棘がある。
+    #[test_case]
+    fn coin_rush_puts_a_spike_on_the_wall(_gba: &mut agb::Gba) {
+        let game = Game::new(Difficulty::Normal, 1);
+        assert!(game.spikes.is_empty());
+        let game = Game::new(Difficulty::Normal, 1).with_mode(Mode::CoinRush);
+        let spike = game.spikes.values().next().copied().unwrap();
+        assert_eq!(game.spikes.len(), 1);
+        assert!(RUSH_OBJECTS.contains(&(EntityKind::Spike, (spike.x, spike.y))));
+    }
+}
@@ -0,0 +1,79 @@
+// 区切りタイムの差 (+ss.ff / -ss.ff) の表示。
+//
+// 記録より速ければ緑、遅ければ赤で書き、出してすぐの FLASH_TICKS の間は白と交互に点滅させる。
+// 色は TextLayer が用意しているパレットバンクを使う (text モジュール参照)。
+
+use core::fmt;
+
+use agb::display::tiled::VRamManager;
+
+use crate::text::{TextLayer, GREEN_PALETTE, RED_PALETTE, TEXT_PALETTE};
+
+/* 速いとき (差が負) と遅いときのパレットバンク */
+const AHEAD_PALETTE: u8 = GREEN_PALETTE;
+const BEHIND_PALETTE: u8 = RED_PALETTE;
+/* 点滅させるティック数と、白と色を入れ替える間隔 */
+const FLASH_TICKS: u32 = 48;
+const FLASH_PERIOD: u32 = 8;
+/* 表示できる一番大きい差 (百分の一秒) */
+const MAX_CENTIS: u32 = 9999;
+
+// 差のティック数を符号付きの「秒.百分の一秒」で表示する。99.99 秒を超える差は 99.99 にする。
+pub struct Delta(pub i32);
+
+impl fmt::Display for Delta {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { '-' } else { '+' };
+        let centis = (self.0.unsigned_abs() * 100 / 60).min(MAX_CENTIS);
+        write!(f, "{}{:02}.{:02}", sign, centis / 100, centis % 100)
+    }
+}
+
+// 出してから age ティック経った差 delta を書く色。
+pub fn palette(delta: i32, age: u32) -> u8 {
+    if age < FLASH_TICKS && age / FLASH_PERIOD % 2 == 1 {
+        TEXT_PALETTE
+    } else if delta < 0 {
+        AHEAD_PALETTE
+    } else {
+        BEHIND_PALETTE
+    }
+}
+
+// (column, row) に差を書く。幅は 6 桁。
+pub fn draw(
+    text: &mut TextLayer,
+    vram: &mut VRamManager,
+    column: u16,
+    row: u16,
+    delta: i32,
+    age: u32,
+) {
+    let palette = palette(delta, age);
+    text.print_at(vram, column, row, palette, format_args!("{}", Delta(delta)));
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+
+    use super::*;
+
+    #[test_case]
+    fn deltas_are_signed_and_capped(_gba: &mut agb::Gba) {
+        assert_eq!(format!("{}", Delta(0)), "+00.00");
+        assert_eq!(format!("{}", Delta(-27)), "-00.45");
+        assert_eq!(format!("{}", Delta(5 * 60 + 14)), "+05.23");
+        assert_eq!(format!("{}", Delta(-200 * 60)), "-99.99");
+    }
+
+    // 点滅が終われば、速いか遅いかの色のまま。
+    #[test_case]
+    fn deltas_flash_then_settle(_gba: &mut agb::Gba) {
+        assert_eq!(palette(-1, 0), AHEAD_PALETTE);
+        assert_eq!(palette(-1, FLASH_PERIOD), TEXT_PALETTE);
+        assert_eq!(palette(1, 2 * FLASH_PERIOD), BEHIND_PALETTE);
+        assert!((FLASH_TICKS..FLASH_TICKS + 2 * FLASH_PERIOD)
+            .all(|age| palette(-1, age) == AHEAD_PALETTE));
+    }
+}
@@ -0,0 +1,164 @@
+// ばね。
+//
+// レベルの足場の層 (Level::platforms) から置く。上に乗ったドロイド君を、その場で Tuning::spring_speed で
+// 真上へ打ち上げる (Game::update_springs)。打ち上げられて上る間は、跳ねる地面と同じく UP を離しても遅くならない。
+// ばねは SPRING_SQUASH_TICKS の間縮んだ絵になってから伸びる。打ち上げたときに Sfx::Spring を鳴らす。
+//
+// 乗るのは他の乗れるものと同じ着地の判定 (collision::Surfaces) で、着地したティックのうちに打ち上げるので
+// ばねの上に立っていることは無い。下と横からは通り抜けられる。
+
+use crate::{
+    collision::{self, Aabb, Surface},
+    entity::{Entity, Look},
+    events::{Event, Sfx},
+    game::Game,
+    physics::Gravity,
+    player::PlayerState,
+    snapshot::encode_fields,
+    time::Timer,
+};
+
+/* 同時に置けるばねの数 */
+pub const MAX_SPRINGS: usize = 4;
+/* ばねの絵の幅 (スプライト 1 枚) */
+pub const SPRING_SIZE: i32 = 16;
+/* 伸びているときの板 (乗れる面) の、絵の左上からの位置と幅 */
+const SPRING_PLATE: Aabb = Aabb::new(2, 6, 12, 10);
+/* 打ち上げてから縮んだ絵を出しておくティック数 */
+const SPRING_SQUASH_TICKS: u16 = 8;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Spring {
+    /* 絵の左上 */
+    pub x: i32,
+    pub y: i32,
+    /* 縮んでいる残り */
+    squash: Timer,
+}
+
+encode_fields!(Spring { x, y, squash });
+
+impl Spring {
+    pub fn new(x: i32, y: i32) -> Self {
+        Self {
+            x,
+            y,
+            squash: Timer::new(),
+        }
+    }
+
+    // 縮んだ絵を出すか。
+    pub fn is_squashed(&self) -> bool {
+        self.squash.is_running()
+    }
+}
+
+impl Surface for Spring {
+    fn bounds(&self) -> Aabb {
+        Aabb::new(
+            self.x + SPRING_PLATE.x,
+            self.y + SPRING_PLATE.y,
+            SPRING_PLATE.width,
+            SPRING_PLATE.height,
+        )
+    }
+}
+
+impl Entity for Spring {
+    fn update(&mut self) {
+        self.squash.tick();
+    }
+
+    // 縮んでいれば絵の番号は 1 。
+    fn draw(&self) -> Option<Look> {
+        Some(Look::at(self.x, self.y).frame(u8::from(self.is_squashed())))
+    }
+}
+
+impl Game {
+    // ばねに乗っているドロイド君を打ち上げる。ドロイド君を動かした後 (着地した後) に呼ぶ。
+    pub fn update_springs(&mut self) {
+        let droid = &mut self.droid;
+        let mut launched = false;
+        for (_, spring) in self.springs.iter_mut() {
+            if droid.is_grounded()
+                && droid.gravity == Gravity::Down
+                && droid.y == spring.top_y()
+                && collision::supports(spring, droid.x)
+            {
+                droid.vy = self.tuning.spring_speed;
+                droid.enter(PlayerState::Airborne { double: false });
+                droid.bouncing = true;
+                spring.squash.start(SPRING_SQUASH_TICKS);
+                launched = true;
+            }
+        }
+        if launched {
+            self.push_event(Event::PlaySfx(Sfx::Spring));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use agb::input::Button;
+
+    use super::*;
+    use crate::{
+        arena::Arena,
+        difficulty::Difficulty,
+        game::{DROID_WIDTH, GROUND_Y},
+        input::Input,
+        math::Fixed,
+        physics::SPRING_HEIGHT,
+        spawn::EntityKind,
+    };
+
+    // 上に頭をぶつけるものが無いよう、レベルの動く足場は除いておく。
+    fn game_with_spring() -> Game {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        game.moving_platforms = Arena::new();
+        game.springs = Arena::new();
+        game.spawn(EntityKind::Spring)
+            .at((96, GROUND_Y))
+            .build()
+            .unwrap();
+        game
+    }
+
+    // 落ちてきて乗ると、UP を押していなくても SPRING_HEIGHT まで打ち上げられ、ばねは少しの間縮む。
+    #[test_case]
+    fn landing_on_a_spring_launches_the_droid(_gba: &mut agb::Gba) {
+        let mut game = game_with_spring();
+        game.droid.x = 96;
+        game.droid.y = 40;
+        game.droid.state = PlayerState::Airborne { double: false };
+        while game.droid.vy <= Fixed::new(0) {
+            game.update(&Input::default());
+        }
+        let (_, spring) = game.springs.iter().next().unwrap();
+        let top = spring.top_y();
+        assert_eq!(game.droid.y, top);
+        assert!(spring.is_squashed());
+        let mut highest = top;
+        while game.droid.vy > Fixed::new(0) {
+            game.update(&Input::default());
+            highest = highest.min(game.droid.y);
+        }
+        assert_eq!(top - highest, SPRING_HEIGHT);
+        assert!(!game.springs.iter().next().unwrap().1.is_squashed());
+    }
+
+    // 横から歩いて通り抜けても打ち上げない。
+    #[test_case]
+    fn walking_through_a_spring_does_nothing(_gba: &mut agb::Gba) {
+        let mut game = game_with_spring();
+        game.droid.x = 120;
+        game.droid.hflip = true;
+        for _ in 0..60 {
+            game.update(&Input::new(Button::LEFT, Button::LEFT));
+            assert_eq!(game.droid.y, GROUND_Y);
+        }
+        assert!(game.droid.x + DROID_WIDTH < 96);
+    }
+}
@@ -0,0 +1,208 @@
+// スイッチと、スイッチで固さの入れ替わるブロック。
+//
+// スイッチはレベルの足場の層 (Level::platforms) から、絵の下端を地面や足場の上面に揃えて置く。
+// スイッチ自体に当たり判定は無く、上に立つのは下の地面や足場。絵は背景のタイル (gameplay::SWITCH_TILES) で
+// 描くので、タイルの格子に揃えて置く。
+// 跳んで (落ちてきて) 上に着地すると踏まれ、A のブロックと B のブロックの固さが入れ替わる (Game::toggle_switch_blocks)。
+// 歩いて乗っても効かず、立っている間は踏まれた絵のままで、降りるまでもう一度は効かない。
+// どのスイッチも同じ組を入れ替える。踏んだときに Sfx::Switch を鳴らす。
+//
+// ブロックはレベルの列ごとに 1 つまで (Level::switch_blocks)。固い方の組 (Game::solid_set) は塗ったタイルで
+// 当たり判定のマップでも固く、もう一方は枠だけのタイルで通り抜けられる。どちらもタイルから当たり判定を決める
+// (tilemap::Cell::of_tile) ので、絵と当たり判定は食い違わない。レベルを始めるときは A が固い。
+// 固くなったブロックにドロイド君が重なっていても押し出さないので、スイッチのそばには置かない。
+
+use crate::{
+    collision::footprint,
+    entity::{Entity, Look},
+    events::{Event, Sfx},
+    game::{Game, DROID_WIDTH},
+    gameplay::SWITCH_BLOCK_TILES,
+    physics::Gravity,
+    snapshot::{encode_fields, encode_variants},
+    tilemap::Cell,
+};
+
+/* 同時に置けるスイッチの数 */
+pub const MAX_SWITCHES: usize = 2;
+/* スイッチの絵の幅と高さ (下半分だけを背景に描く) */
+pub const SWITCH_SIZE: i32 = 16;
+/* 踏める板の、絵の左端からの位置と幅 */
+const SWITCH_PLATE: (i32, i32) = (4, 8);
+
+// スイッチで固さの入れ替わるブロックの組。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockSet {
+    A,
+    B,
+}
+
+encode_variants!(BlockSet { A, B });
+
+impl BlockSet {
+    pub fn other(self) -> Self {
+        match self {
+            BlockSet::A => BlockSet::B,
+            BlockSet::B => BlockSet::A,
+        }
+    }
+
+    // この組のブロックのタイル (tiles::bg.tile_settings の添字)。固いときは塗り、通り抜けられるときは枠。
+    pub fn tile(self, solid: bool) -> usize {
+        SWITCH_BLOCK_TILES[self as usize][usize::from(!solid)]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Switch {
+    /* 絵の左上 */
+    pub x: i32,
+    pub y: i32,
+    /* ドロイド君が乗っている */
+    pub pressed: bool,
+}
+
+encode_fields!(Switch { x, y, pressed });
+
+impl Switch {
+    pub fn new(x: i32, y: i32) -> Self {
+        Self {
+            x,
+            y,
+            pressed: false,
+        }
+    }
+
+    // x, y にいるドロイド君が上に立っているか。
+    fn holds(&self, x: i32, y: i32) -> bool {
+        if y + DROID_WIDTH != self.y + SWITCH_SIZE {
+            return false;
+        }
+        let feet = footprint(x, y);
+        let (left, width) = SWITCH_PLATE;
+        feet.x < self.x + left + width && self.x + left < feet.right()
+    }
+}
+
+impl Entity for Switch {
+    // 押されていれば絵の番号は 1 。
+    fn draw(&self) -> Option<Look> {
+        Some(Look::at(self.x, self.y).frame(u8::from(self.pressed)))
+    }
+}
+
+impl Game {
+    // ドロイド君が踏んだスイッチを押す。ドロイド君を動かした後に、動かす前に宙にいたか (airborne) を渡して呼ぶ。
+    pub fn update_switches(&mut self, airborne: bool) {
+        let droid = &self.droid;
+        let standing = droid.is_grounded() && droid.gravity == Gravity::Down;
+        /* 宙にいたままなら、宙に出たティックに全て上がっている */
+        if airborne && !standing {
+            return;
+        }
+        let mut stomped = false;
+        for (_, switch) in self.switches.iter_mut() {
+            let on = standing && switch.holds(droid.x, droid.y);
+            stomped |= on && airborne;
+            switch.pressed = on;
+        }
+        if stomped {
+            self.toggle_switch_blocks();
+        }
+    }
+
+    // A と B のブロックの固さを入れ替える。
+    #[inline(never)]
+    pub fn toggle_switch_blocks(&mut self) {
+        self.solid_set = self.solid_set.other();
+        log_debug!("{:?} blocks are solid", self.solid_set);
+        for (column, block) in self.switch_blocks.iter().enumerate() {
+            if let Some((row, set)) = *block {
+                let cell = Cell::of_tile(set.tile(set == self.solid_set), false);
+                self.collision_map.set(column, usize::from(row), cell);
+            }
+        }
+        self.push_event(Event::PlaySfx(Sfx::Switch));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use agb::input::Button;
+
+    use super::*;
+    use crate::{
+        arena::Arena,
+        difficulty::Difficulty,
+        game::GROUND_Y,
+        input::Input,
+        level::{Level, GROUND_COLUMNS},
+        player::PlayerState,
+        spawn::EntityKind,
+        tilemap::CollisionMap,
+    };
+
+    /* 出発点 (x = 120) の左のスイッチと、頭の上の A のブロック、スイッチの左の B のブロック */
+    const ROW: u16 = 11;
+
+    fn game_with_switch() -> Game {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        let mut level = Level::load(false);
+        level.blocks = [None; GROUND_COLUMNS];
+        level.switch_blocks = [None; GROUND_COLUMNS];
+        level.switch_blocks[15] = Some((ROW, BlockSet::A));
+        level.switch_blocks[9] = Some((ROW, BlockSet::B));
+        game.switch_blocks = level.switch_blocks;
+        game.collision_map = CollisionMap::new(&level);
+        game.switches = Arena::new();
+        game.spawn(EntityKind::Switch)
+            .at((88, GROUND_Y))
+            .build()
+            .unwrap();
+        game
+    }
+
+    // x で跳ぶと、ブロックの行の下で頭をぶつけるか。
+    fn bonks_at(game: &mut Game, x: i32) -> bool {
+        game.droid.x = x;
+        game.update(&Input::new(Button::UP, Button::empty()));
+        let mut bonked = false;
+        while game.droid.y == GROUND_Y {
+            game.update(&Input::new(Button::UP, Button::UP));
+        }
+        while game.droid.y != GROUND_Y {
+            game.update(&Input::new(Button::UP, Button::UP));
+            bonked |= game.droid.bonked && game.droid.y == i32::from(ROW + 1) * 8;
+        }
+        bonked
+    }
+
+    // 跳んでスイッチに着地すると、固い組が入れ替わる。歩いて乗ったり、乗ったまま立っていても効かない。
+    #[test_case]
+    fn stomping_a_switch_swaps_the_solid_blocks(_gba: &mut agb::Gba) {
+        let mut game = game_with_switch();
+        assert!(bonks_at(&mut game, 116));
+        assert!(!bonks_at(&mut game, 68));
+
+        game.droid.x = 70;
+        for _ in 0..16 {
+            game.update(&Input::new(Button::RIGHT, Button::RIGHT));
+        }
+        assert!(game.switches.values().all(|switch| switch.pressed));
+        assert_eq!(game.solid_set, BlockSet::A);
+
+        game.droid.x = 88;
+        game.droid.y = GROUND_Y - 32;
+        game.droid.state = PlayerState::Airborne { double: false };
+        while game.droid.y != GROUND_Y {
+            game.update(&Input::default());
+        }
+        assert_eq!(game.solid_set, BlockSet::B);
+        for _ in 0..30 {
+            game.update(&Input::default());
+        }
+        assert_eq!(game.solid_set, BlockSet::B);
+        assert!(!bonks_at(&mut game, 116));
+        assert!(bonks_at(&mut game, 68));
+    }
+}
@@ -0,0 +1,211 @@
+// テレポーター。
+//
+// レベルの物の層 (Level::objects) に置く台 (EntityKind::Teleporter) で、同じ番号 (pair) の 2 つが対になる。
+// 置いたときに先に置かれた相手と互いの EntityId を持ち合い (Game::place_teleporter)、片方の台に立って
+// UP と A を押すと (どちらかは押したティック)、もう片方の台の真ん中へ移る。相手の無い台では何も起きない。
+// 移ったら WARP_TICKS の間はドロイド君が動かず、その間 presenter が空を白く光らせて戻していく。Sfx::Warp を鳴らす。
+// 台は当たり判定の無い幅 2 枚、高さ 1 枚のタイルで、背景のタイル (gameplay::TELEPORTER_TILES) は presenter が
+// レベルを始めるときに描く。タイルの格子に揃え、下端を地面の上面に揃えて置く。
+// 重力が逆さの間は使えない。
+
+use agb::input::Button;
+
+use crate::{
+    arena::EntityId,
+    events::{Event, Sfx},
+    game::{Game, DROID_WIDTH},
+    input::Input,
+    math::Fixed,
+    physics::Gravity,
+    player::PlayerState,
+    snapshot::encode_fields,
+};
+
+/* 同時に置ける台の数 (2 対) */
+pub const MAX_TELEPORTERS: usize = 4;
+/* 台の幅と高さ */
+pub const TELEPORTER_WIDTH: i32 = 16;
+pub const TELEPORTER_HEIGHT: i32 = 8;
+/* 移った後、ドロイド君が止まっていて空が光っているティック数 */
+pub const WARP_TICKS: u16 = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Teleporter {
+    /* 左上 */
+    pub x: i32,
+    pub y: i32,
+    /* 対の番号。レベルのデータで同じ番号の台が対になる */
+    pub pair: u8,
+    /* 対の相手。相手が置かれるまでは None */
+    pub twin: Option<EntityId>,
+}
+
+encode_fields!(Teleporter { x, y, pair, twin });
+
+impl Teleporter {
+    pub fn new(x: i32, y: i32, pair: u8) -> Self {
+        Self {
+            x,
+            y,
+            pair,
+            twin: None,
+        }
+    }
+
+    // x, y に立っているドロイド君が乗っているか。足元の真ん中が台の上にあり、足元が台の下端と同じ高さ。
+    fn carries(&self, x: i32, y: i32) -> bool {
+        (self.x..self.x + TELEPORTER_WIDTH).contains(&(x + DROID_WIDTH / 2))
+            && y + DROID_WIDTH == self.y + TELEPORTER_HEIGHT
+    }
+
+    // 移ってきたドロイド君の位置。台の真ん中に立つ。
+    fn arrival(&self) -> (i32, i32) {
+        (
+            self.x + (TELEPORTER_WIDTH - DROID_WIDTH) / 2,
+            self.y + TELEPORTER_HEIGHT - DROID_WIDTH,
+        )
+    }
+}
+
+impl Game {
+    // 台を置き、同じ番号で相手のいない台があれば対にする。置き場所が満杯なら置かずに None 。
+    pub fn place_teleporter(&mut self, pad: Teleporter) -> Option<EntityId> {
+        let id = self.teleporters.spawn(pad).ok()?;
+        let twin = self
+            .teleporters
+            .iter()
+            .find(|(other, other_pad)| {
+                *other != id && other_pad.pair == pad.pair && other_pad.twin.is_none()
+            })
+            .map(|(other, _)| other);
+        if let Some(twin) = twin {
+            for (from, to) in [(id, twin), (twin, id)] {
+                if let Some(pad) = self.teleporters.get_mut(from) {
+                    pad.twin = Some(to);
+                }
+            }
+        }
+        Some(id)
+    }
+
+    // 台に立って UP と A を押していれば対の台へ移る。ドロイド君を動かす前に呼び、
+    // 移っている間 (Game::warp が進んでいる間) はドロイド君を動かさない。
+    pub fn update_teleporters(&mut self, input: &Input) {
+        if self.warp.is_running() {
+            self.warp.tick();
+            return;
+        }
+        if self.teleporters.is_empty()
+            || !input.is_pressed(Button::UP | Button::A)
+            || !(input.is_just_pressed(Button::UP) || input.is_just_pressed(Button::A))
+        {
+            return;
+        }
+        let droid = &mut self.droid;
+        /* 立っているか、同じティックに UP を押してしゃがみ始めたところ */
+        if !matches!(
+            droid.state,
+            PlayerState::Idle | PlayerState::JumpSquat { double: false }
+        ) || droid.gravity != Gravity::Down
+        {
+            return;
+        }
+        let Some(to) = self
+            .teleporters
+            .values()
+            .find(|pad| pad.carries(droid.x, droid.y))
+            .and_then(|pad| pad.twin)
+            .and_then(|twin| self.teleporters.get(twin))
+        else {
+            return;
+        };
+        log_debug!(
+            "warped from ({}, {}) to ({}, {})",
+            droid.x,
+            droid.y,
+            to.x,
+            to.y
+        );
+        (droid.x, droid.y) = to.arrival();
+        droid.vx = Fixed::new(0);
+        droid.x_sub = Fixed::new(0);
+        droid.vy = Fixed::new(0);
+        droid.enter(PlayerState::Idle);
+        droid.ch = 0;
+        droid.coyote.start(0);
+        droid.jump_buffer.start(0);
+        self.warp.start(WARP_TICKS);
+        self.push_event(Event::PlaySfx(Sfx::Warp));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+
+    use super::*;
+    use crate::{arena::Arena, difficulty::Difficulty, game::GROUND_Y, spawn::EntityKind};
+
+    /* 出発点 (x = 120) の足元の台と、左の平らな地面の台 */
+    const HERE: (i32, i32) = (120, GROUND_Y + 8);
+    const THERE: (i32, i32) = (72, GROUND_Y + 8);
+
+    /* Game を作るので、呼ぶところごとに展開されてスタックが溢れないように。
+    テストで幾つも並べるので、Game 自体もヒープに置く */
+    #[inline(never)]
+    fn game_with_pads(pads: &[(u8, (i32, i32))]) -> Box<Game> {
+        let mut game = Box::new(Game::new(Difficulty::Normal, 1));
+        game.teleporters = Arena::new();
+        for &(pair, at) in pads {
+            game.spawn(EntityKind::Teleporter { pair })
+                .at(at)
+                .build()
+                .unwrap();
+        }
+        game
+    }
+
+    // UP と A で対の台へ移り、光っている間は動かない。台の外では普通に跳ぶ。
+    #[test_case]
+    fn up_and_a_warps_to_the_twin_pad(_gba: &mut agb::Gba) {
+        let mut game = game_with_pads(&[(0, HERE), (0, THERE)]);
+        game.update(&Input::new(Button::UP | Button::A, Button::empty()));
+        assert_eq!((game.droid.x, game.droid.y), (THERE.0, GROUND_Y));
+        assert_eq!(game.warp.remaining(), WARP_TICKS);
+        for _ in 1..WARP_TICKS {
+            game.update(&Input::new(Button::RIGHT, Button::RIGHT));
+            assert_eq!((game.droid.x, game.droid.y), (THERE.0, GROUND_Y));
+        }
+        game.update(&Input::new(Button::RIGHT, Button::RIGHT));
+        assert!(!game.warp.is_running());
+        for _ in 0..8 {
+            game.update(&Input::new(Button::RIGHT, Button::RIGHT));
+        }
+        assert!(game.droid.x > THERE.0);
+
+        /* 台から降りていれば移らず、UP を押しておいてから A を押しても移る */
+        let mut game = game_with_pads(&[(0, HERE), (0, THERE)]);
+        game.droid.x = HERE.0 + TELEPORTER_WIDTH;
+        game.update(&Input::new(Button::UP | Button::A, Button::empty()));
+        assert_eq!(game.droid.state, PlayerState::JumpSquat { double: false });
+        let mut game = game_with_pads(&[(0, HERE), (0, THERE)]);
+        game.update(&Input::new(Button::UP, Button::empty()));
+        game.update(&Input::new(Button::UP | Button::A, Button::UP));
+        assert_eq!(game.droid.x, THERE.0);
+    }
+
+    // 同じ番号の台が対になり、相手の無い台では移らない。
+    #[test_case]
+    fn pads_pair_up_by_number(_gba: &mut agb::Gba) {
+        let game = game_with_pads(&[(0, HERE), (1, (0, 0)), (0, THERE), (2, (200, 0))]);
+        let pads: alloc::vec::Vec<_> = game.teleporters.iter().collect();
+        assert_eq!(pads[0].1.twin, Some(pads[2].0));
+        assert_eq!(pads[2].1.twin, Some(pads[0].0));
+        assert_eq!((pads[1].1.twin, pads[3].1.twin), (None, None));
+
+        let mut game = game_with_pads(&[(1, HERE), (0, THERE)]);
+        game.update(&Input::new(Button::UP | Button::A, Button::empty()));
+        assert_eq!(game.droid.x, HERE.0);
+        assert!(!game.warp.is_running());
+    }
+}
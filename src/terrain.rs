@@ -0,0 +1,194 @@
+// 地面の形。
+//
+// 地面は列 (タイル 1 枚の幅) ごとに、平ら (Flat)、1 段 (8 ドット) 高い (Raised)、坂のどれか。
+// 坂は 45° (1 列で 1 段上る) と 22.5° (Low と High の 2 列で 1 段上る) があり、
+// 下り坂は上り坂のタイルを左右反転して描く。平らでない列は、地面の表面の 1 段上の行に
+// 坂や表面のタイルを描き、表面の行は中身のタイルで埋める (gameplay::lay_ground)。
+// ドロイド君が立つ高さは、描いたタイルから作る当たり判定のマップ (tilemap::CollisionMap) で決まる。
+//
+// ドロイド君は足元の真ん中の 2 ドット (x + DROID_WIDTH / 2 - 1 と x + DROID_WIDTH / 2) のうち
+// 高い方に立つ。どちらか片方にすると、ミラーモードで反転したときに 1 ドットずれる。
+// 隣り合う列の境目では高さが 1 ドットまでしか変わらないこと (起動時の検査で確かめる)。
+// 歩いて坂を上り下りするときは、1 ドット進むごとに地面の高さへ合わせる。
+//
+// 表面の材質 (Material) は、レベルの地面の表面のタイル (Level::ground) から決まる。
+// 氷のタイルの上は滑りやすく、左右の加減速が Tuning::ice に変わる。
+// ゴムのタイル (Bouncy) に落ちると、着地せずに跳ね返る (Tuning::bounce)。
+
+use agb::display::WIDTH;
+
+use crate::{
+    game::DROID_WIDTH,
+    gameplay::{GROUND_BOUNCY, GROUND_ICE, GROUND_TOP, SLOPE_22_HIGH, SLOPE_22_LOW, SLOPE_45},
+    level::GROUND_COLUMNS,
+    snapshot::encode_variants,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Terrain {
+    Flat,
+    Raised,
+    Up45,
+    Down45,
+    Up22Low,
+    Up22High,
+    Down22High,
+    Down22Low,
+}
+
+encode_variants!(Terrain {
+    Flat,
+    Raised,
+    Up45,
+    Down45,
+    Up22Low,
+    Up22High,
+    Down22High,
+    Down22Low
+});
+
+impl Terrain {
+    // 列の左端から px (0..8) ドット目の、平らな地面からの高さ (ドット)。
+    pub const fn height(self, px: i32) -> i32 {
+        match self {
+            Terrain::Flat => 0,
+            Terrain::Raised => 8,
+            Terrain::Up45 => px + 1,
+            Terrain::Down45 => 8 - px,
+            Terrain::Up22Low => (px + 2) / 2,
+            Terrain::Up22High => 4 + (px + 2) / 2,
+            Terrain::Down22High => 4 + (9 - px) / 2,
+            Terrain::Down22Low => (9 - px) / 2,
+        }
+    }
+
+    // 左右を反転した形。
+    pub const fn mirrored(self) -> Self {
+        match self {
+            Terrain::Flat => Terrain::Flat,
+            Terrain::Raised => Terrain::Raised,
+            Terrain::Up45 => Terrain::Down45,
+            Terrain::Down45 => Terrain::Up45,
+            Terrain::Up22Low => Terrain::Down22Low,
+            Terrain::Up22High => Terrain::Down22High,
+            Terrain::Down22High => Terrain::Up22High,
+            Terrain::Down22Low => Terrain::Up22Low,
+        }
+    }
+
+    // 表面の 1 段上の行に描くタイルと、左右反転するか。平らなら描かない。
+    pub const fn tile(self) -> Option<(usize, bool)> {
+        match self {
+            Terrain::Flat => None,
+            Terrain::Raised => Some((GROUND_TOP, false)),
+            Terrain::Up45 => Some((SLOPE_45, false)),
+            Terrain::Down45 => Some((SLOPE_45, true)),
+            Terrain::Up22Low => Some((SLOPE_22_LOW, false)),
+            Terrain::Up22High => Some((SLOPE_22_HIGH, false)),
+            Terrain::Down22High => Some((SLOPE_22_HIGH, true)),
+            Terrain::Down22Low => Some((SLOPE_22_LOW, true)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Material {
+    Normal,
+    Ice,
+    Bouncy,
+}
+
+impl Material {
+    // 地面の表面のタイル (tiles::bg.tile_settings の添字) の材質。
+    pub const fn of_tile(tile: usize) -> Self {
+        match tile {
+            GROUND_ICE => Material::Ice,
+            GROUND_BOUNCY => Material::Bouncy,
+            _ => Material::Normal,
+        }
+    }
+}
+
+// x にいるドロイド君の足元 (真ん中) の地面の材質。画面の外は普通の地面。
+pub fn material_at(materials: &[Material; GROUND_COLUMNS], x: i32) -> Material {
+    let foot = x + DROID_WIDTH / 2;
+    if (0..WIDTH).contains(&foot) {
+        materials[(foot / 8) as usize]
+    } else {
+        Material::Normal
+    }
+}
+
+// 隣り合う列の境目で高さが 2 ドット以上変わるところ (右側の列の番号)。
+// 画面の外は平らなので、両端の列も平らな地面へ続いていなければならない。
+#[cfg_attr(not(feature = "debug-tools"), allow(dead_code))]
+pub fn steps(terrain: &[Terrain; GROUND_COLUMNS]) -> impl Iterator<Item = usize> + '_ {
+    let left = |column: usize| {
+        column
+            .checked_sub(1)
+            .map_or(0, |left| terrain[left].height(7))
+    };
+    let right = |column: usize| terrain.get(column).map_or(0, |right| right.height(0));
+    (0..=GROUND_COLUMNS).filter(move |&column| (left(column) - right(column)).abs() > 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Terrain; 8] = [
+        Terrain::Flat,
+        Terrain::Raised,
+        Terrain::Up45,
+        Terrain::Down45,
+        Terrain::Up22Low,
+        Terrain::Up22High,
+        Terrain::Down22High,
+        Terrain::Down22Low,
+    ];
+
+    // 下り坂は上り坂をちょうど左右反転した形で、反転したタイルで描く。
+    #[test_case]
+    fn mirrored_shapes_are_exact_reflections(_gba: &mut agb::Gba) {
+        for terrain in ALL {
+            let mirrored = terrain.mirrored();
+            assert_eq!(mirrored.mirrored(), terrain);
+            for px in 0..8 {
+                assert_eq!(terrain.height(px), mirrored.height(7 - px), "{terrain:?}");
+            }
+            if let (Some((tile, flip)), Some((mirrored_tile, mirrored_flip))) =
+                (terrain.tile(), mirrored.tile())
+            {
+                assert_eq!(tile, mirrored_tile);
+                assert_eq!(flip != mirrored_flip, tile != GROUND_TOP);
+            }
+        }
+    }
+
+    // 坂を並べた丘は段が無い (1 ドットずつ上って下りるのは tilemap のテスト)。
+    #[test_case]
+    fn a_hill_rises_one_dot_at_a_time(_gba: &mut agb::Gba) {
+        let mut terrain = [Terrain::Flat; GROUND_COLUMNS];
+        terrain[2..10].copy_from_slice(&[
+            Terrain::Up22Low,
+            Terrain::Up22High,
+            Terrain::Raised,
+            Terrain::Down45,
+            Terrain::Flat,
+            Terrain::Up45,
+            Terrain::Raised,
+            Terrain::Down45,
+        ]);
+        assert_eq!(steps(&terrain).count(), 0);
+        /* 高いところから坂を挟まずに下りると段になる */
+        terrain[9] = Terrain::Flat;
+        assert_eq!(steps(&terrain).collect::<alloc::vec::Vec<_>>(), [9]);
+        /* 画面の端で高いまま終わっても段になる */
+        terrain[9] = Terrain::Down45;
+        terrain[GROUND_COLUMNS - 1] = Terrain::Raised;
+        assert_eq!(
+            steps(&terrain).collect::<alloc::vec::Vec<_>>(),
+            [GROUND_COLUMNS - 1, GROUND_COLUMNS]
+        );
+    }
+}
@@ -0,0 +1,157 @@
+// 背景 1 枚を使った文字表示レイヤー。
+//
+// 組み込みフォント (font モジュール) のタイルを agb の VRAM マネージャ経由で並べる。
+// 文字色はパレットバンク TEXT_PALETTE の 1 番、横棒は 2 番。
+// 色を変えたい文字 (区切りタイムの差、残り時間の警告など) は、
+// 色番号 1 だけを設定した RED_PALETTE か GREEN_PALETTE を指定して print_at で書く。
+
+use core::fmt::{self, Write};
+
+use agb::display::tiled::VRamManager;
+
+use crate::{
+    font,
+    gfx::rgb5,
+    layer::{Layer, Tile},
+};
+
+pub const COLUMNS: u16 = 30;
+pub const ROWS: u16 = 20;
+
+pub const TEXT_PALETTE: u8 = 15;
+pub const GREEN_PALETTE: u8 = 13;
+pub const RED_PALETTE: u8 = 12;
+
+/* 空白のセル */
+const BLANK: u16 = u16::MAX;
+
+pub struct TextLayer<'a> {
+    layer: Layer<'a>,
+    /* 今マップに置いてあるタイルとパレットバンク (上位 4 ビット)。同じものを置き直すときは何もしない */
+    cells: [u16; (COLUMNS * ROWS) as usize],
+}
+
+impl<'a> TextLayer<'a> {
+    pub fn new(layer: Layer<'a>, vram: &mut VRamManager) -> Self {
+        vram.set_background_palette_colour(TEXT_PALETTE as usize, 1, 0x7fff);
+        vram.set_background_palette_colour(TEXT_PALETTE as usize, 2, 0x03e0);
+        vram.set_background_palette_colour(GREEN_PALETTE as usize, 1, rgb5(8, 31, 8));
+        vram.set_background_palette_colour(RED_PALETTE as usize, 1, rgb5(31, 8, 8));
+        Self {
+            layer,
+            cells: [BLANK; (COLUMNS * ROWS) as usize],
+        }
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.layer.set_visible(visible);
+    }
+
+    #[cfg_attr(not(feature = "debug-tools"), allow(dead_code))]
+    pub fn put_tile(&mut self, vram: &mut VRamManager, column: u16, row: u16, tile: u16) {
+        self.set_cell(vram, column, row, tile, TEXT_PALETTE);
+    }
+
+    pub fn clear_cell(&mut self, vram: &mut VRamManager, column: u16, row: u16) {
+        self.set_cell(vram, column, row, BLANK, TEXT_PALETTE);
+    }
+
+    fn set_cell(&mut self, vram: &mut VRamManager, column: u16, row: u16, tile: u16, palette: u8) {
+        if column >= COLUMNS || row >= ROWS {
+            return;
+        }
+        let value = if tile == BLANK {
+            BLANK
+        } else {
+            tile | u16::from(palette) << 12
+        };
+        let cell = &mut self.cells[(row * COLUMNS + column) as usize];
+        if *cell == value {
+            return;
+        }
+        *cell = value;
+        let entry = (tile != BLANK).then(|| Tile::new(tile, palette));
+        self.layer
+            .write_row(vram, (column, row), &font::TILESET, &[entry]);
+    }
+
+    // row 行目を args で書き換え、残りの桁は空白にする。
+    pub fn print_line(&mut self, vram: &mut VRamManager, row: u16, args: fmt::Arguments) {
+        let mut cursor = Cursor {
+            layer: self,
+            vram,
+            column: 0,
+            row,
+            palette: TEXT_PALETTE,
+        };
+        let _ = cursor.write_fmt(args);
+        let column = cursor.column;
+        for column in column..COLUMNS {
+            self.clear_cell(vram, column, row);
+        }
+    }
+
+    // (column, row) から args をパレットバンク palette の色で書く。その先の桁には触らない。
+    pub fn print_at(
+        &mut self,
+        vram: &mut VRamManager,
+        column: u16,
+        row: u16,
+        palette: u8,
+        args: fmt::Arguments,
+    ) {
+        let mut cursor = Cursor {
+            layer: self,
+            vram,
+            column,
+            row,
+            palette,
+        };
+        let _ = cursor.write_fmt(args);
+    }
+
+    // 全て消してタイルを返す。
+    #[cfg_attr(not(feature = "debug-tools"), allow(dead_code))]
+    pub fn clear(&mut self, vram: &mut VRamManager) {
+        self.layer.clear(vram);
+        self.cells = [BLANK; (COLUMNS * ROWS) as usize];
+    }
+
+    pub fn release(self, vram: &mut VRamManager) {
+        self.layer.release(vram);
+    }
+
+    // 前の commit から書き換えたか。
+    pub fn is_dirty(&self) -> bool {
+        self.layer.is_dirty()
+    }
+
+    // 書き換えたセルがあるときだけ VRAM に転送する。転送したら true。
+    pub fn commit(&mut self, vram: &mut VRamManager) -> bool {
+        self.layer.commit(vram)
+    }
+}
+
+struct Cursor<'l, 'a, 'v> {
+    layer: &'l mut TextLayer<'a>,
+    vram: &'v mut VRamManager,
+    column: u16,
+    row: u16,
+    palette: u8,
+}
+
+impl Write for Cursor<'_, '_, '_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for ch in s.bytes() {
+            if ch == b' ' {
+                self.layer.clear_cell(self.vram, self.column, self.row);
+            } else {
+                let tile = font::tile_for(ch);
+                self.layer
+                    .set_cell(self.vram, self.column, self.row, tile, self.palette);
+            }
+            self.column += 1;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,292 @@
+// 背景のタイルから作る当たり判定のマップ。
+//
+// レベルの地面と壁、壊せるブロックとスイッチのブロックは、背景に並べるタイル (gameplay::ground_column) をそのまま 1 枚ずつ
+// 空 (Empty)、固い (Solid)、坂 (Slope) に分けてマップにする (CollisionMap::new)。
+// 描いたタイルと当たり判定が食い違わないよう、どちらも同じ並びから作る。
+// ブロックが壊れたときは、背景と一緒にマップのそのタイルも空にする (Game::break_block)。
+// スイッチのブロックは、固さが入れ替わったときに新しいタイルから決め直す (Game::toggle_switch_blocks)。
+// マップは画面 1 枚分 (text::COLUMNS x text::ROWS) で、エンドレスでもスクロールしない地面の分だけ持つ。
+//
+// ドロイド君が立つ高さ (floor_y) は、足元の真ん中の 2 ドット (terrain モジュール参照) のそれぞれで、
+// 頭の行から下へ見ていって最初に当たる地面の上面の高い方。頭から探すので、
+// 1 ティックに DROID_WIDTH 未満しか落ちなければ、地面を通り抜けることはない。
+// 画面の外の列は端の列と同じ (両端の列は平らな地面へ続いていること、terrain::steps)。
+//
+// 横は、頭の側の半分が固いタイルにめり込んだら押し戻す (wall_x)。足元の側の半分は坂や段を上るので見ない。
+// 上は、跳び上がった頭が地面のタイル (坂も下側は埋まっている) の下端を跨いだら、そこで止める (ceiling_y)。
+
+use alloc::vec::Vec;
+
+use agb::display::WIDTH;
+
+use crate::{
+    collision::{footprint, Aabb},
+    game::DROID_WIDTH,
+    gameplay::{
+        ground_column, BLOCK, GROUND_FILL, GROUND_ROW, GROUND_TILES, SLOPE_22_HIGH, SLOPE_22_LOW,
+        SLOPE_45, SWITCH_BLOCK_TILES,
+    },
+    level::{Level, GROUND_COLUMNS},
+    physics::Gravity,
+    snapshot::{encode_fields, Encode, Reader},
+    terrain::Terrain,
+    text,
+};
+
+const COLUMNS: usize = text::COLUMNS as usize;
+const ROWS: usize = text::ROWS as usize;
+/* タイル 1 枚の大きさ (ドット) */
+const TILE: i32 = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cell {
+    Empty,
+    Solid,
+    // 坂。形は地面の形 (Terrain) の坂と同じ。
+    Slope(Terrain),
+}
+
+/* 坂は番号の後に形を書く */
+impl Encode for Cell {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        match self {
+            Cell::Empty => bytes.push(0),
+            Cell::Solid => bytes.push(1),
+            Cell::Slope(terrain) => {
+                bytes.push(2);
+                terrain.encode(bytes);
+            }
+        }
+    }
+
+    fn decode(reader: &mut Reader) -> Option<Self> {
+        match reader.take()? {
+            [0] => Some(Cell::Empty),
+            [1] => Some(Cell::Solid),
+            [2] => Terrain::decode(reader).map(Cell::Slope),
+            _ => None,
+        }
+    }
+}
+
+impl Cell {
+    // 背景のタイル (tiles::bg.tile_settings の添字) と左右反転から。地面のタイルでなければ空。
+    pub fn of_tile(tile: usize, hflip: bool) -> Self {
+        let slope = |up: Terrain, down: Terrain| Cell::Slope(if hflip { down } else { up });
+        match tile {
+            SLOPE_45 => slope(Terrain::Up45, Terrain::Down45),
+            SLOPE_22_LOW => slope(Terrain::Up22Low, Terrain::Down22Low),
+            SLOPE_22_HIGH => slope(Terrain::Up22High, Terrain::Down22High),
+            BLOCK => Cell::Solid,
+            /* スイッチのブロックは塗った方だけ固い */
+            tile if SWITCH_BLOCK_TILES.iter().any(|tiles| tiles[0] == tile) => Cell::Solid,
+            tile if GROUND_TILES.contains(&tile) => Cell::Solid,
+            _ => Cell::Empty,
+        }
+    }
+
+    // タイルの左端から px (0..8) ドット目の、タイルの下端からの高さ (ドット)。
+    const fn height(self, px: i32) -> i32 {
+        match self {
+            Cell::Empty => 0,
+            Cell::Solid => TILE,
+            Cell::Slope(terrain) => terrain.height(px),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CollisionMap {
+    cells: [[Cell; COLUMNS]; ROWS],
+}
+
+encode_fields!(CollisionMap { cells });
+
+impl CollisionMap {
+    pub const EMPTY: Self = Self {
+        cells: [[Cell::Empty; COLUMNS]; ROWS],
+    };
+
+    // level の地面を並べたときのマップ。
+    pub fn new(level: &Level) -> Self {
+        let mut map = Self::EMPTY;
+        let surface_row = GROUND_ROW as usize;
+        for column in 0..GROUND_COLUMNS {
+            let ground = ground_column(level, column);
+            if let Some((tile, hflip)) = ground.slope {
+                map.cells[surface_row - 1][column] = Cell::of_tile(tile, hflip);
+            }
+            let (tile, hflip) = ground.surface;
+            map.cells[surface_row][column] = Cell::of_tile(tile, hflip);
+            for row in &mut map.cells[surface_row + 1..] {
+                row[column] = Cell::of_tile(GROUND_FILL, false);
+            }
+            for row in &mut map.cells[surface_row - ground.wall..surface_row] {
+                row[column] = Cell::of_tile(GROUND_FILL, false);
+            }
+            if let Some((row, tile)) = ground.block {
+                map.cells[usize::from(row)][column] = Cell::of_tile(tile, false);
+            }
+        }
+        map
+    }
+
+    pub fn set(&mut self, column: usize, row: usize, cell: Cell) {
+        self.cells[row][column] = cell;
+    }
+
+    // (x, y) にいるドロイド君が、頭より下で立つ地面の y 座標。
+    #[link_section = ".iwram.tilemap_floor_y"]
+    pub fn floor_y(&self, x: i32, y: i32) -> i32 {
+        let foot = x + DROID_WIDTH / 2;
+        self.surface_y(foot - 1, y).min(self.surface_y(foot, y)) - DROID_WIDTH
+    }
+
+    // 画面の px ドット目で、y の行から下で最初に当たる地面の上面。無ければマップの下端。
+    fn surface_y(&self, px: i32, y: i32) -> i32 {
+        let px = px.clamp(0, WIDTH - 1);
+        let column = (px / TILE) as usize;
+        let first = y.div_euclid(TILE).max(0) as usize;
+        for row in first..ROWS {
+            let height = self.cells[row][column].height(px % TILE);
+            if height > 0 {
+                return (row as i32 + 1) * TILE - height;
+            }
+        }
+        ROWS as i32 * TILE
+    }
+
+    // (x_from, y) から x_to へ横に動いたドロイド君が、頭の側の半分で固いタイルにめり込んだら押し戻した x 。
+    // めり込まなければ x_to のまま。
+    #[link_section = ".iwram.tilemap_wall_x"]
+    pub fn wall_x(&self, x_from: i32, x_to: i32, y: i32, gravity: Gravity) -> i32 {
+        let half = DROID_WIDTH / 2;
+        let top = match gravity {
+            Gravity::Down => y,
+            Gravity::Up => y + half,
+        };
+        let dir = (x_to - x_from).signum();
+        let mut x = x_to;
+        for (column, row) in cells_under(Aabb::new(x_to, top, DROID_WIDTH, half)) {
+            if self.cells[row][column] == Cell::Solid {
+                let cell = Aabb::new(column as i32 * TILE, row as i32 * TILE, TILE, TILE);
+                x = Aabb::new(x, top, DROID_WIDTH, half).push_out_x(cell, dir);
+            }
+        }
+        x
+    }
+
+    // x にいるドロイド君が y_before から y_after まで上ったとき、頭が地面のタイルに下からぶつかるなら
+    // 止まる y 座標 (一番近いタイルの下端)。横は足元と同じ幅で見る。
+    pub fn ceiling_y(&self, x: i32, y_before: i32, y_after: i32) -> Option<i32> {
+        let head = footprint(x, y_after);
+        let span = Aabb::new(head.x, y_after, head.width, y_before - y_after);
+        cells_under(span)
+            .filter(|&(column, row)| self.cells[row][column] != Cell::Empty)
+            .map(|(_, row)| (row as i32 + 1) * TILE)
+            .filter(|&bottom| bottom <= y_before)
+            .max()
+    }
+}
+
+// area に掛かっているマップのタイル (列, 行)。マップの外は含まない。
+fn cells_under(area: Aabb) -> impl Iterator<Item = (usize, usize)> {
+    let span = |from: i32, to: i32, count: usize| {
+        let first = from.div_euclid(TILE).max(0) as usize;
+        let last = ((to - 1).div_euclid(TILE) + 1).clamp(0, count as i32) as usize;
+        first..last.max(first)
+    };
+    let columns = span(area.x, area.right(), COLUMNS);
+    span(area.y, area.bottom(), ROWS)
+        .flat_map(move |row| columns.clone().map(move |column| (column, row)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{game::GROUND_Y, terrain};
+
+    // 坂を並べた丘は、平らなところから 1 ドットずつ上って下りる。描いたタイルのとおりに立つ。
+    #[test_case]
+    fn the_floor_follows_the_drawn_hill(_gba: &mut agb::Gba) {
+        let mut level = Level::tutorial();
+        level.terrain[2..10].copy_from_slice(&[
+            Terrain::Up22Low,
+            Terrain::Up22High,
+            Terrain::Raised,
+            Terrain::Down45,
+            Terrain::Flat,
+            Terrain::Up45,
+            Terrain::Raised,
+            Terrain::Down45,
+        ]);
+        assert_eq!(terrain::steps(&level.terrain).count(), 0);
+        let map = CollisionMap::new(&level);
+        let mut previous = map.floor_y(-DROID_WIDTH, 0);
+        for x in -DROID_WIDTH..WIDTH {
+            let y = map.floor_y(x, 0);
+            assert!((y - previous).abs() <= 1, "x = {x}");
+            previous = y;
+        }
+        assert_eq!(map.floor_y(32 - DROID_WIDTH / 2, 0), GROUND_Y - 8);
+        assert_eq!(map.floor_y(0, 0), GROUND_Y);
+        /* 地面より下から探せば、その下の地面 (無ければマップの下端) */
+        assert_eq!(
+            map.floor_y(0, GROUND_Y + DROID_WIDTH + TILE),
+            GROUND_Y + TILE
+        );
+    }
+
+    // 頭の側が固いタイルに入ったら押し戻され、足元の側だけなら通れる。
+    #[test_case]
+    fn solid_tiles_stop_the_head_side(_gba: &mut agb::Gba) {
+        let mut map = CollisionMap::new(&Level::tutorial());
+        let y = GROUND_Y;
+        let row = (y / TILE) as usize;
+        map.set(10, row, Cell::Solid);
+        assert_eq!(map.wall_x(60, 66, y, Gravity::Down), 80 - DROID_WIDTH);
+        assert_eq!(map.wall_x(92, 86, y, Gravity::Down), 88);
+        assert_eq!(map.wall_x(40, 46, y, Gravity::Down), 46);
+        /* 逆さなら頭は下の半分 */
+        assert_eq!(map.wall_x(60, 66, y, Gravity::Up), 66);
+        assert_eq!(map.wall_x(60, 66, y - TILE, Gravity::Up), 80 - DROID_WIDTH);
+        /* 足元の段は見ない */
+        map.set(10, row + 1, Cell::Solid);
+        map.set(10, row, Cell::Empty);
+        assert_eq!(map.wall_x(60, 66, y, Gravity::Down), 66);
+    }
+
+    // レベルの壁は地面から積んだ固いタイルになる。横からは止まり、上には立てる。
+    #[test_case]
+    fn level_walls_are_solid_tiles(_gba: &mut agb::Gba) {
+        let mut level = Level::tutorial();
+        level.walls[10] = 2;
+        let map = CollisionMap::new(&level);
+        let row = GROUND_ROW as usize;
+        assert_eq!(map.cells[row - 1][10], Cell::Solid);
+        assert_eq!(map.cells[row - 2][10], Cell::Solid);
+        assert_eq!(map.cells[row - 3][10], Cell::Empty);
+        assert_eq!(
+            map.wall_x(60, 66, GROUND_Y, Gravity::Down),
+            80 - DROID_WIDTH
+        );
+        assert_eq!(map.floor_y(80 - DROID_WIDTH / 2, 0), GROUND_Y - 2 * TILE);
+    }
+
+    // 上ったときに跨いだ一番近いタイルの下端で止まる。跨がなければ、足元の幅の外なら止まらない。
+    #[test_case]
+    fn heads_stop_under_the_nearest_tile(_gba: &mut agb::Gba) {
+        let mut map = CollisionMap::new(&Level::tutorial());
+        map.set(10, 5, Cell::Solid);
+        map.set(10, 8, Cell::Slope(Terrain::Up45));
+        assert_eq!(map.ceiling_y(80, 90, 30), Some(72));
+        assert_eq!(map.ceiling_y(80, 60, 30), Some(48));
+        assert_eq!(map.ceiling_y(80, 72, 70), Some(72));
+        assert_eq!(map.ceiling_y(80, 71, 40), Some(48));
+        assert_eq!(map.ceiling_y(80, 71, 50), None);
+        assert_eq!(map.ceiling_y(80, 47, 10), None);
+        /* 足元の幅 (左右 3 ドット内側) の外 */
+        assert_eq!(map.ceiling_y(80 - DROID_WIDTH + 3, 90, 30), None);
+        assert_eq!(map.ceiling_y(80 - DROID_WIDTH + 4, 90, 30), Some(72));
+    }
+}
@@ -0,0 +1,330 @@
+// ティック単位のタイマー。
+//
+// どれも 1 ティックに 1 回 tick() (Repeater は advance() でまとめても良い) するだけの値型で、
+// 割り込みやハードウェアのタイマーは使わない。Game に入れておけばリプレイでもそのまま再現する。
+//
+// - Timer: 指定したティック数を数え終わった瞬間を just_finished() で知らせる。
+// - Cooldown: 一度使うと指定したティック数だけ try_use() を断る (無敵時間等)。
+// - Repeater: N ティックごとに発火する。処理落ちでまとめて進めても回数は減らない。
+// - Task: (段階, ティック数) の並びを 1 段ずつ進める。「揺れてから落ちて、しばらくして戻る」のような
+//   何ティックにも渡る動きを、段階ごとのフラグやタイマーを持たずに定数の並びで書ける。
+//   持ち主が毎ティック tick() するだけの協調的なもので、段階に入ったときに何をするかは持ち主が段階を見て決める。
+
+use alloc::vec::Vec;
+
+use crate::snapshot::{encode_fields, Encode, Reader};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Timer {
+    remaining: u16,
+    just_finished: bool,
+}
+
+encode_fields!(Timer {
+    remaining,
+    just_finished
+});
+
+impl Timer {
+    pub const fn new() -> Self {
+        Self {
+            remaining: 0,
+            just_finished: false,
+        }
+    }
+
+    // start(ticks) 済みのタイマー。
+    pub const fn started(ticks: u16) -> Self {
+        Self {
+            remaining: ticks,
+            just_finished: false,
+        }
+    }
+
+    // ticks 回目の tick() で just_finished() になる。0 なら何もしない。
+    pub fn start(&mut self, ticks: u16) {
+        self.remaining = ticks;
+        self.just_finished = false;
+    }
+
+    pub fn tick(&mut self) {
+        self.just_finished = false;
+        if self.remaining > 0 {
+            self.remaining -= 1;
+            self.just_finished = self.remaining == 0;
+        }
+    }
+
+    // 直前の tick() で数え終わったか。次の tick() で false に戻る。
+    pub fn just_finished(&self) -> bool {
+        self.just_finished
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.remaining > 0
+    }
+
+    pub fn remaining(&self) -> u16 {
+        self.remaining
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cooldown {
+    ticks: u16,
+    timer: Timer,
+}
+
+encode_fields!(Cooldown { ticks, timer });
+
+impl Cooldown {
+    pub const fn new(ticks: u16) -> Self {
+        Self {
+            ticks,
+            timer: Timer::new(),
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.timer.tick();
+    }
+
+    // 使えるなら使って true 。使った tick() から数えて ticks 回の間は false 。
+    pub fn try_use(&mut self) -> bool {
+        if self.timer.is_running() {
+            return false;
+        }
+        self.timer.start(self.ticks);
+        true
+    }
+
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn is_ready(&self) -> bool {
+        !self.timer.is_running()
+    }
+
+    // 次に使えるようになるまでのティック数。
+    pub fn remaining(&self) -> u16 {
+        self.timer.remaining()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Repeater {
+    period: u16,
+    elapsed: u16,
+}
+
+encode_fields!(Repeater { period, elapsed });
+
+impl Repeater {
+    pub const fn new(period: u16) -> Self {
+        Self { period, elapsed: 0 }
+    }
+
+    // 発火の間隔を変える。数えはそのまま続ける。
+    pub fn set_period(&mut self, period: u16) {
+        self.period = period;
+    }
+
+    // 数え直す。次の発火は period ティック後。
+    pub fn reset(&mut self) {
+        self.elapsed = 0;
+    }
+
+    // 1 ティック進め、発火したら true 。
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn tick(&mut self) -> bool {
+        self.advance(1) > 0
+    }
+
+    // ticks ティックまとめて進め、その間に発火した回数を返す。
+    pub fn advance(&mut self, ticks: u16) -> u32 {
+        let total = u32::from(self.elapsed) + u32::from(ticks);
+        let period = u32::from(self.period.max(1));
+        self.elapsed = (total % period) as u16;
+        total / period
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Task<S: 'static> {
+    /* (段階, その段階のティック数)。0 ティックの段階は入った時点で飛ばす */
+    steps: &'static [(S, u16)],
+    /* 今の段階の添字。走っていなければ steps.len() */
+    index: usize,
+    /* 今の段階の残り */
+    timer: Timer,
+}
+
+impl<S: Copy> Task<S> {
+    // 走っていないタスク。start() で最初の段階から始める。
+    pub const fn new(steps: &'static [(S, u16)]) -> Self {
+        Self {
+            steps,
+            index: steps.len(),
+            timer: Timer::new(),
+        }
+    }
+
+    // 最初の段階から始める (走っていてもやり直す)。
+    pub fn start(&mut self) {
+        self.enter(0);
+    }
+
+    // index 番目の段階に入る。0 ティックの段階は数え終わらないので、次の段階へ飛ばす。
+    fn enter(&mut self, mut index: usize) {
+        while let Some(&(_, 0)) = self.steps.get(index) {
+            index += 1;
+        }
+        self.index = index;
+        if let Some(&(_, ticks)) = self.steps.get(index) {
+            self.timer.start(ticks);
+        }
+    }
+
+    // 1 ティック進める。今の段階のティック数を数え終わったら次の段階へ移り、最後の段階の後は止まる。
+    // 段階が変わった (か終わった) ら true 。
+    pub fn tick(&mut self) -> bool {
+        if !self.is_running() {
+            return false;
+        }
+        self.timer.tick();
+        if !self.timer.just_finished() {
+            return false;
+        }
+        self.enter(self.index + 1);
+        true
+    }
+
+    // 今の段階。走っていなければ None 。
+    pub fn step(&self) -> Option<S> {
+        self.steps.get(self.index).map(|&(step, _)| step)
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.index < self.steps.len()
+    }
+
+    // 今の段階の残りティック数。
+    pub fn remaining(&self) -> u16 {
+        self.timer.remaining()
+    }
+
+    // 進み具合 (今の段階と残り) を書く (snapshot モジュール)。段階の並びは持ち主が決めるので書かない。
+    pub fn encode_state(&self, bytes: &mut Vec<u8>) {
+        self.index.encode(bytes);
+        self.timer.encode(bytes);
+    }
+
+    // encode_state で書いた進み具合を、同じ段階の並びで作ったこのタスクに戻す。
+    pub fn decode_state(&mut self, reader: &mut Reader) -> Option<()> {
+        let index = usize::decode(reader)?;
+        if index > self.steps.len() {
+            return None;
+        }
+        self.index = index;
+        self.timer = Timer::decode(reader)?;
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn a_timer_finishes_exactly_once_on_its_last_tick(_gba: &mut agb::Gba) {
+        let mut timer = Timer::new();
+        timer.start(3);
+        let finished: [bool; 5] = core::array::from_fn(|_| {
+            timer.tick();
+            timer.just_finished()
+        });
+        assert_eq!(finished, [false, false, true, false, false]);
+        assert!(!timer.is_running());
+
+        /* 0 で始めたタイマーは終わったことにもならない */
+        timer.start(0);
+        timer.tick();
+        assert!(!timer.just_finished());
+
+        /* 始めた状態で作っても同じ */
+        let mut started = Timer::started(3);
+        assert_eq!(started, {
+            let mut timer = Timer::new();
+            timer.start(3);
+            timer
+        });
+        started.tick();
+        assert_eq!(started.remaining(), 2);
+    }
+
+    #[test_case]
+    fn a_cooldown_refuses_until_it_has_run_out(_gba: &mut agb::Gba) {
+        let mut cooldown = Cooldown::new(2);
+        assert!(cooldown.try_use());
+        assert!(!cooldown.try_use());
+        cooldown.tick();
+        assert!(!cooldown.try_use());
+        cooldown.tick();
+        assert!(cooldown.is_ready());
+        assert!(cooldown.try_use());
+        assert!(!cooldown.try_use());
+    }
+
+    #[test_case]
+    fn a_repeater_fires_every_period_and_catches_up(_gba: &mut agb::Gba) {
+        let mut repeater = Repeater::new(3);
+        let fired: [bool; 7] = core::array::from_fn(|_| repeater.tick());
+        assert_eq!(fired, [false, false, true, false, false, true, false]);
+
+        /* まとめて進めても回数と位相は 1 ずつ進めたときと同じ */
+        repeater.reset();
+        assert_eq!(repeater.advance(7), 2);
+        assert!(!repeater.tick());
+        assert!(repeater.tick());
+        assert_eq!(repeater.advance(0), 0);
+    }
+
+    #[test_case]
+    fn a_task_walks_through_its_steps_once(_gba: &mut agb::Gba) {
+        const STEPS: [(char, u16); 2] = [('a', 2), ('b', 1)];
+        let mut task = Task::new(&STEPS);
+        assert_eq!(task.step(), None);
+        assert!(!task.tick());
+        task.start();
+        let steps: [(Option<char>, bool); 4] = core::array::from_fn(|_| {
+            let step = task.step();
+            (step, task.tick())
+        });
+        assert_eq!(
+            steps,
+            [
+                (Some('a'), false),
+                (Some('a'), true),
+                (Some('b'), true),
+                (None, false)
+            ]
+        );
+        assert!(!task.is_running());
+
+        /* 途中からでもやり直せる */
+        task.start();
+        task.tick();
+        task.start();
+        assert_eq!((task.step(), task.remaining()), (Some('a'), 2));
+    }
+
+    // 0 ティックの段階では止まらずに次へ進み、最後なら終わる。
+    #[test_case]
+    fn a_task_skips_empty_steps(_gba: &mut agb::Gba) {
+        const STEPS: [(char, u16); 4] = [('a', 0), ('b', 1), ('c', 0), ('d', 0)];
+        let mut task = Task::new(&STEPS);
+        task.start();
+        assert_eq!(task.step(), Some('b'));
+        assert!(task.tick());
+        assert_eq!(task.step(), None);
+        assert!(!task.is_running());
+    }
+}
@@ -0,0 +1,107 @@
+// タイムアタック。
+//
+// 普通のモードとの違いは全て Mode::TimeAttack を見て切り替える:
+// - 始まる前に 3-2-1 のカウントダウンがあり、その間はドロイド君が動かない (Game::update)。
+// - ダメージは体力を減らさず、タイムに PENALTY_TICKS を足す (Game::handle_events)。
+// - HUD は得点と体力の代わりにタイムを出す (Presenter)。
+// - レベルを終えたタイムをレベルデータの Par と比べてメダルを決め、
+//   結果画面に出して最高タイムと一緒に保存する (ResultsScene, save::Records)。
+//
+// 時間は全てティック数で持つ。表示するときだけ TimeDisplay で分:秒.百分の一秒にする。
+
+use core::fmt;
+
+/* カウントダウンの長さ (3, 2, 1 を 1 秒ずつ) */
+pub const COUNTDOWN_TICKS: u16 = 3 * 60;
+/* ダメージ 1 回で足されるタイム */
+pub const PENALTY_TICKS: u32 = 2 * 60;
+
+// 良い順に並べてある。
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Medal {
+    Gold,
+    Silver,
+    Bronze,
+}
+
+impl Medal {
+    pub fn name(self) -> &'static str {
+        match self {
+            Medal::Gold => "GOLD",
+            Medal::Silver => "SILVER",
+            Medal::Bronze => "BRONZE",
+        }
+    }
+}
+
+// レベルごとの目標タイム (ティック)。このタイム以内ならそのメダル。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Par {
+    pub gold: u32,
+    pub silver: u32,
+    pub bronze: u32,
+}
+
+impl Par {
+    pub fn medal(&self, time: u32) -> Option<Medal> {
+        if time <= self.gold {
+            Some(Medal::Gold)
+        } else if time <= self.silver {
+            Some(Medal::Silver)
+        } else if time <= self.bronze {
+            Some(Medal::Bronze)
+        } else {
+            None
+        }
+    }
+
+    #[cfg_attr(not(feature = "debug-tools"), allow(dead_code))]
+    pub fn is_ordered(&self) -> bool {
+        self.gold <= self.silver && self.silver <= self.bronze
+    }
+}
+
+// ティック数を「分:秒.百分の一秒」で表示する。1 ティックは 1/60 秒として扱う。
+pub struct TimeDisplay(pub u32);
+
+impl fmt::Display for TimeDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let centis = self.0 * 100 / 60;
+        let minutes = (centis / 6000).min(99);
+        write!(
+            f,
+            "{}:{:02}.{:02}",
+            minutes,
+            centis / 100 % 60,
+            centis % 100
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+
+    use super::*;
+
+    #[test_case]
+    fn medals_are_awarded_at_the_par_inclusive(_gba: &mut agb::Gba) {
+        let par = Par {
+            gold: 600,
+            silver: 900,
+            bronze: 1200,
+        };
+        assert_eq!(par.medal(600), Some(Medal::Gold));
+        assert_eq!(par.medal(601), Some(Medal::Silver));
+        assert_eq!(par.medal(1200), Some(Medal::Bronze));
+        assert_eq!(par.medal(1201), None);
+        assert!(Medal::Gold < Medal::Bronze);
+    }
+
+    #[test_case]
+    fn times_are_shown_as_minutes_seconds_and_hundredths(_gba: &mut agb::Gba) {
+        assert_eq!(format!("{}", TimeDisplay(0)), "0:00.00");
+        assert_eq!(format!("{}", TimeDisplay(59)), "0:00.98");
+        assert_eq!(format!("{}", TimeDisplay(61 * 60 + 30)), "1:01.50");
+    }
+}
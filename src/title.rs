@@ -0,0 +1,280 @@
+// タイトル画面のシーン。左右で難易度、上下でモード、SELECT で HARD モード、
+// (レベルをクリアした後は) R でミラーモードを選び、START でゲーム本編へ。
+// 通信対戦はその前に相手を待つ画面へ、エンドレスはシードを選ぶ画面へ。ゴーストとの競争は、その難易度のゴーストがあるときだけ選べる。
+
+use alloc::boxed::Box;
+
+use agb::{display::Priority, input::Button};
+
+use crate::{
+    difficulty::Difficulty,
+    gameplay::GameScene,
+    gfx::rgb5,
+    input::Input,
+    layer::Layer,
+    lobby::LobbyScene,
+    mode::Mode,
+    modifiers::Modifiers,
+    save::Board,
+    scene::{Resources, Scene, Transition},
+    seed_entry::{SeedDisplay, SeedEntryScene},
+    text::TextLayer,
+    time_attack::TimeDisplay,
+};
+
+/* 「PRESS START」を点滅させる周期 (ティック) */
+const BLINK_TICKS: u32 = 32;
+
+pub struct TitleScene<'g> {
+    text: Option<TextLayer<'g>>,
+    ticks: u32,
+    difficulty: Difficulty,
+    mode: Mode,
+    modifiers: Modifiers,
+}
+
+impl TitleScene<'_> {
+    pub fn new() -> Self {
+        Self {
+            text: None,
+            ticks: 0,
+            difficulty: Difficulty::Normal,
+            mode: Mode::Normal,
+            modifiers: Modifiers::NONE,
+        }
+    }
+}
+
+impl<'g> Scene<'g> for TitleScene<'g> {
+    fn enter(&mut self, res: &mut Resources<'g>) {
+        res.vram.set_background_palette_colour(0, 0, rgb5(2, 2, 8));
+        let mut text = TextLayer::new(Layer::new(res.gfx, Priority::P0), &mut res.vram);
+        text.print_line(&mut res.vram, 7, format_args!("        JUMPING DROID"));
+        text.set_visible(true);
+        text.commit(&mut res.vram);
+        self.text = Some(text);
+    }
+
+    fn update(&mut self, res: &mut Resources<'g>, input: &Input) -> Transition<'g> {
+        self.ticks = self.ticks.wrapping_add(1);
+        if input.is_just_pressed(Button::LEFT) {
+            self.difficulty = self.difficulty.prev();
+        }
+        if input.is_just_pressed(Button::RIGHT) {
+            self.difficulty = self.difficulty.next();
+        }
+        if input.is_just_pressed(Button::UP) {
+            self.mode = self.mode.prev();
+        }
+        if input.is_just_pressed(Button::DOWN) {
+            self.mode = self.mode.next();
+        }
+        if input.is_just_pressed(Button::SELECT) {
+            let base = if self.modifiers.is_hard() {
+                Modifiers::NONE
+            } else {
+                Modifiers::HARD
+            };
+            self.modifiers = Modifiers {
+                mirror: self.modifiers.mirror,
+                ..base
+            };
+        }
+        /* ミラーモードはクリアしたレベルを新鮮に遊ぶためのものなので、クリアするまで選べない */
+        if input.is_just_pressed(Button::R) && res.save.records.has_cleared() {
+            self.modifiers.mirror = !self.modifiers.mirror;
+        }
+        /* 練習モードはレベルをクリアするまで、ゴーストとの競争はゴーストができるまで選べない */
+        let locked = match self.mode {
+            Mode::Practice => !res.save.records.has_cleared(),
+            Mode::GhostRace => res.save.ghost(self.difficulty).is_none(),
+            _ => false,
+        };
+        if input.is_just_pressed(Button::START) && !locked {
+            log_info!(
+                "{:?} game start on {:?} after {} ticks on the title",
+                self.mode,
+                self.difficulty,
+                self.ticks
+            );
+            /* タイトルで待っていたティック数をシードにする */
+            if self.mode == Mode::LinkRace {
+                let scene = LobbyScene::new(self.difficulty, self.modifiers, self.ticks);
+                return Transition::Replace(Box::new(scene));
+            }
+            if self.mode == Mode::Endless {
+                let scene = SeedEntryScene::new(self.difficulty, self.modifiers, self.ticks);
+                return Transition::Replace(Box::new(scene));
+            }
+            /* ゴーストとの競争のシードと修飾はゴーストに合わせる */
+            if let Some(replay) = res
+                .save
+                .ghost(self.difficulty)
+                .filter(|_| self.mode == Mode::GhostRace)
+            {
+                let scene = GameScene::ghost(replay.clone());
+                return Transition::Replace(Box::new(scene));
+            }
+            /* 普通のモードはチュートリアルから始める。一度終えていれば飛ばせる */
+            if self.mode == Mode::Normal {
+                let skippable = res.save.records.has_finished_tutorial();
+                let scene =
+                    GameScene::tutorial(self.difficulty, self.modifiers, self.ticks, skippable);
+                return Transition::Replace(Box::new(scene));
+            }
+            let scene = GameScene::new(self.difficulty, self.mode, self.modifiers, self.ticks);
+            return Transition::Replace(Box::new(scene));
+        }
+        Transition::Stay
+    }
+
+    fn draw(&mut self, res: &mut Resources<'g>) {
+        if let Some(text) = &mut self.text {
+            let message = if self.ticks % BLINK_TICKS < BLINK_TICKS / 2 {
+                "         PRESS START"
+            } else {
+                ""
+            };
+            text.print_line(&mut res.vram, 12, format_args!("{}", message));
+            let name = self.difficulty.settings().name;
+            /* 名前の長さに関わらず中央に揃える */
+            let pad = (15 - name.len() / 2) - 2;
+            text.print_line(
+                &mut res.vram,
+                10,
+                format_args!("{:pad$}< {} >", "", name, pad = pad),
+            );
+            let mode = self.mode.name();
+            let pad = 15 - mode.len() / 2;
+            text.print_line(
+                &mut res.vram,
+                9,
+                format_args!("{:pad$}{}", "", mode, pad = pad),
+            );
+            let hard = if self.modifiers.is_hard() {
+                "ON"
+            } else {
+                "OFF"
+            };
+            text.print_line(
+                &mut res.vram,
+                16,
+                format_args!("    SELECT: HARD MODE {}", hard),
+            );
+            if res.save.records.has_cleared() {
+                let mirror = if self.modifiers.mirror { "ON" } else { "OFF" };
+                text.print_line(
+                    &mut res.vram,
+                    17,
+                    format_args!("    R: MIRROR MODE {}", mirror),
+                );
+            }
+            let records = &res.save.records;
+            /* HARD モードで作られた記録には印を付ける */
+            let badge = |board| {
+                if records.is_hard(board, self.difficulty) {
+                    "H"
+                } else {
+                    ""
+                }
+            };
+            match self.mode {
+                Mode::Normal | Mode::HotSeat => {
+                    let best = records.best(self.difficulty);
+                    text.print_line(
+                        &mut res.vram,
+                        14,
+                        format_args!("          BEST {:5} {}", best, badge(Board::Score)),
+                    )
+                }
+                /* 同じ塔で競えるよう、最高得点のシードも出す */
+                Mode::Endless => {
+                    let best = records.best_climb(self.difficulty);
+                    let seed = records.best_climb_seed(self.difficulty);
+                    text.print_line(
+                        &mut res.vram,
+                        14,
+                        format_args!(
+                            " BEST {:5} {:1} SEED {}",
+                            best,
+                            badge(Board::Climb),
+                            SeedDisplay(seed)
+                        ),
+                    )
+                }
+                Mode::CoinRush => {
+                    let best = records.best_rush(self.difficulty);
+                    text.print_line(
+                        &mut res.vram,
+                        14,
+                        format_args!("          BEST {:5} {}", best, badge(Board::CoinRush)),
+                    )
+                }
+                /* ミラーモードのスピードランは記録しない */
+                Mode::Speedrun if self.modifiers.mirror => {
+                    text.print_line(&mut res.vram, 14, format_args!("        NO RECORDS"))
+                }
+                Mode::Speedrun => match records.best_speedrun(self.difficulty) {
+                    Some(splits) => text.print_line(
+                        &mut res.vram,
+                        14,
+                        format_args!(
+                            "      BEST {} {}",
+                            TimeDisplay(splits[splits.len() - 1]),
+                            badge(Board::Speedrun)
+                        ),
+                    ),
+                    None => text.print_line(&mut res.vram, 14, format_args!("      BEST -:--.--")),
+                },
+                /* 通信対戦は記録しない。チュートリアルは一覧に無い */
+                Mode::LinkRace | Mode::Tutorial => {
+                    text.print_line(&mut res.vram, 14, format_args!(""))
+                }
+                Mode::Practice if records.has_cleared() => {
+                    text.print_line(&mut res.vram, 14, format_args!(""))
+                }
+                Mode::Practice => text.print_line(
+                    &mut res.vram,
+                    14,
+                    format_args!("   CLEAR THE LEVEL TO UNLOCK"),
+                ),
+                Mode::GhostRace if res.save.ghost(self.difficulty).is_none() => text.print_line(
+                    &mut res.vram,
+                    14,
+                    format_args!("  SET A TIME ATTACK RECORD"),
+                ),
+                Mode::TimeAttack | Mode::GhostRace => {
+                    /* ゴーストはミラーモードでは作らないので、ゴーストとの競争は普通の最高タイム */
+                    let mirror = self.mode == Mode::TimeAttack && self.modifiers.mirror;
+                    let board = if mirror {
+                        Board::MirrorTime
+                    } else {
+                        Board::Time
+                    };
+                    match records.best_time(self.difficulty, mirror) {
+                        Some(best) => text.print_line(
+                            &mut res.vram,
+                            14,
+                            format_args!(
+                                "      BEST {} {} {}",
+                                TimeDisplay(best.ticks),
+                                best.medal.map_or("", |medal| medal.name()),
+                                badge(board)
+                            ),
+                        ),
+                        None => {
+                            text.print_line(&mut res.vram, 14, format_args!("      BEST -:--.--"))
+                        }
+                    }
+                }
+            }
+            text.commit(&mut res.vram);
+        }
+    }
+
+    fn exit(&mut self, res: &mut Resources<'g>) {
+        if let Some(text) = self.text.take() {
+            text.release(&mut res.vram);
+        }
+    }
+}
@@ -0,0 +1,226 @@
+// 遊びながら調整値を変える (debug-tools フィーチャー専用)。
+//
+// 動きの調整値は physics::Tuning にまとめてあり、Game が持つものを update_droid が毎ティック読む。
+// ここではそのうち手触りに効くもの (歩く速さ、跳ぶ高さ、重力、歩きのコマ送りの速さ、りんごの当たり幅) を、
+// ビルドし直さずに変えられるようにする。
+//
+// デバッグメニューで TUNING を ON にすると、SELECT を押している間はティックを止め、
+// 上下で変えるもの (Knob) を選び、左右で 1 段ずつ変える。変えた段数は DebugState::tweaks に溜めておき、
+// SELECT を離して最初の 1 ティックで Game::tweak が掛ける (デバッグメニューの命令と同じ流れ)。
+// OFF の間は SELECT をそのままゲームに渡す (重力の反転とチュートリアルを飛ばすのに使っている)。
+// 変えた後の値はログに出すので、決まったら Tuning::NORMAL に書き戻すこと。
+
+use agb::{display::tiled::VRamManager, input::Button};
+
+use crate::{
+    debug_menu::DebugState,
+    game::Game,
+    input::Input,
+    math::Fixed,
+    physics::Tuning,
+    text::{self, TextLayer},
+};
+
+/* 1 段で変える量。歩く速さと重力は raw (1/256 ドット単位)、跳ぶ高さはドット、コマ送りはティック */
+const WALK_SPEED_STEP: i32 = 16;
+const JUMP_HEIGHT_STEP: i32 = 4;
+const GRAVITY_STEP: i32 = 4;
+
+/* 選んでいるものを出す行 (計測のオーバーレイの下) */
+const ROW: u16 = text::ROWS - 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Knob {
+    // 接地中と空中の歩く速さ。
+    WalkSpeed,
+    // UP を押し続けたときに上る高さ。跳んだ瞬間の速さを合わせて変える。
+    JumpHeight,
+    // 重力。跳ぶ高さはそのままで、速さを合わせて変える。
+    Gravity,
+    // 歩きのコマを送るティック数。
+    WalkFrame,
+    // りんごの当たり幅 (半分)。NORMAL の幅より広くはしない。
+    AppleWidth,
+}
+
+pub const KNOBS: [Knob; 5] = [
+    Knob::WalkSpeed,
+    Knob::JumpHeight,
+    Knob::Gravity,
+    Knob::WalkFrame,
+    Knob::AppleWidth,
+];
+
+impl Knob {
+    fn label(self) -> &'static str {
+        match self {
+            Knob::WalkSpeed => "WALK SPEED",
+            Knob::JumpHeight => "JUMP HEIGHT",
+            Knob::Gravity => "GRAVITY",
+            Knob::WalkFrame => "WALK FRAME",
+            Knob::AppleWidth => "APPLE WIDTH",
+        }
+    }
+}
+
+pub struct Tuner {
+    cursor: usize,
+    /* Knob ごとに、これまでに変えた段数 (表示用) */
+    steps: [i32; KNOBS.len()],
+}
+
+impl Tuner {
+    pub fn new() -> Self {
+        Self {
+            cursor: 0,
+            steps: [0; KNOBS.len()],
+        }
+    }
+
+    // TUNING が ON の間、描画フレームごとに呼ぶ。SELECT を押していて、ティックを止めてほしいなら true 。
+    // L か R も押していれば、スロー再生とオーバーレイの切り替えなので何もしない。
+    pub fn update(&mut self, input: &Input, state: &mut DebugState) -> bool {
+        if !input.is_pressed(Button::SELECT)
+            || input.is_pressed(Button::L)
+            || input.is_pressed(Button::R)
+        {
+            return false;
+        }
+        if input.is_just_pressed(Button::UP) {
+            self.cursor = (self.cursor + KNOBS.len() - 1) % KNOBS.len();
+        }
+        if input.is_just_pressed(Button::DOWN) {
+            self.cursor = (self.cursor + 1) % KNOBS.len();
+        }
+        let step = if input.is_just_pressed(Button::LEFT) {
+            -1
+        } else if input.is_just_pressed(Button::RIGHT) {
+            1
+        } else {
+            0
+        };
+        self.steps[self.cursor] += step;
+        state.tweaks[self.cursor] += step;
+        true
+    }
+
+    pub fn draw(&self, layer: &mut TextLayer, vram: &mut VRamManager) {
+        layer.print_line(
+            vram,
+            ROW,
+            format_args!(
+                " TUNE {:<12}{:+}",
+                KNOBS[self.cursor].label(),
+                self.steps[self.cursor]
+            ),
+        );
+    }
+}
+
+impl Game {
+    // knob を steps 段変え、変えた後の値をログに出す。
+    pub fn tweak(&mut self, knob: Knob, steps: i32) {
+        let tuning = &mut self.tuning;
+        match knob {
+            Knob::WalkSpeed => {
+                let step = Fixed::from_raw(WALK_SPEED_STEP);
+                for control in [&mut tuning.ground, &mut tuning.air] {
+                    control.speed = (control.speed + step * steps).max(step);
+                }
+                log_info!(
+                    "walk speed {} (air {})",
+                    tuning.ground.speed.to_raw(),
+                    tuning.air.speed.to_raw()
+                );
+            }
+            Knob::JumpHeight => {
+                let max =
+                    (tuning.max_jump_height + JUMP_HEIGHT_STEP * steps).max(tuning.min_jump_height);
+                *tuning = tuning.with_jump(tuning.gravity, tuning.min_jump_height, max);
+                log_info!("max jump height {}", max);
+            }
+            Knob::Gravity => {
+                let raw = (tuning.gravity.to_raw() + GRAVITY_STEP * steps).max(GRAVITY_STEP);
+                *tuning = tuning.with_jump(
+                    Fixed::from_raw(raw),
+                    tuning.min_jump_height,
+                    tuning.max_jump_height,
+                );
+                log_info!("gravity {}", raw);
+            }
+            Knob::WalkFrame => {
+                let ticks =
+                    (i32::from(tuning.walk_frame_ticks) + steps).clamp(1, i32::from(u16::MAX));
+                tuning.walk_frame_ticks = ticks as u16;
+                log_info!("walk frame ticks {}", ticks);
+            }
+            Knob::AppleWidth => {
+                tuning.apple_half_width =
+                    (tuning.apple_half_width + steps).clamp(1, Tuning::NORMAL.apple_half_width);
+                let half_width = self.apple_half_width();
+                self.apple.half_width = half_width;
+                for (_, apple) in self.apples.iter_mut() {
+                    apple.half_width = half_width;
+                }
+                log_info!("apple half width {}", self.tuning.apple_half_width);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::Difficulty;
+
+    fn press(current: Button, previous: Button) -> Input {
+        Input::new(current, previous)
+    }
+
+    // SELECT を押している間だけティックを止め、上下で選んで左右で溜める。
+    #[test_case]
+    fn select_and_the_pad_queue_tweaks(_gba: &mut agb::Gba) {
+        let mut tuner = Tuner::new();
+        let mut state = DebugState::default();
+        assert!(!tuner.update(&press(Button::RIGHT, Button::empty()), &mut state));
+        assert!(tuner.update(&press(Button::SELECT, Button::empty()), &mut state));
+        let held = Button::SELECT;
+        tuner.update(&press(held | Button::DOWN, held), &mut state);
+        tuner.update(&press(held | Button::RIGHT, held), &mut state);
+        tuner.update(&press(held, held | Button::RIGHT), &mut state);
+        tuner.update(&press(held | Button::RIGHT, held), &mut state);
+        tuner.update(&press(held | Button::UP, held), &mut state);
+        tuner.update(&press(held | Button::LEFT, held), &mut state);
+        assert_eq!(state.tweaks, [-1, 2, 0, 0, 0]);
+        /* L+SELECT はスロー再生の切り替え */
+        assert!(!tuner.update(&press(Button::L | Button::SELECT, Button::L), &mut state));
+    }
+
+    // 跳ぶ高さと重力は速さを合わせて変え、幅や速さは下限で止まる。
+    #[test_case]
+    fn tweaks_change_the_game(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        game.tweak(Knob::JumpHeight, 2);
+        let normal = Tuning::NORMAL;
+        assert_eq!(
+            game.tuning,
+            normal.with_jump(
+                normal.gravity,
+                normal.min_jump_height,
+                normal.max_jump_height + 2 * JUMP_HEIGHT_STEP
+            )
+        );
+        game.tweak(Knob::WalkSpeed, -100);
+        assert_eq!(game.tuning.ground.speed, Fixed::from_raw(WALK_SPEED_STEP));
+        let width = normal.apple_half_width;
+        game.tweak(Knob::AppleWidth, 5);
+        assert_eq!(game.apple.half_width, width);
+        game.tweak(Knob::AppleWidth, -3);
+        assert_eq!(
+            (game.tuning.apple_half_width, game.apple.half_width),
+            (width - 3, width - 3)
+        );
+        game.tweak(Knob::WalkFrame, 2);
+        assert_eq!(game.tuning.walk_frame_ticks, normal.walk_frame_ticks + 2);
+    }
+}
@@ -0,0 +1,169 @@
+// 交代で遊ぶときの順番の管理。
+//
+// プレイヤーごとに Game を 1 つずつ持ち (得点、体力、コインの取り具合は全て別々)、
+// 遊んでいるプレイヤーの Game だけを進める。1 人で遊ぶときもプレイヤーが 1 人の Turns を通す。
+//
+// 交代で遊ぶモードでは、体力を 1 つ失うか、そのプレイヤーのランが終わったら次の人の番になる。
+// 終わったプレイヤーは飛ばし、全員終わったら Finished 。
+
+use alloc::vec::Vec;
+
+use crate::{
+    difficulty::Difficulty,
+    game::{Game, Outcome},
+    mode::Mode,
+    modifiers::Modifiers,
+};
+
+// ティックの後に何が起きたか。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TurnEnd {
+    // 同じプレイヤーが続ける。
+    Continue,
+    // 番が player (0 始まり) に移った。
+    Pass { player: usize },
+    // 全員のランが終わった。
+    Finished,
+}
+
+struct Slot {
+    game: Game,
+    /* ランが終わっていればその結果 */
+    outcome: Option<Outcome>,
+    /* 番が回ってきたときの体力。これより減ったら交代 */
+    turn_health: u8,
+}
+
+pub struct Turns {
+    slots: Vec<Slot>,
+    current: usize,
+}
+
+impl Turns {
+    // mode が交代で遊ぶモードなら 2 人分、それ以外は 1 人分。全員同じレベル、同じシードから始める。
+    pub fn new(difficulty: Difficulty, mode: Mode, modifiers: Modifiers, seed: u32) -> Self {
+        let players = if mode == Mode::HotSeat { 2 } else { 1 };
+        let slots = (0..players)
+            .map(|player| {
+                let mut game = Game::new(difficulty, seed)
+                    .with_mode(mode)
+                    .with_modifiers(modifiers);
+                game.player = player as u8;
+                Slot {
+                    turn_health: game.health,
+                    game,
+                    outcome: None,
+                }
+            })
+            .collect();
+        Self { slots, current: 0 }
+    }
+
+    // 今遊んでいるプレイヤー (0 始まり)。
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.slots[self.current].game
+    }
+
+    pub fn game_mut(&mut self) -> &mut Game {
+        &mut self.slots[self.current].game
+    }
+
+    // 全員の Game を順に。
+    pub fn games(&self) -> impl Iterator<Item = &Game> {
+        self.slots.iter().map(|slot| &slot.game)
+    }
+
+    // 全員のランの結果。まだ終わっていなければ None 。
+    pub fn outcomes(&self) -> Vec<Option<Outcome>> {
+        self.slots.iter().map(|slot| slot.outcome).collect()
+    }
+
+    // 今のプレイヤーの Game を 1 ティック進めた後に呼ぶ。
+    pub fn end_turn(&mut self) -> TurnEnd {
+        let slot = &mut self.slots[self.current];
+        let outcome = slot.game.outcome();
+        let lost_life = slot.game.health < slot.turn_health;
+        if outcome.is_none() && !lost_life {
+            return TurnEnd::Continue;
+        }
+        slot.outcome = outcome;
+        let count = self.slots.len();
+        let next = (1..=count)
+            .map(|step| (self.current + step) % count)
+            .find(|&player| self.slots[player].outcome.is_none());
+        match next {
+            None => TurnEnd::Finished,
+            Some(player) => {
+                let slot = &mut self.slots[player];
+                slot.turn_health = slot.game.health;
+                if player == self.current {
+                    /* 1 人で遊んでいるか、他の人が終わっている */
+                    TurnEnd::Continue
+                } else {
+                    log_info!("player {} turn", player + 1);
+                    self.current = player;
+                    TurnEnd::Pass { player }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{events::Event, input::Input};
+
+    fn hit(turns: &mut Turns) {
+        turns.game_mut().push_event(Event::PlayerDamaged {
+            amount: 1,
+            from_x: 0,
+        });
+        turns.game_mut().update(&Input::default());
+    }
+
+    // 体力を 1 つ失うごとに交代し、それぞれの体力と得点は別々に減る。
+    #[test_case]
+    fn losing_a_life_passes_the_turn(_gba: &mut agb::Gba) {
+        let mut turns = Turns::new(Difficulty::Normal, Mode::HotSeat, Modifiers::NONE, 1);
+        let lives = turns.game().health;
+        turns.game_mut().score = 30;
+        hit(&mut turns);
+        assert_eq!(turns.end_turn(), TurnEnd::Pass { player: 1 });
+        assert_eq!((turns.game().health, turns.game().score), (lives, 0));
+        assert_eq!(turns.game().player, 1);
+        hit(&mut turns);
+        assert_eq!(turns.end_turn(), TurnEnd::Pass { player: 0 });
+        assert_eq!((turns.game().health, turns.game().score), (lives - 1, 30));
+        /* 体力が減らなければ続ける */
+        turns.game_mut().update(&Input::default());
+        assert_eq!(turns.end_turn(), TurnEnd::Continue);
+    }
+
+    // 終わったプレイヤーは飛ばし、全員終わったら Finished 。
+    #[test_case]
+    fn finished_players_are_skipped(_gba: &mut agb::Gba) {
+        let mut turns = Turns::new(Difficulty::Hard, Mode::HotSeat, Modifiers::NONE, 1);
+        /* HARD は 1 回で体力が尽きる */
+        hit(&mut turns);
+        assert_eq!(turns.end_turn(), TurnEnd::Pass { player: 1 });
+        for _ in 0..3 {
+            turns.game_mut().update(&Input::default());
+            assert_eq!(turns.end_turn(), TurnEnd::Continue);
+        }
+        hit(&mut turns);
+        assert_eq!(turns.end_turn(), TurnEnd::Finished);
+        assert_eq!(turns.outcomes(), [Some(Outcome::Died), Some(Outcome::Died)]);
+
+        /* 1 人なら体力を失っても交代しない */
+        let mut turns = Turns::new(Difficulty::Normal, Mode::Normal, Modifiers::NONE, 1);
+        hit(&mut turns);
+        assert_eq!(turns.end_turn(), TurnEnd::Continue);
+        assert_eq!(turns.current(), 0);
+    }
+}
@@ -0,0 +1,140 @@
+// チュートリアル (レベル 0)。
+//
+// 普通のモードを始めると、レベル 1 の前にこのレベルを遊ぶ。
+// レベルは関門で区切ってあり、それぞれの区画で操作をやってみせると次の関門が開く。
+// どこで何をすれば開くかは TUTORIAL_SCRIPT (script モジュールのトリガーの並び) に書いてあり、
+// ここでは毎ティックそれを進めるだけ。閉じている関門は右の壁になる (Game::walls)。
+// 待っているトリガーの案内は HUD に出す (Presenter)。
+// 最後のトリガー (りんごに乗る) を満たすとクリアで、そのままレベル 1 へ進む (GameScene)。
+// 一度終えたことがあれば SELECT で飛ばせる (save::Records::has_finished_tutorial)。
+
+use agb::display::WIDTH;
+
+use crate::{
+    collision::Aabb,
+    game::{Game, DROID_WIDTH, GROUND_Y},
+    player::PlayerState,
+    script::{Condition, Trigger},
+};
+
+/* 区画の高さ (画面の上から地面の下まで) */
+const AREA_HEIGHT: i32 = GROUND_Y + DROID_WIDTH;
+
+pub const TUTORIAL_SCRIPT: [Trigger; 4] = [
+    Trigger {
+        rect: Aabb::new(40, 0, 32, AREA_HEIGHT),
+        condition: Condition::Enter,
+        hint: "LEFT/RIGHT: WALK",
+        gate: Some(72),
+    },
+    Trigger {
+        rect: Aabb::new(72, 0, 64, AREA_HEIGHT),
+        condition: Condition::Jump,
+        hint: "UP: JUMP",
+        gate: Some(136),
+    },
+    Trigger {
+        rect: Aabb::new(136, 0, 56, AREA_HEIGHT),
+        condition: Condition::DoubleJump,
+        hint: "UP IN THE AIR: DOUBLE JUMP",
+        gate: Some(192),
+    },
+    Trigger {
+        rect: Aabb::new(192, 0, WIDTH - 192, AREA_HEIGHT),
+        condition: Condition::LandOnApple,
+        hint: "LAND ON THE APPLE",
+        gate: None,
+    },
+];
+
+impl Game {
+    // チュートリアルの 1 ティック分。ドロイド君を動かした後に呼ぶ。
+    // before は動かす前のドロイド君の状態 (Droid::state)。
+    pub fn update_tutorial(&mut self, before: PlayerState) {
+        let Some(mut script) = self.script.take() else {
+            return;
+        };
+        if let Some(trigger) = script.update(before, self) {
+            log_info!("tutorial: {} done", trigger.hint);
+        }
+        self.script = Some(script);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use agb::input::Button;
+
+    use super::*;
+    use crate::{
+        collision::Surface, difficulty::Difficulty, game::Outcome, input::Input, level::Level,
+        math::Fixed, mode::Mode, script::Script,
+    };
+
+    fn hint(game: &Game) -> Option<&'static str> {
+        let script = game.script.as_ref()?;
+        script.current().map(|trigger| trigger.hint)
+    }
+
+    // 閉じた関門は越えられず、やってみせると開く。最後にりんごに乗ればクリア。
+    #[test_case]
+    fn gates_open_one_mechanic_at_a_time(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1).with_mode(Mode::Tutorial);
+        assert_eq!((game.droid.x, game.droid.y), Level::tutorial().droid);
+        let mut previous = Button::empty();
+        let mut press = |game: &mut Game, buttons: Button| {
+            game.update(&Input::new(buttons, previous));
+            previous = buttons;
+        };
+        /* 歩くと 1 つ目が開き、2 つ目の関門で止まる */
+        for _ in 0..200 {
+            press(&mut game, Button::RIGHT);
+        }
+        assert_eq!(hint(&game), Some("UP: JUMP"));
+        assert_eq!(game.droid.x, 136 - DROID_WIDTH);
+        /* 左の画面端からも回り込めない */
+        for _ in 0..200 {
+            press(&mut game, Button::LEFT);
+        }
+        assert_eq!(game.droid.x, 0);
+        for _ in 0..100 {
+            press(&mut game, Button::RIGHT);
+        }
+        press(&mut game, Button::UP);
+        assert_eq!(hint(&game), Some("UP IN THE AIR: DOUBLE JUMP"));
+        /* 次の区画へ入って跳んだだけでは開かない */
+        while game.droid.state != PlayerState::Idle {
+            press(&mut game, Button::empty());
+        }
+        for _ in 0..200 {
+            press(&mut game, Button::RIGHT);
+        }
+        assert_eq!(game.droid.x, 192 - DROID_WIDTH);
+        press(&mut game, Button::UP);
+        assert_eq!(hint(&game), Some("UP IN THE AIR: DOUBLE JUMP"));
+        while !matches!(game.droid.state, PlayerState::Airborne { double: false }) {
+            press(&mut game, Button::empty());
+        }
+        press(&mut game, Button::UP);
+        assert_eq!(hint(&game), Some("LAND ON THE APPLE"));
+        assert_eq!(game.outcome(), None);
+    }
+
+    // りんごに降りるとクリアになる。
+    #[test_case]
+    fn landing_on_the_apple_finishes(_gba: &mut agb::Gba) {
+        let mut game = Game::new(Difficulty::Normal, 1).with_mode(Mode::Tutorial);
+        /* 最後の仕掛けだけ残す */
+        game.script = Some(Script::new(&TUTORIAL_SCRIPT[TUTORIAL_SCRIPT.len() - 1..]));
+        /* りんごの真上から落とす */
+        game.droid.x = game.apple.x;
+        game.droid.y = game.apple.top_y() - 20;
+        game.droid.state = PlayerState::Airborne { double: false };
+        game.droid.vy = Fixed::new(0);
+        while game.droid.state != PlayerState::Idle {
+            game.update(&Input::default());
+        }
+        assert_eq!(game.droid.y, game.apple.top_y());
+        assert_eq!(game.outcome(), Some(Outcome::Cleared));
+    }
+}
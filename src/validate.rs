@@ -0,0 +1,699 @@
+// 起動時のデータ検査 (デバッグビルドのみ)。
+//
+// 素材とコードの食い違い (タグが無い、タイル番号が範囲外、置き場所の足りないレベル、
+// 順番のおかしい目標タイム...) はリリースビルドでは代用品で黙って動くか、
+// 遊んでいる途中で panic して初めて分かる。
+// デバッグビルドではタイトルより前にデータ同士の参照を全て確かめ、
+// 1 つでもおかしければパニック画面に一覧を出して止まる。
+//
+// 音はまだ無いので、サウンド ID の検査は音を足すときにここへ足すこと。
+
+use alloc::{format, string::String, vec::Vec};
+
+use crate::{
+    collision::Aabb,
+    crumbling::{CRUMBLING_PLATFORM_WIDTH, MAX_CRUMBLING_PLATFORMS},
+    door::{DOOR_HEIGHT, DOOR_WIDTH, KEY_SIZE, MAX_DOORS, MAX_KEYS},
+    font,
+    game::{APPLE_SIZE, COIN_SIZE, DROID_WIDTH, GROUND_Y, LEVEL_PAR, MAX_APPLES, MAX_COINS},
+    gameplay::{
+        BLOCK, CONVEYOR_TILES, DOOR_TILES, GROUND_ROW, GROUND_TILES, KEY, LADDER, SPIKE,
+        STREAK_TILES, SWITCH_BLOCK_TILES, SWITCH_TILES, TELEPORTER_TILES, WATER_TILES,
+    },
+    gfx::tiles,
+    level::Level,
+    math::Fixed,
+    memory,
+    moving_platform::{MAX_MOVING_PLATFORMS, MOVING_PLATFORM_WIDTH},
+    resources::{GRAPHICS, TAGS},
+    spawn::EntityKind,
+    spike::{MAX_SPIKES, SPIKE_SIZE},
+    spring::{MAX_SPRINGS, SPRING_SIZE},
+    switch::{MAX_SWITCHES, SWITCH_SIZE},
+    teleporter::{MAX_TELEPORTERS, TELEPORTER_HEIGHT, TELEPORTER_WIDTH},
+    terrain::{self, Material, Terrain},
+    text,
+    water::MAX_WATERS,
+    wind::MAX_WINDS,
+    zone::{ZoneAction, MAX_ZONES},
+};
+
+// 全て検査する。問題があれば一覧を出してここで止まる。
+pub fn check_or_halt() {
+    let problems = check();
+    if problems.is_empty() {
+        log_info!("data check passed");
+        return;
+    }
+    for problem in &problems {
+        log_warn!("bad data: {}", problem);
+    }
+    crate::panic_screen::show_problems(&problems);
+}
+
+// 見つかった問題を 1 件 1 行で返す。パニック画面の幅に収まるよう短く書く。
+pub fn check() -> Vec<String> {
+    let mut problems = Vec::new();
+    check_sprite_tags(&mut problems);
+    check_tile_indices(
+        "ground",
+        &GROUND_TILES,
+        tiles::bg.tile_settings.len(),
+        &mut problems,
+    );
+    check_tile_indices(
+        "conveyor",
+        &CONVEYOR_TILES,
+        tiles::bg.tile_settings.len(),
+        &mut problems,
+    );
+    check_tile_indices(
+        "block",
+        &[BLOCK],
+        tiles::bg.tile_settings.len(),
+        &mut problems,
+    );
+    check_tile_indices(
+        "switch block",
+        SWITCH_BLOCK_TILES.as_flattened(),
+        tiles::bg.tile_settings.len(),
+        &mut problems,
+    );
+    check_tile_indices(
+        "switch",
+        SWITCH_TILES.as_flattened(),
+        tiles::bg.tile_settings.len(),
+        &mut problems,
+    );
+    check_tile_indices(
+        "door",
+        &DOOR_TILES,
+        tiles::bg.tile_settings.len(),
+        &mut problems,
+    );
+    check_tile_indices("key", &[KEY], tiles::bg.tile_settings.len(), &mut problems);
+    check_tile_indices(
+        "ladder",
+        &[LADDER],
+        tiles::bg.tile_settings.len(),
+        &mut problems,
+    );
+    check_tile_indices(
+        "water",
+        &WATER_TILES,
+        tiles::bg.tile_settings.len(),
+        &mut problems,
+    );
+    check_tile_indices(
+        "wind",
+        &STREAK_TILES,
+        tiles::bg.tile_settings.len(),
+        &mut problems,
+    );
+    check_tile_indices(
+        "teleporter",
+        &TELEPORTER_TILES,
+        tiles::bg.tile_settings.len(),
+        &mut problems,
+    );
+    check_tile_indices(
+        "spike",
+        &[SPIKE],
+        tiles::bg.tile_settings.len(),
+        &mut problems,
+    );
+    check_font(&mut problems);
+    /* ミラーモードで反転した後も */
+    for mirror in [false, true] {
+        /* コインラッシュではコインラッシュ層も一緒に置く */
+        let level = Level::load(mirror);
+        let objects: Vec<_> = level
+            .objects
+            .iter()
+            .chain(&level.rush)
+            .chain(&level.platforms)
+            .copied()
+            .collect();
+        check_level(&objects, &mut problems);
+        for column in terrain::steps(&level.terrain) {
+            problems.push(format!("terrain step at column {column}"));
+        }
+        check_walls(&level, &mut problems);
+        check_conveyors(&level, &mut problems);
+        check_blocks(&level, &mut problems);
+        check_ladders(&level, &mut problems);
+    }
+    if !LEVEL_PAR.is_ordered() {
+        problems.push(String::from("par times out of order"));
+    }
+    if memory::iwram_used() > memory::IWRAM_BUDGET {
+        problems.push(format!(
+            "iwram {} > {} bytes",
+            memory::iwram_used(),
+            memory::IWRAM_BUDGET
+        ));
+    }
+    problems
+}
+
+fn check_sprite_tags(problems: &mut Vec<String>) {
+    for (name, frames) in TAGS {
+        match GRAPHICS.tags().try_get(name) {
+            None => problems.push(format!("no sprite tag {}", name)),
+            Some(tag) if tag.sprites().len() < frames => problems.push(format!(
+                "tag {} has {}/{} frames",
+                name,
+                tag.sprites().len(),
+                frames
+            )),
+            Some(_) => {}
+        }
+    }
+}
+
+fn check_tile_indices(what: &str, indices: &[usize], len: usize, problems: &mut Vec<String>) {
+    for &index in indices {
+        if index >= len {
+            problems.push(format!("{} tile {} >= {}", what, index, len));
+        }
+    }
+}
+
+fn check_font(problems: &mut Vec<String>) {
+    let glyphs = (font::FIRST_CHAR..=font::LAST_CHAR).map(font::tile_for);
+    let bars = (0..=8).map(font::bar_tile);
+    for tile in glyphs.chain(bars) {
+        if tile as usize >= font::TILE_COUNT {
+            problems.push(format!("font tile {} >= {}", tile, font::TILE_COUNT));
+        }
+    }
+}
+
+fn check_level(objects: &[(EntityKind, (i32, i32))], problems: &mut Vec<String>) {
+    let count = |of: EntityKind| objects.iter().filter(|(kind, _)| *kind == of).count();
+    let (coins, apples) = (count(EntityKind::Coin), count(EntityKind::Apple));
+    let crumbling = count(EntityKind::CrumblingPlatform);
+    let springs = count(EntityKind::Spring);
+    let switches = count(EntityKind::Switch);
+    let (keys, doors) = (count(EntityKind::Key), count(EntityKind::Door));
+    let spikes = count(EntityKind::Spike);
+    if coins > MAX_COINS {
+        problems.push(format!("{} coins > {}", coins, MAX_COINS));
+    }
+    if apples > MAX_APPLES {
+        problems.push(format!("{} apples > {}", apples, MAX_APPLES));
+    }
+    let zones = objects
+        .iter()
+        .filter(|(kind, _)| matches!(kind, EntityKind::Zone { .. }))
+        .count();
+    if zones > MAX_ZONES {
+        problems.push(format!("{} zones > {}", zones, MAX_ZONES));
+    }
+    let waters: Vec<_> = objects
+        .iter()
+        .filter_map(|&(kind, (x, y))| match kind {
+            EntityKind::Water {
+                size: (width, height),
+            } => Some(Aabb::new(x, y, width, height)),
+            _ => None,
+        })
+        .collect();
+    if waters.len() > MAX_WATERS {
+        problems.push(format!("{} waters > {}", waters.len(), MAX_WATERS));
+    }
+    let winds: Vec<_> = objects
+        .iter()
+        .filter_map(|&(kind, (x, y))| match kind {
+            EntityKind::Wind {
+                size: (width, height),
+                ..
+            } => Some(Aabb::new(x, y, width, height)),
+            _ => None,
+        })
+        .collect();
+    if winds.len() > MAX_WINDS {
+        problems.push(format!("{} winds > {}", winds.len(), MAX_WINDS));
+    }
+    if crumbling > MAX_CRUMBLING_PLATFORMS {
+        problems.push(format!(
+            "{} crumbling platforms > {}",
+            crumbling, MAX_CRUMBLING_PLATFORMS
+        ));
+    }
+    if springs > MAX_SPRINGS {
+        problems.push(format!("{} springs > {}", springs, MAX_SPRINGS));
+    }
+    if switches > MAX_SWITCHES {
+        problems.push(format!("{} switches > {}", switches, MAX_SWITCHES));
+    }
+    if keys > MAX_KEYS {
+        problems.push(format!("{} keys > {}", keys, MAX_KEYS));
+    }
+    if doors > MAX_DOORS {
+        problems.push(format!("{} doors > {}", doors, MAX_DOORS));
+    }
+    if spikes > MAX_SPIKES {
+        problems.push(format!("{} spikes > {}", spikes, MAX_SPIKES));
+    }
+    let pairs: Vec<_> = objects
+        .iter()
+        .filter_map(|(kind, _)| match kind {
+            EntityKind::Teleporter { pair } => Some(*pair),
+            _ => None,
+        })
+        .collect();
+    if pairs.len() > MAX_TELEPORTERS {
+        problems.push(format!("{} teleporters > {}", pairs.len(), MAX_TELEPORTERS));
+    }
+    /* 同じ番号の台はちょうど 2 つ (相手の無い台や、3 つ目の台は使えない) */
+    for (index, pair) in pairs.iter().enumerate() {
+        let pads = pairs.iter().filter(|other| *other == pair).count();
+        if pads != 2 && !pairs[..index].contains(pair) {
+            problems.push(format!("teleporter pair {} has {} pads", pair, pads));
+        }
+    }
+    let platforms = objects.len()
+        - coins
+        - apples
+        - zones
+        - waters.len()
+        - winds.len()
+        - crumbling
+        - springs
+        - switches
+        - keys
+        - doors
+        - spikes
+        - pairs.len();
+    if platforms > MAX_MOVING_PLATFORMS {
+        problems.push(format!(
+            "{} platforms > {}",
+            platforms, MAX_MOVING_PLATFORMS
+        ));
+    }
+    for &(kind, (x, y)) in objects {
+        /* 背景のタイルで描くものは、消したり描き直したりすると水の絵や風の粒も消えるので水にも風にも入れない */
+        let tiled = match kind {
+            EntityKind::Coin => Some(("coin", COIN_SIZE, COIN_SIZE)),
+            EntityKind::Key => Some(("key", KEY_SIZE, KEY_SIZE)),
+            EntityKind::Door => Some(("door", DOOR_WIDTH, DOOR_HEIGHT)),
+            EntityKind::Spike => Some(("spike", SPIKE_SIZE, SPIKE_SIZE)),
+            EntityKind::Switch => Some(("switch", SWITCH_SIZE, SWITCH_SIZE)),
+            EntityKind::Teleporter { .. } => {
+                Some(("teleporter", TELEPORTER_WIDTH, TELEPORTER_HEIGHT))
+            }
+            _ => None,
+        };
+        if let Some((what, width, height)) = tiled {
+            let rect = Aabb::new(x, y, width, height);
+            if waters.iter().any(|water| water.overlaps(rect)) {
+                problems.push(format!("{} at {},{} in water", what, x, y));
+            }
+            if winds.iter().any(|wind| wind.overlaps(rect)) {
+                problems.push(format!("{} at {},{} in wind", what, x, y));
+            }
+        }
+        match kind {
+            EntityKind::Coin => {
+                if !(0..=agb::display::WIDTH - COIN_SIZE).contains(&x)
+                    || !(0..=GROUND_Y - COIN_SIZE).contains(&y)
+                {
+                    problems.push(format!("coin at {},{} off stage", x, y));
+                }
+                /* HUD の文字として描くので 8 の倍数に揃っていること */
+                if x % 8 != 0 || y % 8 != 0 {
+                    problems.push(format!("coin at {},{} off grid", x, y));
+                }
+            }
+            EntityKind::Apple => {
+                /* 乗ったドロイド君が画面の上にはみ出さない */
+                if !(0..=agb::display::WIDTH - APPLE_SIZE).contains(&x)
+                    || !(DROID_WIDTH..=GROUND_Y).contains(&y)
+                {
+                    problems.push(format!("apple at {},{} off stage", x, y));
+                }
+            }
+            EntityKind::Zone {
+                size: (width, height),
+                action,
+            } => {
+                if width <= 0
+                    || height <= 0
+                    || x < 0
+                    || y < 0
+                    || x + width > agb::display::WIDTH
+                    || y + height > agb::display::HEIGHT
+                {
+                    problems.push(format!("zone at {},{} off stage", x, y));
+                }
+                if let ZoneAction::Hint(hint) = action {
+                    if hint.len() > text::COLUMNS as usize {
+                        problems.push(format!("hint at {},{} too long", x, y));
+                    }
+                }
+            }
+            EntityKind::Water {
+                size: (width, height),
+            } => {
+                /* 地面より上に、背景のタイルとして描く */
+                if width <= 0
+                    || height <= 0
+                    || x < 0
+                    || y < 0
+                    || x + width > agb::display::WIDTH
+                    || y + height > GROUND_Y + DROID_WIDTH
+                {
+                    problems.push(format!("water at {},{} off stage", x, y));
+                }
+                if x % 8 != 0 || y % 8 != 0 || width % 8 != 0 || height % 8 != 0 {
+                    problems.push(format!("water at {},{} off grid", x, y));
+                }
+            }
+            EntityKind::Wind {
+                size: (width, height),
+                force,
+            } => {
+                if width <= 0
+                    || height <= 0
+                    || x < 0
+                    || y < 0
+                    || x + width > agb::display::WIDTH
+                    || y + height > agb::display::HEIGHT
+                {
+                    problems.push(format!("wind at {},{} off stage", x, y));
+                }
+                /* 粒をタイル 1 枚ずつ描く */
+                if x % 8 != 0 || y % 8 != 0 || width % 8 != 0 || height % 8 != 0 {
+                    problems.push(format!("wind at {},{} off grid", x, y));
+                }
+                if force == (0, 0) {
+                    problems.push(format!("wind at {},{} does not blow", x, y));
+                }
+            }
+            EntityKind::CrumblingPlatform => {
+                if !(0..=agb::display::WIDTH - CRUMBLING_PLATFORM_WIDTH).contains(&x)
+                    || !(DROID_WIDTH..GROUND_Y).contains(&y)
+                {
+                    problems.push(format!("crumbling platform at {},{} off stage", x, y));
+                }
+            }
+            EntityKind::Spring => {
+                /* 地面か足場の上に置く。打ち上げられたドロイド君は画面の上端で止まる */
+                if !(0..=agb::display::WIDTH - SPRING_SIZE).contains(&x)
+                    || !(DROID_WIDTH..=GROUND_Y).contains(&y)
+                {
+                    problems.push(format!("spring at {},{} off stage", x, y));
+                }
+            }
+            EntityKind::Switch => {
+                /* ばねと同じく地面か足場の上に置く */
+                if !(0..=agb::display::WIDTH - SWITCH_SIZE).contains(&x)
+                    || !(DROID_WIDTH..=GROUND_Y).contains(&y)
+                {
+                    problems.push(format!("switch at {},{} off stage", x, y));
+                }
+                /* 背景のタイルとして描くので 8 の倍数に揃っていること */
+                if x % 8 != 0 || y % 8 != 0 {
+                    problems.push(format!("switch at {},{} off grid", x, y));
+                }
+            }
+            EntityKind::Key => {
+                /* コインと同じく地面より上に浮かべ、背景のタイルとして描く */
+                if !(0..=agb::display::WIDTH - KEY_SIZE).contains(&x)
+                    || !(0..=GROUND_Y - KEY_SIZE).contains(&y)
+                {
+                    problems.push(format!("key at {},{} off stage", x, y));
+                }
+                if x % 8 != 0 || y % 8 != 0 {
+                    problems.push(format!("key at {},{} off grid", x, y));
+                }
+            }
+            EntityKind::Door => {
+                /* 下端を地面か足場の上面に揃えて立てる */
+                if !(0..=agb::display::WIDTH - DOOR_WIDTH).contains(&x)
+                    || !(0..=GROUND_Y + DROID_WIDTH - DOOR_HEIGHT).contains(&y)
+                {
+                    problems.push(format!("door at {},{} off stage", x, y));
+                }
+                if x % 8 != 0 || y % 8 != 0 {
+                    problems.push(format!("door at {},{} off grid", x, y));
+                }
+            }
+            EntityKind::Teleporter { .. } => {
+                /* 扉と同じく下端を地面か足場の上面に揃え、背景のタイルとして描く */
+                if !(0..=agb::display::WIDTH - TELEPORTER_WIDTH).contains(&x)
+                    || !(DROID_WIDTH..=GROUND_Y + DROID_WIDTH - TELEPORTER_HEIGHT).contains(&y)
+                {
+                    problems.push(format!("teleporter at {},{} off stage", x, y));
+                }
+                if x % 8 != 0 || y % 8 != 0 {
+                    problems.push(format!("teleporter at {},{} off grid", x, y));
+                }
+            }
+            EntityKind::Spike => {
+                /* テレポーターと同じく下端を地面か壁の上面に揃え、背景のタイルとして描く */
+                if !(0..=agb::display::WIDTH - SPIKE_SIZE).contains(&x)
+                    || !(DROID_WIDTH..=GROUND_Y + DROID_WIDTH - SPIKE_SIZE).contains(&y)
+                {
+                    problems.push(format!("spike at {},{} off stage", x, y));
+                }
+                if x % 8 != 0 || y % 8 != 0 {
+                    problems.push(format!("spike at {},{} off grid", x, y));
+                }
+            }
+            EntityKind::Elevator { to_y, .. } => {
+                if !(0..=agb::display::WIDTH - MOVING_PLATFORM_WIDTH).contains(&x)
+                    || [y, to_y]
+                        .iter()
+                        .any(|y| !(DROID_WIDTH..GROUND_Y).contains(y))
+                {
+                    problems.push(format!("elevator at {},{} off stage", x, y));
+                }
+            }
+            EntityKind::MovingPlatform { to, .. } => {
+                /* 両端とも画面の中 */
+                for (x, y) in [(x, y), to] {
+                    if !(0..=agb::display::WIDTH - MOVING_PLATFORM_WIDTH).contains(&x)
+                        || !(DROID_WIDTH..GROUND_Y).contains(&y)
+                    {
+                        problems.push(format!("platform at {},{} off stage", x, y));
+                    }
+                }
+                if x != to.0 && y != to.1 {
+                    problems.push(format!("platform at {},{} moves diagonally", x, y));
+                }
+            }
+        }
+    }
+}
+
+// 壁は平らな列に立ち、画面の上端までに収まること。1 段だけの壁は段差として上れてしまうので 2 段から。
+fn check_walls(level: &Level, problems: &mut Vec<String>) {
+    for (column, (&height, terrain)) in level.walls.iter().zip(level.terrain).enumerate() {
+        if height == 0 {
+            continue;
+        }
+        if terrain != Terrain::Flat {
+            problems.push(format!("wall on a slope at column {column}"));
+        }
+        if !(2..GROUND_ROW as usize).contains(&height) {
+            problems.push(format!("wall {height} high at column {column}"));
+        }
+    }
+}
+
+// ベルトコンベアは平らで壁も材質も無い列にだけ置く (描いたベルトと足元の効き目が食い違わないように)。
+fn check_conveyors(level: &Level, problems: &mut Vec<String>) {
+    for (column, &speed) in level.conveyors.iter().enumerate() {
+        if speed == Fixed::new(0) {
+            continue;
+        }
+        if level.terrain[column] != Terrain::Flat {
+            problems.push(format!("conveyor on a slope at column {column}"));
+        }
+        if level.walls[column] > 0 {
+            problems.push(format!("conveyor under a wall at column {column}"));
+        }
+        let material = Material::of_tile(level.ground[column]);
+        if material != Material::Normal {
+            problems.push(format!("conveyor on {material:?} at column {column}"));
+        }
+    }
+}
+
+// 壊せるブロックとスイッチのブロックは地面や壁より上に浮かべる (地面のタイルを上書きしないように)。
+// 坂の列は高い方の端が 1 段上がっているので、1 段上まで地面とみなす。
+// どちらも 1 列に 1 つまでで、同じ列には置けない。
+fn check_blocks(level: &Level, problems: &mut Vec<String>) {
+    let switch_blocks = level.switch_blocks.map(|block| block.map(|(row, _)| row));
+    for (column, (&block, switch_block)) in level.blocks.iter().zip(switch_blocks).enumerate() {
+        let row = match (block, switch_block) {
+            (None, None) => continue,
+            (Some(_), Some(_)) => {
+                problems.push(format!("two blocks in column {column}"));
+                continue;
+            }
+            (Some(row), None) | (None, Some(row)) => row,
+        };
+        let slope = usize::from(level.terrain[column] != Terrain::Flat);
+        let top = (GROUND_ROW as usize).saturating_sub(level.walls[column] + slope);
+        if usize::from(row) >= top {
+            problems.push(format!("block in the ground at column {column}"));
+        }
+    }
+}
+
+// はしごは平らで壁の無い列に、地面より上から掛ける (背景のはしごを地面まで描き、下りたら地面に立つように)。
+// 同じ列のブロックは、はしごの上端より上にだけ置ける。
+fn check_ladders(level: &Level, problems: &mut Vec<String>) {
+    for (column, &ladder) in level.ladders.iter().enumerate() {
+        let Some(top) = ladder else {
+            continue;
+        };
+        if level.terrain[column] != Terrain::Flat {
+            problems.push(format!("ladder on a slope at column {column}"));
+        }
+        if level.walls[column] > 0 {
+            problems.push(format!("ladder in a wall at column {column}"));
+        }
+        if top >= GROUND_ROW {
+            problems.push(format!("ladder in the ground at column {column}"));
+        }
+        let switch_block = level.switch_blocks[column].map(|(row, _)| row);
+        if [level.blocks[column], switch_block]
+            .iter()
+            .flatten()
+            .any(|&row| row >= top)
+        {
+            problems.push(format!("block on a ladder at column {column}"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{collision::Passage, level::GROUND_COLUMNS, switch::BlockSet};
+
+    #[test_case]
+    fn the_shipped_data_is_consistent(_gba: &mut agb::Gba) {
+        assert_eq!(check(), Vec::<String>::new());
+    }
+
+    #[test_case]
+    fn mismatches_are_reported_one_per_line(_gba: &mut agb::Gba) {
+        let mut problems = Vec::new();
+        check_tile_indices("ground", &[0, 9, 10], 10, &mut problems);
+        check_level(
+            &[
+                (EntityKind::Coin, (64, 96)),
+                (EntityKind::Coin, (-8, 96)),
+                (EntityKind::Coin, (60, GROUND_Y)),
+                (EntityKind::Apple, (200, 72)),
+                (EntityKind::Apple, (232, 72)),
+                (
+                    EntityKind::Zone {
+                        size: (16, 16),
+                        action: ZoneAction::Hint("MUCH TOO LONG TO FIT ON ONE LINE"),
+                    },
+                    (232, 16),
+                ),
+                (
+                    EntityKind::MovingPlatform {
+                        to: (16, 64),
+                        passage: Passage::OneWay,
+                    },
+                    (16, 112),
+                ),
+                (
+                    EntityKind::MovingPlatform {
+                        to: (224, 48),
+                        passage: Passage::Solid,
+                    },
+                    (56, 80),
+                ),
+                (EntityKind::CrumblingPlatform, (144, GROUND_Y)),
+                (EntityKind::Elevator { to_y: 0, wait: 60 }, (208, 64)),
+                (EntityKind::Spring, (232, GROUND_Y)),
+                (EntityKind::Switch, (-4, GROUND_Y)),
+                (EntityKind::Key, (100, GROUND_Y)),
+                (EntityKind::Door, (96, GROUND_Y + 8)),
+                (EntityKind::Water { size: (20, 16) }, (56, 88)),
+                (
+                    EntityKind::Wind {
+                        size: (30, 32),
+                        force: (0, 0),
+                    },
+                    (224, 0),
+                ),
+                (EntityKind::Teleporter { pair: 3 }, (244, GROUND_Y + 8)),
+            ],
+            &mut problems,
+        );
+        let mut level = Level::load(false);
+        level.walls[0] = 2;
+        level.walls[1] = 1;
+        level.walls[23] = 3;
+        check_walls(&level, &mut problems);
+        level.conveyors[4] = Fixed::new(1);
+        level.conveyors[10] = Fixed::new(-1);
+        level.conveyors[24] = Fixed::new(1);
+        check_conveyors(&level, &mut problems);
+        level.blocks[0] = Some(GROUND_ROW - 2);
+        level.blocks[2] = Some(GROUND_ROW);
+        level.blocks[22] = Some(GROUND_ROW - 1);
+        /* レベルのスイッチのブロック (20..23 列) と重ならないように */
+        level.switch_blocks = [None; GROUND_COLUMNS];
+        level.switch_blocks[17] = Some((GROUND_ROW, BlockSet::B));
+        level.blocks[12] = Some(3);
+        level.switch_blocks[12] = Some((3, BlockSet::A));
+        check_blocks(&level, &mut problems);
+        level.ladders[4] = Some(GROUND_ROW);
+        level.ladders[12] = Some(2);
+        check_ladders(&level, &mut problems);
+        assert_eq!(
+            problems,
+            [
+                "ground tile 10 >= 10",
+                "teleporter pair 3 has 1 pads",
+                "coin at 64,96 in water",
+                "coin at -8,96 off stage",
+                "coin at 60,120 off stage",
+                "coin at 60,120 off grid",
+                "apple at 232,72 off stage",
+                "zone at 232,16 off stage",
+                "hint at 232,16 too long",
+                "platform at 224,48 off stage",
+                "platform at 56,80 moves diagonally",
+                "crumbling platform at 144,120 off stage",
+                "elevator at 208,64 off stage",
+                "spring at 232,120 off stage",
+                "switch at -4,120 off stage",
+                "switch at -4,120 off grid",
+                "key at 100,120 off stage",
+                "key at 100,120 off grid",
+                "door at 96,128 off stage",
+                "water at 56,88 off grid",
+                "wind at 224,0 off stage",
+                "wind at 224,0 off grid",
+                "wind at 224,0 does not blow",
+                "teleporter at 244,128 off stage",
+                "teleporter at 244,128 off grid",
+                "wall 1 high at column 1",
+                "wall on a slope at column 23",
+                "conveyor under a wall at column 4",
+                "conveyor on Ice at column 10",
+                "conveyor on a slope at column 24",
+                "block in the ground at column 0",
+                "block in the ground at column 2",
+                "two blocks in column 12",
+                "block in the ground at column 17",
+                "block in the ground at column 22",
+                "ladder in a wall at column 4",
+                "ladder in the ground at column 4",
+                "block on a ladder at column 12",
+            ]
+        );
+    }
+}
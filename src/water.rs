@@ -0,0 +1,178 @@
+// 水。
+//
+// レベルの物の層 (Level::objects) に置く矩形 (EntityKind::Water) で、ドロイド君の中心が入っている間は
+// 水の中にいる (Droid::medium)。水の中では Droid::update が Tuning::WATER で動かすので、
+// 重力も落ちる速さの上限も跳ぶ速さも小さく、左右もゆっくりになる。
+// 宙にいる間に UP を押すと、水を掻いて跳んだときと同じ速さで上る。何度でも掻けるが、掻いた後は
+// 二段ジャンプ中 (状態 4) なので二段ジャンプは出ない。水から出たときに状態 2 に戻し、二段ジャンプで水面から跳び出せる。
+// 出入りしたときはドロイド君の中心から水しぶき (debris モジュールの粒) を散らし、Sfx::Splash を鳴らす。
+// 泳いでいる絵は、掻いて上っている間は跳ぶ絵、沈んでいる間は滑空の絵 (swim_pose)。
+// 背景のタイル (gameplay::WATER_TILES) は presenter がレベルを始めるときに描くので、タイルの格子に揃えて置く。
+
+use agb::input::Button;
+
+use crate::{
+    collision::Aabb,
+    events::{Event, Sfx},
+    game::{Game, DROID_WIDTH},
+    input::Input,
+    math::Fixed,
+    physics::{Medium, Tuning},
+    player::PlayerState,
+    snapshot::encode_fields,
+};
+
+/* 同時に置ける水の数 */
+pub const MAX_WATERS: usize = 2;
+/* 水しぶきの粒の飛び出す速度 (1/256 ドット、右と下が正)。左右へ 1 つずつ高く飛ぶ */
+const SPLASH: [(i32, i32); 2] = [(-256, -896), (256, -896)];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Water {
+    pub rect: Aabb,
+}
+
+encode_fields!(Water { rect });
+
+// 泳いでいるときの絵 (Droid::pose の値)。上っている間は跳ぶ絵、沈んでいる間は滑空の絵。
+pub fn swim_pose(vy: Fixed) -> u16 {
+    if Fixed::new(0) < vy {
+        8
+    } else {
+        24
+    }
+}
+
+impl Game {
+    // ドロイド君が水に出入りしたか見て、水の中で UP を押していれば掻く。ドロイド君を動かす前に呼ぶ。
+    pub fn update_water(&mut self, input: &Input) {
+        if self.waters.is_empty() && self.droid.medium == Medium::Air {
+            return;
+        }
+        let droid = &mut self.droid;
+        let centre = (droid.x + DROID_WIDTH / 2, droid.y + DROID_WIDTH / 2);
+        let medium = if self
+            .waters
+            .values()
+            .any(|water| water.rect.contains(centre))
+        {
+            Medium::Water
+        } else {
+            Medium::Air
+        };
+        if medium != droid.medium {
+            log_debug!("{:?} at ({}, {})", medium, droid.x, droid.y);
+            droid.medium = medium;
+            if medium == Medium::Air
+                && matches!(droid.state, PlayerState::Airborne { double: true })
+            {
+                /* 水から出たら、また二段ジャンプできる */
+                droid.enter(PlayerState::Airborne { double: false });
+            }
+            self.scatter(centre, &SPLASH);
+            self.push_event(Event::PlaySfx(Sfx::Splash));
+        }
+        let droid = &mut self.droid;
+        if droid.medium == Medium::Water
+            && matches!(droid.state, PlayerState::Airborne { .. })
+            && input.is_just_pressed(Button::UP)
+        {
+            droid.vy = Tuning::WATER.jump_speed;
+            droid.enter(PlayerState::Airborne { double: true });
+            droid.coyote.start(0);
+            droid.fast_falling = false;
+            droid.gliding = false;
+            droid.bouncing = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{arena::Arena, difficulty::Difficulty, game::GROUND_Y, spawn::EntityKind};
+
+    /* 出発点 (x = 120) の足元から、跳べば頭が出る高さまでの水 */
+    const WATER: (i32, i32) = (96, 104);
+    const SIZE: (i32, i32) = (64, 32);
+
+    fn game_in_water() -> Game {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        game.waters = Arena::new();
+        game.spawn(EntityKind::Water { size: SIZE })
+            .at(WATER)
+            .build()
+            .unwrap();
+        game
+    }
+
+    // 頂点の y と、跳んでから着地するまでのティック数。
+    fn jump(game: &mut Game) -> (i32, u32) {
+        game.update(&Input::new(Button::UP, Button::empty()));
+        let (mut apex, mut ticks) = (GROUND_Y, 0);
+        while game.droid.y == GROUND_Y {
+            game.update(&Input::new(Button::UP, Button::UP));
+        }
+        while game.droid.y != GROUND_Y {
+            game.update(&Input::new(Button::UP, Button::UP));
+            apex = apex.min(game.droid.y);
+            ticks += 1;
+        }
+        (apex, ticks)
+    }
+
+    // 水の中では低くゆっくり跳び、入ったときに水しぶきが飛ぶ。
+    #[test_case]
+    fn water_slows_the_droid_down(_gba: &mut agb::Gba) {
+        let (dry_apex, dry_ticks) = jump(&mut Game::new(Difficulty::Normal, 1));
+        let mut game = game_in_water();
+        game.update(&Input::default());
+        assert_eq!(game.droid.medium, Medium::Water);
+        assert!(!game.debris.is_empty());
+        let (apex, ticks) = jump(&mut game);
+        assert!(apex > dry_apex, "{apex} <= {dry_apex}");
+        assert!(ticks > dry_ticks, "{ticks} <= {dry_ticks}");
+        /* 速く落ちてきても、水に入れば落ちる速さは上限まで落ちる */
+        game.droid.y = WATER.1 - 40;
+        game.droid.state = PlayerState::Airborne { double: false };
+        game.update(&Input::default());
+        assert_eq!(game.droid.medium, Medium::Air);
+        while game.droid.medium == Medium::Air {
+            game.update(&Input::default());
+        }
+        game.update(&Input::default());
+        assert_eq!(game.droid.vy, -Tuning::WATER.max_fall_speed);
+    }
+
+    // 宙で UP を押すたびに掻いて上り、水から出ると二段ジャンプで跳び出せる。
+    #[test_case]
+    fn up_strokes_to_the_surface(_gba: &mut agb::Gba) {
+        let mut game = game_in_water();
+        game.update(&Input::new(Button::UP, Button::empty()));
+        let mut strokes = 0;
+        while game.droid.medium == Medium::Water {
+            let input = if game.droid.vy < Fixed::new(0) {
+                strokes += 1;
+                Input::new(Button::UP, Button::empty())
+            } else {
+                Input::new(Button::UP, Button::UP)
+            };
+            game.update(&input);
+            if matches!(game.droid.state, PlayerState::Airborne { double: true }) {
+                assert_eq!(game.droid.pose(), swim_pose(game.droid.vy));
+            }
+        }
+        assert!(strokes > 0);
+        assert_eq!(game.droid.state, PlayerState::Airborne { double: false });
+        let y = game.droid.y;
+        game.update(&Input::new(Button::UP, Button::empty()));
+        assert_eq!(game.droid.state, PlayerState::JumpSquat { double: true });
+        while matches!(game.droid.state, PlayerState::JumpSquat { double: true }) {
+            game.update(&Input::new(Button::UP, Button::UP));
+        }
+        for _ in 0..10 {
+            game.update(&Input::new(Button::UP, Button::UP));
+        }
+        assert!(game.droid.y < y - DROID_WIDTH, "{} >= {}", game.droid.y, y);
+    }
+}
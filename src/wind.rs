@@ -0,0 +1,214 @@
+// 風。
+//
+// レベルの物の層 (Level::objects) に置く矩形 (EntityKind::Wind) で、宙にいる (跳んでいるか落ちている)
+// ドロイド君の中心が入っている間、毎ティック風の強さ (Wind::force) を速度に足す。
+// 強さは画面の向き (右と下が正) の 1/256 ドット/ティック² で、横にも縦にも (斜めにも) 吹かせられる。
+// 横の風は空中の摩擦 (Tuning::air の friction) より強くないと流されず、上向きの風は重力より強くないと持ち上がらない。
+// 足すのは風の向きの速さが MAX_WIND_SPEED に届くまで。それより速く動いているときは、風は効かずに空中の摩擦で落ちていく。
+// 地面や足場に立っているとき、ぶら下がりやはしご、ヒップドロップの間は流されない。
+//
+// 見た目は presenter が風の向きへ流れる粒 (gameplay::STREAK_TILES) を背景に描く。粒はタイル 1 枚で、
+// 流れた量 (Wind::drift) は毎ティック風が進めておく。粒がタイルに収まるよう、風はタイルの格子に揃えて置く。
+// ミラーモードでは横の向きを反転する (level モジュール)。
+
+use crate::{
+    collision::Aabb,
+    entity::{Entity, Look},
+    game::{Game, DROID_WIDTH},
+    math::Fixed,
+    player::PlayerState,
+    snapshot::encode_fields,
+};
+
+/* 同時に置ける風の数 */
+pub const MAX_WINDS: usize = 2;
+/* 風 1 つに流す粒の数と、粒が 1 ティックに流れるドット数。粒は 8 ドット流れるごとに隣のタイルへ移る */
+pub const STREAKS: usize = 2;
+const STREAK_SPEED: i32 = 2;
+/* 風で出せる速さの上限 (1/256 ドット/ティック)。一番低いジャンプで上る速さより遅くして、UP を離しても落とされない */
+const MAX_WIND_SPEED: i32 = 384;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Wind {
+    pub rect: Aabb,
+    /* 1 ティックに速度へ足す量 (1/256 ドット、右と下が正) */
+    pub force: (i32, i32),
+    /* 粒が流れたドット数 (0..幅, 0..高さ)。毎フレーム割り算しなくて済むよう、回り込みながら足していく */
+    pub drift: (i32, i32),
+    /* 並べた粒の間隔 (横, 縦)。タイルの幅の倍数にして、全ての粒が同じティックに隣のタイルへ移るようにする。割り算は置いたときに一度だけ */
+    spacing: (i32, i32),
+}
+
+encode_fields!(Wind {
+    rect,
+    force,
+    drift,
+    spacing
+});
+
+impl Wind {
+    pub fn new(rect: Aabb, force: (i32, i32)) -> Self {
+        Self {
+            rect,
+            force,
+            drift: (0, 0),
+            spacing: (
+                (rect.width / STREAKS as i32) & !7,
+                (rect.height / STREAKS as i32) & !7,
+            ),
+        }
+    }
+
+    // index 番目の粒のタイルの左上。粒は斜めにずらして並べ、風の向きへ流れて端から端へ回り込む。
+    pub fn streak(&self, index: usize) -> (i32, i32) {
+        let rect = self.rect;
+        let lane = |length: i32, spacing: i32, drift: i32| {
+            let at = spacing * index as i32 + drift;
+            (if at < length { at } else { at - length }) & !7
+        };
+        let ((x, y), (dx, dy)) = (self.drift, self.spacing);
+        (
+            rect.x + lane(rect.width, dx, x),
+            rect.y + lane(rect.height, dy, y),
+        )
+    }
+
+    // 粒が流れたタイル数 (横, 縦)。変わったら粒を描き直す。
+    pub fn step(&self) -> (i32, i32) {
+        (self.drift.0 >> 3, self.drift.1 >> 3)
+    }
+
+    // 粒を 1 ティック分、風の向きへ流す。
+    fn advance(&mut self) {
+        let step = |drift: i32, force: i32, length: i32| {
+            let drift = drift + force.signum() * STREAK_SPEED;
+            if drift < 0 {
+                drift + length
+            } else if drift >= length {
+                drift - length
+            } else {
+                drift
+            }
+        };
+        let ((x, y), (fx, fy)) = (self.drift, self.force);
+        self.drift = (step(x, fx, self.rect.width), step(y, fy, self.rect.height));
+    }
+}
+
+// 速度 v に、風の向きの速さが上限に届くまで force を足す。
+fn blow(v: Fixed, force: i32) -> Fixed {
+    let limit = Fixed::from_raw(MAX_WIND_SPEED);
+    match force.signum() {
+        1 if v < limit => (v + Fixed::from_raw(force)).min(limit),
+        -1 if -limit < v => (v + Fixed::from_raw(force)).max(-limit),
+        _ => v,
+    }
+}
+
+impl Entity for Wind {
+    fn update(&mut self) {
+        self.advance();
+    }
+
+    // 風そのものは描かない。粒は presenter が流れた量 (step) から描く。
+    fn draw(&self) -> Option<Look> {
+        None
+    }
+}
+
+impl Game {
+    // 宙にいるドロイド君を入っている風で流す。ドロイド君を動かす前に呼ぶ。
+    pub fn update_wind(&mut self) {
+        let droid = &mut self.droid;
+        if self.winds.is_empty() || !matches!(droid.state, PlayerState::Airborne { .. }) {
+            return;
+        }
+        let centre = (droid.x + DROID_WIDTH / 2, droid.y + DROID_WIDTH / 2);
+        for wind in self.winds.values() {
+            if !wind.rect.contains(centre) {
+                continue;
+            }
+            let (x, y) = wind.force;
+            droid.vx = blow(droid.vx, x);
+            /* 上下の速度は跳ぶ向きが正 */
+            droid.vy = blow(droid.vy, droid.gravity.rise(y));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use agb::input::Button;
+
+    use super::*;
+    use crate::{
+        arena::Arena, difficulty::Difficulty, game::GROUND_Y, input::Input, spawn::EntityKind,
+    };
+
+    fn game_with_wind(force: (i32, i32)) -> Game {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        game.winds = Arena::new();
+        game.spawn(EntityKind::Wind {
+            size: (240, 96),
+            force,
+        })
+        .at((0, 0))
+        .build()
+        .unwrap();
+        game
+    }
+
+    // 真上に跳んで (地面の段の上でも) 着地するまでの、横に流された距離と頂点の y 。
+    fn jump(game: &mut Game) -> (i32, i32) {
+        let x = game.droid.x;
+        game.update(&Input::new(Button::UP, Button::empty()));
+        let mut apex = GROUND_Y;
+        while game.droid.y == GROUND_Y {
+            game.update(&Input::new(Button::UP, Button::UP));
+        }
+        while game.droid.state != PlayerState::Idle {
+            game.update(&Input::new(Button::UP, Button::UP));
+            apex = apex.min(game.droid.y);
+        }
+        (game.droid.x - x, apex)
+    }
+
+    // 横の風は宙にいる間だけ風下へ流し、立っている間は流さない。
+    #[test_case]
+    fn wind_blows_the_droid_along_in_the_air(_gba: &mut agb::Gba) {
+        let (drift, _) = jump(&mut Game::new(Difficulty::Normal, 1));
+        assert_eq!(drift, 0);
+        for (force, downwind) in [(48, 1), (-48, -1)] {
+            let mut game = game_with_wind((force, 0));
+            let (drift, _) = jump(&mut game);
+            assert!(drift * downwind > 0, "drift {drift} for {force}");
+            /* 着地して止まった後は動かない */
+            for _ in 0..30 {
+                game.update(&Input::default());
+            }
+            let x = game.droid.x;
+            for _ in 0..30 {
+                game.update(&Input::default());
+            }
+            assert_eq!(game.droid.x, x);
+        }
+    }
+
+    // 上向きの風は高く跳ばせ、下向きの風は低くする。流される速さは上限で止まる。
+    #[test_case]
+    fn updrafts_lift_and_downdrafts_press(_gba: &mut agb::Gba) {
+        let (_, apex) = jump(&mut Game::new(Difficulty::Normal, 1));
+        let (_, lifted) = jump(&mut game_with_wind((0, -40)));
+        let (_, pressed) = jump(&mut game_with_wind((0, 40)));
+        assert!(lifted < apex && apex < pressed, "{lifted} {apex} {pressed}");
+
+        let mut game = game_with_wind((128, 0));
+        game.droid.y = 16;
+        game.droid.state = PlayerState::Airborne { double: false };
+        for _ in 0..10 {
+            game.update_wind();
+            assert!(game.droid.vx <= Fixed::from_raw(MAX_WIND_SPEED));
+        }
+        assert_eq!(game.droid.vx, Fixed::from_raw(MAX_WIND_SPEED));
+    }
+}
@@ -0,0 +1,174 @@
+// トリガーゾーン。
+//
+// レベルに置く (EntityKind::Zone) 当たらない矩形で、ドロイド君の中心が入ったときと出たときに
+// イベント (Event::ZoneEntered, Event::ZoneExited) を積む。中にいる間は何も積まない。
+// 何が起きるか (ZoneAction) はゾーンごとにレベルのデータで決め、イベントを処理するときに効かせる:
+// - Exit: レベルをクリアする (コインが残っていても)。
+// - Hint: 中にいる間だけ HUD に案内を出す。
+// - Cutscene: カットシーンを始めてほしいと Game::cutscene に残す (GameScene が取り出して流す)。
+//
+// チュートリアルの仕掛け (script モジュール) は順番に 1 つずつ待つ関門で、こちらは順番の無い、
+// 何度でも出入りできるものに使う。
+
+use alloc::vec::Vec;
+
+use crate::{
+    arena::EntityId,
+    collision::Aabb,
+    game::Game,
+    snapshot::{Encode, Reader},
+};
+
+/* 同時に置けるゾーンの数 */
+pub const MAX_ZONES: usize = 4;
+
+// ゾーンに入ったときに起きること。出口とカットシーンはまだ置いているレベルが無い。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ZoneAction {
+    // レベルの出口。
+    #[cfg_attr(not(test), allow(dead_code))]
+    Exit,
+    // 中にいる間だけ出す案内 (HUD の 1 行に収まる長さ)。
+    Hint(&'static str),
+    // 始めるカットシーンの番号。
+    #[cfg_attr(not(test), allow(dead_code))]
+    Cutscene(u8),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Zone {
+    pub rect: Aabb,
+    pub action: ZoneAction,
+    /* 前のティックにドロイド君の中心が中にあったか */
+    inside: bool,
+}
+
+impl Zone {
+    pub fn new(rect: Aabb, action: ZoneAction) -> Self {
+        Self {
+            rect,
+            action,
+            inside: false,
+        }
+    }
+
+    // ドロイド君の中心が centre にあるとき、入った (true) か出た (false) か。変わらなければ None 。
+    pub fn update(&mut self, centre: (i32, i32)) -> Option<bool> {
+        let inside = self.rect.contains(centre);
+        if inside == self.inside {
+            return None;
+        }
+        self.inside = inside;
+        Some(inside)
+    }
+
+    // 中にいるかを書く (snapshot モジュール)。矩形と起きることはレベルのデータで決まるので書かない。
+    pub fn encode_state(&self, bytes: &mut Vec<u8>) {
+        self.inside.encode(bytes);
+    }
+
+    // encode_state で書いたものを、同じレベルから置いたこのゾーンに戻す。
+    pub fn decode_state(&mut self, reader: &mut Reader) -> Option<()> {
+        self.inside = bool::decode(reader)?;
+        Some(())
+    }
+}
+
+impl Game {
+    // Event::ZoneEntered の処理。毎ティックは通らないので handle_events (IWRAM) から分けておく。
+    #[inline(never)]
+    pub fn enter_zone(&mut self, id: EntityId) {
+        let Some(zone) = self.zones.get(id) else {
+            return;
+        };
+        log_debug!("entered {:?}", zone.action);
+        match zone.action {
+            ZoneAction::Exit => self.exited = true,
+            ZoneAction::Hint(hint) => self.hint = Some(hint),
+            ZoneAction::Cutscene(cutscene) => self.cutscene = Some(cutscene),
+        }
+    }
+
+    // Event::ZoneExited の処理。出した案内を消す。
+    #[inline(never)]
+    pub fn exit_zone(&mut self, id: EntityId) {
+        let Some(zone) = self.zones.get(id) else {
+            return;
+        };
+        if let ZoneAction::Hint(hint) = zone.action {
+            if self.hint == Some(hint) {
+                self.hint = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use agb::input::Button;
+
+    use super::*;
+    use crate::{
+        arena::Arena,
+        difficulty::Difficulty,
+        game::{Outcome, DROID_WIDTH, GROUND_Y},
+        input::Input,
+        spawn::EntityKind,
+    };
+
+    fn game_with(action: ZoneAction) -> Game {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        game.zones = Arena::new();
+        game.spawn(EntityKind::Zone {
+            size: (16, 32),
+            action,
+        })
+        .at((160, GROUND_Y - 16))
+        .build()
+        .unwrap();
+        game
+    }
+
+    // 中心が入ったときと出たときに 1 度ずつ。中にいる間は何も起きない。
+    #[test_case]
+    fn zones_fire_once_on_the_way_in_and_out(_gba: &mut agb::Gba) {
+        let mut zone = Zone::new(Aabb::new(160, 100, 16, 32), ZoneAction::Exit);
+        let fired: alloc::vec::Vec<_> = (140..200)
+            .filter_map(|x| Some((x, zone.update((x, 110))?)))
+            .collect();
+        assert_eq!(fired, [(160, true), (176, false)]);
+        assert_eq!(zone.update((170, 99)), None);
+        assert_eq!(zone.update((170, 100)), Some(true));
+    }
+
+    // 案内は中にいる間だけ出る。
+    #[test_case]
+    fn hints_show_while_inside(_gba: &mut agb::Gba) {
+        let mut game = game_with(ZoneAction::Hint("HELLO"));
+        for _ in 0..100 {
+            game.update(&Input::new(Button::RIGHT, Button::RIGHT));
+            let (_, zone) = game.zones.iter().next().unwrap();
+            let centre = game.droid.x + DROID_WIDTH / 2;
+            let inside = (zone.rect.x..zone.rect.right()).contains(&centre);
+            /* イベントは積んだティックの終わりに処理される */
+            assert_eq!(game.hint.is_some(), inside, "x = {}", game.droid.x);
+        }
+        assert_eq!(game.hint, None);
+    }
+
+    // 出口に入ればコインが残っていてもクリア。カットシーンは番号が残る。
+    #[test_case]
+    fn exits_clear_the_level_and_cutscenes_are_requested(_gba: &mut agb::Gba) {
+        let mut game = game_with(ZoneAction::Exit);
+        game.droid.x = 160;
+        game.update(&Input::default());
+        assert!(!game.coins.is_empty());
+        assert_eq!(game.outcome(), Some(Outcome::Cleared));
+
+        let mut game = game_with(ZoneAction::Cutscene(3));
+        game.droid.x = 160;
+        game.update(&Input::default());
+        assert_eq!(game.cutscene, Some(3));
+        assert_eq!(game.outcome(), None);
+    }
+}